@@ -0,0 +1,289 @@
+use chrono::{Duration as ChronoDuration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::language;
+use crate::window_state::WindowRegistry;
+
+const STORE_FILE: &str = "activity.json";
+/// Gaps between heartbeats longer than this count as idle time.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Focus,
+    Edit,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityPeriod {
+    Today,
+    Week,
+    Month,
+    All,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Bucket {
+    focus_seconds: u64,
+    edit_seconds: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ActivityData {
+    enabled: bool,
+    /// day (YYYY-MM-DD) -> project -> language -> bucket
+    days: BTreeMap<String, BTreeMap<String, BTreeMap<String, Bucket>>>,
+}
+
+struct Heartbeat {
+    at: Instant,
+    project: String,
+    language: String,
+    kind: ActivityKind,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ActivityTotal {
+    pub key: String,
+    pub focus_seconds: u64,
+    pub edit_seconds: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ActivitySummary {
+    pub focus_seconds: u64,
+    pub edit_seconds: u64,
+    pub by_day: Vec<ActivityTotal>,
+    pub by_project: Vec<ActivityTotal>,
+    pub by_language: Vec<ActivityTotal>,
+}
+
+/// Opt-in, local-only record of time spent per project, day and language.
+/// The frontend sends heartbeats while a file is focused or being edited;
+/// the time between consecutive heartbeats is credited to the earlier one.
+#[derive(Default)]
+pub struct ActivityTracker {
+    data: Mutex<Option<(PathBuf, ActivityData)>>,
+    last: Mutex<Option<Heartbeat>>,
+}
+
+impl ActivityTracker {
+    /// Same contract as `RecentStore::with_data`: `f` reports whether it
+    /// changed anything so reads do not rewrite the file.
+    fn with_data<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut ActivityData) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.data.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_data_path(app, STORE_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+
+    fn heartbeat(
+        &self,
+        app: &AppHandle,
+        project: String,
+        language: String,
+        kind: ActivityKind,
+    ) -> Result<(), String> {
+        let now = Instant::now();
+        let previous = self.last.lock().unwrap().replace(Heartbeat {
+            at: now,
+            project,
+            language,
+            kind,
+        });
+
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        let elapsed = now.duration_since(previous.at);
+        if elapsed > IDLE_TIMEOUT || elapsed.as_secs() == 0 {
+            return Ok(());
+        }
+
+        let day = Local::now().date_naive().to_string();
+        self.with_data(app, |data| {
+            if !data.enabled {
+                return ((), false);
+            }
+            let bucket = data
+                .days
+                .entry(day)
+                .or_default()
+                .entry(previous.project)
+                .or_default()
+                .entry(previous.language)
+                .or_default();
+            match previous.kind {
+                ActivityKind::Focus => bucket.focus_seconds += elapsed.as_secs(),
+                ActivityKind::Edit => {
+                    bucket.focus_seconds += elapsed.as_secs();
+                    bucket.edit_seconds += elapsed.as_secs();
+                }
+            }
+            ((), true)
+        })
+    }
+
+    fn summary(
+        &self,
+        app: &AppHandle,
+        period: ActivityPeriod,
+        project: Option<&str>,
+    ) -> Result<ActivitySummary, String> {
+        let today = Local::now().date_naive();
+        let since = match period {
+            ActivityPeriod::Today => Some(today),
+            ActivityPeriod::Week => Some(today - ChronoDuration::days(6)),
+            ActivityPeriod::Month => Some(today - ChronoDuration::days(29)),
+            ActivityPeriod::All => None,
+        };
+
+        self.with_data(app, |data| {
+            let mut summary = ActivitySummary::default();
+            let mut by_project: BTreeMap<String, ActivityTotal> = BTreeMap::new();
+            let mut by_language: BTreeMap<String, ActivityTotal> = BTreeMap::new();
+
+            for (day, projects) in &data.days {
+                let in_period = match (since, NaiveDate::parse_from_str(day, "%Y-%m-%d")) {
+                    (Some(since), Ok(date)) => date >= since,
+                    (None, _) => true,
+                    (Some(_), Err(_)) => false,
+                };
+                if !in_period {
+                    continue;
+                }
+
+                let mut day_total = ActivityTotal {
+                    key: day.clone(),
+                    ..Default::default()
+                };
+                for (project_name, languages) in projects {
+                    if project.is_some_and(|p| p != project_name) {
+                        continue;
+                    }
+                    for (language, bucket) in languages {
+                        for total in [
+                            &mut day_total,
+                            by_project.entry(project_name.clone()).or_insert_with(|| {
+                                ActivityTotal {
+                                    key: project_name.clone(),
+                                    ..Default::default()
+                                }
+                            }),
+                            by_language
+                                .entry(language.clone())
+                                .or_insert_with(|| ActivityTotal {
+                                    key: language.clone(),
+                                    ..Default::default()
+                                }),
+                        ] {
+                            total.focus_seconds += bucket.focus_seconds;
+                            total.edit_seconds += bucket.edit_seconds;
+                        }
+                    }
+                }
+                summary.focus_seconds += day_total.focus_seconds;
+                summary.edit_seconds += day_total.edit_seconds;
+                if day_total.focus_seconds > 0 {
+                    summary.by_day.push(day_total);
+                }
+            }
+
+            summary.by_project = by_project.into_values().collect();
+            summary
+                .by_project
+                .sort_by(|a, b| b.focus_seconds.cmp(&a.focus_seconds));
+            summary.by_language = by_language.into_values().collect();
+            summary
+                .by_language
+                .sort_by(|a, b| b.focus_seconds.cmp(&a.focus_seconds));
+            (summary, false)
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn set_activity_tracking(
+    enabled: bool,
+    app: AppHandle,
+    tracker: State<'_, ActivityTracker>,
+) -> Result<(), String> {
+    tracker.last.lock().unwrap().take();
+    tracker.with_data(&app, |data| {
+        data.enabled = enabled;
+        ((), true)
+    })
+}
+
+#[tauri::command]
+pub async fn get_activity_tracking(
+    app: AppHandle,
+    tracker: State<'_, ActivityTracker>,
+) -> Result<bool, String> {
+    tracker.with_data(&app, |data| (data.enabled, false))
+}
+
+/// Heartbeat from the editor while `path` is focused or being edited.
+#[tauri::command]
+pub async fn record_activity(
+    path: String,
+    kind: ActivityKind,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    tracker: State<'_, ActivityTracker>,
+) -> Result<(), String> {
+    let path = Path::new(&path);
+    let project = windows
+        .scope(window.label())
+        .workspace
+        .active()
+        .filter(|root| path.starts_with(root))
+        .map(|root| root.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let language = language::language_for_path(path);
+    tracker.heartbeat(&app, project, language, kind)
+}
+
+#[tauri::command]
+pub async fn get_activity_summary(
+    period: ActivityPeriod,
+    project: Option<String>,
+    app: AppHandle,
+    tracker: State<'_, ActivityTracker>,
+) -> Result<ActivitySummary, String> {
+    tracker.summary(&app, period, project.as_deref())
+}
+
+#[tauri::command]
+pub async fn clear_activity_data(
+    app: AppHandle,
+    tracker: State<'_, ActivityTracker>,
+) -> Result<(), String> {
+    tracker.last.lock().unwrap().take();
+    tracker.with_data(&app, |data| {
+        data.days.clear();
+        ((), true)
+    })
+}
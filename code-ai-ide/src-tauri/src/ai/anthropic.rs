@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use super::sse;
+use super::{ChatOutcome, ChatRequest, CompletionProvider, ProviderError, Role, TokenSink, Usage};
+
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+pub const API_VERSION: &str = "2023-06-01";
+/// The Messages API requires a limit; used when the caller sets none.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// The Anthropic Messages API.
+pub struct AnthropicProvider {
+    pub base_url: String,
+    pub api_key: String,
+}
+
+impl AnthropicProvider {
+    /// System messages go in the top-level `system` field rather than the
+    /// message list.
+    fn body(&self, request: &ChatRequest) -> Value {
+        let system: Vec<&str> = request
+            .messages
+            .iter()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.content.as_str())
+            .collect();
+        let messages: Vec<&super::ChatMessage> = request
+            .messages
+            .iter()
+            .filter(|message| message.role != Role::System)
+            .collect();
+
+        let mut body = Map::new();
+        body.insert("model".into(), json!(request.model));
+        body.insert("messages".into(), json!(messages));
+        body.insert(
+            "max_tokens".into(),
+            json!(request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)),
+        );
+        body.insert("stream".into(), json!(true));
+        if !system.is_empty() {
+            body.insert("system".into(), json!(system.join("\n\n")));
+        }
+        if let Some(temperature) = request.temperature {
+            body.insert("temperature".into(), json!(temperature));
+        }
+        if !request.stop.is_empty() {
+            body.insert("stop_sequences".into(), json!(request.stop));
+        }
+        Value::Object(body)
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError> {
+        let builder = super::client()?
+            .post(format!(
+                "{}/v1/messages",
+                self.base_url.trim_end_matches('/')
+            ))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&self.body(request));
+        let response = super::send(self.name(), builder).await?;
+
+        let mut outcome = ChatOutcome::default();
+        let mut usage = Usage::default();
+        sse::for_each_event(response, |event| {
+            let data: Value = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Invalid response from anthropic: {}", e))?;
+            match data["type"].as_str().unwrap_or_default() {
+                "message_start" => {
+                    usage.input_tokens = data["message"]["usage"]["input_tokens"]
+                        .as_u64()
+                        .unwrap_or(0);
+                }
+                "content_block_delta" => {
+                    if let Some(text) = data["delta"]["text"].as_str() {
+                        on_token(text);
+                    }
+                }
+                "message_delta" => {
+                    if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                        outcome.finish_reason = Some(reason.to_string());
+                    }
+                    if let Some(output) = data["usage"]["output_tokens"].as_u64() {
+                        usage.output_tokens = output;
+                    }
+                }
+                "message_stop" => return Ok(false),
+                "error" => return Err(super::error_message(&data["error"])),
+                _ => {}
+            }
+            Ok(true)
+        })
+        .await?;
+        outcome.usage = Some(usage);
+        Ok(outcome)
+    }
+}
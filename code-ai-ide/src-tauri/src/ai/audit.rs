@@ -0,0 +1,348 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, State};
+
+use super::models;
+use crate::app_data;
+use crate::metadata::to_millis;
+use crate::settings;
+
+const AUDIT_DB: &str = "ai_audit.sqlite";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestKind {
+    Chat,
+    InlineCompletion,
+    CommitMessage,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestOutcome {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Metadata of one AI request. Prompts and replies are never logged.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub kind: RequestKind,
+    pub provider: &'static str,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Counted locally because the provider reported no usage.
+    pub estimated: bool,
+    pub duration: Duration,
+    /// Files whose content went into the request.
+    pub files: Vec<String>,
+    pub outcome: RequestOutcome,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsagePeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl UsagePeriod {
+    fn days(self) -> Option<u64> {
+        match self {
+            UsagePeriod::Day => Some(1),
+            UsagePeriod::Week => Some(7),
+            UsagePeriod::Month => Some(30),
+            UsagePeriod::Year => Some(365),
+            UsagePeriod::All => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub failed: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// US dollars at catalog prices; `None` when the catalog has no price
+    /// for the model.
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KindUsage {
+    pub kind: RequestKind,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    /// Start of the period, in milliseconds since the epoch.
+    pub since: u64,
+    pub requests: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Requests whose tokens were counted locally.
+    pub estimated: u64,
+    pub average_duration_ms: u64,
+    /// Sum of the priced models' cost.
+    pub cost: f64,
+    pub by_model: Vec<ModelUsage>,
+    pub by_kind: Vec<KindUsage>,
+}
+
+/// The opt-in log of AI requests, in one SQLite database under app data
+/// that is opened on first use.
+#[derive(Default)]
+pub struct AuditLog {
+    conn: Mutex<Option<Connection>>,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Failed to access the AI audit log: {}", e)
+}
+
+fn now() -> u64 {
+    to_millis(SystemTime::now()).unwrap_or(0)
+}
+
+fn kind_name(kind: RequestKind) -> &'static str {
+    match kind {
+        RequestKind::Chat => "chat",
+        RequestKind::InlineCompletion => "inline_completion",
+        RequestKind::CommitMessage => "commit_message",
+    }
+}
+
+fn parse_kind(name: &str) -> RequestKind {
+    match name {
+        "inline_completion" => RequestKind::InlineCompletion,
+        "commit_message" => RequestKind::CommitMessage,
+        _ => RequestKind::Chat,
+    }
+}
+
+fn outcome_name(outcome: RequestOutcome) -> &'static str {
+    match outcome {
+        RequestOutcome::Completed => "completed",
+        RequestOutcome::Failed => "failed",
+        RequestOutcome::Cancelled => "cancelled",
+    }
+}
+
+/// Catalog prices, and nothing for models on this machine.
+fn cost(provider: &str, model: &str, input_tokens: u64, output_tokens: u64) -> Option<f64> {
+    if provider == "ollama" || provider == "llama_cpp" {
+        return Some(0.0);
+    }
+    let entry = models::lookup(model)?;
+    let per_token = |tokens: u64, price: f64| tokens as f64 * price / 1_000_000.0;
+    Some(per_token(input_tokens, entry.input_cost?) + per_token(output_tokens, entry.output_cost?))
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let conn = Connection::open(app_data::app_data_path(app, AUDIT_DB)?).map_err(db_error)?;
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS requests (
+             id INTEGER PRIMARY KEY,
+             timestamp INTEGER NOT NULL,
+             kind TEXT NOT NULL,
+             provider TEXT NOT NULL,
+             model TEXT NOT NULL,
+             input_tokens INTEGER NOT NULL,
+             output_tokens INTEGER NOT NULL,
+             estimated INTEGER NOT NULL,
+             duration_ms INTEGER NOT NULL,
+             files TEXT NOT NULL,
+             outcome TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS requests_timestamp ON requests (timestamp);",
+    )
+    .map_err(db_error)?;
+    Ok(conn)
+}
+
+impl AuditLog {
+    fn with_conn<T>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut Connection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(open(app)?);
+        }
+        f(conn.as_mut().unwrap())
+    }
+
+    /// Logs `entry` and drops entries older than
+    /// `ai.auditLog.retentionDays`.
+    fn insert(&self, app: &AppHandle, entry: &AuditEntry) -> Result<(), String> {
+        let retention = settings::user_setting(app, "ai.auditLog.retentionDays")?
+            .as_u64()
+            .unwrap_or(0);
+        let files = serde_json::to_string(&entry.files).map_err(|e| e.to_string())?;
+        let timestamp = now();
+        self.with_conn(app, |conn| {
+            conn.execute(
+                "INSERT INTO requests (timestamp, kind, provider, model, input_tokens,
+                     output_tokens, estimated, duration_ms, files, outcome)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    timestamp,
+                    kind_name(entry.kind),
+                    entry.provider,
+                    entry.model,
+                    entry.input_tokens,
+                    entry.output_tokens,
+                    entry.estimated,
+                    entry.duration.as_millis() as u64,
+                    files,
+                    outcome_name(entry.outcome),
+                ],
+            )
+            .map_err(db_error)?;
+            if retention > 0 {
+                conn.execute(
+                    "DELETE FROM requests WHERE timestamp < ?1",
+                    [timestamp.saturating_sub(retention * DAY_MS)],
+                )
+                .map_err(db_error)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn stats(&self, app: &AppHandle, since: u64) -> Result<UsageStats, String> {
+        self.with_conn(app, |conn| {
+            let mut stats = UsageStats {
+                since,
+                requests: 0,
+                failed: 0,
+                cancelled: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                estimated: 0,
+                average_duration_ms: 0,
+                cost: 0.0,
+                by_model: Vec::new(),
+                by_kind: Vec::new(),
+            };
+            let (cancelled, estimated, duration): (u64, u64, u64) = conn
+                .query_row(
+                    "SELECT COALESCE(SUM(outcome = 'cancelled'), 0),
+                            COALESCE(SUM(estimated), 0),
+                            COALESCE(SUM(duration_ms), 0)
+                     FROM requests WHERE timestamp >= ?1",
+                    [since],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .map_err(db_error)?;
+            stats.cancelled = cancelled;
+            stats.estimated = estimated;
+
+            let mut statement = conn
+                .prepare(
+                    "SELECT provider, model, COUNT(*), SUM(outcome = 'failed'),
+                            SUM(input_tokens), SUM(output_tokens)
+                     FROM requests WHERE timestamp >= ?1
+                     GROUP BY provider, model
+                     ORDER BY SUM(input_tokens + output_tokens) DESC",
+                )
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map([since], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, u64>(2)?,
+                        row.get::<_, u64>(3)?,
+                        row.get::<_, u64>(4)?,
+                        row.get::<_, u64>(5)?,
+                    ))
+                })
+                .map_err(db_error)?;
+            for row in rows {
+                let (provider, model, requests, failed, input_tokens, output_tokens) =
+                    row.map_err(db_error)?;
+                let cost = cost(&provider, &model, input_tokens, output_tokens);
+                stats.requests += requests;
+                stats.failed += failed;
+                stats.input_tokens += input_tokens;
+                stats.output_tokens += output_tokens;
+                stats.cost += cost.unwrap_or(0.0);
+                stats.by_model.push(ModelUsage {
+                    provider,
+                    model,
+                    requests,
+                    failed,
+                    input_tokens,
+                    output_tokens,
+                    cost,
+                });
+            }
+            stats.average_duration_ms = duration / stats.requests.max(1);
+
+            let mut statement = conn
+                .prepare(
+                    "SELECT kind, COUNT(*), SUM(input_tokens), SUM(output_tokens)
+                     FROM requests WHERE timestamp >= ?1
+                     GROUP BY kind ORDER BY COUNT(*) DESC",
+                )
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map([since], |row| {
+                    Ok(KindUsage {
+                        kind: parse_kind(&row.get::<_, String>(0)?),
+                        requests: row.get(1)?,
+                        input_tokens: row.get(2)?,
+                        output_tokens: row.get(3)?,
+                    })
+                })
+                .map_err(db_error)?;
+            stats.by_kind = rows.collect::<Result<_, _>>().map_err(db_error)?;
+            Ok(stats)
+        })
+    }
+}
+
+/// Logs the request `entry` describes when `ai.auditLog.enabled` is on.
+/// The entry is only built then, since counting tokens is not free; a
+/// failure to log never fails the request.
+pub(crate) fn record(app: &AppHandle, entry: impl FnOnce() -> AuditEntry) {
+    let enabled = settings::user_setting(app, "ai.auditLog.enabled")
+        .is_ok_and(|enabled| enabled.as_bool() == Some(true));
+    if enabled {
+        let _ = app.state::<AuditLog>().insert(app, &entry());
+    }
+}
+
+/// Requests, tokens and cost at catalog prices from the audit log over
+/// the last `period`, in total and per model and kind of request. Empty
+/// unless `ai.auditLog.enabled` was on.
+#[tauri::command]
+pub async fn get_ai_usage_stats(
+    period: UsagePeriod,
+    app: AppHandle,
+    audit: State<'_, AuditLog>,
+) -> Result<UsageStats, String> {
+    let since = period
+        .days()
+        .map_or(0, |days| now().saturating_sub(days * DAY_MS));
+    audit.stats(&app, since)
+}
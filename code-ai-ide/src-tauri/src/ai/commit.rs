@@ -0,0 +1,222 @@
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, State, Window};
+
+use super::audit::{self, AuditEntry, RequestKind, RequestOutcome};
+use super::tokens;
+use super::{ChatMessage, ChatRequest, ProviderKind, Role};
+use crate::git;
+use crate::window_state::WindowRegistry;
+
+/// Diff text sent to the model. Large changes are cut down per file so
+/// every file is represented.
+const MAX_DIFF_CHARS: usize = 16_000;
+/// No file's diff is cut below this, so each keeps some substance.
+const MIN_FILE_CHARS: usize = 800;
+const RECENT_SUBJECTS: &str = "10";
+
+/// Generated or vendored files whose diffs say nothing about the change;
+/// they only appear in the stat.
+const SUMMARY_ONLY: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+const INSTRUCTIONS: &str = "You write git commit messages in the Conventional Commits style: \
+a subject line `type(scope): summary` of at most 72 characters, where type is one of feat, fix, \
+refactor, perf, docs, test, build, ci, chore or style and the scope is optional; then, only if \
+the change needs explaining, a blank line and a short body wrapped at 72 columns saying what \
+changed and why. Use the imperative mood. Reply with the message only.";
+
+struct FileDiff {
+    path: String,
+    text: String,
+}
+
+/// Splits `git diff` output at each file header.
+fn split_files(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            let path = header
+                .trim_end()
+                .rsplit_once(" b/")
+                .map_or(header.trim_end(), |(_, path)| path);
+            files.push(FileDiff {
+                path: path.to_string(),
+                text: String::new(),
+            });
+        }
+        if let Some(file) = files.last_mut() {
+            file.text.push_str(line);
+        }
+    }
+    files
+}
+
+fn summary_only(path: &str) -> bool {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    SUMMARY_ONLY.contains(&name.as_ref()) || name.ends_with(".min.js") || name.ends_with(".map")
+}
+
+/// Cuts `text` to at most `max` bytes at a line boundary, noting how many
+/// lines were left out.
+fn truncate_lines(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut kept = String::new();
+    let mut lines = text.split_inclusive('\n');
+    for line in lines.by_ref() {
+        if kept.len() + line.len() > max {
+            let omitted = 1 + lines.count();
+            kept.push_str(&format!("[... {} more lines]\n", omitted));
+            return kept;
+        }
+        kept.push_str(line);
+    }
+    kept
+}
+
+/// Fits the staged diff into `MAX_DIFF_CHARS`. Small files are kept whole
+/// and the rest share what is left equally, each cut at a line boundary.
+fn fit_diff(diff: &str) -> String {
+    let mut files: Vec<FileDiff> = split_files(diff)
+        .into_iter()
+        .filter(|file| !summary_only(&file.path))
+        .collect();
+    files.sort_by_key(|file| file.text.len());
+
+    let mut budget = MAX_DIFF_CHARS;
+    let mut fitted = Vec::with_capacity(files.len());
+    let count = files.len();
+    for (i, file) in files.into_iter().enumerate() {
+        let share = (budget / (count - i)).max(MIN_FILE_CHARS);
+        let text = truncate_lines(&file.text, share);
+        budget = budget.saturating_sub(text.len());
+        fitted.push((file.path, text));
+    }
+    // Back in path order, as git printed them.
+    fitted.sort();
+    fitted.into_iter().map(|(_, text)| text).collect()
+}
+
+fn prompt(root: &Path) -> Result<String, String> {
+    let stat = git::run(root, &["diff", "--cached", "--no-ext-diff", "--stat"])?;
+    if stat.trim().is_empty() {
+        return Err("No staged changes".to_string());
+    }
+    let diff = git::run(root, &["diff", "--cached", "--no-ext-diff", "--no-color"])?;
+    let mut prompt = format!("Files changed:\n{}\nDiff:\n{}", stat, fit_diff(&diff));
+    if git::has_head(root) {
+        let subjects = git::run(root, &["log", "-n", RECENT_SUBJECTS, "--format=%s"])?;
+        if !subjects.trim().is_empty() {
+            prompt.push_str(&format!(
+                "\nRecent commit subjects in this repository, for tone:\n{}",
+                subjects
+            ));
+        }
+    }
+    Ok(prompt)
+}
+
+/// Takes the message out of a reply that quoted or fenced it.
+fn clean_message(reply: &str) -> String {
+    let message = super::strip_fence(reply).trim();
+    let message = message
+        .strip_prefix('"')
+        .and_then(|m| m.strip_suffix('"'))
+        .unwrap_or(message);
+    message.trim().to_string()
+}
+
+/// Writes a Conventional Commits message for what is staged in the
+/// repository at `repo_path` (the active workspace root when omitted), for
+/// the source control panel to prefill. Large diffs are cut down per file
+/// and lockfiles are left to the stat.
+#[tauri::command]
+pub async fn ai_generate_commit_message(
+    repo_path: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let repo_path = match repo_path {
+        Some(path) => path,
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .map(|root| root.to_string_lossy().to_string())
+            .ok_or("No workspace is open")?,
+    };
+    let root = git::repo_root(&windows, &window, &repo_path)?;
+    let prompt = tauri::async_runtime::spawn_blocking(move || prompt(&root))
+        .await
+        .map_err(|e| format!("Failed to read staged changes: {}", e))??;
+
+    let kind = super::provider_kind(&app, None)?;
+    let model = super::string_setting(&app, "ai.model")?;
+    if model.is_empty() && kind != ProviderKind::LlamaCpp {
+        return Err("No model selected; set ai.model".to_string());
+    }
+    let request = ChatRequest {
+        model,
+        messages: vec![
+            ChatMessage {
+                role: Role::System,
+                content: INSTRUCTIONS.to_string(),
+            },
+            ChatMessage {
+                role: Role::User,
+                content: prompt,
+            },
+        ],
+        temperature: Some(0.2),
+        max_tokens: Some(400),
+        stop: Vec::new(),
+    };
+    let provider = super::provider(&app, &window, kind).await?;
+    let started = Instant::now();
+    let result = super::complete(provider.as_ref(), &request).await;
+    audit::record(&app, || {
+        let (reply, reported) = match &result {
+            Ok((reply, usage)) => (reply.as_str(), usage.clone()),
+            Err(_) => ("", None),
+        };
+        let input_tokens = tokens::for_model(&request.model)
+            .map_or(0, |bpe| tokens::count_messages(bpe, &request.messages));
+        let (usage, estimated) =
+            tokens::usage_or_estimate(reported, &request.model, input_tokens, reply);
+        AuditEntry {
+            kind: RequestKind::CommitMessage,
+            provider: provider.name(),
+            model: request.model.clone(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            estimated,
+            duration: started.elapsed(),
+            files: Vec::new(),
+            outcome: if result.is_ok() {
+                RequestOutcome::Completed
+            } else {
+                RequestOutcome::Failed
+            },
+        }
+    });
+    let (reply, _) = result?;
+    let message = clean_message(&reply);
+    if message.is_empty() {
+        return Err("The model returned an empty commit message".to_string());
+    }
+    Ok(message)
+}
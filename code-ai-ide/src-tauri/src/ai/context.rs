@@ -0,0 +1,570 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tauri::{Manager, State, Window};
+use tiktoken_rs::CoreBPE;
+
+use super::tokens;
+use crate::exclude::ExclusionMatcher;
+use crate::git;
+use crate::problem_matcher::Severity;
+use crate::semantic;
+use crate::syntax::{self, DocumentSymbol};
+use crate::walk::walk_files;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+const DEFAULT_BUDGET_TOKENS: usize = 8000;
+/// Larger files are skipped; they would not fit any budget anyway.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// A section is cut down only if at least this much of it fits; smaller
+/// leftovers are rarely useful.
+const MIN_PARTIAL_TOKENS: usize = 200;
+/// Entries listed for an `@folder` mention.
+const MAX_FOLDER_ENTRIES: usize = 200;
+/// Files read while looking for an `@symbol` definition.
+const MAX_SYMBOL_FILES: usize = 5000;
+const MAX_SYMBOL_MATCHES: usize = 5;
+const MAX_DIAGNOSTICS: usize = 50;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionKind {
+    File,
+    Folder,
+    Symbol,
+    Diagnostics,
+    GitDiff,
+    Semantic,
+    OpenEditor,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextSection {
+    pub kind: SectionKind,
+    pub title: String,
+    pub path: Option<String>,
+    /// Rendered as it appears in `context`.
+    pub content: String,
+    pub tokens: usize,
+    pub truncated: bool,
+}
+
+/// A section that did not fit the budget at all.
+#[derive(Debug, Clone, Serialize)]
+pub struct OmittedSection {
+    pub kind: SectionKind,
+    pub title: String,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedMention {
+    pub mention: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssembledContext {
+    pub sections: Vec<ContextSection>,
+    pub omitted: Vec<OmittedSection>,
+    pub unresolved: Vec<UnresolvedMention>,
+    pub total_tokens: usize,
+    pub budget_tokens: usize,
+    /// Every kept section joined, ready to send as a system message.
+    pub context: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContextOptions {
+    pub budget_tokens: Option<usize>,
+    pub include_open_editors: Option<bool>,
+    pub include_diagnostics: Option<bool>,
+    pub include_git_diff: Option<bool>,
+    /// Adds this many semantic index hits for the prompt.
+    pub semantic_results: Option<usize>,
+}
+
+/// Keeps whole lines of `text` while they fit in `budget` tokens.
+fn truncate_lines(bpe: &CoreBPE, text: &str, budget: usize) -> String {
+    let mut used = 0;
+    let mut kept = String::new();
+    for line in text.split_inclusive('\n') {
+        used += tokens::count(bpe, line);
+        if used > budget {
+            break;
+        }
+        kept.push_str(line);
+    }
+    kept
+}
+
+/// A piece of context before it is fitted into the budget.
+struct Candidate {
+    kind: SectionKind,
+    title: String,
+    path: Option<String>,
+    body: String,
+    /// Info string of the code fence, usually the language.
+    fence: String,
+}
+
+impl Candidate {
+    fn render(&self, body: &str, truncated: bool) -> String {
+        format!(
+            "### {}{}\n```{}\n{}\n```\n",
+            self.title,
+            if truncated { " (truncated)" } else { "" },
+            self.fence,
+            body.trim_end_matches('\n')
+        )
+    }
+}
+
+enum Mention {
+    File(String),
+    Folder(String),
+    Symbol(String),
+}
+
+impl Mention {
+    fn label(&self) -> String {
+        match self {
+            Mention::File(path) => format!("@file:{}", path),
+            Mention::Folder(path) => format!("@folder:{}", path),
+            Mention::Symbol(name) => format!("@symbol:{}", name),
+        }
+    }
+}
+
+/// `@file:path`, `@folder:path` and `@symbol:name`; paths with spaces are
+/// quoted, `@file:"my file.rs"`.
+fn parse_mentions(prompt: &str) -> Vec<Mention> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern =
+        PATTERN.get_or_init(|| Regex::new(r#"@(file|folder|symbol):(?:"([^"]+)"|(\S+))"#).unwrap());
+    pattern
+        .captures_iter(prompt)
+        .map(|captures| {
+            let target = match (captures.get(2), captures.get(3)) {
+                (Some(quoted), _) => quoted.as_str().to_string(),
+                // Trailing punctuation belongs to the sentence.
+                (None, Some(bare)) => bare
+                    .as_str()
+                    .trim_end_matches([',', '.', ';', ':', ')', '?', '!'])
+                    .to_string(),
+                (None, None) => String::new(),
+            };
+            match &captures[1] {
+                "file" => Mention::File(target),
+                "folder" => Mention::Folder(target),
+                _ => Mention::Symbol(target),
+            }
+        })
+        .collect()
+}
+
+struct Assembler<'a> {
+    window: &'a Window,
+    scope: &'a WindowState,
+    root: Option<PathBuf>,
+    candidates: Vec<Candidate>,
+    unresolved: Vec<UnresolvedMention>,
+    /// Files already included in full, so open editors do not repeat them.
+    included: Vec<PathBuf>,
+}
+
+pub(crate) fn fence_for(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+impl Assembler<'_> {
+    fn authorize(&self, path: &str) -> Result<PathBuf, String> {
+        let path = match &self.root {
+            Some(root) if Path::new(path).is_relative() => root.join(path),
+            _ => PathBuf::from(path),
+        };
+        let windows = self.window.state::<WindowRegistry>();
+        workspace::authorize(&windows, self.window, &path.to_string_lossy())
+    }
+
+    fn display(&self, path: &Path) -> String {
+        self.root
+            .as_ref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// The open buffer if there is one, so unsaved edits are what the
+    /// model sees.
+    fn read(&self, path: &Path) -> Result<String, String> {
+        if let Some(document) = self.scope.documents.get(&path.to_string_lossy()) {
+            return Ok(document.content);
+        }
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        if metadata.len() > MAX_FILE_BYTES {
+            return Err("File is too large".to_string());
+        }
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        if bytes.contains(&0) {
+            return Err("File is binary".to_string());
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn resolve(&mut self, mention: Mention) {
+        let label = mention.label();
+        let resolved = match mention {
+            Mention::File(path) => self.file(&path),
+            Mention::Folder(path) => self.folder(&path),
+            Mention::Symbol(name) => self.symbol(&name),
+        };
+        if let Err(reason) = resolved {
+            self.unresolved.push(UnresolvedMention {
+                mention: label,
+                reason,
+            });
+        }
+    }
+
+    fn file(&mut self, path: &str) -> Result<(), String> {
+        let path = self.authorize(path)?;
+        let body = self.read(&path)?;
+        self.candidates.push(Candidate {
+            kind: SectionKind::File,
+            title: self.display(&path),
+            path: Some(path.to_string_lossy().to_string()),
+            body,
+            fence: fence_for(&path),
+        });
+        self.included.push(path);
+        Ok(())
+    }
+
+    fn folder(&mut self, path: &str) -> Result<(), String> {
+        let path = self.authorize(path)?;
+        if !path.is_dir() {
+            return Err("Not a folder".to_string());
+        }
+        let globs = self.scope.exclusions.globs();
+        let matcher = ExclusionMatcher::new(&path, &globs)?;
+        let mut entries = Vec::new();
+        let mut more = false;
+        walk_files(&path, Some(&matcher), |file| {
+            if entries.len() == MAX_FOLDER_ENTRIES {
+                more = true;
+                return false;
+            }
+            entries.push(self.display(file));
+            true
+        });
+        entries.sort();
+        if more {
+            entries.push(format!("… more than {} files", MAX_FOLDER_ENTRIES));
+        }
+        self.candidates.push(Candidate {
+            kind: SectionKind::Folder,
+            title: format!("{}/", self.display(&path)),
+            path: Some(path.to_string_lossy().to_string()),
+            body: entries.join("\n"),
+            fence: String::new(),
+        });
+        Ok(())
+    }
+
+    /// Definitions of `name` found by parsing the indexed files that
+    /// mention it.
+    fn symbol(&mut self, name: &str) -> Result<(), String> {
+        fn find<'s>(symbols: &'s [DocumentSymbol], name: &str, out: &mut Vec<&'s DocumentSymbol>) {
+            for symbol in symbols {
+                if symbol.name == name {
+                    out.push(symbol);
+                }
+                find(&symbol.children, name, out);
+            }
+        }
+
+        let mut found = 0;
+        for path in self
+            .scope
+            .file_index
+            .files()
+            .into_iter()
+            .take(MAX_SYMBOL_FILES)
+        {
+            let Ok(content) = self.read(&path) else {
+                continue;
+            };
+            if !content.contains(name) {
+                continue;
+            }
+            let Some(symbols) = syntax::outline(&path, &content) else {
+                continue;
+            };
+            let mut matches = Vec::new();
+            find(&symbols, name, &mut matches);
+            let lines: Vec<&str> = content.lines().collect();
+            for symbol in matches {
+                let start = symbol.range.start.line as usize;
+                let end = (symbol.range.end.line as usize).min(lines.len().saturating_sub(1));
+                self.candidates.push(Candidate {
+                    kind: SectionKind::Symbol,
+                    title: format!("{} ({}:{})", name, self.display(&path), start + 1),
+                    path: Some(path.to_string_lossy().to_string()),
+                    body: lines[start..=end].join("\n"),
+                    fence: fence_for(&path),
+                });
+                found += 1;
+                if found == MAX_SYMBOL_MATCHES {
+                    return Ok(());
+                }
+            }
+        }
+        if found == 0 {
+            return Err("No definition found in the workspace".to_string());
+        }
+        Ok(())
+    }
+
+    /// Diagnostics of the mentioned and open files, or of everything when
+    /// there are none.
+    fn diagnostics(&mut self) {
+        let open: Vec<String> = self
+            .scope
+            .documents
+            .summaries()
+            .into_iter()
+            .map(|summary| summary.path)
+            .collect();
+        let relevant = |path: &str| {
+            open.iter().any(|open| open == path)
+                || self
+                    .included
+                    .iter()
+                    .any(|file| file.to_string_lossy() == path)
+        };
+        let all = self.scope.diagnostics.get(None);
+        let focused: Vec<_> = all.iter().filter(|file| relevant(&file.path)).collect();
+        let files = if focused.is_empty() {
+            all.iter().collect()
+        } else {
+            focused
+        };
+
+        let mut lines = Vec::new();
+        for file in files {
+            for diagnostic in &file.diagnostics {
+                if lines.len() == MAX_DIAGNOSTICS {
+                    break;
+                }
+                let severity = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info => "info",
+                };
+                let code = diagnostic
+                    .code
+                    .as_ref()
+                    .map(|code| format!(" [{}]", code))
+                    .unwrap_or_default();
+                lines.push(format!(
+                    "{}:{}:{}: {}: {}{}",
+                    self.display(Path::new(&file.path)),
+                    diagnostic.line,
+                    diagnostic.column,
+                    severity,
+                    diagnostic.message,
+                    code
+                ));
+            }
+        }
+        if !lines.is_empty() {
+            self.candidates.push(Candidate {
+                kind: SectionKind::Diagnostics,
+                title: "Diagnostics".to_string(),
+                path: None,
+                body: lines.join("\n"),
+                fence: String::new(),
+            });
+        }
+    }
+
+    /// Uncommitted changes, staged or not, in the active root.
+    fn git_diff(&mut self) {
+        let Some(root) = &self.root else {
+            return;
+        };
+        let diff = if git::has_head(root) {
+            git::run(root, &["diff", "HEAD", "--no-ext-diff"])
+        } else {
+            git::run(root, &["diff", "--cached", "--no-ext-diff"])
+        };
+        match diff {
+            Ok(diff) if !diff.trim().is_empty() => self.candidates.push(Candidate {
+                kind: SectionKind::GitDiff,
+                title: "Uncommitted changes".to_string(),
+                path: None,
+                body: diff,
+                fence: "diff".to_string(),
+            }),
+            _ => {}
+        }
+    }
+
+    fn open_editors(&mut self) {
+        for summary in self.scope.documents.summaries() {
+            let path = PathBuf::from(&summary.path);
+            if self.included.contains(&path) {
+                continue;
+            }
+            let Some(document) = self.scope.documents.get(&summary.path) else {
+                continue;
+            };
+            self.candidates.push(Candidate {
+                kind: SectionKind::OpenEditor,
+                title: format!(
+                    "{}{}",
+                    self.display(&path),
+                    if summary.dirty { " (unsaved)" } else { "" }
+                ),
+                path: Some(summary.path.clone()),
+                body: document.content,
+                fence: fence_for(&path),
+            });
+        }
+    }
+}
+
+/// Fits candidates into the budget in order; earlier ones win.
+fn fit(
+    bpe: &CoreBPE,
+    candidates: Vec<Candidate>,
+    budget: usize,
+) -> (Vec<ContextSection>, Vec<OmittedSection>, usize) {
+    let mut sections = Vec::new();
+    let mut omitted = Vec::new();
+    let mut used = 0;
+    for candidate in candidates {
+        let rendered = candidate.render(&candidate.body, false);
+        let tokens = tokens::count(bpe, &rendered);
+        let remaining = budget.saturating_sub(used);
+        let (content, truncated) = if tokens <= remaining {
+            (rendered, false)
+        } else if remaining >= MIN_PARTIAL_TOKENS {
+            // Leave room for the heading and fence around the cut body.
+            let overhead = tokens::count(bpe, &candidate.render("", true));
+            let body = truncate_lines(bpe, &candidate.body, remaining.saturating_sub(overhead));
+            (candidate.render(&body, true), true)
+        } else {
+            omitted.push(OmittedSection {
+                kind: candidate.kind,
+                title: candidate.title,
+                tokens,
+            });
+            continue;
+        };
+        let tokens = tokens::count(bpe, &content);
+        used += tokens;
+        sections.push(ContextSection {
+            kind: candidate.kind,
+            title: candidate.title,
+            path: candidate.path,
+            content,
+            tokens,
+            truncated,
+        });
+    }
+    (sections, omitted, used)
+}
+
+fn assemble(
+    window: &Window,
+    scope: &WindowState,
+    prompt: &str,
+    options: &ContextOptions,
+    semantic_hits: Vec<semantic::SemanticHit>,
+) -> Result<AssembledContext, String> {
+    let bpe = tokens::cl100k()?;
+    let mut assembler = Assembler {
+        window,
+        scope,
+        root: scope.workspace.active(),
+        candidates: Vec::new(),
+        unresolved: Vec::new(),
+        included: Vec::new(),
+    };
+    for mention in parse_mentions(prompt) {
+        assembler.resolve(mention);
+    }
+    if options.include_diagnostics.unwrap_or(true) {
+        assembler.diagnostics();
+    }
+    if options.include_git_diff.unwrap_or(true) {
+        assembler.git_diff();
+    }
+    for hit in semantic_hits {
+        assembler.candidates.push(Candidate {
+            kind: SectionKind::Semantic,
+            title: format!(
+                "{}:{}-{}",
+                hit.relative_path,
+                hit.start_line + 1,
+                hit.end_line + 1
+            ),
+            fence: fence_for(Path::new(&hit.path)),
+            path: Some(hit.path),
+            body: hit.text,
+        });
+    }
+    if options.include_open_editors.unwrap_or(true) {
+        assembler.open_editors();
+    }
+
+    let budget = options.budget_tokens.unwrap_or(DEFAULT_BUDGET_TOKENS);
+    let (sections, omitted, total_tokens) = fit(bpe, assembler.candidates, budget);
+    let context = sections
+        .iter()
+        .map(|section| section.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(AssembledContext {
+        sections,
+        omitted,
+        unresolved: assembler.unresolved,
+        total_tokens,
+        budget_tokens: budget,
+        context,
+    })
+}
+
+/// Gathers the context for a chat prompt: `@file:`, `@folder:` and
+/// `@symbol:` mentions, diagnostics, the uncommitted diff, semantic index
+/// hits and open editors, in that priority, trimmed to the token budget.
+#[tauri::command]
+pub async fn assemble_context(
+    prompt: String,
+    options: Option<ContextOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<AssembledContext, String> {
+    let options = options.unwrap_or_default();
+    let scope: Arc<WindowState> = windows.scope(window.label());
+    let semantic_hits = match options.semantic_results.filter(|k| *k > 0) {
+        // An index that is not running just contributes nothing.
+        Some(k) => semantic::search(&scope, &prompt, k)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        assemble(&window, &scope, &prompt, &options, semantic_hits)
+    })
+    .await
+    .map_err(|e| format!("Failed to assemble context: {}", e))?
+}
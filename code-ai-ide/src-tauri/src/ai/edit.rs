@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, State, Window};
+
+use super::sessions::ChatSessions;
+use crate::compare::{self, ComparisonResult, DiffOptions};
+use crate::documents::DocumentStore;
+use crate::encoding;
+use crate::local_history::{LocalHistory, RevisionSource};
+use crate::replace;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// A change to one file as a model proposes it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum AiEdit {
+    /// A unified diff. File headers are ignored and the line numbers in hunk
+    /// headers are only hints, since models rarely get them right.
+    UnifiedDiff { text: String },
+    /// `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks.
+    SearchReplace { text: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposedHunk {
+    /// Position of the hunk in the proposal; what `apply_ai_edit` accepts.
+    pub index: usize,
+    /// One-based line of the current content where the hunk applies.
+    pub start_line: usize,
+    pub old_text: String,
+    pub new_text: String,
+    pub diff: ComparisonResult,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HunkFailure {
+    pub index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiEditPreview {
+    pub path: String,
+    /// Version of the open document the preview was made against; `None`
+    /// when the file is not open.
+    pub version: Option<u64>,
+    pub hunks: Vec<ProposedHunk>,
+    pub failures: Vec<HunkFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiEditResult {
+    pub applied: bool,
+    pub applied_hunks: Vec<usize>,
+    pub failures: Vec<HunkFailure>,
+    /// New version of the open document, when the edit went to one.
+    pub version: Option<u64>,
+}
+
+struct Hunk {
+    old: Vec<String>,
+    new: Vec<String>,
+    /// One-based old start line from a unified diff hunk header.
+    old_start: Option<usize>,
+}
+
+/// A hunk located in the current content: it replaces lines `start..end`.
+struct Placed {
+    index: usize,
+    start: usize,
+    end: usize,
+    new: Vec<String>,
+}
+
+enum Source {
+    Document {
+        version: u64,
+        content: String,
+        encoding: String,
+        has_bom: bool,
+    },
+    Disk {
+        bytes: Vec<u8>,
+        content: String,
+        encoding: String,
+        has_bom: bool,
+    },
+}
+
+impl Source {
+    /// The open document wins over the file on disk, as everywhere else.
+    fn read(store: &DocumentStore, path: &Path) -> Result<Self, String> {
+        if let Some(doc) = store.get(&path.to_string_lossy()) {
+            return Ok(Source::Document {
+                version: doc.version,
+                content: doc.content,
+                encoding: doc.encoding,
+                has_bom: doc.has_bom,
+            });
+        }
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let decoded = encoding::decode(&bytes);
+        // Writing lossy text back would destroy the bytes it could not map.
+        if decoded.had_errors {
+            return Err(format!(
+                "File is not valid {} text: {}",
+                decoded.encoding,
+                path.display()
+            ));
+        }
+        Ok(Source::Disk {
+            bytes,
+            content: decoded.content,
+            encoding: decoded.encoding,
+            has_bom: decoded.has_bom,
+        })
+    }
+
+    fn content(&self) -> &str {
+        match self {
+            Source::Document { content, .. } | Source::Disk { content, .. } => content,
+        }
+    }
+
+    fn version(&self) -> Option<u64> {
+        match self {
+            Source::Document { version, .. } => Some(*version),
+            Source::Disk { .. } => None,
+        }
+    }
+}
+
+fn parse(edit: &AiEdit) -> Result<Vec<Hunk>, String> {
+    let hunks = match edit {
+        AiEdit::UnifiedDiff { text } => parse_unified_diff(text),
+        AiEdit::SearchReplace { text } => parse_search_replace(text)?,
+    };
+    if hunks.is_empty() {
+        return Err("The proposed edit contains no changes".to_string());
+    }
+    Ok(hunks)
+}
+
+/// "@@ -12,5 +12,6 @@ fn main" gives 12.
+fn hunk_old_start(header: &str) -> Option<usize> {
+    let old = header.trim_start().strip_prefix('-')?;
+    let end = old.find([',', ' ']).unwrap_or(old.len());
+    old[..end].parse().ok()
+}
+
+fn parse_unified_diff(text: &str) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(header) = line.strip_prefix("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                old: Vec::new(),
+                new: Vec::new(),
+                old_start: hunk_old_start(header),
+            });
+            continue;
+        }
+        // A "--- " line is a file header only when "+++ " follows; on its
+        // own it is a removed line starting with "--".
+        let file_header = line.starts_with("diff ")
+            || line.starts_with("index ")
+            || (line.starts_with("--- ")
+                && lines.peek().is_some_and(|next| next.starts_with("+++ ")));
+        if file_header {
+            hunks.extend(current.take());
+            continue;
+        }
+        let Some(hunk) = current.as_mut() else {
+            continue;
+        };
+        match line.chars().next() {
+            Some('-') => hunk.old.push(line[1..].to_string()),
+            Some('+') => hunk.new.push(line[1..].to_string()),
+            // "\ No newline at end of file"
+            Some('\\') => {}
+            // Models often drop the leading space of context lines.
+            _ => {
+                let context = line.strip_prefix(' ').unwrap_or(line);
+                hunk.old.push(context.to_string());
+                hunk.new.push(context.to_string());
+            }
+        }
+    }
+    hunks.extend(current);
+    hunks.retain(|hunk| hunk.old != hunk.new);
+    hunks
+}
+
+fn parse_search_replace(text: &str) -> Result<Vec<Hunk>, String> {
+    #[derive(PartialEq)]
+    enum Part {
+        Outside,
+        Search,
+        Replace,
+    }
+
+    let mut hunks = Vec::new();
+    let mut part = Part::Outside;
+    let (mut old, mut new) = (Vec::new(), Vec::new());
+    for line in text.lines() {
+        let marker = line.trim();
+        match part {
+            Part::Outside if marker.starts_with("<<<<<<<") => part = Part::Search,
+            Part::Outside => {}
+            Part::Search if marker.len() >= 7 && marker.chars().all(|c| c == '=') => {
+                part = Part::Replace
+            }
+            Part::Search => old.push(line.to_string()),
+            Part::Replace if marker.starts_with(">>>>>>>") => {
+                hunks.push(Hunk {
+                    old: std::mem::take(&mut old),
+                    new: std::mem::take(&mut new),
+                    old_start: None,
+                });
+                part = Part::Outside;
+            }
+            Part::Replace => new.push(line.to_string()),
+        }
+    }
+    if part != Part::Outside {
+        return Err("Unterminated SEARCH/REPLACE block".to_string());
+    }
+    Ok(hunks)
+}
+
+/// Finds where `hunk` applies in `lines`: an exact match first, then one
+/// ignoring trailing and then all surrounding whitespace. Several matches
+/// are resolved by the line number from the diff when there is one and are
+/// an error otherwise.
+fn locate(lines: &[&str], hunk: &Hunk) -> Result<usize, String> {
+    if hunk.old.is_empty() {
+        // "@@ -5,0 +6,2 @@" inserts after line 5.
+        return match hunk.old_start {
+            Some(line) if line <= lines.len() => Ok(line),
+            Some(line) => Err(format!(
+                "Line {} is past the end of the file ({} lines)",
+                line,
+                lines.len()
+            )),
+            None if lines.is_empty() => Ok(0),
+            None => Err("The text to replace is empty".to_string()),
+        };
+    }
+    if hunk.old.len() > lines.len() {
+        return Err("The text to replace was not found".to_string());
+    }
+    let hint = hunk.old_start.map(|line| line.saturating_sub(1));
+    let normalizers: [fn(&str) -> &str; 3] = [|line| line, str::trim_end, str::trim];
+    for normalize in normalizers {
+        let found: Vec<usize> = (0..=lines.len() - hunk.old.len())
+            .filter(|&start| {
+                hunk.old
+                    .iter()
+                    .zip(&lines[start..])
+                    .all(|(old, line)| normalize(old) == normalize(line))
+            })
+            .collect();
+        match (found.as_slice(), hint) {
+            ([], _) => continue,
+            ([start], _) => return Ok(*start),
+            (_, Some(hint)) => {
+                return Ok(found
+                    .into_iter()
+                    .min_by_key(|start| start.abs_diff(hint))
+                    .unwrap_or_default())
+            }
+            (_, None) => {
+                return Err(format!(
+                    "The text to replace matches {} places; include more context",
+                    found.len()
+                ))
+            }
+        }
+    }
+    Err("The text to replace was not found".to_string())
+}
+
+fn place(lines: &[&str], hunks: Vec<Hunk>) -> (Vec<Placed>, Vec<HunkFailure>) {
+    let mut placed = Vec::new();
+    let mut failures = Vec::new();
+    for (index, hunk) in hunks.into_iter().enumerate() {
+        match locate(lines, &hunk) {
+            Ok(start) => placed.push(Placed {
+                index,
+                start,
+                end: start + hunk.old.len(),
+                new: hunk.new,
+            }),
+            Err(message) => failures.push(HunkFailure { index, message }),
+        }
+    }
+    (placed, failures)
+}
+
+/// Sorts the hunks by position and drops, with a failure, any that overlaps
+/// one before it.
+fn remove_overlaps(placed: &mut Vec<Placed>, failures: &mut Vec<HunkFailure>) {
+    placed.sort_by_key(|hunk| (hunk.start, hunk.end));
+    let mut kept: Vec<Placed> = Vec::new();
+    for hunk in placed.drain(..) {
+        match kept.last() {
+            Some(previous) if hunk.start < previous.end => failures.push(HunkFailure {
+                index: hunk.index,
+                message: format!("Overlaps hunk {}", previous.index),
+            }),
+            _ => kept.push(hunk),
+        }
+    }
+    *placed = kept;
+    failures.sort_by_key(|failure| failure.index);
+}
+
+/// Replaces the placed hunks, which must be sorted and disjoint, keeping the
+/// line ending style and final newline of `content`.
+fn splice(content: &str, lines: &[&str], placed: &[Placed]) -> String {
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut out: Vec<&str> = Vec::new();
+    let mut next = 0;
+    for hunk in placed {
+        out.extend(&lines[next..hunk.start]);
+        out.extend(hunk.new.iter().map(String::as_str));
+        next = hunk.end;
+    }
+    out.extend(&lines[next..]);
+    let mut text = out.join(eol);
+    if !out.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+        text.push_str(eol);
+    }
+    text
+}
+
+fn text_of<S: AsRef<str>>(lines: &[S]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{}\n", line.as_ref()))
+        .collect()
+}
+
+/// Locates every hunk of a model-proposed edit in the current content of
+/// `path` (the open document, else the file) without changing anything.
+/// Hunks that do not apply are listed in `failures`; the rest carry a diff
+/// for review.
+#[tauri::command]
+pub async fn preview_ai_edit(
+    path: String,
+    edit: AiEdit,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<AiEditPreview, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let hunks = parse(&edit)?;
+    let source = Source::read(&windows.scope(window.label()).documents, &path)?;
+    let lines: Vec<&str> = source.content().lines().collect();
+    let (mut placed, mut failures) = place(&lines, hunks);
+    remove_overlaps(&mut placed, &mut failures);
+
+    let options = DiffOptions::default();
+    let hunks = placed
+        .iter()
+        .map(|hunk| {
+            let old_text = text_of(&lines[hunk.start..hunk.end]);
+            let new_text = text_of(&hunk.new);
+            ProposedHunk {
+                index: hunk.index,
+                start_line: hunk.start + 1,
+                diff: compare::compare(&old_text, &new_text, &options),
+                old_text,
+                new_text,
+            }
+        })
+        .collect();
+    Ok(AiEditPreview {
+        path: path.to_string_lossy().to_string(),
+        version: source.version(),
+        hunks,
+        failures,
+    })
+}
+
+/// Applies the `accepted` hunks of a model-proposed edit (all of them when
+/// omitted), all or nothing. The hunks are located again against the
+/// current content, so typing since the preview is fine as long as they
+/// still apply. An open document gets the change in its buffer as one new
+/// version; otherwise the file is rewritten in its own encoding. Either
+/// way the previous content is kept in local history, and the edit is
+/// recorded in the chat session `session_id` when given.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn apply_ai_edit(
+    path: String,
+    edit: AiEdit,
+    accepted: Option<Vec<usize>>,
+    session_id: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+    sessions: State<'_, ChatSessions>,
+) -> Result<AiEditResult, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let hunks = parse(&edit)?;
+    let accepted = accepted.unwrap_or_else(|| (0..hunks.len()).collect());
+    let mut failures: Vec<HunkFailure> = accepted
+        .iter()
+        .filter(|&&index| index >= hunks.len())
+        .map(|&index| HunkFailure {
+            index,
+            message: "No such hunk".to_string(),
+        })
+        .collect();
+
+    let scope = windows.scope(window.label());
+    let source = Source::read(&scope.documents, &path)?;
+    let lines: Vec<&str> = source.content().lines().collect();
+    let (mut placed, placing_failures) = place(&lines, hunks);
+    placed.retain(|hunk| accepted.contains(&hunk.index));
+    failures.extend(
+        placing_failures
+            .into_iter()
+            .filter(|failure| accepted.contains(&failure.index)),
+    );
+    remove_overlaps(&mut placed, &mut failures);
+    if !failures.is_empty() || placed.is_empty() {
+        return Ok(AiEditResult {
+            applied: false,
+            applied_hunks: Vec::new(),
+            failures,
+            version: source.version(),
+        });
+    }
+
+    let content = splice(source.content(), &lines, &placed);
+    let mut applied_hunks: Vec<usize> = placed.iter().map(|hunk| hunk.index).collect();
+    applied_hunks.sort_unstable();
+    let (previous, version) = match source {
+        Source::Document {
+            version,
+            content: previous,
+            encoding,
+            has_bom,
+        } => {
+            if !scope
+                .documents
+                .replace_if_current(&path.to_string_lossy(), version, content)
+            {
+                return Err("The document changed while the edit was applied".to_string());
+            }
+            let previous = encoding::encode(&previous, &encoding, has_bom)
+                .unwrap_or_else(|_| previous.into_bytes());
+            (previous, Some(version + 1))
+        }
+        Source::Disk {
+            bytes,
+            encoding,
+            has_bom,
+            ..
+        } => {
+            let encoded = encoding::encode(&content, &encoding, has_bom)?;
+            replace::write_replacing(&path, &encoded)?;
+            (bytes, None)
+        }
+    };
+    let _ = history.record(&app, &path, &previous, RevisionSource::AiEdit);
+    if let Some(session_id) = &session_id {
+        let _ = sessions.record_edit(&app, session_id, &path.to_string_lossy(), &applied_hunks);
+    }
+    Ok(AiEditResult {
+        applied: true,
+        applied_hunks,
+        failures: Vec::new(),
+        version,
+    })
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+use super::local::{self, LocalBackend};
+use super::openai::OPENAI_BASE_URL;
+use super::ProviderKind;
+use crate::secrets;
+use crate::settings;
+
+/// Turns text into vectors for similarity search.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Provider and model; vectors from different ids are not comparable,
+    /// so an index built with another id has to be rebuilt.
+    fn id(&self) -> String;
+
+    /// One vector per input, in input order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+fn vector(value: &Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|n| n.as_f64().map(|n| n as f32))
+        .collect()
+}
+
+/// `/embeddings` of the OpenAI API and servers that copy it.
+pub struct OpenAiEmbeddings {
+    pub name: &'static str,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddings {
+    fn id(&self) -> String {
+        format!("{}:{}", self.name, self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut builder = super::client()?
+            .post(format!(
+                "{}/embeddings",
+                self.base_url.trim_end_matches('/')
+            ))
+            .json(&json!({ "model": self.model, "input": texts }));
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        let body: Value = super::send(self.name, builder)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from {}: {}", self.name, e))?;
+        let mut data: Vec<&Value> = body["data"].as_array().into_iter().flatten().collect();
+        data.sort_by_key(|item| item["index"].as_u64().unwrap_or(0));
+        let vectors: Vec<Vec<f32>> = data
+            .into_iter()
+            .filter_map(|item| vector(&item["embedding"]))
+            .collect();
+        if vectors.len() != texts.len() {
+            return Err(format!(
+                "{} returned {} embeddings for {} inputs",
+                self.name,
+                vectors.len(),
+                texts.len()
+            ));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Ollama's `/api/embed`.
+pub struct OllamaEmbeddings {
+    pub base_url: String,
+    pub model: String,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    fn id(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let builder = super::client()?
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({ "model": self.model, "input": texts }));
+        let body: Value = super::send("ollama", builder)
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from ollama: {}", e))?;
+        let vectors: Vec<Vec<f32>> = body["embeddings"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(vector)
+            .collect();
+        if vectors.len() != texts.len() {
+            return Err(format!(
+                "ollama returned {} embeddings for {} inputs",
+                vectors.len(),
+                texts.len()
+            ));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Builds the embedding provider from the `ai.embeddings.*` settings.
+/// Without a model, each provider's common default is used.
+pub async fn provider(app: &AppHandle) -> Result<Box<dyn EmbeddingProvider>, String> {
+    let kind: ProviderKind =
+        serde_json::from_value(settings::user_setting(app, "ai.embeddings.provider")?)
+            .map_err(|e| format!("Invalid ai.embeddings.provider: {}", e))?;
+    let model = super::string_setting(app, "ai.embeddings.model")?;
+    let model = |default: &str| {
+        if model.is_empty() {
+            default.to_string()
+        } else {
+            model.clone()
+        }
+    };
+    Ok(match kind {
+        ProviderKind::Openai => Box::new(OpenAiEmbeddings {
+            name: "openai",
+            base_url: OPENAI_BASE_URL.to_string(),
+            api_key: Some(
+                secrets::load("openai")
+                    .await?
+                    .ok_or("No OpenAI API key is set")?,
+            ),
+            model: model("text-embedding-3-small"),
+        }),
+        ProviderKind::OpenaiCompatible => {
+            let base_url = super::string_setting(app, "ai.baseUrl")?;
+            if base_url.is_empty() {
+                return Err("Set ai.baseUrl to use an OpenAI-compatible server".to_string());
+            }
+            // There is no common default model to fall back to.
+            let model = model("");
+            if model.is_empty() {
+                return Err("Set ai.embeddings.model for the OpenAI-compatible server".to_string());
+            }
+            Box::new(OpenAiEmbeddings {
+                name: "openai_compatible",
+                base_url,
+                api_key: secrets::load("openai_compatible").await?,
+                model,
+            })
+        }
+        ProviderKind::Ollama => Box::new(OllamaEmbeddings {
+            base_url: local::endpoint(app, LocalBackend::Ollama)?,
+            model: model("nomic-embed-text"),
+        }),
+        ProviderKind::LlamaCpp => Box::new(OpenAiEmbeddings {
+            name: "llama_cpp",
+            base_url: format!("{}/v1", local::endpoint(app, LocalBackend::LlamaCpp)?),
+            api_key: None,
+            model: model("default"),
+        }),
+        ProviderKind::Anthropic => {
+            return Err(
+                "Anthropic has no embeddings API; pick another ai.embeddings.provider".to_string(),
+            )
+        }
+    })
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State, Window};
+
+use super::context::fence_for;
+use super::{ChatMessage, ChatOptions, Role};
+use crate::encoding;
+use crate::problem_matcher::{Diagnostic, ProblemMatcher, Severity};
+use crate::process::OutputStream;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+/// Lines shown above and below the reported line.
+const CONTEXT_LINES: usize = 20;
+/// Locations from terminal output given code context.
+const MAX_LOCATIONS: usize = 3;
+/// The end of long output is kept; tools print the summary last.
+const MAX_OUTPUT_CHARS: usize = 12_000;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+const INSTRUCTIONS: &str = "You explain errors reported by compilers, linters, test runners and \
+other developer tools. Say briefly what the error means and why it happens in this code. Then, \
+if a code change fixes it, give the fix as one or more blocks of this form, each preceded by a \
+line with the file path:\n\n<<<<<<< SEARCH\nlines to replace, copied exactly from the file\n\
+=======\nthe replacement lines\n>>>>>>> REPLACE\n\nKeep the SEARCH part short but unique in the file.";
+
+/// The open buffer if there is one, else the file as text.
+pub(crate) fn read_text(scope: &WindowState, path: &Path) -> Option<String> {
+    if let Some(document) = scope.documents.get(&path.to_string_lossy()) {
+        return Some(document.content);
+    }
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    (!bytes.contains(&0)).then(|| encoding::decode(&bytes).content)
+}
+
+/// Lines around one-based `line`, numbered, with the line itself marked.
+fn excerpt(content: &str, line: u32) -> String {
+    let target = (line as usize).max(1) - 1;
+    let first = target.saturating_sub(CONTEXT_LINES);
+    content
+        .lines()
+        .enumerate()
+        .skip(first)
+        .take(target - first + CONTEXT_LINES + 1)
+        .map(|(i, text)| {
+            let marker = if i == target { ">" } else { " " };
+            format!("{}{:>5} | {}\n", marker, i + 1, text)
+        })
+        .collect()
+}
+
+pub(crate) fn display(path: &Path, root: Option<&Path>) -> String {
+    root.and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+pub(crate) fn describe(diagnostic: &Diagnostic, root: Option<&Path>) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    };
+    let code = diagnostic
+        .code
+        .as_ref()
+        .map(|code| format!("[{}]", code))
+        .unwrap_or_default();
+    format!(
+        "{}:{}:{}: {}{}: {} ({})",
+        display(Path::new(&diagnostic.path), root),
+        diagnostic.line,
+        diagnostic.column,
+        severity,
+        code,
+        diagnostic.message,
+        diagnostic.source
+    )
+}
+
+/// A fenced excerpt of `path` around `line`, when the file can be read.
+fn code_section(
+    scope: &WindowState,
+    path: &Path,
+    line: u32,
+    root: Option<&Path>,
+) -> Option<String> {
+    let content = read_text(scope, path)?;
+    Some(format!(
+        "{} around line {}:\n```{}\n{}```\n",
+        display(path, root),
+        line,
+        fence_for(path),
+        excerpt(&content, line)
+    ))
+}
+
+fn messages(prompt: String) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: Role::System,
+            content: INSTRUCTIONS.to_string(),
+        },
+        ChatMessage {
+            role: Role::User,
+            content: prompt,
+        },
+    ]
+}
+
+/// Asks the model to explain a diagnostic (by the id `get_diagnostics`
+/// gave it) and suggest a fix, with the code around it and the other
+/// problems reported nearby. Returns the chat request id; the answer
+/// streams as for `ai_chat`, with fixes as SEARCH/REPLACE blocks ready for
+/// `preview_ai_edit`.
+#[tauri::command]
+pub async fn ai_explain_diagnostic(
+    diagnostic_id: String,
+    options: Option<ChatOptions>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let scope = windows.scope(window.label());
+    let diagnostic = scope
+        .diagnostics
+        .find(&diagnostic_id)
+        .ok_or_else(|| format!("No such diagnostic: {}", diagnostic_id))?;
+    let path = workspace::authorize(&windows, &window, &diagnostic.path)?;
+    let root = scope.workspace.active();
+    let root = root.as_deref();
+
+    let mut prompt = format!(
+        "Explain this problem:\n\n{}\n\n",
+        describe(&diagnostic, root)
+    );
+    if let Some(code) = code_section(&scope, &path, diagnostic.line, root) {
+        prompt.push_str(&code);
+    }
+    let nearby: Vec<String> = scope
+        .diagnostics
+        .get(Some(&diagnostic.path))
+        .into_iter()
+        .flat_map(|file| file.diagnostics)
+        .filter(|other| {
+            other.id != diagnostic.id
+                && other.line.abs_diff(diagnostic.line) as usize <= CONTEXT_LINES
+        })
+        .map(|other| describe(&other, root))
+        .collect();
+    if !nearby.is_empty() {
+        prompt.push_str(&format!("\nAlso reported nearby:\n{}\n", nearby.join("\n")));
+    }
+    super::start_chat(
+        app,
+        window,
+        messages(prompt),
+        None,
+        options.unwrap_or_default(),
+    )
+    .await
+}
+
+/// Asks the model to explain terminal output and suggest a fix. `output`
+/// is the range the user selected, which only the terminal view holds.
+/// Errors with file locations in it are looked up and the code around the
+/// first few is included. `cwd` resolves relative paths in the output and
+/// defaults to the workspace root. Returns the chat request id.
+#[tauri::command]
+pub async fn ai_explain_terminal_output(
+    output: String,
+    cwd: Option<String>,
+    command: Option<String>,
+    options: Option<ChatOptions>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    if output.trim().is_empty() {
+        return Err("No output to explain".to_string());
+    }
+    let scope = windows.scope(window.label());
+    let root = scope.workspace.active();
+    let cwd: Option<PathBuf> = match cwd {
+        Some(cwd) => Some(workspace::authorize(&windows, &window, &cwd)?),
+        None => root.clone(),
+    };
+
+    let start = output
+        .char_indices()
+        .rev()
+        .nth(MAX_OUTPUT_CHARS)
+        .map_or(0, |(i, _)| i);
+    let mut prompt = String::from("Explain this terminal output");
+    if let Some(command) = command.filter(|c| !c.trim().is_empty()) {
+        prompt.push_str(&format!(" from `{}`", command.trim()));
+    }
+    prompt.push_str(&format!(
+        ":\n\n```\n{}\n```\n\n",
+        output[start..].trim_end()
+    ));
+
+    if let Some(cwd) = &cwd {
+        let mut matcher = ProblemMatcher::new(cwd);
+        matcher.feed(OutputStream::Stdout, &output);
+        let mut seen = Vec::new();
+        for diagnostic in matcher.finish() {
+            if seen.len() == MAX_LOCATIONS {
+                break;
+            }
+            if diagnostic.line == 0 || seen.contains(&diagnostic.path) {
+                continue;
+            }
+            let Ok(path) = workspace::authorize(&windows, &window, &diagnostic.path) else {
+                continue;
+            };
+            if let Some(code) = code_section(&scope, &path, diagnostic.line, root.as_deref()) {
+                prompt.push_str(&code);
+                seen.push(diagnostic.path);
+            }
+        }
+    }
+    super::start_chat(
+        app,
+        window,
+        messages(prompt),
+        None,
+        options.unwrap_or_default(),
+    )
+    .await
+}
@@ -0,0 +1,430 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State, Window};
+use tokio::sync::oneshot;
+
+use super::audit::{self, AuditEntry, RequestKind, RequestOutcome};
+use super::local::{self, LocalBackend};
+use super::tokens;
+use super::{ChatMessage, ChatRequest, ProviderKind, Role};
+use crate::secrets;
+use crate::settings;
+use crate::window_state::WindowRegistry;
+
+/// Context sent around the cursor; completions need the nearby code, not
+/// the whole file.
+const MAX_PREFIX_CHARS: usize = 6000;
+const MAX_SUFFIX_CHARS: usize = 2000;
+const MAX_COMPLETION_TOKENS: u32 = 128;
+const TEMPERATURE: f32 = 0.2;
+/// Ghost text that arrives later than this is no longer wanted.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const CACHE_SIZE: usize = 64;
+const LATENCY_SAMPLES: usize = 100;
+
+/// Used with chat models, which cannot fill in the middle natively.
+const CHAT_INSTRUCTIONS: &str = "You complete code. The user sends a file with the cursor marked \
+as <CURSOR>. Reply with only the text to insert at the cursor, without explanations or code \
+fences. Reply with nothing if no insertion makes sense.";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineCompletion {
+    pub text: String,
+    /// Served from recent completions without a request.
+    pub cached: bool,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InlineCompletionStats {
+    pub requests: u64,
+    pub completions: u64,
+    pub cache_hits: u64,
+    /// Requests dropped because a newer one came in first.
+    pub superseded: u64,
+    pub errors: u64,
+    /// Over the last requests that reached the model.
+    pub average_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+struct CachedCompletion {
+    path: String,
+    /// Length of the whole prefix and its last `MAX_PREFIX_CHARS` chars.
+    prefix_len: usize,
+    prefix_tail: String,
+    suffix_head: String,
+    text: String,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancel: Option<oneshot::Sender<()>>,
+    cache: VecDeque<CachedCompletion>,
+    latencies: VecDeque<u64>,
+    stats: InlineCompletionStats,
+}
+
+/// Inline completion state of a window. Only the newest request matters:
+/// starting one cancels the one before it.
+#[derive(Default)]
+pub struct InlineCompletions {
+    inner: Mutex<Inner>,
+}
+
+impl InlineCompletions {
+    fn begin(&self) -> oneshot::Receiver<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stats.requests += 1;
+        if let Some(cancel) = inner.cancel.take() {
+            let _ = cancel.send(());
+        }
+        let (sender, receiver) = oneshot::channel();
+        inner.cancel = Some(sender);
+        receiver
+    }
+
+    pub fn cancel(&self) {
+        if let Some(cancel) = self.inner.lock().unwrap().cancel.take() {
+            let _ = cancel.send(());
+        }
+    }
+
+    /// A cached completion still valid at the cursor: the same spot, or
+    /// further on after typing the start of the completion.
+    fn lookup(&self, path: &str, prefix: &str, suffix: &str) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let suffix_head = head(suffix, MAX_SUFFIX_CHARS);
+        let text = inner.cache.iter().rev().find_map(|entry| {
+            if entry.path != path || entry.suffix_head != suffix_head {
+                return None;
+            }
+            let before = prefix.get(..entry.prefix_len)?;
+            let typed = &prefix[entry.prefix_len..];
+            if !before.ends_with(&entry.prefix_tail) {
+                return None;
+            }
+            let rest = entry.text.strip_prefix(typed)?;
+            (!rest.is_empty()).then(|| rest.to_string())
+        })?;
+        inner.stats.cache_hits += 1;
+        inner.stats.completions += 1;
+        Some(text)
+    }
+
+    fn store(&self, path: &str, prefix: &str, suffix: &str, text: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.cache.len() == CACHE_SIZE {
+            inner.cache.pop_front();
+        }
+        inner.cache.push_back(CachedCompletion {
+            path: path.to_string(),
+            prefix_len: prefix.len(),
+            prefix_tail: tail(prefix, MAX_PREFIX_CHARS).to_string(),
+            suffix_head: head(suffix, MAX_SUFFIX_CHARS).to_string(),
+            text: text.to_string(),
+        });
+    }
+
+    fn record(&self, outcome: &Outcome, latency: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        match outcome {
+            Outcome::Superseded => inner.stats.superseded += 1,
+            Outcome::Failed => inner.stats.errors += 1,
+            Outcome::Completed { empty } => {
+                if !empty {
+                    inner.stats.completions += 1;
+                }
+                if inner.latencies.len() == LATENCY_SAMPLES {
+                    inner.latencies.pop_front();
+                }
+                inner.latencies.push_back(latency.as_millis() as u64);
+            }
+        }
+    }
+
+    fn stats(&self) -> InlineCompletionStats {
+        let inner = self.inner.lock().unwrap();
+        let mut sorted: Vec<u64> = inner.latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: usize| {
+            sorted
+                .get((sorted.len() * p / 100).min(sorted.len().saturating_sub(1)))
+                .copied()
+                .unwrap_or(0)
+        };
+        InlineCompletionStats {
+            average_ms: sorted.iter().sum::<u64>() / sorted.len().max(1) as u64,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            ..inner.stats.clone()
+        }
+    }
+}
+
+enum Outcome {
+    Completed { empty: bool },
+    Superseded,
+    Failed,
+}
+
+/// The last `max` chars of `text`.
+fn tail(text: &str, max: usize) -> &str {
+    if max == 0 {
+        return "";
+    }
+    match text.char_indices().rev().nth(max - 1) {
+        Some((start, _)) => &text[start..],
+        None => text,
+    }
+}
+
+/// The first `max` chars of `text`.
+fn head(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+fn completion_text(value: &Value) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Unexpected completion response".to_string())
+}
+
+async fn post_json(provider: &str, builder: reqwest::RequestBuilder) -> Result<Value, String> {
+    super::send(provider, builder)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", provider, e))
+}
+
+/// Asks for the text between `prefix` and `suffix`, with the native
+/// fill-in-the-middle API where the backend has one.
+async fn fill_in_middle(
+    app: &AppHandle,
+    window: &Window,
+    kind: ProviderKind,
+    model: String,
+    prefix: &str,
+    suffix: &str,
+    language: &str,
+) -> Result<String, String> {
+    let client = super::client()?;
+    match kind {
+        ProviderKind::Ollama => {
+            let base_url = local::endpoint(app, LocalBackend::Ollama)?;
+            let body = json!({
+                "model": model,
+                "prompt": prefix,
+                "suffix": suffix,
+                "stream": false,
+                "options": { "num_predict": MAX_COMPLETION_TOKENS, "temperature": TEMPERATURE },
+            });
+            let builder = client
+                .post(format!("{}/api/generate", base_url))
+                .json(&body);
+            completion_text(&post_json("ollama", builder).await?["response"])
+        }
+        ProviderKind::LlamaCpp => {
+            let base_url = local::endpoint(app, LocalBackend::LlamaCpp)?;
+            let body = json!({
+                "input_prefix": prefix,
+                "input_suffix": suffix,
+                "n_predict": MAX_COMPLETION_TOKENS,
+                "temperature": TEMPERATURE,
+            });
+            let builder = client.post(format!("{}/infill", base_url)).json(&body);
+            completion_text(&post_json("llama_cpp", builder).await?["content"])
+        }
+        // The legacy Completions API takes a suffix on most servers that
+        // copy OpenAI's.
+        ProviderKind::OpenaiCompatible => {
+            let base_url = super::string_setting(app, "ai.baseUrl")?;
+            if base_url.is_empty() {
+                return Err("Set ai.baseUrl to use an OpenAI-compatible server".to_string());
+            }
+            let body = json!({
+                "model": model,
+                "prompt": prefix,
+                "suffix": suffix,
+                "max_tokens": MAX_COMPLETION_TOKENS,
+                "temperature": TEMPERATURE,
+            });
+            let mut builder = client
+                .post(format!("{}/completions", base_url.trim_end_matches('/')))
+                .json(&body);
+            if let Some(key) = secrets::load("openai_compatible")
+                .await?
+                .filter(|key| !key.is_empty())
+            {
+                builder = builder.bearer_auth(key);
+            }
+            completion_text(&post_json("openai_compatible", builder).await?["choices"][0]["text"])
+        }
+        ProviderKind::Openai | ProviderKind::Anthropic => {
+            let provider = super::provider(app, window, kind).await?;
+            let request = ChatRequest {
+                model,
+                messages: vec![
+                    ChatMessage {
+                        role: Role::System,
+                        content: CHAT_INSTRUCTIONS.to_string(),
+                    },
+                    ChatMessage {
+                        role: Role::User,
+                        content: format!("Language: {}\n\n{}<CURSOR>{}", language, prefix, suffix),
+                    },
+                ],
+                temperature: Some(TEMPERATURE),
+                max_tokens: Some(MAX_COMPLETION_TOKENS),
+                stop: Vec::new(),
+            };
+            let (text, _) = super::complete(provider.as_ref(), &request).await?;
+            Ok(super::strip_fence(&text).to_string())
+        }
+    }
+}
+
+/// Completes the code at the cursor of `path`, where `prefix` and `suffix`
+/// are the text before and after it, for ghost text in the editor. Returns
+/// `None` when inline completions are off for `language`, when a newer
+/// request replaced this one during the debounce or while waiting, or
+/// when the model had nothing to add. Call on every keystroke; requests
+/// only go out once typing pauses for `ai.inlineCompletion.debounceMs`.
+#[tauri::command]
+pub async fn ai_inline_completion(
+    path: String,
+    prefix: String,
+    suffix: String,
+    language: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<InlineCompletion>, String> {
+    let setting = |key: &str| settings::window_setting(&app, &window, key);
+    let disabled = setting("ai.inlineCompletion.disabledLanguages")?;
+    let off_for_language = disabled
+        .as_array()
+        .is_some_and(|languages| languages.iter().any(|l| l.as_str() == Some(&language)));
+    if setting("ai.inlineCompletion.enabled")? != Value::Bool(true) || off_for_language {
+        return Ok(None);
+    }
+    let debounce = setting("ai.inlineCompletion.debounceMs")?
+        .as_u64()
+        .unwrap_or(0);
+
+    let scope = windows.scope(window.label());
+    let completions = &scope.inline_completions;
+    let mut cancelled = completions.begin();
+    if let Some(text) = completions.lookup(&path, &prefix, &suffix) {
+        return Ok(Some(InlineCompletion {
+            text,
+            cached: true,
+            latency_ms: 0,
+        }));
+    }
+
+    let kind = super::provider_kind(&app, None)?;
+    let model = match super::string_setting(&app, "ai.inlineCompletion.model")? {
+        model if model.is_empty() => super::string_setting(&app, "ai.model")?,
+        model => model,
+    };
+    if model.is_empty() && kind != ProviderKind::LlamaCpp {
+        return Err("No model selected; set ai.inlineCompletion.model or ai.model".to_string());
+    }
+
+    tokio::select! {
+        _ = tokio::time::sleep(Duration::from_millis(debounce)) => {}
+        _ = &mut cancelled => {
+            completions.record(&Outcome::Superseded, Duration::ZERO);
+            return Ok(None);
+        }
+    }
+    let started = Instant::now();
+    let (prefix_tail, suffix_head) = (
+        tail(&prefix, MAX_PREFIX_CHARS),
+        head(&suffix, MAX_SUFFIX_CHARS),
+    );
+    let request = tokio::time::timeout(
+        REQUEST_TIMEOUT,
+        fill_in_middle(
+            &app,
+            &window,
+            kind,
+            model.clone(),
+            prefix_tail,
+            suffix_head,
+            &language,
+        ),
+    );
+    let result = tokio::select! {
+        result = request => Some(result),
+        _ = cancelled => None,
+    };
+    let latency = started.elapsed();
+    // No backend reports usage for these, so tokens are always estimated.
+    let log = |outcome: RequestOutcome, reply: &str| {
+        audit::record(&app, || {
+            let count =
+                |text: &str| tokens::for_model(&model).map_or(0, |bpe| tokens::count(bpe, text));
+            AuditEntry {
+                kind: RequestKind::InlineCompletion,
+                provider: kind.name(),
+                model: model.clone(),
+                input_tokens: (count(prefix_tail) + count(suffix_head)) as u64,
+                output_tokens: count(reply) as u64,
+                estimated: true,
+                duration: latency,
+                files: vec![path.clone()],
+                outcome,
+            }
+        })
+    };
+    let text = match result {
+        None => {
+            completions.record(&Outcome::Superseded, latency);
+            log(RequestOutcome::Cancelled, "");
+            return Ok(None);
+        }
+        Some(Err(_)) => {
+            completions.record(&Outcome::Failed, latency);
+            log(RequestOutcome::Failed, "");
+            return Err("Inline completion timed out".to_string());
+        }
+        Some(Ok(Err(e))) => {
+            completions.record(&Outcome::Failed, latency);
+            log(RequestOutcome::Failed, "");
+            return Err(e);
+        }
+        Some(Ok(Ok(text))) => text,
+    };
+    log(RequestOutcome::Completed, &text);
+    let empty = text.trim().is_empty();
+    completions.record(&Outcome::Completed { empty }, latency);
+    if empty {
+        return Ok(None);
+    }
+    completions.store(&path, &prefix, &suffix, &text);
+    Ok(Some(InlineCompletion {
+        text,
+        cached: false,
+        latency_ms: latency.as_millis() as u64,
+    }))
+}
+
+/// Request counts and model latency of inline completions in this window.
+#[tauri::command]
+pub async fn get_inline_completion_stats(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<InlineCompletionStats, String> {
+    Ok(windows.scope(window.label()).inline_completions.stats())
+}
@@ -0,0 +1,350 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+use tauri::{AppHandle, State, Window};
+
+use super::{ChatOutcome, ChatRequest, CompletionProvider, ProviderError, TokenSink, Usage};
+use crate::window_state::WindowRegistry;
+
+/// Health checks and model listing should fail fast when nothing listens.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A model server running on this machine or the local network.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalBackend {
+    Ollama,
+    /// llama.cpp's `llama-server`, which serves the one model it loaded.
+    LlamaCpp,
+}
+
+impl LocalBackend {
+    const ALL: [LocalBackend; 2] = [LocalBackend::Ollama, LocalBackend::LlamaCpp];
+
+    fn setting(self) -> &'static str {
+        match self {
+            LocalBackend::Ollama => "ai.ollama.url",
+            LocalBackend::LlamaCpp => "ai.llamaCpp.url",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalModel {
+    pub name: String,
+    pub backend: LocalBackend,
+    /// Bytes on disk, where the backend reports it.
+    pub size: Option<u64>,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalHealth {
+    pub backend: LocalBackend,
+    pub url: String,
+    pub available: bool,
+    pub version: Option<String>,
+    /// Why it is unavailable; llama.cpp reports `loading model` while
+    /// starting up.
+    pub error: Option<String>,
+}
+
+/// Emitted as `ai-pull-progress` while Ollama downloads a model.
+#[derive(Debug, Clone, Serialize)]
+struct PullProgress {
+    pull_id: String,
+    model: String,
+    status: String,
+    total: Option<u64>,
+    completed: Option<u64>,
+}
+
+/// Emitted as `ai-pull-done` when a pull ends for any reason.
+#[derive(Debug, Clone, Serialize)]
+struct PullDone {
+    pull_id: String,
+    model: String,
+    cancelled: bool,
+    error: Option<String>,
+}
+
+/// The configured base URL of the backend, without a trailing slash.
+pub fn endpoint(app: &AppHandle, backend: LocalBackend) -> Result<String, String> {
+    Ok(super::string_setting(app, backend.setting())?
+        .trim_end_matches('/')
+        .to_string())
+}
+
+/// Feeds a newline-delimited JSON body through `on_line` until it ends or
+/// it returns `Ok(false)`. Ollama streams this way rather than with SSE.
+async fn for_each_line(
+    mut response: reqwest::Response,
+    mut on_line: impl FnMut(Value) -> Result<bool, String>,
+) -> Result<(), String> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let value: Value = serde_json::from_slice(&line)
+                .map_err(|e| format!("Invalid response from ollama: {}", e))?;
+            if let Some(error) = value.get("error").and_then(Value::as_str) {
+                return Err(error.to_string());
+            }
+            if !on_line(value)? {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ollama's native `/api/chat`.
+pub struct OllamaProvider {
+    pub base_url: String,
+}
+
+impl OllamaProvider {
+    fn body(request: &ChatRequest) -> Value {
+        let mut options = Map::new();
+        if let Some(temperature) = request.temperature {
+            options.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            options.insert("num_predict".into(), json!(max_tokens));
+        }
+        if !request.stop.is_empty() {
+            options.insert("stop".into(), json!(request.stop));
+        }
+        json!({
+            "model": request.model,
+            "messages": request.messages,
+            "stream": true,
+            "options": options,
+        })
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError> {
+        let builder = super::client()?
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&Self::body(request));
+        let response = super::send(self.name(), builder).await?;
+
+        let mut outcome = ChatOutcome::default();
+        for_each_line(response, |chunk| {
+            if let Some(text) = chunk["message"]["content"].as_str() {
+                if !text.is_empty() {
+                    on_token(text);
+                }
+            }
+            if chunk["done"].as_bool() != Some(true) {
+                return Ok(true);
+            }
+            outcome.finish_reason = chunk["done_reason"].as_str().map(str::to_string);
+            outcome.usage = Some(Usage {
+                input_tokens: chunk["prompt_eval_count"].as_u64().unwrap_or(0),
+                output_tokens: chunk["eval_count"].as_u64().unwrap_or(0),
+            });
+            Ok(false)
+        })
+        .await?;
+        Ok(outcome)
+    }
+}
+
+async fn get_json(url: String) -> Result<Value, String> {
+    super::client()?
+        .get(url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) async fn list_models(
+    base_url: &str,
+    backend: LocalBackend,
+) -> Result<Vec<LocalModel>, String> {
+    Ok(match backend {
+        LocalBackend::Ollama => {
+            let tags = get_json(format!("{}/api/tags", base_url)).await?;
+            tags["models"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|model| {
+                    let details = &model["details"];
+                    Some(LocalModel {
+                        name: model["name"].as_str()?.to_string(),
+                        backend,
+                        size: model["size"].as_u64(),
+                        family: details["family"].as_str().map(str::to_string),
+                        parameter_size: details["parameter_size"].as_str().map(str::to_string),
+                        quantization: details["quantization_level"].as_str().map(str::to_string),
+                    })
+                })
+                .collect()
+        }
+        LocalBackend::LlamaCpp => {
+            let models = get_json(format!("{}/v1/models", base_url)).await?;
+            models["data"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|model| {
+                    Some(LocalModel {
+                        name: model["id"].as_str()?.to_string(),
+                        backend,
+                        size: model["meta"]["size"].as_u64(),
+                        family: None,
+                        parameter_size: None,
+                        quantization: None,
+                    })
+                })
+                .collect()
+        }
+    })
+}
+
+async fn health(base_url: String, backend: LocalBackend) -> LocalHealth {
+    let probe = match backend {
+        LocalBackend::Ollama => get_json(format!("{}/api/version", base_url))
+            .await
+            .map(|body| body["version"].as_str().map(str::to_string)),
+        // 503 with `{"error": {"message": "Loading model"}}` until ready.
+        LocalBackend::LlamaCpp => get_json(format!("{}/health", base_url)).await.map(|_| None),
+    };
+    let (available, version, error) = match probe {
+        Ok(version) => (true, version, None),
+        Err(e) => (false, None, Some(e)),
+    };
+    LocalHealth {
+        backend,
+        url: base_url,
+        available,
+        version,
+        error,
+    }
+}
+
+fn backends(backend: Option<LocalBackend>) -> Vec<LocalBackend> {
+    backend
+        .map(|b| vec![b])
+        .unwrap_or_else(|| LocalBackend::ALL.to_vec())
+}
+
+/// Models available from the local backends. Without `backend`, every
+/// backend is asked and those not running are skipped.
+#[tauri::command]
+pub async fn ai_list_local_models(
+    backend: Option<LocalBackend>,
+    app: AppHandle,
+) -> Result<Vec<LocalModel>, String> {
+    let mut models = Vec::new();
+    for candidate in backends(backend) {
+        let base_url = endpoint(&app, candidate)?;
+        match list_models(&base_url, candidate).await {
+            Ok(found) => models.extend(found),
+            Err(e) if backend.is_some() => {
+                return Err(format!("Failed to list models at {}: {}", base_url, e))
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(models)
+}
+
+/// Whether each local backend is up and ready to serve.
+#[tauri::command]
+pub async fn ai_local_health(
+    backend: Option<LocalBackend>,
+    app: AppHandle,
+) -> Result<Vec<LocalHealth>, String> {
+    let mut found = Vec::new();
+    for candidate in backends(backend) {
+        found.push(health(endpoint(&app, candidate)?, candidate).await);
+    }
+    Ok(found)
+}
+
+/// Starts downloading `model` into Ollama and returns the pull id.
+/// Progress arrives as `ai-pull-progress` and the pull ends with
+/// `ai-pull-done`; `ai_cancel` with the pull id stops it.
+#[tauri::command]
+pub async fn ai_pull_model(
+    model: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let base_url = endpoint(&app, LocalBackend::Ollama)?;
+    let scope = windows.scope(window.label());
+    let pull_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = scope.ai_requests.register(&pull_id);
+
+    let id = pull_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let pull = async {
+            // Pulls take minutes; only connecting is time-limited.
+            let builder = super::client()?
+                .post(format!("{}/api/pull", base_url))
+                .json(&json!({ "model": model, "stream": true }));
+            let response = super::send("ollama", builder).await?;
+            for_each_line(response, |progress| {
+                let _ = window.emit(
+                    "ai-pull-progress",
+                    PullProgress {
+                        pull_id: id.clone(),
+                        model: model.clone(),
+                        status: progress["status"].as_str().unwrap_or_default().to_string(),
+                        total: progress["total"].as_u64(),
+                        completed: progress["completed"].as_u64(),
+                    },
+                );
+                Ok(true)
+            })
+            .await
+        };
+        let result = tokio::select! {
+            result = pull => Some(result),
+            _ = cancelled => None,
+        };
+        scope.ai_requests.finish(&id);
+        let _ = window.emit(
+            "ai-pull-done",
+            PullDone {
+                pull_id: id,
+                model,
+                cancelled: result.is_none(),
+                error: result.and_then(Result::err),
+            },
+        );
+    });
+
+    Ok(pull_id)
+}
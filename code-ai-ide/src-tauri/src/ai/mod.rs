@@ -0,0 +1,564 @@
+pub mod anthropic;
+pub mod audit;
+pub mod commit;
+pub mod context;
+pub mod edit;
+pub mod embeddings;
+pub mod explain;
+pub mod inline;
+pub mod local;
+pub mod models;
+pub mod openai;
+pub mod policy;
+pub mod sessions;
+pub mod sse;
+pub mod templates;
+pub mod tokens;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, Window};
+use tokio::sync::oneshot;
+
+use self::anthropic::{AnthropicProvider, ANTHROPIC_BASE_URL};
+use self::audit::{AuditEntry, RequestKind, RequestOutcome};
+use self::local::{LocalBackend, OllamaProvider};
+use self::openai::{OpenAiProvider, OPENAI_BASE_URL};
+use self::sessions::ChatSessions;
+use crate::secrets;
+use crate::semantic::{self, SemanticHit};
+use crate::settings;
+use crate::window_state::WindowRegistry;
+
+const USER_AGENT: &str = concat!("code-ai-ide/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Openai,
+    Anthropic,
+    /// Any server with the OpenAI Chat Completions API at `ai.baseUrl`.
+    OpenaiCompatible,
+    /// The local Ollama server at `ai.ollama.url`.
+    Ollama,
+    /// The local llama.cpp server at `ai.llamaCpp.url`.
+    LlamaCpp,
+}
+
+impl ProviderKind {
+    /// As in settings and `CompletionProvider::name`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProviderKind::Openai => "openai",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenaiCompatible => "openai_compatible",
+            ProviderKind::Ollama => "ollama",
+            ProviderKind::LlamaCpp => "llama_cpp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+/// Everything but the messages and model is optional; the provider,
+/// endpoint and key default to the `ai.*` settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatOptions {
+    pub provider: Option<ProviderKind>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Adds this many workspace snippets matching the last user message
+    /// from the semantic index, as a system message.
+    pub context_results: Option<usize>,
+    /// Chat session to record the last user message and the reply in.
+    pub session_id: Option<String>,
+    /// Files the last user message refers to, kept with it in the session.
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// What a provider is asked for.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatOutcome {
+    /// As the provider reports it, e.g. `stop`, `length`, `end_turn`.
+    pub finish_reason: Option<String>,
+    pub usage: Option<Usage>,
+}
+
+/// A failed provider request.
+#[derive(Debug, Clone)]
+pub struct ProviderError {
+    pub message: String,
+    /// Rate limited, overloaded or unreachable: waiting or another provider
+    /// may help where nothing would for a bad key or request.
+    pub retryable: bool,
+    /// The server's `Retry-After`, when it sent one.
+    pub retry_after: Option<Duration>,
+}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        ProviderError {
+            message,
+            retryable: false,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<ProviderError> for String {
+    fn from(error: ProviderError) -> Self {
+        error.message
+    }
+}
+
+/// Receives each piece of generated text as it arrives.
+pub type TokenSink<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+/// A chat model behind some API. Implementations stream the reply through
+/// `on_token` and return once the model is done.
+#[async_trait]
+pub trait CompletionProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError>;
+}
+
+/// Emitted as `ai-chat-token` for each piece of the reply.
+#[derive(Debug, Clone, Serialize)]
+struct ChatToken {
+    request_id: String,
+    text: String,
+}
+
+/// Emitted as `ai-chat-done` once a request ends for any reason.
+#[derive(Debug, Clone, Serialize)]
+struct ChatDone {
+    request_id: String,
+    /// The whole reply, or what arrived before cancellation or an error.
+    text: String,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+    cancelled: bool,
+    error: Option<String>,
+}
+
+/// In-flight chat requests of a window, so they can be cancelled.
+#[derive(Default)]
+pub struct AiRequests {
+    running: Mutex<HashMap<String, oneshot::Sender<()>>>,
+}
+
+impl AiRequests {
+    pub(crate) fn register(&self, id: &str) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.running.lock().unwrap().insert(id.to_string(), sender);
+        receiver
+    }
+
+    pub(crate) fn finish(&self, id: &str) {
+        self.running.lock().unwrap().remove(id);
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().remove(id) {
+            Some(sender) => sender.send(()).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for (_, sender) in self.running.lock().unwrap().drain() {
+            let _ = sender.send(());
+        }
+    }
+}
+
+pub(crate) fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// `error.message` from an API error body, or the body itself.
+pub(crate) fn error_message(error: &Value) -> String {
+    error
+        .get("message")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| error.to_string())
+}
+
+/// Sends the request, turning an error status into a message with the
+/// provider's explanation.
+pub(crate) async fn send(
+    provider: &str,
+    builder: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, ProviderError> {
+    let response = builder.send().await.map_err(|e| ProviderError {
+        message: format!("Failed to reach {}: {}", provider, e),
+        retryable: e.is_connect() || e.is_timeout(),
+        retry_after: None,
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    // Only the delay-seconds form; servers rarely send a date.
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    let detail = serde_json::from_str::<Value>(&body)
+        .ok()
+        .map(|body| error_message(body.get("error").unwrap_or(&body)))
+        .unwrap_or(body);
+    Err(ProviderError {
+        message: format!("{} returned {}: {}", provider, status, detail),
+        retryable: status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+        retry_after,
+    })
+}
+
+/// Runs a request to the end and returns the whole reply and the usage
+/// the provider reported, for callers that do not stream.
+pub(crate) async fn complete(
+    provider: &dyn CompletionProvider,
+    request: &ChatRequest,
+) -> Result<(String, Option<Usage>), String> {
+    let text = Mutex::new(String::new());
+    let outcome = provider
+        .stream_chat(request, &|token: &str| text.lock().unwrap().push_str(token))
+        .await?;
+    Ok((text.into_inner().unwrap(), outcome.usage))
+}
+
+/// Drops a code fence a chat model wrapped the answer in despite being
+/// asked not to.
+pub(crate) fn strip_fence(text: &str) -> &str {
+    let Some(rest) = text.trim_start().strip_prefix("```") else {
+        return text;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end()
+        .strip_suffix("```")
+        .unwrap_or(body)
+        .trim_end_matches('\n')
+}
+
+pub(crate) fn string_setting(app: &AppHandle, key: &str) -> Result<String, String> {
+    Ok(settings::user_setting(app, key)?
+        .as_str()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+pub(crate) fn provider_kind(
+    app: &AppHandle,
+    kind: Option<ProviderKind>,
+) -> Result<ProviderKind, String> {
+    match kind {
+        Some(kind) => Ok(kind),
+        None => serde_json::from_value(settings::user_setting(app, "ai.provider")?)
+            .map_err(|e| format!("Invalid ai.provider: {}", e)),
+    }
+}
+
+/// Builds the provider behind the outbound request policy, failing over
+/// to `ai.fallback.provider` when one is set. Retries and failovers are
+/// reported to `window`.
+pub(crate) async fn provider(
+    app: &AppHandle,
+    window: &Window,
+    kind: ProviderKind,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    let primary = policy::govern(app, window, connect(app, kind).await?)?;
+    let Some(fallback) = policy::fallback_kind(app)?.filter(|fallback| *fallback != kind) else {
+        return Ok(primary);
+    };
+    Ok(Box::new(policy::WithFailover {
+        primary,
+        secondary: policy::govern(app, window, connect(app, fallback).await?)?,
+        model: string_setting(app, "ai.fallback.model")?,
+        window: window.clone(),
+    }))
+}
+
+/// Builds the provider from the user settings and the keys in the
+/// keychain. Endpoints are only read from the user layer, so a workspace
+/// cannot redirect requests that carry the user's key.
+async fn connect(
+    app: &AppHandle,
+    kind: ProviderKind,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    let base_url = string_setting(app, "ai.baseUrl")?;
+    let key = |provider: &'static str| async move {
+        secrets::load(provider)
+            .await
+            .map(|key| key.filter(|key| !key.is_empty()))
+    };
+    Ok(match kind {
+        ProviderKind::Openai => Box::new(OpenAiProvider {
+            name: "openai",
+            base_url: OPENAI_BASE_URL.to_string(),
+            api_key: Some(key("openai").await?.ok_or("No OpenAI API key is set")?),
+            include_usage: true,
+        }),
+        ProviderKind::Anthropic => Box::new(AnthropicProvider {
+            base_url: ANTHROPIC_BASE_URL.to_string(),
+            api_key: key("anthropic")
+                .await?
+                .ok_or("No Anthropic API key is set")?,
+        }),
+        ProviderKind::OpenaiCompatible => {
+            if base_url.is_empty() {
+                return Err("Set ai.baseUrl to use an OpenAI-compatible server".to_string());
+            }
+            Box::new(OpenAiProvider {
+                name: "openai_compatible",
+                base_url,
+                api_key: key("openai_compatible").await?,
+                include_usage: false,
+            })
+        }
+        ProviderKind::Ollama => Box::new(OllamaProvider {
+            base_url: local::endpoint(app, LocalBackend::Ollama)?,
+        }),
+        ProviderKind::LlamaCpp => Box::new(OpenAiProvider {
+            name: "llama_cpp",
+            base_url: format!("{}/v1", local::endpoint(app, LocalBackend::LlamaCpp)?),
+            api_key: None,
+            include_usage: false,
+        }),
+    })
+}
+
+fn context_message(hits: &[SemanticHit]) -> ChatMessage {
+    let mut content = String::from("Possibly relevant code from the workspace:\n");
+    for hit in hits {
+        content.push_str(&format!(
+            "\n{}:{}-{}\n```\n{}\n```\n",
+            hit.relative_path,
+            hit.start_line + 1,
+            hit.end_line + 1,
+            hit.text
+        ));
+    }
+    ChatMessage {
+        role: Role::System,
+        content,
+    }
+}
+
+/// Starts a chat request in the background and returns its id, for
+/// commands that build the messages themselves. Events are as for
+/// `ai_chat`.
+pub(crate) async fn start_chat(
+    app: AppHandle,
+    window: Window,
+    mut messages: Vec<ChatMessage>,
+    model: Option<String>,
+    options: ChatOptions,
+) -> Result<String, String> {
+    if messages.is_empty() {
+        return Err("No messages to send".to_string());
+    }
+    let kind = provider_kind(&app, options.provider)?;
+    let provider = provider(&app, &window, kind).await?;
+    let model = match model.filter(|model| !model.trim().is_empty()) {
+        Some(model) => model,
+        None => string_setting(&app, "ai.model")?,
+    };
+    // llama-server answers with whatever model it loaded.
+    if model.is_empty() && kind != ProviderKind::LlamaCpp {
+        return Err("No model selected; set ai.model".to_string());
+    }
+
+    let scope = window.state::<WindowRegistry>().scope(window.label());
+    let mut has_context = false;
+    let mut context_files: Vec<String> = Vec::new();
+    if let Some(k) = options.context_results.filter(|k| *k > 0) {
+        let query = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == Role::User)
+            .map(|message| message.content.clone());
+        if let Some(query) = query {
+            let hits = semantic::search(&scope, &query, k).await?;
+            if !hits.is_empty() {
+                messages.insert(0, context_message(&hits));
+                has_context = true;
+                context_files = hits.iter().map(|hit| hit.relative_path.clone()).collect();
+            }
+        }
+    }
+    let input_tokens =
+        tokens::check_budget(&app, &window, &model, &messages, has_context, &options)?;
+    let (session_id, files) = (options.session_id, options.files);
+    let request = ChatRequest {
+        model,
+        messages,
+        temperature: options.temperature,
+        max_tokens: options.max_tokens,
+        stop: options.stop,
+    };
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = scope.ai_requests.register(&request_id);
+
+    let id = request_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        let text = Arc::new(Mutex::new(String::new()));
+        let on_token = {
+            let (window, id, text) = (window.clone(), id.clone(), text.clone());
+            move |token: &str| {
+                text.lock().unwrap().push_str(token);
+                let _ = window.emit(
+                    "ai-chat-token",
+                    ChatToken {
+                        request_id: id.clone(),
+                        text: token.to_string(),
+                    },
+                );
+            }
+        };
+        // Dropping the provider future closes the connection, which is what
+        // stops generation on the server side.
+        let result = tokio::select! {
+            result = provider.stream_chat(&request, &on_token) => Some(result),
+            _ = cancelled => None,
+        };
+        scope.ai_requests.finish(&id);
+
+        let text = text.lock().unwrap().clone();
+        // Providers that report no usage are charged the estimate.
+        let reported = match &result {
+            Some(Ok(outcome)) => outcome.usage.clone(),
+            _ => None,
+        };
+        let (usage, estimated) =
+            tokens::usage_or_estimate(reported, &request.model, input_tokens, &text);
+        if let Some(session_id) = &session_id {
+            let stored = sessions::exchange(&request.messages, &files, &text, &request.model);
+            let sessions = app.state::<ChatSessions>();
+            let _ = sessions.append(&app, session_id, &stored);
+            let _ =
+                sessions.record_usage(&app, session_id, usage.input_tokens, usage.output_tokens);
+        }
+        audit::record(&app, || AuditEntry {
+            kind: RequestKind::Chat,
+            provider: provider.name(),
+            model: request.model.clone(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            estimated,
+            duration: started.elapsed(),
+            files: files.iter().chain(&context_files).cloned().collect(),
+            outcome: match &result {
+                Some(Ok(_)) => RequestOutcome::Completed,
+                Some(Err(_)) => RequestOutcome::Failed,
+                None => RequestOutcome::Cancelled,
+            },
+        });
+        let done = match result {
+            Some(Ok(outcome)) => ChatDone {
+                request_id: id,
+                text,
+                finish_reason: outcome.finish_reason,
+                usage: outcome.usage,
+                cancelled: false,
+                error: None,
+            },
+            Some(Err(e)) => ChatDone {
+                request_id: id,
+                text,
+                finish_reason: None,
+                usage: None,
+                cancelled: false,
+                error: Some(e.message),
+            },
+            None => ChatDone {
+                request_id: id,
+                text,
+                finish_reason: None,
+                usage: None,
+                cancelled: true,
+                error: None,
+            },
+        };
+        let _ = window.emit("ai-chat-done", done);
+    });
+
+    Ok(request_id)
+}
+
+/// Starts a chat request in the background and returns its id. The reply
+/// streams in as `ai-chat-token` events and the request ends with
+/// `ai-chat-done`. `model` defaults to the `ai.model` setting.
+#[tauri::command]
+pub async fn ai_chat(
+    messages: Vec<ChatMessage>,
+    model: Option<String>,
+    options: Option<ChatOptions>,
+    app: AppHandle,
+    window: Window,
+) -> Result<String, String> {
+    start_chat(app, window, messages, model, options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn ai_cancel(
+    request_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows
+        .scope(window.label())
+        .ai_requests
+        .cancel(&request_id))
+}
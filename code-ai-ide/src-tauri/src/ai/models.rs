@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::anthropic::{ANTHROPIC_BASE_URL, API_VERSION};
+use super::local::{self, LocalBackend};
+use super::openai::OPENAI_BASE_URL;
+use super::ProviderKind;
+use crate::secrets;
+
+const BUILTIN_CATALOG: &str = include_str!("../../models.json");
+
+/// Listing should not keep the model picker waiting for long.
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OpenAI lists every model on the account; these cannot chat.
+const NOT_CHAT: &[&str] = &[
+    "embedding",
+    "tts",
+    "whisper",
+    "transcribe",
+    "dall-e",
+    "image",
+    "moderation",
+    "davinci",
+    "babbage",
+    "realtime",
+    "audio",
+];
+
+/// What the bundled catalog knows about a model family. `id` also matches
+/// ids that continue with `-`, `:` or `@`, so dated snapshots and local
+/// tags share their family's entry; the longest match wins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub display_name: Option<String>,
+    pub context_window: Option<usize>,
+    pub max_output_tokens: Option<u32>,
+    #[serde(default = "streams")]
+    pub streaming: bool,
+    /// US dollars per million tokens.
+    pub input_cost: Option<f64>,
+    pub output_cost: Option<f64>,
+}
+
+fn streams() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct CatalogFile {
+    models: Vec<CatalogEntry>,
+}
+
+fn catalog() -> &'static [CatalogEntry] {
+    static CATALOG: OnceLock<Vec<CatalogEntry>> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        serde_json::from_str::<CatalogFile>(BUILTIN_CATALOG)
+            .expect("bundled models.json is valid")
+            .models
+    })
+}
+
+/// The catalog entry for `model`, ignoring a `vendor/` prefix such as
+/// OpenRouter puts on ids.
+pub fn lookup(model: &str) -> Option<&'static CatalogEntry> {
+    let model = model
+        .rsplit('/')
+        .next()
+        .unwrap_or(model)
+        .to_ascii_lowercase();
+    catalog()
+        .iter()
+        .filter(|entry| match model.strip_prefix(entry.id.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with(['-', ':', '@']),
+            None => false,
+        })
+        .max_by_key(|entry| entry.id.len())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub provider: ProviderKind,
+    pub display_name: String,
+    pub context_window: Option<usize>,
+    pub max_output_tokens: Option<u32>,
+    pub streaming: bool,
+    /// US dollars per million tokens; 0 for local models.
+    pub input_cost: Option<f64>,
+    pub output_cost: Option<f64>,
+    /// Whether the catalog knows the model; otherwise only what the
+    /// provider reported is filled in.
+    pub cataloged: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderListError {
+    pub provider: ProviderKind,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelList {
+    pub models: Vec<ModelInfo>,
+    /// Configured providers that could not be listed.
+    pub errors: Vec<ProviderListError>,
+}
+
+/// A model as the provider lists it.
+struct Listed {
+    id: String,
+    display_name: Option<String>,
+}
+
+fn annotate(provider: ProviderKind, listed: Listed) -> ModelInfo {
+    let entry = lookup(&listed.id);
+    let local = matches!(provider, ProviderKind::Ollama | ProviderKind::LlamaCpp);
+    let cost = |cost: Option<f64>| if local { Some(0.0) } else { cost };
+    ModelInfo {
+        display_name: listed
+            .display_name
+            .or_else(|| entry.and_then(|entry| entry.display_name.clone()))
+            .unwrap_or_else(|| listed.id.clone()),
+        context_window: entry.and_then(|entry| entry.context_window),
+        max_output_tokens: entry.and_then(|entry| entry.max_output_tokens),
+        streaming: entry.map_or(true, |entry| entry.streaming),
+        input_cost: cost(entry.and_then(|entry| entry.input_cost)),
+        output_cost: cost(entry.and_then(|entry| entry.output_cost)),
+        cataloged: entry.is_some(),
+        id: listed.id,
+        provider,
+    }
+}
+
+/// `data` of an OpenAI-style model list.
+async fn get_list(provider: &str, builder: reqwest::RequestBuilder) -> Result<Vec<Value>, String> {
+    let body: Value = super::send(provider, builder.timeout(LIST_TIMEOUT))
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", provider, e))?;
+    Ok(body["data"].as_array().cloned().unwrap_or_default())
+}
+
+async fn key(provider: &str) -> Result<Option<String>, String> {
+    Ok(secrets::load(provider).await?.filter(|key| !key.is_empty()))
+}
+
+async fn openai_models(
+    base_url: &str,
+    key: Option<&str>,
+    name: &str,
+) -> Result<Vec<Listed>, String> {
+    let mut builder = super::client()?.get(format!("{}/models", base_url.trim_end_matches('/')));
+    if let Some(key) = key {
+        builder = builder.bearer_auth(key);
+    }
+    Ok(get_list(name, builder)
+        .await?
+        .iter()
+        .filter_map(|model| {
+            Some(Listed {
+                id: model["id"].as_str()?.to_string(),
+                display_name: None,
+            })
+        })
+        .collect())
+}
+
+async fn anthropic_models(key: &str) -> Result<Vec<Listed>, String> {
+    let builder = super::client()?
+        .get(format!("{}/v1/models?limit=1000", ANTHROPIC_BASE_URL))
+        .header("x-api-key", key)
+        .header("anthropic-version", API_VERSION);
+    Ok(get_list("anthropic", builder)
+        .await?
+        .iter()
+        .filter_map(|model| {
+            Some(Listed {
+                id: model["id"].as_str()?.to_string(),
+                display_name: model["display_name"].as_str().map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+async fn local_models(app: &AppHandle, backend: LocalBackend) -> Result<Vec<Listed>, String> {
+    let base_url = local::endpoint(app, backend)?;
+    Ok(local::list_models(&base_url, backend)
+        .await?
+        .into_iter()
+        .map(|model| Listed {
+            id: model.name,
+            display_name: None,
+        })
+        .collect())
+}
+
+/// Models of every configured provider, annotated from the bundled
+/// catalog with context window, output limit, streaming support and cost,
+/// for the model picker. OpenAI and Anthropic are asked when their key is
+/// set and an OpenAI-compatible server when `ai.baseUrl` is; the local
+/// backends are skipped when not running. Providers that fail are listed
+/// in `errors` rather than failing the whole command.
+#[tauri::command]
+pub async fn ai_list_models(app: AppHandle) -> Result<ModelList, String> {
+    let openai_key = key("openai").await?;
+    let anthropic_key = key("anthropic").await?;
+    let compatible_key = key("openai_compatible").await?;
+    let base_url = super::string_setting(&app, "ai.baseUrl")?;
+
+    let (openai, anthropic, compatible, ollama, llama_cpp) = tokio::join!(
+        async {
+            match &openai_key {
+                Some(key) => Some(openai_models(OPENAI_BASE_URL, Some(key), "openai").await),
+                None => None,
+            }
+        },
+        async {
+            match &anthropic_key {
+                Some(key) => Some(anthropic_models(key).await),
+                None => None,
+            }
+        },
+        async {
+            if base_url.is_empty() {
+                return None;
+            }
+            Some(openai_models(&base_url, compatible_key.as_deref(), "openai_compatible").await)
+        },
+        local_models(&app, LocalBackend::Ollama),
+        local_models(&app, LocalBackend::LlamaCpp),
+    );
+
+    let mut list = ModelList {
+        models: Vec::new(),
+        errors: Vec::new(),
+    };
+    let results = [
+        (ProviderKind::Openai, openai),
+        (ProviderKind::Anthropic, anthropic),
+        (ProviderKind::OpenaiCompatible, compatible),
+        (ProviderKind::Ollama, ollama.ok().map(Ok)),
+        (ProviderKind::LlamaCpp, llama_cpp.ok().map(Ok)),
+    ];
+    for (provider, result) in results {
+        match result {
+            Some(Ok(listed)) => {
+                let mut models: Vec<ModelInfo> = listed
+                    .into_iter()
+                    .filter(|model| {
+                        provider != ProviderKind::Openai
+                            || !NOT_CHAT.iter().any(|part| model.id.contains(part))
+                    })
+                    .map(|model| annotate(provider, model))
+                    .collect();
+                models.sort_by(|a, b| a.id.cmp(&b.id));
+                list.models.extend(models);
+            }
+            Some(Err(error)) => list.errors.push(ProviderListError { provider, error }),
+            None => {}
+        }
+    }
+    Ok(list)
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use super::sse;
+use super::{ChatOutcome, ChatRequest, CompletionProvider, ProviderError, TokenSink, Usage};
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// The Chat Completions API, spoken by OpenAI and by the many servers that
+/// copy it (vLLM, LM Studio, OpenRouter, ...).
+pub struct OpenAiProvider {
+    pub name: &'static str,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    /// `stream_options.include_usage`; some compatible servers reject the
+    /// field, so it is only sent to OpenAI itself.
+    pub include_usage: bool,
+}
+
+impl OpenAiProvider {
+    fn body(&self, request: &ChatRequest) -> Value {
+        let mut body = Map::new();
+        body.insert("model".into(), json!(request.model));
+        body.insert("messages".into(), json!(request.messages));
+        body.insert("stream".into(), json!(true));
+        if let Some(temperature) = request.temperature {
+            body.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            body.insert("max_tokens".into(), json!(max_tokens));
+        }
+        if !request.stop.is_empty() {
+            body.insert("stop".into(), json!(request.stop));
+        }
+        if self.include_usage {
+            body.insert("stream_options".into(), json!({ "include_usage": true }));
+        }
+        Value::Object(body)
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError> {
+        let mut builder = super::client()?
+            .post(format!(
+                "{}/chat/completions",
+                self.base_url.trim_end_matches('/')
+            ))
+            .json(&self.body(request));
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        let response = super::send(self.name, builder).await?;
+
+        let mut outcome = ChatOutcome::default();
+        sse::for_each_event(response, |event| {
+            if event.data == "[DONE]" {
+                return Ok(false);
+            }
+            let chunk: Value = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Invalid response from {}: {}", self.name, e))?;
+            if let Some(error) = chunk.get("error") {
+                return Err(super::error_message(error));
+            }
+            if let Some(choice) = chunk["choices"].get(0) {
+                if let Some(text) = choice["delta"]["content"].as_str() {
+                    if !text.is_empty() {
+                        on_token(text);
+                    }
+                }
+                if let Some(reason) = choice["finish_reason"].as_str() {
+                    outcome.finish_reason = Some(reason.to_string());
+                }
+            }
+            if let Some(usage) = chunk.get("usage").filter(|usage| usage.is_object()) {
+                outcome.usage = Some(Usage {
+                    input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+                    output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+                });
+            }
+            Ok(true)
+        })
+        .await?;
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Window};
+use tokio::sync::Semaphore;
+
+use super::{ChatOutcome, ChatRequest, CompletionProvider, ProviderError, ProviderKind, TokenSink};
+use crate::settings;
+
+/// Longest wait between attempts, whatever the backoff or `Retry-After`
+/// says.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Emitted as `ai-request-retrying` before waiting to send a failed
+/// request again.
+#[derive(Debug, Clone, Serialize)]
+struct Retrying {
+    provider: &'static str,
+    /// 1 for the first retry.
+    attempt: u32,
+    max_retries: u32,
+    delay_ms: u64,
+    error: String,
+}
+
+/// Emitted as `ai-provider-failover` when a request moves to the fallback
+/// provider.
+#[derive(Debug, Clone, Serialize)]
+struct ProviderFailover {
+    from: &'static str,
+    to: &'static str,
+    model: String,
+    error: String,
+}
+
+/// Semaphores capping the requests in flight to each provider.
+#[derive(Default)]
+pub struct ProviderLimits {
+    semaphores: Mutex<HashMap<&'static str, (usize, Arc<Semaphore>)>>,
+}
+
+impl ProviderLimits {
+    /// A changed cap applies to requests started afterwards; those running
+    /// finish under the old one.
+    fn semaphore(&self, provider: &'static str, cap: usize) -> Arc<Semaphore> {
+        let mut semaphores = self.semaphores.lock().unwrap();
+        match semaphores.get(provider) {
+            Some((current, semaphore)) if *current == cap => semaphore.clone(),
+            _ => {
+                let semaphore = Arc::new(Semaphore::new(cap));
+                semaphores.insert(provider, (cap, semaphore.clone()));
+                semaphore
+            }
+        }
+    }
+}
+
+fn integer_setting(app: &AppHandle, key: &str) -> Result<u64, String> {
+    Ok(settings::user_setting(app, key)?.as_u64().unwrap_or(0))
+}
+
+/// `ai.fallback.provider`, `None` when failover is off.
+pub(crate) fn fallback_kind(app: &AppHandle) -> Result<Option<ProviderKind>, String> {
+    let value = settings::user_setting(app, "ai.fallback.provider")?;
+    match value.as_str() {
+        None | Some("") | Some("none") => Ok(None),
+        Some(_) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| format!("Invalid ai.fallback.provider: {}", e)),
+    }
+}
+
+/// Wraps `inner` in the outbound request policy, reporting retries to
+/// `window`.
+pub(crate) fn govern(
+    app: &AppHandle,
+    window: &Window,
+    inner: Box<dyn CompletionProvider>,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    let cap = integer_setting(app, "ai.maxConcurrentRequests")?.max(1) as usize;
+    Ok(Box::new(Governed {
+        semaphore: app.state::<ProviderLimits>().semaphore(inner.name(), cap),
+        max_retries: integer_setting(app, "ai.retry.maxRetries")? as u32,
+        initial_delay: Duration::from_millis(integer_setting(app, "ai.retry.initialDelayMs")?),
+        window: window.clone(),
+        inner,
+    }))
+}
+
+/// `initial` doubled for each retry after the first, or `Retry-After` when
+/// the server asks for longer.
+fn backoff(initial: Duration, retry: u32, retry_after: Option<Duration>) -> Duration {
+    let exponential = initial.saturating_mul(1 << (retry - 1).min(16));
+    retry_after
+        .unwrap_or_default()
+        .max(exponential)
+        .min(MAX_DELAY)
+}
+
+/// A provider with at most `ai.maxConcurrentRequests` requests in flight,
+/// the rest waiting their turn, and up to `ai.retry.maxRetries` retries
+/// with exponential backoff when it is rate limited, overloaded or
+/// unreachable. A request is only retried before any of the reply has
+/// streamed, so the client never sees text twice.
+struct Governed {
+    inner: Box<dyn CompletionProvider>,
+    window: Window,
+    semaphore: Arc<Semaphore>,
+    max_retries: u32,
+    initial_delay: Duration,
+}
+
+#[async_trait]
+impl CompletionProvider for Governed {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError> {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .map_err(|e| format!("Failed to queue request: {}", e))?;
+        let streamed = AtomicBool::new(false);
+        let tracked = |token: &str| {
+            streamed.store(true, Ordering::Relaxed);
+            on_token(token);
+        };
+        let mut retry = 0;
+        loop {
+            match self.inner.stream_chat(request, &tracked).await {
+                Err(e)
+                    if e.retryable
+                        && retry < self.max_retries
+                        && !streamed.load(Ordering::Relaxed) =>
+                {
+                    retry += 1;
+                    let delay = backoff(self.initial_delay, retry, e.retry_after);
+                    let _ = self.window.emit(
+                        "ai-request-retrying",
+                        Retrying {
+                            provider: self.name(),
+                            attempt: retry,
+                            max_retries: self.max_retries,
+                            delay_ms: delay.as_millis() as u64,
+                            error: e.message,
+                        },
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Sends to `primary`, and to `secondary` with `model` when the primary
+/// fails with a retryable error before any of the reply has streamed,
+/// its own retries spent.
+pub(crate) struct WithFailover {
+    pub primary: Box<dyn CompletionProvider>,
+    pub secondary: Box<dyn CompletionProvider>,
+    /// `ai.fallback.model`.
+    pub model: String,
+    pub window: Window,
+}
+
+#[async_trait]
+impl CompletionProvider for WithFailover {
+    fn name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    async fn stream_chat(
+        &self,
+        request: &ChatRequest,
+        on_token: TokenSink<'_>,
+    ) -> Result<ChatOutcome, ProviderError> {
+        let streamed = AtomicBool::new(false);
+        let tracked = |token: &str| {
+            streamed.store(true, Ordering::Relaxed);
+            on_token(token);
+        };
+        match self.primary.stream_chat(request, &tracked).await {
+            Err(e) if e.retryable && !streamed.load(Ordering::Relaxed) => {
+                let _ = self.window.emit(
+                    "ai-provider-failover",
+                    ProviderFailover {
+                        from: self.primary.name(),
+                        to: self.secondary.name(),
+                        model: self.model.clone(),
+                        error: e.message,
+                    },
+                );
+                let request = ChatRequest {
+                    model: self.model.clone(),
+                    ..request.clone()
+                };
+                self.secondary.stream_chat(&request, on_token).await
+            }
+            result => result,
+        }
+    }
+}
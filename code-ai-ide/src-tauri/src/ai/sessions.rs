@@ -0,0 +1,564 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, State, Window};
+
+use super::{ChatMessage, Role};
+use crate::app_data;
+use crate::metadata::to_millis;
+use crate::window_state::WindowRegistry;
+
+const SESSIONS_DB: &str = "chat_sessions.sqlite";
+/// Untitled sessions are named after the start of their first message.
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSessionSummary {
+    pub id: String,
+    pub title: String,
+    /// Model of the latest reply.
+    pub model: Option<String>,
+    /// Workspace root of the window the session was started in.
+    pub workspace: Option<String>,
+    pub created: u64,
+    pub updated: u64,
+    pub message_count: u64,
+    /// Input and output tokens of every request made in the session.
+    pub tokens_used: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMessage {
+    pub role: Role,
+    pub content: String,
+    /// Model that wrote an assistant message.
+    pub model: Option<String>,
+    /// Files the message referred to, for user messages.
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub timestamp: u64,
+}
+
+/// An AI edit applied from the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEdit {
+    pub path: String,
+    pub hunks: Vec<usize>,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSession {
+    #[serde(flatten)]
+    pub summary: ChatSessionSummary,
+    pub messages: Vec<SessionMessage>,
+    pub edits: Vec<SessionEdit>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// Chat sessions of all windows, in one SQLite database under app data that
+/// is opened on first use.
+#[derive(Default)]
+pub struct ChatSessions {
+    conn: Mutex<Option<Connection>>,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Failed to access chat sessions: {}", e)
+}
+
+fn now() -> u64 {
+    to_millis(SystemTime::now()).unwrap_or(0)
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+fn parse_role(name: &str) -> Role {
+    match name {
+        "system" => Role::System,
+        "assistant" => Role::Assistant,
+        _ => Role::User,
+    }
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let conn = Connection::open(app_data::app_data_path(app, SESSIONS_DB)?).map_err(db_error)?;
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA foreign_keys = ON;
+         CREATE TABLE IF NOT EXISTS sessions (
+             id TEXT PRIMARY KEY,
+             title TEXT NOT NULL,
+             model TEXT,
+             workspace TEXT,
+             created INTEGER NOT NULL,
+             updated INTEGER NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS messages (
+             session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+             seq INTEGER NOT NULL,
+             role TEXT NOT NULL,
+             content TEXT NOT NULL,
+             model TEXT,
+             files TEXT NOT NULL,
+             timestamp INTEGER NOT NULL,
+             PRIMARY KEY (session_id, seq)
+         );
+         CREATE TABLE IF NOT EXISTS edits (
+             id INTEGER PRIMARY KEY,
+             session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+             path TEXT NOT NULL,
+             hunks TEXT NOT NULL,
+             timestamp INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS edits_session ON edits (session_id);
+         CREATE TABLE IF NOT EXISTS usage (
+             session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+             input_tokens INTEGER NOT NULL,
+             output_tokens INTEGER NOT NULL,
+             timestamp INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS usage_session ON usage (session_id);",
+    )
+    .map_err(db_error)?;
+    Ok(conn)
+}
+
+impl ChatSessions {
+    fn with_conn<T>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut Connection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(open(app)?);
+        }
+        f(conn.as_mut().unwrap())
+    }
+
+    fn create(
+        &self,
+        app: &AppHandle,
+        title: &str,
+        workspace: Option<String>,
+    ) -> Result<ChatSessionSummary, String> {
+        let summary = ChatSessionSummary {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            model: None,
+            workspace,
+            created: now(),
+            updated: now(),
+            message_count: 0,
+            tokens_used: 0,
+        };
+        self.with_conn(app, |conn| {
+            conn.execute(
+                "INSERT INTO sessions (id, title, model, workspace, created, updated)
+                 VALUES (?1, ?2, NULL, ?3, ?4, ?4)",
+                params![
+                    summary.id,
+                    summary.title,
+                    summary.workspace,
+                    summary.created
+                ],
+            )
+            .map_err(db_error)
+        })?;
+        Ok(summary)
+    }
+
+    /// Appends messages to a session, naming it after the first user
+    /// message if it has no title yet.
+    pub fn append(
+        &self,
+        app: &AppHandle,
+        session_id: &str,
+        messages: &[SessionMessage],
+    ) -> Result<(), String> {
+        self.with_conn(app, |conn| {
+            let tx = conn.transaction().map_err(db_error)?;
+            let title: Option<String> = tx
+                .query_row(
+                    "SELECT title FROM sessions WHERE id = ?1",
+                    [session_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(db_error)?;
+            let Some(title) = title else {
+                return Err(format!("No such chat session: {}", session_id));
+            };
+            let mut seq: i64 = tx
+                .query_row(
+                    "SELECT COALESCE(MAX(seq), -1) FROM messages WHERE session_id = ?1",
+                    [session_id],
+                    |row| row.get(0),
+                )
+                .map_err(db_error)?;
+            for message in messages {
+                seq += 1;
+                let files = serde_json::to_string(&message.files).map_err(|e| e.to_string())?;
+                tx.execute(
+                    "INSERT INTO messages (session_id, seq, role, content, model, files, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        session_id,
+                        seq,
+                        role_name(message.role),
+                        message.content,
+                        message.model,
+                        files,
+                        message.timestamp
+                    ],
+                )
+                .map_err(db_error)?;
+                if let Some(model) = &message.model {
+                    tx.execute(
+                        "UPDATE sessions SET model = ?2 WHERE id = ?1",
+                        params![session_id, model],
+                    )
+                    .map_err(db_error)?;
+                }
+            }
+            let first_user = messages.iter().find(|m| m.role == Role::User);
+            if let Some(message) = first_user.filter(|_| title.is_empty()) {
+                tx.execute(
+                    "UPDATE sessions SET title = ?2 WHERE id = ?1",
+                    params![session_id, default_title(&message.content)],
+                )
+                .map_err(db_error)?;
+            }
+            tx.execute(
+                "UPDATE sessions SET updated = ?2 WHERE id = ?1",
+                params![session_id, now()],
+            )
+            .map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        })
+    }
+
+    pub fn record_edit(
+        &self,
+        app: &AppHandle,
+        session_id: &str,
+        path: &str,
+        hunks: &[usize],
+    ) -> Result<(), String> {
+        let hunks = serde_json::to_string(hunks).map_err(|e| e.to_string())?;
+        self.with_conn(app, |conn| {
+            let timestamp = now();
+            let inserted = conn
+                .execute(
+                    "INSERT INTO edits (session_id, path, hunks, timestamp)
+                     SELECT id, ?2, ?3, ?4 FROM sessions WHERE id = ?1",
+                    params![session_id, path, hunks, timestamp],
+                )
+                .map_err(db_error)?;
+            if inserted == 0 {
+                return Err(format!("No such chat session: {}", session_id));
+            }
+            conn.execute(
+                "UPDATE sessions SET updated = ?2 WHERE id = ?1",
+                params![session_id, timestamp],
+            )
+            .map_err(db_error)?;
+            Ok(())
+        })
+    }
+
+    /// Records the tokens of one request made in the session.
+    pub fn record_usage(
+        &self,
+        app: &AppHandle,
+        session_id: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> Result<(), String> {
+        self.with_conn(app, |conn| {
+            conn.execute(
+                "INSERT INTO usage (session_id, input_tokens, output_tokens, timestamp)
+                 SELECT id, ?2, ?3, ?4 FROM sessions WHERE id = ?1",
+                params![session_id, input_tokens, output_tokens, now()],
+            )
+            .map(|_| ())
+            .map_err(db_error)
+        })
+    }
+
+    pub fn tokens_used(&self, app: &AppHandle, session_id: &str) -> Result<u64, String> {
+        self.with_conn(app, |conn| {
+            conn.query_row(
+                "SELECT COALESCE(SUM(input_tokens + output_tokens), 0) FROM usage
+                 WHERE session_id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .map_err(db_error)
+        })
+    }
+
+    fn summaries(
+        &self,
+        app: &AppHandle,
+        id: Option<&str>,
+    ) -> Result<Vec<ChatSessionSummary>, String> {
+        self.with_conn(app, |conn| {
+            let mut statement = conn
+                .prepare(
+                    "SELECT s.id, s.title, s.model, s.workspace, s.created, s.updated,
+                            (SELECT COUNT(*) FROM messages m WHERE m.session_id = s.id),
+                            (SELECT COALESCE(SUM(u.input_tokens + u.output_tokens), 0)
+                             FROM usage u WHERE u.session_id = s.id)
+                     FROM sessions s
+                     WHERE ?1 IS NULL OR s.id = ?1
+                     ORDER BY s.updated DESC",
+                )
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map([id], |row| {
+                    Ok(ChatSessionSummary {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        model: row.get(2)?,
+                        workspace: row.get(3)?,
+                        created: row.get(4)?,
+                        updated: row.get(5)?,
+                        message_count: row.get(6)?,
+                        tokens_used: row.get(7)?,
+                    })
+                })
+                .map_err(db_error)?;
+            rows.collect::<Result<_, _>>().map_err(db_error)
+        })
+    }
+
+    fn load(&self, app: &AppHandle, id: &str) -> Result<ChatSession, String> {
+        let summary = self
+            .summaries(app, Some(id))?
+            .pop()
+            .ok_or_else(|| format!("No such chat session: {}", id))?;
+        self.with_conn(app, |conn| {
+            let mut statement = conn
+                .prepare(
+                    "SELECT role, content, model, files, timestamp FROM messages
+                     WHERE session_id = ?1 ORDER BY seq",
+                )
+                .map_err(db_error)?;
+            let messages = statement
+                .query_map([id], |row| {
+                    let files: String = row.get(3)?;
+                    Ok(SessionMessage {
+                        role: parse_role(&row.get::<_, String>(0)?),
+                        content: row.get(1)?,
+                        model: row.get(2)?,
+                        files: serde_json::from_str(&files).unwrap_or_default(),
+                        timestamp: row.get(4)?,
+                    })
+                })
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+
+            let mut statement = conn
+                .prepare(
+                    "SELECT path, hunks, timestamp FROM edits
+                     WHERE session_id = ?1 ORDER BY id",
+                )
+                .map_err(db_error)?;
+            let edits = statement
+                .query_map([id], |row| {
+                    let hunks: String = row.get(1)?;
+                    Ok(SessionEdit {
+                        path: row.get(0)?,
+                        hunks: serde_json::from_str(&hunks).unwrap_or_default(),
+                        timestamp: row.get(2)?,
+                    })
+                })
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            Ok(ChatSession {
+                summary,
+                messages,
+                edits,
+            })
+        })
+    }
+}
+
+fn default_title(content: &str) -> String {
+    let line = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let title: String = line.trim().chars().take(TITLE_CHARS).collect();
+    if title.is_empty() {
+        "Untitled chat".to_string()
+    } else {
+        title
+    }
+}
+
+/// The messages of a chat request as stored: the last user message, then
+/// the reply when there is one.
+pub(crate) fn exchange(
+    messages: &[ChatMessage],
+    files: &[String],
+    reply: &str,
+    model: &str,
+) -> Vec<SessionMessage> {
+    let mut stored: Vec<SessionMessage> = messages
+        .iter()
+        .rev()
+        .find(|message| message.role == Role::User)
+        .map(|message| SessionMessage {
+            role: Role::User,
+            content: message.content.clone(),
+            model: None,
+            files: files.to_vec(),
+            timestamp: now(),
+        })
+        .into_iter()
+        .collect();
+    if !reply.is_empty() {
+        stored.push(SessionMessage {
+            role: Role::Assistant,
+            content: reply.to_string(),
+            model: Some(model.to_string()),
+            files: Vec::new(),
+            timestamp: now(),
+        });
+    }
+    stored
+}
+
+fn to_markdown(session: &ChatSession) -> String {
+    let mut out = format!("# {}\n\n", session.summary.title);
+    if let Some(model) = &session.summary.model {
+        out.push_str(&format!("Model: {}\n\n", model));
+    }
+    for message in &session.messages {
+        let heading = match message.role {
+            Role::System => "System",
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        };
+        out.push_str(&format!("## {}\n\n", heading));
+        if !message.files.is_empty() {
+            out.push_str(&format!("Files: {}\n\n", message.files.join(", ")));
+        }
+        out.push_str(message.content.trim_end());
+        out.push_str("\n\n");
+    }
+    if !session.edits.is_empty() {
+        out.push_str("## Applied edits\n\n");
+        for edit in &session.edits {
+            out.push_str(&format!("- {} ({} hunks)\n", edit.path, edit.hunks.len()));
+        }
+    }
+    out
+}
+
+/// Starts an empty session. Without a title it is named after its first
+/// user message.
+#[tauri::command]
+pub async fn create_chat_session(
+    title: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    sessions: State<'_, ChatSessions>,
+) -> Result<ChatSessionSummary, String> {
+    let workspace = windows
+        .scope(window.label())
+        .workspace
+        .active()
+        .map(|root| root.to_string_lossy().to_string());
+    let title = title.map(|t| t.trim().to_string()).unwrap_or_default();
+    sessions.create(&app, &title, workspace)
+}
+
+/// Sessions, most recently updated first.
+#[tauri::command]
+pub async fn list_chat_sessions(
+    app: AppHandle,
+    sessions: State<'_, ChatSessions>,
+) -> Result<Vec<ChatSessionSummary>, String> {
+    sessions.summaries(&app, None)
+}
+
+#[tauri::command]
+pub async fn load_chat_session(
+    id: String,
+    app: AppHandle,
+    sessions: State<'_, ChatSessions>,
+) -> Result<ChatSession, String> {
+    sessions.load(&app, &id)
+}
+
+#[tauri::command]
+pub async fn rename_chat_session(
+    id: String,
+    title: String,
+    app: AppHandle,
+    sessions: State<'_, ChatSessions>,
+) -> Result<(), String> {
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+    let changed = sessions.with_conn(&app, |conn| {
+        conn.execute(
+            "UPDATE sessions SET title = ?2 WHERE id = ?1",
+            params![id, title],
+        )
+        .map_err(db_error)
+    })?;
+    if changed == 0 {
+        return Err(format!("No such chat session: {}", id));
+    }
+    Ok(())
+}
+
+/// Returns the session as Markdown or JSON for the client to save.
+#[tauri::command]
+pub async fn export_chat_session(
+    id: String,
+    format: ExportFormat,
+    app: AppHandle,
+    sessions: State<'_, ChatSessions>,
+) -> Result<String, String> {
+    let session = sessions.load(&app, &id)?;
+    match format {
+        ExportFormat::Markdown => Ok(to_markdown(&session)),
+        ExportFormat::Json => serde_json::to_string_pretty(&session)
+            .map_err(|e| format!("Failed to export chat session: {}", e)),
+    }
+}
+
+/// Deletes a session with its messages and edit records. Returns whether
+/// it existed.
+#[tauri::command]
+pub async fn delete_chat_session(
+    id: String,
+    app: AppHandle,
+    sessions: State<'_, ChatSessions>,
+) -> Result<bool, String> {
+    sessions.with_conn(&app, |conn| {
+        conn.execute("DELETE FROM sessions WHERE id = ?1", [&id])
+            .map(|deleted| deleted > 0)
+            .map_err(db_error)
+    })
+}
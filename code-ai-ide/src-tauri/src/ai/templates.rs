@@ -0,0 +1,333 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, State, Window};
+
+use super::explain;
+use super::{ChatMessage, ChatOptions, Role};
+use crate::app_data;
+use crate::jsonc::read_jsonc;
+use crate::language;
+use crate::snippets::one_or_many;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// In the app config directory.
+const USER_TEMPLATES_DIR: &str = "prompts";
+/// Relative to the workspace root.
+const WORKSPACE_TEMPLATES_DIR: &str = ".codeai/prompts";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    User,
+    Workspace,
+}
+
+/// A prompt template as written in `<id>.json`. `prompt` may be a string
+/// or a list of lines, which are joined with newlines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplateDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Instructions sent as a system message before the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub prompt: Vec<String>,
+    /// Overrides `ai.model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Used when the request gives none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    /// The file name without `.json`.
+    pub id: String,
+    pub source: TemplateSource,
+    pub file: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub system: Option<String>,
+    pub prompt: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    /// Variables the system message and prompt refer to, such as
+    /// `selection`, in order of first use.
+    pub variables: Vec<String>,
+}
+
+/// `{{name}}`, with optional spaces inside the braces.
+fn variable_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid regex"))
+}
+
+fn variables_in(texts: &[&str]) -> Vec<String> {
+    let mut variables: Vec<String> = Vec::new();
+    for text in texts {
+        for captures in variable_pattern().captures_iter(text) {
+            if !variables.iter().any(|name| name == &captures[1]) {
+                variables.push(captures[1].to_string());
+            }
+        }
+    }
+    variables
+}
+
+/// Fills in every variable of `text`, failing with the names of those
+/// `values` has no value for.
+fn render(text: &str, values: &HashMap<String, String>) -> Result<String, String> {
+    let mut missing: Vec<String> = Vec::new();
+    let rendered = variable_pattern().replace_all(text, |captures: &Captures| {
+        match values.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => {
+                if !missing.iter().any(|name| name == &captures[1]) {
+                    missing.push(captures[1].to_string());
+                }
+                String::new()
+            }
+        }
+    });
+    if !missing.is_empty() {
+        return Err(format!("No value for {}", missing.join(", ")));
+    }
+    Ok(rendered.into_owned())
+}
+
+fn templates_dir(
+    source: TemplateSource,
+    app: &AppHandle,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<PathBuf, String> {
+    match source {
+        TemplateSource::User => app_data::app_config_path(app, USER_TEMPLATES_DIR),
+        TemplateSource::Workspace => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .map(|root| root.join(WORKSPACE_TEMPLATES_DIR))
+            .ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+fn template_file(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.trim().is_empty() || id.contains(['/', '\\', '.']) {
+        return Err(format!("Invalid template id: {}", id));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+fn read_template(file: &Path, source: TemplateSource) -> Result<PromptTemplate, String> {
+    let definition: PromptTemplateDefinition = serde_json::from_value(read_jsonc(file)?)
+        .map_err(|e| format!("Invalid template {}: {}", file.display(), e))?;
+    let prompt = definition.prompt.join("\n");
+    let variables = variables_in(&[definition.system.as_deref().unwrap_or(""), &prompt]);
+    Ok(PromptTemplate {
+        id: file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        source,
+        file: file.to_string_lossy().to_string(),
+        name: definition.name,
+        description: definition.description,
+        system: definition.system,
+        prompt,
+        model: definition.model,
+        temperature: definition.temperature,
+        variables,
+    })
+}
+
+fn templates_in(dir: &Path, source: TemplateSource) -> Vec<PromptTemplate> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    // Broken files are skipped so one typo does not hide the rest.
+    files
+        .iter()
+        .filter_map(|file| read_template(file, source).ok())
+        .collect()
+}
+
+/// User templates first and then the workspace's, which win when both
+/// have the same id.
+#[tauri::command]
+pub async fn list_prompt_templates(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let mut templates = Vec::new();
+    for source in [TemplateSource::User, TemplateSource::Workspace] {
+        if let Ok(dir) = templates_dir(source, &app, &window, &windows) {
+            templates.extend(templates_in(&dir, source));
+        }
+    }
+    Ok(templates)
+}
+
+/// Creates or replaces the template `id` in the user's or the workspace's
+/// prompt directory. The file is rewritten as plain JSON.
+#[tauri::command]
+pub async fn save_prompt_template(
+    source: TemplateSource,
+    id: String,
+    template: PromptTemplateDefinition,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<PromptTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name must not be empty".to_string());
+    }
+    if template.prompt.iter().all(|line| line.trim().is_empty()) {
+        return Err("Template prompt must not be empty".to_string());
+    }
+    let dir = templates_dir(source, &app, &window, &windows)?;
+    let path = template_file(&dir, &id)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    app_data::save_json(&path, &template)?;
+    read_template(&path, source)
+}
+
+#[tauri::command]
+pub async fn delete_prompt_template(
+    source: TemplateSource,
+    id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let dir = templates_dir(source, &app, &window, &windows)?;
+    let path = template_file(&dir, &id)?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete {}: {}", path.display(), e)),
+    }
+}
+
+/// Fills in the variables the backend knows about `vars["file"]`, a path,
+/// where the template uses them and `vars` does not set them: `file`
+/// becomes the path relative to the workspace, and `file_content`,
+/// `language` and `diagnostics` are those of the open buffer or the file.
+fn file_variables(
+    vars: &mut HashMap<String, String>,
+    used: &[String],
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<(), String> {
+    let Some(file) = vars.get("file").cloned() else {
+        return Ok(());
+    };
+    let path = workspace::authorize(windows, window, &file)?;
+    let scope = windows.scope(window.label());
+    let root = scope.workspace.active();
+    let wanted = |name: &str| used.iter().any(|used| used == name) && !vars.contains_key(name);
+    let (want_content, want_language, want_diagnostics) = (
+        wanted("file_content"),
+        wanted("language"),
+        wanted("diagnostics"),
+    );
+
+    let content = (want_content || want_language)
+        .then(|| explain::read_text(&scope, &path))
+        .flatten();
+    if want_content {
+        let content = content
+            .clone()
+            .ok_or_else(|| format!("Failed to read {}", path.display()))?;
+        vars.insert("file_content".to_string(), content);
+    }
+    if want_language {
+        let language = match &content {
+            Some(content) => language::detect_language(&path, content),
+            None => language::language_for_path(&path),
+        };
+        vars.insert("language".to_string(), language);
+    }
+    if want_diagnostics {
+        let problems: Vec<String> = scope
+            .diagnostics
+            .get(Some(path.to_string_lossy().as_ref()))
+            .into_iter()
+            .flat_map(|file| file.diagnostics)
+            .map(|diagnostic| explain::describe(&diagnostic, root.as_deref()))
+            .collect();
+        let problems = if problems.is_empty() {
+            "No problems reported.".to_string()
+        } else {
+            problems.join("\n")
+        };
+        vars.insert("diagnostics".to_string(), problems);
+    }
+    vars.insert("file".to_string(), explain::display(&path, root.as_deref()));
+    Ok(())
+}
+
+/// Renders the template `id` with `vars` and sends it as a chat request,
+/// for actions such as "refactor this" or "write tests". Without `source`
+/// the workspace's template wins over the user's. `{{selection}}` and
+/// other editor state come from `vars`; see `file_variables` for what is
+/// filled in from `vars["file"]`. Returns the chat request id; the reply
+/// streams as for `ai_chat`.
+#[tauri::command]
+pub async fn run_prompt_template(
+    id: String,
+    source: Option<TemplateSource>,
+    vars: Option<HashMap<String, String>>,
+    options: Option<ChatOptions>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let sources = match source {
+        Some(source) => vec![source],
+        None => vec![TemplateSource::Workspace, TemplateSource::User],
+    };
+    let template = sources
+        .into_iter()
+        .filter_map(|source| {
+            let dir = templates_dir(source, &app, &window, &windows).ok()?;
+            let path = template_file(&dir, &id).ok()?;
+            path.exists().then(|| read_template(&path, source))
+        })
+        .next()
+        .ok_or_else(|| format!("No such prompt template: {}", id))??;
+
+    let mut vars = vars.unwrap_or_default();
+    file_variables(&mut vars, &template.variables, &window, &windows)?;
+    let mut messages = Vec::new();
+    if let Some(system) = &template.system {
+        messages.push(ChatMessage {
+            role: Role::System,
+            content: render(system, &vars)?,
+        });
+    }
+    messages.push(ChatMessage {
+        role: Role::User,
+        content: render(&template.prompt, &vars)?,
+    });
+
+    let mut options = options.unwrap_or_default();
+    options.temperature = options.temperature.or(template.temperature);
+    super::start_chat(app, window, messages, template.model, options).await
+}
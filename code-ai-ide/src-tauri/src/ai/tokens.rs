@@ -0,0 +1,276 @@
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, Window};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+use super::models;
+use super::sessions::ChatSessions;
+use super::{ChatMessage, ChatOptions, Role, Usage};
+use crate::settings;
+
+/// What the chat format adds around each message and the reply, as
+/// OpenAI documents it; other providers are close.
+const TOKENS_PER_MESSAGE: usize = 4;
+const TOKENS_PER_REPLY: usize = 3;
+
+type Cached = OnceLock<Result<CoreBPE, String>>;
+
+fn cached<E: Display>(
+    cell: &'static Cached,
+    load: impl FnOnce() -> Result<CoreBPE, E>,
+) -> Result<&'static CoreBPE, String> {
+    cell.get_or_init(|| load().map_err(|e| format!("Failed to load tokenizer: {}", e)))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// `cl100k_base`; close enough to every provider's tokenizer for budgeting.
+pub fn cl100k() -> Result<&'static CoreBPE, String> {
+    static CL100K: Cached = OnceLock::new();
+    cached(&CL100K, tiktoken_rs::cl100k_base)
+}
+
+/// The tokenizer of an OpenAI model, or `cl100k_base` for every other
+/// model: not exact for Claude or Llama, but close enough for budgets.
+pub fn for_model(model: &str) -> Result<&'static CoreBPE, String> {
+    static O200K: Cached = OnceLock::new();
+    static P50K: Cached = OnceLock::new();
+    static R50K: Cached = OnceLock::new();
+    match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => cached(&O200K, tiktoken_rs::o200k_base),
+        Some(Tokenizer::P50kBase) | Some(Tokenizer::P50kEdit) => {
+            cached(&P50K, tiktoken_rs::p50k_base)
+        }
+        Some(Tokenizer::R50kBase) | Some(Tokenizer::Gpt2) => cached(&R50K, tiktoken_rs::r50k_base),
+        _ => cl100k(),
+    }
+}
+
+pub fn count(bpe: &CoreBPE, text: &str) -> usize {
+    bpe.encode_with_special_tokens(text).len()
+}
+
+fn count_message(bpe: &CoreBPE, message: &ChatMessage) -> usize {
+    TOKENS_PER_MESSAGE + count(bpe, &message.content)
+}
+
+/// Tokens the messages take as the input of a chat request.
+pub fn count_messages(bpe: &CoreBPE, messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| count_message(bpe, message))
+        .sum::<usize>()
+        + TOKENS_PER_REPLY
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetScope {
+    /// The input of one request is over the request limit.
+    Request,
+    /// The request would take the chat session past its limit.
+    Session,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropKind {
+    /// The workspace snippets added for `context_results`.
+    SemanticContext,
+    Message,
+    /// The last message is over the limit by itself.
+    ShortenLastMessage,
+    StartNewSession,
+}
+
+/// Something the client can leave out to fit the budget.
+#[derive(Debug, Clone, Serialize)]
+pub struct DropSuggestion {
+    pub kind: DropKind,
+    /// Index in the messages the client sent, for `Message`.
+    pub index: Option<usize>,
+    pub role: Option<Role>,
+    pub tokens: usize,
+}
+
+/// Emitted as `ai-budget-exceeded` when a chat request is refused for its
+/// size; the command itself fails with `message`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetExceeded {
+    pub scope: BudgetScope,
+    pub model: String,
+    pub input_tokens: usize,
+    pub limit: usize,
+    /// Tokens the session had used before this request.
+    pub session_tokens: u64,
+    /// The cheapest way back under the limit, largest savings first.
+    pub suggestions: Vec<DropSuggestion>,
+    pub message: String,
+}
+
+fn budget_setting(app: &AppHandle, key: &str) -> Result<usize, String> {
+    Ok(settings::user_setting(app, key)?.as_u64().unwrap_or(0) as usize)
+}
+
+/// Messages to drop, largest first, until `excess` tokens are saved. The
+/// last message and system instructions are kept; the semantic context
+/// message, when `has_context`, is the first in `messages`.
+fn suggest_drops(
+    bpe: &CoreBPE,
+    messages: &[ChatMessage],
+    has_context: bool,
+    excess: usize,
+) -> Vec<DropSuggestion> {
+    let last = messages.len().saturating_sub(1);
+    let mut candidates: Vec<DropSuggestion> = messages
+        .iter()
+        .enumerate()
+        .filter(|(i, message)| {
+            *i != last && ((has_context && *i == 0) || message.role != Role::System)
+        })
+        .map(|(i, message)| {
+            let context = has_context && i == 0;
+            DropSuggestion {
+                kind: if context {
+                    DropKind::SemanticContext
+                } else {
+                    DropKind::Message
+                },
+                index: (!context).then(|| i - usize::from(has_context)),
+                role: (!context).then_some(message.role),
+                tokens: count_message(bpe, message),
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.tokens.cmp(&a.tokens));
+
+    let mut saved = 0;
+    let mut suggestions = Vec::new();
+    for candidate in candidates {
+        if saved >= excess {
+            break;
+        }
+        saved += candidate.tokens;
+        suggestions.push(candidate);
+    }
+    if saved < excess {
+        suggestions.push(DropSuggestion {
+            kind: DropKind::ShortenLastMessage,
+            index: Some(last - usize::from(has_context)),
+            role: messages.last().map(|message| message.role),
+            tokens: excess - saved,
+        });
+    }
+    suggestions
+}
+
+/// Counts the input of a request and checks it against the request limit
+/// (`ai.budget.requestTokens`, else the model's context window from the
+/// catalog less the reply) and the session limit (`ai.budget.sessionTokens`). Returns the
+/// input tokens; a request over either limit is refused with
+/// `ai-budget-exceeded`.
+pub(crate) fn check_budget(
+    app: &AppHandle,
+    window: &Window,
+    model: &str,
+    messages: &[ChatMessage],
+    has_context: bool,
+    options: &ChatOptions,
+) -> Result<usize, String> {
+    let bpe = for_model(model)?;
+    let input_tokens = count_messages(bpe, messages);
+    let reply = options.max_tokens.unwrap_or(0) as usize;
+    let refuse = |exceeded: BudgetExceeded| {
+        let message = exceeded.message.clone();
+        let _ = window.emit("ai-budget-exceeded", exceeded);
+        Err(message)
+    };
+
+    let limit = match budget_setting(app, "ai.budget.requestTokens")? {
+        0 => models::lookup(model)
+            .and_then(|entry| entry.context_window)
+            .map(|window| window.saturating_sub(reply)),
+        limit => Some(limit),
+    };
+    if let Some(limit) = limit.filter(|limit| input_tokens > *limit) {
+        return refuse(BudgetExceeded {
+            scope: BudgetScope::Request,
+            model: model.to_string(),
+            input_tokens,
+            limit,
+            session_tokens: 0,
+            suggestions: suggest_drops(bpe, messages, has_context, input_tokens - limit),
+            message: format!(
+                "Context too large: {} tokens for a limit of {}",
+                input_tokens, limit
+            ),
+        });
+    }
+
+    let session_limit = budget_setting(app, "ai.budget.sessionTokens")?;
+    if let Some(session_id) = options.session_id.as_ref().filter(|_| session_limit > 0) {
+        let used = app.state::<ChatSessions>().tokens_used(app, session_id)?;
+        if used + (input_tokens + reply) as u64 > session_limit as u64 {
+            return refuse(BudgetExceeded {
+                scope: BudgetScope::Session,
+                model: model.to_string(),
+                input_tokens,
+                limit: session_limit,
+                session_tokens: used,
+                suggestions: vec![DropSuggestion {
+                    kind: DropKind::StartNewSession,
+                    index: None,
+                    role: None,
+                    tokens: used as usize,
+                }],
+                message: format!(
+                    "Chat session budget exhausted: {} of {} tokens used",
+                    used, session_limit
+                ),
+            });
+        }
+    }
+    Ok(input_tokens)
+}
+
+/// The usage the provider reported, or else one estimated from the
+/// request's `input_tokens` and the reply, with `true` for an estimate.
+pub(crate) fn usage_or_estimate(
+    reported: Option<Usage>,
+    model: &str,
+    input_tokens: usize,
+    reply: &str,
+) -> (Usage, bool) {
+    match reported {
+        Some(usage) => (usage, false),
+        None => {
+            let output_tokens = for_model(model).map_or(0, |bpe| count(bpe, reply));
+            let usage = Usage {
+                input_tokens: input_tokens as u64,
+                output_tokens: output_tokens as u64,
+            };
+            (usage, true)
+        }
+    }
+}
+
+/// Tokens `text` takes for `model` (the `ai.model` setting when omitted),
+/// with the model's own tokenizer for OpenAI models and an estimate for
+/// others.
+#[tauri::command]
+pub async fn count_tokens(
+    model: Option<String>,
+    text: String,
+    app: AppHandle,
+) -> Result<usize, String> {
+    let model = match model {
+        Some(model) => model,
+        None => super::string_setting(&app, "ai.model")?,
+    };
+    let bpe = for_model(&model)?;
+    tauri::async_runtime::spawn_blocking(move || count(bpe, &text))
+        .await
+        .map_err(|e| format!("Failed to count tokens: {}", e))
+}
@@ -0,0 +1,58 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Path of `name` inside the app data directory, creating the directory (and
+/// any parents in `name`) on first use.
+pub fn app_data_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Failed to locate app data directory".to_string())?;
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    Ok(path)
+}
+
+/// Like `app_data_path`, in the app config directory, for files the user
+/// may want to edit or sync by hand.
+pub fn app_config_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or_else(|| "Failed to locate app config directory".to_string())?;
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+    }
+    Ok(path)
+}
+
+/// Reads a JSON file, treating a missing or unreadable file as empty state
+/// so a corrupt store never prevents the app from starting.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Writes JSON through a temporary file and rename so a crash mid-write
+/// leaves the previous contents intact.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize data: {}", e))?;
+    write_atomic(path, text.as_bytes())
+}
+
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, bytes).map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+    fs::rename(&temp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
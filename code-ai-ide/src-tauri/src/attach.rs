@@ -0,0 +1,225 @@
+#[cfg(windows)]
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+#[cfg(not(windows))]
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tauri::{State, Window};
+
+use crate::debug::{self, DebugSessionInfo, LaunchConfig, Variables};
+use crate::window_state::WindowRegistry;
+
+/// The debugger type attached with when the process gives no hint.
+const DEFAULT_DEBUG_TYPE: &str = "lldb";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebuggableProcess {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub command_line: String,
+    /// The debugger type to attach with, guessed from the runtime; native
+    /// processes get `lldb`.
+    pub debug_type: String,
+}
+
+/// The fields of `Win32_Process` the listing asks PowerShell for.
+#[cfg(windows)]
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Win32Process {
+    process_id: u32,
+    parent_process_id: Option<u32>,
+    name: Option<String>,
+    command_line: Option<String>,
+}
+
+fn debug_type(name: &str) -> &'static str {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    if name.starts_with("python") || name == "py" || name == "uvicorn" || name == "gunicorn" {
+        "debugpy"
+    } else if name == "node" || name == "nodejs" {
+        "node"
+    } else {
+        DEFAULT_DEBUG_TYPE
+    }
+}
+
+fn process(
+    pid: u32,
+    parent_pid: Option<u32>,
+    name: String,
+    command_line: String,
+) -> DebuggableProcess {
+    DebuggableProcess {
+        debug_type: debug_type(&name).to_string(),
+        pid,
+        parent_pid,
+        name,
+        command_line,
+    }
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to list processes: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to list processes: {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Every process `ps` shows, kernel threads aside.
+#[cfg(not(windows))]
+fn running_processes() -> Result<Vec<DebuggableProcess>, String> {
+    let output =
+        run(Command::new("ps").args(["-A", "-ww", "-o", "pid=", "-o", "ppid=", "-o", "args="]))?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let parent_pid = fields.next()?.parse().ok();
+            let command_line = fields.collect::<Vec<_>>().join(" ");
+            // Kernel threads, such as `[kworker/0:1]`, have no command line.
+            if command_line.is_empty() || command_line.starts_with('[') {
+                return None;
+            }
+            let program = command_line.split(' ').next().unwrap_or_default();
+            let name = Path::new(program)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| program.to_string());
+            Some(process(pid, parent_pid, name, command_line))
+        })
+        .collect())
+}
+
+/// Every process `Win32_Process` shows, through PowerShell.
+#[cfg(windows)]
+fn running_processes() -> Result<Vec<DebuggableProcess>, String> {
+    let output = run(Command::new("powershell").args([
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        "Get-CimInstance Win32_Process | Select-Object ProcessId,ParentProcessId,Name,CommandLine | ConvertTo-Json -Compress",
+    ]))?;
+    // A single process comes as an object rather than an array.
+    let processes: Vec<Win32Process> = match serde_json::from_str(output.trim())
+        .map_err(|e| format!("Failed to list processes: {}", e))?
+    {
+        Value::Array(items) => items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect(),
+        item => serde_json::from_value(item).into_iter().collect(),
+    };
+    Ok(processes
+        .into_iter()
+        .map(|p| {
+            let name = p.name.unwrap_or_default();
+            let command_line = p.command_line.unwrap_or_else(|| name.clone());
+            process(p.process_id, p.parent_process_id, name, command_line)
+        })
+        .collect())
+}
+
+/// The processes that can be attached to, such as servers already running
+/// in a terminal, whose name, command line or pid contains `filter`
+/// (case-insensitive). The IDE itself is left out.
+#[tauri::command]
+pub async fn list_debuggable_processes(
+    filter: Option<String>,
+) -> Result<Vec<DebuggableProcess>, String> {
+    let filter = filter
+        .map(|filter| filter.trim().to_lowercase())
+        .filter(|filter| !filter.is_empty());
+    tauri::async_runtime::spawn_blocking(move || {
+        let own = std::process::id();
+        let mut processes: Vec<DebuggableProcess> = running_processes()?
+            .into_iter()
+            .filter(|p| p.pid != own)
+            .filter(|p| {
+                filter.as_ref().map_or(true, |filter| {
+                    p.name.to_lowercase().contains(filter.as_str())
+                        || p.command_line.to_lowercase().contains(filter.as_str())
+                        || p.pid.to_string().contains(filter.as_str())
+                })
+            })
+            .collect();
+        processes.sort_by(|a, b| {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then(a.pid.cmp(&b.pid))
+        });
+        Ok(processes)
+    })
+    .await
+    .map_err(|e| format!("Failed to list processes: {}", e))?
+}
+
+/// The `attach` configuration for `pid` with the debugger `kind`, in the
+/// form each adapter expects.
+fn attach_config(name: &str, pid: u32, kind: &str) -> LaunchConfig {
+    let properties = match kind {
+        "lldb" | "lldb-dap" => json!({ "pid": "${command:pickProcess}" }),
+        "go" => json!({ "mode": "local", "processId": "${command:pickProcess}" }),
+        _ => json!({ "processId": "${command:pickProcess}" }),
+    };
+    let properties: Map<String, Value> = match properties {
+        Value::Object(properties) => properties,
+        _ => Map::new(),
+    };
+    LaunchConfig {
+        name: format!("Attach to {} ({})", name, pid),
+        kind: kind.to_string(),
+        request: "attach".to_string(),
+        properties,
+    }
+}
+
+/// Attaches a debugger to the running process `pid`, without restarting
+/// it. `kind` is the debugger type (default: the one
+/// `list_debuggable_processes` suggests). The session is then driven like
+/// one from `start_debug`: the client sends `initialize` and the `attach`
+/// request with the returned `configuration`.
+#[tauri::command]
+pub async fn attach_debug(
+    pid: u32,
+    kind: Option<String>,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DebugSessionInfo, String> {
+    let root = debug::launch_root(root, &window, &windows)?;
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let process = running_processes()?
+            .into_iter()
+            .find(|p| p.pid == pid)
+            .ok_or_else(|| format!("No process with id {}", pid))?;
+        let kind = kind.unwrap_or(process.debug_type);
+        if !debug::has_adapter(&kind) {
+            return Err(format!("No debug adapter for type {}", kind));
+        }
+        let variables = Variables {
+            root: &root,
+            file: None,
+            process_id: Some(pid),
+        };
+        debug::start(
+            window,
+            scope,
+            attach_config(&process.name, pid, &kind),
+            &variables,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to attach debugger: {}", e))?
+}
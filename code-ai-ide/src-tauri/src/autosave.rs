@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Manager, State, Window};
+
+use crate::documents::document_key;
+use crate::formatter;
+use crate::local_history::{LocalHistory, RevisionSource};
+use crate::window_state::{WindowRegistry, WindowState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoSaveSettings {
+    pub enabled: bool,
+    /// Idle time after the last change before a buffer is written.
+    pub delay_ms: u64,
+    /// Also save every dirty buffer when the window loses focus.
+    pub on_blur: bool,
+    pub format_on_save: bool,
+}
+
+impl Default for AutoSaveSettings {
+    fn default() -> Self {
+        AutoSaveSettings {
+            enabled: false,
+            delay_ms: 1000,
+            on_blur: true,
+            format_on_save: false,
+        }
+    }
+}
+
+/// Emitted as `document-autosaved`.
+#[derive(Debug, Clone, Serialize)]
+struct AutoSaved {
+    path: String,
+    version: u64,
+    /// Formatting changed the buffer; the editor should reload it.
+    formatted: bool,
+}
+
+/// Emitted as `autosave-skipped` when a buffer was left unsaved.
+#[derive(Debug, Clone, Serialize)]
+struct AutoSaveSkipped {
+    path: String,
+    /// `conflict` when the file changed on disk since it was read, so
+    /// writing would overwrite someone else's edit; `error` otherwise.
+    reason: &'static str,
+    message: String,
+}
+
+/// Auto-save state of a window: the settings, and per document a counter
+/// bumped on every change so only the last of a burst of changes saves.
+#[derive(Default)]
+pub struct AutoSave {
+    settings: Mutex<AutoSaveSettings>,
+    generations: Mutex<HashMap<String, u64>>,
+}
+
+impl AutoSave {
+    pub fn settings(&self) -> AutoSaveSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    fn bump(&self, key: &str) -> u64 {
+        let mut generations = self.generations.lock().unwrap();
+        let generation = generations.entry(key.to_string()).or_default();
+        *generation += 1;
+        *generation
+    }
+
+    fn is_latest(&self, key: &str, generation: u64) -> bool {
+        self.generations.lock().unwrap().get(key) == Some(&generation)
+    }
+
+    pub fn clear(&self) {
+        self.generations.lock().unwrap().clear();
+    }
+}
+
+/// Restarts the idle timer of a document after a change.
+pub fn schedule(window: &Window, scope: &Arc<WindowState>, path: &str) {
+    let settings = scope.autosave.settings();
+    if !settings.enabled {
+        return;
+    }
+    let key = document_key(path);
+    let generation = scope.autosave.bump(&key);
+    let (window, scope) = (window.clone(), scope.clone());
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(settings.delay_ms)).await;
+        if scope.autosave.is_latest(&key, generation) {
+            save(&window, &scope, &key, settings.format_on_save).await;
+        }
+    });
+}
+
+/// Saves every dirty document of the window, if saving on blur is on.
+pub fn on_blur(window: &Window, scope: &Arc<WindowState>) {
+    let settings = scope.autosave.settings();
+    if !settings.enabled || !settings.on_blur {
+        return;
+    }
+    let (window, scope) = (window.clone(), scope.clone());
+    tauri::async_runtime::spawn(async move {
+        for doc in scope.documents.dirty_snapshots() {
+            // Pending timers for these are now moot.
+            scope.autosave.bump(&doc.path);
+            save(&window, &scope, &doc.path, settings.format_on_save).await;
+        }
+    });
+}
+
+async fn save(window: &Window, scope: &WindowState, key: &str, format_on_save: bool) {
+    let skipped = |reason, message: String| {
+        let _ = window.emit(
+            "autosave-skipped",
+            AutoSaveSkipped {
+                path: key.to_string(),
+                reason,
+                message,
+            },
+        );
+    };
+    let Some(doc) = scope.documents.get(key) else {
+        return;
+    };
+    if !doc.dirty {
+        return;
+    }
+    if scope.documents.changed_on_disk(key) {
+        skipped(
+            "conflict",
+            "The file changed on disk since it was opened".to_string(),
+        );
+        return;
+    }
+
+    // A failing formatter must not stop the save; the user will see the
+    // error the next time they format explicitly.
+    let mut formatted = false;
+    if format_on_save {
+        let result = formatter::format_text(
+            window.clone(),
+            PathBuf::from(key),
+            doc.content.clone(),
+            None,
+        )
+        .await;
+        if let Ok(text) = result {
+            formatted =
+                text != doc.content && scope.documents.replace_if_current(key, doc.version, text);
+        }
+    }
+
+    let previous = fs::read(key).ok();
+    match scope.documents.save(key) {
+        Ok(version) => {
+            if let Some(previous) = previous {
+                let history = window.state::<LocalHistory>();
+                let _ = history.record(
+                    &window.app_handle(),
+                    Path::new(key),
+                    &previous,
+                    RevisionSource::Save,
+                );
+            }
+            let _ = window.emit(
+                "document-autosaved",
+                AutoSaved {
+                    path: key.to_string(),
+                    version,
+                    formatted,
+                },
+            );
+        }
+        Err(e) => skipped("error", e),
+    }
+}
+
+#[tauri::command]
+pub async fn get_autosave_settings(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<AutoSaveSettings, String> {
+    Ok(windows.scope(window.label()).autosave.settings())
+}
+
+#[tauri::command]
+pub async fn set_autosave_settings(
+    settings: AutoSaveSettings,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let scope = windows.scope(window.label());
+    *scope.autosave.settings.lock().unwrap() = settings;
+    Ok(())
+}
@@ -0,0 +1,506 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+const BREAKPOINTS_DIR: &str = "breakpoints";
+
+/// A source breakpoint. Lines and columns are one-based, as debug
+/// adapters expect by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub id: String,
+    pub path: String,
+    pub line: u32,
+    #[serde(default)]
+    pub column: Option<u32>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Only break when this expression is true.
+    #[serde(default)]
+    pub condition: Option<String>,
+    /// Only break after this many hits, in the adapter's syntax (`5`,
+    /// `>= 5`, `% 2`).
+    #[serde(default)]
+    pub hit_condition: Option<String>,
+    /// Log this message instead of breaking: a logpoint. `{expression}`
+    /// parts are evaluated.
+    #[serde(default)]
+    pub log_message: Option<String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// A breakpoint to add, or to change when `id` (or else its path and line)
+/// matches an existing one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BreakpointSpec {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub path: String,
+    pub line: u32,
+    #[serde(default)]
+    pub column: Option<u32>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub hit_condition: Option<String>,
+    #[serde(default)]
+    pub log_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakpointView {
+    #[serde(flatten)]
+    pub breakpoint: Breakpoint,
+    /// A running debug session bound it. Until then it is pending.
+    pub verified: bool,
+    /// The line the adapter bound it to, when that differs from `line`.
+    pub actual_line: Option<u32>,
+    /// Why the adapter could not bind it, if it says.
+    pub message: Option<String>,
+}
+
+/// Emitted as `breakpoints-changed` when breakpoints are edited or a debug
+/// session verifies them; `path` is `None` when any file may be affected.
+#[derive(Debug, Clone, Serialize)]
+struct BreakpointsChanged {
+    path: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BreakpointsFile {
+    #[serde(default)]
+    breakpoints: Vec<Breakpoint>,
+}
+
+/// What a debug adapter reported for one breakpoint.
+#[derive(Debug, Clone)]
+struct Binding {
+    path: String,
+    verified: bool,
+    line: Option<u32>,
+    message: Option<String>,
+    /// The adapter's id, for later `breakpoint` events.
+    adapter_id: Option<i64>,
+}
+
+/// A `setBreakpoints` request on its way to an adapter: the file, and the
+/// ids of the breakpoints in the order they were sent.
+struct SentBreakpoints {
+    path: String,
+    ids: Vec<Option<String>>,
+}
+
+/// How a window's debug sessions see the workspace's breakpoints. The
+/// breakpoints themselves are stored per workspace under app data.
+#[derive(Default)]
+pub struct BreakpointBindings {
+    /// Serializes changes to the breakpoint files.
+    lock: Mutex<()>,
+    /// Keyed by session id and request `seq`.
+    sent: Mutex<HashMap<(String, i64), SentBreakpoints>>,
+    /// Keyed by session id, then breakpoint id.
+    bindings: Mutex<HashMap<String, HashMap<String, Binding>>>,
+}
+
+impl BreakpointBindings {
+    /// Notes a `setBreakpoints` request the client sends to an adapter, so
+    /// its response can be matched to the stored breakpoints.
+    pub fn observe_request(
+        &self,
+        app: &AppHandle,
+        scope: &WindowState,
+        session_id: &str,
+        message: &Value,
+    ) {
+        if message["command"] != "setBreakpoints" {
+            return;
+        }
+        let (Some(seq), Some(path)) = (
+            message["seq"].as_i64(),
+            message["arguments"]["source"]["path"].as_str(),
+        ) else {
+            return;
+        };
+        let stored = scope
+            .workspace
+            .root_for(Some(Path::new(path)))
+            .and_then(|root| load(app, &root).ok())
+            .unwrap_or_default();
+        let ids = message["arguments"]["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|sent| {
+                let line = sent["line"].as_u64()?;
+                stored
+                    .iter()
+                    .find(|b| b.enabled && b.path == path && u64::from(b.line) == line)
+                    .map(|b| b.id.clone())
+            })
+            .collect();
+        self.sent.lock().unwrap().insert(
+            (session_id.to_string(), seq),
+            SentBreakpoints {
+                path: path.to_string(),
+                ids,
+            },
+        );
+    }
+
+    /// Records what an adapter says about breakpoints: the response to a
+    /// `setBreakpoints` request, or a `breakpoint` event.
+    pub fn observe_message(&self, window: &Window, session_id: &str, message: &Value) {
+        let changed = match (message["type"].as_str(), message["command"].as_str()) {
+            (Some("response"), Some("setBreakpoints")) => self.on_response(session_id, message),
+            (Some("event"), _) if message["event"] == "breakpoint" => {
+                self.on_event(session_id, &message["body"])
+            }
+            _ => return,
+        };
+        if changed {
+            let _ = window.emit("breakpoints-changed", BreakpointsChanged { path: None });
+        }
+    }
+
+    fn on_response(&self, session_id: &str, message: &Value) -> bool {
+        let Some(seq) = message["request_seq"].as_i64() else {
+            return false;
+        };
+        let Some(sent) = self
+            .sent
+            .lock()
+            .unwrap()
+            .remove(&(session_id.to_string(), seq))
+        else {
+            return false;
+        };
+        let results = message["body"]["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut bindings = self.bindings.lock().unwrap();
+        let session = bindings.entry(session_id.to_string()).or_default();
+        // The request replaces every breakpoint of the file.
+        session.retain(|_, binding| binding.path != sent.path);
+        for (id, result) in sent.ids.iter().zip(results.iter()) {
+            let Some(id) = id else {
+                continue;
+            };
+            session.insert(
+                id.clone(),
+                Binding {
+                    path: sent.path.clone(),
+                    // A failed request binds nothing.
+                    verified: message["success"] != false
+                        && result["verified"].as_bool().unwrap_or(false),
+                    line: result["line"].as_u64().map(|line| line as u32),
+                    message: result["message"]
+                        .as_str()
+                        .or_else(|| message["message"].as_str())
+                        .map(str::to_string),
+                    adapter_id: result["id"].as_i64(),
+                },
+            );
+        }
+        true
+    }
+
+    fn on_event(&self, session_id: &str, body: &Value) -> bool {
+        let breakpoint = &body["breakpoint"];
+        let Some(adapter_id) = breakpoint["id"].as_i64() else {
+            return false;
+        };
+        let mut bindings = self.bindings.lock().unwrap();
+        let Some(session) = bindings.get_mut(session_id) else {
+            return false;
+        };
+        let Some(id) = session
+            .iter()
+            .find(|(_, binding)| binding.adapter_id == Some(adapter_id))
+            .map(|(id, _)| id.clone())
+        else {
+            return false;
+        };
+        if body["reason"] == "removed" {
+            session.remove(&id);
+            return true;
+        }
+        let binding = session.get_mut(&id).expect("binding found above");
+        if let Some(verified) = breakpoint["verified"].as_bool() {
+            binding.verified = verified;
+        }
+        if let Some(line) = breakpoint["line"].as_u64() {
+            binding.line = Some(line as u32);
+        }
+        binding.message = breakpoint["message"].as_str().map(str::to_string);
+        true
+    }
+
+    /// Forgets what a session reported, once it has ended.
+    pub fn end_session(&self, window: &Window, session_id: &str) {
+        self.sent
+            .lock()
+            .unwrap()
+            .retain(|(session, _), _| session != session_id);
+        let removed = self.bindings.lock().unwrap().remove(session_id);
+        if removed.is_some() {
+            let _ = window.emit("breakpoints-changed", BreakpointsChanged { path: None });
+        }
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().unwrap().clear();
+        self.bindings.lock().unwrap().clear();
+    }
+
+    fn view(&self, breakpoint: Breakpoint) -> BreakpointView {
+        let bindings = self.bindings.lock().unwrap();
+        let found: Vec<&Binding> = bindings
+            .values()
+            .filter_map(|session| session.get(&breakpoint.id))
+            .collect();
+        let bound = found.iter().find(|binding| binding.verified);
+        BreakpointView {
+            verified: bound.is_some(),
+            actual_line: bound
+                .and_then(|binding| binding.line)
+                .filter(|line| *line != breakpoint.line),
+            message: found.iter().find_map(|binding| binding.message.clone()),
+            breakpoint,
+        }
+    }
+}
+
+/// One file per workspace, named by a hash of its root.
+fn breakpoints_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let hash = blake3::hash(root.to_string_lossy().as_bytes());
+    let name = format!("{}/{}.json", BREAKPOINTS_DIR, &hash.to_hex()[..32]);
+    app_data::app_data_path(app, &name)
+}
+
+fn load(app: &AppHandle, root: &Path) -> Result<Vec<Breakpoint>, String> {
+    let file: BreakpointsFile = app_data::load_json(&breakpoints_path(app, root)?);
+    Ok(file.breakpoints)
+}
+
+fn save(app: &AppHandle, root: &Path, breakpoints: Vec<Breakpoint>) -> Result<(), String> {
+    app_data::save_json(
+        &breakpoints_path(app, root)?,
+        &BreakpointsFile { breakpoints },
+    )
+}
+
+/// Loads the breakpoints of the root holding `path`, lets `change` edit
+/// them and saves them when `change` says it changed anything.
+fn update<T>(
+    app: &AppHandle,
+    scope: &WindowState,
+    path: &Path,
+    change: impl FnOnce(&mut Vec<Breakpoint>) -> (T, bool),
+) -> Result<T, String> {
+    let root = scope
+        .workspace
+        .root_for(Some(path))
+        .ok_or_else(|| "No workspace is open".to_string())?;
+    let _guard = scope.breakpoints.lock.lock().unwrap();
+    let mut breakpoints = load(app, &root)?;
+    let (result, changed) = change(&mut breakpoints);
+    if changed {
+        save(app, &root, breakpoints)?;
+    }
+    Ok(result)
+}
+
+fn notify(window: &Window, path: Option<String>) {
+    let _ = window.emit("breakpoints-changed", BreakpointsChanged { path });
+}
+
+/// The breakpoints of `path`, or of every open workspace root, with
+/// whether a running debug session has verified them.
+#[tauri::command]
+pub async fn list_breakpoints(
+    path: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<BreakpointView>, String> {
+    let scope = windows.scope(window.label());
+    let (roots, path) = match path {
+        Some(path) => {
+            let path = workspace::authorize(&windows, &window, &path)?;
+            let root = scope.workspace.root_for(Some(&path));
+            (root.into_iter().collect(), Some(path))
+        }
+        None => (scope.workspace.roots(), None),
+    };
+    let mut breakpoints = Vec::new();
+    for root in roots {
+        breakpoints.extend(load(&app, &root)?);
+    }
+    if let Some(path) = path {
+        let path = path.to_string_lossy();
+        breakpoints.retain(|b| b.path == path);
+    }
+    breakpoints.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    Ok(breakpoints
+        .into_iter()
+        .map(|b| scope.breakpoints.view(b))
+        .collect())
+}
+
+/// Adds a breakpoint, or changes the one `spec` matches. The frontend
+/// sends the file's breakpoints to running sessions with `setBreakpoints`
+/// as usual; the responses passing through `debug_send` mark them
+/// verified.
+#[tauri::command]
+pub async fn set_breakpoint(
+    spec: BreakpointSpec,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<BreakpointView, String> {
+    if spec.line == 0 {
+        return Err("Breakpoint lines start at 1".to_string());
+    }
+    let path = workspace::authorize(&windows, &window, &spec.path)?;
+    let path_text = path.to_string_lossy().to_string();
+    let scope = windows.scope(window.label());
+    let breakpoint = update(&app, &scope, &path, |breakpoints| {
+        let existing = breakpoints.iter_mut().find(|b| match &spec.id {
+            Some(id) => &b.id == id,
+            None => b.path == path_text && b.line == spec.line,
+        });
+        let breakpoint = Breakpoint {
+            id: existing
+                .as_ref()
+                .map(|b| b.id.clone())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            path: path_text.clone(),
+            line: spec.line,
+            column: spec.column,
+            enabled: spec
+                .enabled
+                .or(existing.as_ref().map(|b| b.enabled))
+                .unwrap_or(true),
+            condition: spec.condition.filter(|text| !text.trim().is_empty()),
+            hit_condition: spec.hit_condition.filter(|text| !text.trim().is_empty()),
+            log_message: spec.log_message.filter(|text| !text.is_empty()),
+        };
+        match existing {
+            Some(existing) => *existing = breakpoint.clone(),
+            None => breakpoints.push(breakpoint.clone()),
+        }
+        (breakpoint, true)
+    })?;
+    notify(&window, Some(path_text));
+    Ok(scope.breakpoints.view(breakpoint))
+}
+
+/// Returns whether there was a breakpoint to remove.
+#[tauri::command]
+pub async fn remove_breakpoint(
+    id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let scope = windows.scope(window.label());
+    for root in scope.workspace.roots() {
+        let removed = update(&app, &scope, &root, |breakpoints| {
+            match breakpoints.iter().position(|b| b.id == id) {
+                Some(index) => (Some(breakpoints.remove(index)), true),
+                None => (None, false),
+            }
+        })?;
+        if let Some(removed) = removed {
+            notify(&window, Some(removed.path));
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Removes the breakpoints of `path`, or all of them. Returns how many
+/// were removed.
+#[tauri::command]
+pub async fn clear_breakpoints(
+    path: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<usize, String> {
+    let scope = windows.scope(window.label());
+    let path = path
+        .map(|path| workspace::authorize(&windows, &window, &path))
+        .transpose()?;
+    let roots = match &path {
+        Some(path) => scope.workspace.root_for(Some(path)).into_iter().collect(),
+        None => scope.workspace.roots(),
+    };
+    let text = path.as_ref().map(|path| path.to_string_lossy().to_string());
+    let mut removed = 0;
+    for root in roots {
+        removed += update(&app, &scope, &root, |breakpoints| {
+            let before = breakpoints.len();
+            breakpoints.retain(|b| text.as_ref().is_some_and(|path| &b.path != path));
+            let removed = before - breakpoints.len();
+            (removed, removed > 0)
+        })?;
+    }
+    if removed > 0 {
+        notify(&window, text);
+    }
+    Ok(removed)
+}
+
+/// The `setBreakpoints` arguments for `path`'s enabled breakpoints, ready
+/// to send to an adapter.
+#[tauri::command]
+pub async fn breakpoint_arguments(
+    path: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Value, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let root = windows
+        .scope(window.label())
+        .workspace
+        .root_for(Some(&path))
+        .ok_or_else(|| "No workspace is open".to_string())?;
+    let path = path.to_string_lossy().to_string();
+    let breakpoints: Vec<Value> = load(&app, &root)?
+        .into_iter()
+        .filter(|b| b.enabled && b.path == path)
+        .map(|b| {
+            let mut source = json!({ "line": b.line });
+            for (key, value) in [
+                ("column", b.column.map(Value::from)),
+                ("condition", b.condition.map(Value::from)),
+                ("hitCondition", b.hit_condition.map(Value::from)),
+                ("logMessage", b.log_message.map(Value::from)),
+            ] {
+                if let Some(value) = value {
+                    source[key] = value;
+                }
+            }
+            source
+        })
+        .collect();
+    Ok(json!({
+        "source": { "path": path },
+        "breakpoints": breakpoints,
+    }))
+}
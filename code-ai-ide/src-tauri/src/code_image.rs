@@ -0,0 +1,256 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const DEFAULT_THEME: &str = "base16-ocean.dark";
+const FONT_SIZE: f32 = 14.0;
+/// Advance width of a monospace glyph at `FONT_SIZE`, close enough for the
+/// common code fonts that the layout does not clip.
+const CHAR_WIDTH: f32 = 8.4;
+const LINE_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 20.0;
+const CHROME_HEIGHT: f32 = 36.0;
+const TAB_WIDTH: usize = 4;
+const MAX_LINES: usize = 500;
+
+/// Zero-based, end-exclusive line range, as used by `read_file_range`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LineRange {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CodeImage {
+    pub mime_type: String,
+    /// Base64-encoded image bytes.
+    pub data: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Lays out the highlighted lines as SVG with a title bar and line numbers.
+pub fn render_svg(
+    content: &str,
+    file_name: &str,
+    first_line_number: usize,
+    theme_name: &str,
+) -> Result<(String, u32, u32), String> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = themes
+        .themes
+        .get(theme_name)
+        .ok_or_else(|| format!("Unknown theme: {}", theme_name))?;
+    let syntax = std::path::Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| syntaxes.find_syntax_by_extension(ext))
+        .or_else(|| syntaxes.find_syntax_by_first_line(content))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let background = theme.settings.background.unwrap_or(Color {
+        r: 40,
+        g: 44,
+        b: 52,
+        a: 255,
+    });
+    let foreground = theme.settings.foreground.unwrap_or(Color::WHITE);
+    let gutter = theme.settings.gutter_foreground.unwrap_or(Color {
+        r: 110,
+        g: 118,
+        b: 129,
+        a: 255,
+    });
+
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| line.replace('\t', &" ".repeat(TAB_WIDTH)))
+        .collect();
+    let last_number = first_line_number + lines.len().saturating_sub(1);
+    let gutter_chars = last_number.to_string().len();
+    let gutter_width = gutter_chars as f32 * CHAR_WIDTH + PADDING;
+    let longest = lines
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(20);
+
+    let width = (PADDING * 2.0 + gutter_width + longest as f32 * CHAR_WIDTH).ceil() as u32;
+    let height = (CHROME_HEIGHT + PADDING * 2.0 + lines.len() as f32 * LINE_HEIGHT).ceil() as u32;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="'JetBrains Mono', 'Fira Code', Menlo, Consolas, monospace" font-size="{fs}">"#,
+        w = width,
+        h = height,
+        fs = FONT_SIZE
+    );
+    let _ = write!(
+        svg,
+        r#"<rect width="{}" height="{}" rx="10" fill="{}"/>"#,
+        width,
+        height,
+        hex(background)
+    );
+    for (i, color) in ["#ff5f57", "#febc2e", "#28c840"].iter().enumerate() {
+        let _ = write!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="6" fill="{}"/>"#,
+            PADDING + i as f32 * 20.0,
+            CHROME_HEIGHT / 2.0,
+            color
+        );
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" fill="{}" text-anchor="middle" opacity="0.7">{}</text>"#,
+        width as f32 / 2.0,
+        CHROME_HEIGHT / 2.0 + 5.0,
+        hex(foreground),
+        escape_xml(file_name)
+    );
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for (index, line) in lines.iter().enumerate() {
+        let y = CHROME_HEIGHT + PADDING + (index as f32 + 0.75) * LINE_HEIGHT;
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}" text-anchor="end">{}</text>"#,
+            PADDING + gutter_width - PADDING / 2.0,
+            y,
+            hex(gutter),
+            first_line_number + index
+        );
+
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" xml:space="preserve">"#,
+            PADDING + gutter_width,
+            y
+        );
+        let with_newline = format!("{}\n", line);
+        let regions = highlighter
+            .highlight_line(&with_newline, &syntaxes)
+            .map_err(|e| format!("Failed to highlight code: {}", e))?;
+        for (style, text) in regions {
+            let text = text.trim_end_matches('\n');
+            if text.is_empty() {
+                continue;
+            }
+            let _ = write!(
+                svg,
+                r#"<tspan fill="{}">{}</tspan>"#,
+                hex(style.foreground),
+                escape_xml(text)
+            );
+        }
+        svg.push_str("</text>");
+    }
+    svg.push_str("</svg>");
+
+    Ok((svg, width, height))
+}
+
+fn rasterize(svg: &str) -> Result<Vec<u8>, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default(), &fontdb)
+        .map_err(|e| format!("Failed to parse rendered SVG: {}", e))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| "Image is too large to render".to_string())?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
+}
+
+#[tauri::command]
+pub async fn render_code_image(
+    path: String,
+    range: Option<LineRange>,
+    theme: Option<String>,
+    format: Option<ImageFormat>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<CodeImage, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let total = content.lines().count();
+    let range = range.unwrap_or(LineRange {
+        start_line: 0,
+        end_line: total,
+    });
+    let start = range.start_line.min(total);
+    let end = range.end_line.min(total).max(start);
+    if end - start > MAX_LINES {
+        return Err(format!("Cannot render more than {} lines", MAX_LINES));
+    }
+
+    let snippet = content
+        .lines()
+        .skip(start)
+        .take(end - start)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let theme = theme.unwrap_or_else(|| DEFAULT_THEME.to_string());
+    let format = format.unwrap_or(ImageFormat::Png);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (svg, width, height) = render_svg(&snippet, &file_name, start + 1, &theme)?;
+        let (mime_type, bytes) = match format {
+            ImageFormat::Svg => ("image/svg+xml", svg.into_bytes()),
+            ImageFormat::Png => ("image/png", rasterize(&svg)?),
+        };
+        Ok(CodeImage {
+            mime_type: mime_type.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            width,
+            height,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to render code image: {}", e))?
+}
+
+#[tauri::command]
+pub async fn list_code_image_themes() -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = ThemeSet::load_defaults().themes.keys().cloned().collect();
+    names.sort();
+    Ok(names)
+}
@@ -0,0 +1,651 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_data;
+
+const KEYBINDINGS_FILE: &str = "keybindings.json";
+
+/// (id, title, category, backend command, default key)
+type Builtin = (
+    &'static str,
+    &'static str,
+    &'static str,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+/// Actions the backend provides. The frontend, and later extensions, add
+/// their own with `register_commands`.
+const BUILTIN_COMMANDS: &[Builtin] = &[
+    (
+        "file.open",
+        "Open File…",
+        "File",
+        Some("open_file_dialog"),
+        Some("ctrl+o"),
+    ),
+    (
+        "file.openFolder",
+        "Open Folder…",
+        "File",
+        Some("open_folder_dialog"),
+        Some("ctrl+k ctrl+o"),
+    ),
+    (
+        "file.save",
+        "Save",
+        "File",
+        Some("document_save"),
+        Some("ctrl+s"),
+    ),
+    (
+        "file.saveAs",
+        "Save As…",
+        "File",
+        Some("save_file_dialog"),
+        Some("ctrl+shift+s"),
+    ),
+    (
+        "file.newFile",
+        "New File",
+        "File",
+        Some("create_file"),
+        Some("ctrl+n"),
+    ),
+    (
+        "file.newFolder",
+        "New Folder",
+        "File",
+        Some("create_directory"),
+        None,
+    ),
+    ("file.delete", "Delete", "File", Some("delete_file"), None),
+    (
+        "file.duplicate",
+        "Duplicate",
+        "File",
+        Some("duplicate_path"),
+        None,
+    ),
+    (
+        "file.revealInFileManager",
+        "Reveal in File Manager",
+        "File",
+        Some("reveal_in_file_manager"),
+        Some("shift+alt+r"),
+    ),
+    (
+        "file.openWithSystem",
+        "Open with System Application",
+        "File",
+        Some("open_with_system"),
+        None,
+    ),
+    (
+        "file.convertLineEndings",
+        "Change End of Line Sequence",
+        "File",
+        Some("convert_line_endings"),
+        None,
+    ),
+    (
+        "file.compare",
+        "Compare Files",
+        "File",
+        Some("compare_files"),
+        None,
+    ),
+    (
+        "file.localHistory",
+        "Show Local History",
+        "File",
+        Some("list_local_history"),
+        None,
+    ),
+    (
+        "file.quickOpen",
+        "Go to File…",
+        "Go",
+        Some("fuzzy_find_files"),
+        Some("ctrl+p"),
+    ),
+    (
+        "workspace.addFolder",
+        "Add Folder to Workspace…",
+        "Workspace",
+        Some("add_workspace_root"),
+        None,
+    ),
+    (
+        "workspace.trust",
+        "Trust Workspace",
+        "Workspace",
+        Some("request_workspace_trust"),
+        None,
+    ),
+    (
+        "workspace.textHealth",
+        "Check Text Health",
+        "Workspace",
+        Some("audit_text_health"),
+        None,
+    ),
+    (
+        "search.findInFiles",
+        "Find in Files",
+        "Search",
+        Some("search_workspace"),
+        Some("ctrl+shift+f"),
+    ),
+    (
+        "search.replaceInFiles",
+        "Replace in Files",
+        "Search",
+        Some("replace_in_workspace"),
+        Some("ctrl+shift+h"),
+    ),
+    (
+        "editor.format",
+        "Format Document",
+        "Editor",
+        Some("format_document"),
+        Some("shift+alt+f"),
+    ),
+    (
+        "editor.lint",
+        "Run Linter",
+        "Editor",
+        Some("run_linter"),
+        None,
+    ),
+    (
+        "editor.rename",
+        "Rename Symbol",
+        "Editor",
+        Some("rename_symbol_textual"),
+        Some("f2"),
+    ),
+    (
+        "editor.outline",
+        "Go to Symbol in File…",
+        "Go",
+        Some("get_document_outline"),
+        Some("ctrl+shift+o"),
+    ),
+    (
+        "editor.codeImage",
+        "Export Code as Image",
+        "Editor",
+        Some("render_code_image"),
+        None,
+    ),
+    (
+        "git.status",
+        "Refresh Source Control",
+        "Git",
+        Some("git_status"),
+        None,
+    ),
+    ("git.stage", "Stage Changes", "Git", Some("git_stage"), None),
+    (
+        "git.unstage",
+        "Unstage Changes",
+        "Git",
+        Some("git_unstage"),
+        None,
+    ),
+    (
+        "git.commit",
+        "Commit",
+        "Git",
+        Some("git_commit"),
+        Some("ctrl+enter"),
+    ),
+    (
+        "git.discard",
+        "Discard Changes",
+        "Git",
+        Some("git_discard"),
+        None,
+    ),
+    (
+        "git.checkout",
+        "Checkout to…",
+        "Git",
+        Some("git_checkout"),
+        None,
+    ),
+    (
+        "git.createBranch",
+        "Create Branch…",
+        "Git",
+        Some("git_create_branch"),
+        None,
+    ),
+    (
+        "git.deleteBranch",
+        "Delete Branch…",
+        "Git",
+        Some("git_delete_branch"),
+        None,
+    ),
+    ("git.pull", "Pull", "Git", Some("git_pull"), None),
+    ("git.push", "Push", "Git", Some("git_push"), None),
+    ("git.fetch", "Fetch", "Git", Some("git_fetch"), None),
+    ("git.stash", "Stash", "Git", Some("git_stash_save"), None),
+    (
+        "git.stashPop",
+        "Pop Stash",
+        "Git",
+        Some("git_stash_pop"),
+        None,
+    ),
+    ("git.log", "View History", "Git", Some("git_log"), None),
+    ("git.blame", "Toggle Blame", "Git", Some("git_blame"), None),
+    (
+        "git.clone",
+        "Clone Repository…",
+        "Git",
+        Some("git_clone"),
+        None,
+    ),
+    (
+        "git.createTag",
+        "Create Tag…",
+        "Git",
+        Some("git_create_tag"),
+        None,
+    ),
+    ("tasks.run", "Run Task…", "Tasks", Some("run_task"), None),
+    (
+        "tasks.runBuild",
+        "Run Build Task",
+        "Tasks",
+        Some("run_task"),
+        Some("ctrl+shift+b"),
+    ),
+    (
+        "tasks.list",
+        "Show Tasks",
+        "Tasks",
+        Some("list_tasks"),
+        None,
+    ),
+    (
+        "process.kill",
+        "Terminate Process",
+        "Tasks",
+        Some("kill_process"),
+        None,
+    ),
+    (
+        "devServer.start",
+        "Start Dev Server",
+        "Run",
+        Some("start_dev_server"),
+        None,
+    ),
+    (
+        "devServer.stop",
+        "Stop Dev Server",
+        "Run",
+        Some("stop_dev_server"),
+        None,
+    ),
+    (
+        "preview.start",
+        "Open Preview",
+        "Run",
+        Some("start_preview_server"),
+        None,
+    ),
+    (
+        "lsp.restart",
+        "Restart Language Server",
+        "Language",
+        Some("lsp_start"),
+        None,
+    ),
+    (
+        "lsp.install",
+        "Install Language Server",
+        "Language",
+        Some("ensure_language_server"),
+        None,
+    ),
+    (
+        "settings.open",
+        "Open Settings",
+        "Preferences",
+        Some("get_settings"),
+        Some("ctrl+,"),
+    ),
+    (
+        "keybindings.open",
+        "Open Keyboard Shortcuts",
+        "Preferences",
+        Some("get_keybindings"),
+        Some("ctrl+k ctrl+s"),
+    ),
+    (
+        "commandPalette.show",
+        "Show All Commands",
+        "View",
+        None,
+        Some("ctrl+shift+p"),
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandInfo {
+    pub id: String,
+    pub title: String,
+    pub category: String,
+    /// The backend command to invoke, for builtin actions.
+    #[serde(default)]
+    pub invoke: Option<String>,
+    #[serde(default)]
+    pub default_key: Option<String>,
+    /// Context expression in which the default key applies, e.g.
+    /// `editorFocus`.
+    #[serde(default)]
+    pub when: Option<String>,
+    /// `builtin`, `frontend`, or the id of the extension that added it.
+    #[serde(default)]
+    pub source: String,
+}
+
+/// A user override: binds `key` to `command`, or with `key` unset removes
+/// the command's default binding.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeybindingOverride {
+    pub command: String,
+    pub key: Option<String>,
+    #[serde(default)]
+    pub when: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeybindingSource {
+    Default,
+    User,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Keybinding {
+    pub key: String,
+    pub command: String,
+    pub when: Option<String>,
+    pub source: KeybindingSource,
+    /// Other commands bound to the same key in the same context.
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeybindingsFile {
+    #[serde(default)]
+    overrides: Vec<KeybindingOverride>,
+}
+
+/// Commands registered at runtime plus the persisted keybinding overrides,
+/// shared by every window.
+#[derive(Default)]
+pub struct CommandRegistry {
+    registered: Mutex<HashMap<String, CommandInfo>>,
+    keybindings: Mutex<Option<(PathBuf, KeybindingsFile)>>,
+}
+
+impl CommandRegistry {
+    fn with_keybindings<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut KeybindingsFile) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.keybindings.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_config_path(app, KEYBINDINGS_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+
+    pub fn commands(&self) -> Vec<CommandInfo> {
+        let mut commands: Vec<CommandInfo> = BUILTIN_COMMANDS
+            .iter()
+            .map(|(id, title, category, invoke, key)| CommandInfo {
+                id: id.to_string(),
+                title: title.to_string(),
+                category: category.to_string(),
+                invoke: invoke.map(str::to_string),
+                default_key: key.map(str::to_string),
+                when: None,
+                source: "builtin".to_string(),
+            })
+            .collect();
+        let registered = self.registered.lock().unwrap();
+        commands.extend(
+            registered
+                .values()
+                .filter(|command| !BUILTIN_COMMANDS.iter().any(|b| b.0 == command.id))
+                .cloned(),
+        );
+        commands.sort_by(|a, b| (&a.category, &a.title).cmp(&(&b.category, &b.title)));
+        commands
+    }
+
+    /// Adds or updates commands. Builtin ids cannot be taken over.
+    pub fn register(&self, commands: Vec<CommandInfo>) -> Result<(), String> {
+        let mut registered = self.registered.lock().unwrap();
+        for mut command in commands {
+            if BUILTIN_COMMANDS.iter().any(|b| b.0 == command.id) {
+                return Err(format!("Command id is reserved: {}", command.id));
+            }
+            if let Some(key) = &command.default_key {
+                command.default_key = Some(normalize_key(key)?);
+            }
+            if command.source.is_empty() {
+                command.source = "frontend".to_string();
+            }
+            registered.insert(command.id.clone(), command);
+        }
+        Ok(())
+    }
+
+    /// Drops every command contributed by `source`, e.g. an extension that
+    /// was disabled.
+    pub fn unregister_source(&self, source: &str) {
+        self.registered
+            .lock()
+            .unwrap()
+            .retain(|_, command| command.source != source);
+    }
+}
+
+/// Canonical form of a key sequence: lower case, modifiers in a fixed
+/// order, chords separated by one space. `Shift+Ctrl+P` -> `ctrl+shift+p`.
+fn normalize_key(key: &str) -> Result<String, String> {
+    const MODIFIERS: [&str; 4] = ["ctrl", "shift", "alt", "meta"];
+    let chords = key
+        .split_whitespace()
+        .map(|chord| {
+            let parts: Vec<String> = chord.split('+').map(str::to_ascii_lowercase).collect();
+            let (base, modifiers) = parts
+                .split_last()
+                .filter(|(base, _)| !base.is_empty())
+                .ok_or_else(|| format!("Invalid key: {}", key))?;
+            let mut found = Vec::new();
+            for modifier in modifiers {
+                let modifier = match modifier.as_str() {
+                    "cmd" | "win" | "super" => "meta",
+                    "control" => "ctrl",
+                    "option" => "alt",
+                    other => other,
+                };
+                if !MODIFIERS.contains(&modifier) {
+                    return Err(format!("Unknown modifier '{}' in {}", modifier, key));
+                }
+                found.push(modifier);
+            }
+            let mut chord: Vec<&str> = MODIFIERS
+                .iter()
+                .copied()
+                .filter(|m| found.contains(m))
+                .collect();
+            chord.push(base.as_str());
+            Ok(chord.join("+"))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if chords.is_empty() {
+        return Err("Key must not be empty".to_string());
+    }
+    Ok(chords.join(" "))
+}
+
+/// Defaults with the user's overrides applied, in VS Code's manner: a user
+/// entry for a command replaces that command's default binding.
+fn resolve_keybindings(
+    commands: &[CommandInfo],
+    overrides: &[KeybindingOverride],
+) -> Vec<Keybinding> {
+    let mut bindings: Vec<Keybinding> = commands
+        .iter()
+        .filter(|command| !overrides.iter().any(|o| o.command == command.id))
+        .filter_map(|command| {
+            Some(Keybinding {
+                key: normalize_key(command.default_key.as_deref()?).ok()?,
+                command: command.id.clone(),
+                when: command.when.clone(),
+                source: KeybindingSource::Default,
+                conflicts: Vec::new(),
+            })
+        })
+        .collect();
+    bindings.extend(overrides.iter().filter_map(|o| {
+        Some(Keybinding {
+            key: o.key.clone()?,
+            command: o.command.clone(),
+            when: o.when.clone(),
+            source: KeybindingSource::User,
+            conflicts: Vec::new(),
+        })
+    }));
+
+    let keys: Vec<(String, Option<String>, String)> = bindings
+        .iter()
+        .map(|b| (b.key.clone(), b.when.clone(), b.command.clone()))
+        .collect();
+    for binding in &mut bindings {
+        binding.conflicts = keys
+            .iter()
+            .filter(|(key, when, command)| {
+                *key == binding.key && *when == binding.when && *command != binding.command
+            })
+            .map(|(_, _, command)| command.clone())
+            .collect();
+    }
+    bindings.sort_by(|a, b| a.key.cmp(&b.key));
+    bindings
+}
+
+pub fn broadcast(app: &AppHandle, event: &str) {
+    let _ = app.emit_all(event, ());
+}
+
+#[tauri::command]
+pub async fn list_commands(
+    registry: State<'_, CommandRegistry>,
+) -> Result<Vec<CommandInfo>, String> {
+    Ok(registry.commands())
+}
+
+/// Adds or updates commands contributed by the frontend.
+#[tauri::command]
+pub async fn register_commands(
+    commands: Vec<CommandInfo>,
+    app: AppHandle,
+    registry: State<'_, CommandRegistry>,
+) -> Result<(), String> {
+    registry.register(commands)?;
+    broadcast(&app, "commands-changed");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unregister_commands(
+    ids: Vec<String>,
+    app: AppHandle,
+    registry: State<'_, CommandRegistry>,
+) -> Result<(), String> {
+    {
+        let mut registered = registry.registered.lock().unwrap();
+        for id in ids {
+            registered.remove(&id);
+        }
+    }
+    broadcast(&app, "commands-changed");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_keybindings(
+    app: AppHandle,
+    registry: State<'_, CommandRegistry>,
+) -> Result<Vec<Keybinding>, String> {
+    let commands = registry.commands();
+    registry.with_keybindings(&app, |file| {
+        (resolve_keybindings(&commands, &file.overrides), false)
+    })
+}
+
+/// Binds `key` to `command`, replacing its default; `key` unset removes
+/// the binding altogether.
+#[tauri::command]
+pub async fn set_keybinding(
+    command: String,
+    key: Option<String>,
+    when: Option<String>,
+    app: AppHandle,
+    registry: State<'_, CommandRegistry>,
+) -> Result<(), String> {
+    if !registry.commands().iter().any(|c| c.id == command) {
+        return Err(format!("Unknown command: {}", command));
+    }
+    let key = key.as_deref().map(normalize_key).transpose()?;
+    let entry = KeybindingOverride { command, key, when };
+    registry.with_keybindings(&app, |file| {
+        file.overrides.retain(|o| o.command != entry.command);
+        file.overrides.push(entry);
+        ((), true)
+    })?;
+    broadcast(&app, "keybindings-changed");
+    Ok(())
+}
+
+/// Drops the user's overrides for `command`, restoring its default.
+#[tauri::command]
+pub async fn reset_keybinding(
+    command: String,
+    app: AppHandle,
+    registry: State<'_, CommandRegistry>,
+) -> Result<(), String> {
+    let changed = registry.with_keybindings(&app, |file| {
+        let before = file.overrides.len();
+        file.overrides.retain(|o| o.command != command);
+        let changed = file.overrides.len() != before;
+        (changed, changed)
+    })?;
+    if changed {
+        broadcast(&app, "keybindings-changed");
+    }
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, ChangeTag, DiffOp, DiffTag, TextDiff};
+use std::fs;
+use std::time::{Duration, Instant};
+use tauri::{State, Window};
+
+use crate::encoding;
+use crate::git::diff::DiffLineKind;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Past this the diff settles for a less minimal result rather than hang.
+const DIFF_DEADLINE: Duration = Duration::from_secs(5);
+/// Word refinement of very long lines costs more than it shows.
+const MAX_REFINE_LINE_LEN: usize = 4_000;
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Patience,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    pub algorithm: DiffAlgorithm,
+    /// Lines differing only in whitespace compare equal.
+    pub ignore_whitespace: bool,
+    /// Mark the changed words within modified lines.
+    pub word_diff: bool,
+    pub context_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            algorithm: DiffAlgorithm::Myers,
+            ignore_whitespace: false,
+            word_diff: true,
+            context_lines: DEFAULT_CONTEXT_LINES,
+        }
+    }
+}
+
+/// Changed part of a line, in UTF-16 columns, end exclusive.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ChangedSpan {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedLine {
+    pub kind: DiffLineKind,
+    /// Without the line break.
+    pub content: String,
+    /// One-based line numbers on each side; `None` on the side the line
+    /// does not exist in.
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    /// Words that changed, for removed and added lines paired with a
+    /// counterpart on the other side.
+    pub changes: Vec<ChangedSpan>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<ComparedLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonResult {
+    pub identical: bool,
+    pub added: u32,
+    pub removed: u32,
+    pub hunks: Vec<ComparedHunk>,
+}
+
+fn algorithm(options: &DiffOptions) -> Algorithm {
+    match options.algorithm {
+        DiffAlgorithm::Myers => Algorithm::Myers,
+        DiffAlgorithm::Patience => Algorithm::Patience,
+    }
+}
+
+/// What a line is compared by: the line itself, or with whitespace runs
+/// collapsed and the ends trimmed.
+fn line_key(line: &str, ignore_whitespace: bool) -> String {
+    if ignore_whitespace {
+        line.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        line.to_string()
+    }
+}
+
+fn strip_eol(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
+}
+
+/// Changed spans of `old` and `new`, two versions of one line.
+fn refine(old: &str, new: &str, options: &DiffOptions) -> (Vec<ChangedSpan>, Vec<ChangedSpan>) {
+    if old.len() > MAX_REFINE_LINE_LEN || new.len() > MAX_REFINE_LINE_LEN {
+        return (Vec::new(), Vec::new());
+    }
+    let diff = TextDiff::configure()
+        .algorithm(algorithm(options))
+        .diff_unicode_words(old, new);
+    let (mut old_spans, mut new_spans) = (Vec::new(), Vec::new());
+    let (mut old_col, mut new_col) = (0u32, 0u32);
+    for change in diff.iter_all_changes() {
+        let width = change.value().encode_utf16().count() as u32;
+        let whitespace = change.value().trim().is_empty();
+        match change.tag() {
+            ChangeTag::Equal => {
+                old_col += width;
+                new_col += width;
+            }
+            ChangeTag::Delete => {
+                if !(whitespace && options.ignore_whitespace) {
+                    push_span(&mut old_spans, old_col, old_col + width);
+                }
+                old_col += width;
+            }
+            ChangeTag::Insert => {
+                if !(whitespace && options.ignore_whitespace) {
+                    push_span(&mut new_spans, new_col, new_col + width);
+                }
+                new_col += width;
+            }
+        }
+    }
+    (old_spans, new_spans)
+}
+
+/// Adjacent changed words merge into one span.
+fn push_span(spans: &mut Vec<ChangedSpan>, start: u32, end: u32) {
+    match spans.last_mut() {
+        Some(last) if last.end == start => last.end = end,
+        _ => spans.push(ChangedSpan { start, end }),
+    }
+}
+
+pub fn compare(left: &str, right: &str, options: &DiffOptions) -> ComparisonResult {
+    let old_lines: Vec<&str> = left.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = right.split_inclusive('\n').collect();
+    let old_keys: Vec<String> = old_lines
+        .iter()
+        .map(|line| line_key(line, options.ignore_whitespace))
+        .collect();
+    let new_keys: Vec<String> = new_lines
+        .iter()
+        .map(|line| line_key(line, options.ignore_whitespace))
+        .collect();
+
+    let ops = similar::capture_diff_slices_deadline(
+        algorithm(options),
+        &old_keys,
+        &new_keys,
+        Some(Instant::now() + DIFF_DEADLINE),
+    );
+    let (mut added, mut removed) = (0, 0);
+    let mut hunks = Vec::new();
+    for group in similar::group_diff_ops(ops, options.context_lines) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        let mut lines = Vec::new();
+        for op in &group {
+            compare_op(op, &old_lines, &new_lines, options, &mut lines);
+        }
+        added += lines
+            .iter()
+            .filter(|l| l.kind == DiffLineKind::Added)
+            .count() as u32;
+        removed += lines
+            .iter()
+            .filter(|l| l.kind == DiffLineKind::Removed)
+            .count() as u32;
+        hunks.push(ComparedHunk {
+            old_start: old_range.start as u32 + 1,
+            old_lines: old_range.len() as u32,
+            new_start: new_range.start as u32 + 1,
+            new_lines: new_range.len() as u32,
+            lines,
+        });
+    }
+
+    ComparisonResult {
+        identical: hunks.is_empty(),
+        added,
+        removed,
+        hunks,
+    }
+}
+
+fn compare_op(
+    op: &DiffOp,
+    old_lines: &[&str],
+    new_lines: &[&str],
+    options: &DiffOptions,
+    out: &mut Vec<ComparedLine>,
+) {
+    let (old_range, new_range) = (op.old_range(), op.new_range());
+    let line =
+        |kind, content: &str, old_line: Option<usize>, new_line: Option<usize>| ComparedLine {
+            kind,
+            content: strip_eol(content).to_string(),
+            old_line: old_line.map(|i| i as u32 + 1),
+            new_line: new_line.map(|i| i as u32 + 1),
+            changes: Vec::new(),
+        };
+    match op.tag() {
+        DiffTag::Equal => {
+            // With whitespace ignored the sides can differ; show the new one.
+            for (old, new) in old_range.zip(new_range) {
+                out.push(line(
+                    DiffLineKind::Context,
+                    new_lines[new],
+                    Some(old),
+                    Some(new),
+                ));
+            }
+        }
+        DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+            let mut removed: Vec<ComparedLine> = old_range
+                .clone()
+                .map(|i| line(DiffLineKind::Removed, old_lines[i], Some(i), None))
+                .collect();
+            let mut added: Vec<ComparedLine> = new_range
+                .clone()
+                .map(|i| line(DiffLineKind::Added, new_lines[i], None, Some(i)))
+                .collect();
+            if options.word_diff {
+                // Lines are paired in order; the surplus on either side is
+                // wholly new or wholly gone.
+                for (old, new) in removed.iter_mut().zip(added.iter_mut()) {
+                    let (old_spans, new_spans) = refine(&old.content, &new.content, options);
+                    old.changes = old_spans;
+                    new.changes = new_spans;
+                }
+            }
+            out.append(&mut removed);
+            out.append(&mut added);
+        }
+    }
+}
+
+/// Structured diff of two texts, for compare views, history and previews
+/// of proposed edits.
+#[tauri::command]
+pub async fn compute_diff(
+    left: String,
+    right: String,
+    options: Option<DiffOptions>,
+) -> Result<ComparisonResult, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || compare(&left, &right, &options))
+        .await
+        .map_err(|e| format!("Diff failed: {}", e))
+}
+
+/// Like `compute_diff`, reading both sides from disk.
+#[tauri::command]
+pub async fn compare_files(
+    left_path: String,
+    right_path: String,
+    options: Option<DiffOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<ComparisonResult, String> {
+    let read = |path: &str| {
+        let path = workspace::authorize(&windows, &window, path)?;
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+        Ok::<_, String>(encoding::decode(&bytes).content)
+    };
+    let (left, right) = (read(&left_path)?, read(&right_path)?);
+    compute_diff(left, right, options).await
+}
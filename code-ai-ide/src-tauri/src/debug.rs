@@ -0,0 +1,868 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::jsonc::read_jsonc;
+use crate::lsp;
+use crate::process::{self, ExecOptions, ProcessHooks, ProcessKind};
+use crate::project::{self, ProjectKind};
+use crate::tasks;
+use crate::watches;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+/// Launch configurations, relative to the workspace root. The format is
+/// VS Code's `launch.json`.
+const LAUNCH_FILE: &str = ".codeai/launch.json";
+
+/// How long to wait for a debug adapter that listens on a port to accept
+/// the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a request the backend sends itself may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Where the `seq` of requests the backend sends itself starts, far above
+/// what the client's own requests reach, so responses can be told apart.
+const BACKEND_SEQ: i64 = 1 << 40;
+
+/// A debug configuration, named uniquely in its file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchConfig {
+    pub name: String,
+    /// The debugger, such as `lldb`, `debugpy`, `go` or `node`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// `launch` or `attach`.
+    #[serde(default = "default_request")]
+    pub request: String,
+    /// Everything else: `program`, `args`, `cwd`, `env`, `preLaunchTask`,
+    /// and whatever the adapter understands.
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+fn default_request() -> String {
+    "launch".to_string()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LaunchFile {
+    #[serde(default)]
+    configurations: Vec<LaunchConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchConfigEntry {
+    /// What `start_debug` takes; the configuration's name.
+    pub id: String,
+    pub config: LaunchConfig,
+    /// Made up for the detected project types rather than read from the
+    /// workspace; saving it makes it the workspace's.
+    pub generated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugSessionInfo {
+    /// Also the id of the adapter's process in `list_processes`, when the
+    /// session started one.
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub request: String,
+    pub root: String,
+    /// The configuration with its variables substituted, as the client
+    /// sends it in the `launch` or `attach` request.
+    pub configuration: Value,
+}
+
+/// Emitted as `debug-message` for everything the adapter sends: responses,
+/// events and reverse requests.
+#[derive(Debug, Clone, Serialize)]
+struct DebugMessage {
+    session_id: String,
+    message: Value,
+}
+
+/// Emitted as `debug-exit` when a session ends for any reason.
+#[derive(Debug, Clone, Serialize)]
+struct DebugExit {
+    session_id: String,
+}
+
+type Reader = Box<dyn Read + Send>;
+type Writer = Box<dyn Write + Send>;
+
+struct DebugSession {
+    writer: Arc<Mutex<Writer>>,
+    /// The adapter's process, unless the session connected to a running
+    /// `debugServer`.
+    process_id: Option<String>,
+    socket: Option<TcpStream>,
+    root: PathBuf,
+}
+
+/// Debug sessions of a window, keyed by session id.
+pub struct DebugSessions {
+    sessions: Mutex<HashMap<String, DebugSession>>,
+    /// Requests the backend sent itself, keyed by session id and `seq`.
+    pending: Mutex<HashMap<(String, i64), Sender<Value>>>,
+    next_seq: AtomicI64,
+}
+
+impl Default for DebugSessions {
+    fn default() -> Self {
+        Self {
+            sessions: Mutex::default(),
+            pending: Mutex::default(),
+            next_seq: AtomicI64::new(BACKEND_SEQ),
+        }
+    }
+}
+
+impl DebugSessions {
+    fn writer(&self, id: &str) -> Option<Arc<Mutex<Writer>>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|session| session.writer.clone())
+    }
+
+    /// The workspace root the session was started in.
+    pub fn root(&self, id: &str) -> Option<PathBuf> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|session| session.root.clone())
+    }
+
+    fn remove(&self, id: &str) -> Option<DebugSession> {
+        // Waiting requests fail once their senders are dropped.
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|(session, _), _| session != id);
+        self.sessions.lock().unwrap().remove(id)
+    }
+
+    /// Hands a response to a request the backend sent itself to whoever
+    /// waits for it. Returns whether it was one, so it is not forwarded to
+    /// the client, which never sent it.
+    fn resolve(&self, id: &str, message: &Value) -> bool {
+        if message["type"] != "response" {
+            return false;
+        }
+        let Some(seq) = message["request_seq"]
+            .as_i64()
+            .filter(|seq| *seq >= BACKEND_SEQ)
+        else {
+            return false;
+        };
+        if let Some(sender) = self.pending.lock().unwrap().remove(&(id.to_string(), seq)) {
+            let _ = sender.send(message.clone());
+        }
+        true
+    }
+
+    /// Closes the connections; adapter processes go with the window's
+    /// other processes.
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+        for (_, session) in self.sessions.lock().unwrap().drain() {
+            if let Some(socket) = session.socket {
+                let _ = socket.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+/// How to start the debug adapter for a configuration type.
+struct Adapter {
+    command: String,
+    /// `{port}` is replaced with a free port for adapters that only listen
+    /// on TCP.
+    args: Vec<String>,
+    tcp: bool,
+}
+
+fn adapter(kind: &str) -> Option<Adapter> {
+    let adapter = |command: &str, args: &[&str], tcp| Adapter {
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        tcp,
+    };
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    Some(match kind {
+        "lldb" | "lldb-dap" => adapter("lldb-dap", &[], false),
+        "debugpy" | "python" => adapter(python, &["-m", "debugpy.adapter"], false),
+        "go" => adapter("dlv", &["dap", "--listen", "127.0.0.1:{port}"], true),
+        "node" | "pwa-node" => adapter("js-debug-adapter", &["{port}", "127.0.0.1"], true),
+        _ => return None,
+    })
+}
+
+/// Whether `start_debug` knows how to run configurations of `kind`.
+pub fn has_adapter(kind: &str) -> bool {
+    adapter(kind).is_some()
+}
+
+fn read_launch_file(root: &Path) -> Result<LaunchFile, String> {
+    let path = root.join(LAUNCH_FILE);
+    if !path.is_file() {
+        return Ok(LaunchFile::default());
+    }
+    serde_json::from_value(read_jsonc(&path)?)
+        .map_err(|e| format!("Invalid {}: {}", LAUNCH_FILE, e))
+}
+
+fn write_launch_file(root: &Path, file: &LaunchFile) -> Result<(), String> {
+    let path = root.join(LAUNCH_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    app_data::save_json(&path, file)
+}
+
+/// Appends `configs` to the launch configurations of `root`, skipping
+/// names the file has already. Returns the names added.
+pub(crate) fn add_launch_configs(
+    root: &Path,
+    configs: Vec<LaunchConfig>,
+) -> Result<Vec<String>, String> {
+    let mut file = read_launch_file(root)?;
+    let mut added = Vec::new();
+    for config in configs {
+        if file.configurations.iter().any(|c| c.name == config.name) {
+            continue;
+        }
+        added.push(config.name.clone());
+        file.configurations.push(config);
+    }
+    if !added.is_empty() {
+        write_launch_file(root, &file)?;
+    }
+    Ok(added)
+}
+
+fn config(name: &str, kind: &str, properties: Value) -> LaunchConfig {
+    LaunchConfig {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        request: default_request(),
+        properties: match properties {
+            Value::Object(properties) => properties,
+            _ => Map::new(),
+        },
+    }
+}
+
+/// A configuration for each detected project that can be debugged as it
+/// is: the Cargo binary, the package's main script, the current Python
+/// file, the Go main package.
+fn generate(root: &Path) -> Vec<LaunchConfig> {
+    let mut configs = Vec::new();
+    for detected in project::detect_projects(root) {
+        match detected.kind {
+            ProjectKind::Cargo => {
+                let (Some(name), true) = (&detected.name, root.join("src/main.rs").is_file())
+                else {
+                    continue;
+                };
+                let binary = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+                configs.push(config(
+                    &format!("Debug {}", name),
+                    "lldb",
+                    json!({
+                        "program": format!("${{workspaceFolder}}/target/debug/{}", binary),
+                        "args": [],
+                        "cwd": "${workspaceFolder}",
+                        "preLaunchTask": "cargo:build",
+                    }),
+                ));
+            }
+            ProjectKind::Node => {
+                let main = fs::read_to_string(root.join("package.json"))
+                    .ok()
+                    .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+                    .and_then(|manifest| manifest["main"].as_str().map(str::to_string))
+                    .unwrap_or_else(|| "index.js".to_string());
+                if !root.join(&main).is_file() {
+                    continue;
+                }
+                configs.push(config(
+                    &format!("Launch {}", main),
+                    "node",
+                    json!({
+                        "program": format!("${{workspaceFolder}}/{}", main),
+                        "cwd": "${workspaceFolder}",
+                    }),
+                ));
+            }
+            ProjectKind::Python => configs.push(config(
+                "Python: current file",
+                "debugpy",
+                json!({
+                    "program": "${file}",
+                    "cwd": "${workspaceFolder}",
+                    "console": "internalConsole",
+                }),
+            )),
+            ProjectKind::Go => configs.push(config(
+                "Go: launch package",
+                "go",
+                json!({
+                    "mode": "debug",
+                    "program": "${workspaceFolder}",
+                }),
+            )),
+        }
+    }
+    configs
+}
+
+fn entries(root: &Path) -> Result<Vec<LaunchConfigEntry>, String> {
+    let saved = read_launch_file(root)?.configurations;
+    let generated = saved.is_empty();
+    let configs = if generated { generate(root) } else { saved };
+    Ok(configs
+        .into_iter()
+        .map(|config| LaunchConfigEntry {
+            id: config.name.clone(),
+            config,
+            generated,
+        })
+        .collect())
+}
+
+/// What the variables of a launch configuration stand for.
+pub(crate) struct Variables<'a> {
+    pub root: &'a Path,
+    /// The file open in the editor, for `${file}` and its parts.
+    pub file: Option<&'a Path>,
+    /// The process picked to attach to, for `${command:pickProcess}`.
+    pub process_id: Option<u32>,
+}
+
+/// Substitutes VS Code's variables in `text`: `${workspaceFolder}`,
+/// `${file}` and its parts, `${env:NAME}` and so on.
+fn substitute(text: &str, variables: &Variables) -> Result<String, String> {
+    let (root, file) = (variables.root, variables.file);
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("Unterminated variable in {}", text))?;
+        let name = &rest[start + 2..end];
+        let lossy = |path: &Path| path.to_string_lossy().to_string();
+        let current = || file.ok_or_else(|| format!("${{{}}} needs an open file", name));
+        let value = match name {
+            "workspaceFolder" | "workspaceRoot" | "cwd" => lossy(root),
+            "workspaceFolderBasename" => root
+                .file_name()
+                .map(|n| lossy(Path::new(n)))
+                .unwrap_or_default(),
+            "file" => lossy(current()?),
+            "fileBasename" => current()?
+                .file_name()
+                .map(|n| lossy(Path::new(n)))
+                .unwrap_or_default(),
+            "fileBasenameNoExtension" => current()?
+                .file_stem()
+                .map(|n| lossy(Path::new(n)))
+                .unwrap_or_default(),
+            "fileExtname" => current()?
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default(),
+            "fileDirname" => current()?.parent().map(lossy).unwrap_or_default(),
+            "relativeFile" => {
+                let current = current()?;
+                lossy(current.strip_prefix(root).unwrap_or(current))
+            }
+            "pathSeparator" | "/" => std::path::MAIN_SEPARATOR.to_string(),
+            "command:pickProcess" => variables
+                .process_id
+                .map(|pid| pid.to_string())
+                .ok_or("${command:pickProcess} needs a process to attach to")?,
+            "userHome" => tauri::api::path::home_dir()
+                .map(|home| lossy(&home))
+                .unwrap_or_default(),
+            _ => match name.strip_prefix("env:") {
+                Some(variable) => std::env::var(variable).unwrap_or_default(),
+                None => return Err(format!("Unsupported variable ${{{}}}", name)),
+            },
+        };
+        result.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn substitute_value(value: &Value, variables: &Variables) -> Result<Value, String> {
+    Ok(match value {
+        // Adapters expect the process id as a number.
+        Value::String(text) if text == "${command:pickProcess}" => variables
+            .process_id
+            .map(Value::from)
+            .ok_or("${command:pickProcess} needs a process to attach to")?,
+        Value::String(text) => Value::String(substitute(text, variables)?),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| substitute_value(item, variables))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), substitute_value(value, variables)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Runs the task `preLaunchTask` names (by id or label) to completion,
+/// failing if it does.
+fn run_pre_launch_task(
+    window: &Window,
+    scope: &WindowState,
+    root: &Path,
+    task: &str,
+) -> Result<(), String> {
+    let task = tasks::detect_tasks(root)?
+        .into_iter()
+        .find(|t| t.id == task || t.label == task)
+        .ok_or_else(|| format!("Unknown preLaunchTask: {}", task))?;
+    let cmd = task.command_for(window)?;
+    let result = process::run_blocking(scope, cmd, ProcessKind::Task, None, None)?;
+    if result.exit_code == Some(0) {
+        return Ok(());
+    }
+    let tail: Vec<&str> = result.combined.lines().rev().take(20).collect();
+    Err(format!(
+        "preLaunchTask {} failed:\n{}",
+        task.label,
+        tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+    ))
+}
+
+fn free_port() -> Result<u16, String> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to find a free port: {}", e))
+}
+
+/// Connects to an adapter listening on `port`, waiting for it to start
+/// listening while `alive` says it is still running.
+fn connect(port: u16, alive: impl Fn() -> bool) -> Result<TcpStream, String> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if Instant::now() >= deadline || !alive() => {
+                return Err(format!("Failed to connect to the debug adapter: {}", e))
+            }
+            Err(_) => thread::sleep(CONNECT_INTERVAL),
+        }
+    }
+}
+
+fn write_message(writer: &Mutex<Writer>, message: &Value) -> Result<(), String> {
+    let body = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+    let mut writer = writer.lock().unwrap();
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .and_then(|_| writer.write_all(&body))
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write to debug adapter: {}", e))
+}
+
+/// Starts the adapter for `info` (or connects to `debug_server`), records
+/// the session and forwards what the adapter sends as `debug-message`
+/// events until it goes away.
+fn launch(
+    window: Window,
+    scope: Arc<WindowState>,
+    mut info: DebugSessionInfo,
+    debug_server: Option<u16>,
+) -> Result<DebugSessionInfo, String> {
+    let (reader, writer, process_id, socket): (Reader, Writer, _, _) =
+        match (debug_server, adapter(&info.kind)) {
+            (Some(port), _) => {
+                let stream = connect(port, || true)?;
+                let socket = stream.try_clone().map_err(|e| e.to_string())?;
+                let writer = stream.try_clone().map_err(|e| e.to_string())?;
+                (
+                    Box::new(stream) as Reader,
+                    Box::new(writer) as Writer,
+                    None,
+                    Some(socket),
+                )
+            }
+            (None, Some(adapter)) => {
+                let port = if adapter.tcp {
+                    Some(free_port()?)
+                } else {
+                    None
+                };
+                let args: Vec<String> = adapter
+                    .args
+                    .iter()
+                    .map(|arg| arg.replace("{port}", &port.unwrap_or_default().to_string()))
+                    .collect();
+                let windows = window.state::<WindowRegistry>();
+                let cmd = process::prepare(
+                    &adapter.command,
+                    &args,
+                    Some(info.root.clone()),
+                    &ExecOptions::default(),
+                    &window,
+                    &windows,
+                )?;
+                match port {
+                    Some(port) => {
+                        let id = process::start(
+                            window.clone(),
+                            scope.clone(),
+                            cmd,
+                            ProcessKind::Debugger,
+                            None,
+                            None,
+                            ProcessHooks::default(),
+                        )?;
+                        let stream =
+                            connect(port, || scope.processes.is_running(&id)).map_err(|e| {
+                                scope.processes.cancel(&id);
+                                e
+                            })?;
+                        let socket = stream.try_clone().map_err(|e| e.to_string())?;
+                        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+                        (
+                            Box::new(stream) as Reader,
+                            Box::new(writer) as Writer,
+                            Some(id),
+                            Some(socket),
+                        )
+                    }
+                    None => {
+                        let (id, stdin, stdout) = process::start_attached(
+                            window.clone(),
+                            scope.clone(),
+                            cmd,
+                            ProcessKind::Debugger,
+                            ProcessHooks::default(),
+                        )?;
+                        (
+                            Box::new(stdout) as Reader,
+                            Box::new(stdin) as Writer,
+                            Some(id),
+                            None,
+                        )
+                    }
+                }
+            }
+            (None, None) => {
+                return Err(format!(
+            "No debug adapter for {} configurations; set debugServer to the port of a running one",
+            info.kind
+        ))
+            }
+        };
+
+    info.id = process_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    scope.debug_sessions.sessions.lock().unwrap().insert(
+        info.id.clone(),
+        DebugSession {
+            writer: Arc::new(Mutex::new(writer)),
+            process_id: process_id.clone(),
+            socket,
+            root: PathBuf::from(&info.root),
+        },
+    );
+
+    let session_id = info.id.clone();
+    thread::spawn(move || {
+        lsp::read_messages(reader, |message| {
+            if scope.debug_sessions.resolve(&session_id, &message) {
+                return;
+            }
+            scope
+                .breakpoints
+                .observe_message(&window, &session_id, &message);
+            watches::observe_message(&window, &scope, &session_id, &message);
+            let _ = window.emit(
+                "debug-message",
+                DebugMessage {
+                    session_id: session_id.clone(),
+                    message,
+                },
+            );
+        });
+        // The connection closed: the adapter exited or hung up.
+        scope.debug_sessions.remove(&session_id);
+        if let Some(process_id) = process_id {
+            scope.processes.cancel(&process_id);
+        }
+        scope.breakpoints.end_session(&window, &session_id);
+        scope.watches.end_session(&session_id);
+        let _ = window.emit("debug-exit", DebugExit { session_id });
+    });
+    Ok(info)
+}
+
+pub(crate) fn launch_root(
+    root: Option<String>,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<PathBuf, String> {
+    match root {
+        Some(root) => workspace::authorize(windows, window, &root),
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+/// The launch configurations of `root` (default: the active workspace).
+/// Without any, configurations generated for the detected project types
+/// are listed instead, marked `generated`.
+#[tauri::command]
+pub async fn list_launch_configs(
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<LaunchConfigEntry>, String> {
+    let root = launch_root(root, &window, &windows)?;
+    entries(&root)
+}
+
+/// Adds `config` to the workspace's launch configurations, replacing the
+/// one with the same name.
+#[tauri::command]
+pub async fn save_launch_config(
+    config: LaunchConfig,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    if config.name.trim().is_empty() {
+        return Err("A launch configuration needs a name".to_string());
+    }
+    let root = launch_root(root, &window, &windows)?;
+    let mut file = read_launch_file(&root)?;
+    match file
+        .configurations
+        .iter_mut()
+        .find(|c| c.name == config.name)
+    {
+        Some(existing) => *existing = config,
+        None => file.configurations.push(config),
+    }
+    write_launch_file(&root, &file)
+}
+
+/// Returns whether there was a configuration to delete.
+#[tauri::command]
+pub async fn delete_launch_config(
+    id: String,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let root = launch_root(root, &window, &windows)?;
+    let mut file = read_launch_file(&root)?;
+    let before = file.configurations.len();
+    file.configurations.retain(|c| c.name != id);
+    if file.configurations.len() == before {
+        return Ok(false);
+    }
+    write_launch_file(&root, &file)?;
+    Ok(true)
+}
+
+/// Substitutes the variables of `config`, runs its `preLaunchTask` and
+/// starts the session. Blocks while the task runs.
+pub(crate) fn start(
+    window: Window,
+    scope: Arc<WindowState>,
+    config: LaunchConfig,
+    variables: &Variables,
+) -> Result<DebugSessionInfo, String> {
+    let mut configuration = substitute_value(
+        &serde_json::to_value(&config).map_err(|e| e.to_string())?,
+        variables,
+    )?;
+    if let Some(task) = configuration["preLaunchTask"].as_str() {
+        run_pre_launch_task(&window, &scope, variables.root, task)?;
+    }
+    let debug_server = configuration
+        .as_object_mut()
+        .and_then(|c| c.remove("debugServer"))
+        .and_then(|port| port.as_u64())
+        .map(|port| u16::try_from(port).map_err(|_| format!("Invalid debugServer: {}", port)))
+        .transpose()?;
+    let info = DebugSessionInfo {
+        id: String::new(),
+        name: config.name,
+        kind: config.kind,
+        request: config.request,
+        root: variables.root.to_string_lossy().to_string(),
+        configuration,
+    };
+    launch(window, scope, info, debug_server)
+}
+
+/// Starts a debug session for the launch configuration `config_id`, with
+/// `file` as the editor's current file for `${file}` and `process_id` as
+/// the process for `${command:pickProcess}`. Its `preLaunchTask` runs
+/// first and must succeed. The adapter for the configuration's type is
+/// started (`lldb-dap`, `debugpy`, `dlv`, `js-debug-adapter`), or with
+/// `debugServer` the one listening on that port is used. As with language
+/// servers the client drives the protocol, sending `initialize` and then
+/// `launch` or `attach` with the returned `configuration`; the adapter's
+/// messages arrive as `debug-message` events.
+#[tauri::command]
+pub async fn start_debug(
+    config_id: String,
+    file: Option<String>,
+    process_id: Option<u32>,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DebugSessionInfo, String> {
+    let root = launch_root(root, &window, &windows)?;
+    let file = file
+        .map(|file| workspace::authorize(&windows, &window, &file))
+        .transpose()?;
+    let config = entries(&root)?
+        .into_iter()
+        .find(|entry| entry.id == config_id)
+        .map(|entry| entry.config)
+        .ok_or_else(|| format!("Unknown launch configuration: {}", config_id))?;
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let variables = Variables {
+            root: &root,
+            file: file.as_deref(),
+            process_id,
+        };
+        start(window, scope, config, &variables)
+    })
+    .await
+    .map_err(|e| format!("Failed to start debugging: {}", e))?
+}
+
+/// Sends the request `command` to the session's adapter on the backend's
+/// behalf and waits for its response; the client never sees either. The
+/// response's `body`, or its error message when it failed. Blocking.
+pub(crate) fn request(
+    scope: &WindowState,
+    session_id: &str,
+    command: &str,
+    arguments: Value,
+) -> Result<Value, String> {
+    let sessions = &scope.debug_sessions;
+    let writer = sessions
+        .writer(session_id)
+        .ok_or_else(|| format!("Debug session is not running: {}", session_id))?;
+    let seq = sessions.next_seq.fetch_add(1, Ordering::SeqCst);
+    let key = (session_id.to_string(), seq);
+    let (sender, receiver) = mpsc::channel();
+    sessions.pending.lock().unwrap().insert(key.clone(), sender);
+    let message = json!({
+        "seq": seq,
+        "type": "request",
+        "command": command,
+        "arguments": arguments,
+    });
+    if let Err(e) = write_message(&writer, &message) {
+        sessions.pending.lock().unwrap().remove(&key);
+        return Err(e);
+    }
+    let response = receiver.recv_timeout(REQUEST_TIMEOUT);
+    sessions.pending.lock().unwrap().remove(&key);
+    let response = response.map_err(|_| format!("The debug adapter did not answer {}", command))?;
+    if response["success"] == false {
+        // Adapters put the details in `body.error`, with `message` as a
+        // short code.
+        let error = &response["body"]["error"];
+        return Err(error["format"]
+            .as_str()
+            .or_else(|| response["message"].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{} failed", command)));
+    }
+    Ok(response["body"].clone())
+}
+
+/// Sends one Debug Adapter Protocol message to the session's adapter,
+/// adding the framing. `setBreakpoints` requests are noted so the
+/// responses can mark the stored breakpoints verified, and requests that
+/// resume the debuggee so watches are not evaluated in a stale frame.
+#[tauri::command]
+pub async fn debug_send(
+    session_id: String,
+    message: Value,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let scope = windows.scope(window.label());
+    let writer = scope
+        .debug_sessions
+        .writer(&session_id)
+        .ok_or_else(|| format!("Debug session is not running: {}", session_id))?;
+    scope
+        .breakpoints
+        .observe_request(&app, &scope, &session_id, &message);
+    scope.watches.observe_request(&session_id, &message);
+    tauri::async_runtime::spawn_blocking(move || write_message(&writer, &message))
+        .await
+        .map_err(|e| format!("Failed to send message: {}", e))?
+}
+
+/// Ends the session without asking: the adapter is killed, or the
+/// connection to a `debugServer` closed. Clients send `disconnect` first
+/// for a clean stop. Returns whether the session was running.
+#[tauri::command]
+pub async fn stop_debug(
+    session_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let scope = windows.scope(window.label());
+    let Some(session) = scope.debug_sessions.remove(&session_id) else {
+        return Ok(false);
+    };
+    if let Some(socket) = &session.socket {
+        let _ = socket.shutdown(Shutdown::Both);
+    }
+    if let Some(process_id) = &session.process_id {
+        scope.processes.cancel(process_id);
+    }
+    Ok(true)
+}
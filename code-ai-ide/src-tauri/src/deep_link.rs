@@ -0,0 +1,239 @@
+use reqwest::Url;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::window_manager;
+use crate::window_state::WindowRegistry;
+
+pub const SCHEME: &str = "codeai";
+
+/// Emitted as `clone-requested` for a `codeai://clone` link. Cloning is
+/// left to the frontend, which asks where to put the repository and calls
+/// `git_clone`.
+#[derive(Debug, Clone, Serialize)]
+struct CloneRequested {
+    repo: String,
+    branch: Option<String>,
+}
+
+/// Emitted as `deep-link-failed` for a link that could not be handled.
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkFailed {
+    url: String,
+    error: String,
+}
+
+/// Links that arrived before any frontend was listening, as happens when
+/// a link starts the app.
+#[derive(Default)]
+pub struct PendingLinks {
+    state: Mutex<(bool, Vec<String>)>,
+}
+
+enum Action {
+    Open {
+        file: String,
+        line: Option<u32>,
+        column: Option<u32>,
+    },
+    Clone {
+        repo: String,
+        branch: Option<String>,
+    },
+}
+
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.len() > SCHEME.len()
+        && arg[..SCHEME.len()].eq_ignore_ascii_case(SCHEME)
+        && arg[SCHEME.len()..].starts_with(':')
+}
+
+/// Whether git should be allowed to fetch `repo` on behalf of a web page:
+/// network transports only, never `file://` or `ext::`, which runs a
+/// command.
+fn is_remote_repo(repo: &str) -> bool {
+    if repo.starts_with('-') {
+        return false;
+    }
+    match Url::parse(repo) {
+        Ok(url) => matches!(url.scheme(), "https" | "http" | "ssh" | "git") && url.has_host(),
+        // scp-like `user@host:path`.
+        Err(_) => repo.split_once(':').is_some_and(|(host, path)| {
+            host.contains('@') && !host.contains('/') && !path.is_empty()
+        }),
+    }
+}
+
+fn parse(link: &str) -> Result<Action, String> {
+    let url = Url::parse(link).map_err(|e| format!("Invalid link: {}", e))?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .filter(|value| !value.is_empty())
+    };
+    let number = |name: &str| param(name).and_then(|value| value.parse::<u32>().ok());
+    // `codeai://open` puts the action in the host, `codeai:open` in the path.
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path())
+        .trim_matches('/')
+        .to_ascii_lowercase();
+    match action.as_str() {
+        "open" => {
+            let file = param("file").ok_or("Missing file")?;
+            if !Path::new(&file).is_absolute() {
+                return Err(format!("File must be an absolute path: {}", file));
+            }
+            Ok(Action::Open {
+                file,
+                line: number("line"),
+                column: number("column").or_else(|| number("col")),
+            })
+        }
+        "clone" => {
+            let repo = param("repo").ok_or("Missing repo")?;
+            if !is_remote_repo(&repo) {
+                return Err(format!("Unsupported repository URL: {}", repo));
+            }
+            Ok(Action::Clone {
+                repo,
+                branch: param("branch"),
+            })
+        }
+        _ => Err(format!("Unknown action: {}", action)),
+    }
+}
+
+fn run(app: &AppHandle, link: &str) -> Result<(), String> {
+    match parse(link)? {
+        Action::Open { file, line, column } => {
+            let windows = app.state::<WindowRegistry>();
+            window_manager::open(app, &windows, Some(Path::new(&file)), line, column, false)?;
+        }
+        Action::Clone { repo, branch } => {
+            let window = window_manager::current_window(app).ok_or("No window to clone into")?;
+            window_manager::focus(&window)?;
+            window
+                .emit("clone-requested", CloneRequested { repo, branch })
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Handles `codeai://open?file=<path>&line=<n>&column=<n>`, which opens
+/// the file with `window_manager::open`, and `codeai://clone?repo=<url>`
+/// (optionally `&branch=`), which asks the frontend to clone. Failures
+/// are reported to the current window, since links come from outside and
+/// nobody else would see them.
+fn handle(app: &AppHandle, link: &str) {
+    if let Err(error) = run(app, link) {
+        if let Some(window) = window_manager::current_window(app) {
+            let _ = window.emit(
+                "deep-link-failed",
+                DeepLinkFailed {
+                    url: link.to_string(),
+                    error,
+                },
+            );
+        }
+    }
+}
+
+/// Handles `link` now, or once a frontend is ready when none is yet.
+pub fn receive(app: &AppHandle, link: &str) {
+    {
+        let mut state = app.state::<PendingLinks>().state.lock().unwrap();
+        if !state.0 {
+            state.1.push(link.to_string());
+            return;
+        }
+    }
+    handle(app, link);
+}
+
+/// Called when a frontend has set up its listeners; handles the links
+/// that were waiting for it.
+pub fn frontend_ready(app: &AppHandle) {
+    let links = {
+        let mut state = app.state::<PendingLinks>().state.lock().unwrap();
+        state.0 = true;
+        std::mem::take(&mut state.1)
+    };
+    for link in links {
+        handle(app, &link);
+    }
+}
+
+/// Makes the OS hand `codeai:` links to this executable. macOS reads the
+/// scheme from the bundle's `Info.plist` instead, and delivers links as
+/// `RunEvent::Opened`; elsewhere the link arrives as an argument, through
+/// the single-instance lock when the app is already running.
+pub fn register_scheme() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate executable: {}", e))?;
+    register_for(&exe)
+}
+
+#[cfg(target_os = "windows")]
+fn register_for(exe: &Path) -> Result<(), String> {
+    use std::process::Command;
+
+    let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let entries: [(String, Option<&str>, String); 3] = [
+        (key.clone(), None, "URL:Code AI IDE".to_string()),
+        (key.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{}\shell\open\command", key), None, command),
+    ];
+    for (key, name, value) in entries {
+        let mut reg = Command::new("reg");
+        reg.args(["add", &key, "/f", "/t", "REG_SZ", "/d", &value]);
+        match name {
+            Some(name) => reg.args(["/v", name]),
+            None => reg.arg("/ve"),
+        };
+        let status = reg
+            .status()
+            .map_err(|e| format!("Failed to register the {} scheme: {}", SCHEME, e))?;
+        if !status.success() {
+            return Err(format!("Failed to register the {} scheme", SCHEME));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn register_for(exe: &Path) -> Result<(), String> {
+    use std::process::Command;
+
+    let applications = std::env::var_os("XDG_DATA_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or("No data directory to register links in")?
+        .join("applications");
+    let desktop_file = format!("{}-url-handler.desktop", SCHEME);
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Code AI IDE\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe.display(),
+        SCHEME
+    );
+    std::fs::create_dir_all(&applications)
+        .and_then(|_| std::fs::write(applications.join(&desktop_file), entry))
+        .map_err(|e| format!("Failed to register the {} scheme: {}", SCHEME, e))?;
+    let _ = Command::new("xdg-mime")
+        .args([
+            "default",
+            &desktop_file,
+            &format!("x-scheme-handler/{}", SCHEME),
+        ])
+        .status();
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn register_for(_exe: &Path) -> Result<(), String> {
+    Ok(())
+}
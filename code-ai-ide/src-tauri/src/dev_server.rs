@@ -0,0 +1,340 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Manager, State, Window};
+
+use crate::metadata;
+use crate::process::{
+    self, CommandExit, OutputHook, OutputStream, ProcessHooks, ProcessKind, ProcessSignal,
+};
+use crate::tasks;
+use crate::window_state::{WindowRegistry, WindowState};
+
+/// Ports dev tools bind by default (CRA/Next, Vite, Angular, Django,
+/// Flask, Rails, Phoenix, webpack, Astro), probed when the output does not
+/// name one.
+const COMMON_PORTS: &[u16] = &[
+    3000, 3001, 4000, 4200, 4321, 5000, 5173, 5174, 8000, 8080, 8081, 8888,
+];
+const PROBE_INTERVAL: Duration = Duration::from_millis(500);
+/// Give up probing after this long; the server may still print its URL.
+const PROBE_WINDOW: Duration = Duration::from_secs(60);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(100);
+/// How long a restart waits for the old process to exit before killing it.
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevServerInfo {
+    pub task_id: String,
+    pub process_id: String,
+    /// `None` until the port is known.
+    pub url: Option<String>,
+    pub port: Option<u16>,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: Option<u64>,
+}
+
+/// Emitted as `dev-server-stopped` when the process exits for any reason.
+#[derive(Debug, Clone, Serialize)]
+pub struct DevServerStopped {
+    pub task_id: String,
+    pub exit_code: Option<i32>,
+}
+
+/// Dev servers of a window, keyed by task id. They belong to the window,
+/// not to the panel showing them, so reloading the UI finds them running.
+#[derive(Default)]
+pub struct DevServers {
+    servers: Mutex<HashMap<String, DevServerInfo>>,
+}
+
+impl DevServers {
+    fn get(&self, task_id: &str) -> Option<DevServerInfo> {
+        self.servers.lock().unwrap().get(task_id).cloned()
+    }
+
+    /// Records the port once; the first detection wins.
+    fn set_port(&self, task_id: &str, process_id: &str, port: u16) -> Option<DevServerInfo> {
+        let mut servers = self.servers.lock().unwrap();
+        let server = servers
+            .get_mut(task_id)
+            .filter(|s| s.process_id == process_id && s.port.is_none())?;
+        server.port = Some(port);
+        server.url = Some(format!("http://localhost:{}", port));
+        Some(server.clone())
+    }
+}
+
+fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?i)(?:https?://(?:localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1?\]):(\d{2,5}))|(?:(?:listening|running|started|serving)\b.*?\bport\s*:?\s*(\d{2,5}))",
+        )
+        .expect("valid regex")
+    })
+}
+
+fn ansi_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").expect("valid regex"))
+}
+
+/// The first local port the output announces, e.g. Vite's
+/// `Local: http://localhost:5173/` or `Listening on port 8000`.
+pub fn port_in_output(text: &str) -> Option<u16> {
+    let text = ansi_pattern().replace_all(text, "");
+    let captures = url_pattern().captures(&text)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(2))?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+fn port_open(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok()
+}
+
+fn announce(window: &Window, server: &DevServerInfo) {
+    let _ = window.emit("dev-server-ready", server.clone());
+}
+
+/// Watches for the server to start listening on one of the usual ports
+/// that was free before it started.
+fn probe_ports(
+    window: Window,
+    scope: Arc<WindowState>,
+    task_id: String,
+    process_id: String,
+    busy: HashSet<u16>,
+) {
+    let started = Instant::now();
+    while started.elapsed() < PROBE_WINDOW {
+        thread::sleep(PROBE_INTERVAL);
+        match scope.dev_servers.get(&task_id) {
+            Some(server) if server.process_id == process_id && server.port.is_none() => {}
+            _ => return,
+        }
+        let opened = COMMON_PORTS
+            .iter()
+            .copied()
+            .find(|port| !busy.contains(port) && port_open(*port));
+        if let Some(port) = opened {
+            if let Some(server) = scope.dev_servers.set_port(&task_id, &process_id, port) {
+                announce(&window, &server);
+            }
+            return;
+        }
+    }
+}
+
+fn launch(
+    window: Window,
+    scope: Arc<WindowState>,
+    root: Option<String>,
+    task_id: String,
+) -> Result<DevServerInfo, String> {
+    let windows = window.state::<WindowRegistry>();
+    let root = tasks::task_root(&windows, &window, root)?;
+    let task = tasks::detect_tasks(&root)?
+        .into_iter()
+        .find(|task| task.id == task_id)
+        .ok_or_else(|| format!("Unknown task: {}", task_id))?;
+    let cmd = task.command_for(&window)?;
+
+    // Ports already taken cannot be the new server's.
+    let busy: HashSet<u16> = COMMON_PORTS
+        .iter()
+        .copied()
+        .filter(|p| port_open(*p))
+        .collect();
+    let process_id = Arc::new(OnceLock::<String>::new());
+
+    let on_output: OutputHook = {
+        let (window, scope, task_id, process_id) = (
+            window.clone(),
+            scope.clone(),
+            task_id.clone(),
+            process_id.clone(),
+        );
+        let mut recent = String::new();
+        Arc::new(Mutex::new(move |_: OutputStream, chunk: &str| {
+            let Some(process_id) = process_id.get() else {
+                return;
+            };
+            // Keep a little context so a URL split across chunks is found.
+            recent.push_str(chunk);
+            if let Some(port) = port_in_output(&recent) {
+                if let Some(server) = scope.dev_servers.set_port(&task_id, process_id, port) {
+                    announce(&window, &server);
+                }
+            }
+            let keep = recent.len().saturating_sub(256);
+            let keep = (keep..=recent.len())
+                .find(|&i| recent.is_char_boundary(i))
+                .unwrap_or(0);
+            recent.drain(..keep);
+        }))
+    };
+    let on_exit = {
+        let (window, scope, task_id, process_id) = (
+            window.clone(),
+            scope.clone(),
+            task_id.clone(),
+            process_id.clone(),
+        );
+        move |exit: &CommandExit| {
+            let mut servers = scope.dev_servers.servers.lock().unwrap();
+            // A restart may already have replaced this entry.
+            if servers.get(&task_id).map(|s| &s.process_id) == process_id.get() {
+                servers.remove(&task_id);
+            }
+            drop(servers);
+            let _ = window.emit(
+                "dev-server-stopped",
+                DevServerStopped {
+                    task_id,
+                    exit_code: exit.exit_code,
+                },
+            );
+        }
+    };
+
+    let id = process::start(
+        window.clone(),
+        scope.clone(),
+        cmd,
+        ProcessKind::DevServer,
+        None,
+        None,
+        ProcessHooks {
+            on_output: Some(on_output),
+            on_exit: Some(Box::new(on_exit)),
+        },
+    )?;
+    let server = DevServerInfo {
+        task_id: task_id.clone(),
+        process_id: id.clone(),
+        url: None,
+        port: None,
+        started_at: metadata::to_millis(SystemTime::now()),
+    };
+    scope
+        .dev_servers
+        .servers
+        .lock()
+        .unwrap()
+        .insert(task_id.clone(), server.clone());
+    let _ = process_id.set(id.clone());
+    // A server that died at once may have exited before it was recorded.
+    if !scope.processes.is_running(&id) {
+        let mut servers = scope.dev_servers.servers.lock().unwrap();
+        if servers.get(&task_id).is_some_and(|s| s.process_id == id) {
+            servers.remove(&task_id);
+        }
+    }
+
+    thread::spawn(move || probe_ports(window, scope, task_id, id, busy));
+    Ok(server)
+}
+
+/// Stops a server and waits for it to exit, killing it after a grace
+/// period.
+fn stop(scope: &WindowState, task_id: &str) -> bool {
+    let server = match scope.dev_servers.get(task_id) {
+        Some(server) => server,
+        None => return false,
+    };
+    scope
+        .processes
+        .signal(&server.process_id, ProcessSignal::Interrupt);
+    let deadline = Instant::now() + STOP_GRACE;
+    while scope
+        .dev_servers
+        .get(task_id)
+        .is_some_and(|s| s.process_id == server.process_id)
+    {
+        if Instant::now() >= deadline {
+            scope
+                .processes
+                .signal(&server.process_id, ProcessSignal::Kill);
+            break;
+        }
+        thread::sleep(PROBE_INTERVAL / 5);
+    }
+    true
+}
+
+/// Starts the task as a dev server, or returns the one already running for
+/// it. `dev-server-ready` follows once its URL is known.
+#[tauri::command]
+pub async fn start_dev_server(
+    task_id: String,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DevServerInfo, String> {
+    let scope = windows.scope(window.label());
+    if let Some(server) = scope.dev_servers.get(&task_id) {
+        return Ok(server);
+    }
+    launch(window, scope, root, task_id)
+}
+
+#[tauri::command]
+pub async fn get_server_url(
+    task_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    Ok(windows
+        .scope(window.label())
+        .dev_servers
+        .get(&task_id)
+        .and_then(|server| server.url))
+}
+
+#[tauri::command]
+pub async fn list_dev_servers(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<DevServerInfo>, String> {
+    let scope = windows.scope(window.label());
+    let servers = scope.dev_servers.servers.lock().unwrap();
+    Ok(servers.values().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn restart_dev_server(
+    task_id: String,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DevServerInfo, String> {
+    let scope = windows.scope(window.label());
+    let stopping = scope.clone();
+    let id = task_id.clone();
+    tauri::async_runtime::spawn_blocking(move || stop(&stopping, &id))
+        .await
+        .map_err(|e| format!("Failed to stop dev server: {}", e))?;
+    launch(window, scope, root, task_id)
+}
+
+#[tauri::command]
+pub async fn stop_dev_server(
+    task_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || stop(&scope, &task_id))
+        .await
+        .map_err(|e| format!("Failed to stop dev server: {}", e))
+}
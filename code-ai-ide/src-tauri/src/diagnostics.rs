@@ -0,0 +1,258 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{State, Window};
+
+use crate::lsp;
+use crate::problem_matcher::{Diagnostic, Severity};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiagnostics {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Emitted as `diagnostics-changed` with the files whose diagnostics
+/// changed; fetch them again with `get_diagnostics`.
+#[derive(Debug, Clone, Serialize)]
+struct DiagnosticsChanged {
+    paths: Vec<String>,
+}
+
+/// Every diagnostic a window knows about, from every source, so the
+/// Problems panel and the editor gutters agree. Diagnostics are grouped by
+/// owner, whoever reports a set and later replaces it: `lsp:<server id>`,
+/// `task:<task id>` or `lint:<linter>`.
+#[derive(Default)]
+pub struct DiagnosticStore {
+    owners: Mutex<HashMap<String, HashMap<String, Vec<Diagnostic>>>>,
+}
+
+/// Tools print paths relative, with `./` or through symlinks; compare them
+/// the way the workspace sandbox does.
+fn normalize(path: &str) -> String {
+    workspace::resolve(Path::new(path))
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+impl DiagnosticStore {
+    /// Replaces what `owner` reported for one file. Returns the changed
+    /// paths.
+    pub fn set_file(&self, owner: &str, path: &str, diagnostics: Vec<Diagnostic>) -> Vec<String> {
+        let path = normalize(path);
+        let mut owners = self.owners.lock().unwrap();
+        let files = owners.entry(owner.to_string()).or_default();
+        let had = files.contains_key(&path);
+        if diagnostics.is_empty() {
+            files.remove(&path);
+            if !had {
+                return Vec::new();
+            }
+        } else {
+            files.insert(path.clone(), diagnostics);
+        }
+        vec![path]
+    }
+
+    /// Replaces everything `owner` reported. Returns the changed paths.
+    pub fn set_all(&self, owner: &str, diagnostics: Vec<Diagnostic>) -> Vec<String> {
+        let mut files: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+        for mut diagnostic in diagnostics {
+            diagnostic.path = normalize(&diagnostic.path);
+            files
+                .entry(diagnostic.path.clone())
+                .or_default()
+                .push(diagnostic);
+        }
+        let mut owners = self.owners.lock().unwrap();
+        let previous = owners.remove(owner).unwrap_or_default();
+        let mut changed: Vec<String> = previous.into_keys().chain(files.keys().cloned()).collect();
+        changed.sort();
+        changed.dedup();
+        if !files.is_empty() {
+            owners.insert(owner.to_string(), files);
+        }
+        changed
+    }
+
+    /// Replaces what `owner` reported for files under any of `scope`, e.g.
+    /// the files and folders a linter was run over. Returns the changed
+    /// paths.
+    pub fn set_within(
+        &self,
+        owner: &str,
+        scope: &[PathBuf],
+        diagnostics: Vec<Diagnostic>,
+    ) -> Vec<String> {
+        let mut owners = self.owners.lock().unwrap();
+        let files = owners.entry(owner.to_string()).or_default();
+        let mut changed: Vec<String> = files
+            .keys()
+            .filter(|path| scope.iter().any(|dir| Path::new(path).starts_with(dir)))
+            .cloned()
+            .collect();
+        files.retain(|path, _| !changed.contains(path));
+        for mut diagnostic in diagnostics {
+            diagnostic.path = normalize(&diagnostic.path);
+            changed.push(diagnostic.path.clone());
+            files
+                .entry(diagnostic.path.clone())
+                .or_default()
+                .push(diagnostic);
+        }
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+
+    pub fn clear_owner(&self, owner: &str) -> Vec<String> {
+        self.set_all(owner, Vec::new())
+    }
+
+    /// Diagnostics per file, from all owners, optionally for one file only.
+    pub fn get(&self, path: Option<&str>) -> Vec<FileDiagnostics> {
+        let path = path.map(normalize);
+        let owners = self.owners.lock().unwrap();
+        let mut merged: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+        for (owner, files) in owners.iter() {
+            for (file, diagnostics) in files {
+                if path.as_ref().is_some_and(|path| path != file) {
+                    continue;
+                }
+                merged.entry(file.clone()).or_default().extend(
+                    diagnostics
+                        .iter()
+                        .map(|diagnostic| identified(owner, file, diagnostic)),
+                );
+            }
+        }
+        merged
+            .into_iter()
+            .map(|(path, mut diagnostics)| {
+                diagnostics.sort_by_key(|d| (d.line, d.column));
+                FileDiagnostics { path, diagnostics }
+            })
+            .collect()
+    }
+
+    /// The diagnostic with `id`, as `get` handed it out.
+    pub fn find(&self, id: &str) -> Option<Diagnostic> {
+        let owners = self.owners.lock().unwrap();
+        owners.iter().find_map(|(owner, files)| {
+            files.iter().find_map(|(file, diagnostics)| {
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| identified(owner, file, diagnostic))
+                    .find(|diagnostic| diagnostic.id == id)
+            })
+        })
+    }
+
+    pub fn clear(&self) {
+        self.owners.lock().unwrap().clear();
+    }
+}
+
+/// A copy of `diagnostic` with its id, derived from who reported what
+/// where, and the path it is stored under.
+fn identified(owner: &str, file: &str, diagnostic: &Diagnostic) -> Diagnostic {
+    let key = format!(
+        "{}\0{}\0{}\0{}\0{}",
+        owner, file, diagnostic.line, diagnostic.column, diagnostic.message
+    );
+    Diagnostic {
+        id: blake3::hash(key.as_bytes()).to_hex()[..16].to_string(),
+        path: file.to_string(),
+        ..diagnostic.clone()
+    }
+}
+
+pub fn notify(window: &Window, paths: Vec<String>) {
+    if !paths.is_empty() {
+        let _ = window.emit("diagnostics-changed", DiagnosticsChanged { paths });
+    }
+}
+
+/// Converts the params of a `textDocument/publishDiagnostics` notification
+/// into the file's path and its diagnostics. LSP positions are zero-based.
+pub fn from_lsp(params: &Value, default_source: &str) -> Option<(String, Vec<Diagnostic>)> {
+    let path = lsp::uri_to_path(params.get("uri")?.as_str()?)?;
+    let position = |value: &Value| {
+        let line = value.get("line")?.as_u64()? as u32;
+        let character = value.get("character")?.as_u64()? as u32;
+        Some((line + 1, character + 1))
+    };
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|item| {
+            let range = item.get("range")?;
+            let (line, column) = position(range.get("start")?)?;
+            let end = range.get("end").and_then(position);
+            let severity = match item.get("severity").and_then(Value::as_u64) {
+                Some(2) => Severity::Warning,
+                Some(3) | Some(4) => Severity::Info,
+                _ => Severity::Error,
+            };
+            let code = match item.get("code") {
+                Some(Value::String(code)) => Some(code.clone()),
+                Some(Value::Number(code)) => Some(code.to_string()),
+                _ => None,
+            };
+            Some(Diagnostic {
+                id: String::new(),
+                path: path.clone(),
+                line,
+                column,
+                end_line: end.map(|(line, _)| line),
+                end_column: end.map(|(_, column)| column),
+                severity,
+                message: item.get("message")?.as_str()?.to_string(),
+                code,
+                source: item
+                    .get("source")
+                    .and_then(Value::as_str)
+                    .unwrap_or(default_source)
+                    .to_string(),
+                // Language servers offer fixes as code actions instead.
+                fixes: Vec::new(),
+            })
+        })
+        .collect();
+    Some((path, diagnostics))
+}
+
+#[tauri::command]
+pub async fn get_diagnostics(
+    path: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<FileDiagnostics>, String> {
+    Ok(windows
+        .scope(window.label())
+        .diagnostics
+        .get(path.as_deref()))
+}
+
+/// Replaces the diagnostics of a linter run by the frontend, e.g. through
+/// `run_command`.
+#[tauri::command]
+pub async fn publish_lint_diagnostics(
+    linter: String,
+    diagnostics: Vec<Diagnostic>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let changed = windows
+        .scope(window.label())
+        .diagnostics
+        .set_all(&format!("lint:{}", linter), diagnostics);
+    notify(&window, changed);
+    Ok(())
+}
@@ -0,0 +1,533 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, State, Window};
+
+use crate::autosave;
+use crate::encoding;
+use crate::language;
+use crate::line_endings::{self, LineEnding};
+use crate::local_history::{LocalHistory, RevisionSource};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Authoritative backend copy of a document the user has open. The editor
+/// sends edits here instead of round-tripping whole files, so save, search,
+/// language servers and AI context all see the same text.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub path: String,
+    pub content: String,
+    pub version: u64,
+    pub dirty: bool,
+    pub language: String,
+    pub encoding: String,
+    pub has_bom: bool,
+    /// Modification time of the file when it was last read or written here,
+    /// to tell whether someone else changed it since.
+    pub disk_modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSnapshot {
+    pub path: String,
+    pub content: String,
+    pub version: u64,
+    pub dirty: bool,
+    pub language: String,
+    pub encoding: String,
+    pub has_bom: bool,
+    pub eol: Option<LineEnding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSummary {
+    pub path: String,
+    pub version: u64,
+    pub dirty: bool,
+    pub language: String,
+}
+
+/// Zero-based line and UTF-16 column, matching what Monaco and LSP use.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// An incremental change; a missing range replaces the whole document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentChange {
+    pub range: Option<Range>,
+    pub text: String,
+}
+
+/// A range edit expressed against a specific document version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub range: Range,
+    /// Also accepted as `newText`, so LSP edits deserialize as they are.
+    #[serde(alias = "newText")]
+    pub new_text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenDocumentMatch {
+    pub path: String,
+    pub line: u32,
+    pub character: u32,
+    pub preview: String,
+}
+
+#[derive(Default)]
+pub struct DocumentStore {
+    docs: Mutex<HashMap<String, Document>>,
+}
+
+impl Document {
+    fn snapshot(&self) -> DocumentSnapshot {
+        DocumentSnapshot {
+            path: self.path.clone(),
+            content: self.content.clone(),
+            version: self.version,
+            dirty: self.dirty,
+            language: self.language.clone(),
+            encoding: self.encoding.clone(),
+            has_bom: self.has_bom,
+            eol: line_endings::detect(&self.content),
+        }
+    }
+
+    fn summary(&self) -> DocumentSummary {
+        DocumentSummary {
+            path: self.path.clone(),
+            version: self.version,
+            dirty: self.dirty,
+            language: self.language.clone(),
+        }
+    }
+
+    fn apply_change(&mut self, change: &ContentChange) -> Result<(), String> {
+        match change.range {
+            Some(range) => {
+                let start = offset_at(&self.content, range.start)?;
+                let end = offset_at(&self.content, range.end)?;
+                if end < start {
+                    return Err("Edit range end is before its start".to_string());
+                }
+                self.content.replace_range(start..end, &change.text);
+            }
+            None => self.content = change.text.clone(),
+        }
+        Ok(())
+    }
+}
+
+impl DocumentStore {
+    pub fn open(&self, path: &str) -> Result<DocumentSnapshot, String> {
+        let key = document_key(path);
+        let mut docs = self.docs.lock().unwrap();
+        if let Some(doc) = docs.get(&key) {
+            return Ok(doc.snapshot());
+        }
+
+        let bytes = fs::read(&key).map_err(|e| format!("Failed to read file: {}", e))?;
+        let decoded = encoding::decode(&bytes);
+        let doc = Document {
+            language: language::detect_language(Path::new(&key), &decoded.content),
+            path: key.clone(),
+            content: decoded.content,
+            version: 1,
+            dirty: false,
+            encoding: decoded.encoding,
+            has_bom: decoded.has_bom,
+            disk_modified: modified_time(&key),
+        };
+        let snapshot = doc.snapshot();
+        docs.insert(key, doc);
+        Ok(snapshot)
+    }
+
+    pub fn clear(&self) {
+        self.docs.lock().unwrap().clear();
+    }
+
+    pub fn close(&self, path: &str) -> Option<Document> {
+        self.docs.lock().unwrap().remove(&document_key(path))
+    }
+
+    pub fn snapshot(&self, path: &str) -> Option<DocumentSnapshot> {
+        self.docs
+            .lock()
+            .unwrap()
+            .get(&document_key(path))
+            .map(Document::snapshot)
+    }
+
+    pub fn summaries(&self) -> Vec<DocumentSummary> {
+        let mut list: Vec<_> = self
+            .docs
+            .lock()
+            .unwrap()
+            .values()
+            .map(Document::summary)
+            .collect();
+        list.sort_by(|a, b| a.path.cmp(&b.path));
+        list
+    }
+
+    pub fn dirty_snapshots(&self) -> Vec<DocumentSnapshot> {
+        self.docs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|doc| doc.dirty)
+            .map(Document::snapshot)
+            .collect()
+    }
+
+    /// Applies changes in order and bumps the version once. Changes are
+    /// applied to a copy first so a bad range leaves the document untouched.
+    pub fn change(&self, path: &str, changes: &[ContentChange]) -> Result<u64, String> {
+        let key = document_key(path);
+        let mut docs = self.docs.lock().unwrap();
+        let doc = docs
+            .get_mut(&key)
+            .ok_or_else(|| format!("Document is not open: {}", path))?;
+
+        let mut updated = doc.clone();
+        for change in changes {
+            updated.apply_change(change)?;
+        }
+        updated.version += 1;
+        updated.dirty = true;
+        *doc = updated;
+        Ok(doc.version)
+    }
+
+    /// Applies a batch of non-overlapping edits, all expressed against
+    /// `base_version`. Either every edit lands and the version is bumped, or
+    /// the document is left unchanged and an error explains why.
+    pub fn apply_edits(
+        &self,
+        path: &str,
+        edits: &[TextEdit],
+        base_version: u64,
+    ) -> Result<u64, String> {
+        let key = document_key(path);
+        let mut docs = self.docs.lock().unwrap();
+        let doc = docs
+            .get_mut(&key)
+            .ok_or_else(|| format!("Document is not open: {}", path))?;
+
+        if doc.version != base_version {
+            return Err(format!(
+                "Document version mismatch: edits target version {} but current version is {}",
+                base_version, doc.version
+            ));
+        }
+
+        doc.content = edit_text(&doc.content, edits)?;
+        doc.version += 1;
+        doc.dirty = true;
+        Ok(doc.version)
+    }
+
+    pub fn get(&self, path: &str) -> Option<Document> {
+        self.docs.lock().unwrap().get(&document_key(path)).cloned()
+    }
+
+    /// Replaces the whole text of an open document as one change.
+    pub fn replace_content(&self, path: &str, content: String) -> Result<u64, String> {
+        let mut docs = self.docs.lock().unwrap();
+        let doc = docs
+            .get_mut(&document_key(path))
+            .ok_or_else(|| format!("Document is not open: {}", path))?;
+        doc.content = content;
+        doc.version += 1;
+        doc.dirty = true;
+        Ok(doc.version)
+    }
+
+    /// Puts a document back exactly as it was, version included. Used to
+    /// undo a multi-file edit that failed halfway.
+    pub fn restore(&self, doc: Document) {
+        self.docs
+            .lock()
+            .unwrap()
+            .insert(document_key(&doc.path), doc);
+    }
+
+    /// Follows a file that was renamed on disk. `to` must already exist.
+    pub fn rename(&self, from: &Path, to: &Path) {
+        let mut docs = self.docs.lock().unwrap();
+        if let Some(mut doc) = docs.remove(&document_key(&from.to_string_lossy())) {
+            doc.path = document_key(&to.to_string_lossy());
+            docs.insert(doc.path.clone(), doc);
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<u64, String> {
+        let key = document_key(path);
+        let mut docs = self.docs.lock().unwrap();
+        let doc = docs
+            .get_mut(&key)
+            .ok_or_else(|| format!("Document is not open: {}", path))?;
+
+        let bytes = encoding::encode(&doc.content, &doc.encoding, doc.has_bom)?;
+        fs::write(&doc.path, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+        doc.dirty = false;
+        doc.disk_modified = modified_time(&doc.path);
+        Ok(doc.version)
+    }
+
+    /// Whether the file was modified, or removed, by something other than
+    /// this store since the document last read or wrote it.
+    pub fn changed_on_disk(&self, path: &str) -> bool {
+        let docs = self.docs.lock().unwrap();
+        match docs.get(&document_key(path)) {
+            Some(doc) => modified_time(&doc.path) != doc.disk_modified,
+            None => false,
+        }
+    }
+
+    /// Replaces the content only if the document is still at `version`, so
+    /// a slow rewrite such as formatting cannot clobber newer typing.
+    pub fn replace_if_current(&self, path: &str, version: u64, content: String) -> bool {
+        let mut docs = self.docs.lock().unwrap();
+        match docs.get_mut(&document_key(path)) {
+            Some(doc) if doc.version == version => {
+                doc.content = content;
+                doc.version += 1;
+                doc.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn search(&self, query: &str, case_sensitive: bool) -> Vec<OpenDocumentMatch> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let needle = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        let docs = self.docs.lock().unwrap();
+        let mut matches = Vec::new();
+        for doc in docs.values() {
+            for (line_index, line) in doc.content.lines().enumerate() {
+                let haystack = if case_sensitive {
+                    line.to_string()
+                } else {
+                    line.to_lowercase()
+                };
+                for (byte_index, _) in haystack.match_indices(&needle) {
+                    let character = haystack[..byte_index].encode_utf16().count() as u32;
+                    matches.push(OpenDocumentMatch {
+                        path: doc.path.clone(),
+                        line: line_index as u32,
+                        character,
+                        preview: line.trim_end().to_string(),
+                    });
+                }
+            }
+        }
+        matches.sort_by(|a, b| (&a.path, a.line, a.character).cmp(&(&b.path, b.line, b.character)));
+        matches
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Documents are keyed by canonical path so `./src/a.rs` and the absolute
+/// form refer to the same buffer. A path that no longer exists, such as
+/// the old name after a rename, is keyed by its canonical parent.
+pub fn document_key(path: &str) -> String {
+    let path = Path::new(path);
+    fs::canonicalize(path)
+        .or_else(|e| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).map(|p| p.join(name)),
+            _ => Err(e),
+        })
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Applies non-overlapping edits, all expressed against `content`.
+pub fn edit_text(content: &str, edits: &[TextEdit]) -> Result<String, String> {
+    let mut resolved = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let start = offset_at(content, edit.range.start)?;
+        let end = offset_at(content, edit.range.end)?;
+        if end < start {
+            return Err("Edit range end is before its start".to_string());
+        }
+        resolved.push((start, end, edit.new_text.as_str()));
+    }
+
+    resolved.sort_by_key(|(start, end, _)| (*start, *end));
+    for pair in resolved.windows(2) {
+        if pair[1].0 < pair[0].1 {
+            return Err("Edits overlap and cannot be applied together".to_string());
+        }
+    }
+
+    // Apply back to front so earlier offsets stay valid.
+    let mut edited = content.to_string();
+    for (start, end, text) in resolved.iter().rev() {
+        edited.replace_range(*start..*end, text);
+    }
+    Ok(edited)
+}
+
+/// Converts a line/UTF-16 column position into a byte offset into `content`.
+pub fn offset_at(content: &str, position: Position) -> Result<usize, String> {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match content[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return Err(format!("Line {} is out of range", position.line)),
+        }
+    }
+
+    let line_end = content[line_start..]
+        .find('\n')
+        .map(|i| line_start + i)
+        .unwrap_or(content.len());
+    let line = &content[line_start..line_end];
+
+    let mut units = 0u32;
+    for (byte_index, ch) in line.char_indices() {
+        if units >= position.character {
+            return Ok(line_start + byte_index);
+        }
+        units += ch.len_utf16() as u32;
+    }
+    if units >= position.character {
+        Ok(line_end)
+    } else {
+        Err(format!(
+            "Column {} is out of range on line {}",
+            position.character, position.line
+        ))
+    }
+}
+
+#[tauri::command]
+pub async fn document_open(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DocumentSnapshot, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    windows
+        .scope(window.label())
+        .documents
+        .open(&path.to_string_lossy())
+}
+
+#[tauri::command]
+pub async fn document_get(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<DocumentSnapshot, String> {
+    windows
+        .scope(window.label())
+        .documents
+        .snapshot(&path)
+        .ok_or_else(|| format!("Document is not open: {}", path))
+}
+
+#[tauri::command]
+pub async fn document_change(
+    path: String,
+    changes: Vec<ContentChange>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<u64, String> {
+    let scope = windows.scope(window.label());
+    let version = scope.documents.change(&path, &changes)?;
+    autosave::schedule(&window, &scope, &path);
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn apply_edits(
+    path: String,
+    edits: Vec<TextEdit>,
+    base_version: u64,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<u64, String> {
+    let scope = windows.scope(window.label());
+    let version = scope.documents.apply_edits(&path, &edits, base_version)?;
+    autosave::schedule(&window, &scope, &path);
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn document_save(
+    path: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<u64, String> {
+    let key = document_key(&path);
+    let previous = fs::read(&key).ok();
+    let version = windows.scope(window.label()).documents.save(&path)?;
+    if let Some(previous) = previous {
+        let _ = history.record(&app, Path::new(&key), &previous, RevisionSource::Save);
+    }
+    Ok(version)
+}
+
+#[tauri::command]
+pub async fn document_close(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let scope = windows.scope(window.label());
+    scope.documents.close(&path);
+    scope.syntax_trees.forget(&document_key(&path));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn document_list(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<DocumentSummary>, String> {
+    Ok(windows.scope(window.label()).documents.summaries())
+}
+
+#[tauri::command]
+pub async fn search_open_documents(
+    query: String,
+    case_sensitive: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<OpenDocumentMatch>, String> {
+    Ok(windows
+        .scope(window.label())
+        .documents
+        .search(&query, case_sensitive))
+}
@@ -0,0 +1,356 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// The profile made of `.env` and `.env.local` alone.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Shown in place of secret values.
+const REDACTED: &str = "********";
+
+/// `.env.<name>` files that document variables rather than set them.
+const NOT_PROFILES: &[&str] = &["example", "sample", "template", "dist", "defaults"];
+
+/// Parts of variable names that mark the value as secret.
+const SECRET_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE",
+    "CREDENTIAL",
+    "AUTH",
+    "SALT",
+    "SIGNATURE",
+    "DSN",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvProfile {
+    pub name: String,
+    /// The files it is made of, lowest precedence first.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvVariable {
+    pub key: String,
+    /// Redacted when `secret`.
+    pub value: String,
+    pub secret: bool,
+    /// The file the value comes from.
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvProfileView {
+    pub profile: String,
+    pub files: Vec<String>,
+    pub variables: Vec<EnvVariable>,
+}
+
+/// `${NAME}`, `${NAME:-default}` or `$NAME`.
+fn reference_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}|\$([A-Za-z_][A-Za-z0-9_]*)")
+            .expect("valid regex")
+    })
+}
+
+/// Expands references to variables set earlier, then to the app's own
+/// environment; unknown ones become empty, as in a shell.
+fn expand(value: &str, defined: &HashMap<String, String>) -> String {
+    reference_pattern()
+        .replace_all(value, |captures: &Captures| {
+            let name = captures
+                .get(1)
+                .or_else(|| captures.get(3))
+                .map_or("", |name| name.as_str());
+            defined
+                .get(name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())
+                .filter(|value| !value.is_empty())
+                .or_else(|| captures.get(2).map(|default| default.as_str().to_string()))
+                .unwrap_or_default()
+        })
+        .into_owned()
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// The end of a value opened by `quote`, skipping escaped quotes inside
+/// double quotes.
+fn closing_quote(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Parses a dotenv file: `KEY=value` lines with optional `export`, `#`
+/// comments, single-quoted literal values, double-quoted values with
+/// escapes that may span lines, and `${VAR}` references in all but
+/// single-quoted values. Lines that are not assignments are skipped.
+/// `defined` holds the variables of files read before this one and
+/// receives this file's.
+fn parse(text: &str, defined: &mut HashMap<String, String>) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, rest)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid_key {
+            continue;
+        }
+
+        let rest = rest.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'' | '`')) => {
+                let mut raw = rest[1..].to_string();
+                let end = loop {
+                    if let Some(end) = closing_quote(&raw, quote) {
+                        break end;
+                    }
+                    match lines.next() {
+                        Some(next) => {
+                            raw.push('\n');
+                            raw.push_str(next);
+                        }
+                        // Unterminated: take everything.
+                        None => break raw.len(),
+                    }
+                };
+                raw.truncate(end);
+                match quote {
+                    '"' => expand(&unescape(&raw), defined),
+                    _ => raw,
+                }
+            }
+            _ => {
+                let value = match rest.find(" #").or_else(|| rest.find("\t#")) {
+                    Some(comment) => &rest[..comment],
+                    None => rest,
+                };
+                expand(value.trim_end(), defined)
+            }
+        };
+        defined.insert(key.to_string(), value.clone());
+        variables.push((key.to_string(), value));
+    }
+    variables
+}
+
+/// The files of `profile` in `root` that exist, lowest precedence first:
+/// `.env`, `.env.local`, then `.env.<profile>` and `.env.<profile>.local`.
+fn profile_files(root: &Path, profile: &str) -> Vec<PathBuf> {
+    let mut names = vec![".env".to_string(), ".env.local".to_string()];
+    if profile != DEFAULT_PROFILE {
+        names.push(format!(".env.{}", profile));
+        names.push(format!(".env.{}.local", profile));
+    }
+    names
+        .into_iter()
+        .map(|name| root.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn check_profile(profile: &str) -> Result<(), String> {
+    if profile.is_empty() || profile.contains(['/', '\\']) || profile.starts_with('.') {
+        return Err(format!("Invalid env profile: {}", profile));
+    }
+    Ok(())
+}
+
+/// Each variable of `profile` with the file its value comes from; later
+/// files override earlier ones.
+fn load(root: &Path, profile: &str) -> Result<Vec<(String, String, PathBuf)>, String> {
+    check_profile(profile)?;
+    let files = profile_files(root, profile);
+    if files.is_empty() && profile != DEFAULT_PROFILE {
+        return Err(format!("No env files for profile {}", profile));
+    }
+    let mut defined = HashMap::new();
+    let mut variables: Vec<(String, String, PathBuf)> = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        for (key, value) in parse(&text, &mut defined) {
+            variables.retain(|(existing, _, _)| existing != &key);
+            variables.push((key, value, file.clone()));
+        }
+    }
+    Ok(variables)
+}
+
+/// The environment `profile` sets for processes started in `cwd`, read
+/// from the workspace root that holds `cwd`, or the active one.
+pub fn profile_env(
+    windows: &WindowRegistry,
+    window: &Window,
+    cwd: Option<&Path>,
+    profile: &str,
+) -> Result<HashMap<String, String>, String> {
+    let root = windows
+        .scope(window.label())
+        .workspace
+        .root_for(cwd)
+        .ok_or_else(|| "No workspace is open".to_string())?;
+    Ok(load(&root, profile)?
+        .into_iter()
+        .map(|(key, value, _)| (key, value))
+        .collect())
+}
+
+fn is_secret(key: &str, value: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    if SECRET_MARKERS.iter().any(|marker| key.contains(marker)) || key.ends_with("_KEY") {
+        return true;
+    }
+    // Credentials in a URL, such as a database connection string.
+    value.split_once("://").is_some_and(|(_, rest)| {
+        rest.split(['/', '?', '#'])
+            .next()
+            .is_some_and(|authority| authority.contains('@') && authority.contains(':'))
+    })
+}
+
+fn env_root(
+    root: Option<String>,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<PathBuf, String> {
+    match root {
+        Some(root) => workspace::authorize(windows, window, &root),
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+/// The env profiles of `root` (default: the active workspace): `default`
+/// when `.env` or `.env.local` exists, and one per other `.env.<name>`
+/// file, such as `development` or `test`. Example files are left out.
+#[tauri::command]
+pub async fn list_env_profiles(
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<EnvProfile>, String> {
+    let root = env_root(root, &window, &windows)?;
+    let mut names: Vec<String> = fs::read_dir(&root)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let profile = name.strip_prefix(".env.")?;
+            let profile = profile.strip_suffix(".local").unwrap_or(profile);
+            let valid = !profile.is_empty()
+                && profile != "local"
+                && !NOT_PROFILES.contains(&profile)
+                && check_profile(profile).is_ok();
+            valid.then(|| profile.to_string())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+
+    let display = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    Ok(names
+        .into_iter()
+        .map(|name| EnvProfile {
+            files: profile_files(&root, &name).iter().map(display).collect(),
+            name,
+        })
+        .filter(|profile| !profile.files.is_empty())
+        .collect())
+}
+
+/// The variables `profile` (default: `default`) sets, for showing in the
+/// UI. Values of secret-looking variables (tokens, passwords, keys,
+/// URLs with credentials) are redacted; the real values only ever go to
+/// the processes started with the profile.
+#[tauri::command]
+pub async fn get_env_profile(
+    profile: Option<String>,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<EnvProfileView, String> {
+    let root = env_root(root, &window, &windows)?;
+    let profile = profile.unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    let files = profile_files(&root, &profile)
+        .iter()
+        .map(|file| file.to_string_lossy().to_string())
+        .collect();
+    let variables = load(&root, &profile)?
+        .into_iter()
+        .map(|(key, value, file)| {
+            let secret = is_secret(&key, &value);
+            EnvVariable {
+                value: if secret { REDACTED.to_string() } else { value },
+                secret,
+                file: file.to_string_lossy().to_string(),
+                key,
+            }
+        })
+        .collect();
+    Ok(EnvProfileView {
+        profile,
+        files,
+        variables,
+    })
+}
@@ -0,0 +1,262 @@
+use globset::GlobBuilder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const FILE_NAME: &str = ".editorconfig";
+/// `{1..100}` expands to a list of alternatives; wider ranges are left as
+/// they are and never match.
+const MAX_RANGE_EXPANSION: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+/// The EditorConfig properties that apply to one file. Unset properties are
+/// `None`, leaving the editor's own setting in force.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<u32>,
+    pub tab_width: Option<u32>,
+    pub end_of_line: Option<EndOfLine>,
+    pub charset: Option<String>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub max_line_length: Option<u32>,
+    /// The `.editorconfig` files that were read, nearest last.
+    pub sources: Vec<String>,
+}
+
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+struct ConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+fn parse(text: &str) -> ConfigFile {
+    let mut file = ConfigFile {
+        root: false,
+        sections: Vec::new(),
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            if let Some(end) = header.rfind(']') {
+                file.sections.push(Section {
+                    glob: header[..end].to_string(),
+                    properties: Vec::new(),
+                });
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+        match file.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            // The preamble only knows `root`.
+            None if key == "root" => file.root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+    file
+}
+
+/// Rewrites `{n1..n2}` as `{n1,n1+1,...,n2}`, which globset understands.
+fn expand_ranges(glob: &str) -> String {
+    let mut out = String::new();
+    let mut rest = glob;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let body = &rest[start + 1..start + len];
+        let range = body
+            .split_once("..")
+            .and_then(|(from, to)| Some((from.parse::<i64>().ok()?, to.parse::<i64>().ok()?)));
+        match range {
+            Some((from, to)) if (to - from).abs() <= MAX_RANGE_EXPANSION => {
+                let numbers: Vec<String> = if from <= to {
+                    (from..=to).map(|n| n.to_string()).collect()
+                } else {
+                    (to..=from).rev().map(|n| n.to_string()).collect()
+                };
+                out.push('{');
+                out.push_str(&numbers.join(","));
+                out.push('}');
+            }
+            _ => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether a section glob from the `.editorconfig` in `dir` covers `path`.
+/// Globs without a `/` match the file name at any depth below `dir`.
+fn section_matches(glob: &str, dir: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(dir) else {
+        return false;
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    let glob = expand_ranges(glob);
+    let glob = match glob.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if glob.contains('/') => glob,
+        None => format!("**/{}", glob),
+    };
+    GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .backslash_escape(true)
+        .build()
+        .map(|glob| glob.compile_matcher().is_match(&relative))
+        .unwrap_or(false)
+}
+
+/// Reads `.editorconfig` files from the file's folder upwards until one
+/// says `root = true`, and merges the sections that match the file. Nearer
+/// files win, and within a file later sections win.
+pub fn resolve(path: &Path) -> EditorConfig {
+    let mut files: Vec<(PathBuf, ConfigFile)> = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let candidate = dir.join(FILE_NAME);
+        if let Ok(text) = fs::read_to_string(&candidate) {
+            let file = parse(&text);
+            let root = file.root;
+            files.push((dir.to_path_buf(), file));
+            if root {
+                break;
+            }
+        }
+    }
+
+    let mut properties: HashMap<String, String> = HashMap::new();
+    let mut sources = Vec::new();
+    for (dir, file) in files.iter().rev() {
+        sources.push(dir.join(FILE_NAME).to_string_lossy().to_string());
+        for section in &file.sections {
+            if section_matches(&section.glob, dir, path) {
+                for (key, value) in &section.properties {
+                    properties.insert(key.clone(), value.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+    // `unset` clears whatever a farther file said.
+    properties.retain(|_, value| value != "unset");
+
+    let get = |key: &str| properties.get(key).map(String::as_str);
+    let number = |key: &str| get(key).and_then(|value| value.parse::<u32>().ok());
+    let flag = |key: &str| match get(key) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        _ => None,
+    };
+
+    let indent_style = match get("indent_style") {
+        Some("tab") => Some(IndentStyle::Tab),
+        Some("space") => Some(IndentStyle::Space),
+        _ => None,
+    };
+    let mut tab_width = number("tab_width");
+    let mut indent_size = number("indent_size");
+    // Defaults from the spec: `indent_size = tab` means the tab width, and
+    // the tab width follows a numeric indent size.
+    if get("indent_size") == Some("tab")
+        || (indent_size.is_none() && indent_style == Some(IndentStyle::Tab))
+    {
+        indent_size = tab_width;
+    }
+    if tab_width.is_none() {
+        tab_width = indent_size;
+    }
+
+    EditorConfig {
+        indent_style,
+        indent_size,
+        tab_width,
+        end_of_line: match get("end_of_line") {
+            Some("lf") => Some(EndOfLine::Lf),
+            Some("crlf") => Some(EndOfLine::Crlf),
+            Some("cr") => Some(EndOfLine::Cr),
+            _ => None,
+        },
+        charset: get("charset").map(str::to_string),
+        trim_trailing_whitespace: flag("trim_trailing_whitespace"),
+        insert_final_newline: flag("insert_final_newline"),
+        max_line_length: number("max_line_length"),
+        sources,
+    }
+}
+
+/// Applies the rules that only touch whitespace at save time: trailing
+/// whitespace and the final newline. Line endings and charset are left to
+/// the editor, which already passes them to `save_file`.
+pub fn apply_on_save(config: &EditorConfig, content: &str) -> String {
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut text = content.to_string();
+
+    if config.trim_trailing_whitespace == Some(true) {
+        let ends_with_newline = text.ends_with('\n');
+        text = text
+            .lines()
+            .map(|line| line.trim_end_matches([' ', '\t']))
+            .collect::<Vec<_>>()
+            .join(eol);
+        if ends_with_newline {
+            text.push_str(eol);
+        }
+    }
+
+    match config.insert_final_newline {
+        Some(true) if !text.is_empty() && !text.ends_with('\n') => text.push_str(eol),
+        Some(false) => {
+            let trimmed = text.trim_end_matches(['\r', '\n']).len();
+            text.truncate(trimmed);
+        }
+        _ => {}
+    }
+    text
+}
+
+#[tauri::command]
+pub async fn get_editor_config(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<EditorConfig, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    Ok(resolve(&path))
+}
@@ -0,0 +1,123 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+
+use crate::line_endings::{self, LineEnding};
+
+/// Text decoded from disk along with what we learned about its encoding,
+/// so a later save can write it back byte-for-byte compatible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedText {
+    pub content: String,
+    pub encoding: String,
+    pub has_bom: bool,
+    /// `None` when the text has no line breaks at all.
+    pub eol: Option<LineEnding>,
+    /// True when some bytes could not be mapped and were replaced with U+FFFD.
+    pub had_errors: bool,
+}
+
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return DecodedText {
+            eol: line_endings::detect(&content),
+            content: content.into_owned(),
+            encoding: encoding.name().to_string(),
+            has_bom: true,
+            had_errors,
+        };
+    }
+
+    let encoding = detect_without_bom(bytes);
+    let (content, had_errors) = encoding.decode_without_bom_handling(bytes);
+    DecodedText {
+        eol: line_endings::detect(&content),
+        content: content.into_owned(),
+        encoding: encoding.name().to_string(),
+        has_bom: false,
+        had_errors,
+    }
+}
+
+fn detect_without_bom(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    if let Some(utf16) = sniff_utf16(bytes) {
+        return utf16;
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// BOM-less UTF-16 is common for files produced by Windows tooling. ASCII-heavy
+/// UTF-16 text has a zero in every other byte, which no single-byte charset does.
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 4 {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+pub fn encode(text: &str, label: &str, with_bom: bool) -> Result<Vec<u8>, String> {
+    let encoding = Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", label))?;
+
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+
+    // encoding_rs only decodes UTF-16; its encoder falls back to UTF-8, so
+    // the UTF-16 variants are written by hand.
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        if with_bom {
+            bytes.extend_from_slice(if little_endian {
+                &[0xFF, 0xFE]
+            } else {
+                &[0xFE, 0xFF]
+            });
+        }
+        for unit in text.encode_utf16() {
+            let pair = if little_endian {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            };
+            bytes.extend_from_slice(&pair);
+        }
+        return Ok(bytes);
+    }
+
+    if with_bom && encoding == UTF_8 {
+        bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+
+    let (encoded, _, had_unmappable) = encoding.encode(text);
+    if had_unmappable {
+        return Err(format!(
+            "Content contains characters that cannot be represented in {}",
+            encoding.name()
+        ));
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
@@ -0,0 +1,173 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+
+pub const DEFAULT_EXCLUDES: &[&str] = &[".git", "node_modules", "target", "dist", ".DS_Store"];
+
+/// User-configured exclusion globs for a window. Patterns without a `/` match
+/// any file or directory name; patterns with one match the root-relative path.
+pub struct ExclusionSettings {
+    globs: Mutex<Vec<String>>,
+}
+
+impl Default for ExclusionSettings {
+    fn default() -> Self {
+        ExclusionSettings {
+            globs: Mutex::new(DEFAULT_EXCLUDES.iter().map(|g| g.to_string()).collect()),
+        }
+    }
+}
+
+impl ExclusionSettings {
+    pub fn globs(&self) -> Vec<String> {
+        self.globs.lock().unwrap().clone()
+    }
+
+    pub fn set_globs(&self, globs: Vec<String>) -> Result<(), String> {
+        build_globset(&globs)?;
+        *self.globs.lock().unwrap() = globs;
+        Ok(())
+    }
+}
+
+/// Decides whether a path under `root` should be hidden, combining the
+/// configured globs with every `.gitignore` between the root and the path.
+/// Shared by directory listings, tree walks, and search.
+pub struct ExclusionMatcher {
+    root: PathBuf,
+    globs: GlobSet,
+    gitignores: Mutex<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl ExclusionMatcher {
+    pub fn new(root: &Path, globs: &[String]) -> Result<ExclusionMatcher, String> {
+        Ok(ExclusionMatcher {
+            root: root.to_path_buf(),
+            globs: build_globset(globs)?,
+            gitignores: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => return false,
+        };
+
+        globs_match(&self.globs, relative) || self.is_gitignored(path, is_dir)
+    }
+
+    /// Walks from the path's directory up to the root; the deepest
+    /// `.gitignore` with an opinion wins, as in git itself.
+    fn is_gitignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if !current.starts_with(&self.root) {
+                break;
+            }
+            if let Some(ignored) = self.gitignore_verdict(current, path, is_dir) {
+                return ignored;
+            }
+            dir = current.parent();
+        }
+        false
+    }
+
+    fn gitignore_verdict(&self, dir: &Path, path: &Path, is_dir: bool) -> Option<bool> {
+        let mut cache = self.gitignores.lock().unwrap();
+        let gitignore = cache
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| load_gitignore(dir, dir == self.root.as_path()));
+        match gitignore
+            .as_ref()?
+            .matched_path_or_any_parents(path, is_dir)
+        {
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+            Match::None => None,
+        }
+    }
+}
+
+fn load_gitignore(dir: &Path, is_root: bool) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+
+    let gitignore = dir.join(".gitignore");
+    if gitignore.is_file() {
+        found |= builder.add(&gitignore).is_none();
+    }
+    if is_root {
+        let info_exclude = dir.join(".git").join("info").join("exclude");
+        if info_exclude.is_file() {
+            found |= builder.add(&info_exclude).is_none();
+        }
+    }
+
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
+
+/// Applies the pattern convention of `ExclusionSettings` to a root-relative
+/// path: the file name is tried on its own, then the whole path.
+pub fn globs_match(globs: &GlobSet, relative: &Path) -> bool {
+    relative
+        .file_name()
+        .is_some_and(|name| globs.is_match(Path::new(name)))
+        || globs.is_match(relative)
+}
+
+pub fn build_globset(globs: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in globs {
+        let glob =
+            Glob::new(pattern).map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build glob patterns: {}", e))
+}
+
+/// Builds a matcher for `path` using the workspace root that contains it,
+/// falling back to the path itself when it is outside every root.
+pub fn matcher_for(
+    windows: &WindowRegistry,
+    window: &Window,
+    path: &Path,
+) -> Result<ExclusionMatcher, String> {
+    let scope = windows.scope(window.label());
+    let root = scope
+        .workspace
+        .roots()
+        .into_iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .unwrap_or_else(|| path.to_path_buf());
+    ExclusionMatcher::new(&root, &scope.exclusions.globs())
+}
+
+#[tauri::command]
+pub async fn get_exclusion_globs(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<String>, String> {
+    Ok(windows.scope(window.label()).exclusions.globs())
+}
+
+#[tauri::command]
+pub async fn set_exclusion_globs(
+    globs: Vec<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    windows.scope(window.label()).exclusions.set_globs(globs)
+}
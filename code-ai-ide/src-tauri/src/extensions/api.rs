@@ -0,0 +1,170 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tauri::{Manager, Window};
+
+use super::Capability;
+use crate::command_registry::{self, CommandInfo, CommandRegistry};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Emitted as `extension-event` when an extension emits an event.
+#[derive(Debug, Clone, Serialize)]
+struct ExtensionEvent {
+    extension_id: String,
+    event: String,
+    payload: Value,
+}
+
+/// Emitted as `extension-message` for `window/showMessage`.
+#[derive(Debug, Clone, Serialize)]
+struct ExtensionMessage {
+    extension_id: String,
+    level: String,
+    message: String,
+}
+
+/// What a call from an extension may touch: its own id, the capabilities
+/// its manifest declares, and the window it runs in.
+#[derive(Clone)]
+pub struct ApiContext {
+    pub extension_id: String,
+    pub capabilities: Vec<Capability>,
+    pub window: Window,
+    /// Events the extension asked to receive with `events/subscribe`.
+    pub subscriptions: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ApiContext {
+    fn require(&self, capability: Capability) -> Result<(), String> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Extension {} lacks the {} capability",
+                self.extension_id,
+                capability.as_str()
+            ))
+        }
+    }
+
+    /// Paths go through the same workspace sandbox as the frontend's.
+    fn authorize(&self, params: &Value) -> Result<std::path::PathBuf, String> {
+        let path = string_param(params, "path")?;
+        let windows = self.window.state::<WindowRegistry>();
+        workspace::authorize(&windows, &self.window, path)
+    }
+
+    pub fn is_subscribed(&self, event: &str) -> bool {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.contains(event) || subscriptions.contains("*")
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, String> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing parameter: {}", name))
+}
+
+/// Runs one API call made by an extension. Blocking; hosts call it from
+/// their reader thread or from inside the WASM import.
+pub fn handle_call(ctx: &ApiContext, method: &str, params: &Value) -> Result<Value, String> {
+    match method {
+        "fs/readFile" => {
+            ctx.require(Capability::FsRead)?;
+            let path = ctx.authorize(params)?;
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            Ok(json!({ "content": content }))
+        }
+        "fs/listDirectory" => {
+            ctx.require(Capability::FsRead)?;
+            let path = ctx.authorize(params)?;
+            let mut entries = Vec::new();
+            for entry in
+                fs::read_dir(&path).map_err(|e| format!("Failed to read directory: {}", e))?
+            {
+                let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                entries.push(json!({
+                    "name": entry.file_name().to_string_lossy(),
+                    "path": entry.path().to_string_lossy(),
+                    "is_dir": is_dir,
+                }));
+            }
+            Ok(Value::Array(entries))
+        }
+        "fs/writeFile" => {
+            ctx.require(Capability::FsWrite)?;
+            let path = ctx.authorize(params)?;
+            let content = string_param(params, "content")?;
+            fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+            Ok(Value::Null)
+        }
+        "commands/register" => {
+            ctx.require(Capability::Commands)?;
+            let mut commands: Vec<CommandInfo> =
+                serde_json::from_value(params.get("commands").cloned().unwrap_or_default())
+                    .map_err(|e| format!("Invalid commands: {}", e))?;
+            let prefix = format!("{}.", ctx.extension_id);
+            for command in &mut commands {
+                if !command.id.starts_with(&prefix) {
+                    return Err(format!("Command ids must start with {}", prefix));
+                }
+                // Extensions run their own commands; they cannot point one
+                // at a backend command.
+                command.invoke = None;
+                command.source = ctx.extension_id.clone();
+            }
+            let app = ctx.window.app_handle();
+            app.state::<CommandRegistry>().register(commands)?;
+            command_registry::broadcast(&app, "commands-changed");
+            Ok(Value::Null)
+        }
+        "events/subscribe" => {
+            ctx.require(Capability::Events)?;
+            let event = string_param(params, "event")?;
+            ctx.subscriptions.lock().unwrap().insert(event.to_string());
+            Ok(Value::Null)
+        }
+        "events/unsubscribe" => {
+            ctx.require(Capability::Events)?;
+            let event = string_param(params, "event")?;
+            ctx.subscriptions.lock().unwrap().remove(event);
+            Ok(Value::Null)
+        }
+        "events/emit" => {
+            ctx.require(Capability::Events)?;
+            let _ = ctx.window.emit(
+                "extension-event",
+                ExtensionEvent {
+                    extension_id: ctx.extension_id.clone(),
+                    event: string_param(params, "event")?.to_string(),
+                    payload: params.get("payload").cloned().unwrap_or_default(),
+                },
+            );
+            Ok(Value::Null)
+        }
+        // Needs no capability: it only shows text in the extension's name.
+        "window/showMessage" => {
+            let _ = ctx.window.emit(
+                "extension-message",
+                ExtensionMessage {
+                    extension_id: ctx.extension_id.clone(),
+                    level: params
+                        .get("level")
+                        .and_then(Value::as_str)
+                        .unwrap_or("info")
+                        .to_string(),
+                    message: string_param(params, "message")?.to_string(),
+                },
+            );
+            Ok(Value::Null)
+        }
+        _ => Err(format!("Unknown method: {}", method)),
+    }
+}
@@ -0,0 +1,416 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::ChildStdin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{Manager, Window};
+use wasmtime::{
+    AsContext, AsContextMut, Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, TypedFunc,
+};
+
+use super::api::{self, ApiContext};
+use super::{Manifest, Runtime};
+use crate::lsp::{read_messages, write_message};
+use crate::process::{self, CommandExit, ExecOptions, ProcessHooks, ProcessKind, ProcessSignal};
+use crate::window_state::{WindowRegistry, WindowState};
+
+/// Replaced in a process runtime's command and arguments.
+const EXTENSION_PATH: &str = "${extensionPath}";
+/// How long a request to an extension may take before it is abandoned.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a process extension gets to exit after `deactivate`.
+const STOP_GRACE: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Instructions a WASM extension may execute per call into it, so a
+/// runaway loop traps instead of hanging the host.
+const WASM_FUEL: u64 = 2_000_000_000;
+const WASM_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Emitted as `extension-exit` when a process extension stops on its own.
+#[derive(Debug, Clone, Serialize)]
+struct ExtensionExit {
+    extension_id: String,
+    exit_code: Option<i32>,
+}
+
+type Pending = Arc<Mutex<HashMap<u64, Sender<Result<Value, String>>>>>;
+
+/// A process extension speaks JSON-RPC over stdio with the same framing as
+/// a language server.
+struct ProcessHost {
+    process_id: String,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    next_id: AtomicU64,
+}
+
+struct WasmState {
+    ctx: ApiContext,
+    limits: StoreLimits,
+}
+
+/// A WASM extension exports `memory`, `alloc(len) -> ptr` and
+/// `handle(ptr, len) -> packed`, and imports `codeai.call(ptr, len) ->
+/// packed` for API calls. Messages are JSON; `packed` is the pointer in
+/// the high 32 bits and the length in the low 32, or 0 for no reply.
+struct WasmHost {
+    store: Store<WasmState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    handle: TypedFunc<(i32, i32), i64>,
+}
+
+enum Transport {
+    Process(ProcessHost),
+    Wasm(Mutex<WasmHost>),
+}
+
+/// An extension running in one window.
+pub struct RunningExtension {
+    ctx: ApiContext,
+    transport: Transport,
+}
+
+impl RunningExtension {
+    pub fn is_subscribed(&self, event: &str) -> bool {
+        self.ctx.is_subscribed(event)
+    }
+
+    /// The process backing the extension, if it runs as one.
+    pub fn process_id(&self) -> Option<&str> {
+        match &self.transport {
+            Transport::Process(host) => Some(&host.process_id),
+            Transport::Wasm(_) => None,
+        }
+    }
+
+    /// Sends a request and waits for the reply. Blocking.
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        match &self.transport {
+            Transport::Process(host) => {
+                let id = host.next_id.fetch_add(1, Ordering::SeqCst);
+                let (sender, receiver) = mpsc::channel();
+                host.pending.lock().unwrap().insert(id, sender);
+                let message =
+                    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+                if let Err(e) = write_message(&host.stdin, &message) {
+                    host.pending.lock().unwrap().remove(&id);
+                    return Err(e);
+                }
+                let reply = receiver.recv_timeout(REQUEST_TIMEOUT);
+                host.pending.lock().unwrap().remove(&id);
+                reply.map_err(|_| {
+                    format!(
+                        "Extension {} did not answer {}",
+                        self.ctx.extension_id, method
+                    )
+                })?
+            }
+            Transport::Wasm(host) => {
+                let reply = host
+                    .lock()
+                    .unwrap()
+                    .call(&json!({ "method": method, "params": params }))?;
+                match reply {
+                    Some(reply) => from_reply(reply),
+                    None => Ok(Value::Null),
+                }
+            }
+        }
+    }
+
+    /// Sends a notification; no reply is expected. Blocking.
+    pub fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        match &self.transport {
+            Transport::Process(host) => write_message(
+                &host.stdin,
+                &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+            ),
+            Transport::Wasm(host) => host
+                .lock()
+                .unwrap()
+                .call(&json!({ "method": method, "params": params }))
+                .map(|_| ()),
+        }
+    }
+
+    /// Sends `deactivate` and, for a process, kills it if it has not
+    /// exited after a grace period. Blocking.
+    pub fn stop(&self, scope: &WindowState) {
+        let _ = self.notify("deactivate", Value::Null);
+        let Transport::Process(host) = &self.transport else {
+            return;
+        };
+        let deadline = Instant::now() + STOP_GRACE;
+        while scope.processes.is_running(&host.process_id) {
+            if Instant::now() >= deadline {
+                scope
+                    .processes
+                    .signal(&host.process_id, ProcessSignal::Kill);
+                break;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// `{ "result": ... }` or `{ "error": ... }`, as both transports reply.
+fn from_reply(reply: Value) -> Result<Value, String> {
+    match reply.get("error") {
+        Some(error) if !error.is_null() => Err(error
+            .get("message")
+            .and_then(Value::as_str)
+            .or_else(|| error.as_str())
+            .unwrap_or("Extension request failed")
+            .to_string()),
+        _ => Ok(reply.get("result").cloned().unwrap_or_default()),
+    }
+}
+
+/// Starts the extension in `dir` for the window. Blocking: a process
+/// extension is spawned, a WASM module compiled and instantiated.
+pub fn start(
+    window: &Window,
+    scope: Arc<WindowState>,
+    dir: &Path,
+    manifest: &Manifest,
+) -> Result<Arc<RunningExtension>, String> {
+    let ctx = ApiContext {
+        extension_id: manifest.id.clone(),
+        capabilities: manifest.capabilities.clone(),
+        window: window.clone(),
+        subscriptions: Arc::new(Mutex::new(HashSet::new())),
+    };
+    let transport = match &manifest.runtime {
+        Runtime::Process { command, args } => {
+            Transport::Process(start_process(window, scope, dir, command, args, &ctx)?)
+        }
+        Runtime::Wasm { module } => {
+            Transport::Wasm(Mutex::new(WasmHost::start(&dir.join(module), ctx.clone())?))
+        }
+    };
+    Ok(Arc::new(RunningExtension { ctx, transport }))
+}
+
+fn start_process(
+    window: &Window,
+    scope: Arc<WindowState>,
+    dir: &Path,
+    command: &str,
+    args: &[String],
+    ctx: &ApiContext,
+) -> Result<ProcessHost, String> {
+    let dir = dir.to_string_lossy();
+    let command = command.replace(EXTENSION_PATH, &dir);
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.replace(EXTENSION_PATH, &dir))
+        .collect();
+    // Runs in the workspace like a task, so trust and the execution policy
+    // apply to extensions too.
+    let cwd = scope
+        .workspace
+        .active()
+        .or_else(|| scope.workspace.roots().into_iter().next())
+        .map(|root| root.to_string_lossy().to_string());
+    let windows = window.state::<WindowRegistry>();
+    let cmd = process::prepare(
+        &command,
+        &args,
+        cwd,
+        &ExecOptions::default(),
+        window,
+        &windows,
+    )?;
+
+    let process_id = Arc::new(OnceLock::<String>::new());
+    let on_exit = {
+        let (window, scope, process_id) = (window.clone(), scope.clone(), process_id.clone());
+        let extension_id = ctx.extension_id.clone();
+        move |exit: &CommandExit| {
+            let Some(process_id) = process_id.get() else {
+                return;
+            };
+            if scope.extensions.remove_process(&extension_id, process_id) {
+                let _ = window.emit(
+                    "extension-exit",
+                    ExtensionExit {
+                        extension_id,
+                        exit_code: exit.exit_code,
+                    },
+                );
+            }
+        }
+    };
+    let (id, stdin, stdout) = process::start_attached(
+        window.clone(),
+        scope,
+        cmd,
+        ProcessKind::Extension,
+        ProcessHooks {
+            on_output: None,
+            on_exit: Some(Box::new(on_exit)),
+        },
+    )?;
+    let _ = process_id.set(id.clone());
+
+    let stdin = Arc::new(Mutex::new(stdin));
+    let pending = Pending::default();
+    {
+        let (ctx, stdin, pending) = (ctx.clone(), stdin.clone(), pending.clone());
+        thread::spawn(move || {
+            read_messages(stdout, |message| {
+                if let Some(method) = message.get("method").and_then(Value::as_str) {
+                    let params = message.get("params").cloned().unwrap_or_default();
+                    let result = api::handle_call(&ctx, method, &params);
+                    // Notifications from the extension get no reply.
+                    let Some(id) = message.get("id") else {
+                        return;
+                    };
+                    let reply = match result {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        Err(e) => json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": -32000, "message": e },
+                        }),
+                    };
+                    let _ = write_message(&stdin, &reply);
+                } else if let Some(id) = message.get("id").and_then(Value::as_u64) {
+                    if let Some(sender) = pending.lock().unwrap().remove(&id) {
+                        let _ = sender.send(from_reply(message));
+                    }
+                }
+            })
+        });
+    }
+    Ok(ProcessHost {
+        process_id: id,
+        stdin,
+        pending,
+        next_id: AtomicU64::new(1),
+    })
+}
+
+fn trap(message: impl Into<String>) -> wasmtime::Error {
+    wasmtime::Error::msg(message.into())
+}
+
+/// Copies `value` into guest memory through its `alloc` export.
+fn write_guest(
+    mut store: impl AsContextMut<Data = WasmState>,
+    memory: Memory,
+    alloc: &TypedFunc<i32, i32>,
+    value: &Value,
+) -> wasmtime::Result<i64> {
+    let bytes = serde_json::to_vec(value)?;
+    let len = i32::try_from(bytes.len()).map_err(|_| trap("Message too large"))?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, &bytes)?;
+    Ok(((ptr as u32 as i64) << 32) | len as i64)
+}
+
+fn read_guest(
+    store: impl AsContext<Data = WasmState>,
+    memory: Memory,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<Value> {
+    let mut bytes = vec![0; len as u32 as usize];
+    memory.read(&store, ptr as u32 as usize, &mut bytes)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// `codeai.call`: runs an API call for the guest and hands the reply back
+/// in its own memory.
+fn host_call(mut caller: Caller<'_, WasmState>, ptr: i32, len: i32) -> wasmtime::Result<i64> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| trap("Extension exports no memory"))?;
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| trap("Extension exports no alloc"))?
+        .typed::<i32, i32>(&caller)?;
+    let reply = match read_guest(&caller, memory, ptr, len) {
+        Ok(request) => {
+            let method = request
+                .get("method")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let params = request.get("params").cloned().unwrap_or_default();
+            match api::handle_call(&caller.data().ctx, method, &params) {
+                Ok(result) => json!({ "result": result }),
+                Err(e) => json!({ "error": e }),
+            }
+        }
+        Err(e) => json!({ "error": format!("Invalid request: {}", e) }),
+    };
+    write_guest(&mut caller, memory, &alloc, &reply)
+}
+
+impl WasmHost {
+    fn start(module: &Path, ctx: ApiContext) -> Result<WasmHost, String> {
+        let fail = |e: wasmtime::Error| format!("Failed to load extension module: {}", e);
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(fail)?;
+        let module = Module::from_file(&engine, module).map_err(fail)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap("codeai", "call", host_call)
+            .map_err(fail)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(WASM_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(&engine, WasmState { ctx, limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(WASM_FUEL).map_err(fail)?;
+        let instance = linker.instantiate(&mut store, &module).map_err(fail)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| "Extension module exports no memory".to_string())?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(fail)?;
+        let handle = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "handle")
+            .map_err(fail)?;
+        Ok(WasmHost {
+            store,
+            memory,
+            alloc,
+            handle,
+        })
+    }
+
+    /// Passes one message to the guest's `handle` export with a fresh fuel
+    /// budget.
+    fn call(&mut self, message: &Value) -> Result<Option<Value>, String> {
+        let fail = |e: wasmtime::Error| format!("Extension failed: {}", e);
+        self.store.set_fuel(WASM_FUEL).map_err(fail)?;
+        let packed =
+            write_guest(&mut self.store, self.memory, &self.alloc, message).map_err(fail)?;
+        let (ptr, len) = ((packed >> 32) as i32, packed as i32);
+        let packed = self
+            .handle
+            .call(&mut self.store, (ptr, len))
+            .map_err(fail)?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        let (ptr, len) = ((packed >> 32) as i32, packed as i32);
+        read_guest(&self.store, self.memory, ptr, len)
+            .map(Some)
+            .map_err(fail)
+    }
+}
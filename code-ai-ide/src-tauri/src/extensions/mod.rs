@@ -0,0 +1,603 @@
+pub mod api;
+pub mod host;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::api::dialog;
+use tauri::{AppHandle, Manager, State, Window};
+
+use self::host::RunningExtension;
+use crate::app_data;
+use crate::command_registry::{self, CommandInfo, CommandRegistry};
+use crate::fs_ops;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+const EXTENSIONS_DIR: &str = "extensions";
+const STATE_FILE: &str = "extensions.json";
+const MANIFEST_FILE: &str = "extension.json";
+
+/// What an extension may do through the API. Declared in the manifest and
+/// shown to the user before installing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Capability {
+    #[serde(rename = "fs.read")]
+    FsRead,
+    #[serde(rename = "fs.write")]
+    FsWrite,
+    #[serde(rename = "commands")]
+    Commands,
+    #[serde(rename = "events")]
+    Events,
+}
+
+impl Capability {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Capability::FsRead => "fs.read",
+            Capability::FsWrite => "fs.write",
+            Capability::Commands => "commands",
+            Capability::Events => "events",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::FsRead => "Read files in the workspace",
+            Capability::FsWrite => "Write files in the workspace",
+            Capability::Commands => "Add commands",
+            Capability::Events => "Send and receive editor events",
+        }
+    }
+}
+
+/// How the extension runs. A process gets `${extensionPath}` replaced in
+/// its command and arguments; a WASM module path is relative to the
+/// extension folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Runtime {
+    Process {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Wasm {
+        module: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContributedCommand {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub when: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Contributions {
+    #[serde(default)]
+    pub commands: Vec<ContributedCommand>,
+}
+
+/// `extension.json` at the root of an extension folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    pub runtime: Runtime,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    #[serde(default)]
+    pub contributes: Contributions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionInfo {
+    #[serde(flatten)]
+    pub manifest: Manifest,
+    pub path: String,
+    pub enabled: bool,
+    /// Running in the calling window.
+    pub active: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExtensionData {
+    enabled: Vec<String>,
+}
+
+/// Which installed extensions are enabled, shared by every window and
+/// persisted in the app data directory.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    data: Mutex<Option<(PathBuf, ExtensionData)>>,
+}
+
+impl ExtensionRegistry {
+    fn with_data<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut ExtensionData) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.data.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_data_path(app, STATE_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+
+    fn enabled(&self, app: &AppHandle) -> Result<Vec<String>, String> {
+        self.with_data(app, |data| (data.enabled.clone(), false))
+    }
+
+    fn set_enabled(&self, app: &AppHandle, id: &str, enabled: bool) -> Result<(), String> {
+        self.with_data(app, |data| {
+            data.enabled.retain(|existing| existing != id);
+            if enabled {
+                data.enabled.push(id.to_string());
+            }
+            ((), true)
+        })
+    }
+}
+
+/// Extensions running in a window, keyed by extension id.
+#[derive(Default)]
+pub struct ExtensionHosts {
+    running: Mutex<HashMap<String, Arc<RunningExtension>>>,
+}
+
+impl ExtensionHosts {
+    fn get(&self, id: &str) -> Option<Arc<RunningExtension>> {
+        self.running.lock().unwrap().get(id).cloned()
+    }
+
+    fn is_running(&self, id: &str) -> bool {
+        self.running.lock().unwrap().contains_key(id)
+    }
+
+    fn all(&self) -> Vec<Arc<RunningExtension>> {
+        self.running.lock().unwrap().values().cloned().collect()
+    }
+
+    fn remove(&self, id: &str) -> Option<Arc<RunningExtension>> {
+        self.running.lock().unwrap().remove(id)
+    }
+
+    /// Forgets `id` if it is still backed by `process_id`, i.e. it was not
+    /// restarted meanwhile. Returns whether it was.
+    pub fn remove_process(&self, id: &str, process_id: &str) -> bool {
+        let mut running = self.running.lock().unwrap();
+        if running.get(id).and_then(|running| running.process_id()) == Some(process_id) {
+            running.remove(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Process extensions are killed with the window's other processes.
+    pub fn clear(&self) {
+        self.running.lock().unwrap().clear();
+    }
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let text = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST_FILE, e))?;
+    let manifest: Manifest =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))?;
+    if !valid_id(&manifest.id) {
+        return Err(format!("Invalid extension id: {}", manifest.id));
+    }
+    let prefix = format!("{}.", manifest.id);
+    if let Some(command) = manifest
+        .contributes
+        .commands
+        .iter()
+        .find(|command| !command.id.starts_with(&prefix))
+    {
+        return Err(format!(
+            "Command id must start with {}: {}",
+            prefix, command.id
+        ));
+    }
+    if let Runtime::Wasm { module } = &manifest.runtime {
+        let relative = Path::new(module);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(format!(
+                "Module path must stay inside the extension: {}",
+                module
+            ));
+        }
+        if !dir.join(relative).is_file() {
+            return Err(format!("Module not found: {}", module));
+        }
+    }
+    Ok(manifest)
+}
+
+fn extension_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    app_data::app_data_path(app, &format!("{}/{}", EXTENSIONS_DIR, id))
+}
+
+/// Every installed extension with a readable manifest, by id.
+fn installed(app: &AppHandle) -> Result<Vec<(PathBuf, Manifest)>, String> {
+    let dir = app_data::app_data_path(app, EXTENSIONS_DIR)?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut found: Vec<(PathBuf, Manifest)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| read_manifest(&path).ok().map(|manifest| (path, manifest)))
+        .collect();
+    found.sort_by(|a, b| a.1.id.cmp(&b.1.id));
+    Ok(found)
+}
+
+fn find_installed(app: &AppHandle, id: &str) -> Result<(PathBuf, Manifest), String> {
+    installed(app)?
+        .into_iter()
+        .find(|(_, manifest)| manifest.id == id)
+        .ok_or_else(|| format!("Extension is not installed: {}", id))
+}
+
+/// Makes the manifest's commands available in the palette.
+fn register_contributions(app: &AppHandle, manifest: &Manifest) -> Result<(), String> {
+    let commands = manifest
+        .contributes
+        .commands
+        .iter()
+        .map(|command| CommandInfo {
+            id: command.id.clone(),
+            title: command.title.clone(),
+            category: command
+                .category
+                .clone()
+                .unwrap_or_else(|| manifest.name.clone()),
+            invoke: None,
+            default_key: command.key.clone(),
+            when: command.when.clone(),
+            source: manifest.id.clone(),
+        })
+        .collect();
+    app.state::<CommandRegistry>().register(commands)
+}
+
+/// Starts the extension in the window and sends it `activate`. Blocking.
+fn activate(
+    window: &Window,
+    scope: Arc<WindowState>,
+    dir: &Path,
+    manifest: &Manifest,
+) -> Result<(), String> {
+    if scope.extensions.is_running(&manifest.id) {
+        return Ok(());
+    }
+    let running = host::start(window, scope.clone(), dir, manifest)?;
+    scope
+        .extensions
+        .running
+        .lock()
+        .unwrap()
+        .insert(manifest.id.clone(), running.clone());
+    let roots: Vec<String> = scope
+        .workspace
+        .roots()
+        .iter()
+        .map(|root| root.to_string_lossy().to_string())
+        .collect();
+    let activated = running.request(
+        "activate",
+        json!({
+            "extensionPath": dir.to_string_lossy(),
+            "workspaceRoots": roots,
+        }),
+    );
+    if let Err(e) = activated {
+        if let Some(running) = scope.extensions.remove(&manifest.id) {
+            running.stop(&scope);
+        }
+        return Err(format!("Failed to activate {}: {}", manifest.id, e));
+    }
+    Ok(())
+}
+
+/// Stops the extension in every window and drops its commands. Blocking.
+fn deactivate_everywhere(app: &AppHandle, id: &str) {
+    for scope in app.state::<WindowRegistry>().scopes() {
+        if let Some(running) = scope.extensions.remove(id) {
+            running.stop(&scope);
+        }
+    }
+    app.state::<CommandRegistry>().unregister_source(id);
+}
+
+/// The install prompt. Capabilities only limit what an extension may ask
+/// the editor for; a process extension is a native program and can do
+/// anything the user can regardless, which the prompt has to say plainly.
+fn describe_capabilities(manifest: &Manifest) -> String {
+    let mut text = match &manifest.runtime {
+        Runtime::Process { command, .. } => format!(
+            "It runs a native program ({}) with your full user rights: it can read, change and \
+             delete any of your files, use the network and start other programs, whatever \
+             capabilities it lists. Only install it if you trust its author.\n\n",
+            command
+        ),
+        Runtime::Wasm { .. } => String::new(),
+    };
+    if manifest.capabilities.is_empty() {
+        text.push_str("It requests no editor capabilities.");
+        return text;
+    }
+    let lines: Vec<String> = manifest
+        .capabilities
+        .iter()
+        .map(|capability| format!("• {}", capability.describe()))
+        .collect();
+    text.push_str(&format!("It requests permission to:\n{}", lines.join("\n")));
+    text
+}
+
+#[tauri::command]
+pub async fn list_extensions(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<Vec<ExtensionInfo>, String> {
+    let enabled = registry.enabled(&app)?;
+    let scope = windows.scope(window.label());
+    Ok(installed(&app)?
+        .into_iter()
+        .map(|(path, manifest)| ExtensionInfo {
+            path: path.to_string_lossy().to_string(),
+            enabled: enabled.contains(&manifest.id),
+            active: scope.extensions.is_running(&manifest.id),
+            manifest,
+        })
+        .collect())
+}
+
+/// Copies the extension folder at `path` into the app data directory after
+/// the user approves its capabilities in a native dialog. Installing over
+/// an existing version stops it first; the enabled state is kept. Returns
+/// `None` when the user declined.
+#[tauri::command]
+pub async fn install_extension(
+    path: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<Option<ExtensionInfo>, String> {
+    let source = workspace::authorize(&windows, &window, &path)?;
+    let manifest = read_manifest(&source)?;
+    let approved = dialog::blocking::ask(
+        Some(&window),
+        "Install extension?",
+        format!(
+            "{} {} ({})\n\n{}",
+            manifest.name,
+            manifest.version,
+            manifest.id,
+            describe_capabilities(&manifest)
+        ),
+    );
+    if !approved {
+        return Ok(None);
+    }
+
+    let target = extension_dir(&app, &manifest.id)?;
+    let staging = extension_dir(&app, &format!(".{}.partial", manifest.id))?;
+    let handle = app.clone();
+    let id = manifest.id.clone();
+    let target_path = target.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let _ = fs::remove_dir_all(&staging);
+        fs_ops::copy_recursive(&source, &staging)
+            .map_err(|e| format!("Failed to copy extension: {}", e))?;
+        deactivate_everywhere(&handle, &id);
+        if target_path.exists() {
+            fs::remove_dir_all(&target_path)
+                .map_err(|e| format!("Failed to replace extension: {}", e))?;
+        }
+        fs::rename(&staging, &target_path)
+            .map_err(|e| format!("Failed to install extension: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Failed to install extension: {}", e))??;
+
+    let enabled = registry.enabled(&app)?.contains(&manifest.id);
+    if enabled {
+        register_contributions(&app, &manifest)?;
+        command_registry::broadcast(&app, "commands-changed");
+    }
+    command_registry::broadcast(&app, "extensions-changed");
+    Ok(Some(ExtensionInfo {
+        path: target.to_string_lossy().to_string(),
+        enabled,
+        active: false,
+        manifest,
+    }))
+}
+
+#[tauri::command]
+pub async fn uninstall_extension(
+    id: String,
+    app: AppHandle,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<(), String> {
+    let (dir, _) = find_installed(&app, &id)?;
+    registry.set_enabled(&app, &id, false)?;
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        deactivate_everywhere(&handle, &id);
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to uninstall extension: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Failed to uninstall extension: {}", e))??;
+    command_registry::broadcast(&app, "commands-changed");
+    command_registry::broadcast(&app, "extensions-changed");
+    Ok(())
+}
+
+/// Enables the extension and starts it in the calling window. Other
+/// windows start it on `activate_extensions` after `extensions-changed`.
+#[tauri::command]
+pub async fn enable_extension(
+    id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<(), String> {
+    let (dir, manifest) = find_installed(&app, &id)?;
+    registry.set_enabled(&app, &id, true)?;
+    register_contributions(&app, &manifest)?;
+    command_registry::broadcast(&app, "commands-changed");
+    command_registry::broadcast(&app, "extensions-changed");
+
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || activate(&window, scope, &dir, &manifest))
+        .await
+        .map_err(|e| format!("Failed to activate extension: {}", e))?
+}
+
+/// Disables the extension and stops it in every window.
+#[tauri::command]
+pub async fn disable_extension(
+    id: String,
+    app: AppHandle,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<(), String> {
+    registry.set_enabled(&app, &id, false)?;
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || deactivate_everywhere(&handle, &id))
+        .await
+        .map_err(|e| format!("Failed to disable extension: {}", e))?;
+    command_registry::broadcast(&app, "commands-changed");
+    command_registry::broadcast(&app, "extensions-changed");
+    Ok(())
+}
+
+/// Starts every enabled extension not yet running in the calling window.
+/// Returns the failures by extension id; one broken extension does not
+/// keep the others from starting.
+#[tauri::command]
+pub async fn activate_extensions(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    registry: State<'_, ExtensionRegistry>,
+) -> Result<HashMap<String, String>, String> {
+    let enabled = registry.enabled(&app)?;
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut failures = HashMap::new();
+        for (dir, manifest) in installed(&app)? {
+            if !enabled.contains(&manifest.id) {
+                continue;
+            }
+            if let Err(e) = register_contributions(&app, &manifest)
+                .and_then(|_| activate(&window, scope.clone(), &dir, &manifest))
+            {
+                failures.insert(manifest.id, e);
+            }
+        }
+        Ok(failures)
+    })
+    .await
+    .map_err(|e| format!("Failed to activate extensions: {}", e))?
+}
+
+/// Runs an extension command in the calling window and returns what the
+/// extension replied.
+#[tauri::command]
+pub async fn execute_extension_command(
+    command: String,
+    args: Option<Value>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Value, String> {
+    let scope = windows.scope(window.label());
+    let running = command
+        .rsplit_once('.')
+        .and_then(|(id, _)| {
+            // Ids may contain dots; try the longest prefix first.
+            let mut prefix = Some(id);
+            while let Some(id) = prefix {
+                if let Some(running) = scope.extensions.get(id) {
+                    return Some(running);
+                }
+                prefix = id.rsplit_once('.').map(|(head, _)| head);
+            }
+            None
+        })
+        .ok_or_else(|| format!("No active extension provides {}", command))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        running.request(
+            "executeCommand",
+            json!({ "command": command, "args": args.unwrap_or_default() }),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to execute extension command: {}", e))?
+}
+
+/// Delivers an editor event to the extensions in the calling window that
+/// subscribed to it. Returns how many received it.
+#[tauri::command]
+pub async fn dispatch_extension_event(
+    event: String,
+    payload: Option<Value>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<usize, String> {
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let params = json!({ "event": event, "payload": payload.unwrap_or_default() });
+        scope
+            .extensions
+            .all()
+            .iter()
+            .filter(|running| running.is_subscribed(&event))
+            .filter(|running| running.notify("event", params.clone()).is_ok())
+            .count()
+    })
+    .await
+    .map_err(|e| format!("Failed to dispatch extension event: {}", e))
+}
@@ -0,0 +1,353 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{State, Window};
+
+use crate::exclude::ExclusionMatcher;
+use crate::walk::walk_files;
+use crate::watcher::{FsChange, FsChangeKind};
+use crate::window_state::{WindowRegistry, WindowState};
+
+const DEFAULT_FUZZY_LIMIT: usize = 50;
+
+/// Flat list of the files in a window's active workspace, built in the
+/// background so features like quick-open never walk the disk per request.
+/// After the initial walk it is kept current from the workspace watcher.
+#[derive(Default)]
+pub struct FileIndex {
+    inner: Arc<Mutex<IndexState>>,
+    subscription: Mutex<Option<u64>>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    root: Option<PathBuf>,
+    files: BTreeMap<PathBuf, IndexedFile>,
+    /// Bumped on every rebuild so a slow walk of an old root cannot
+    /// overwrite the index of the root opened after it.
+    generation: u64,
+    indexing: bool,
+    /// Watcher changes that arrived during the walk, replayed after it.
+    pending: Vec<FsChange>,
+}
+
+struct IndexedFile {
+    /// Path relative to the root with `/` separators, as shown in quick-open.
+    relative: String,
+    /// Which query characters occur in `relative`; a query whose mask is not
+    /// a subset cannot match, which skips most files without scoring them.
+    mask: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub root: Option<String>,
+    pub file_count: usize,
+    pub indexing: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub relative_path: String,
+    pub score: i64,
+    /// Character indices into `relative_path` that matched, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+fn char_mask(text: &str) -> u64 {
+    text.chars().fold(0, |mask, c| {
+        let bit = match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => c as u64 - 'a' as u64,
+            c @ '0'..='9' => 26 + (c as u64 - '0' as u64),
+            '.' => 36,
+            '_' => 37,
+            '-' => 38,
+            '/' => 39,
+            _ => return mask,
+        };
+        mask | (1 << bit)
+    })
+}
+
+impl IndexState {
+    fn insert(&mut self, path: &Path) {
+        let root = match &self.root {
+            Some(root) => root,
+            None => return,
+        };
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => return,
+        };
+        let mask = char_mask(&relative);
+        self.files
+            .insert(path.to_path_buf(), IndexedFile { relative, mask });
+    }
+
+    /// Brings the entries at or under `path` in line with the disk.
+    fn reconcile(&mut self, path: &Path, matcher: &ExclusionMatcher) {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            walk_files(path, Some(matcher), |file| {
+                found.push(file.to_path_buf());
+                true
+            });
+            for file in found {
+                self.insert(&file);
+            }
+        } else if path.is_file() {
+            self.insert(path);
+        } else {
+            // Gone: drop the path itself and, if it was a directory,
+            // everything below it.
+            let below: Vec<PathBuf> = self
+                .files
+                .range(path.to_path_buf()..)
+                .map(|(file, _)| file)
+                .take_while(|file| file.starts_with(path))
+                .cloned()
+                .collect();
+            for file in below {
+                self.files.remove(&file);
+            }
+        }
+    }
+
+    fn apply(&mut self, change: &FsChange, matcher: &ExclusionMatcher) {
+        // Content changes do not affect the file list.
+        if change.kind == FsChangeKind::Modified {
+            return;
+        }
+        for path in &change.paths {
+            self.reconcile(path, matcher);
+        }
+    }
+}
+
+impl FileIndex {
+    pub fn status(&self) -> IndexStatus {
+        let state = self.inner.lock().unwrap();
+        IndexStatus {
+            root: state.root.as_ref().map(|r| r.to_string_lossy().to_string()),
+            file_count: state.files.len(),
+            indexing: state.indexing,
+        }
+    }
+
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.inner.lock().unwrap().files.keys().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.generation += 1;
+        state.root = None;
+        state.files.clear();
+        state.pending.clear();
+        state.indexing = false;
+    }
+
+    fn begin(&self, root: &Path) -> u64 {
+        let mut state = self.inner.lock().unwrap();
+        state.generation += 1;
+        state.root = Some(root.to_path_buf());
+        state.files.clear();
+        state.pending.clear();
+        state.indexing = true;
+        state.generation
+    }
+
+    fn complete(&self, generation: u64, files: Vec<PathBuf>, matcher: &ExclusionMatcher) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        if state.generation != generation {
+            return false;
+        }
+        for file in files {
+            state.insert(&file);
+        }
+        for change in std::mem::take(&mut state.pending) {
+            state.apply(&change, matcher);
+        }
+        state.indexing = false;
+        true
+    }
+
+    /// Ranks indexed files against `query` as a fuzzy subsequence match,
+    /// preferring consecutive runs, word starts and hits in the file name.
+    pub fn fuzzy_find(&self, query: &str, limit: usize) -> Vec<FuzzyMatch> {
+        let query: Vec<char> = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+        let state = self.inner.lock().unwrap();
+
+        if query.is_empty() {
+            return state
+                .files
+                .iter()
+                .take(limit)
+                .map(|(path, file)| FuzzyMatch {
+                    path: path.to_string_lossy().to_string(),
+                    relative_path: file.relative.clone(),
+                    score: 0,
+                    positions: Vec::new(),
+                })
+                .collect();
+        }
+
+        let query_mask = char_mask(&query.iter().collect::<String>());
+        let mut matches: Vec<FuzzyMatch> = state
+            .files
+            .iter()
+            .filter(|(_, file)| file.mask & query_mask == query_mask)
+            .filter_map(|(path, file)| {
+                let (score, positions) = fuzzy_score(&query, &file.relative)?;
+                Some(FuzzyMatch {
+                    path: path.to_string_lossy().to_string(),
+                    relative_path: file.relative.clone(),
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    fn replace_subscription(&self, id: u64) -> Option<u64> {
+        self.subscription.lock().unwrap().replace(id)
+    }
+}
+
+fn is_word_start(chars: &[char], i: usize) -> bool {
+    i == 0
+        || matches!(chars[i - 1], '/' | '_' | '-' | '.' | ' ')
+        || (chars[i].is_uppercase() && chars[i - 1].is_lowercase())
+}
+
+/// Finds the shortest window of `candidate` containing `query` as a
+/// subsequence and scores the match inside it. `query` is lowercase.
+fn fuzzy_score(query: &[char], candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // Leftmost end of a match, then walk back to the latest start.
+    let mut next = 0;
+    let end = lower.iter().position(|&c| {
+        if c == query[next] {
+            next += 1;
+        }
+        next == query.len()
+    })?;
+    let mut remaining = query.len();
+    let start = (0..=end).rev().find(|&i| {
+        if lower[i] == query[remaining - 1] {
+            remaining -= 1;
+        }
+        remaining == 0
+    })?;
+
+    let basename_start = lower.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+    let mut score = 0i64;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut previous: Option<usize> = None;
+    for i in start..=end {
+        if positions.len() == query.len() || lower[i] != query[positions.len()] {
+            continue;
+        }
+        score += 16;
+        match previous {
+            Some(p) if p + 1 == i => score += 12,
+            Some(p) => score -= (i - p - 1).min(10) as i64,
+            None => {}
+        }
+        if is_word_start(&chars, i) {
+            score += 10;
+        }
+        if i >= basename_start {
+            score += 6;
+        }
+        positions.push(i);
+        previous = Some(i);
+    }
+    // Among equal matches, shorter paths are usually what was meant.
+    score -= chars.len() as i64 / 8;
+    Some((score, positions))
+}
+
+/// Rebuilds the window's index for `root` on a blocking thread and emits
+/// `workspace-indexed` with the final status when done. The index then
+/// follows watcher events, so this runs once per opened workspace.
+pub fn start_indexing(
+    window: Window,
+    scope: Arc<WindowState>,
+    root: PathBuf,
+) -> Result<(), String> {
+    let globs = scope.exclusions.globs();
+    let matcher = ExclusionMatcher::new(&root, &globs)?;
+    let live_matcher = ExclusionMatcher::new(&root, &globs)?;
+    let generation = scope.file_index.begin(&root);
+
+    let inner = scope.file_index.inner.clone();
+    let subscription = scope.watcher.subscribe(Box::new(move |change| {
+        let mut state = inner.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        if state.indexing {
+            state.pending.push(change.clone());
+        } else {
+            state.apply(change, &live_matcher);
+        }
+    }));
+    if let Some(previous) = scope.file_index.replace_subscription(subscription) {
+        scope.watcher.unsubscribe(previous);
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        walk_files(&root, Some(&matcher), |path| {
+            files.push(path.to_path_buf());
+            true
+        });
+        if scope.file_index.complete(generation, files, &matcher) {
+            let _ = window.emit("workspace-indexed", scope.file_index.status());
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_index_status(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<IndexStatus, String> {
+    Ok(windows.scope(window.label()).file_index.status())
+}
+
+/// Quick-open lookup against the in-memory index; never touches the disk.
+#[tauri::command]
+pub async fn fuzzy_find_files(
+    query: String,
+    limit: Option<usize>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<FuzzyMatch>, String> {
+    Ok(windows
+        .scope(window.label())
+        .file_index
+        .fuzzy_find(&query, limit.unwrap_or(DEFAULT_FUZZY_LIMIT)))
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use similar::{DiffOp, TextDiff};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Manager, State, Window};
+
+use crate::language;
+use crate::process::{self, ExecOptions, ProcessKind};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Per-language overrides, relative to the workspace root.
+const FORMATTERS_FILE: &str = ".codeai/formatters.json";
+/// Formatters are quick; one that hangs must not hold up a save.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Replaced in `args` with the path of the file being formatted.
+const FILE_PLACEHOLDER: &str = "${file}";
+
+/// A formatter reads the buffer on stdin and writes the result to stdout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatterConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Replaces lines `start_line..end_line` (zero-based, end exclusive) with
+/// `text`. Edits are listed top to bottom against the original text.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextEdit {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatResult {
+    pub content: String,
+    pub changed: bool,
+    pub edits: Vec<TextEdit>,
+}
+
+fn default_formatter(language: &str) -> Option<FormatterConfig> {
+    let (command, args): (&str, &[&str]) = match language {
+        "rust" => ("rustfmt", &["--emit", "stdout", "--edition", "2021"]),
+        "javascript" | "typescript" | "json" | "css" | "scss" | "less" | "html" | "markdown"
+        | "yaml" | "vue" | "graphql" => ("prettier", &["--stdin-filepath", FILE_PLACEHOLDER]),
+        "python" => (
+            "black",
+            &["--quiet", "--stdin-filename", FILE_PLACEHOLDER, "-"],
+        ),
+        "go" => ("gofmt", &[]),
+        _ => return None,
+    };
+    Some(FormatterConfig {
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        timeout_ms: None,
+    })
+}
+
+/// The formatter configured for `language` in the workspace, falling back
+/// to the usual tool. `null` in the file turns formatting off.
+fn formatter_for(root: Option<&Path>, language: &str) -> Result<Option<FormatterConfig>, String> {
+    if let Some(text) = root.and_then(|root| fs::read_to_string(root.join(FORMATTERS_FILE)).ok()) {
+        let mut configured: HashMap<String, Option<FormatterConfig>> = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid {}: {}", FORMATTERS_FILE, e))?;
+        if let Some(formatter) = configured.remove(language) {
+            return Ok(formatter);
+        }
+    }
+    Ok(default_formatter(language))
+}
+
+fn format_blocking(
+    window: &Window,
+    path: &Path,
+    content: &str,
+    language: Option<String>,
+) -> Result<String, String> {
+    let windows = window.state::<WindowRegistry>();
+    let scope = windows.scope(window.label());
+    let root = scope
+        .workspace
+        .roots()
+        .into_iter()
+        .find(|root| path.starts_with(root));
+    let language = language.unwrap_or_else(|| language::detect_language(path, content));
+    let formatter = formatter_for(root.as_deref(), &language)?
+        .ok_or_else(|| format!("No formatter configured for {}", language))?;
+
+    let file = path.to_string_lossy();
+    let args: Vec<String> = formatter
+        .args
+        .iter()
+        .map(|arg| arg.replace(FILE_PLACEHOLDER, &file))
+        .collect();
+    // Run next to the file so the formatter finds its config
+    // (rustfmt.toml, .prettierrc, pyproject.toml).
+    let cwd = path.parent().map(|dir| dir.to_string_lossy().to_string());
+    let options = ExecOptions {
+        stdin: Some(content.to_string()),
+        ..ExecOptions::default()
+    };
+    let cmd = process::prepare(&formatter.command, &args, cwd, &options, window, &windows)?;
+    let timeout = formatter
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let result = process::run_blocking(
+        &scope,
+        cmd,
+        ProcessKind::Command,
+        options.stdin,
+        Some(timeout),
+    )?;
+
+    if result.timed_out {
+        return Err(format!(
+            "{} timed out after {} ms",
+            formatter.command,
+            timeout.as_millis()
+        ));
+    }
+    if result.exit_code != Some(0) {
+        return Err(format!(
+            "{} failed: {}",
+            formatter.command,
+            result.stderr.trim()
+        ));
+    }
+    // An empty result for a non-empty buffer is a broken formatter, not a
+    // request to delete everything.
+    if result.stdout.is_empty() && !content.trim().is_empty() {
+        return Err(format!("{} produced no output", formatter.command));
+    }
+    Ok(result.stdout)
+}
+
+/// Formats `content` as the file at `path` would be, off the async runtime.
+pub async fn format_text(
+    window: Window,
+    path: PathBuf,
+    content: String,
+    language: Option<String>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        format_blocking(&window, &path, &content, language)
+    })
+    .await
+    .map_err(|e| format!("Formatting failed: {}", e))?
+}
+
+/// Line-based edits turning `old` into `new`, for editors that would rather
+/// not replace the whole buffer and lose markers and undo history.
+pub fn line_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    let diff = TextDiff::from_lines(old, new);
+    let new_lines = diff.new_slices();
+    diff.ops()
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+        .map(|op| {
+            let (old_range, new_range) = (op.old_range(), op.new_range());
+            TextEdit {
+                start_line: old_range.start,
+                end_line: old_range.end,
+                text: new_lines[new_range].concat(),
+            }
+        })
+        .collect()
+}
+
+/// Formats `content` as if it were the file at `path`, or the file on disk
+/// when `content` is omitted, in which case the result is written back.
+#[tauri::command]
+pub async fn format_document(
+    path: String,
+    content: Option<String>,
+    language: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<FormatResult, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let (original, on_disk) = match content {
+        Some(content) => (content, false),
+        None => (
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?,
+            true,
+        ),
+    };
+    let formatted = format_text(window, path.clone(), original.clone(), language).await?;
+    let changed = formatted != original;
+    if on_disk && changed {
+        fs::write(&path, &formatted).map_err(|e| format!("Failed to write file: {}", e))?;
+    }
+    Ok(FormatResult {
+        edits: line_edits(&original, &formatted),
+        content: formatted,
+        changed,
+    })
+}
@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace::{self, WorkspaceRoots};
+
+/// One step of a multi-select explorer action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FsOp {
+    Delete { path: String },
+    Move { from: String, to: String },
+    Copy { from: String, to: String },
+    CreateDir { path: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsOpStatus {
+    Done,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub operation_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub op: FsOp,
+    pub status: FsOpStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    pub index: usize,
+    pub op: FsOp,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchComplete {
+    pub operation_id: String,
+    pub succeeded: usize,
+    pub failed: Vec<BatchFailure>,
+    pub cancelled: bool,
+}
+
+/// Cancellation flags for the background operations running in a window,
+/// keyed by operation id. Used for fs batches and searches.
+#[derive(Default)]
+pub struct BatchOperations {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl BatchOperations {
+    pub fn register(&self, id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), flag.clone());
+        flag
+    }
+
+    pub fn finish(&self, id: &str) {
+        self.running.lock().unwrap().remove(id);
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for flag in self.running.lock().unwrap().values() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+pub fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if file_type.is_symlink() {
+        copy_symlink(from, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Renames, falling back to copy-and-delete when the destination is on a
+/// different filesystem. Any other rename error, such as permission denied
+/// or an existing destination, is returned as is.
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices && !to.exists() => {
+            if to.starts_with(from) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "cannot move a folder into itself",
+                ));
+            }
+            copy_recursive(from, to)?;
+            remove_path(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Picks a free sibling name the way file managers do: `name copy.ext`,
+/// then `name copy 2.ext`, ... Duplicating a copy continues its numbering
+/// instead of producing `name copy copy`.
+pub fn duplicate_name(path: &Path) -> Option<PathBuf> {
+    let parent = path.parent()?;
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let (stem, extension) = if path.is_dir() {
+        (file_name.as_str(), "")
+    } else {
+        match file_name.rfind('.') {
+            // A leading dot is a hidden file, not an extension.
+            Some(dot) if dot > 0 => file_name.split_at(dot),
+            _ => (file_name.as_str(), ""),
+        }
+    };
+
+    let base = match stem.rsplit_once(" copy") {
+        Some((base, rest)) if rest.is_empty() || rest.trim().parse::<u32>().is_ok() => base,
+        _ => stem,
+    };
+
+    (1..)
+        .map(|n| {
+            let name = if n == 1 {
+                format!("{} copy{}", base, extension)
+            } else {
+                format!("{} copy {}{}", base, n, extension)
+            };
+            parent.join(name)
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+}
+
+/// Deletes and moves act on a symlink itself, never on its target.
+fn run_op(op: &FsOp, workspace: &WorkspaceRoots) -> Result<(), String> {
+    let authorize = |path: &str| workspace.check(Path::new(path));
+    let authorize_entry = |path: &str| workspace.check_entry(Path::new(path));
+    match op {
+        FsOp::Delete { path } => {
+            remove_path(&authorize_entry(path)?).map_err(|e| format!("Failed to delete: {}", e))
+        }
+        FsOp::Move { from, to } => {
+            let (from, to) = (authorize_entry(from)?, authorize(to)?);
+            if to.exists() {
+                return Err(format!("Destination already exists: {}", to.display()));
+            }
+            move_path(&from, &to).map_err(|e| format!("Failed to move: {}", e))
+        }
+        FsOp::Copy { from, to } => {
+            let (from, to) = (authorize(from)?, authorize(to)?);
+            if to.exists() {
+                return Err(format!("Destination already exists: {}", to.display()));
+            }
+            if to.starts_with(&from) {
+                return Err(format!("Cannot copy {} into itself", from.display()));
+            }
+            copy_recursive(&from, &to).map_err(|e| format!("Failed to copy: {}", e))
+        }
+        FsOp::CreateDir { path } => fs::create_dir_all(authorize(path)?)
+            .map_err(|e| format!("Failed to create directory: {}", e)),
+    }
+}
+
+/// Starts a batch in the background and returns its operation id. Progress
+/// arrives as `fs-batch-progress` events and the summary as
+/// `fs-batch-complete`; a failed item does not stop the remaining ones.
+#[tauri::command]
+pub async fn batch_fs_operation(
+    ops: Vec<FsOp>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let scope = windows.scope(window.label());
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = scope.batch_operations.register(&operation_id);
+
+    let id = operation_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = ops.len();
+        let mut succeeded = 0;
+        let mut failed = Vec::new();
+
+        for (index, op) in ops.into_iter().enumerate() {
+            let (status, error) = if cancelled.load(Ordering::SeqCst) {
+                (FsOpStatus::Skipped, None)
+            } else {
+                match run_op(&op, &scope.workspace) {
+                    Ok(()) => {
+                        succeeded += 1;
+                        (FsOpStatus::Done, None)
+                    }
+                    Err(error) => {
+                        failed.push(BatchFailure {
+                            index,
+                            op: op.clone(),
+                            error: error.clone(),
+                        });
+                        (FsOpStatus::Failed, Some(error))
+                    }
+                }
+            };
+
+            let _ = window.emit(
+                "fs-batch-progress",
+                BatchProgress {
+                    operation_id: id.clone(),
+                    index,
+                    total,
+                    op,
+                    status,
+                    error,
+                },
+            );
+        }
+
+        scope.batch_operations.finish(&id);
+        let _ = window.emit(
+            "fs-batch-complete",
+            BatchComplete {
+                operation_id: id.clone(),
+                succeeded,
+                failed,
+                cancelled: cancelled.load(Ordering::SeqCst),
+            },
+        );
+    });
+
+    Ok(operation_id)
+}
+
+/// Copies a file or folder next to itself and returns the new path.
+#[tauri::command]
+pub async fn duplicate_path(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let source = workspace::authorize(&windows, &window, &path)?;
+    if !source.exists() {
+        return Err(format!("Path does not exist: {}", source.display()));
+    }
+    let target =
+        duplicate_name(&source).ok_or_else(|| format!("Cannot duplicate {}", source.display()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        copy_recursive(&source, &target)
+            .map(|()| target.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to duplicate: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Failed to duplicate: {}", e))?
+}
+
+#[tauri::command]
+pub async fn cancel_batch_operation(
+    operation_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows
+        .scope(window.label())
+        .batch_operations
+        .cancel(&operation_id))
+}
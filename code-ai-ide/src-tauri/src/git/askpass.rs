@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::Window;
+
+const PORT_ENV: &str = "CODEAI_ASKPASS_PORT";
+const TOKEN_ENV: &str = "CODEAI_ASKPASS_TOKEN";
+/// How long a prompt waits for the user before git is told "no".
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Answers for outstanding prompts, keyed by request id.
+pub type PromptRegistry = Arc<Mutex<HashMap<String, Sender<Option<String>>>>>;
+
+/// Emitted as `git-credential-request`; answered with
+/// `git_credential_response`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialRequest {
+    pub request_id: String,
+    pub operation_id: String,
+    /// The prompt exactly as git or ssh wrote it, e.g.
+    /// `Password for 'https://user@github.com': `.
+    pub prompt: String,
+    /// Whether the answer should be typed into a masked field.
+    pub secret: bool,
+}
+
+/// Git and ssh ask for credentials by running the program named in
+/// `GIT_ASKPASS`/`SSH_ASKPASS` with the prompt as its argument. The IDE
+/// binary doubles as that program: started with the askpass environment,
+/// it forwards the prompt to the running IDE, prints the answer and exits
+/// without starting the UI.
+pub fn run_helper_if_requested() {
+    let (port, token) = match (env::var(PORT_ENV), env::var(TOKEN_ENV)) {
+        (Ok(port), Ok(token)) => (port, token),
+        _ => return,
+    };
+    let prompt = env::args().nth(1).unwrap_or_default();
+
+    let answer = (|| -> std::io::Result<String> {
+        let port: u16 = port.parse().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+        let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+        writeln!(stream, "{}", token)?;
+        writeln!(stream, "{}", prompt.replace('\n', " "))?;
+        stream.shutdown(Shutdown::Write)?;
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer)?;
+        Ok(answer)
+    })();
+
+    match answer {
+        Ok(answer) if !answer.is_empty() => {
+            println!("{}", answer);
+            std::process::exit(0);
+        }
+        // Cancelled, timed out or the IDE went away: git treats a failing
+        // askpass as "no credentials".
+        _ => std::process::exit(1),
+    }
+}
+
+/// Local endpoint the askpass helper talks to for the duration of one git
+/// operation. Stops accepting when dropped.
+pub struct AskpassServer {
+    port: u16,
+    token: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl AskpassServer {
+    pub fn start(
+        window: Window,
+        operation_id: String,
+        prompts: PromptRegistry,
+    ) -> Result<AskpassServer, String> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .map_err(|e| format!("Failed to start credential prompt server: {}", e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to start credential prompt server: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to start credential prompt server: {}", e))?
+            .port();
+        let token = uuid::Uuid::new_v4().to_string();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (expected, stopped) = (token.clone(), stop.clone());
+        thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    // Git prompts one at a time (username, then password),
+                    // so connections are served in order.
+                    Ok((stream, _)) => {
+                        let _ = stream.set_nonblocking(false);
+                        serve_prompt(stream, &expected, &window, &operation_id, &prompts);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(AskpassServer { port, token, stop })
+    }
+
+    /// Points git and ssh at the helper for prompts.
+    pub fn configure(&self, command: &mut Command) -> Result<(), String> {
+        let exe = env::current_exe()
+            .map_err(|e| format!("Failed to locate the application binary: {}", e))?;
+        command
+            .env("GIT_ASKPASS", &exe)
+            .env("SSH_ASKPASS", &exe)
+            .env("SSH_ASKPASS_REQUIRE", "force")
+            .env(PORT_ENV, self.port.to_string())
+            .env(TOKEN_ENV, &self.token);
+        Ok(())
+    }
+}
+
+impl Drop for AskpassServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+fn serve_prompt(
+    stream: TcpStream,
+    expected_token: &str,
+    window: &Window,
+    operation_id: &str,
+    prompts: &PromptRegistry,
+) {
+    let mut reader = BufReader::new(&stream);
+    let (mut token, mut prompt) = (String::new(), String::new());
+    if reader.read_line(&mut token).is_err() || reader.read_line(&mut prompt).is_err() {
+        return;
+    }
+    // Only helpers started by this operation know the token.
+    if token.trim_end() != expected_token {
+        return;
+    }
+
+    let prompt = prompt.trim_end().to_string();
+    let lower = prompt.to_lowercase();
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = mpsc::channel();
+    prompts.lock().unwrap().insert(request_id.clone(), sender);
+    let _ = window.emit(
+        "git-credential-request",
+        CredentialRequest {
+            request_id: request_id.clone(),
+            operation_id: operation_id.to_string(),
+            secret: ["password", "passphrase", "token", "pin"]
+                .iter()
+                .any(|word| lower.contains(word)),
+            prompt,
+        },
+    );
+
+    let answer = receiver.recv_timeout(PROMPT_TIMEOUT).ok().flatten();
+    prompts.lock().unwrap().remove(&request_id);
+    if let Some(answer) = answer {
+        let mut stream = &stream;
+        let _ = stream.write_all(answer.as_bytes());
+    }
+}
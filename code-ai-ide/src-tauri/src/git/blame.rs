@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlameCommit {
+    pub author: String,
+    pub author_email: String,
+    /// Author date, seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub summary: String,
+    /// Lines changed in the working tree but not committed yet.
+    pub uncommitted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlameLine {
+    /// Zero-based line in the current file.
+    pub line: u32,
+    pub commit: String,
+    /// Zero-based line in that commit's version of the file.
+    pub original_line: u32,
+}
+
+/// Lines refer to commits by hash; each commit is described once.
+#[derive(Debug, Default, Serialize)]
+pub struct Blame {
+    pub lines: Vec<BlameLine>,
+    pub commits: HashMap<String, BlameCommit>,
+}
+
+/// Parses `git blame --porcelain`. Commit headers only follow the first
+/// line attributed to each commit.
+pub fn parse_porcelain(output: &str) -> Blame {
+    let mut blame = Blame::default();
+    let mut current: Option<String> = None;
+
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        let mut words = line.split(' ');
+        let first = words.next().unwrap_or("");
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            let original: u32 = words.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+            let final_line: u32 = words.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+            blame.lines.push(BlameLine {
+                line: final_line.saturating_sub(1),
+                commit: first.to_string(),
+                original_line: original.saturating_sub(1),
+            });
+            blame
+                .commits
+                .entry(first.to_string())
+                .or_insert_with(|| BlameCommit {
+                    uncommitted: first == UNCOMMITTED,
+                    ..Default::default()
+                });
+            current = Some(first.to_string());
+            continue;
+        }
+
+        let commit = match current
+            .as_ref()
+            .and_then(|hash| blame.commits.get_mut(hash))
+        {
+            Some(commit) => commit,
+            None => continue,
+        };
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => commit.author = value.to_string(),
+            "author-mail" => {
+                commit.author_email = value.trim_matches(|c| c == '<' || c == '>').to_string()
+            }
+            "author-time" => commit.timestamp = value.parse().unwrap_or(0),
+            "summary" => commit.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    blame
+}
+
+/// Blames `start_line..end_line` (zero-based, end exclusive) so the editor
+/// can annotate just the visible lines.
+#[tauri::command]
+pub async fn git_blame(
+    path: String,
+    start_line: u32,
+    end_line: u32,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Blame, String> {
+    if end_line <= start_line {
+        return Ok(Blame::default());
+    }
+    let root = super::repo_root(&windows, &window, &path)?;
+    let file = workspace::authorize(&windows, &window, &path)?;
+    let relative = super::relative_to(&root, &file)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let range = format!("-L{},{}", start_line + 1, end_line);
+        let output = super::output(&root, &["blame", "--porcelain", &range, "--", &relative])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Asking past the end of the file is normal while scrolling a
+            // buffer with unsaved lines; blame what exists instead.
+            if stderr.contains("has only") {
+                let lines = stderr
+                    .split("has only ")
+                    .nth(1)
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .unwrap_or(0);
+                if lines <= start_line {
+                    return Ok(Blame::default());
+                }
+                let range = format!("-L{},{}", start_line + 1, lines);
+                let text = super::run(&root, &["blame", "--porcelain", &range, "--", &relative])?;
+                return Ok(parse_porcelain(&text));
+            }
+            return Err(stderr.trim().to_string());
+        }
+        Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+    })
+    .await
+    .map_err(|e| format!("git blame failed: {}", e))?
+}
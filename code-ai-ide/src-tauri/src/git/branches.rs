@@ -0,0 +1,193 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use super::GitError;
+use crate::window_state::WindowRegistry;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Branch {
+    /// Short name, e.g. `main` or `origin/main`.
+    pub name: String,
+    pub full_ref: String,
+    pub remote: bool,
+    pub current: bool,
+    pub commit: String,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    /// The upstream branch was deleted on the remote.
+    pub upstream_gone: bool,
+    /// Committer date of the tip, seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub subject: String,
+}
+
+const BRANCH_FORMAT: &str = "%(refname)%00%(refname:short)%00%(objectname)%00%(upstream:short)%00%(upstream:track,nobracket)%00%(HEAD)%00%(committerdate:unix)%00%(contents:subject)";
+
+fn parse_branch(line: &str) -> Option<Branch> {
+    let fields: Vec<&str> = line.split('\0').collect();
+    if fields.len() < 8 {
+        return None;
+    }
+    // `origin/HEAD` is an alias, not a branch.
+    if fields[0].starts_with("refs/remotes/") && fields[0].ends_with("/HEAD") {
+        return None;
+    }
+
+    let mut branch = Branch {
+        name: fields[1].to_string(),
+        full_ref: fields[0].to_string(),
+        remote: fields[0].starts_with("refs/remotes/"),
+        current: fields[5] == "*",
+        commit: fields[2].to_string(),
+        upstream: Some(fields[3].to_string()).filter(|u| !u.is_empty()),
+        ahead: 0,
+        behind: 0,
+        upstream_gone: fields[4] == "gone",
+        timestamp: fields[6].parse().unwrap_or(0),
+        subject: fields[7].to_string(),
+    };
+    // "ahead 2, behind 1"
+    for part in fields[4].split(", ") {
+        if let Some(count) = part.strip_prefix("ahead ") {
+            branch.ahead = count.parse().unwrap_or(0);
+        } else if let Some(count) = part.strip_prefix("behind ") {
+            branch.behind = count.parse().unwrap_or(0);
+        }
+    }
+    Some(branch)
+}
+
+/// Maps checkout/branch stderr onto the cases the UI treats specially.
+pub fn classify(stderr: &str, name: &str) -> GitError {
+    let overwritten = stderr.contains("would be overwritten by");
+    if overwritten {
+        let files = stderr
+            .lines()
+            .filter_map(|line| line.strip_prefix('\t'))
+            .map(|file| file.trim().to_string())
+            .collect();
+        return GitError::DirtyWorktree {
+            files,
+            untracked: stderr.contains("untracked working tree files"),
+        };
+    }
+
+    let name = name.to_string();
+    if stderr.contains("already exists") {
+        GitError::AlreadyExists { name }
+    } else if stderr.contains("not fully merged") {
+        GitError::NotFullyMerged { name }
+    } else if stderr.contains("checked out at") || stderr.contains("Cannot delete branch") {
+        GitError::CheckedOut { name }
+    } else if stderr.contains("not found")
+        || stderr.contains("did not match any")
+        || stderr.contains("not a valid object name")
+        || stderr.contains("invalid reference")
+    {
+        GitError::NotFound { name }
+    } else if stderr.contains("not a valid branch name") {
+        GitError::InvalidName { name }
+    } else {
+        GitError::Git {
+            message: stderr.trim().to_string(),
+        }
+    }
+}
+
+fn run_classified(root: &std::path::Path, args: &[&str], name: &str) -> Result<(), GitError> {
+    let output = super::output(root, args)?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(classify(&String::from_utf8_lossy(&output.stderr), name))
+    }
+}
+
+/// Local branches followed by remote-tracking ones.
+#[tauri::command]
+pub async fn git_branches(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<Branch>, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let format = format!("--format={}", BRANCH_FORMAT);
+        let output = super::run(
+            &root,
+            &["for-each-ref", &format, "refs/heads", "refs/remotes"],
+        )?;
+        Ok(output.lines().filter_map(parse_branch).collect())
+    })
+    .await
+    .map_err(|e| format!("git for-each-ref failed: {}", e))?
+}
+
+/// Creates `name` at `from` (default HEAD) without switching to it.
+#[tauri::command]
+pub async fn git_create_branch(
+    repo_path: String,
+    name: String,
+    from: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        if !super::output(&root, &["check-ref-format", "--branch", &name])?
+            .status
+            .success()
+        {
+            return Err(GitError::InvalidName { name });
+        }
+        let mut args = vec!["branch", "--", name.as_str()];
+        if let Some(from) = &from {
+            args.push(from);
+        }
+        run_classified(&root, &args, &name)
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git branch failed: {}", e)))?
+}
+
+/// Switches to a branch, tag or commit. Refuses (as git does) when local
+/// changes would be overwritten, reporting the files in the way.
+#[tauri::command]
+pub async fn git_checkout(
+    repo_path: String,
+    reference: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    // `checkout` has no `--end-of-options` before the revision.
+    super::reject_option(&reference)?;
+    super::check_hooks(&windows, &window, &root)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run_classified(
+            &root,
+            &["checkout", "--quiet", &reference, "--"],
+            &reference,
+        )
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git checkout failed: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn git_delete_branch(
+    repo_path: String,
+    name: String,
+    force: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let flag = if force { "-D" } else { "-d" };
+        run_classified(&root, &["branch", flag, "--", &name], &name)
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git branch failed: {}", e)))?
+}
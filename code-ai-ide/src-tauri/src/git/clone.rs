@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tauri::{Manager, State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Deserialize)]
+pub struct CloneOptions {
+    /// Branch or tag to check out instead of the remote's default.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Shallow clone with this many commits of history.
+    #[serde(default)]
+    pub depth: Option<u32>,
+    #[serde(default)]
+    pub recurse_submodules: bool,
+    /// Open the clone as the window's workspace once it is done.
+    #[serde(default = "default_open")]
+    pub open: bool,
+}
+
+fn default_open() -> bool {
+    true
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false)
+}
+
+/// Clones `url` into `dest` in the background and returns the operation id.
+/// Progress and the result arrive as `git-progress` and
+/// `git-operation-complete`, like a fetch. The parent of `dest` must be
+/// accessible; `dest` itself must not exist yet or be empty. A failed or
+/// cancelled clone leaves nothing behind.
+#[tauri::command]
+pub async fn git_clone(
+    url: String,
+    dest: String,
+    options: CloneOptions,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let url = url.trim().to_string();
+    if url.is_empty() || url.starts_with('-') {
+        return Err(format!("Invalid repository URL: {}", url));
+    }
+
+    let requested = Path::new(&dest);
+    let name = requested
+        .file_name()
+        .ok_or_else(|| format!("Invalid clone destination: {}", dest))?;
+    let parent = match requested.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent = workspace::authorize(&windows, &window, &parent.to_string_lossy())?;
+    let target = parent.join(name);
+    let created = !target.exists();
+    if !created && !is_empty_dir(&target) {
+        return Err(format!(
+            "Destination already exists and is not empty: {}",
+            target.display()
+        ));
+    }
+
+    let mut args = vec!["clone".to_string(), "--progress".to_string()];
+    if let Some(branch) = options.branch {
+        args.extend(["--branch".to_string(), branch]);
+    }
+    if let Some(depth) = options.depth {
+        args.push(format!("--depth={}", depth.max(1)));
+    }
+    if options.recurse_submodules {
+        args.push("--recurse-submodules".to_string());
+    }
+    args.extend(["--".to_string(), url, target.to_string_lossy().to_string()]);
+
+    let open = options.open;
+    let scope = windows.scope(window.label());
+    super::remote::start_operation(window, scope, parent, args, move |window, succeeded| {
+        if !succeeded {
+            // git removes a partial clone when it fails on its own, but not
+            // when it is killed.
+            if created {
+                let _ = fs::remove_dir_all(&target);
+            } else if let Ok(entries) = fs::read_dir(&target) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let _ = if path.is_dir() {
+                        fs::remove_dir_all(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    };
+                }
+            }
+            return Ok(());
+        }
+        if open {
+            let windows = window.state::<WindowRegistry>();
+            workspace::open_workspace(window, &windows, &target)?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,212 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub content: String,
+    /// One-based line numbers on each side; `None` on the side the line
+    /// does not exist in.
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// Text after the second `@@`, usually the enclosing function.
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileDiff {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub binary: bool,
+    pub added: u32,
+    pub removed: u32,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// `-12,3` / `+7` -> (start, count); a missing count means one line.
+fn parse_range(range: &str) -> (u32, u32) {
+    let range = &range[1..];
+    match range.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (range.parse().unwrap_or(0), 1),
+    }
+}
+
+fn parse_hunk_header(line: &str) -> Option<DiffHunk> {
+    let rest = line.strip_prefix("@@ ")?;
+    let (ranges, header) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(' ')?;
+    let (old_start, old_lines) = parse_range(old);
+    let (new_start, new_lines) = parse_range(new);
+    Some(DiffHunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        header: header.trim().to_string(),
+        lines: Vec::new(),
+    })
+}
+
+fn header_path(value: &str) -> Option<String> {
+    if value == "/dev/null" {
+        return None;
+    }
+    let path = value
+        .strip_prefix("a/")
+        .or_else(|| value.strip_prefix("b/"))
+        .unwrap_or(value);
+    Some(path.to_string())
+}
+
+/// Parses `git diff` output (any number of files) into structured hunks.
+pub fn parse_unified_diff(text: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+
+    for line in text.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FileDiff::default());
+            continue;
+        }
+        let file = match files.last_mut() {
+            Some(file) => file,
+            None => continue,
+        };
+
+        if let Some(hunk) = parse_hunk_header(line) {
+            old_line = hunk.old_start;
+            new_line = hunk.new_start;
+            file.hunks.push(hunk);
+            continue;
+        }
+
+        let hunk = match file.hunks.last_mut() {
+            Some(hunk) => hunk,
+            None => {
+                // Still in the file header.
+                if let Some(path) = line.strip_prefix("--- ") {
+                    file.old_path = header_path(path);
+                } else if let Some(path) = line.strip_prefix("+++ ") {
+                    file.new_path = header_path(path);
+                } else if let Some(path) = line.strip_prefix("rename from ") {
+                    file.old_path = Some(path.to_string());
+                } else if let Some(path) = line.strip_prefix("rename to ") {
+                    file.new_path = Some(path.to_string());
+                } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                    file.binary = true;
+                }
+                continue;
+            }
+        };
+
+        let (kind, content) = match line.chars().next() {
+            Some('+') => (DiffLineKind::Added, &line[1..]),
+            Some('-') => (DiffLineKind::Removed, &line[1..]),
+            Some(' ') => (DiffLineKind::Context, &line[1..]),
+            // "\ No newline at end of file" and anything unexpected.
+            _ => continue,
+        };
+        let (old, new) = match kind {
+            DiffLineKind::Added => {
+                file.added += 1;
+                new_line += 1;
+                (None, Some(new_line - 1))
+            }
+            DiffLineKind::Removed => {
+                file.removed += 1;
+                old_line += 1;
+                (Some(old_line - 1), None)
+            }
+            DiffLineKind::Context => {
+                old_line += 1;
+                new_line += 1;
+                (Some(old_line - 1), Some(new_line - 1))
+            }
+        };
+        hunk.lines.push(DiffLine {
+            kind,
+            content: content.to_string(),
+            old_line: old,
+            new_line: new,
+        });
+    }
+    files
+}
+
+/// Diff of one file against the index (`staged: false`) or of the index
+/// against HEAD (`staged: true`). An untracked file diffs as all-added.
+#[tauri::command]
+pub async fn git_diff_file(
+    path: String,
+    staged: bool,
+    context: Option<u32>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<FileDiff, String> {
+    let root = super::repo_root(&windows, &window, &path)?;
+    let file = workspace::authorize(&windows, &window, &path)?;
+    let relative = super::relative_to(&root, &file)?;
+    let unified = format!("-U{}", context.unwrap_or(3));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let tracked = super::output(&root, &["ls-files", "--error-unmatch", "--", &relative])?
+            .status
+            .success();
+
+        let text = if !tracked && !staged {
+            // --no-index exits with 1 when the files differ.
+            let output = super::output(
+                &root,
+                &[
+                    "diff",
+                    "--no-index",
+                    "--no-ext-diff",
+                    &unified,
+                    "--",
+                    "/dev/null",
+                    &relative,
+                ],
+            )?;
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        } else {
+            let mut args = vec!["diff", "--no-ext-diff", "--find-renames", unified.as_str()];
+            if staged {
+                args.push("--cached");
+            }
+            args.extend(["--", relative.as_str()]);
+            super::run(&root, &args)?
+        };
+
+        Ok(parse_unified_diff(&text)
+            .into_iter()
+            .next()
+            .unwrap_or(FileDiff {
+                old_path: Some(relative.clone()),
+                new_path: Some(relative),
+                ..Default::default()
+            }))
+    })
+    .await
+    .map_err(|e| format!("git diff failed: {}", e))?
+}
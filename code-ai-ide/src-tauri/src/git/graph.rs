@@ -0,0 +1,226 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+
+/// The graph is laid out in one go, so this is a hard cap rather than a
+/// page size.
+const MAX_GRAPH_COMMITS: usize = 5000;
+const GRAPH_FORMAT: &str = "--format=%H%x00%P%x00%an%x00%at%x00%s%x00%D";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    Head,
+    Branch,
+    Remote,
+    Tag,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphRef {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphCommit {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author_name: String,
+    /// Author date, seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub subject: String,
+    pub refs: Vec<GraphRef>,
+    /// Lane the commit's node is drawn in.
+    pub column: usize,
+    /// Lines from this row down to the next one as `[from, to]` columns:
+    /// from the node to its parents' lanes, and straight through for lanes
+    /// passing by. Lanes of parents beyond the loaded range run off the
+    /// bottom of the last row.
+    pub edges: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitGraph {
+    pub commits: Vec<GraphCommit>,
+    /// Number of lanes, i.e. columns needed to draw the widest row.
+    pub width: usize,
+    pub has_more: bool,
+}
+
+/// `HEAD -> refs/heads/main, refs/remotes/origin/main, tag: refs/tags/v1`
+fn parse_refs(decoration: &str) -> Vec<GraphRef> {
+    let mut refs = Vec::new();
+    for part in decoration.split(", ").filter(|part| !part.is_empty()) {
+        let (head, part) = match part.strip_prefix("HEAD -> ") {
+            Some(rest) => (true, rest),
+            None => (false, part),
+        };
+        if head || part == "HEAD" {
+            refs.push(GraphRef {
+                name: "HEAD".to_string(),
+                kind: RefKind::Head,
+            });
+        }
+        let part = part.strip_prefix("tag: ").unwrap_or(part);
+        let (name, kind) = if let Some(name) = part.strip_prefix("refs/heads/") {
+            (name, RefKind::Branch)
+        } else if let Some(name) = part.strip_prefix("refs/remotes/") {
+            // `origin/HEAD` only repeats the remote's default branch.
+            if name.ends_with("/HEAD") {
+                continue;
+            }
+            (name, RefKind::Remote)
+        } else if let Some(name) = part.strip_prefix("refs/tags/") {
+            (name, RefKind::Tag)
+        } else {
+            continue;
+        };
+        refs.push(GraphRef {
+            name: name.to_string(),
+            kind,
+        });
+    }
+    refs
+}
+
+fn parse_commit(line: &str) -> Option<GraphCommit> {
+    let fields: Vec<&str> = line.splitn(6, '\0').collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    Some(GraphCommit {
+        hash: fields[0].to_string(),
+        parents: fields[1].split_whitespace().map(str::to_string).collect(),
+        author_name: fields[2].to_string(),
+        timestamp: fields[3].parse().unwrap_or(0),
+        subject: fields[4].to_string(),
+        refs: parse_refs(fields[5]),
+        column: 0,
+        edges: Vec::new(),
+    })
+}
+
+fn free_lane(lanes: &mut Vec<Option<String>>, taken: usize) -> usize {
+    match (0..lanes.len()).find(|&i| i != taken && lanes[i].is_none()) {
+        Some(i) => i,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+/// Assigns lanes and edges to commits in topological order. Each lane holds
+/// the commit it is waiting for; a commit takes the first lane waiting for
+/// it, hands that lane to its first parent and opens lanes for the others.
+/// Freed lanes are reused, so the graph stays as narrow as the history
+/// allows.
+pub fn layout(commits: &mut [GraphCommit]) -> usize {
+    let mut lanes: Vec<Option<String>> = Vec::new();
+    let mut width = 0;
+
+    for row in 0..commits.len() {
+        let hash = commits[row].hash.clone();
+        let waiting: Vec<usize> = (0..lanes.len())
+            .filter(|&i| lanes[i].as_deref() == Some(hash.as_str()))
+            .collect();
+        let column = match waiting.first() {
+            Some(&column) => column,
+            None => free_lane(&mut lanes, usize::MAX),
+        };
+
+        // Lanes converging on this commit end at its node.
+        if row > 0 {
+            for edge in &mut commits[row - 1].edges {
+                if waiting.contains(&edge[1]) {
+                    edge[1] = column;
+                }
+            }
+        }
+        let before = lanes.clone();
+        for &lane in &waiting {
+            lanes[lane] = None;
+        }
+
+        let parents = commits[row].parents.clone();
+        lanes[column] = parents.first().cloned();
+        let mut edges = Vec::new();
+        for (i, parent) in parents.iter().enumerate() {
+            let lane = if i == 0 {
+                column
+            } else {
+                match lanes.iter().position(|l| l.as_ref() == Some(parent)) {
+                    Some(lane) => lane,
+                    None => {
+                        let lane = free_lane(&mut lanes, column);
+                        lanes[lane] = Some(parent.clone());
+                        lane
+                    }
+                }
+            };
+            edges.push([column, lane]);
+        }
+        for (lane, expected) in before.iter().enumerate() {
+            if lane != column && expected.is_some() && lanes[lane] == *expected {
+                edges.push([lane, lane]);
+            }
+        }
+
+        while lanes.last().is_some_and(Option::is_none) {
+            lanes.pop();
+        }
+        width = width.max(lanes.len()).max(column + 1);
+        commits[row].column = column;
+        commits[row].edges = edges;
+    }
+    width
+}
+
+/// Commits of every branch and tag in topological order, newest first,
+/// laid out for drawing.
+#[tauri::command]
+pub async fn git_graph(
+    repo_path: String,
+    limit: usize,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<CommitGraph, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    let limit = limit.clamp(1, MAX_GRAPH_COMMITS);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if !super::has_head(&root) {
+            return Ok(CommitGraph {
+                commits: Vec::new(),
+                width: 0,
+                has_more: false,
+            });
+        }
+        let count = format!("--max-count={}", limit + 1);
+        let output = super::run(
+            &root,
+            &[
+                "log",
+                "--topo-order",
+                "--decorate=full",
+                "--exclude=refs/stash",
+                "--all",
+                GRAPH_FORMAT,
+                &count,
+            ],
+        )?;
+        let mut commits: Vec<GraphCommit> = output.lines().filter_map(parse_commit).collect();
+        let has_more = commits.len() > limit;
+        commits.truncate(limit);
+        let width = layout(&mut commits);
+        Ok(CommitGraph {
+            commits,
+            width,
+            has_more,
+        })
+    })
+    .await
+    .map_err(|e| format!("git graph failed: {}", e))?
+}
@@ -0,0 +1,142 @@
+use std::path::Path;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+
+fn with_pathspecs<'a>(args: &[&'a str], paths: &'a [String]) -> Vec<&'a str> {
+    let mut all = args.to_vec();
+    all.push("--");
+    all.extend(paths.iter().map(String::as_str));
+    all
+}
+
+/// Files under `paths` that `ls-files` reports with `options`, one per
+/// entry so they can be handed back to git as literal pathspecs.
+fn list_files(root: &Path, options: &[&str], paths: &[String]) -> Result<Vec<String>, String> {
+    let mut args = vec!["ls-files", "-z"];
+    args.extend_from_slice(options);
+    let listed = super::run(root, &with_pathspecs(&args, paths))?;
+    Ok(listed
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Splits the files under pathspecs into those git tracks and the untracked
+/// ones that are not ignored. Untracked directories are listed whole.
+fn split_tracked(root: &Path, paths: &[String]) -> Result<(Vec<String>, Vec<String>), String> {
+    let tracked = list_files(root, &[], paths)?;
+    let untracked = list_files(
+        root,
+        &["--others", "--exclude-standard", "--directory"],
+        paths,
+    )?;
+    Ok((tracked, untracked))
+}
+
+/// Stages paths, including deletions.
+#[tauri::command]
+pub async fn git_stage(
+    paths: Vec<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let (root, paths) = super::repo_paths(&windows, &window, &paths)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        super::run(&root, &with_pathspecs(&["add", "--all"], &paths)).map(|_| ())
+    })
+    .await
+    .map_err(|e| format!("git add failed: {}", e))?
+}
+
+/// Removes paths from the index, leaving the working tree as is.
+#[tauri::command]
+pub async fn git_unstage(
+    paths: Vec<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let (root, paths) = super::repo_paths(&windows, &window, &paths)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        if super::has_head(&root) {
+            super::run(&root, &with_pathspecs(&["restore", "--staged"], &paths))?;
+        } else {
+            // Nothing to restore from before the first commit.
+            super::run(
+                &root,
+                &with_pathspecs(&["rm", "--cached", "-r", "--quiet"], &paths),
+            )?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("git restore failed: {}", e))?
+}
+
+/// Commits the index and returns the new commit hash. With `amend`, an
+/// empty message keeps the previous one.
+#[tauri::command]
+pub async fn git_commit(
+    repo_path: String,
+    message: String,
+    amend: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    if message.trim().is_empty() && !amend {
+        return Err("Commit message is empty".to_string());
+    }
+    super::check_hooks(&windows, &window, &root)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut args = vec!["commit", "--quiet"];
+        if amend {
+            args.push("--amend");
+        }
+        if message.trim().is_empty() {
+            args.push("--no-edit");
+        } else {
+            args.extend(["-m", message.as_str()]);
+        }
+        // Commit hooks may take a while.
+        super::run(&root, &args)?;
+        super::run(&root, &["rev-parse", "HEAD"]).map(|hash| hash.trim().to_string())
+    })
+    .await
+    .map_err(|e| format!("git commit failed: {}", e))?
+}
+
+/// Throws away unstaged changes: tracked paths are restored from the index
+/// (staged changes survive), untracked ones are deleted. Ignored files are
+/// never touched.
+#[tauri::command]
+pub async fn git_discard(
+    paths: Vec<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let (root, paths) = super::repo_paths(&windows, &window, &paths)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (tracked, untracked) = split_tracked(&root, &paths)?;
+        if !tracked.is_empty() {
+            super::run(
+                &root,
+                &with_pathspecs(&["--literal-pathspecs", "restore", "--worktree"], &tracked),
+            )?;
+        }
+        if !untracked.is_empty() {
+            super::run(
+                &root,
+                &with_pathspecs(
+                    &["--literal-pathspecs", "clean", "-f", "-d", "-q"],
+                    &untracked,
+                ),
+            )?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("git discard failed: {}", e))?
+}
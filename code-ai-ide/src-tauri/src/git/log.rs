@@ -0,0 +1,142 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Upper bound on one page so a careless caller cannot pull the whole
+/// history through IPC at once.
+const MAX_PAGE_SIZE: usize = 500;
+const RECORD_START: char = '\u{1e}';
+const FIELD_END: char = '\u{1f}';
+const LOG_FORMAT: &str = "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%at%x1f%s%x1f%b%x1f";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedFile {
+    pub path: String,
+    /// `None` for binary files.
+    pub added: Option<u32>,
+    pub removed: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Commit {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author_name: String,
+    pub author_email: String,
+    /// Author date, seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub subject: String,
+    pub body: String,
+    pub files: Vec<ChangedFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    pub commits: Vec<Commit>,
+    pub has_more: bool,
+}
+
+fn parse_numstat(line: &str) -> Option<ChangedFile> {
+    let mut parts = line.splitn(3, '\t');
+    let added = parts.next()?;
+    let removed = parts.next()?;
+    let path = parts.next()?;
+    Some(ChangedFile {
+        path: path.to_string(),
+        added: added.parse().ok(),
+        removed: removed.parse().ok(),
+    })
+}
+
+pub fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .split(RECORD_START)
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(8, FIELD_END).collect();
+            if fields.len() < 8 {
+                return None;
+            }
+            Some(Commit {
+                hash: fields[0].to_string(),
+                parents: fields[1].split_whitespace().map(str::to_string).collect(),
+                author_name: fields[2].to_string(),
+                author_email: fields[3].to_string(),
+                timestamp: fields[4].parse().unwrap_or(0),
+                subject: fields[5].to_string(),
+                body: fields[6].trim().to_string(),
+                files: fields[7].lines().filter_map(parse_numstat).collect(),
+            })
+        })
+        .collect()
+}
+
+/// One page of history for `reference` (default HEAD), newest first,
+/// optionally limited to commits touching `path_filter`. A single file
+/// filter follows the file across renames.
+#[tauri::command]
+pub async fn git_log(
+    repo_path: String,
+    offset: usize,
+    limit: usize,
+    path_filter: Option<String>,
+    reference: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<LogPage, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    let filter = match &path_filter {
+        Some(path) => {
+            let path = workspace::authorize(&windows, &window, path)?;
+            Some((super::relative_to(&root, &path)?, path.is_file()))
+        }
+        None => None,
+    };
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if !super::has_head(&root) {
+            return Ok(LogPage {
+                commits: Vec::new(),
+                has_more: false,
+            });
+        }
+
+        let skip = format!("--skip={}", offset);
+        // One extra commit tells whether another page exists.
+        let count = format!("--max-count={}", limit + 1);
+        let mut args = vec![
+            "log",
+            LOG_FORMAT,
+            "--numstat",
+            "--no-ext-diff",
+            skip.as_str(),
+            count.as_str(),
+        ];
+        if let Some((_, true)) = &filter {
+            args.push("--follow");
+        }
+        // Resolved first so a reference can never be read as an option.
+        let commit = match &reference {
+            Some(reference) => {
+                let spec = format!("{}^{{commit}}", reference);
+                super::run(&root, &["rev-parse", "--verify", "--end-of-options", &spec])
+                    .map_err(|_| format!("Unknown revision: {}", reference))?
+            }
+            None => "HEAD".to_string(),
+        };
+        args.push(commit.trim());
+        if let Some((path, _)) = &filter {
+            args.extend(["--", path.as_str()]);
+        }
+
+        let mut commits = parse_log(&super::run(&root, &args)?);
+        let has_more = commits.len() > limit;
+        commits.truncate(limit);
+        Ok(LogPage { commits, has_more })
+    })
+    .await
+    .map_err(|e| format!("git log failed: {}", e))?
+}
@@ -0,0 +1,184 @@
+pub mod askpass;
+pub mod blame;
+pub mod branches;
+pub mod clone;
+pub mod diff;
+pub mod graph;
+pub mod index;
+pub mod log;
+pub mod remote;
+pub mod stash;
+pub mod status;
+pub mod submodule;
+pub mod tags;
+pub mod worktree;
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use tauri::Window;
+
+use crate::trust;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Failures the UI handles differently from a plain message, e.g. by
+/// offering to stash before a checkout.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GitError {
+    /// Local changes would be overwritten; `untracked` when the files in
+    /// the way are untracked ones.
+    DirtyWorktree {
+        files: Vec<String>,
+        untracked: bool,
+    },
+    NotFound {
+        name: String,
+    },
+    AlreadyExists {
+        name: String,
+    },
+    InvalidName {
+        name: String,
+    },
+    NotFullyMerged {
+        name: String,
+    },
+    CheckedOut {
+        name: String,
+    },
+    /// Credentials were missing, wrong or cancelled.
+    AuthFailed {
+        message: String,
+    },
+    /// The remote has work the local branch does not; pull first.
+    NonFastForward {
+        message: String,
+    },
+    DivergentBranches {
+        message: String,
+    },
+    MergeConflict {
+        files: Vec<String>,
+    },
+    Git {
+        message: String,
+    },
+}
+
+impl From<String> for GitError {
+    fn from(message: String) -> Self {
+        GitError::Git { message }
+    }
+}
+
+/// A `git` invocation with the environment every command needs: no
+/// interactive prompts, unquoted paths and untranslated messages, so the
+/// output can be parsed. Git is run as a program rather than through
+/// libgit2 so hooks, config and credential helpers behave as in a terminal.
+///
+/// The one exception is `core.fsmonitor`: it names a program that even a
+/// read-only `status` would start, and a folder the user only opened, not
+/// trusted, can set it. It is turned off everywhere, which only makes
+/// `status` slower in repositories that used it.
+pub fn git_command(repo: &Path) -> Command {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "core.quotepath=off", "-c", "color.ui=false"])
+        .args(["-c", "core.fsmonitor=false"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null());
+    command
+}
+
+pub fn output(repo: &Path, args: &[&str]) -> Result<Output, String> {
+    git_command(repo)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))
+}
+
+/// Runs git and returns stdout, or stderr as the error on a non-zero exit.
+pub fn run(repo: &Path, args: &[&str]) -> Result<String, String> {
+    let output = output(repo, args)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Refuses a ref or remote name git would read as an option. For commands
+/// that cannot take `--end-of-options` before their revision.
+pub fn reject_option(value: &str) -> Result<(), String> {
+    if value.starts_with('-') {
+        return Err(format!("Invalid name: {}", value));
+    }
+    Ok(())
+}
+
+/// Commands that can run the repository's hooks (commit, checkout, merge,
+/// push) start programs from the repository, so they need the workspace
+/// trusted like any other command.
+pub fn check_hooks(windows: &WindowRegistry, window: &Window, root: &Path) -> Result<(), String> {
+    trust::check_execution(window, windows, "git", false, Some(root))
+}
+
+/// Authorizes `path` and returns the top level of the repository it is in.
+pub fn repo_root(windows: &WindowRegistry, window: &Window, path: &str) -> Result<PathBuf, String> {
+    let path = workspace::authorize(windows, window, path)?;
+    let dir = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent().unwrap_or(&path)
+    };
+    let top = run(dir, &["rev-parse", "--show-toplevel"])
+        .map_err(|_| format!("Not a git repository: {}", path.display()))?;
+    Ok(PathBuf::from(top.trim_end()))
+}
+
+/// Path of `path` relative to the repository root, as git expects it in
+/// pathspecs.
+pub fn relative_to(root: &Path, path: &Path) -> Result<String, String> {
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| format!("{} is outside the repository", path.display()))?;
+    if relative.as_os_str().is_empty() {
+        return Ok(".".to_string());
+    }
+    Ok(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Resolves paths that must all live in one repository into that
+/// repository's root and their pathspecs.
+pub fn repo_paths(
+    windows: &WindowRegistry,
+    window: &Window,
+    paths: &[String],
+) -> Result<(PathBuf, Vec<String>), String> {
+    let first = paths.first().ok_or_else(|| "No paths given".to_string())?;
+    let root = repo_root(windows, window, first)?;
+    let relative = paths
+        .iter()
+        .map(|path| relative_to(&root, &workspace::authorize(windows, window, path)?))
+        .collect::<Result<_, _>>()?;
+    Ok((root, relative))
+}
+
+/// The git directory shared by every worktree of the repository `path` is
+/// in; `None` outside a repository.
+pub fn common_dir(path: &Path) -> Option<PathBuf> {
+    let dir = run(path, &["rev-parse", "--git-common-dir"]).ok()?;
+    // Printed relative to `path` when inside the main worktree.
+    std::fs::canonicalize(path.join(dir.trim_end())).ok()
+}
+
+pub fn has_head(repo: &Path) -> bool {
+    output(repo, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
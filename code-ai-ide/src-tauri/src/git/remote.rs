@@ -0,0 +1,397 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{State, Window};
+
+use super::askpass::{AskpassServer, PromptRegistry};
+use super::GitError;
+use crate::window_state::{WindowRegistry, WindowState};
+
+/// Keep only the tail of git's stderr for error reporting.
+const MAX_CAPTURED_STDERR: usize = 64 * 1024;
+
+struct RunningOperation {
+    child: Arc<Mutex<Child>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Long-running git processes of a window (push, pull, fetch, clone) and
+/// the credential prompts they have open.
+#[derive(Default)]
+pub struct GitOperations {
+    running: Mutex<HashMap<String, RunningOperation>>,
+    prompts: PromptRegistry,
+}
+
+impl GitOperations {
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().get(id) {
+            Some(operation) => {
+                operation.cancelled.store(true, Ordering::SeqCst);
+                let _ = operation.child.lock().unwrap().kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        let ids: Vec<String> = self.running.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            self.cancel(&id);
+        }
+        // Unblock prompts waiting for an answer that will never come.
+        self.prompts.lock().unwrap().clear();
+    }
+
+    fn answer(&self, request_id: &str, value: Option<String>) -> bool {
+        match self.prompts.lock().unwrap().remove(request_id) {
+            Some(sender) => sender.send(value).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// Emitted as `git-progress` for each progress line git prints.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitProgress {
+    pub operation_id: String,
+    pub message: String,
+    pub percent: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum GitOutcome {
+    Success { output: String },
+    Failed { error: GitError },
+    Cancelled,
+}
+
+/// Emitted as `git-operation-complete` when the process exits.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitOperationComplete {
+    pub operation_id: String,
+    pub outcome: GitOutcome,
+}
+
+fn parse_percent(message: &str) -> Option<u32> {
+    let end = message.find('%')?;
+    let digits: String = message[..end]
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.chars().rev().collect::<String>().parse().ok()
+}
+
+/// Maps remote failures onto typed errors. Local changes in the way of a
+/// pull are reported like a blocked checkout.
+pub fn classify_remote(output: &str) -> GitError {
+    let message = output.trim().to_string();
+    let auth_markers = [
+        "Authentication failed",
+        "could not read Username",
+        "could not read Password",
+        "Permission denied (publickey",
+        "Invalid username or password",
+        "HTTP Basic: Access denied",
+        "The requested URL returned error: 403",
+    ];
+    if auth_markers.iter().any(|marker| output.contains(marker)) {
+        return GitError::AuthFailed { message };
+    }
+    if output.contains("[rejected]") || output.contains("Updates were rejected") {
+        return GitError::NonFastForward { message };
+    }
+    if output.contains("Need to specify how to reconcile divergent branches") {
+        return GitError::DivergentBranches { message };
+    }
+    let conflicts: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.split_once("Merge conflict in "))
+        .map(|(_, file)| file.trim().to_string())
+        .collect();
+    if !conflicts.is_empty() {
+        return GitError::MergeConflict { files: conflicts };
+    }
+    if output.contains("would be overwritten by") {
+        return super::branches::classify(output, "");
+    }
+    GitError::Git { message }
+}
+
+fn register(scope: &WindowState, id: &str, child: Arc<Mutex<Child>>) -> Arc<AtomicBool> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    scope.git_operations.running.lock().unwrap().insert(
+        id.to_string(),
+        RunningOperation {
+            child,
+            cancelled: cancelled.clone(),
+        },
+    );
+    cancelled
+}
+
+/// Runs a network git command in the background, streaming its progress,
+/// and returns the operation id right away. `finish` runs on the background
+/// thread before the completion event is sent, told whether git succeeded;
+/// its error only counts after a success.
+pub fn start_operation(
+    window: Window,
+    scope: Arc<WindowState>,
+    cwd: PathBuf,
+    args: Vec<String>,
+    finish: impl FnOnce(&Window, bool) -> Result<(), String> + Send + 'static,
+) -> Result<String, String> {
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    let askpass = AskpassServer::start(
+        window.clone(),
+        operation_id.clone(),
+        scope.git_operations.prompts.clone(),
+    )?;
+
+    let mut command = super::git_command(&cwd);
+    command
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    askpass.configure(&mut command)?;
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let child = Arc::new(Mutex::new(child));
+    let cancelled = register(&scope, &operation_id, child.clone());
+
+    let id = operation_id.clone();
+    thread::spawn(move || {
+        let stdout_reader = thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut stdout) = stdout {
+                let _ = stdout.read_to_string(&mut text);
+            }
+            text
+        });
+
+        // Progress lines are redrawn with `\r`, so split on both.
+        let mut captured = String::new();
+        if let Some(mut stderr) = stderr {
+            let mut buffer = [0u8; 4096];
+            let mut pending = String::new();
+            while let Ok(read) = stderr.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                pending.push_str(&String::from_utf8_lossy(&buffer[..read]));
+                while let Some(end) = pending.find(['\r', '\n']) {
+                    let line: String = pending.drain(..=end).collect();
+                    let message = line.trim().to_string();
+                    if message.is_empty() {
+                        continue;
+                    }
+                    let _ = window.emit(
+                        "git-progress",
+                        GitProgress {
+                            operation_id: id.clone(),
+                            percent: parse_percent(&message),
+                            message: message.clone(),
+                        },
+                    );
+                    // Percentages are redrawn many times; keep the rest.
+                    if parse_percent(&message).is_none() {
+                        captured.push_str(&message);
+                        captured.push('\n');
+                    }
+                }
+                if captured.len() > MAX_CAPTURED_STDERR {
+                    let mut cut = captured.len() - MAX_CAPTURED_STDERR / 2;
+                    while !captured.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    captured.drain(..cut);
+                }
+            }
+            captured.push_str(&pending);
+        }
+
+        let stdout = stdout_reader.join().unwrap_or_default();
+        let status = child.lock().unwrap().wait();
+        drop(askpass);
+        scope.git_operations.running.lock().unwrap().remove(&id);
+
+        let succeeded = matches!(&status, Ok(status) if status.success());
+        let finished = finish(&window, succeeded && !cancelled.load(Ordering::SeqCst));
+        let outcome = if cancelled.load(Ordering::SeqCst) {
+            GitOutcome::Cancelled
+        } else {
+            match status {
+                Ok(status) if status.success() => match finished {
+                    Ok(()) => GitOutcome::Success {
+                        output: format!("{}{}", stdout, captured).trim().to_string(),
+                    },
+                    Err(message) => GitOutcome::Failed {
+                        error: GitError::Git { message },
+                    },
+                },
+                Ok(_) => GitOutcome::Failed {
+                    error: classify_remote(&format!("{}\n{}", stdout, captured)),
+                },
+                Err(e) => GitOutcome::Failed {
+                    error: GitError::Git {
+                        message: format!("Failed to wait for git: {}", e),
+                    },
+                },
+            }
+        };
+        let _ = window.emit(
+            "git-operation-complete",
+            GitOperationComplete {
+                operation_id: id,
+                outcome,
+            },
+        );
+    });
+
+    Ok(operation_id)
+}
+
+/// `start_operation` at the top of the repository containing `repo_path`.
+pub fn start_in_repo(
+    window: Window,
+    windows: &WindowRegistry,
+    repo_path: &str,
+    args: Vec<String>,
+) -> Result<String, String> {
+    let root = super::repo_root(windows, &window, repo_path)?;
+    let scope = windows.scope(window.label());
+    start_operation(window, scope, root, args, |_, _| Ok(()))
+}
+
+/// The `<remote> [<branch>]` arguments of a push or pull. The remote must be
+/// one configured in the repository, not a URL or an option; the branch can
+/// not pass for an option either.
+fn remote_target(
+    root: &Path,
+    remote: Option<String>,
+    branch: Option<String>,
+) -> Result<Vec<String>, String> {
+    let Some(remote) = remote else {
+        return Ok(Vec::new());
+    };
+    let remotes = super::run(root, &["remote"])?;
+    if !remotes.lines().any(|name| name == remote) {
+        return Err(format!("Unknown remote: {}", remote));
+    }
+    if let Some(branch) = &branch {
+        super::reject_option(branch)?;
+    }
+    let mut args = vec!["--end-of-options".to_string(), remote];
+    args.extend(branch);
+    Ok(args)
+}
+
+/// Pushes `branch` (default: the current one) to `remote` (default: its
+/// upstream). `force` uses `--force-with-lease` so someone else's pushed
+/// work is never overwritten blindly.
+#[tauri::command]
+pub async fn git_push(
+    repo_path: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    force: bool,
+    set_upstream: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let mut args = vec!["push".to_string(), "--progress".to_string()];
+    if force {
+        args.push("--force-with-lease".to_string());
+    }
+    if set_upstream {
+        args.push("--set-upstream".to_string());
+    }
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    super::check_hooks(&windows, &window, &root)?;
+    args.extend(remote_target(&root, remote, branch)?);
+    let scope = windows.scope(window.label());
+    start_operation(window, scope, root, args, |_, _| Ok(()))
+}
+
+/// Pulls with a merge, or a rebase when `rebase` is set, so git never
+/// stops to ask how to reconcile divergent branches.
+#[tauri::command]
+pub async fn git_pull(
+    repo_path: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    rebase: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let mode = if rebase { "--rebase" } else { "--no-rebase" };
+    let mut args = vec![
+        "pull".to_string(),
+        "--progress".to_string(),
+        mode.to_string(),
+    ];
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    super::check_hooks(&windows, &window, &root)?;
+    args.extend(remote_target(&root, remote, branch)?);
+    let scope = windows.scope(window.label());
+    start_operation(window, scope, root, args, |_, _| Ok(()))
+}
+
+/// Fetches `remote`, or every remote when none is given.
+#[tauri::command]
+pub async fn git_fetch(
+    repo_path: String,
+    remote: Option<String>,
+    prune: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let mut args = vec!["fetch".to_string(), "--progress".to_string()];
+    if prune {
+        args.push("--prune".to_string());
+    }
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    match remote {
+        Some(remote) => args.extend(remote_target(&root, Some(remote), None)?),
+        None => args.push("--all".to_string()),
+    }
+    let scope = windows.scope(window.label());
+    start_operation(window, scope, root, args, |_, _| Ok(()))
+}
+
+#[tauri::command]
+pub async fn cancel_git_operation(
+    operation_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows
+        .scope(window.label())
+        .git_operations
+        .cancel(&operation_id))
+}
+
+/// Answers a `git-credential-request`; `None` cancels the prompt.
+#[tauri::command]
+pub async fn git_credential_response(
+    request_id: String,
+    value: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows
+        .scope(window.label())
+        .git_operations
+        .answer(&request_id, value))
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::path::Path;
+use tauri::{State, Window};
+
+use super::GitError;
+use crate::window_state::WindowRegistry;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StashEntry {
+    /// Position in the stash list; `0` is the most recent.
+    pub index: usize,
+    pub commit: String,
+    /// Branch the work was stashed from.
+    pub branch: Option<String>,
+    pub message: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+}
+
+const STASH_FORMAT: &str = "--format=%gd%x00%H%x00%ct%x00%gs";
+
+fn parse_stash(line: &str) -> Option<StashEntry> {
+    let fields: Vec<&str> = line.split('\0').collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    let index = fields[0]
+        .strip_prefix("stash@{")?
+        .strip_suffix('}')?
+        .parse()
+        .ok()?;
+    // "On main: message" for named stashes, "WIP on main: abc1234 subject"
+    // otherwise.
+    let subject = fields[3];
+    let (branch, message) = match subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "))
+        .and_then(|rest| rest.split_once(": "))
+    {
+        Some((branch, message)) => (Some(branch.to_string()), message.to_string()),
+        None => (None, subject.to_string()),
+    };
+    Some(StashEntry {
+        index,
+        commit: fields[1].to_string(),
+        branch,
+        message,
+        timestamp: fields[2].parse().unwrap_or(0),
+    })
+}
+
+fn stash_ref(index: usize) -> String {
+    format!("stash@{{{}}}", index)
+}
+
+/// Runs a stash command that touches the working tree; conflicts and local
+/// changes in the way come back typed.
+fn run_restoring(root: &Path, args: &[&str]) -> Result<(), GitError> {
+    let output = super::output(root, args)?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Err(super::remote::classify_remote(&text))
+}
+
+/// Stashes local changes (staged and unstaged, plus untracked files when
+/// asked). Returns `false` when there was nothing to stash.
+#[tauri::command]
+pub async fn git_stash_save(
+    repo_path: String,
+    message: Option<String>,
+    include_untracked: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let before = super::run(&root, &["stash", "list", "--format=%H"])?;
+        let mut args = vec!["stash", "push", "--quiet"];
+        if include_untracked {
+            args.push("--include-untracked");
+        }
+        if let Some(message) = message.as_deref().filter(|m| !m.trim().is_empty()) {
+            args.extend(["--message", message]);
+        }
+        super::run(&root, &args)?;
+        let after = super::run(&root, &["stash", "list", "--format=%H"])?;
+        Ok(after.lines().next() != before.lines().next())
+    })
+    .await
+    .map_err(|e| format!("git stash failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_stash_list(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<StashEntry>, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let output = super::run(&root, &["stash", "list", STASH_FORMAT])?;
+        Ok(output.lines().filter_map(parse_stash).collect())
+    })
+    .await
+    .map_err(|e| format!("git stash failed: {}", e))?
+}
+
+/// Applies a stash and keeps it in the list.
+#[tauri::command]
+pub async fn git_stash_apply(
+    repo_path: String,
+    index: usize,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run_restoring(&root, &["stash", "apply", &stash_ref(index)])
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git stash failed: {}", e)))?
+}
+
+/// Applies a stash and drops it. On conflicts git keeps the stash, so
+/// nothing is lost.
+#[tauri::command]
+pub async fn git_stash_pop(
+    repo_path: String,
+    index: usize,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run_restoring(&root, &["stash", "pop", &stash_ref(index)])
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git stash failed: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn git_stash_drop(
+    repo_path: String,
+    index: usize,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        super::run(&root, &["stash", "drop", "--quiet", &stash_ref(index)]).map(|_| ())
+    })
+    .await
+    .map_err(|e| format!("git stash failed: {}", e))?
+}
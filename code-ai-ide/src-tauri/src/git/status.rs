@@ -0,0 +1,249 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    /// Relative to the repository root.
+    pub path: String,
+    /// The source path of a rename or copy.
+    pub original_path: Option<String>,
+    pub kind: ChangeKind,
+}
+
+/// How the two sides of a merge conflict touched the file, from git's
+/// `XY` code (`UU` both modified, `AU` added by us, ...).
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub path: String,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubmoduleStatus {
+    /// Relative to the repository root.
+    pub path: String,
+    /// Commit checked out in the submodule; `None` until initialized.
+    pub commit: Option<String>,
+    pub initialized: bool,
+    /// Checked out at a different commit than the superproject records.
+    pub commit_changed: bool,
+    /// The new commit is staged in the superproject.
+    pub staged: bool,
+    /// Tracked files inside the submodule are modified.
+    pub modified: bool,
+    pub untracked: bool,
+    pub conflicted: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GitStatus {
+    pub root: String,
+    /// `None` on a detached HEAD.
+    pub branch: Option<String>,
+    /// `None` before the first commit.
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: Vec<FileChange>,
+    pub unstaged: Vec<FileChange>,
+    pub untracked: Vec<String>,
+    pub conflicted: Vec<Conflict>,
+    /// Submodules are listed here rather than as file changes.
+    pub submodules: Vec<SubmoduleStatus>,
+}
+
+fn change_kind(code: char) -> Option<ChangeKind> {
+    match code {
+        'A' => Some(ChangeKind::Added),
+        'M' => Some(ChangeKind::Modified),
+        'D' => Some(ChangeKind::Deleted),
+        'R' => Some(ChangeKind::Renamed),
+        'C' => Some(ChangeKind::Copied),
+        'T' => Some(ChangeKind::TypeChanged),
+        _ => None,
+    }
+}
+
+fn push_changes(
+    status: &mut GitStatus,
+    xy: &str,
+    sub: &str,
+    path: &str,
+    original_path: Option<&str>,
+) {
+    let mut codes = xy.chars();
+    let (x, y) = (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'));
+    // `S<c><m><u>` for submodules, `N...` for everything else.
+    if let Some(flags) = sub.strip_prefix('S') {
+        let flags: Vec<char> = flags.chars().collect();
+        status.submodules.push(SubmoduleStatus {
+            path: path.to_string(),
+            initialized: true,
+            commit_changed: flags.first() == Some(&'C'),
+            staged: x != '.',
+            modified: flags.get(1) == Some(&'M'),
+            untracked: flags.get(2) == Some(&'U'),
+            ..Default::default()
+        });
+        return;
+    }
+    if let Some(kind) = change_kind(x) {
+        status.staged.push(FileChange {
+            path: path.to_string(),
+            original_path: original_path.map(str::to_string),
+            kind,
+        });
+    }
+    if let Some(kind) = change_kind(y) {
+        status.unstaged.push(FileChange {
+            path: path.to_string(),
+            // The worktree side of a staged rename is the new path.
+            original_path: None,
+            kind,
+        });
+    }
+}
+
+/// Parses `git status --porcelain=v2 --branch -z`.
+pub fn parse_porcelain_v2(output: &str, status: &mut GitStatus) {
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => status.head = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for part in value.split_whitespace() {
+                        if let Some(ahead) = part.strip_prefix('+') {
+                            status.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = part.strip_prefix('-') {
+                            status.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let kind = record.split(' ').next().unwrap_or("");
+        match kind {
+            // 1 XY sub mH mI mW hH hI path
+            "1" => {
+                let fields: Vec<&str> = record.splitn(9, ' ').collect();
+                if fields.len() == 9 {
+                    push_changes(status, fields[1], fields[2], fields[8], None);
+                }
+            }
+            // 2 XY sub mH mI mW hH hI Xscore path, then the original path
+            // as its own NUL-terminated record.
+            "2" => {
+                let fields: Vec<&str> = record.splitn(10, ' ').collect();
+                let original = records.next();
+                if fields.len() == 10 {
+                    push_changes(status, fields[1], fields[2], fields[9], original);
+                }
+            }
+            // u XY sub m1 m2 m3 mW h1 h2 h3 path
+            "u" => {
+                let fields: Vec<&str> = record.splitn(11, ' ').collect();
+                if fields.len() == 11 {
+                    status.conflicted.push(Conflict {
+                        path: fields[10].to_string(),
+                        code: fields[1].to_string(),
+                    });
+                }
+            }
+            "?" => status.untracked.push(record[2..].to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Merges `git submodule status` into the flags from the porcelain output,
+/// adding submodules without changes and those not initialized yet.
+fn merge_submodule_status(output: &str, status: &mut GitStatus) {
+    // `<state><sha> <path>[ (<describe>)]`, state one of ` -+U`.
+    for line in output.lines() {
+        let mut chars = line.chars();
+        let state = match chars.next() {
+            Some(state) => state,
+            None => continue,
+        };
+        let mut parts = chars.as_str().splitn(2, ' ');
+        let (sha, rest) = match (parts.next(), parts.next()) {
+            (Some(sha), Some(rest)) => (sha, rest),
+            _ => continue,
+        };
+        let path = match rest.rfind(" (") {
+            Some(end) if rest.ends_with(')') => &rest[..end],
+            _ => rest,
+        };
+
+        let index = match status.submodules.iter().position(|s| s.path == path) {
+            Some(index) => index,
+            None => {
+                status.submodules.push(SubmoduleStatus {
+                    path: path.to_string(),
+                    ..Default::default()
+                });
+                status.submodules.len() - 1
+            }
+        };
+        let submodule = &mut status.submodules[index];
+        submodule.initialized = state != '-';
+        submodule.commit = Some(sha.to_string()).filter(|_| state != '-');
+        submodule.commit_changed |= state == '+';
+        submodule.conflicted = state == 'U';
+    }
+    status.submodules.sort_by(|a, b| a.path.cmp(&b.path));
+}
+
+#[tauri::command]
+pub async fn git_status(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<GitStatus, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let output = super::run(
+            &root,
+            &[
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "-z",
+                "--untracked-files=all",
+            ],
+        )?;
+        let mut status = GitStatus {
+            root: root.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        parse_porcelain_v2(&output, &mut status);
+        if root.join(".gitmodules").is_file() {
+            let submodules = super::run(&root, &["submodule", "status"])?;
+            merge_submodule_status(&submodules, &mut status);
+        }
+        Ok(status)
+    })
+    .await
+    .map_err(|e| format!("git status failed: {}", e))?
+}
@@ -0,0 +1,29 @@
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+
+/// Checks submodules out at the commits the superproject records, in the
+/// background like a fetch. `init` first registers submodules that were
+/// never initialized, e.g. right after a plain clone. Returns the operation
+/// id.
+#[tauri::command]
+pub async fn git_submodule_update(
+    repo_path: String,
+    init: bool,
+    recursive: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let mut args = vec![
+        "submodule".to_string(),
+        "update".to_string(),
+        "--progress".to_string(),
+    ];
+    if init {
+        args.push("--init".to_string());
+    }
+    if recursive {
+        args.push("--recursive".to_string());
+    }
+    super::remote::start_in_repo(window, &windows, &repo_path, args)
+}
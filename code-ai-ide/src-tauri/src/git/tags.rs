@@ -0,0 +1,189 @@
+use serde::Serialize;
+use tauri::{State, Window};
+
+use super::GitError;
+use crate::window_state::WindowRegistry;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Tag {
+    pub name: String,
+    pub annotated: bool,
+    /// The tagged commit (peeled through annotated tag objects).
+    pub commit: String,
+    /// Tagger date for annotated tags, commit date otherwise; seconds since
+    /// the Unix epoch.
+    pub timestamp: i64,
+    /// Tag message for annotated tags, commit subject otherwise.
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeadDescription {
+    /// As printed by `git describe`, e.g. `v1.2.0-3-g1a2b3c4-dirty`.
+    pub description: String,
+    /// Nearest reachable tag; `None` when no tag is reachable.
+    pub tag: Option<String>,
+    /// Commits on top of `tag`.
+    pub distance: u32,
+    pub commit: String,
+    pub dirty: bool,
+}
+
+const TAG_FORMAT: &str = "--format=%(refname:short)%00%(objecttype)%00%(objectname)%00%(*objectname)%00%(creatordate:unix)%00%(contents:subject)";
+
+fn parse_tag(line: &str) -> Option<Tag> {
+    let fields: Vec<&str> = line.split('\0').collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let annotated = fields[1] == "tag";
+    Some(Tag {
+        name: fields[0].to_string(),
+        annotated,
+        commit: if annotated { fields[3] } else { fields[2] }.to_string(),
+        timestamp: fields[4].parse().unwrap_or(0),
+        subject: fields[5].to_string(),
+    })
+}
+
+fn parse_description(description: &str) -> HeadDescription {
+    let description = description.trim().to_string();
+    let (rest, dirty) = match description.strip_suffix("-dirty") {
+        Some(rest) => (rest, true),
+        None => (description.as_str(), false),
+    };
+    // `<tag>-<distance>-g<hash>`; the tag itself may contain dashes. With
+    // no tag in reach `--always` prints the bare hash.
+    let mut parts = rest.rsplitn(3, '-');
+    let (hash, distance, tag) = (parts.next(), parts.next(), parts.next());
+    let (tag, distance, commit) = match (tag, distance, hash.and_then(|h| h.strip_prefix('g'))) {
+        (Some(tag), Some(distance), Some(commit)) if distance.parse::<u32>().is_ok() => (
+            Some(tag.to_string()),
+            distance.parse().unwrap_or(0),
+            commit.to_string(),
+        ),
+        _ => (None, 0, rest.to_string()),
+    };
+    HeadDescription {
+        description,
+        tag,
+        distance,
+        commit,
+        dirty,
+    }
+}
+
+/// Tags, newest first.
+#[tauri::command]
+pub async fn git_tags(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<Tag>, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let output = super::run(
+            &root,
+            &[
+                "for-each-ref",
+                "--sort=-creatordate",
+                TAG_FORMAT,
+                "refs/tags",
+            ],
+        )?;
+        Ok(output.lines().filter_map(parse_tag).collect())
+    })
+    .await
+    .map_err(|e| format!("git tag failed: {}", e))?
+}
+
+/// Tags `target` (default HEAD). A message makes an annotated tag, none a
+/// lightweight one.
+#[tauri::command]
+pub async fn git_create_tag(
+    repo_path: String,
+    name: String,
+    target: Option<String>,
+    message: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let full_ref = format!("refs/tags/{}", name);
+        if name.starts_with('-')
+            || !super::output(&root, &["check-ref-format", &full_ref])?
+                .status
+                .success()
+        {
+            return Err(GitError::InvalidName { name });
+        }
+
+        let mut args = vec!["tag"];
+        let message = message.filter(|m| !m.trim().is_empty());
+        if let Some(message) = &message {
+            args.extend(["--annotate", "--message", message.as_str()]);
+        }
+        args.push(&name);
+        if let Some(target) = &target {
+            args.push(target);
+        }
+        let output = super::output(&root, &args)?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(super::branches::classify(
+                &String::from_utf8_lossy(&output.stderr),
+                &name,
+            ))
+        }
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git tag failed: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn git_delete_tag(
+    repo_path: String,
+    name: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let output = super::output(&root, &["tag", "--delete", "--", &name])?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(super::branches::classify(
+                &String::from_utf8_lossy(&output.stderr),
+                &name,
+            ))
+        }
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git tag failed: {}", e)))?
+}
+
+/// Describes HEAD relative to the nearest tag, counting lightweight tags
+/// too.
+#[tauri::command]
+pub async fn git_describe(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<HeadDescription>, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        if !super::has_head(&root) {
+            return Ok(None);
+        }
+        let output = super::run(
+            &root,
+            &["describe", "--tags", "--long", "--always", "--dirty"],
+        )?;
+        Ok(Some(parse_description(&output)))
+    })
+    .await
+    .map_err(|e| format!("git tag failed: {}", e))?
+}
@@ -0,0 +1,187 @@
+use serde::Serialize;
+use std::path::Path;
+use tauri::{State, Window};
+
+use super::GitError;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Worktree {
+    pub path: String,
+    /// `None` for a bare repository.
+    pub head: Option<String>,
+    /// Short branch name; `None` when detached.
+    pub branch: Option<String>,
+    /// The repository's original checkout, which cannot be removed.
+    pub main: bool,
+    pub bare: bool,
+    pub detached: bool,
+    pub locked: bool,
+    /// Its directory is gone; `git worktree prune` would drop it.
+    pub prunable: bool,
+    /// Open as a root of this window's workspace.
+    pub open: bool,
+}
+
+fn parse_worktrees(output: &str) -> Vec<Worktree> {
+    let mut worktrees: Vec<Worktree> = Vec::new();
+    for block in output.split("\n\n") {
+        let mut worktree = Worktree::default();
+        for line in block.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "worktree" => worktree.path = value.to_string(),
+                "HEAD" => worktree.head = Some(value.to_string()),
+                "branch" => {
+                    let name = value.strip_prefix("refs/heads/").unwrap_or(value);
+                    worktree.branch = Some(name.to_string());
+                }
+                "bare" => worktree.bare = true,
+                "detached" => worktree.detached = true,
+                "locked" => worktree.locked = true,
+                "prunable" => worktree.prunable = true,
+                _ => {}
+            }
+        }
+        if !worktree.path.is_empty() {
+            // git always lists the main worktree first.
+            worktree.main = worktrees.is_empty();
+            worktrees.push(worktree);
+        }
+    }
+    worktrees
+}
+
+fn classify(stderr: &str, name: &str) -> GitError {
+    if stderr.contains("already used by worktree") || stderr.contains("already checked out") {
+        return GitError::CheckedOut {
+            name: name.to_string(),
+        };
+    }
+    if stderr.contains("contains modified or untracked files") {
+        return GitError::DirtyWorktree {
+            files: Vec::new(),
+            untracked: false,
+        };
+    }
+    super::branches::classify(stderr, name)
+}
+
+#[tauri::command]
+pub async fn git_worktree_list(
+    repo_path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<Worktree>, String> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    let roots = windows.scope(window.label()).workspace.roots();
+    tauri::async_runtime::spawn_blocking(move || {
+        let output = super::run(&root, &["worktree", "list", "--porcelain"])?;
+        let mut worktrees = parse_worktrees(&output);
+        for worktree in &mut worktrees {
+            let path = std::fs::canonicalize(&worktree.path).ok();
+            worktree.open = path.is_some_and(|path| roots.contains(&path));
+        }
+        Ok(worktrees)
+    })
+    .await
+    .map_err(|e| format!("git worktree failed: {}", e))?
+}
+
+/// Checks out `reference` (default: a new branch named after the folder, as
+/// git does) in a new worktree at `path`, optionally as a new branch
+/// `new_branch`. The parent of `path` must be accessible. With `open`, the
+/// worktree is added as a root of the workspace, next to the current one.
+#[tauri::command]
+pub async fn git_worktree_add(
+    repo_path: String,
+    path: String,
+    reference: Option<String>,
+    new_branch: Option<String>,
+    open: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    let requested = Path::new(&path);
+    let name = requested
+        .file_name()
+        .ok_or_else(|| format!("Invalid worktree path: {}", path))?;
+    let parent = requested
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let target = workspace::authorize(&windows, &window, &parent.to_string_lossy())?.join(name);
+    let target_arg = target.to_string_lossy().to_string();
+    // The new checkout runs the `post-checkout` hook.
+    super::check_hooks(&windows, &window, &root)?;
+    let scope = windows.scope(window.label());
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut args = vec!["worktree", "add", "--quiet"];
+        if let Some(new_branch) = &new_branch {
+            args.extend(["-b", new_branch.as_str()]);
+        }
+        args.extend(["--", target_arg.as_str()]);
+        if let Some(reference) = &reference {
+            args.push(reference);
+        }
+        let output = super::output(&root, &args)?;
+        if !output.status.success() {
+            let name = new_branch.or(reference).unwrap_or_default();
+            return Err(classify(&String::from_utf8_lossy(&output.stderr), &name));
+        }
+
+        if open {
+            scope.workspace.add_root(&target)?;
+        }
+        Ok(target_arg)
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git worktree failed: {}", e)))?
+}
+
+/// Removes a linked worktree and closes it in the workspace if open. Without
+/// `force`, refuses when it has local changes.
+#[tauri::command]
+pub async fn git_worktree_remove(
+    repo_path: String,
+    path: String,
+    force: bool,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), GitError> {
+    let root = super::repo_root(&windows, &window, &repo_path)?;
+    // Resolved without requiring the directory to exist, so worktrees whose
+    // folder was deleted can still be cleaned up.
+    let target = workspace::resolve(Path::new(&path))?;
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let listed = parse_worktrees(&super::run(&root, &["worktree", "list", "--porcelain"])?);
+        let worktree = listed
+            .iter()
+            .find(|w| workspace::resolve(Path::new(&w.path)).ok().as_ref() == Some(&target))
+            .ok_or_else(|| GitError::NotFound { name: path.clone() })?;
+        if worktree.main {
+            return Err(GitError::Git {
+                message: "The main worktree cannot be removed".to_string(),
+            });
+        }
+
+        let target_arg = target.to_string_lossy().to_string();
+        let mut args = vec!["worktree", "remove"];
+        if force {
+            args.push("--force");
+        }
+        args.extend(["--", target_arg.as_str()]);
+        let output = super::output(&root, &args)?;
+        if !output.status.success() {
+            return Err(classify(&String::from_utf8_lossy(&output.stderr), &path));
+        }
+        scope.workspace.remove_root(&target);
+        Ok(())
+    })
+    .await
+    .map_err(|e| GitError::from(format!("git worktree failed: {}", e)))?
+}
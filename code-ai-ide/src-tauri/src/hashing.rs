@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileHash {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex digest.
+    pub digest: String,
+    pub size: u64,
+}
+
+/// Streams `reader` through the hasher so memory use stays flat regardless
+/// of file size. Returns the hex digest and the number of bytes read.
+pub fn hash_reader(
+    mut reader: impl Read,
+    algorithm: HashAlgorithm,
+) -> std::io::Result<(String, u64)> {
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    let mut size = 0u64;
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                size += read as u64;
+            }
+            Ok((format!("{:x}", hasher.finalize()), size))
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                size += read as u64;
+            }
+            Ok((hasher.finalize().to_hex().to_string(), size))
+        }
+    }
+}
+
+pub fn hash_path(path: &Path, algorithm: HashAlgorithm) -> Result<FileHash, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let (digest, size) = hash_reader(BufReader::new(file), algorithm)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(FileHash {
+        path: path.to_string_lossy().to_string(),
+        algorithm,
+        digest,
+        size,
+    })
+}
+
+#[tauri::command]
+pub async fn hash_file(
+    path: String,
+    algorithm: Option<HashAlgorithm>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<FileHash, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let algorithm = algorithm.unwrap_or(HashAlgorithm::Sha256);
+    tauri::async_runtime::spawn_blocking(move || hash_path(&path, algorithm))
+        .await
+        .map_err(|e| format!("Failed to hash file: {}", e))?
+}
@@ -0,0 +1,247 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use tauri::{State, Window};
+
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+const MAX_TOKENIZE_LINES: u64 = 5_000;
+/// Parser state is remembered every this many lines, so scrolling back or
+/// jumping near an earlier position does not parse from the top again.
+const CHECKPOINT_INTERVAL: u64 = 1_000;
+/// Parsing up to a far-away range would freeze the request; past this gap
+/// the range is parsed from a fresh state a little above it instead, which
+/// is exact for nearly everything but multi-thousand-line strings and
+/// comments.
+const MAX_CATCH_UP_LINES: u64 = 50_000;
+const WARMUP_LINES: u64 = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HighlightToken {
+    /// UTF-16 column.
+    pub start: u32,
+    pub length: u32,
+    /// Innermost TextMate scope, such as `keyword.other.sql`; the frontend
+    /// maps scopes onto its theme.
+    pub scope: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineTokens {
+    pub line: u64,
+    pub tokens: Vec<HighlightToken>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenizedRange {
+    pub start_line: u64,
+    pub end_line: u64,
+    pub total_lines: u64,
+    /// The grammar used, e.g. `SQL`.
+    pub syntax: String,
+    pub lines: Vec<LineTokens>,
+}
+
+struct Checkpoints {
+    syntax: String,
+    len: u64,
+    modified: Option<SystemTime>,
+    /// Entry `i` is the state at the start of line `i * CHECKPOINT_INTERVAL`.
+    states: Vec<(ParseState, ScopeStack)>,
+}
+
+/// Parser checkpoints per large file of a window.
+#[derive(Default)]
+pub struct HighlightStates {
+    files: Mutex<HashMap<String, Checkpoints>>,
+}
+
+impl HighlightStates {
+    pub fn forget(&self, path: &str) {
+        self.files.lock().unwrap().remove(path);
+    }
+
+    pub fn clear(&self) {
+        self.files.lock().unwrap().clear();
+    }
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn find_syntax<'a>(
+    syntaxes: &'a SyntaxSet,
+    path: &Path,
+    language: Option<&str>,
+) -> &'a SyntaxReference {
+    language
+        .and_then(|language| syntaxes.find_syntax_by_token(language))
+        .or_else(|| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| syntaxes.find_syntax_by_extension(ext))
+        })
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+/// Tokens of one line, advancing `state` and `stack` past it.
+fn tokenize_line(
+    line: &str,
+    state: &mut ParseState,
+    stack: &mut ScopeStack,
+    syntaxes: &SyntaxSet,
+) -> Result<Vec<HighlightToken>, String> {
+    let ops = state
+        .parse_line(line, syntaxes)
+        .map_err(|e| format!("Failed to tokenize: {}", e))?;
+    let text = line.trim_end_matches(['\n', '\r']);
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut column = 0u32;
+    let mut emit = |stack: &ScopeStack, from: usize, to: usize, column: &mut u32| {
+        let to = to.min(text.len());
+        if from >= to {
+            return;
+        }
+        let length = text[from..to].encode_utf16().count() as u32;
+        // The bare top-level scope (`source.sql`) carries no colour.
+        if stack.as_slice().len() > 1 {
+            if let Some(scope) = stack.as_slice().last() {
+                tokens.push(HighlightToken {
+                    start: *column,
+                    length,
+                    scope: scope.build_string(),
+                });
+            }
+        }
+        *column += length;
+    };
+    for (offset, op) in ops {
+        emit(stack, position, offset, &mut column);
+        position = position.max(offset.min(text.len()));
+        stack
+            .apply(&op)
+            .map_err(|e| format!("Failed to tokenize: {:?}", e))?;
+    }
+    emit(stack, position, text.len(), &mut column);
+    Ok(tokens)
+}
+
+fn file_stamp(path: &str) -> (u64, Option<SystemTime>) {
+    let metadata = fs::metadata(path).ok();
+    (
+        metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        metadata.and_then(|m| m.modified().ok()),
+    )
+}
+
+fn tokenize(
+    scope: &WindowState,
+    path: &str,
+    start_line: u64,
+    end_line: u64,
+    language: Option<&str>,
+) -> Result<TokenizedRange, String> {
+    let syntaxes = syntaxes();
+    let syntax = find_syntax(syntaxes, Path::new(path), language);
+    let (len, modified) = file_stamp(path);
+
+    let mut files = scope.highlight_states.files.lock().unwrap();
+    let checkpoints = files
+        .entry(path.to_string())
+        .and_modify(|c| {
+            if c.syntax != syntax.name || c.len != len || c.modified != modified {
+                c.states.clear();
+                c.syntax = syntax.name.clone();
+                c.len = len;
+                c.modified = modified;
+            }
+        })
+        .or_insert_with(|| Checkpoints {
+            syntax: syntax.name.clone(),
+            len,
+            modified,
+            states: Vec::new(),
+        });
+    if checkpoints.states.is_empty() {
+        checkpoints
+            .states
+            .push((ParseState::new(syntax), ScopeStack::new()));
+    }
+
+    let nearest = (start_line / CHECKPOINT_INTERVAL).min(checkpoints.states.len() as u64 - 1);
+    let nearest_line = nearest * CHECKPOINT_INTERVAL;
+    let (from, exact, (mut state, mut stack)) = if start_line - nearest_line <= MAX_CATCH_UP_LINES {
+        (
+            nearest_line,
+            true,
+            checkpoints.states[nearest as usize].clone(),
+        )
+    } else {
+        (
+            start_line.saturating_sub(WARMUP_LINES),
+            false,
+            (ParseState::new(syntax), ScopeStack::new()),
+        )
+    };
+
+    let range = scope.large_files.read_lines(path, from, end_line)?;
+    let mut lines = Vec::new();
+    for (i, text) in range.content.split_inclusive('\n').enumerate() {
+        let line = from + i as u64;
+        if exact
+            && line % CHECKPOINT_INTERVAL == 0
+            && line / CHECKPOINT_INTERVAL == checkpoints.states.len() as u64
+        {
+            checkpoints.states.push((state.clone(), stack.clone()));
+        }
+        let tokens = tokenize_line(text, &mut state, &mut stack, syntaxes)?;
+        if line >= start_line {
+            lines.push(LineTokens { line, tokens });
+        }
+    }
+
+    Ok(TokenizedRange {
+        start_line: start_line.min(range.end_line),
+        end_line: range.end_line,
+        total_lines: range.total_lines,
+        syntax: syntax.name.clone(),
+        lines,
+    })
+}
+
+/// Highlight tokens for lines `start_line..end_line` (zero-based, end
+/// exclusive) of a file too large for the editor to highlight itself.
+/// `language` picks the grammar; the extension is used when it is omitted.
+#[tauri::command]
+pub async fn tokenize_range(
+    path: String,
+    start_line: u64,
+    end_line: u64,
+    language: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<TokenizedRange, String> {
+    if end_line.saturating_sub(start_line) > MAX_TOKENIZE_LINES {
+        return Err(format!(
+            "Cannot tokenize more than {} lines at once",
+            MAX_TOKENIZE_LINES
+        ));
+    }
+    let path = workspace::authorize(&windows, &window, &path)?
+        .to_string_lossy()
+        .to_string();
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        tokenize(&scope, &path, start_line, end_line, language.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Tokenizing failed: {}", e))?
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::metadata::to_millis;
+use crate::window_state::{WindowRegistry, WindowState};
+
+const STASH_DIR: &str = "hot_exit";
+
+/// Contents of an unsaved editor buffer, stashed so it survives a crash or
+/// a close without saving.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsavedBuffer {
+    /// The file path for file-backed buffers, or an editor-assigned id for
+    /// untitled ones.
+    pub id: String,
+    pub path: Option<String>,
+    pub workspace: Option<String>,
+    pub content: String,
+    pub language: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub stashed_at: u64,
+    /// Modification time of the file on disk when the buffer was stashed.
+    pub disk_modified: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveredBuffer {
+    #[serde(flatten)]
+    pub buffer: UnsavedBuffer,
+    /// The file on disk changed (or vanished) after the buffer was stashed,
+    /// so restoring it blindly would overwrite someone else's edit.
+    pub disk_changed: bool,
+}
+
+fn modified_millis(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(to_millis)
+}
+
+/// One file per buffer, named by a hash of the workspace and buffer id so
+/// the same file open in two workspaces does not collide.
+fn stash_path(app: &AppHandle, workspace: Option<&str>, id: &str) -> Result<PathBuf, String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(workspace.unwrap_or("").as_bytes());
+    hasher.update(&[0]);
+    hasher.update(id.as_bytes());
+    let name = format!("{}/{}.json", STASH_DIR, &hasher.finalize().to_hex()[..32]);
+    app_data::app_data_path(app, &name)
+}
+
+fn active_workspace(scope: &WindowState) -> Option<String> {
+    scope
+        .workspace
+        .active()
+        .map(|root| root.to_string_lossy().to_string())
+}
+
+pub fn stash(app: &AppHandle, buffer: &UnsavedBuffer) -> Result<(), String> {
+    let path = stash_path(app, buffer.workspace.as_deref(), &buffer.id)?;
+    app_data::save_json(&path, buffer)
+}
+
+/// Stashes every dirty document of a closing window. Called on window
+/// teardown so closing without saving keeps the edits for next time.
+pub fn stash_dirty_documents(app: &AppHandle, scope: &WindowState) {
+    let workspace = active_workspace(scope);
+    for doc in scope.documents.dirty_snapshots() {
+        let buffer = UnsavedBuffer {
+            id: doc.path.clone(),
+            disk_modified: modified_millis(Path::new(&doc.path)),
+            path: Some(doc.path),
+            workspace: workspace.clone(),
+            content: doc.content,
+            language: Some(doc.language),
+            stashed_at: to_millis(SystemTime::now()).unwrap_or(0),
+        };
+        let _ = stash(app, &buffer);
+    }
+}
+
+fn load_all(app: &AppHandle) -> Result<Vec<UnsavedBuffer>, String> {
+    let dir = app_data::app_data_path(app, STASH_DIR)?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut buffers = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let buffer = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<UnsavedBuffer>(&text).ok());
+        match buffer {
+            Some(buffer) => buffers.push(buffer),
+            // A stash that cannot be parsed can never be recovered.
+            None => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    Ok(buffers)
+}
+
+/// Called by the editor on change (debounced) with the buffer's current
+/// contents. `id` defaults to `path` for file-backed buffers.
+#[tauri::command]
+pub async fn stash_unsaved_buffer(
+    id: Option<String>,
+    path: Option<String>,
+    content: String,
+    language: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let id = id
+        .or_else(|| path.clone())
+        .ok_or_else(|| "A buffer id or path is required".to_string())?;
+    let scope = windows.scope(window.label());
+    let buffer = UnsavedBuffer {
+        disk_modified: path.as_deref().and_then(|p| modified_millis(Path::new(p))),
+        id,
+        path,
+        workspace: active_workspace(&scope),
+        content,
+        language,
+        stashed_at: to_millis(SystemTime::now()).unwrap_or(0),
+    };
+    stash(&app, &buffer)
+}
+
+/// Drops a stash once the buffer was saved, reverted or closed on purpose.
+#[tauri::command]
+pub async fn discard_unsaved_buffer(
+    id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let workspace = active_workspace(&windows.scope(window.label()));
+    let path = stash_path(&app, workspace.as_deref(), &id)?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to discard buffer: {}", e)),
+    }
+}
+
+/// Lists stashed buffers, oldest first. With `workspace` set, only buffers
+/// stashed for that workspace are returned. Stashes stay until discarded,
+/// so the editor decides what to restore.
+#[tauri::command]
+pub async fn recover_unsaved_buffers(
+    workspace: Option<String>,
+    app: AppHandle,
+) -> Result<Vec<RecoveredBuffer>, String> {
+    let mut recovered: Vec<RecoveredBuffer> = load_all(&app)?
+        .into_iter()
+        .filter(|buffer| workspace.is_none() || buffer.workspace == workspace)
+        .map(|buffer| {
+            let disk_changed = match &buffer.path {
+                Some(path) => modified_millis(Path::new(path)) != buffer.disk_modified,
+                None => false,
+            };
+            RecoveredBuffer {
+                buffer,
+                disk_changed,
+            }
+        })
+        .collect();
+    recovered.sort_by_key(|r| r.buffer.stashed_at);
+    Ok(recovered)
+}
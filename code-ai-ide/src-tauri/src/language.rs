@@ -0,0 +1,345 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// User-defined mappings in the app data directory; the built-in ones ship
+/// with the app.
+const REGISTRY_FILE: &str = "languages.json";
+const BUILTIN_LANGUAGES: &str = include_str!("../languages.json");
+
+/// How much of a file is inspected when the extension does not settle the
+/// language. Modelines may sit at the end, so small files are read whole.
+const SNIFF_BYTES: usize = 16 * 1024;
+const MODELINE_LINES: usize = 5;
+
+/// Extensions that map to more than one language; content gets a say unless
+/// the user mapped the extension themselves.
+const AMBIGUOUS_EXTENSIONS: &[&str] = &["h", "m", "pl", "inc", "conf", "cfg", "in", "txt"];
+
+/// How files are recognised as one language. Every list may be empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageDefinition {
+    pub id: String,
+    /// Without the dot, matched case-insensitively.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Exact file names such as `Dockerfile`.
+    #[serde(default)]
+    pub filenames: Vec<String>,
+    /// Shebang interpreters; version suffixes are ignored, so `python`
+    /// also covers `python3.12`.
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+    /// Regex tried against the first non-blank line.
+    #[serde(default)]
+    pub first_line: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    languages: Vec<LanguageDefinition>,
+}
+
+struct Registry {
+    /// User definitions come first, so they win over the built-in ones.
+    definitions: Vec<LanguageDefinition>,
+    user_count: usize,
+    first_line: Vec<(Regex, String)>,
+}
+
+impl Registry {
+    fn build(user: Vec<LanguageDefinition>) -> Registry {
+        let builtin: RegistryFile =
+            serde_json::from_str(BUILTIN_LANGUAGES).expect("bundled languages.json is valid");
+        let user_count = user.len();
+        let definitions: Vec<LanguageDefinition> =
+            user.into_iter().chain(builtin.languages).collect();
+        // A user pattern that no longer compiles is skipped rather than
+        // breaking detection for everything else.
+        let first_line = definitions
+            .iter()
+            .filter_map(|definition| {
+                let pattern = Regex::new(definition.first_line.as_deref()?).ok()?;
+                Some((pattern, definition.id.clone()))
+            })
+            .collect();
+        Registry {
+            definitions,
+            user_count,
+            first_line,
+        }
+    }
+
+    /// The first definition matching, and whether it is a user one.
+    fn find(&self, matches: impl Fn(&LanguageDefinition) -> bool) -> Option<(String, bool)> {
+        self.definitions
+            .iter()
+            .position(matches)
+            .map(|i| (self.definitions[i].id.clone(), i < self.user_count))
+    }
+
+    fn by_filename(&self, name: &str) -> Option<String> {
+        self.find(|d| d.filenames.iter().any(|f| f == name))
+            .map(|(id, _)| id)
+    }
+
+    fn by_extension(&self, extension: &str) -> Option<(String, bool)> {
+        self.find(|d| {
+            d.extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+
+    fn by_interpreter(&self, interpreter: &str) -> Option<String> {
+        self.find(|d| d.interpreters.iter().any(|i| i == interpreter))
+            .map(|(id, _)| id)
+    }
+
+    fn by_first_line(&self, line: &str) -> Option<String> {
+        self.first_line
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(line))
+            .map(|(_, id)| id.clone())
+    }
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(Registry::build(Vec::new())))
+}
+
+fn load_user_file(app: &AppHandle) -> Result<(PathBuf, RegistryFile), String> {
+    let path = app_data::app_data_path(app, REGISTRY_FILE)?;
+    let file = app_data::load_json(&path);
+    Ok((path, file))
+}
+
+/// Merges the user's mappings into the registry. Called once at startup
+/// and again whenever they change.
+pub fn load_user_mappings(app: &AppHandle) -> Result<(), String> {
+    let (_, file) = load_user_file(app)?;
+    *registry().write().unwrap() = Registry::build(file.languages);
+    Ok(())
+}
+
+/// Language from the file name or extension alone; `plaintext` when
+/// neither is known.
+pub fn language_for_path(path: &Path) -> String {
+    let registry = registry().read().unwrap();
+    path.file_name()
+        .and_then(|name| registry.by_filename(&name.to_string_lossy()))
+        .or_else(|| {
+            let extension = path.extension()?.to_str()?;
+            registry.by_extension(extension).map(|(id, _)| id)
+        })
+        .unwrap_or_else(|| "plaintext".to_string())
+}
+
+/// Picks a language from the file name or extension first, then falls back
+/// to the file's contents (modeline, shebang, first line) when those are
+/// missing or ambiguous, e.g. extensionless `build` scripts.
+pub fn detect_language(path: &Path, content: &str) -> String {
+    let registry = registry().read().unwrap();
+    if let Some(language) = path
+        .file_name()
+        .and_then(|name| registry.by_filename(&name.to_string_lossy()))
+    {
+        return language;
+    }
+
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let by_extension = extension.and_then(|ext| registry.by_extension(ext));
+    if let (Some(ext), Some((language, user))) = (extension, &by_extension) {
+        if *user || !AMBIGUOUS_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) {
+            return language.clone();
+        }
+    }
+
+    detect_from_content(&registry, content)
+        .or(by_extension.map(|(language, _)| language))
+        .unwrap_or_else(|| "plaintext".to_string())
+}
+
+/// Like `detect_language`, but reads only the head of the file from disk.
+pub fn detect_language_for_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buffer = vec![0u8; SNIFF_BYTES];
+    let read = file
+        .read(&mut buffer)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    buffer.truncate(read);
+    Ok(detect_language(path, &String::from_utf8_lossy(&buffer)))
+}
+
+fn detect_from_content(registry: &Registry, content: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    // An explicit modeline is the author's own statement, so it wins over
+    // heuristics on the first line.
+    let head = lines.iter().take(MODELINE_LINES);
+    let tail = lines.iter().rev().take(MODELINE_LINES);
+    for line in head.chain(tail) {
+        if let Some(language) = parse_vim_modeline(line).or_else(|| parse_emacs_modeline(line)) {
+            return Some(language);
+        }
+    }
+
+    let first = lines.iter().find(|l| !l.trim().is_empty())?.trim();
+    if let Some(shebang) = first.strip_prefix("#!") {
+        return parse_shebang(registry, shebang);
+    }
+    registry.by_first_line(first)
+}
+
+fn parse_shebang(registry: &Registry, shebang: &str) -> Option<String> {
+    let mut parts = shebang.split_whitespace();
+    let program = parts.next()?;
+    let mut interpreter = program.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = parts.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+    }
+
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    registry.by_interpreter(name)
+}
+
+/// `vim: set ft=python:` / `vi: filetype=sh` / `ex: syntax=ruby`
+fn parse_vim_modeline(line: &str) -> Option<String> {
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .find_map(|marker| line.find(marker).map(|i| i + marker.len()))?;
+    for option in line[start..].split(|c: char| c == ':' || c.is_whitespace()) {
+        if let Some((key, value)) = option.split_once('=') {
+            if matches!(key, "ft" | "filetype" | "syntax" | "syn") && !value.is_empty() {
+                return Some(normalize_alias(value));
+            }
+        }
+    }
+    None
+}
+
+/// `-*- mode: python -*-` or the short form `-*- python -*-`
+fn parse_emacs_modeline(line: &str) -> Option<String> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let body = line[start..end].trim();
+
+    if !body.contains(':') {
+        return Some(normalize_alias(body));
+    }
+    body.split(';').find_map(|setting| {
+        let (key, value) = setting.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case("mode") {
+            Some(normalize_alias(value.trim()))
+        } else {
+            None
+        }
+    })
+}
+
+/// Maps Vim/Emacs mode names onto the editor's language ids.
+fn normalize_alias(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    let name = name.strip_suffix("-mode").unwrap_or(&name);
+    match name {
+        "sh" | "bash" | "zsh" | "shell-script" => "shell",
+        "js" | "js2" | "javascript" => "javascript",
+        "ts" | "typescript" => "typescript",
+        "py" | "python" => "python",
+        "rb" | "ruby" => "ruby",
+        "rs" | "rust" => "rust",
+        "c++" | "cpp" => "cpp",
+        "cs" | "csharp" => "csharp",
+        "md" | "markdown" => "markdown",
+        "yml" | "yaml" => "yaml",
+        "conf" | "dosini" | "ini" => "ini",
+        "make" | "makefile" => "makefile",
+        other => other,
+    }
+    .to_string()
+}
+
+#[tauri::command]
+pub async fn detect_file_language(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    detect_language_for_file(&path)
+}
+
+#[tauri::command]
+pub async fn get_language_registry() -> Result<Vec<LanguageDefinition>, String> {
+    Ok(registry().read().unwrap().definitions.clone())
+}
+
+/// Adds user mappings for `mapping.id`, merged into any the user already
+/// has for that language. They take precedence over the built-in ones.
+#[tauri::command]
+pub async fn add_language_mapping(
+    mapping: LanguageDefinition,
+    app: AppHandle,
+) -> Result<Vec<LanguageDefinition>, String> {
+    if mapping.id.trim().is_empty() {
+        return Err("Language id must not be empty".to_string());
+    }
+    if let Some(pattern) = &mapping.first_line {
+        Regex::new(pattern).map_err(|e| format!("Invalid first-line pattern: {}", e))?;
+    }
+    let mapping = LanguageDefinition {
+        extensions: mapping
+            .extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_string())
+            .collect(),
+        ..mapping
+    };
+
+    let (path, mut file) = load_user_file(&app)?;
+    match file.languages.iter_mut().find(|d| d.id == mapping.id) {
+        Some(existing) => {
+            for (into, from) in [
+                (&mut existing.extensions, mapping.extensions),
+                (&mut existing.filenames, mapping.filenames),
+                (&mut existing.interpreters, mapping.interpreters),
+            ] {
+                for value in from {
+                    if !into.contains(&value) {
+                        into.push(value);
+                    }
+                }
+            }
+            if mapping.first_line.is_some() {
+                existing.first_line = mapping.first_line;
+            }
+        }
+        None => file.languages.push(mapping),
+    }
+    app_data::save_json(&path, &file)?;
+    load_user_mappings(&app)?;
+    Ok(file.languages)
+}
+
+/// Drops the user mappings for `language`, leaving the built-in ones.
+#[tauri::command]
+pub async fn remove_language_mapping(
+    language: String,
+    app: AppHandle,
+) -> Result<Vec<LanguageDefinition>, String> {
+    let (path, mut file) = load_user_file(&app)?;
+    file.languages.retain(|d| d.id != language);
+    app_data::save_json(&path, &file)?;
+    load_user_mappings(&app)?;
+    Ok(file.languages)
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const SCAN_CHUNK_SIZE: usize = 1024 * 1024;
+const MAX_RANGE_LINES: u64 = 100_000;
+/// Bounds a window of lines too, for minified files that are one huge line.
+const MAX_RANGE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Byte offset of the start of every line, so a window of lines can be read
+/// with a single seek instead of shipping the whole file over IPC.
+struct LineIndex {
+    len: u64,
+    modified: Option<SystemTime>,
+    line_starts: Vec<u64>,
+}
+
+#[derive(Default)]
+pub struct LargeFileIndexes {
+    indexes: Mutex<HashMap<String, Arc<LineIndex>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LargeFileInfo {
+    pub path: String,
+    pub size: u64,
+    pub line_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileRange {
+    pub start_line: u64,
+    pub end_line: u64,
+    pub total_lines: u64,
+    pub content: String,
+    /// The window hit `MAX_RANGE_BYTES`: `end_line` is where the content
+    /// actually stops, and its last line may be cut short.
+    pub truncated: bool,
+}
+
+impl LineIndex {
+    fn build(path: &str) -> Result<LineIndex, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let metadata = file
+            .metadata()
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+
+        let mut reader = BufReader::with_capacity(SCAN_CHUNK_SIZE, file);
+        let mut buffer = vec![0u8; SCAN_CHUNK_SIZE];
+        let mut line_starts = vec![0u64];
+        let mut offset = 0u64;
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if read == 0 {
+                break;
+            }
+            for (i, byte) in buffer[..read].iter().enumerate() {
+                if *byte == b'\n' {
+                    line_starts.push(offset + i as u64 + 1);
+                }
+            }
+            offset += read as u64;
+        }
+
+        // A trailing newline does not start another line.
+        if line_starts.len() > 1 && line_starts.last() == Some(&offset) {
+            line_starts.pop();
+        }
+
+        Ok(LineIndex {
+            len: offset,
+            modified: metadata.modified().ok(),
+            line_starts,
+        })
+    }
+
+    fn is_stale(&self, path: &str) -> bool {
+        match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len() != self.len || metadata.modified().ok() != self.modified,
+            Err(_) => true,
+        }
+    }
+
+    fn line_count(&self) -> u64 {
+        if self.len == 0 {
+            0
+        } else {
+            self.line_starts.len() as u64
+        }
+    }
+}
+
+impl LargeFileIndexes {
+    pub fn forget(&self, path: &str) {
+        self.indexes.lock().unwrap().remove(path);
+    }
+
+    pub fn clear(&self) {
+        self.indexes.lock().unwrap().clear();
+    }
+
+    /// Lines `start_line..end_line` of `path`, indexing it on first use.
+    /// Blocking.
+    pub fn read_lines(
+        &self,
+        path: &str,
+        start_line: u64,
+        end_line: u64,
+    ) -> Result<FileRange, String> {
+        read_range(path, &self.index(path)?, start_line, end_line)
+    }
+
+    /// The index of `path`, rebuilt when the file changed. The scan runs
+    /// without the lock, so one multi-GB file does not hold up the others.
+    /// Blocking.
+    fn index(&self, path: &str) -> Result<Arc<LineIndex>, String> {
+        let cached = self.indexes.lock().unwrap().get(path).cloned();
+        if let Some(index) = cached.filter(|index| !index.is_stale(path)) {
+            return Ok(index);
+        }
+        let index = Arc::new(LineIndex::build(path)?);
+        self.indexes
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), index.clone());
+        Ok(index)
+    }
+}
+
+fn read_range(
+    path: &str,
+    index: &LineIndex,
+    start_line: u64,
+    end_line: u64,
+) -> Result<FileRange, String> {
+    let total_lines = index.line_count();
+    let start = start_line.min(total_lines);
+    let end = end_line.min(total_lines).max(start);
+
+    let start_offset = if start < total_lines {
+        index.line_starts[start as usize]
+    } else {
+        index.len
+    };
+    let end_offset = if end < total_lines {
+        index.line_starts[end as usize]
+    } else {
+        index.len
+    };
+
+    let truncated = end_offset - start_offset > MAX_RANGE_BYTES;
+    let read_len = (end_offset - start_offset).min(MAX_RANGE_BYTES);
+
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    file.seek(SeekFrom::Start(start_offset))
+        .map_err(|e| format!("Failed to seek file: {}", e))?;
+    let mut bytes = vec![0u8; read_len as usize];
+    file.read_exact(&mut bytes)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let mut end = end;
+    if truncated {
+        // Stop after the last whole line, unless not even one fits.
+        if let Some(newline) = bytes.iter().rposition(|byte| *byte == b'\n') {
+            bytes.truncate(newline + 1);
+        }
+        let stop = start_offset + bytes.len() as u64;
+        end = index
+            .line_starts
+            .partition_point(|line_start| *line_start < stop) as u64;
+    }
+
+    Ok(FileRange {
+        start_line: start,
+        end_line: end,
+        total_lines,
+        content: String::from_utf8_lossy(&bytes).to_string(),
+        truncated,
+    })
+}
+
+#[tauri::command]
+pub async fn open_large_file(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<LargeFileInfo, String> {
+    let path = workspace::authorize(&windows, &window, &path)?
+        .to_string_lossy()
+        .to_string();
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = scope.large_files.index(&path)?;
+        Ok(LargeFileInfo {
+            size: index.len,
+            line_count: index.line_count(),
+            path,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to open large file: {}", e))?
+}
+
+/// Reads lines `start_line..end_line` (zero-based, end exclusive).
+#[tauri::command]
+pub async fn read_file_range(
+    path: String,
+    start_line: u64,
+    end_line: u64,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<FileRange, String> {
+    if end_line.saturating_sub(start_line) > MAX_RANGE_LINES {
+        return Err(format!(
+            "Cannot read more than {} lines at once",
+            MAX_RANGE_LINES
+        ));
+    }
+    let path = workspace::authorize(&windows, &window, &path)?
+        .to_string_lossy()
+        .to_string();
+    let scope = windows.scope(window.label());
+    tauri::async_runtime::spawn_blocking(move || {
+        scope.large_files.read_lines(&path, start_line, end_line)
+    })
+    .await
+    .map_err(|e| format!("Failed to read file range: {}", e))?
+}
+
+#[tauri::command]
+pub async fn close_large_file(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let path = workspace::resolve(Path::new(&path))?;
+    let scope = windows.scope(window.label());
+    scope.large_files.forget(&path.to_string_lossy());
+    scope.highlight_states.forget(&path.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::deep_link;
+use crate::window_manager::{self, PendingOpens};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Emitted as `open-path-failed` when a path from another launch of the
+/// app cannot be opened, since no caller is waiting for the error.
+#[derive(Debug, Clone, Serialize)]
+struct OpenPathFailed {
+    path: String,
+    error: String,
+}
+
+/// A path from the command line, with the position of an optional
+/// `:line` or `:line:column` suffix.
+#[derive(Debug, Clone)]
+struct Target {
+    path: PathBuf,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+/// Splits `:line` or `:line:column` off `arg`.
+fn split_position(arg: &str) -> (&str, Option<u32>, Option<u32>) {
+    let number = |text: &str| text.parse::<u32>().ok().filter(|n| *n > 0);
+    if let Some((rest, last)) = arg.rsplit_once(':') {
+        if let Some(last) = number(last) {
+            if let Some((path, line)) = rest.rsplit_once(':') {
+                if let Some(line) = number(line) {
+                    return (path, Some(line), Some(last));
+                }
+            }
+            return (rest, Some(last), None);
+        }
+    }
+    (arg, None, None)
+}
+
+fn receive_links(app: &AppHandle, args: &[String]) {
+    for link in args
+        .iter()
+        .skip(1)
+        .filter(|arg| deep_link::is_deep_link(arg))
+    {
+        deep_link::receive(app, link);
+    }
+}
+
+/// The paths in `args` (the program name first) relative to `cwd`, and
+/// whether `--new-window` was given. An argument that names an existing
+/// path as a whole is never split, so names with colons still open.
+/// `codeai:` links are left to `deep_link`.
+fn parse_args(args: &[String], cwd: &Path) -> (Vec<Target>, bool) {
+    let new_window = args.iter().any(|arg| arg == "--new-window" || arg == "-n");
+    let targets = args
+        .iter()
+        .skip(1)
+        // Flags, including the `-psn_` macOS adds.
+        .filter(|arg| !arg.starts_with('-') && !deep_link::is_deep_link(arg))
+        .map(|arg| {
+            let whole = cwd.join(arg);
+            if whole.exists() {
+                return Target {
+                    path: whole,
+                    line: None,
+                    column: None,
+                };
+            }
+            let (path, line, column) = split_position(arg);
+            Target {
+                path: cwd.join(path),
+                line,
+                column,
+            }
+        })
+        .collect();
+    (targets, new_window)
+}
+
+/// Opens the paths the app was started with. The first goes to the main
+/// window, whose frontend has not loaded yet and reads it with
+/// `take_pending_open`; the others open as for a later launch.
+pub fn open_startup_args(app: &AppHandle) {
+    let Ok(cwd) = std::env::current_dir() else {
+        return;
+    };
+    let args: Vec<String> = std::env::args().collect();
+    receive_links(app, &args);
+    let (targets, _) = parse_args(&args, &cwd);
+    let windows = app.state::<WindowRegistry>();
+    let mut targets = targets.into_iter();
+    if let (Some(first), Some(main)) = (targets.next(), app.get_window("main")) {
+        let opened = workspace::open_in(&main, &windows, &first.path, first.line, first.column);
+        if let Ok(opened) = opened {
+            app.state::<PendingOpens>().insert(main.label(), opened);
+        }
+    }
+    for target in targets {
+        let _ = window_manager::open(
+            app,
+            &windows,
+            Some(&target.path),
+            target.line,
+            target.column,
+            false,
+        );
+    }
+}
+
+/// Called by the single-instance lock with the arguments and working
+/// directory of a second launch, which exits instead of starting another
+/// app. Its paths open here with `window_manager::open`; without any, the
+/// current window is brought to the front.
+pub fn forward_launch(app: &AppHandle, args: Vec<String>, cwd: String) {
+    receive_links(app, &args);
+    let (targets, new_window) = parse_args(&args, Path::new(&cwd));
+    let windows = app.state::<WindowRegistry>();
+    if targets.is_empty() {
+        if args.iter().skip(1).any(|arg| deep_link::is_deep_link(arg)) {
+            return;
+        }
+        let _ = match window_manager::current_window(app) {
+            Some(window) if !new_window => window_manager::focus(&window),
+            _ => window_manager::open(app, &windows, None, None, None, true).map(|_| ()),
+        };
+        return;
+    }
+    for target in targets {
+        let opened = window_manager::open(
+            app,
+            &windows,
+            Some(&target.path),
+            target.line,
+            target.column,
+            new_window,
+        );
+        if let Err(error) = opened {
+            if let Some(window) = window_manager::current_window(app) {
+                let _ = window.emit(
+                    "open-path-failed",
+                    OpenPathFailed {
+                        path: target.path.to_string_lossy().to_string(),
+                        error,
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::{State, Window};
+
+use crate::encoding;
+use crate::text_health::is_probably_binary;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Only ever reported by detection; not a valid conversion target.
+    Mixed,
+}
+
+/// Returns `(lf, crlf)` counts, where `lf` excludes the `\n` of each `\r\n`.
+pub fn count(text: &str) -> (usize, usize) {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    (lf, crlf)
+}
+
+/// `None` for text without any line breaks.
+pub fn detect(text: &str) -> Option<LineEnding> {
+    match count(text) {
+        (0, 0) => None,
+        (_, 0) => Some(LineEnding::Lf),
+        (0, _) => Some(LineEnding::Crlf),
+        _ => Some(LineEnding::Mixed),
+    }
+}
+
+pub fn convert(text: &str, eol: LineEnding) -> Result<String, String> {
+    let normalized = text.replace("\r\n", "\n");
+    match eol {
+        LineEnding::Lf => Ok(normalized),
+        LineEnding::Crlf => Ok(normalized.replace('\n', "\r\n")),
+        LineEnding::Mixed => Err("Cannot convert to mixed line endings".to_string()),
+    }
+}
+
+/// Rewrites the file with `eol` line endings, keeping its encoding and BOM.
+/// Returns whether the file changed.
+#[tauri::command]
+pub async fn convert_line_endings(
+    path: String,
+    eol: LineEnding,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let decoded = encoding::decode(&bytes);
+    // UTF-16 text is full of NUL bytes, so only sniff other encodings.
+    if !decoded.encoding.starts_with("UTF-16") && is_probably_binary(&bytes) {
+        return Err("File looks binary; refusing to rewrite it".to_string());
+    }
+
+    let converted = convert(&decoded.content, eol)?;
+    if converted == decoded.content {
+        return Ok(false);
+    }
+    let bytes = encoding::encode(&converted, &decoded.encoding, decoded.has_bom)?;
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(true)
+}
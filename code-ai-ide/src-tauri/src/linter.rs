@@ -0,0 +1,504 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Manager, State, Window};
+
+use crate::diagnostics;
+use crate::problem_matcher::{Diagnostic, Fix, FixEdit, Severity};
+use crate::process::{self, CommandResult, ExecOptions, ProcessKind};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Linter {
+    Clippy,
+    Eslint,
+    Ruff,
+}
+
+impl Linter {
+    fn for_language(language: &str) -> Option<Self> {
+        match language {
+            "rust" => Some(Linter::Clippy),
+            "javascript" | "typescript" | "javascriptreact" | "typescriptreact" => {
+                Some(Linter::Eslint)
+            }
+            "python" => Some(Linter::Ruff),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Linter::Clippy => "clippy",
+            Linter::Eslint => "eslint",
+            Linter::Ruff => "ruff",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintReport {
+    pub linter: Linter,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+fn run(
+    window: &Window,
+    windows: &WindowRegistry,
+    command: &str,
+    args: &[String],
+    cwd: &Path,
+) -> Result<CommandResult, String> {
+    let cmd = process::prepare(
+        command,
+        args,
+        Some(cwd.to_string_lossy().to_string()),
+        &ExecOptions::default(),
+        window,
+        windows,
+    )?;
+    process::run_blocking(
+        &windows.scope(window.label()),
+        cmd,
+        ProcessKind::Command,
+        None,
+        None,
+    )
+}
+
+/// Linters exit non-zero when they find problems; only a run that produced
+/// nothing to parse is a failure.
+fn check_output(linter: Linter, result: &CommandResult, parsed_any: bool) -> Result<(), String> {
+    if parsed_any || result.exit_code == Some(0) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed: {}",
+            linter.name(),
+            result.stderr.trim()
+        ))
+    }
+}
+
+fn severity(level: &str) -> Severity {
+    match level {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => Severity::Info,
+    }
+}
+
+fn number(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+fn nearest_manifest(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn clippy_span_edit(root: &Path, span: &Value) -> Option<FixEdit> {
+    Some(FixEdit {
+        path: root
+            .join(span.get("file_name")?.as_str()?)
+            .to_string_lossy()
+            .to_string(),
+        start_line: number(span, "line_start"),
+        start_column: number(span, "column_start"),
+        end_line: number(span, "line_end"),
+        end_column: number(span, "column_end"),
+        text: span.get("suggested_replacement")?.as_str()?.to_string(),
+    })
+}
+
+/// Turns one `compiler-message` into a diagnostic at its primary span, with
+/// a fix per `help` child that carries replacements.
+fn clippy_diagnostic(root: &Path, message: &Value) -> Option<Diagnostic> {
+    let spans = message.get("spans")?.as_array()?;
+    let primary = spans
+        .iter()
+        .find(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true))?;
+    let fixes = message
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| {
+            let edits: Vec<FixEdit> = child
+                .get("spans")?
+                .as_array()?
+                .iter()
+                .filter_map(|span| clippy_span_edit(root, span))
+                .collect();
+            (!edits.is_empty()).then(|| Fix {
+                title: child
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Apply suggestion")
+                    .to_string(),
+                edits,
+            })
+        })
+        .collect();
+    let code = message
+        .get("code")
+        .and_then(|code| code.get("code"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Some(Diagnostic {
+        id: String::new(),
+        path: root
+            .join(primary.get("file_name")?.as_str()?)
+            .to_string_lossy()
+            .to_string(),
+        line: number(primary, "line_start"),
+        column: number(primary, "column_start"),
+        end_line: Some(number(primary, "line_end")),
+        end_column: Some(number(primary, "column_end")),
+        severity: severity(message.get("level")?.as_str()?),
+        message: message.get("message")?.as_str()?.to_string(),
+        source: if code.as_deref().is_some_and(|c| c.starts_with("clippy::")) {
+            "clippy".to_string()
+        } else {
+            "rustc".to_string()
+        },
+        code,
+        fixes,
+    })
+}
+
+/// Clippy checks whole crates; `paths` only pick which one.
+fn lint_clippy(
+    window: &Window,
+    windows: &WindowRegistry,
+    start: &Path,
+) -> Result<(PathBuf, Vec<Diagnostic>), String> {
+    let crate_dir =
+        nearest_manifest(start).ok_or_else(|| "No Cargo.toml found to lint".to_string())?;
+    // Span file names are relative to the workspace root, which may sit
+    // above the crate.
+    let located = run(
+        window,
+        windows,
+        "cargo",
+        &[
+            "locate-project".to_string(),
+            "--workspace".to_string(),
+            "--message-format".to_string(),
+            "plain".to_string(),
+        ],
+        &crate_dir,
+    )?;
+    let root = Path::new(located.stdout.trim())
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| crate_dir.clone());
+
+    let result = run(
+        window,
+        windows,
+        "cargo",
+        &["clippy".to_string(), "--message-format=json".to_string()],
+        &crate_dir,
+    )?;
+    let messages: Vec<Value> = result
+        .stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|line| line.get("reason").and_then(Value::as_str) == Some("compiler-message"))
+        .filter_map(|line| line.get("message").cloned())
+        .collect();
+    check_output(Linter::Clippy, &result, !messages.is_empty())?;
+
+    let mut found: Vec<Diagnostic> = Vec::new();
+    for message in &messages {
+        if let Some(diagnostic) = clippy_diagnostic(&root, message) {
+            // The same message is reported once per target that builds the
+            // file (lib and tests, say).
+            let duplicate = found.iter().any(|d| {
+                d.path == diagnostic.path
+                    && d.line == diagnostic.line
+                    && d.column == diagnostic.column
+                    && d.message == diagnostic.message
+            });
+            if !duplicate {
+                found.push(diagnostic);
+            }
+        }
+    }
+    Ok((root, found))
+}
+
+/// Line and column (one-based, UTF-16 columns like ESLint's own) of a
+/// UTF-16 offset into `text`.
+fn position_at(text: &str, offset: usize) -> (u32, u32) {
+    let (mut line, mut column, mut units) = (1, 1, 0);
+    for c in text.chars() {
+        if units >= offset {
+            break;
+        }
+        units += c.len_utf16();
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += c.len_utf16() as u32;
+        }
+    }
+    (line, column)
+}
+
+fn eslint_fix(path: &str, text: &str, title: String, fix: &Value) -> Option<Fix> {
+    let range = fix.get("range")?.as_array()?;
+    let start = position_at(text, range.first()?.as_u64()? as usize);
+    let end = position_at(text, range.get(1)?.as_u64()? as usize);
+    Some(Fix {
+        title,
+        edits: vec![FixEdit {
+            path: path.to_string(),
+            start_line: start.0,
+            start_column: start.1,
+            end_line: end.0,
+            end_column: end.1,
+            text: fix.get("text")?.as_str()?.to_string(),
+        }],
+    })
+}
+
+fn lint_eslint(
+    window: &Window,
+    windows: &WindowRegistry,
+    root: &Path,
+    paths: &[PathBuf],
+) -> Result<Vec<Diagnostic>, String> {
+    // The project's own ESLint knows its config and plugins.
+    let local = root
+        .join("node_modules")
+        .join(".bin")
+        .join(if cfg!(windows) {
+            "eslint.cmd"
+        } else {
+            "eslint"
+        });
+    let command = if local.is_file() {
+        local.to_string_lossy().to_string()
+    } else {
+        "eslint".to_string()
+    };
+    let mut args = vec!["-f".to_string(), "json".to_string()];
+    if paths.is_empty() {
+        args.push(".".to_string());
+    }
+    args.extend(paths.iter().map(|path| path.to_string_lossy().to_string()));
+    let result = run(window, windows, &command, &args, root)?;
+    let files: Vec<Value> = serde_json::from_str(result.stdout.trim()).unwrap_or_default();
+    check_output(Linter::Eslint, &result, !files.is_empty())?;
+
+    let mut found = Vec::new();
+    for file in &files {
+        let Some(path) = file.get("filePath").and_then(Value::as_str) else {
+            continue;
+        };
+        let messages = file.get("messages").and_then(Value::as_array);
+        let text = fs::read_to_string(path).unwrap_or_default();
+        for message in messages.into_iter().flatten() {
+            let mut fixes: Vec<Fix> = message
+                .get("fix")
+                .and_then(|fix| eslint_fix(path, &text, "Fix this problem".to_string(), fix))
+                .into_iter()
+                .collect();
+            for suggestion in message
+                .get("suggestions")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let title = suggestion
+                    .get("desc")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Apply suggestion")
+                    .to_string();
+                if let Some(fix) = suggestion
+                    .get("fix")
+                    .and_then(|fix| eslint_fix(path, &text, title, fix))
+                {
+                    fixes.push(fix);
+                }
+            }
+            let end_line = message.get("endLine").and_then(Value::as_u64);
+            found.push(Diagnostic {
+                id: String::new(),
+                path: path.to_string(),
+                line: number(message, "line"),
+                column: number(message, "column"),
+                end_line: end_line.map(|line| line as u32),
+                end_column: end_line.map(|_| number(message, "endColumn")),
+                severity: if number(message, "severity") >= 2 {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                },
+                message: message
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                code: message
+                    .get("ruleId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                source: "eslint".to_string(),
+                fixes,
+            });
+        }
+    }
+    Ok(found)
+}
+
+fn lint_ruff(
+    window: &Window,
+    windows: &WindowRegistry,
+    root: &Path,
+    paths: &[PathBuf],
+) -> Result<Vec<Diagnostic>, String> {
+    let args: Vec<String> = ["check", "--output-format", "json", "--exit-zero"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain(paths.iter().map(|path| path.to_string_lossy().to_string()))
+        .collect();
+    let result = run(window, windows, "ruff", &args, root)?;
+    let items: Vec<Value> = serde_json::from_str(result.stdout.trim()).unwrap_or_default();
+    check_output(Linter::Ruff, &result, !items.is_empty())?;
+
+    let location = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .map(|location| (number(location, "row"), number(location, "column")))
+    };
+    let found = items
+        .iter()
+        .filter_map(|item| {
+            let path = item.get("filename")?.as_str()?.to_string();
+            let start = location(item, "location")?;
+            let end = location(item, "end_location");
+            let fixes = item
+                .get("fix")
+                .filter(|fix| !fix.is_null())
+                .and_then(|fix| {
+                    let edits = fix
+                        .get("edits")?
+                        .as_array()?
+                        .iter()
+                        .filter_map(|edit| {
+                            let (start, end) =
+                                (location(edit, "location")?, location(edit, "end_location")?);
+                            Some(FixEdit {
+                                path: path.clone(),
+                                start_line: start.0,
+                                start_column: start.1,
+                                end_line: end.0,
+                                end_column: end.1,
+                                text: edit.get("content")?.as_str()?.to_string(),
+                            })
+                        })
+                        .collect();
+                    Some(Fix {
+                        title: fix
+                            .get("message")
+                            .and_then(Value::as_str)
+                            .unwrap_or("Apply fix")
+                            .to_string(),
+                        edits,
+                    })
+                })
+                .into_iter()
+                .collect();
+            Some(Diagnostic {
+                id: String::new(),
+                path,
+                line: start.0,
+                column: start.1,
+                end_line: end.map(|end| end.0),
+                end_column: end.map(|end| end.1),
+                severity: Severity::Warning,
+                message: item.get("message")?.as_str()?.to_string(),
+                code: item.get("code").and_then(Value::as_str).map(str::to_string),
+                source: "ruff".to_string(),
+                fixes,
+            })
+        })
+        .collect();
+    Ok(found)
+}
+
+/// What a run over `paths` covers, for replacing earlier results.
+fn paths_or_root(paths: &[PathBuf], root: &Path) -> Vec<PathBuf> {
+    if paths.is_empty() {
+        vec![root.to_path_buf()]
+    } else {
+        paths.to_vec()
+    }
+}
+
+/// Runs the linter for `language` over `paths` (files or folders; the
+/// workspace when empty) and replaces what it reported for them in the
+/// diagnostics store.
+#[tauri::command]
+pub async fn run_linter(
+    language: String,
+    paths: Vec<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<LintReport, String> {
+    let linter = Linter::for_language(&language)
+        .ok_or_else(|| format!("No linter available for {}", language))?;
+    let paths = paths
+        .iter()
+        .map(|path| workspace::authorize(&windows, &window, path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let scope = windows.scope(window.label());
+    let root = match paths.first() {
+        Some(first) => scope
+            .workspace
+            .roots()
+            .into_iter()
+            .find(|root| first.starts_with(root)),
+        None => scope
+            .workspace
+            .active()
+            .or_else(|| scope.workspace.roots().into_iter().next()),
+    }
+    .ok_or_else(|| "No workspace is open".to_string())?;
+
+    let worker = window.clone();
+    let (covered, found) = tauri::async_runtime::spawn_blocking(move || {
+        let windows = worker.state::<WindowRegistry>();
+        match linter {
+            Linter::Clippy => {
+                let start = paths.first().unwrap_or(&root);
+                lint_clippy(&worker, &windows, start).map(|(root, found)| (vec![root], found))
+            }
+            Linter::Eslint => lint_eslint(&worker, &windows, &root, &paths)
+                .map(|found| (paths_or_root(&paths, &root), found)),
+            Linter::Ruff => lint_ruff(&worker, &windows, &root, &paths)
+                .map(|found| (paths_or_root(&paths, &root), found)),
+        }
+    })
+    .await
+    .map_err(|e| format!("Linter failed: {}", e))??;
+
+    let changed =
+        scope
+            .diagnostics
+            .set_within(&format!("lint:{}", linter.name()), &covered, found.clone());
+    diagnostics::notify(&window, changed);
+    Ok(LintReport {
+        linter,
+        diagnostics: found,
+    })
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::encoding::{self, DecodedText};
+use crate::metadata::to_millis;
+use crate::replace;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const HISTORY_DIR: &str = "history";
+const MAX_REVISIONS: usize = 50;
+/// Larger files are not snapshotted; history is for source, not assets.
+const MAX_SNAPSHOT_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionSource {
+    Save,
+    Restore,
+    Replace,
+    AiEdit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: String,
+    /// When the content was replaced, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub size: u64,
+    /// What replaced this content.
+    pub source: RevisionSource,
+    hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryIndex {
+    path: String,
+    /// Newest first.
+    revisions: Vec<Revision>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionDiff {
+    /// Unified diff from the revision to the current file.
+    pub diff: String,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Previous versions of saved files, kept in the app data directory and
+/// independent of any version control. Each file gets a directory named by
+/// a hash of its path holding an index and one blob per revision.
+#[derive(Default)]
+pub struct LocalHistory {
+    lock: Mutex<()>,
+}
+
+fn file_dir(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let key = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    let dir = app_data::app_data_path(app, &format!("{}/{}", HISTORY_DIR, &key[..32]))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory: {}", e))?;
+    Ok(dir)
+}
+
+impl LocalHistory {
+    /// Stores `previous` as the newest revision of `path`, unless it matches
+    /// the newest revision already, and drops revisions beyond the cap.
+    pub fn record(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        previous: &[u8],
+        source: RevisionSource,
+    ) -> Result<(), String> {
+        if previous.len() > MAX_SNAPSHOT_BYTES {
+            return Ok(());
+        }
+        let _guard = self.lock.lock().unwrap();
+        let dir = file_dir(app, path)?;
+        let index_path = dir.join("index.json");
+        let mut index: HistoryIndex = app_data::load_json(&index_path);
+
+        let hash = blake3::hash(previous).to_hex().to_string();
+        if index.revisions.first().map(|r| &r.hash) == Some(&hash) {
+            return Ok(());
+        }
+
+        let revision = Revision {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: to_millis(SystemTime::now()).unwrap_or(0),
+            size: previous.len() as u64,
+            source,
+            hash,
+        };
+        app_data::write_atomic(&dir.join(&revision.id), previous)?;
+        index.path = path.to_string_lossy().to_string();
+        index.revisions.insert(0, revision);
+        for dropped in index
+            .revisions
+            .split_off(index.revisions.len().min(MAX_REVISIONS))
+        {
+            let _ = fs::remove_file(dir.join(&dropped.id));
+        }
+        app_data::save_json(&index_path, &index)
+    }
+
+    fn list(&self, app: &AppHandle, path: &Path) -> Result<Vec<Revision>, String> {
+        let _guard = self.lock.lock().unwrap();
+        let index: HistoryIndex = app_data::load_json(&file_dir(app, path)?.join("index.json"));
+        Ok(index.revisions)
+    }
+
+    fn read(&self, app: &AppHandle, path: &Path, revision_id: &str) -> Result<Vec<u8>, String> {
+        // Ids come from the frontend; only accept ones the index knows.
+        if !self.list(app, path)?.iter().any(|r| r.id == revision_id) {
+            return Err(format!("Unknown revision: {}", revision_id));
+        }
+        fs::read(file_dir(app, path)?.join(revision_id))
+            .map_err(|e| format!("Failed to read revision: {}", e))
+    }
+}
+
+#[tauri::command]
+pub async fn list_local_history(
+    path: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<Vec<Revision>, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    history.list(&app, &path)
+}
+
+#[tauri::command]
+pub async fn read_local_history(
+    path: String,
+    revision_id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<DecodedText, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    Ok(encoding::decode(&history.read(
+        &app,
+        &path,
+        &revision_id,
+    )?))
+}
+
+#[tauri::command]
+pub async fn diff_local_history(
+    path: String,
+    revision_id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<RevisionDiff, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let old = encoding::decode(&history.read(&app, &path, &revision_id)?).content;
+    // A deleted file diffs against empty content.
+    let new = fs::read(&path)
+        .map(|bytes| encoding::decode(&bytes).content)
+        .unwrap_or_default();
+
+    let diff = TextDiff::from_lines(&old, &new);
+    let (mut insertions, mut deletions) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => insertions += 1,
+            similar::ChangeTag::Delete => deletions += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+    let name = path.to_string_lossy();
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("{} ({})", name, revision_id), &name)
+        .to_string();
+
+    Ok(RevisionDiff {
+        diff: unified,
+        insertions,
+        deletions,
+    })
+}
+
+/// Writes the revision back to disk. The content being replaced is recorded
+/// first, so a restore can itself be undone; when it cannot be, the file is
+/// left as is.
+#[tauri::command]
+pub async fn restore_local_history(
+    path: String,
+    revision_id: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<(), String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let bytes = history.read(&app, &path, &revision_id)?;
+    match fs::read(&path) {
+        Ok(previous) => {
+            if previous.len() > MAX_SNAPSHOT_BYTES {
+                return Err(format!(
+                    "{} is too large to keep in local history, so it cannot be restored over",
+                    path.display()
+                ));
+            }
+            history.record(&app, &path, &previous, RevisionSource::Restore)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    }
+    replace::write_replacing(&path, &bytes)
+}
@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::ChildStdin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{Manager, State, Window};
+
+use crate::diagnostics;
+use crate::preview;
+use crate::process::{self, CommandExit, ExecOptions, ProcessHooks, ProcessKind, ProcessSignal};
+use crate::toolchain;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+/// How long `lsp_stop` waits after `shutdown`/`exit` before killing.
+const STOP_GRACE: Duration = Duration::from_secs(3);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The program to start for a language server; overrides the default for
+/// the language.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageServerInfo {
+    /// Also the id of the process in `list_processes`.
+    pub id: String,
+    pub language: String,
+    pub root: String,
+    pub command: ServerCommand,
+}
+
+/// Emitted as `lsp-message` for everything the server writes: responses,
+/// notifications and requests to the client.
+#[derive(Debug, Clone, Serialize)]
+struct LspMessage {
+    server_id: String,
+    message: Value,
+}
+
+/// Emitted as `lsp-exit` when a server stops for any reason.
+#[derive(Debug, Clone, Serialize)]
+struct LspExit {
+    server_id: String,
+    language: String,
+    root: String,
+    exit_code: Option<i32>,
+}
+
+struct LanguageServer {
+    info: LanguageServerInfo,
+    stdin: Arc<Mutex<ChildStdin>>,
+}
+
+/// Language servers of a window, keyed by server id. One server runs per
+/// command and root, so TypeScript and JavaScript share theirs.
+#[derive(Default)]
+pub struct LanguageServers {
+    servers: Mutex<HashMap<String, LanguageServer>>,
+}
+
+impl LanguageServers {
+    fn find(&self, command: &ServerCommand, root: &str) -> Option<LanguageServerInfo> {
+        self.servers
+            .lock()
+            .unwrap()
+            .values()
+            .find(|server| server.info.command == *command && server.info.root == root)
+            .map(|server| server.info.clone())
+    }
+
+    fn stdin(&self, id: &str) -> Option<Arc<Mutex<ChildStdin>>> {
+        self.servers
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|server| server.stdin.clone())
+    }
+
+    fn remove(&self, id: &str) -> Option<LanguageServerInfo> {
+        self.servers
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map(|server| server.info)
+    }
+
+    pub fn clear(&self) {
+        self.servers.lock().unwrap().clear();
+    }
+}
+
+/// The server usually installed for a language id, talking over stdio.
+pub fn default_server(language: &str) -> Option<ServerCommand> {
+    let (command, args): (&str, &[&str]) = match language {
+        "rust" => ("rust-analyzer", &[]),
+        "typescript" | "javascript" | "typescriptreact" | "javascriptreact" => {
+            ("typescript-language-server", &["--stdio"])
+        }
+        "python" => ("pyright-langserver", &["--stdio"]),
+        "go" => ("gopls", &[]),
+        "c" | "cpp" | "objective-c" | "objective-cpp" => ("clangd", &[]),
+        "html" => ("vscode-html-language-server", &["--stdio"]),
+        "css" | "scss" | "less" => ("vscode-css-language-server", &["--stdio"]),
+        "json" | "jsonc" => ("vscode-json-language-server", &["--stdio"]),
+        "yaml" => ("yaml-language-server", &["--stdio"]),
+        "shell" => ("bash-language-server", &["start"]),
+        "lua" => ("lua-language-server", &[]),
+        _ => return None,
+    };
+    Some(ServerCommand {
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    })
+}
+
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let path = preview::percent_decode(uri.strip_prefix("file://")?);
+    // file:///C:/src/main.rs
+    if cfg!(windows) {
+        Some(path.trim_start_matches('/').replace('/', "\\"))
+    } else {
+        Some(path)
+    }
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Reads `Content-Length` framed JSON-RPC messages until the pipe closes.
+/// Frames that are not JSON are skipped.
+pub fn read_messages(pipe: impl Read, mut on_message: impl FnMut(Value)) {
+    let mut reader = BufReader::new(pipe);
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let Some(length) = length else {
+            continue;
+        };
+        let mut body = vec![0; length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+        if let Ok(message) = serde_json::from_slice(&body) {
+            on_message(message);
+        }
+    }
+}
+
+pub fn write_message(stdin: &Mutex<ChildStdin>, message: &Value) -> Result<(), String> {
+    let body = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+    let mut stdin = stdin.lock().unwrap();
+    stdin
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .and_then(|_| stdin.write_all(&body))
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("Failed to write to language server: {}", e))
+}
+
+fn launch(
+    window: Window,
+    scope: Arc<WindowState>,
+    language: String,
+    root: String,
+    command: ServerCommand,
+) -> Result<LanguageServerInfo, String> {
+    let windows = window.state::<WindowRegistry>();
+    let cmd = process::prepare(
+        &command.command,
+        &command.args,
+        Some(root.clone()),
+        &ExecOptions::default(),
+        &window,
+        &windows,
+    )?;
+
+    let server_id = Arc::new(OnceLock::<String>::new());
+    let on_exit = {
+        let (window, scope, server_id) = (window.clone(), scope.clone(), server_id.clone());
+        let (language, root) = (language.clone(), root.clone());
+        move |exit: &CommandExit| {
+            let Some(server_id) = server_id.get() else {
+                return;
+            };
+            scope.language_servers.remove(server_id);
+            let cleared = scope.diagnostics.clear_owner(&format!("lsp:{}", server_id));
+            diagnostics::notify(&window, cleared);
+            let _ = window.emit(
+                "lsp-exit",
+                LspExit {
+                    server_id: server_id.clone(),
+                    language,
+                    root,
+                    exit_code: exit.exit_code,
+                },
+            );
+        }
+    };
+
+    let (id, stdin, stdout) = process::start_attached(
+        window.clone(),
+        scope.clone(),
+        cmd,
+        ProcessKind::LanguageServer,
+        ProcessHooks {
+            on_output: None,
+            on_exit: Some(Box::new(on_exit)),
+        },
+    )?;
+    let info = LanguageServerInfo {
+        id: id.clone(),
+        language,
+        root,
+        command,
+    };
+    scope.language_servers.servers.lock().unwrap().insert(
+        id.clone(),
+        LanguageServer {
+            info: info.clone(),
+            stdin: Arc::new(Mutex::new(stdin)),
+        },
+    );
+    let _ = server_id.set(id.clone());
+    // A server that died at once may have exited before it was recorded.
+    if !scope.processes.is_running(&id) {
+        scope.language_servers.remove(&id);
+    }
+
+    let (owner, source) = (format!("lsp:{}", id), info.language.clone());
+    thread::spawn(move || {
+        read_messages(stdout, |message| {
+            // Diagnostics also go to the shared store; the client still sees
+            // the notification.
+            if message.get("method").and_then(Value::as_str)
+                == Some("textDocument/publishDiagnostics")
+            {
+                if let Some((path, found)) = diagnostics::from_lsp(&message["params"], &source) {
+                    let changed = scope.diagnostics.set_file(&owner, &path, found);
+                    diagnostics::notify(&window, changed);
+                }
+            }
+            let _ = window.emit(
+                "lsp-message",
+                LspMessage {
+                    server_id: id.clone(),
+                    message,
+                },
+            );
+        })
+    });
+    Ok(info)
+}
+
+/// Starts the language server for `language` in `root`, or returns the one
+/// already running there. The client drives the protocol, `initialize`
+/// included; server output arrives as `lsp-message` events.
+#[tauri::command]
+pub async fn lsp_start(
+    language: String,
+    root: String,
+    server: Option<ServerCommand>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<LanguageServerInfo, String> {
+    let root = workspace::authorize(&windows, &window, &root)?
+        .to_string_lossy()
+        .to_string();
+    // Prefer what is on PATH or was installed by `ensure_language_server`.
+    let command = server
+        .or_else(|| toolchain::locate(&window.app_handle(), &language).map(|found| found.server))
+        .or_else(|| default_server(&language))
+        .ok_or_else(|| format!("No language server known for {}", language))?;
+    let scope = windows.scope(window.label());
+    if let Some(server) = scope.language_servers.find(&command, &root) {
+        return Ok(server);
+    }
+    launch(window, scope, language, root, command)
+}
+
+/// Sends one JSON-RPC message (request, response or notification) to the
+/// server, adding the framing.
+#[tauri::command]
+pub async fn lsp_send(
+    server_id: String,
+    message: Value,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let stdin = windows
+        .scope(window.label())
+        .language_servers
+        .stdin(&server_id)
+        .ok_or_else(|| format!("Language server is not running: {}", server_id))?;
+    // A server busy writing may not read for a while; do not block the
+    // command thread on a full pipe.
+    tauri::async_runtime::spawn_blocking(move || write_message(&stdin, &message))
+        .await
+        .map_err(|e| format!("Failed to send message: {}", e))?
+}
+
+/// Asks the server to shut down and exit, killing it if it has not after a
+/// grace period. Returns whether it was running.
+#[tauri::command]
+pub async fn lsp_stop(
+    server_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let scope = windows.scope(window.label());
+    let Some(stdin) = scope.language_servers.stdin(&server_id) else {
+        return Ok(false);
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let shutdown = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": format!("codeai-shutdown-{}", server_id),
+            "method": "shutdown",
+        });
+        let exit = serde_json::json!({ "jsonrpc": "2.0", "method": "exit" });
+        let _ = write_message(&stdin, &shutdown).and_then(|_| write_message(&stdin, &exit));
+
+        let deadline = Instant::now() + STOP_GRACE;
+        while scope.processes.is_running(&server_id) {
+            if Instant::now() >= deadline {
+                scope.processes.signal(&server_id, ProcessSignal::Kill);
+                break;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        true
+    })
+    .await
+    .map_err(|e| format!("Failed to stop language server: {}", e))
+}
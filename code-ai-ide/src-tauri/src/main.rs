@@ -1,11 +1,82 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod activity;
+mod ai;
+mod app_data;
+mod attach;
+mod autosave;
+mod breakpoints;
+mod code_image;
+mod command_registry;
+mod compare;
+mod debug;
+mod deep_link;
+mod dev_server;
+mod diagnostics;
+mod documents;
+mod dotenv;
+mod editorconfig;
+mod encoding;
+mod exclude;
+mod extensions;
+mod file_index;
+mod formatter;
+mod fs_ops;
+mod git;
+mod hashing;
+mod highlight;
+mod hot_exit;
+mod jsonc;
+mod language;
+mod large_file;
+mod launch;
+mod line_endings;
+mod linter;
+mod local_history;
+mod lsp;
+mod metadata;
+mod path_resolve;
+mod preflight;
+mod preview;
+mod problem_matcher;
+mod process;
+mod project;
+mod recent;
+mod rename;
+mod replace;
+mod scaffold;
+mod search;
+mod secrets;
+mod semantic;
+mod session;
+mod settings;
+mod snippets;
+mod syntax;
+mod system_open;
+mod tasks;
+mod test_explorer;
+mod text_health;
+mod themes;
+mod toolchain;
+mod trust;
+mod vault;
+mod vscode_import;
+mod walk;
+mod watcher;
+mod watches;
+mod window_manager;
+mod window_state;
+mod workspace;
+mod workspace_edit;
+
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use tauri::api::dialog;
-use tauri::Manager;
+use tauri::{AppHandle, Manager, State, Window};
+
+use window_state::WindowRegistry;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct FileInfo {
@@ -13,6 +84,34 @@ struct FileInfo {
     path: String,
     content: String,
     language: String,
+    encoding: String,
+    has_bom: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DirEntryInfo {
+    name: String,
+    path: String,
+    /// For symlinks this describes the target, not the link itself.
+    is_dir: bool,
+    is_symlink: bool,
+    /// Canonical target of a symlink; `None` for dangling links.
+    symlink_target: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DialogFilter {
+    name: String,
+    extensions: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SaveOutcome {
+    /// What was written when format-on-save or `.editorconfig` rules
+    /// changed the content; the editor should take it over.
+    formatted: Option<String>,
+    /// Why formatting was skipped. The unformatted content was saved.
+    format_error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,28 +122,35 @@ struct ProjectInfo {
 }
 
 #[tauri::command]
-async fn open_file_dialog() -> Result<Option<FileInfo>, String> {
-    let window = tauri::Manager::app_handle(&tauri::AppHandle::default());
-    
+async fn open_file_dialog(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<FileInfo>, String> {
     let file_path = dialog::blocking::FileDialogBuilder::new()
         .add_filter("All Files", &["*"])
         .pick_file();
-    
+
     match file_path {
         Some(path) => {
-            let content = fs::read_to_string(&path)
-                .map_err(|e| format!("Failed to read file: {}", e))?;
-            
-            let language = get_language_from_extension(&path);
-            
+            // Picking a file in the native dialog is an explicit user grant.
+            windows.scope(window.label()).workspace.grant(&path)?;
+
+            let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            let decoded = encoding::decode(&bytes);
+
+            let language = language::detect_language(&path, &decoded.content);
+
             Ok(Some(FileInfo {
-                name: path.file_name()
+                name: path
+                    .file_name()
                     .and_then(|n| n.to_str())
                     .unwrap_or("Unknown")
                     .to_string(),
                 path: path.to_string_lossy().to_string(),
-                content,
+                content: decoded.content,
                 language,
+                encoding: decoded.encoding,
+                has_bom: decoded.has_bom,
             }))
         }
         None => Ok(None),
@@ -52,117 +158,242 @@ async fn open_file_dialog() -> Result<Option<FileInfo>, String> {
 }
 
 #[tauri::command]
-async fn save_file(path: String, content: String) -> Result<(), String> {
-    fs::write(&path, content)
-        .map_err(|e| format!("Failed to write file: {}", e))?;
-    Ok(())
+async fn save_file_dialog(
+    default_name: Option<String>,
+    default_dir: Option<String>,
+    filters: Option<Vec<DialogFilter>>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    let mut builder = dialog::blocking::FileDialogBuilder::new().set_parent(&window);
+    if let Some(name) = &default_name {
+        builder = builder.set_file_name(name);
+    }
+    if let Some(dir) = &default_dir {
+        builder = builder.set_directory(dir);
+    }
+    for filter in filters.unwrap_or_default() {
+        let extensions: Vec<&str> = filter.extensions.iter().map(String::as_str).collect();
+        builder = builder.add_filter(&filter.name, &extensions);
+    }
+
+    match builder.save_file() {
+        Some(path) => {
+            // The user chose this location in the native dialog, so it is
+            // writable even when it lies outside the workspace.
+            windows.scope(window.label()).workspace.grant(&path)?;
+            Ok(Some(path.to_string_lossy().to_string()))
+        }
+        None => Ok(None),
+    }
 }
 
 #[tauri::command]
-async fn read_file(path: String) -> Result<String, String> {
-    fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read file: {}", e))
+#[allow(clippy::too_many_arguments)]
+async fn save_file(
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    bom: Option<bool>,
+    eol: Option<line_endings::LineEnding>,
+    keep_history: Option<bool>,
+    format_on_save: Option<bool>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, local_history::LocalHistory>,
+) -> Result<SaveOutcome, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let mut outcome = SaveOutcome::default();
+    // A formatter that fails or hangs must not lose the save.
+    let content = if format_on_save.unwrap_or(false) {
+        match formatter::format_text(window.clone(), path.clone(), content.clone(), None).await {
+            Ok(formatted) if formatted != content => {
+                outcome.formatted = Some(formatted.clone());
+                formatted
+            }
+            Ok(_) => content,
+            Err(e) => {
+                outcome.format_error = Some(e);
+                content
+            }
+        }
+    } else {
+        content
+    };
+    let rules = editorconfig::apply_on_save(&editorconfig::resolve(&path), &content);
+    let content = if rules != content {
+        outcome.formatted = Some(rules.clone());
+        rules
+    } else {
+        content
+    };
+    let content = match eol {
+        Some(eol) => line_endings::convert(&content, eol)?,
+        None => content,
+    };
+    let bytes = match encoding {
+        Some(label) => encoding::encode(&content, &label, bom.unwrap_or(false))?,
+        None if bom.unwrap_or(false) => encoding::encode(&content, "utf-8", true)?,
+        None => content.into_bytes(),
+    };
+    let previous = if keep_history.unwrap_or(true) {
+        fs::read(&path).ok()
+    } else {
+        None
+    };
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+    if let Some(previous) = previous {
+        // History is best effort; the save itself already succeeded.
+        let _ = history.record(&app, &path, &previous, local_history::RevisionSource::Save);
+    }
+    Ok(outcome)
+}
+
+#[tauri::command]
+async fn read_file(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<encoding::DecodedText, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(encoding::decode(&bytes))
 }
 
 #[tauri::command]
-async fn list_directory(path: String) -> Result<Vec<String>, String> {
-    let entries = fs::read_dir(&path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-    
+async fn list_directory(
+    path: String,
+    include_ignored: Option<bool>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<DirEntryInfo>, String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    let entries = fs::read_dir(&path).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let matcher = if include_ignored.unwrap_or(false) {
+        None
+    } else {
+        Some(exclude::matcher_for(&windows, &window, &path)?)
+    };
+
     let mut files = Vec::new();
     for entry in entries {
         if let Ok(entry) = entry {
+            let entry_path = entry.path();
+            let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+            let symlink_target = if is_symlink {
+                fs::canonicalize(&entry_path).ok()
+            } else {
+                None
+            };
+            let is_dir = entry_path.is_dir();
+            if let Some(matcher) = &matcher {
+                if matcher.is_excluded(&entry_path, is_dir) {
+                    continue;
+                }
+            }
             if let Ok(file_name) = entry.file_name().into_string() {
-                files.push(file_name);
+                files.push(DirEntryInfo {
+                    name: file_name,
+                    path: entry_path.to_string_lossy().to_string(),
+                    is_dir,
+                    is_symlink,
+                    symlink_target: symlink_target.map(|t| t.to_string_lossy().to_string()),
+                });
             }
         }
     }
-    
+
     Ok(files)
 }
 
 #[tauri::command]
-async fn create_file(path: String, name: String) -> Result<(), String> {
+async fn create_file(
+    path: String,
+    name: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
     let full_path = Path::new(&path).join(&name);
-    fs::write(&full_path, "")
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let full_path = workspace::authorize(&windows, &window, &full_path.to_string_lossy())?;
+    fs::write(&full_path, "").map_err(|e| format!("Failed to create file: {}", e))?;
     Ok(())
 }
 
 #[tauri::command]
-async fn create_directory(path: String, name: String) -> Result<(), String> {
+async fn create_directory(
+    path: String,
+    name: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
     let full_path = Path::new(&path).join(&name);
-    fs::create_dir(&full_path)
-        .map_err(|e| format!("Failed to create directory: {}", e))?;
+    let full_path = workspace::authorize(&windows, &window, &full_path.to_string_lossy())?;
+    fs::create_dir(&full_path).map_err(|e| format!("Failed to create directory: {}", e))?;
     Ok(())
 }
 
 #[tauri::command]
-async fn delete_file(path: String) -> Result<(), String> {
-    fs::remove_file(&path)
-        .map_err(|e| format!("Failed to delete file: {}", e))?;
+async fn delete_file(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let path = workspace::authorize_entry(&windows, &window, &path)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete file: {}", e))?;
     Ok(())
 }
 
 #[tauri::command]
-async fn delete_directory(path: String) -> Result<(), String> {
-    fs::remove_dir_all(&path)
-        .map_err(|e| format!("Failed to delete directory: {}", e))?;
+async fn delete_directory(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let path = workspace::authorize_entry(&windows, &window, &path)?;
+    fs::remove_dir_all(&path).map_err(|e| format!("Failed to delete directory: {}", e))?;
     Ok(())
 }
 
-#[tauri::command]
-async fn run_command(command: String, args: Vec<String>, cwd: Option<String>) -> Result<String, String> {
-    use std::process::Command;
-    
-    let mut cmd = Command::new(&command);
-    cmd.args(&args);
-    
-    if let Some(working_dir) = cwd {
-        cmd.current_dir(working_dir);
-    }
-    
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
-    
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
-}
-
-fn get_language_from_extension(path: &Path) -> String {
-    match path.extension().and_then(|ext| ext.to_str()) {
-        Some("rs") => "rust".to_string(),
-        Some("py") => "python".to_string(),
-        Some("js") => "javascript".to_string(),
-        Some("ts") => "typescript".to_string(),
-        Some("tsx") => "typescript".to_string(),
-        Some("jsx") => "javascript".to_string(),
-        Some("cs") => "csharp".to_string(),
-        Some("java") => "java".to_string(),
-        Some("cpp") => "cpp".to_string(),
-        Some("c") => "c".to_string(),
-        Some("go") => "go".to_string(),
-        Some("php") => "php".to_string(),
-        Some("rb") => "ruby".to_string(),
-        Some("sql") => "sql".to_string(),
-        Some("html") => "html".to_string(),
-        Some("css") => "css".to_string(),
-        Some("scss") => "scss".to_string(),
-        Some("json") => "json".to_string(),
-        Some("xml") => "xml".to_string(),
-        Some("yaml") | Some("yml") => "yaml".to_string(),
-        Some("md") => "markdown".to_string(),
-        _ => "plaintext".to_string(),
-    }
-}
-
 fn main() {
+    // When started by git as its askpass helper, answer and exit here.
+    git::askpass::run_helper_if_requested();
+
     tauri::Builder::default()
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::forward_launch(app, args, cwd);
+        }))
+        .manage(WindowRegistry::default())
+        .manage(recent::RecentStore::default())
+        .manage(activity::ActivityTracker::default())
+        .manage(local_history::LocalHistory::default())
+        .manage(trust::TrustStore::default())
+        .manage(settings::SettingsStore::default())
+        .manage(command_registry::CommandRegistry::default())
+        .manage(extensions::ExtensionRegistry::default())
+        .manage(ai::sessions::ChatSessions::default())
+        .manage(ai::policy::ProviderLimits::default())
+        .manage(ai::audit::AuditLog::default())
+        .manage(window_manager::PendingOpens::default())
+        .manage(deep_link::PendingLinks::default())
+        .manage(vault::WorkspaceVaults::default())
+        .setup(|app| {
+            // Detection falls back to the built-in mappings if this fails.
+            let _ = language::load_user_mappings(&app.handle());
+            // The keychain may prompt for access; keep it off the startup path.
+            let handle = app.handle();
+            std::thread::spawn(move || {
+                let _ = secrets::migrate_plaintext(&handle);
+            });
+            std::thread::spawn(|| {
+                let _ = deep_link::register_scheme();
+            });
+            launch::open_startup_args(&app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             open_file_dialog,
+            save_file_dialog,
             save_file,
             read_file,
             list_directory,
@@ -170,8 +401,257 @@ fn main() {
             create_directory,
             delete_file,
             delete_directory,
-            run_command,
+            process::run_command,
+            documents::document_open,
+            documents::document_get,
+            documents::document_change,
+            documents::document_save,
+            documents::document_close,
+            documents::document_list,
+            documents::search_open_documents,
+            large_file::open_large_file,
+            large_file::read_file_range,
+            large_file::close_large_file,
+            documents::apply_edits,
+            workspace::add_workspace_root,
+            workspace::remove_workspace_root,
+            workspace::list_workspace_roots,
+            workspace::request_path_access,
+            exclude::get_exclusion_globs,
+            exclude::set_exclusion_globs,
+            language::detect_file_language,
+            metadata::stat_path,
+            text_health::audit_text_health,
+            text_health::apply_text_health_fixes,
+            fs_ops::batch_fs_operation,
+            fs_ops::cancel_batch_operation,
+            preflight::preflight_check,
+            preflight::preflight_fs_ops,
+            file_index::get_index_status,
+            workspace::open_folder_dialog,
+            path_resolve::resolve_workspace_path,
+            preview::start_preview_server,
+            preview::stop_preview_server,
+            preview::list_preview_servers,
+            code_image::render_code_image,
+            code_image::list_code_image_themes,
+            recent::record_recent,
+            recent::get_recent_files,
+            recent::get_recent_projects,
+            recent::clear_recent,
+            hashing::hash_file,
+            activity::set_activity_tracking,
+            activity::get_activity_tracking,
+            activity::record_activity,
+            activity::get_activity_summary,
+            activity::clear_activity_data,
+            line_endings::convert_line_endings,
+            metadata::resolve_symlink,
+            fs_ops::duplicate_path,
+            system_open::reveal_in_file_manager,
+            system_open::open_with_system,
+            hot_exit::stash_unsaved_buffer,
+            hot_exit::discard_unsaved_buffer,
+            hot_exit::recover_unsaved_buffers,
+            local_history::list_local_history,
+            local_history::read_local_history,
+            local_history::diff_local_history,
+            local_history::restore_local_history,
+            search::search_workspace,
+            search::cancel_search,
+            replace::replace_in_workspace,
+            replace::apply_workspace_replace,
+            file_index::fuzzy_find_files,
+            git::status::git_status,
+            git::diff::git_diff_file,
+            git::index::git_stage,
+            git::index::git_unstage,
+            git::index::git_commit,
+            git::index::git_discard,
+            git::branches::git_branches,
+            git::branches::git_create_branch,
+            git::branches::git_checkout,
+            git::branches::git_delete_branch,
+            git::log::git_log,
+            git::blame::git_blame,
+            git::remote::git_push,
+            git::remote::git_pull,
+            git::remote::git_fetch,
+            git::remote::cancel_git_operation,
+            git::remote::git_credential_response,
+            git::stash::git_stash_save,
+            git::stash::git_stash_list,
+            git::stash::git_stash_apply,
+            git::stash::git_stash_pop,
+            git::stash::git_stash_drop,
+            git::clone::git_clone,
+            git::graph::git_graph,
+            git::tags::git_tags,
+            git::tags::git_create_tag,
+            git::tags::git_delete_tag,
+            git::tags::git_describe,
+            git::worktree::git_worktree_list,
+            git::worktree::git_worktree_add,
+            git::worktree::git_worktree_remove,
+            git::submodule::git_submodule_update,
+            process::spawn_command,
+            process::cancel_command,
+            process::list_processes,
+            process::kill_process,
+            tasks::list_tasks,
+            tasks::run_task,
+            dev_server::start_dev_server,
+            dev_server::get_server_url,
+            dev_server::list_dev_servers,
+            dev_server::restart_dev_server,
+            dev_server::stop_dev_server,
+            trust::get_workspace_trust,
+            trust::request_workspace_trust,
+            trust::revoke_workspace_trust,
+            trust::get_execution_policy,
+            trust::set_execution_policy,
+            lsp::lsp_start,
+            lsp::lsp_send,
+            lsp::lsp_stop,
+            toolchain::ensure_language_server,
+            diagnostics::get_diagnostics,
+            diagnostics::publish_lint_diagnostics,
+            formatter::format_document,
+            linter::run_linter,
+            workspace_edit::apply_workspace_edit,
+            rename::rename_symbol_textual,
+            editorconfig::get_editor_config,
+            syntax::get_document_outline,
+            syntax::get_folding_ranges,
+            highlight::tokenize_range,
+            language::get_language_registry,
+            language::add_language_mapping,
+            language::remove_language_mapping,
+            compare::compute_diff,
+            compare::compare_files,
+            autosave::get_autosave_settings,
+            autosave::set_autosave_settings,
+            settings::get_settings_schema,
+            settings::get_settings,
+            settings::get_setting,
+            settings::set_setting,
+            command_registry::list_commands,
+            command_registry::register_commands,
+            command_registry::unregister_commands,
+            command_registry::get_keybindings,
+            command_registry::set_keybinding,
+            command_registry::reset_keybinding,
+            themes::list_themes,
+            themes::get_theme,
+            snippets::list_snippets,
+            snippets::save_snippet,
+            snippets::delete_snippet,
+            extensions::list_extensions,
+            extensions::install_extension,
+            extensions::uninstall_extension,
+            extensions::enable_extension,
+            extensions::disable_extension,
+            extensions::activate_extensions,
+            extensions::execute_extension_command,
+            extensions::dispatch_extension_event,
+            ai::ai_chat,
+            ai::ai_cancel,
+            ai::local::ai_list_local_models,
+            ai::local::ai_local_health,
+            ai::local::ai_pull_model,
+            secrets::set_secret,
+            secrets::get_secret,
+            secrets::delete_secret,
+            semantic::start_semantic_index,
+            semantic::get_semantic_index_status,
+            semantic::semantic_search,
+            ai::context::assemble_context,
+            ai::edit::preview_ai_edit,
+            ai::edit::apply_ai_edit,
+            ai::sessions::create_chat_session,
+            ai::sessions::list_chat_sessions,
+            ai::sessions::load_chat_session,
+            ai::sessions::rename_chat_session,
+            ai::sessions::export_chat_session,
+            ai::sessions::delete_chat_session,
+            ai::inline::ai_inline_completion,
+            ai::inline::get_inline_completion_stats,
+            ai::commit::ai_generate_commit_message,
+            ai::explain::ai_explain_diagnostic,
+            ai::explain::ai_explain_terminal_output,
+            ai::tokens::count_tokens,
+            ai::models::ai_list_models,
+            ai::templates::list_prompt_templates,
+            ai::templates::save_prompt_template,
+            ai::templates::delete_prompt_template,
+            ai::templates::run_prompt_template,
+            ai::audit::get_ai_usage_stats,
+            session::save_session,
+            session::restore_session,
+            window_manager::open_window,
+            window_manager::take_pending_open,
+            window_manager::list_windows,
+            window_manager::focus_window,
+            scaffold::list_project_templates,
+            scaffold::create_project,
+            project::detect_project,
+            dotenv::list_env_profiles,
+            dotenv::get_env_profile,
+            vault::set_workspace_secret,
+            vault::get_workspace_secret,
+            vault::list_workspace_secrets,
+            vault::delete_workspace_secret,
+            vscode_import::import_vscode_config,
+            debug::list_launch_configs,
+            debug::save_launch_config,
+            debug::delete_launch_config,
+            debug::start_debug,
+            debug::debug_send,
+            debug::stop_debug,
+            breakpoints::list_breakpoints,
+            breakpoints::set_breakpoint,
+            breakpoints::remove_breakpoint,
+            breakpoints::clear_breakpoints,
+            breakpoints::breakpoint_arguments,
+            attach::list_debuggable_processes,
+            attach::attach_debug,
+            watches::list_watches,
+            watches::add_watch,
+            watches::update_watch,
+            watches::remove_watch,
+            watches::evaluate_watches,
+            watches::debug_evaluate,
+            test_explorer::discover_tests,
+            test_explorer::run_tests,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::Focused(false) = event.event() {
+                let window = event.window();
+                let windows = window.state::<WindowRegistry>();
+                autosave::on_blur(window, &windows.scope(window.label()));
+            }
+            if let tauri::WindowEvent::Destroyed = event.event() {
+                let window = event.window();
+                let windows = window.state::<WindowRegistry>();
+                // Keep unsaved edits for the next launch before the
+                // window's documents are dropped.
+                let scope = windows.scope(window.label());
+                hot_exit::stash_dirty_documents(&window.app_handle(), &scope);
+                windows.teardown(window.label());
+                window
+                    .state::<window_manager::PendingOpens>()
+                    .take(window.label());
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            // macOS hands over `codeai:` links as an event, not arguments.
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                for url in urls {
+                    deep_link::receive(_app, url.as_str());
+                }
+            }
+        });
 }
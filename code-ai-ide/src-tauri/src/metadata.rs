@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Files above this size should be opened through the large-file mode.
+pub const LARGE_FILE_THRESHOLD: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PathStat {
+    pub path: String,
+    pub size: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+    /// Milliseconds since the Unix epoch; `None` where the platform or
+    /// filesystem does not record the timestamp.
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    pub readonly: bool,
+    /// Unix permission string such as `rw-r--r--`.
+    pub permissions: Option<String>,
+    pub owner: Option<String>,
+    pub is_large: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SymlinkResolution {
+    pub path: String,
+    pub is_symlink: bool,
+    /// The link's own target as stored on disk, possibly relative.
+    pub link_target: Option<String>,
+    /// Fully resolved path after following every link in the chain;
+    /// `None` when the chain is dangling or loops.
+    pub resolved: Option<String>,
+    pub is_dir: bool,
+    /// Whether the resolved path is inside a workspace root or grant, i.e.
+    /// whether the IDE may open it.
+    pub accessible: bool,
+}
+
+pub fn stat(path: &Path) -> Result<PathStat, String> {
+    let link_metadata =
+        fs::symlink_metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    let symlink_target = if is_symlink {
+        fs::read_link(path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
+
+    // Report what the link points at; a dangling link falls back to the
+    // link's own metadata.
+    let metadata = fs::metadata(path).unwrap_or(link_metadata);
+
+    Ok(PathStat {
+        path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        is_file: metadata.is_file(),
+        is_dir: metadata.is_dir(),
+        is_symlink,
+        symlink_target,
+        created: metadata.created().ok().and_then(to_millis),
+        modified: metadata.modified().ok().and_then(to_millis),
+        accessed: metadata.accessed().ok().and_then(to_millis),
+        readonly: metadata.permissions().readonly(),
+        permissions: permission_string(&metadata),
+        owner: owner_name(&metadata),
+        is_large: metadata.is_file() && metadata.len() > LARGE_FILE_THRESHOLD,
+    })
+}
+
+pub fn to_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+#[cfg(unix)]
+fn permission_string(metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    let flags = ['r', 'w', 'x'];
+    let text = (0..9)
+        .map(|bit| {
+            if mode & (1 << (8 - bit)) != 0 {
+                flags[bit % 3]
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    Some(text)
+}
+
+#[cfg(not(unix))]
+fn permission_string(_metadata: &fs::Metadata) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn owner_name(metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = metadata.uid();
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let name = passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let entry_uid = fields.nth(1)?.parse::<u32>().ok()?;
+        (entry_uid == uid).then(|| name.to_string())
+    });
+    Some(name.unwrap_or_else(|| uid.to_string()))
+}
+
+#[cfg(not(unix))]
+fn owner_name(_metadata: &fs::Metadata) -> Option<String> {
+    None
+}
+
+#[tauri::command]
+pub async fn resolve_symlink(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<SymlinkResolution, String> {
+    // Authorize where the link lives, not where it points; pointing outside
+    // the workspace is exactly what the caller wants to find out.
+    let requested = Path::new(&path);
+    let name = requested
+        .file_name()
+        .ok_or_else(|| format!("Not a file path: {}", path))?;
+    let parent = match requested.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let link_path = workspace::authorize(&windows, &window, &parent.to_string_lossy())?.join(name);
+    let link_path = link_path.as_path();
+    let link_metadata =
+        fs::symlink_metadata(link_path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    let link_target = if is_symlink {
+        fs::read_link(link_path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
+
+    // canonicalize fails with ELOOP on cyclic chains and ENOENT on dangling
+    // ones; both are reported as unresolved rather than as errors.
+    let resolved = fs::canonicalize(link_path).ok();
+    let accessible = resolved.as_ref().is_some_and(|resolved| {
+        workspace::authorize(&windows, &window, &resolved.to_string_lossy()).is_ok()
+    });
+
+    Ok(SymlinkResolution {
+        path,
+        is_symlink,
+        link_target,
+        is_dir: resolved.as_ref().is_some_and(|r| r.is_dir()),
+        resolved: resolved.map(|r| r.to_string_lossy().to_string()),
+        accessible,
+    })
+}
+
+#[tauri::command]
+pub async fn stat_path(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<PathStat, String> {
+    // Keep the path as given so a symlink is reported as a link rather
+    // than as its already-resolved target.
+    workspace::authorize(&windows, &window, &path)?;
+    stat(Path::new(&path))
+}
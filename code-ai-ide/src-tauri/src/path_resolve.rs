@@ -0,0 +1,338 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Suffixes tried when an import omits its extension, in resolution order.
+const PROBE_SUFFIXES: &[&str] = &[
+    "",
+    ".ts",
+    ".tsx",
+    ".d.ts",
+    ".js",
+    ".jsx",
+    ".mjs",
+    ".cjs",
+    ".json",
+    ".py",
+    ".rs",
+    "/index.ts",
+    "/index.tsx",
+    "/index.js",
+    "/index.jsx",
+    "/__init__.py",
+    "/mod.rs",
+];
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionKind {
+    Absolute,
+    Relative,
+    PathAlias,
+    BaseUrl,
+    CargoMember,
+    WorkspaceRoot,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedPath {
+    pub path: String,
+    pub kind: ResolutionKind,
+}
+
+fn probe(candidate: &Path) -> Option<PathBuf> {
+    let base = candidate.to_string_lossy();
+    PROBE_SUFFIXES
+        .iter()
+        .map(|suffix| PathBuf::from(format!("{}{}", base, suffix)))
+        .find(|path| path.is_file())
+}
+
+/// Finds the nearest file named one of `names` between `start` and `root`.
+fn find_upwards(start: &Path, root: &Path, names: &[&str]) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        for name in names {
+            let candidate = dir.join(name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        if dir == root {
+            break;
+        }
+    }
+    None
+}
+
+/// tsconfig.json allows comments and trailing commas, which serde_json does
+/// not; strip both before parsing.
+fn parse_jsonc(text: &str) -> Option<serde_json::Value> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                out.extend(chars.next());
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.peek().map_or(false, |c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    let mut cleaned = String::with_capacity(out.len());
+    let chars: Vec<char> = out.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == ',' {
+            let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        cleaned.push(*c);
+    }
+    serde_json::from_str(&cleaned).ok()
+}
+
+fn resolve_ts_paths(input: &str, base_dir: &Path, root: &Path) -> Option<ResolvedPath> {
+    let config_path = find_upwards(base_dir, root, &["tsconfig.json", "jsconfig.json"])?;
+    let config = parse_jsonc(&fs::read_to_string(&config_path).ok()?)?;
+    let config_dir = config_path.parent()?;
+    let options = config.get("compilerOptions")?;
+    let base_url = config_dir.join(
+        options
+            .get("baseUrl")
+            .and_then(|v| v.as_str())
+            .unwrap_or("."),
+    );
+
+    if let Some(paths) = options.get("paths").and_then(|v| v.as_object()) {
+        for (pattern, targets) in paths {
+            let captured = match pattern.split_once('*') {
+                Some((prefix, suffix)) => input
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_suffix(suffix)),
+                None if pattern == input => Some(""),
+                None => None,
+            };
+            let captured = match captured {
+                Some(captured) => captured,
+                None => continue,
+            };
+            for target in targets
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t.as_str())
+            {
+                if let Some(path) = probe(&base_url.join(target.replace('*', captured))) {
+                    return Some(ResolvedPath {
+                        path: path.to_string_lossy().to_string(),
+                        kind: ResolutionKind::PathAlias,
+                    });
+                }
+            }
+        }
+    }
+
+    if options.get("baseUrl").is_some() {
+        return probe(&base_url.join(input)).map(|path| ResolvedPath {
+            path: path.to_string_lossy().to_string(),
+            kind: ResolutionKind::BaseUrl,
+        });
+    }
+    None
+}
+
+/// Cargo workspace `members` entries, with trailing `/*` globs expanded.
+fn cargo_members(manifest: &Path) -> Vec<PathBuf> {
+    let text = match fs::read_to_string(manifest) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+    let value: toml::Value = match text.parse() {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let members = value
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut dirs = Vec::new();
+    for member in members.iter().filter_map(|m| m.as_str()) {
+        match member.strip_suffix("/*") {
+            Some(parent) => {
+                if let Ok(entries) = fs::read_dir(dir.join(parent)) {
+                    dirs.extend(
+                        entries
+                            .flatten()
+                            .map(|e| e.path())
+                            .filter(|p| p.join("Cargo.toml").is_file()),
+                    );
+                }
+            }
+            None => dirs.push(dir.join(member)),
+        }
+    }
+    dirs
+}
+
+fn package_name(member: &Path) -> Option<String> {
+    let text = fs::read_to_string(member.join("Cargo.toml")).ok()?;
+    let value: toml::Value = text.parse().ok()?;
+    Some(value.get("package")?.get("name")?.as_str()?.to_string())
+}
+
+/// Resolves `crate_name` or `crate_name::path::to::module` to a file inside
+/// a workspace member.
+fn resolve_cargo_member(input: &str, base_dir: &Path, root: &Path) -> Option<ResolvedPath> {
+    let mut segments = input.split("::");
+    let crate_name = segments.next()?.replace('-', "_");
+    let modules: Vec<&str> = segments.collect();
+
+    let mut search_from = base_dir.to_path_buf();
+    while let Some(manifest) = find_upwards(&search_from, root, &["Cargo.toml"]) {
+        for member in cargo_members(&manifest) {
+            let name = match package_name(&member) {
+                Some(name) => name.replace('-', "_"),
+                None => continue,
+            };
+            if name != crate_name {
+                continue;
+            }
+
+            let src = member.join("src");
+            let target = if modules.is_empty() {
+                ["lib.rs", "main.rs"]
+                    .iter()
+                    .map(|f| src.join(f))
+                    .find(|p| p.is_file())
+            } else {
+                probe(&src.join(modules.join("/")))
+            };
+            let path = target.unwrap_or_else(|| member.join("Cargo.toml"));
+            return Some(ResolvedPath {
+                path: path.to_string_lossy().to_string(),
+                kind: ResolutionKind::CargoMember,
+            });
+        }
+
+        let parent = manifest.parent()?.parent()?;
+        if !parent.starts_with(root) {
+            break;
+        }
+        search_from = parent.to_path_buf();
+    }
+    None
+}
+
+pub fn resolve(input: &str, base_file: Option<&Path>, root: &Path) -> Option<ResolvedPath> {
+    let input = input
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`');
+    if input.is_empty() {
+        return None;
+    }
+
+    let as_path = Path::new(input);
+    if as_path.is_absolute() {
+        return probe(as_path).map(|path| ResolvedPath {
+            path: path.to_string_lossy().to_string(),
+            kind: ResolutionKind::Absolute,
+        });
+    }
+
+    let base_dir = base_file
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| root.to_path_buf());
+
+    if input.starts_with("./") || input.starts_with("../") {
+        return probe(&base_dir.join(input)).map(|path| ResolvedPath {
+            path: path.to_string_lossy().to_string(),
+            kind: ResolutionKind::Relative,
+        });
+    }
+
+    if let Some(resolved) = resolve_ts_paths(input, &base_dir, root) {
+        return Some(resolved);
+    }
+    if let Some(resolved) = resolve_cargo_member(input, &base_dir, root) {
+        return Some(resolved);
+    }
+
+    // Bare paths such as `src/main.rs` in terminal output or markdown links.
+    probe(&base_dir.join(input))
+        .map(|path| ResolvedPath {
+            path: path.to_string_lossy().to_string(),
+            kind: ResolutionKind::Relative,
+        })
+        .or_else(|| {
+            probe(&root.join(input)).map(|path| ResolvedPath {
+                path: path.to_string_lossy().to_string(),
+                kind: ResolutionKind::WorkspaceRoot,
+            })
+        })
+}
+
+#[tauri::command]
+pub async fn resolve_workspace_path(
+    input: String,
+    base_file: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<ResolvedPath>, String> {
+    let scope = windows.scope(window.label());
+    let base_file = match base_file {
+        Some(base_file) => Some(workspace::authorize(&windows, &window, &base_file)?),
+        None => None,
+    };
+    let root = base_file
+        .as_ref()
+        .and_then(|file| {
+            scope
+                .workspace
+                .roots()
+                .into_iter()
+                .find(|root| file.starts_with(root))
+        })
+        .or_else(|| scope.workspace.active())
+        .or_else(|| scope.workspace.roots().into_iter().next())
+        .ok_or_else(|| "No workspace is open".to_string())?;
+
+    // Only hand back paths the window is allowed to open.
+    Ok(resolve(&input, base_file.as_deref(), &root)
+        .filter(|resolved| scope.workspace.check(Path::new(&resolved.path)).is_ok()))
+}
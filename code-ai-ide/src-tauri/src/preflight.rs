@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{State, Window};
+
+use crate::fs_ops::FsOp;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Classic Win32 MAX_PATH; paths at or beyond it fail in many tools even
+/// when long-path support is enabled in the OS.
+const WINDOWS_MAX_PATH: usize = 260;
+const MAX_COMPONENT_BYTES: usize = 255;
+/// Headroom kept free so the disk is not filled to the last byte.
+const SPACE_MARGIN_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct PreflightRequest {
+    /// Directory that will receive the output.
+    pub destination: String,
+    /// Sources whose total size will be written to the destination.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Explicit size for operations whose output is not on disk yet
+    /// (clones, downloads, archive extraction).
+    pub required_bytes: Option<u64>,
+    /// Paths the operation will create, checked against length limits.
+    #[serde(default)]
+    pub target_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PreflightProblem {
+    InsufficientSpace {
+        required: u64,
+        available: u64,
+    },
+    NotWritable {
+        path: String,
+        reason: String,
+    },
+    PathTooLong {
+        path: String,
+        length: usize,
+        limit: usize,
+    },
+    ComponentTooLong {
+        path: String,
+        component: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreflightReport {
+    pub ok: bool,
+    pub required_bytes: u64,
+    pub available_bytes: Option<u64>,
+    pub problems: Vec<PreflightProblem>,
+}
+
+pub fn total_size(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| total_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Nearest existing directory at or above `path`; space and permission
+/// checks are made there since the destination may not exist yet.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.is_dir()).map(Path::to_path_buf)
+}
+
+fn check_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(format!(".codeai-preflight-{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"").map_err(|e| e.to_string())?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+fn check_path_length(path: &str, problems: &mut Vec<PreflightProblem>) {
+    if cfg!(windows) && path.chars().count() >= WINDOWS_MAX_PATH {
+        problems.push(PreflightProblem::PathTooLong {
+            path: path.to_string(),
+            length: path.chars().count(),
+            limit: WINDOWS_MAX_PATH,
+        });
+    }
+    for component in Path::new(path).components() {
+        let component = component.as_os_str().to_string_lossy();
+        if component.len() > MAX_COMPONENT_BYTES {
+            problems.push(PreflightProblem::ComponentTooLong {
+                path: path.to_string(),
+                component: component.to_string(),
+            });
+        }
+    }
+}
+
+pub fn run_preflight(
+    destination: &Path,
+    required_bytes: u64,
+    target_paths: &[String],
+) -> PreflightReport {
+    let mut problems = Vec::new();
+    let mut available_bytes = None;
+
+    match existing_ancestor(destination) {
+        Some(dir) => {
+            if let Err(reason) = check_writable(&dir) {
+                problems.push(PreflightProblem::NotWritable {
+                    path: dir.to_string_lossy().to_string(),
+                    reason,
+                });
+            }
+            if let Ok(available) = fs2::available_space(&dir) {
+                available_bytes = Some(available);
+                if required_bytes.saturating_add(SPACE_MARGIN_BYTES) > available {
+                    problems.push(PreflightProblem::InsufficientSpace {
+                        required: required_bytes,
+                        available,
+                    });
+                }
+            }
+        }
+        None => problems.push(PreflightProblem::NotWritable {
+            path: destination.to_string_lossy().to_string(),
+            reason: "No existing parent directory".to_string(),
+        }),
+    }
+
+    check_path_length(&destination.to_string_lossy(), &mut problems);
+    for path in target_paths {
+        check_path_length(path, &mut problems);
+    }
+
+    PreflightReport {
+        ok: problems.is_empty(),
+        required_bytes,
+        available_bytes,
+        problems,
+    }
+}
+
+#[tauri::command]
+pub async fn preflight_check(
+    request: PreflightRequest,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<PreflightReport, String> {
+    let destination = workspace::authorize(&windows, &window, &request.destination)?;
+    let mut sources = Vec::new();
+    for source in &request.sources {
+        sources.push(workspace::authorize(&windows, &window, source)?);
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let required = request
+            .required_bytes
+            .unwrap_or_else(|| sources.iter().map(|s| total_size(s)).sum());
+        run_preflight(&destination, required, &request.target_paths)
+    })
+    .await
+    .map_err(|e| format!("Preflight check failed: {}", e))
+}
+
+/// Preflight for a `batch_fs_operation` payload: copies need space at their
+/// destinations, and every created path is length-checked.
+#[tauri::command]
+pub async fn preflight_fs_ops(
+    ops: Vec<FsOp>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<PreflightReport, String> {
+    let mut copies = Vec::new();
+    let mut targets = Vec::new();
+    for op in &ops {
+        match op {
+            FsOp::Copy { from, to } => {
+                copies.push(workspace::authorize(&windows, &window, from)?);
+                targets.push(to.clone());
+            }
+            FsOp::Move { to, .. } => targets.push(to.clone()),
+            FsOp::CreateDir { path } => targets.push(path.clone()),
+            FsOp::Delete { .. } => {}
+        }
+    }
+
+    let destination = match targets.first() {
+        Some(target) => workspace::authorize(&windows, &window, target)?,
+        None => {
+            return Ok(PreflightReport {
+                ok: true,
+                required_bytes: 0,
+                available_bytes: None,
+                problems: Vec::new(),
+            })
+        }
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let required = copies.iter().map(|s| total_size(s)).sum();
+        let parent = destination.parent().unwrap_or(&destination).to_path_buf();
+        run_preflight(&parent, required, &targets)
+    })
+    .await
+    .map_err(|e| format!("Preflight check failed: {}", e))
+}
@@ -0,0 +1,384 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{State, Window};
+
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const DEFAULT_PORT: u16 = 5500;
+const RELOAD_PATH: &str = "/__codeai_reload";
+const RELOAD_SCRIPT: &str =
+    "<script>new EventSource('/__codeai_reload').onmessage=function(){location.reload()}</script>";
+
+/// Bumped whenever a file under the served root changes; live-reload
+/// connections wait on the condvar and push an event to the page.
+type ReloadSignal = Arc<(Mutex<u64>, Condvar)>;
+
+struct PreviewServer {
+    root: PathBuf,
+    spa_fallback: bool,
+    stop: Arc<AtomicBool>,
+    reload: ReloadSignal,
+    watch_subscription: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct PreviewServers {
+    servers: Mutex<HashMap<u16, PreviewServer>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewInfo {
+    pub url: String,
+    pub port: u16,
+    pub root: String,
+    pub spa_fallback: bool,
+}
+
+impl PreviewServer {
+    fn info(&self, port: u16) -> PreviewInfo {
+        PreviewInfo {
+            url: format!("http://127.0.0.1:{}/", port),
+            port,
+            root: self.root.to_string_lossy().to_string(),
+            spa_fallback: self.spa_fallback,
+        }
+    }
+
+    fn shutdown(&self, port: u16) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop and any live-reload connections.
+        let _ = TcpStream::connect(("127.0.0.1", port));
+        self.reload.1.notify_all();
+    }
+}
+
+impl PreviewServers {
+    pub fn stop_all(&self) {
+        for (port, server) in self.servers.lock().unwrap().drain() {
+            server.shutdown(port);
+        }
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("txt") | Some("md") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn percent_decode(input: &str) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Maps a URL path onto a file under `root`, refusing anything that would
+/// escape it, through `..` or through a symlink pointing outside.
+fn resolve_request(root: &Path, url_path: &str, spa_fallback: bool) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path.split(['?', '#']).next().unwrap_or("/"));
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let root = fs::canonicalize(root).ok()?;
+    let inside = |path: PathBuf| {
+        fs::canonicalize(path)
+            .ok()
+            .filter(|resolved| resolved.starts_with(&root) && resolved.is_file())
+    };
+
+    let mut candidate = root.join(relative);
+    if candidate.is_dir() {
+        candidate = candidate.join("index.html");
+    }
+    if candidate.is_file() {
+        return inside(candidate);
+    }
+
+    // Client-side routes like /settings/profile have no extension; serve the
+    // app shell and let the router handle them.
+    if spa_fallback && relative.extension().is_none() {
+        return inside(root.join("index.html"));
+    }
+    None
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    let _ = stream.write_all(header.as_bytes());
+    let _ = stream.write_all(body);
+}
+
+fn serve_reload_stream(mut stream: TcpStream, reload: ReloadSignal, stop: Arc<AtomicBool>) {
+    let header = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\n";
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+
+    let (lock, condvar) = &*reload;
+    let mut seen = *lock.lock().unwrap();
+    loop {
+        let guard = lock.lock().unwrap();
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, Duration::from_secs(15), |generation| {
+                *generation == seen && !stop.load(Ordering::SeqCst)
+            })
+            .unwrap();
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+        // A timeout sends a comment line as keep-alive, which also detects
+        // closed tabs.
+        let message = if *guard != seen {
+            "data: reload\n\n"
+        } else {
+            ": ping\n\n"
+        };
+        seen = *guard;
+        drop(guard);
+        if stream.write_all(message.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    spa_fallback: bool,
+    reload: ReloadSignal,
+    stop: Arc<AtomicBool>,
+) {
+    let mut request_line = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        // Drain headers; nothing in them changes how files are served.
+        let mut line = String::new();
+        while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+            line.clear();
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let url_path = parts.next().unwrap_or("/");
+
+    if method != "GET" && method != "HEAD" {
+        write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed",
+        );
+        return;
+    }
+    if url_path == RELOAD_PATH {
+        serve_reload_stream(stream, reload, stop);
+        return;
+    }
+
+    match resolve_request(root, url_path, spa_fallback) {
+        Some(file) => match fs::read(&file) {
+            Ok(mut body) => {
+                let content_type = content_type(&file);
+                if content_type.starts_with("text/html") {
+                    body = inject_reload_script(body);
+                }
+                if method == "HEAD" {
+                    body.clear();
+                }
+                write_response(&mut stream, "200 OK", content_type, &body);
+            }
+            Err(_) => write_response(
+                &mut stream,
+                "500 Internal Server Error",
+                "text/plain",
+                b"Failed to read file",
+            ),
+        },
+        None => write_response(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+fn inject_reload_script(body: Vec<u8>) -> Vec<u8> {
+    let html = String::from_utf8_lossy(&body);
+    let injected = match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], RELOAD_SCRIPT, &html[index..]),
+        None => format!("{}{}", html, RELOAD_SCRIPT),
+    };
+    injected.into_bytes()
+}
+
+fn bind(port: Option<u16>) -> Result<TcpListener, String> {
+    match port {
+        Some(port) => TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to bind preview server to port {}: {}", port, e)),
+        // Try a small range from the default before letting the OS pick.
+        None => (DEFAULT_PORT..DEFAULT_PORT + 20)
+            .find_map(|port| TcpListener::bind(("127.0.0.1", port)).ok())
+            .map(Ok)
+            .unwrap_or_else(|| {
+                TcpListener::bind(("127.0.0.1", 0))
+                    .map_err(|e| format!("Failed to start preview server: {}", e))
+            }),
+    }
+}
+
+#[tauri::command]
+pub async fn start_preview_server(
+    root: String,
+    port: Option<u16>,
+    spa_fallback: Option<bool>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<PreviewInfo, String> {
+    let root = workspace::authorize(&windows, &window, &root)?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    let spa_fallback = spa_fallback.unwrap_or(true);
+    let listener = bind(port)?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to start preview server: {}", e))?
+        .port();
+
+    let scope = windows.scope(window.label());
+    let stop = Arc::new(AtomicBool::new(false));
+    let reload: ReloadSignal = Arc::new((Mutex::new(0), Condvar::new()));
+
+    let watched_root = root.clone();
+    let signal = reload.clone();
+    let watch_subscription = scope.watcher.subscribe(Box::new(move |change| {
+        if change.paths.iter().any(|p| p.starts_with(&watched_root)) {
+            *signal.0.lock().unwrap() += 1;
+            signal.1.notify_all();
+        }
+    }));
+
+    {
+        let root = root.clone();
+        let stop = stop.clone();
+        let reload = reload.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let root = root.clone();
+                    let reload = reload.clone();
+                    let stop = stop.clone();
+                    thread::spawn(move || {
+                        handle_connection(stream, &root, spa_fallback, reload, stop)
+                    });
+                }
+            }
+        });
+    }
+
+    let server = PreviewServer {
+        root,
+        spa_fallback,
+        stop,
+        reload,
+        watch_subscription: Some(watch_subscription),
+    };
+    let info = server.info(port);
+    scope
+        .preview_servers
+        .servers
+        .lock()
+        .unwrap()
+        .insert(port, server);
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_preview_server(
+    port: u16,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let scope = windows.scope(window.label());
+    let server = scope.preview_servers.servers.lock().unwrap().remove(&port);
+    match server {
+        Some(server) => {
+            server.shutdown(port);
+            if let Some(id) = server.watch_subscription {
+                scope.watcher.unsubscribe(id);
+            }
+            Ok(())
+        }
+        None => Err(format!("No preview server is running on port {}", port)),
+    }
+}
+
+#[tauri::command]
+pub async fn list_preview_servers(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<PreviewInfo>, String> {
+    let scope = windows.scope(window.label());
+    let servers = scope.preview_servers.servers.lock().unwrap();
+    let mut list: Vec<_> = servers
+        .iter()
+        .map(|(port, server)| server.info(*port))
+        .collect();
+    list.sort_by_key(|info| info.port);
+    Ok(list)
+}
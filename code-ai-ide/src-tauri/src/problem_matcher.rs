@@ -0,0 +1,275 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::process::OutputStream;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Set by the diagnostic store when handing diagnostics out; stays the
+    /// same while the problem is reported unchanged.
+    #[serde(default)]
+    pub id: String,
+    pub path: String,
+    /// One-based, as tools print them; 0 when only the file is known.
+    pub line: u32,
+    pub column: u32,
+    /// Where the range ends, for tools that report one (language servers).
+    #[serde(default)]
+    pub end_line: Option<u32>,
+    #[serde(default)]
+    pub end_column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    /// Tool-specific code such as `E0308` or `TS2322`.
+    pub code: Option<String>,
+    /// The tool the diagnostic came from, e.g. `rustc` or `eslint`.
+    pub source: String,
+    /// Fixes the tool suggests, ready to apply.
+    #[serde(default)]
+    pub fixes: Vec<Fix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<FixEdit>,
+}
+
+/// Replaces the range with `text`. Positions are one-based, with columns
+/// counted the way the tool counts them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixEdit {
+    pub path: String,
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub text: String,
+}
+
+struct Patterns {
+    ansi: Regex,
+    rust_header: Regex,
+    rust_location: Regex,
+    tsc: Regex,
+    tsc_pretty: Regex,
+    eslint_file: Regex,
+    eslint_entry: Regex,
+    eslint_compact: Regex,
+    pytest: Regex,
+    gcc: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let re = |pattern: &str| Regex::new(pattern).expect("valid regex");
+        Patterns {
+            ansi: re(r"\x1b\[[0-9;]*[A-Za-z]"),
+            // error[E0308]: mismatched types
+            rust_header: re(r"^(error|warning)(?:\[(\w+)\])?: (.+)$"),
+            //   --> src/main.rs:4:5
+            rust_location: re(r"^\s*--> (.+?):(\d+):(\d+)$"),
+            // src/app.ts(12,5): error TS2322: Type ...
+            tsc: re(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+): (.+)$"),
+            // src/app.ts:12:5 - error TS2322: Type ...
+            tsc_pretty: re(r"^(.+?):(\d+):(\d+) - (error|warning) (TS\d+): (.+)$"),
+            // /abs/path/file.js  (stylish formatter heading)
+            eslint_file: re(r"^(/|[A-Za-z]:\\)\S.*\.[cm]?[jt]sx?$"),
+            //   12:5  error  'x' is defined but never used  no-unused-vars
+            eslint_entry: re(r"^\s+(\d+):(\d+)\s+(error|warning)\s+(.+?)(?:\s{2,}(\S+))?$"),
+            // file.js: line 12, col 5, Error - message (rule)
+            eslint_compact: re(
+                r"^(.+?): line (\d+), col (\d+), (Error|Warning) - (.+?)(?: \((\S+)\))?$",
+            ),
+            // tests/test_app.py:12: AssertionError
+            pytest: re(r"^(.+?\.py):(\d+): (\w*(?:Error|Exception|Failed)\w*)(.*)$"),
+            // main.c:12:5: error: ... (gcc, clang, go vet)
+            gcc: re(r"^(.+?):(\d+):(\d+): (fatal error|error|warning|note): (.+)$"),
+        }
+    })
+}
+
+fn severity(word: &str) -> Severity {
+    match word.to_ascii_lowercase().as_str() {
+        "error" | "fatal error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => Severity::Info,
+    }
+}
+
+/// Turns the output of build tools (cargo/rustc, tsc, eslint, pytest and
+/// gcc-style compilers) into diagnostics, line by line as it streams in.
+pub struct ProblemMatcher {
+    cwd: PathBuf,
+    partial: [String; 2],
+    /// A rustc message waiting for its `-->` location line.
+    rust_pending: Option<(Severity, Option<String>, String)>,
+    /// The file the current block of eslint's stylish output is about.
+    eslint_file: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ProblemMatcher {
+    /// Relative paths in the output are resolved against `cwd`.
+    pub fn new(cwd: &Path) -> ProblemMatcher {
+        ProblemMatcher {
+            cwd: cwd.to_path_buf(),
+            partial: [String::new(), String::new()],
+            rust_pending: None,
+            eslint_file: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, stream: OutputStream, chunk: &str) {
+        let slot = match stream {
+            OutputStream::Stdout => 0,
+            OutputStream::Stderr => 1,
+        };
+        self.partial[slot].push_str(chunk);
+        while let Some(end) = self.partial[slot].find('\n') {
+            let line: String = self.partial[slot].drain(..=end).collect();
+            self.match_line(&line);
+        }
+    }
+
+    /// Flushes unterminated lines and hands over everything matched.
+    pub fn finish(&mut self) -> Vec<Diagnostic> {
+        for slot in 0..2 {
+            let line = std::mem::take(&mut self.partial[slot]);
+            if !line.is_empty() {
+                self.match_line(&line);
+            }
+        }
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn path(&self, path: &str) -> String {
+        self.cwd.join(path.trim()).to_string_lossy().to_string()
+    }
+
+    /// `location` is (path, line, column) as captured.
+    fn push(
+        &mut self,
+        (path, line, column): (&str, &str, &str),
+        severity: Severity,
+        message: &str,
+        code: Option<&str>,
+        source: &str,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            id: String::new(),
+            path: self.path(path),
+            line: line.parse().unwrap_or(0),
+            column: column.parse().unwrap_or(0),
+            end_line: None,
+            end_column: None,
+            severity,
+            message: message.trim().to_string(),
+            code: code.map(str::to_string),
+            source: source.to_string(),
+            fixes: Vec::new(),
+        });
+    }
+
+    fn match_line(&mut self, raw: &str) {
+        let p = patterns();
+        let line = p.ansi.replace_all(raw.trim_end_matches(['\r', '\n']), "");
+        let line = line.as_ref();
+
+        if let Some(c) = p.rust_header.captures(line) {
+            // Summaries like "warning: 3 warnings emitted" never get a
+            // location and are dropped when the next header arrives.
+            self.rust_pending = Some((
+                severity(&c[1]),
+                c.get(2).map(|m| m.as_str().to_string()),
+                c[3].to_string(),
+            ));
+            return;
+        }
+        if let Some(c) = p.rust_location.captures(line) {
+            if let Some((severity, code, message)) = self.rust_pending.take() {
+                self.push(
+                    (&c[1], &c[2], &c[3]),
+                    severity,
+                    &message,
+                    code.as_deref(),
+                    "rustc",
+                );
+            }
+            return;
+        }
+        if let Some(c) = p.tsc.captures(line).or_else(|| p.tsc_pretty.captures(line)) {
+            self.push(
+                (&c[1], &c[2], &c[3]),
+                severity(&c[4]),
+                &c[6],
+                Some(&c[5]),
+                "tsc",
+            );
+            return;
+        }
+        if let Some(c) = p.eslint_compact.captures(line) {
+            let code = c.get(6).map(|m| m.as_str());
+            self.push(
+                (&c[1], &c[2], &c[3]),
+                severity(&c[4]),
+                &c[5],
+                code,
+                "eslint",
+            );
+            return;
+        }
+        if p.eslint_file.is_match(line) {
+            self.eslint_file = Some(line.trim().to_string());
+            return;
+        }
+        if let Some(file) = self.eslint_file.clone() {
+            if let Some(c) = p.eslint_entry.captures(line) {
+                let code = c.get(5).map(|m| m.as_str());
+                self.push(
+                    (&file, &c[1], &c[2]),
+                    severity(&c[3]),
+                    &c[4],
+                    code,
+                    "eslint",
+                );
+                return;
+            }
+            if line.trim().is_empty() {
+                self.eslint_file = None;
+            }
+        }
+        if let Some(c) = p.gcc.captures(line) {
+            self.push(
+                (&c[1], &c[2], &c[3]),
+                severity(&c[4]),
+                &c[5],
+                None,
+                "compiler",
+            );
+            return;
+        }
+        if let Some(c) = p.pytest.captures(line) {
+            let message = format!("{}{}", &c[3], &c[4]);
+            self.push(
+                (&c[1], &c[2], "0"),
+                Severity::Error,
+                &message,
+                None,
+                "pytest",
+            );
+        }
+    }
+}
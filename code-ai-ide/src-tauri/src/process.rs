@@ -0,0 +1,705 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Manager, State, Window};
+
+use crate::dotenv;
+use crate::metadata;
+use crate::trust;
+use crate::vault;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CHUNK_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Emitted as `command-output` for each chunk a process writes.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutput {
+    pub id: String,
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+/// Emitted as `command-exit` once the process is gone and its output has
+/// been delivered.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandExit {
+    pub id: String,
+    /// `None` when the process was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub cancelled: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandResult {
+    pub stdout: String,
+    pub stderr: String,
+    /// Both streams interleaved in the order the output arrived, as a
+    /// terminal would show them.
+    pub combined: String,
+    /// `None` when the process was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+}
+
+/// How to run a command beyond its arguments; everything is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExecOptions {
+    /// Added to (or overriding) the inherited environment.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Run `command` through the shell (`sh -c` / `cmd /C`), so pipes, `&&`
+    /// and globs work. `args` are appended quoted.
+    #[serde(default)]
+    pub use_shell: bool,
+    /// Written to the process's stdin, which is then closed.
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Loads this env profile from the workspace's `.env` files underneath
+    /// `env`; see `dotenv::profile_env`.
+    #[serde(default)]
+    pub env_profile: Option<String>,
+    /// Sets the workspace's vault secrets after the env profile and before
+    /// `env`; see `vault::secrets_for`. Only tasks set this, never the
+    /// webview.
+    #[serde(skip)]
+    pub workspace_secrets: bool,
+}
+
+/// What a process was started for, so the process list can group them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessKind {
+    Command,
+    Task,
+    DevServer,
+    LanguageServer,
+    Debugger,
+    Test,
+    Terminal,
+    Extension,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessSignal {
+    /// Ctrl+C; lets dev servers shut down cleanly.
+    Interrupt,
+    #[default]
+    Terminate,
+    Kill,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub pid: u32,
+    pub kind: ProcessKind,
+    /// Command line as started, for display.
+    pub label: String,
+    pub cwd: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: Option<u64>,
+}
+
+pub type OutputHook = Arc<Mutex<dyn FnMut(OutputStream, &str) + Send>>;
+
+/// Callbacks for whoever started a process through `start`.
+#[derive(Default)]
+pub struct ProcessHooks {
+    /// Sees every output chunk, from the reader threads.
+    pub on_output: Option<OutputHook>,
+    /// Runs after `command-exit` has been emitted.
+    pub on_exit: Option<Box<dyn FnOnce(&CommandExit) + Send>>,
+}
+
+struct RunningProcess {
+    info: ProcessInfo,
+    child: Arc<Mutex<Child>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Every child process started from a window (commands, tasks, dev
+/// servers, language servers, debug adapters, test runs), keyed by id. All
+/// of them are killed when the window closes.
+#[derive(Default)]
+pub struct ProcessRegistry {
+    running: Mutex<HashMap<String, RunningProcess>>,
+}
+
+impl ProcessRegistry {
+    fn register(
+        &self,
+        kind: ProcessKind,
+        label: String,
+        cwd: Option<String>,
+        child: Child,
+    ) -> (String, Arc<Mutex<Child>>, Arc<AtomicBool>) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let info = ProcessInfo {
+            id: id.clone(),
+            pid: child.id(),
+            kind,
+            label,
+            cwd,
+            started_at: metadata::to_millis(SystemTime::now()),
+        };
+        let child = Arc::new(Mutex::new(child));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running.lock().unwrap().insert(
+            id.clone(),
+            RunningProcess {
+                info,
+                child: child.clone(),
+                cancelled: cancelled.clone(),
+            },
+        );
+        (id, child, cancelled)
+    }
+
+    fn unregister(&self, id: &str) {
+        self.running.lock().unwrap().remove(id);
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        self.running.lock().unwrap().contains_key(id)
+    }
+
+    pub fn list(&self) -> Vec<ProcessInfo> {
+        let mut processes: Vec<ProcessInfo> = self
+            .running
+            .lock()
+            .unwrap()
+            .values()
+            .map(|process| process.info.clone())
+            .collect();
+        processes.sort_by_key(|info| info.started_at);
+        processes
+    }
+
+    /// Signals the process and everything it started. The exit is still
+    /// reported through the usual event, marked as cancelled.
+    pub fn signal(&self, id: &str, signal: ProcessSignal) -> bool {
+        match self.running.lock().unwrap().get(id) {
+            Some(process) => {
+                process.cancelled.store(true, Ordering::SeqCst);
+                signal_tree(&mut process.child.lock().unwrap(), signal);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        self.signal(id, ProcessSignal::Kill)
+    }
+
+    pub fn cancel_all(&self) {
+        let ids: Vec<String> = self.running.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            self.cancel(&id);
+        }
+    }
+}
+
+/// Signals the process together with whatever it started (`npm run`
+/// spawns node, `cargo` spawns rustc), which a plain `kill` would leave
+/// running.
+fn signal_tree(child: &mut Child, signal: ProcessSignal) {
+    #[cfg(unix)]
+    {
+        let name = match signal {
+            ProcessSignal::Interrupt => "-INT",
+            ProcessSignal::Terminate => "-TERM",
+            ProcessSignal::Kill => "-KILL",
+        };
+        // The child leads its own process group; see `prepare`.
+        let _ = Command::new("kill")
+            .args([name, &format!("-{}", child.id())])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    #[cfg(windows)]
+    {
+        // Windows has no signals; without /F the processes are asked to
+        // close.
+        let mut args = vec!["/T".to_string(), "/PID".to_string(), child.id().to_string()];
+        if signal == ProcessSignal::Kill {
+            args.push("/F".to_string());
+        }
+        let _ = Command::new("taskkill")
+            .args(&args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    if signal == ProcessSignal::Kill {
+        let _ = child.kill();
+    }
+}
+
+/// Waits for the process without holding its lock, so it can be signalled
+/// meanwhile, and kills it once `deadline` passes. Returns the exit status
+/// and whether it timed out.
+fn wait_polling(child: &Mutex<Child>, deadline: Option<Instant>) -> (Option<ExitStatus>, bool) {
+    loop {
+        if let Some(status) = child.lock().unwrap().try_wait().ok().flatten() {
+            return (Some(status), false);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let mut child = child.lock().unwrap();
+            signal_tree(&mut child, ProcessSignal::Kill);
+            return (child.wait().ok(), true);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Reads a pipe to the end in chunks cut at UTF-8 boundaries, so a
+/// multi-byte character is never split between two chunks.
+fn pump(mut pipe: impl Read, mut on_chunk: impl FnMut(String)) {
+    let mut buffer = [0u8; CHUNK_BYTES];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        let read = match pipe.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+        let complete = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            // An incomplete sequence at the end waits for the next read;
+            // invalid bytes are passed on (and replaced) as they are.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        if complete == 0 {
+            continue;
+        }
+        on_chunk(String::from_utf8_lossy(&pending[..complete]).into_owned());
+        pending.drain(..complete);
+    }
+    if !pending.is_empty() {
+        on_chunk(String::from_utf8_lossy(&pending).into_owned());
+    }
+}
+
+/// Forwards a pipe as `command-output` events, showing each chunk to the
+/// output hook first.
+fn forward(
+    window: Window,
+    id: String,
+    stream: OutputStream,
+    pipe: impl Read,
+    on_output: Option<OutputHook>,
+) {
+    pump(pipe, |data| {
+        if let Some(hook) = &on_output {
+            (hook.lock().unwrap())(stream, &data);
+        }
+        let _ = window.emit(
+            "command-output",
+            CommandOutput {
+                id: id.clone(),
+                stream,
+                data,
+            },
+        );
+    });
+}
+
+/// PATH as the user's login shell sets it up. Apps started from the Dock or
+/// a desktop launcher get a minimal PATH without Homebrew, nvm, cargo and
+/// friends; asked once and cached.
+#[cfg(unix)]
+pub fn login_path() -> Option<&'static str> {
+    use std::sync::OnceLock;
+
+    static LOGIN_PATH: OnceLock<Option<String>> = OnceLock::new();
+    LOGIN_PATH
+        .get_or_init(|| {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+            // Markers keep whatever the profile prints out of the value.
+            let output = Command::new(shell)
+                .args(["-l", "-c", "printf '\\n__PATH__%s__PATH__' \"$PATH\""])
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            let text = String::from_utf8_lossy(&output.stdout);
+            let start = text.find("__PATH__")? + "__PATH__".len();
+            let end = start + text[start..].find("__PATH__")?;
+            Some(text[start..end].to_string()).filter(|path| !path.is_empty())
+        })
+        .as_deref()
+}
+
+#[cfg(not(unix))]
+pub fn login_path() -> Option<&'static str> {
+    None
+}
+
+#[cfg(unix)]
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(windows)]
+fn shell_quote(arg: &str) -> String {
+    if arg.contains([' ', '\t', '"', '&', '|', '<', '>', '^']) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Builds the command with piped output, resolving `cwd` against the
+/// window's workspace. Only `cwd` is sandboxed; paths in `command` and
+/// `args` are not checked, and running them at all is gated by
+/// `trust::check_execution` alone.
+pub fn prepare(
+    command: &str,
+    args: &[String],
+    cwd: Option<String>,
+    options: &ExecOptions,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<Command, String> {
+    let working_dir = cwd
+        .map(|working_dir| workspace::authorize(windows, window, &working_dir))
+        .transpose()?;
+    let line = options.use_shell.then(|| {
+        std::iter::once(command.to_string())
+            .chain(args.iter().map(|arg| shell_quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    });
+    trust::check_execution(
+        window,
+        windows,
+        line.as_deref().unwrap_or(command),
+        options.use_shell,
+        working_dir.as_deref(),
+    )?;
+
+    let mut cmd = if let Some(line) = line {
+        let mut cmd = if cfg!(windows) {
+            Command::new("cmd")
+        } else {
+            Command::new("sh")
+        };
+        cmd.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(line);
+        cmd
+    } else {
+        let mut cmd = Command::new(command);
+        cmd.args(args);
+        cmd
+    };
+
+    if let Some(path) = login_path() {
+        cmd.env("PATH", path);
+    }
+    if let Some(profile) = &options.env_profile {
+        cmd.envs(dotenv::profile_env(
+            windows,
+            window,
+            working_dir.as_deref(),
+            profile,
+        )?);
+    }
+    if options.workspace_secrets {
+        let root = windows
+            .scope(window.label())
+            .workspace
+            .root_for(working_dir.as_deref());
+        if let Some(root) = root {
+            cmd.envs(vault::secrets_for(&window.app_handle(), &root)?);
+        }
+    }
+    cmd.envs(&options.env)
+        .stdin(if options.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(working_dir) = working_dir {
+        cmd.current_dir(working_dir);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    Ok(cmd)
+}
+
+fn describe(cmd: &Command) -> (String, Option<String>) {
+    let label = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let cwd = cmd
+        .get_current_dir()
+        .map(|dir| dir.to_string_lossy().to_string());
+    (label, cwd)
+}
+
+/// Writes the requested input on its own thread, so a process that fills
+/// its output pipe before reading stdin cannot deadlock us, then closes it.
+fn feed_stdin(pipe: Option<ChildStdin>, input: Option<String>) {
+    if let (Some(mut pipe), Some(input)) = (pipe, input) {
+        thread::spawn(move || {
+            let _ = pipe.write_all(input.as_bytes());
+        });
+    }
+}
+
+/// Spawns `cmd` (built by `prepare`), registers it under `kind` and streams
+/// its output as `command-output` events followed by `command-exit`, calling
+/// the hooks along the way. Returns the process id right away.
+pub fn start(
+    window: Window,
+    scope: Arc<WindowState>,
+    cmd: Command,
+    kind: ProcessKind,
+    stdin: Option<String>,
+    timeout: Option<Duration>,
+    hooks: ProcessHooks,
+) -> Result<String, String> {
+    let (id, pipe, _) = launch(window, scope, cmd, kind, timeout, hooks, false)?;
+    feed_stdin(pipe, stdin);
+    Ok(id)
+}
+
+/// Like `start`, for processes that talk a protocol over stdio (language
+/// servers): stdin and stdout are handed to the caller instead of being fed
+/// and streamed as events. Stderr and the exit are still reported as usual.
+pub fn start_attached(
+    window: Window,
+    scope: Arc<WindowState>,
+    mut cmd: Command,
+    kind: ProcessKind,
+    hooks: ProcessHooks,
+) -> Result<(String, ChildStdin, ChildStdout), String> {
+    cmd.stdin(Stdio::piped());
+    let (id, stdin, stdout) = launch(window, scope, cmd, kind, None, hooks, true)?;
+    match (stdin, stdout) {
+        (Some(stdin), Some(stdout)) => Ok((id, stdin, stdout)),
+        _ => Err("Failed to open the process's stdio".to_string()),
+    }
+}
+
+fn launch(
+    window: Window,
+    scope: Arc<WindowState>,
+    mut cmd: Command,
+    kind: ProcessKind,
+    timeout: Option<Duration>,
+    hooks: ProcessHooks,
+    attach: bool,
+) -> Result<(String, Option<ChildStdin>, Option<ChildStdout>), String> {
+    let (label, cwd) = describe(&cmd);
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    let stdin = child.stdin.take();
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let (attached, stdout) = if attach {
+        (stdout, None)
+    } else {
+        (None, stdout)
+    };
+    let (id, child, cancelled) = scope.processes.register(kind, label, cwd, child);
+
+    let readers = [
+        stdout.map(|pipe| {
+            let (window, id, hook) = (window.clone(), id.clone(), hooks.on_output.clone());
+            thread::spawn(move || forward(window, id, OutputStream::Stdout, pipe, hook))
+        }),
+        stderr.map(|pipe| {
+            let (window, id, hook) = (window.clone(), id.clone(), hooks.on_output.clone());
+            thread::spawn(move || forward(window, id, OutputStream::Stderr, pipe, hook))
+        }),
+    ];
+
+    let deadline = timeout.map(|timeout| started + timeout);
+    let process_id = id.clone();
+    let on_exit = hooks.on_exit;
+    thread::spawn(move || {
+        let (status, timed_out) = wait_polling(&child, deadline);
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+        scope.processes.unregister(&process_id);
+
+        let exit = CommandExit {
+            id: process_id,
+            exit_code: status.and_then(|status| status.code()),
+            cancelled: cancelled.load(Ordering::SeqCst),
+            timed_out,
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        let _ = window.emit("command-exit", exit.clone());
+        if let Some(on_exit) = on_exit {
+            on_exit(&exit);
+        }
+    });
+
+    Ok((id, stdin, attached))
+}
+
+/// Starts a process and returns its id right away. Output streams in as
+/// `command-output` events and the result as `command-exit`. With
+/// `timeout_ms`, the process is killed once it runs longer than that.
+#[tauri::command]
+pub async fn spawn_command(
+    command: String,
+    args: Vec<String>,
+    cwd: Option<String>,
+    timeout_ms: Option<u64>,
+    options: Option<ExecOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let cmd = prepare(&command, &args, cwd, &options, &window, &windows)?;
+    let scope = windows.scope(window.label());
+    start(
+        window,
+        scope,
+        cmd,
+        ProcessKind::Command,
+        options.stdin,
+        timeout_ms.map(Duration::from_millis),
+        ProcessHooks::default(),
+    )
+}
+
+/// Runs `cmd` (built by `prepare`) to completion on the calling thread,
+/// registered under `kind` so it can be killed meanwhile. With `timeout`,
+/// the process is killed once it runs longer than that.
+pub fn run_blocking(
+    scope: &WindowState,
+    mut cmd: Command,
+    kind: ProcessKind,
+    stdin: Option<String>,
+    timeout: Option<Duration>,
+) -> Result<CommandResult, String> {
+    let (label, cwd) = describe(&cmd);
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    feed_stdin(child.stdin.take(), stdin);
+    let (child_stdout, child_stderr) = (child.stdout.take(), child.stderr.take());
+
+    let combined = Arc::new(Mutex::new(String::new()));
+    let capture = |pipe: Option<Box<dyn Read + Send>>| {
+        let combined = combined.clone();
+        thread::spawn(move || {
+            let mut own = String::new();
+            if let Some(pipe) = pipe {
+                pump(pipe, |chunk| {
+                    combined.lock().unwrap().push_str(&chunk);
+                    own.push_str(&chunk);
+                });
+            }
+            own
+        })
+    };
+    let stdout = capture(child_stdout.map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let stderr = capture(child_stderr.map(|p| Box::new(p) as Box<dyn Read + Send>));
+
+    let (id, child, _) = scope.processes.register(kind, label, cwd, child);
+    let (status, timed_out) = wait_polling(&child, timeout.map(|timeout| started + timeout));
+    scope.processes.unregister(&id);
+    let status = status.ok_or_else(|| "Failed to wait for command".to_string())?;
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    let combined = combined.lock().unwrap().clone();
+    Ok(CommandResult {
+        stdout,
+        stderr,
+        combined,
+        exit_code: status.code(),
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Runs a process to completion. A non-zero exit is not an error: tools
+/// like cargo and tsc report diagnostics on stderr either way, so callers
+/// get both streams and the exit code. Errors only when the process cannot
+/// be started.
+#[tauri::command]
+pub async fn run_command(
+    command: String,
+    args: Vec<String>,
+    cwd: Option<String>,
+    options: Option<ExecOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<CommandResult, String> {
+    let options = options.unwrap_or_default();
+    let cmd = prepare(&command, &args, cwd, &options, &window, &windows)?;
+    let scope = windows.scope(window.label());
+
+    tauri::async_runtime::spawn_blocking(move || {
+        run_blocking(&scope, cmd, ProcessKind::Command, options.stdin, None)
+    })
+    .await
+    .map_err(|e| format!("Command failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn list_processes(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<ProcessInfo>, String> {
+    Ok(windows.scope(window.label()).processes.list())
+}
+
+/// Sends `signal` (default: terminate) to a process and its children.
+#[tauri::command]
+pub async fn kill_process(
+    id: String,
+    signal: Option<ProcessSignal>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows
+        .scope(window.label())
+        .processes
+        .signal(&id, signal.unwrap_or_default()))
+}
+
+/// Kills a process started with `spawn_command`, including its children.
+#[tauri::command]
+pub async fn cancel_command(
+    id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows.scope(window.label()).processes.cancel(&id))
+}
@@ -0,0 +1,381 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use tauri::{AppHandle, State, Window};
+
+use crate::lsp;
+use crate::process;
+use crate::tasks;
+use crate::toolchain;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectKind {
+    Cargo,
+    Node,
+    Python,
+    Go,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedProject {
+    pub kind: ProjectKind,
+    /// The manifest it was detected from, relative to the root.
+    pub manifest: String,
+    pub name: Option<String>,
+    pub package_manager: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub path: Option<String>,
+    /// The version it reports, when it is on PATH.
+    pub version: Option<String>,
+    /// What the project asks for, such as `engines.node` or the `go`
+    /// directive.
+    pub required: Option<String>,
+    /// How to get the tool, when it is missing.
+    pub install_hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageServerStatus {
+    pub language: String,
+    pub command: String,
+    pub available: bool,
+    /// `ensure_language_server` can install it.
+    pub installable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    pub root: String,
+    pub projects: Vec<DetectedProject>,
+    pub languages: Vec<String>,
+    pub package_managers: Vec<String>,
+    pub tools: Vec<ToolStatus>,
+    pub missing_tools: Vec<String>,
+    pub language_servers: Vec<LanguageServerStatus>,
+}
+
+/// What a manifest says the project needs.
+struct Needs {
+    project: DetectedProject,
+    languages: Vec<&'static str>,
+    /// Tool names with the version the project requires, if it says.
+    tools: Vec<(String, Option<String>)>,
+}
+
+fn install_hint(tool: &str) -> Option<&'static str> {
+    Some(match tool {
+        "cargo" | "rustc" => "Install Rust with rustup: https://rustup.rs",
+        "node" | "npm" => "Install Node.js, which includes npm: https://nodejs.org",
+        "pnpm" => "npm install -g pnpm, or corepack enable",
+        "yarn" => "npm install -g yarn, or corepack enable",
+        "bun" => "Install Bun: https://bun.sh",
+        "python" | "python3" => "Install Python: https://www.python.org/downloads/",
+        "pip" => "python -m ensurepip --upgrade",
+        "poetry" => "pipx install poetry",
+        "pdm" => "pipx install pdm",
+        "hatch" => "pipx install hatch",
+        "uv" => "Install uv: https://docs.astral.sh/uv/getting-started/installation/",
+        "go" => "Install Go: https://go.dev/dl/",
+        _ => return None,
+    })
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+fn toml_str<'a>(value: &'a toml::Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .try_fold(value, |value, key| value.get(*key))?
+        .as_str()
+}
+
+fn cargo_needs(root: &Path) -> Option<Needs> {
+    let manifest = read_toml(&root.join("Cargo.toml"))?;
+    let rust_version = toml_str(&manifest, &["package", "rust-version"])
+        .or_else(|| toml_str(&manifest, &["workspace", "package", "rust-version"]))
+        .map(str::to_string);
+    Some(Needs {
+        project: DetectedProject {
+            kind: ProjectKind::Cargo,
+            manifest: "Cargo.toml".to_string(),
+            name: toml_str(&manifest, &["package", "name"]).map(str::to_string),
+            package_manager: "cargo".to_string(),
+        },
+        languages: vec!["rust"],
+        tools: vec![
+            ("cargo".to_string(), None),
+            ("rustc".to_string(), rust_version),
+        ],
+    })
+}
+
+fn node_needs(root: &Path) -> Option<Needs> {
+    let text = fs::read_to_string(root.join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&text).ok()?;
+    // `packageManager` (as Corepack reads it) wins over lockfiles.
+    let manager = manifest["packageManager"]
+        .as_str()
+        .and_then(|spec| spec.split('@').next())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| tasks::package_manager(root).to_string());
+    let has_dependency = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| manifest[section].get(name).is_some())
+    };
+    let mut languages = vec!["javascript"];
+    if root.join("tsconfig.json").is_file() || has_dependency("typescript") {
+        languages.push("typescript");
+    }
+    let mut tools = Vec::new();
+    // Bun runs the scripts itself.
+    if manager != "bun" {
+        tools.push((
+            "node".to_string(),
+            manifest["engines"]["node"].as_str().map(str::to_string),
+        ));
+    }
+    tools.push((
+        manager.clone(),
+        manifest["engines"][manager.as_str()]
+            .as_str()
+            .map(str::to_string),
+    ));
+    Some(Needs {
+        project: DetectedProject {
+            kind: ProjectKind::Node,
+            manifest: "package.json".to_string(),
+            name: manifest["name"].as_str().map(str::to_string),
+            package_manager: manager,
+        },
+        languages,
+        tools,
+    })
+}
+
+fn python_needs(root: &Path) -> Option<Needs> {
+    let (manifest_name, manifest) = match read_toml(&root.join("pyproject.toml")) {
+        Some(manifest) => ("pyproject.toml", Some(manifest)),
+        None if root.join("setup.py").is_file() => ("setup.py", None),
+        None if root.join("requirements.txt").is_file() => ("requirements.txt", None),
+        None => return None,
+    };
+    let has_tool = |name: &str| {
+        manifest.as_ref().is_some_and(|manifest| {
+            manifest
+                .get("tool")
+                .and_then(|tool| tool.get(name))
+                .is_some()
+        })
+    };
+    let manager = if root.join("uv.lock").is_file() || has_tool("uv") {
+        "uv"
+    } else if root.join("poetry.lock").is_file() || has_tool("poetry") {
+        "poetry"
+    } else if root.join("pdm.lock").is_file() || has_tool("pdm") {
+        "pdm"
+    } else if has_tool("hatch") {
+        "hatch"
+    } else {
+        "pip"
+    };
+    let requires_python = manifest
+        .as_ref()
+        .and_then(|manifest| toml_str(manifest, &["project", "requires-python"]))
+        .map(str::to_string);
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    Some(Needs {
+        project: DetectedProject {
+            kind: ProjectKind::Python,
+            manifest: manifest_name.to_string(),
+            name: manifest
+                .as_ref()
+                .and_then(|manifest| {
+                    toml_str(manifest, &["project", "name"])
+                        .or_else(|| toml_str(manifest, &["tool", "poetry", "name"]))
+                })
+                .map(str::to_string),
+            package_manager: manager.to_string(),
+        },
+        languages: vec!["python"],
+        tools: vec![
+            (python.to_string(), requires_python),
+            (manager.to_string(), None),
+        ],
+    })
+}
+
+fn go_needs(root: &Path) -> Option<Needs> {
+    let text = fs::read_to_string(root.join("go.mod")).ok()?;
+    let directive = |name: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(' '))
+            .map(|value| value.trim().to_string())
+    };
+    Some(Needs {
+        project: DetectedProject {
+            kind: ProjectKind::Go,
+            manifest: "go.mod".to_string(),
+            name: directive("module"),
+            package_manager: "go".to_string(),
+        },
+        languages: vec!["go"],
+        tools: vec![("go".to_string(), directive("go"))],
+    })
+}
+
+/// `major.minor[.patch]`, the first one in `output`.
+fn version_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\d+\.\d+(\.\d+)?").expect("valid regex"))
+}
+
+/// Asks `binary` for its version. Go is the odd one out with `go version`.
+fn tool_version(name: &str, binary: &Path) -> Option<String> {
+    let flag = if name == "go" { "version" } else { "--version" };
+    let mut command = Command::new(binary);
+    command
+        .arg(flag)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(path) = process::login_path() {
+        command.env("PATH", path);
+    }
+    let output = command.output().ok()?;
+    // Older Pythons print the version to stderr.
+    let text = [output.stdout, output.stderr]
+        .iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(version_pattern().find(&text)?.as_str().to_string())
+}
+
+fn tool_status(name: String, required: Option<String>) -> ToolStatus {
+    match toolchain::find_on_path(&name) {
+        Some(path) => ToolStatus {
+            version: tool_version(&name, &path),
+            path: Some(path.to_string_lossy().to_string()),
+            name,
+            required,
+            install_hint: None,
+        },
+        None => ToolStatus {
+            install_hint: install_hint(&name).map(str::to_string),
+            path: None,
+            version: None,
+            name,
+            required,
+        },
+    }
+}
+
+/// The projects in `root`, from their manifests alone.
+pub(crate) fn detect_projects(root: &Path) -> Vec<DetectedProject> {
+    [cargo_needs, node_needs, python_needs, go_needs]
+        .iter()
+        .filter_map(|detect| detect(root))
+        .map(|need| need.project)
+        .collect()
+}
+
+fn detect(app: &AppHandle, root: PathBuf) -> ProjectInfo {
+    let needs: Vec<Needs> = [cargo_needs, node_needs, python_needs, go_needs]
+        .iter()
+        .filter_map(|detect| detect(&root))
+        .collect();
+
+    let mut languages: Vec<String> = Vec::new();
+    let mut package_managers: Vec<String> = Vec::new();
+    let mut wanted: Vec<(String, Option<String>)> = Vec::new();
+    for need in &needs {
+        for language in &need.languages {
+            if !languages.iter().any(|known| known == language) {
+                languages.push(language.to_string());
+            }
+        }
+        if !package_managers.contains(&need.project.package_manager) {
+            package_managers.push(need.project.package_manager.clone());
+        }
+        for (tool, required) in &need.tools {
+            if !wanted.iter().any(|(known, _)| known == tool) {
+                wanted.push((tool.clone(), required.clone()));
+            }
+        }
+    }
+
+    let tools: Vec<ToolStatus> = wanted
+        .into_iter()
+        .map(|(name, required)| tool_status(name, required))
+        .collect();
+    let missing_tools = tools
+        .iter()
+        .filter(|tool| tool.path.is_none())
+        .map(|tool| tool.name.clone())
+        .collect();
+    let language_servers = languages
+        .iter()
+        .filter_map(|language| {
+            let server = lsp::default_server(language)?;
+            Some(LanguageServerStatus {
+                language: language.clone(),
+                available: toolchain::locate(app, language).is_some(),
+                installable: toolchain::can_install(&server.command),
+                command: server.command,
+            })
+        })
+        .collect();
+
+    ProjectInfo {
+        root: root.to_string_lossy().to_string(),
+        projects: needs.into_iter().map(|need| need.project).collect(),
+        languages,
+        package_managers,
+        tools,
+        missing_tools,
+        language_servers,
+    }
+}
+
+/// What kind of project `root` is, from its Cargo.toml, package.json,
+/// pyproject.toml (or setup.py, requirements.txt) and go.mod: languages,
+/// package managers, the tools they need with the versions found on PATH,
+/// install hints for missing ones, and whether each language's server is
+/// available. Defaults to the active workspace. Used to set up tasks and
+/// language servers for a freshly opened project.
+#[tauri::command]
+pub async fn detect_project(
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<ProjectInfo, String> {
+    let root = match root {
+        Some(root) => workspace::authorize(&windows, &window, &root)?,
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string())?,
+    };
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    // Asking every tool for its version takes a moment.
+    tauri::async_runtime::spawn_blocking(move || detect(&app, root))
+        .await
+        .map_err(|e| format!("Failed to detect project: {}", e))
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, State};
+
+use crate::app_data;
+use crate::metadata::to_millis;
+
+const STORE_FILE: &str = "recent.json";
+const MAX_RECENT_FILES: usize = 50;
+const MAX_RECENT_PROJECTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentKind {
+    File,
+    Project,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub path: String,
+    pub kind: RecentKind,
+    /// Milliseconds since the Unix epoch.
+    pub last_opened: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecentData {
+    files: Vec<RecentEntry>,
+    projects: Vec<RecentEntry>,
+}
+
+impl RecentData {
+    fn list_mut(&mut self, kind: RecentKind) -> &mut Vec<RecentEntry> {
+        match kind {
+            RecentKind::File => &mut self.files,
+            RecentKind::Project => &mut self.projects,
+        }
+    }
+}
+
+/// Recently opened files and projects, shared by every window and persisted
+/// as JSON in the app data directory.
+#[derive(Default)]
+pub struct RecentStore {
+    data: Mutex<Option<(PathBuf, RecentData)>>,
+}
+
+impl RecentStore {
+    /// Runs `f` against the loaded data; `f` reports whether it changed
+    /// anything so unchanged reads do not rewrite the file.
+    fn with_data<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut RecentData) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.data.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_data_path(app, STORE_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+
+    pub fn record(&self, app: &AppHandle, path: &Path, kind: RecentKind) -> Result<(), String> {
+        let entry = RecentEntry {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            last_opened: to_millis(SystemTime::now()).unwrap_or(0),
+        };
+        let cap = match kind {
+            RecentKind::File => MAX_RECENT_FILES,
+            RecentKind::Project => MAX_RECENT_PROJECTS,
+        };
+        self.with_data(app, |data| {
+            let list = data.list_mut(kind);
+            list.retain(|existing| existing.path != entry.path);
+            list.insert(0, entry);
+            list.truncate(cap);
+            ((), true)
+        })
+    }
+
+    /// Returns the entries of `kind`, dropping any whose path no longer exists.
+    pub fn list(&self, app: &AppHandle, kind: RecentKind) -> Result<Vec<RecentEntry>, String> {
+        self.with_data(app, |data| {
+            let list = data.list_mut(kind);
+            let before = list.len();
+            list.retain(|entry| Path::new(&entry.path).exists());
+            let pruned = list.len() != before;
+            (list.clone(), pruned)
+        })
+    }
+
+    pub fn clear(&self, app: &AppHandle, kind: Option<RecentKind>) -> Result<(), String> {
+        self.with_data(app, |data| {
+            match kind {
+                Some(kind) => data.list_mut(kind).clear(),
+                None => {
+                    data.files.clear();
+                    data.projects.clear();
+                }
+            }
+            ((), true)
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn record_recent(
+    path: String,
+    kind: RecentKind,
+    app: AppHandle,
+    recent: State<'_, RecentStore>,
+) -> Result<(), String> {
+    recent.record(&app, Path::new(&path), kind)
+}
+
+#[tauri::command]
+pub async fn get_recent_files(
+    app: AppHandle,
+    recent: State<'_, RecentStore>,
+) -> Result<Vec<RecentEntry>, String> {
+    recent.list(&app, RecentKind::File)
+}
+
+#[tauri::command]
+pub async fn get_recent_projects(
+    app: AppHandle,
+    recent: State<'_, RecentStore>,
+) -> Result<Vec<RecentEntry>, String> {
+    recent.list(&app, RecentKind::Project)
+}
+
+#[tauri::command]
+pub async fn clear_recent(
+    kind: Option<RecentKind>,
+    app: AppHandle,
+    recent: State<'_, RecentStore>,
+) -> Result<(), String> {
+    recent.clear(&app, kind)
+}
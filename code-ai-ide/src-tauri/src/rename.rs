@@ -0,0 +1,324 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{State, Window};
+
+use crate::documents::{Position, Range};
+use crate::encoding;
+use crate::exclude::{self, ExclusionMatcher};
+use crate::language;
+use crate::lsp;
+use crate::metadata::LARGE_FILE_THRESHOLD;
+use crate::text_health::is_probably_binary;
+use crate::walk::walk_files;
+use crate::window_state::{WindowRegistry, WindowState};
+use crate::workspace;
+
+/// A rename touching more places than this is almost certainly a common
+/// word; the preview stops there.
+const MAX_OCCURRENCES: usize = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameOccurrence {
+    pub range: Range,
+    /// The whole line, for the preview.
+    pub line_text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRename {
+    pub path: String,
+    /// Version of the open document the ranges refer to; `None` for files
+    /// read from disk.
+    pub version: Option<u64>,
+    pub occurrences: Vec<RenameOccurrence>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextualRename {
+    pub symbol: String,
+    pub new_name: String,
+    pub files: Vec<FileRename>,
+    pub occurrence_count: usize,
+    pub limit_hit: bool,
+    /// The same edits as an LSP `WorkspaceEdit`, ready for
+    /// `apply_workspace_edit` once the user accepts the preview.
+    pub workspace_edit: Value,
+}
+
+/// How comments and strings look, so occurrences inside them are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    /// C-like, with `'` for char literals and lifetimes rather than strings.
+    Rust,
+    /// `//` and `/* */` comments, `"`, `'` and `` ` `` strings.
+    CLike,
+    /// `#` comments and `"""` strings.
+    Python,
+    /// `#` comments.
+    Hash,
+    /// Strings only.
+    Plain,
+}
+
+fn syntax_for(language: &str) -> Syntax {
+    match language {
+        "rust" => Syntax::Rust,
+        "javascript" | "typescript" | "java" | "c" | "cpp" | "csharp" | "go" | "php" | "swift"
+        | "kotlin" | "scala" | "dart" | "css" | "scss" | "less" => Syntax::CLike,
+        "python" => Syntax::Python,
+        "shell" | "ruby" | "perl" | "yaml" | "toml" | "r" | "makefile" => Syntax::Hash,
+        _ => Syntax::Plain,
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_identifier_start) && chars.all(is_identifier_char)
+}
+
+/// Byte offsets of `symbol` as a whole identifier in code, leaving out
+/// comments, strings and longer identifiers that merely contain it.
+fn identifier_occurrences(content: &str, symbol: &str, syntax: Syntax) -> Vec<usize> {
+    let chars: Vec<(usize, char)> = content.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|&(_, c)| c);
+    let starts_with = |i: usize, pattern: &str| content[chars[i].0..].starts_with(pattern);
+    let skip_to = |mut i: usize, end: &str| {
+        while i < chars.len() && !starts_with(i, end) {
+            i += 1;
+        }
+        i + end.chars().count()
+    };
+    let c_like = matches!(syntax, Syntax::Rust | Syntax::CLike);
+    let hash = matches!(syntax, Syntax::Python | Syntax::Hash);
+
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if c_like && starts_with(i, "//") {
+            i = skip_to(i, "\n");
+        } else if c_like && starts_with(i, "/*") {
+            i = skip_to(i + 2, "*/");
+        } else if hash && c == '#' {
+            i = skip_to(i, "\n");
+        } else if syntax == Syntax::Python && (starts_with(i, "\"\"\"") || starts_with(i, "'''")) {
+            let quote = &content[chars[i].0..chars[i].0 + 3];
+            i = skip_to(i + 3, quote);
+        } else if syntax == Syntax::Rust && c == '\'' {
+            // 'a' and '\n' are chars; 'a on its own is a lifetime.
+            i += match (at(i + 1), at(i + 2)) {
+                (Some('\\'), _) => skip_to(i + 2, "'") - i,
+                (Some(_), Some('\'')) => 3,
+                _ => 1,
+            };
+        } else if c == '"' || c == '\'' || (c == '`' && c_like) {
+            // Strings end at the closing quote; all but template literals
+            // also end at the line break.
+            i += 1;
+            while let Some(next) = at(i) {
+                i += 1;
+                if next == '\\' {
+                    i += 1;
+                } else if next == c || (next == '\n' && c != '`') {
+                    break;
+                }
+            }
+        } else if c.is_ascii_digit() {
+            // Numbers such as 0x1f or 1e10 are not identifiers.
+            while at(i).is_some_and(|c| is_identifier_char(c) || c == '.') {
+                i += 1;
+            }
+        } else if is_identifier_start(c) {
+            let start = i;
+            while at(i).is_some_and(is_identifier_char) {
+                i += 1;
+            }
+            let end = chars
+                .get(i)
+                .map(|&(offset, _)| offset)
+                .unwrap_or(content.len());
+            if &content[chars[start].0..end] == symbol {
+                found.push(chars[start].0);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+fn position_at(content: &str, line_starts: &[usize], offset: usize) -> Position {
+    let line = line_starts.partition_point(|&start| start <= offset) - 1;
+    Position {
+        line: line as u32,
+        character: content[line_starts[line]..offset].encode_utf16().count() as u32,
+    }
+}
+
+fn file_rename(
+    path: &Path,
+    content: &str,
+    version: Option<u64>,
+    symbol: &str,
+    language_filter: Option<&str>,
+) -> Option<FileRename> {
+    if !content.contains(symbol) {
+        return None;
+    }
+    let language = language::detect_language(path, content);
+    if language_filter.is_some_and(|filter| filter != language) {
+        return None;
+    }
+    let offsets = identifier_occurrences(content, symbol, syntax_for(&language));
+    if offsets.is_empty() {
+        return None;
+    }
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let symbol_units = symbol.encode_utf16().count() as u32;
+    let occurrences = offsets
+        .into_iter()
+        .map(|offset| {
+            let start = position_at(content, &line_starts, offset);
+            let line_start = line_starts[start.line as usize];
+            let line_end = content[line_start..]
+                .find('\n')
+                .map(|i| line_start + i)
+                .unwrap_or(content.len());
+            RenameOccurrence {
+                range: Range {
+                    start,
+                    end: Position {
+                        line: start.line,
+                        character: start.character + symbol_units,
+                    },
+                },
+                line_text: content[line_start..line_end].trim_end().to_string(),
+            }
+        })
+        .collect();
+    Some(FileRename {
+        path: path.to_string_lossy().to_string(),
+        version,
+        occurrences,
+    })
+}
+
+/// Prefers the open buffer, so ranges match what the user sees.
+fn read_text(scope: &WindowState, path: &Path) -> Option<(String, Option<u64>)> {
+    if let Some(doc) = scope.documents.get(&path.to_string_lossy()) {
+        return Some((doc.content, Some(doc.version)));
+    }
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > LARGE_FILE_THRESHOLD {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if is_probably_binary(&bytes) {
+        return None;
+    }
+    Some((encoding::decode(&bytes).content, None))
+}
+
+fn workspace_edit(files: &[FileRename], new_name: &str) -> Value {
+    let changes: Vec<Value> = files
+        .iter()
+        .map(|file| {
+            let edits: Vec<Value> = file
+                .occurrences
+                .iter()
+                .map(|occurrence| json!({ "range": occurrence.range, "newText": new_name }))
+                .collect();
+            json!({
+                "textDocument": {
+                    "uri": lsp::path_to_uri(Path::new(&file.path)),
+                    "version": file.version,
+                },
+                "edits": edits,
+            })
+        })
+        .collect();
+    json!({ "documentChanges": changes })
+}
+
+/// Rename for languages without a language server: finds `symbol` as a
+/// whole identifier outside comments and strings, in `scope` (a file or
+/// folder; every workspace root when omitted), optionally only in files of
+/// `language`. Returns a preview; nothing is changed.
+#[tauri::command]
+pub async fn rename_symbol_textual(
+    symbol: String,
+    new_name: String,
+    scope: Option<String>,
+    language: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<TextualRename, String> {
+    if !is_identifier(&symbol) {
+        return Err(format!("Not an identifier: {}", symbol));
+    }
+    if !is_identifier(&new_name) {
+        return Err(format!("Not a valid name: {}", new_name));
+    }
+
+    let state = windows.scope(window.label());
+    let roots = match scope {
+        Some(path) => vec![workspace::authorize(&windows, &window, &path)?],
+        None => state.workspace.roots(),
+    };
+    let targets: Vec<(PathBuf, ExclusionMatcher)> = roots
+        .into_iter()
+        .map(|root| {
+            let matcher = exclude::matcher_for(&windows, &window, &root)?;
+            Ok((root, matcher))
+        })
+        .collect::<Result<_, String>>()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        let mut count = 0;
+        for (root, matcher) in &targets {
+            let mut visit = |path: &Path| {
+                if let Some((content, version)) = read_text(&state, path) {
+                    if let Some(file) =
+                        file_rename(path, &content, version, &symbol, language.as_deref())
+                    {
+                        count += file.occurrences.len();
+                        files.push(file);
+                    }
+                }
+                count < MAX_OCCURRENCES
+            };
+            if root.is_file() {
+                visit(root);
+            } else {
+                walk_files(root, Some(matcher), visit);
+            }
+            if count >= MAX_OCCURRENCES {
+                break;
+            }
+        }
+        TextualRename {
+            workspace_edit: workspace_edit(&files, &new_name),
+            symbol,
+            new_name,
+            occurrence_count: count,
+            files,
+            limit_hit: count >= MAX_OCCURRENCES,
+        }
+    })
+    .await
+    .map_err(|e| format!("Rename failed: {}", e))
+}
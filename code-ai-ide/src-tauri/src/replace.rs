@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::documents::Range;
+use crate::encoding::{self, DecodedText};
+use crate::local_history::{LocalHistory, RevisionSource};
+use crate::search::{self, SearchOptions, SearchPattern, SearchTargets};
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaceEdit {
+    /// Position of the edit within its file; selections refer to it.
+    pub index: usize,
+    pub range: Range,
+    pub matched: String,
+    pub replacement: String,
+    /// The affected line before and after this edit alone.
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReplacePreview {
+    pub path: String,
+    /// Hash of the file the preview was computed from; applying refuses to
+    /// touch the file if it changed in between.
+    pub content_hash: String,
+    pub edits: Vec<ReplaceEdit>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplacePreview {
+    pub files: Vec<FileReplacePreview>,
+    pub match_count: usize,
+    pub limit_hit: bool,
+}
+
+/// The edits accepted from a preview for one file, by `ReplaceEdit::index`.
+/// Always explicit: a preview cut off by `max_results` does not show every
+/// match in the file, and those it did not show are left alone.
+#[derive(Debug, Deserialize)]
+pub struct ReplaceSelection {
+    pub path: String,
+    pub content_hash: String,
+    pub edits: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceFailure {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceResult {
+    pub files_changed: usize,
+    pub matches_replaced: usize,
+    pub failed: Vec<ReplaceFailure>,
+}
+
+/// A pending edit in byte offsets.
+struct PlannedEdit {
+    start: usize,
+    end: usize,
+    replacement: String,
+}
+
+fn plan(
+    content: &str,
+    pattern: &SearchPattern,
+    replacement: &str,
+    limit: usize,
+) -> Vec<PlannedEdit> {
+    let mut edits = Vec::new();
+    pattern.for_each_match(content, |base, caps| {
+        let whole = caps.get(0).unwrap();
+        edits.push(PlannedEdit {
+            start: base + whole.start(),
+            end: base + whole.end(),
+            replacement: pattern.replacement(caps, replacement),
+        });
+        edits.len() < limit
+    });
+    edits
+}
+
+fn preview_file(
+    path: &Path,
+    decoded: &DecodedText,
+    bytes_hash: String,
+    edits: &[PlannedEdit],
+) -> FileReplacePreview {
+    let content = &decoded.content;
+    let starts = search::line_starts(content);
+    let edits = edits
+        .iter()
+        .enumerate()
+        .map(|(index, edit)| {
+            let range = Range {
+                start: search::position_at(content, &starts, edit.start),
+                end: search::position_at(content, &starts, edit.end),
+            };
+            let end_line = range.end.line as usize;
+            let line_start = starts[range.start.line as usize];
+            let line_end = starts[end_line] + search::line_text(content, &starts, end_line).len();
+            ReplaceEdit {
+                index,
+                range,
+                matched: content[edit.start..edit.end].to_string(),
+                replacement: edit.replacement.clone(),
+                before: search::line_text(content, &starts, range.start.line as usize).to_string(),
+                after: format!(
+                    "{}{}{}",
+                    &content[line_start..edit.start],
+                    edit.replacement,
+                    &content[edit.end..line_end]
+                ),
+            }
+        })
+        .collect();
+    FileReplacePreview {
+        path: path.to_string_lossy().to_string(),
+        content_hash: bytes_hash,
+        edits,
+    }
+}
+
+fn hash_file(path: &Path) -> Result<(Vec<u8>, String), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let hash = blake3::hash(&bytes).to_hex().to_string();
+    Ok((bytes, hash))
+}
+
+/// Writes through a temporary sibling and a rename so the file is either
+/// fully replaced or untouched, keeping the original permissions.
+pub fn write_replacing(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.replace.tmp", name.to_string_lossy()));
+    fs::write(&temp, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(&temp, metadata.permissions());
+    }
+    fs::rename(&temp, path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        format!("Failed to write file: {}", e)
+    })
+}
+
+fn apply_file(
+    app: &AppHandle,
+    history: &LocalHistory,
+    path: &Path,
+    selection: &ReplaceSelection,
+    pattern: &SearchPattern,
+    replacement: &str,
+) -> Result<usize, String> {
+    let (bytes, hash) = hash_file(path)?;
+    if hash != selection.content_hash {
+        return Err("File changed since the preview; search again".to_string());
+    }
+    let Some(last_index) = selection.edits.iter().max() else {
+        return Ok(0);
+    };
+    let decoded = encoding::decode(&bytes);
+    let edits = plan(&decoded.content, pattern, replacement, last_index + 1);
+    let accepted: Vec<&PlannedEdit> = edits
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| selection.edits.contains(index))
+        .map(|(_, edit)| edit)
+        .collect();
+    if accepted.is_empty() {
+        return Ok(0);
+    }
+
+    let mut content = String::with_capacity(decoded.content.len());
+    let mut last = 0;
+    for edit in &accepted {
+        content.push_str(&decoded.content[last..edit.start]);
+        content.push_str(&edit.replacement);
+        last = edit.end;
+    }
+    content.push_str(&decoded.content[last..]);
+
+    let encoded = encoding::encode(&content, &decoded.encoding, decoded.has_bom)?;
+    // The previous content goes to local history so the replace can be
+    // undone per file with `restore_local_history`.
+    history.record(app, path, &bytes, RevisionSource::Replace)?;
+    write_replacing(path, &encoded)?;
+    Ok(accepted.len())
+}
+
+/// Dry run: lists every edit the replace would make, grouped by file,
+/// without touching the disk.
+#[tauri::command]
+pub async fn replace_in_workspace(
+    query: String,
+    replacement: String,
+    options: Option<SearchOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<ReplacePreview, String> {
+    let options = options.unwrap_or_default();
+    let pattern = SearchPattern::new(&query, &options)?;
+    let targets = SearchTargets::resolve(&windows, &window, &options)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        let mut match_count = 0;
+        targets.for_each_file(|path| {
+            if match_count >= options.max_results {
+                return false;
+            }
+            if let Some(decoded) = search::read_searchable(path, options.max_file_size) {
+                let edits = plan(
+                    &decoded.content,
+                    &pattern,
+                    &replacement,
+                    options.max_results - match_count,
+                );
+                if !edits.is_empty() {
+                    if let Ok((_, hash)) = hash_file(path) {
+                        match_count += edits.len();
+                        files.push(preview_file(path, &decoded, hash, &edits));
+                    }
+                }
+            }
+            true
+        });
+        ReplacePreview {
+            files,
+            limit_hit: match_count >= options.max_results,
+            match_count,
+        }
+    })
+    .await
+    .map_err(|e| format!("Replace preview failed: {}", e))
+}
+
+/// Applies the accepted edits of a preview. Each file is rewritten
+/// atomically and its previous content saved to local history; a file that
+/// fails is reported without stopping the others.
+#[tauri::command]
+pub async fn apply_workspace_replace(
+    query: String,
+    replacement: String,
+    options: Option<SearchOptions>,
+    selection: Vec<ReplaceSelection>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<ReplaceResult, String> {
+    let options = options.unwrap_or_default();
+    let pattern = SearchPattern::new(&query, &options)?;
+    let selection: Vec<_> = selection
+        .into_iter()
+        .map(|file| (workspace::authorize(&windows, &window, &file.path), file))
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let history = app.state::<LocalHistory>();
+        let mut result = ReplaceResult {
+            files_changed: 0,
+            matches_replaced: 0,
+            failed: Vec::new(),
+        };
+        for (path, file) in selection {
+            let outcome = path
+                .and_then(|path| apply_file(&app, &history, &path, &file, &pattern, &replacement));
+            match outcome {
+                Ok(0) => {}
+                Ok(replaced) => {
+                    result.files_changed += 1;
+                    result.matches_replaced += replaced;
+                }
+                Err(message) => result.failed.push(ReplaceFailure {
+                    path: file.path,
+                    message,
+                }),
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Replace failed: {}", e))
+}
@@ -0,0 +1,398 @@
+use chrono::Datelike;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::git;
+use crate::jsonc::read_jsonc;
+use crate::snippets::one_or_many;
+use crate::walk::walk_files_with;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const BUILTIN_TEMPLATES: &str = include_str!("../project_templates.json");
+
+/// In the app config directory, one subdirectory per template.
+const USER_TEMPLATES_DIR: &str = "project_templates";
+
+/// Optional in a user template directory; never copied.
+const MANIFEST: &str = "template.json";
+
+/// Variables every template can use, filled in from the project name.
+const BUILTIN_VARIABLES: &[&str] = &["name", "package_name", "module_name", "author", "year"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectTemplateSource {
+    Builtin,
+    User,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TemplateFile {
+    path: String,
+    #[serde(deserialize_with = "one_or_many")]
+    content: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BuiltinTemplate {
+    id: String,
+    name: String,
+    description: String,
+    files: Vec<TemplateFile>,
+}
+
+#[derive(Deserialize)]
+struct BuiltinFile {
+    templates: Vec<BuiltinTemplate>,
+}
+
+/// `template.json` of a user template.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Manifest {
+    name: Option<String>,
+    description: Option<String>,
+    /// Extra variables and their defaults.
+    #[serde(default)]
+    variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub source: ProjectTemplateSource,
+    pub name: String,
+    pub description: Option<String>,
+    /// Variables beyond the built-in ones that the template declares.
+    pub variables: Vec<TemplateVariable>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProjectOptions {
+    /// Values for template variables; they override the defaults and the
+    /// built-in ones except `name`.
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    #[serde(default = "enabled")]
+    pub git_init: bool,
+    /// Open the project as the window's workspace once it is created.
+    #[serde(default = "enabled")]
+    pub open: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedProject {
+    pub path: String,
+    /// Relative to `path`.
+    pub files: Vec<String>,
+    pub git_initialized: bool,
+}
+
+fn builtin_templates() -> &'static [BuiltinTemplate] {
+    static TEMPLATES: OnceLock<Vec<BuiltinTemplate>> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        serde_json::from_str::<BuiltinFile>(BUILTIN_TEMPLATES)
+            .expect("bundled project_templates.json is valid")
+            .templates
+    })
+}
+
+/// `{{name}}`, with optional spaces inside the braces.
+fn variable_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid regex"))
+}
+
+/// Replaces the variables `values` has; others are left as written, since
+/// template files may use the same braces for their own purposes.
+fn substitute(text: &str, values: &HashMap<String, String>) -> String {
+    variable_pattern()
+        .replace_all(text, |captures: &Captures| match values.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => captures[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// `name` lowercased with every run of other characters replaced by
+/// `separator`. A Python module cannot start with a digit, so
+/// `module_name` gets a leading `_` then.
+fn identifier(name: &str, separator: char) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.is_empty() && !out.ends_with(separator) {
+            out.push(separator);
+        }
+    }
+    let out = out.trim_end_matches(separator).to_string();
+    if separator == '_' && out.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("{}{}", separator, out)
+    } else {
+        out
+    }
+}
+
+fn builtin_values(name: &str, dest: &Path) -> HashMap<String, String> {
+    let author = git::run(dest, &["config", "user.name"])
+        .map(|author| author.trim().to_string())
+        .unwrap_or_default();
+    HashMap::from([
+        ("name".to_string(), name.to_string()),
+        ("package_name".to_string(), identifier(name, '-')),
+        ("module_name".to_string(), identifier(name, '_')),
+        ("author".to_string(), author),
+        ("year".to_string(), chrono::Local::now().year().to_string()),
+    ])
+}
+
+fn user_templates_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app_data::app_config_path(app, USER_TEMPLATES_DIR)
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let path = dir.join(MANIFEST);
+    if !path.exists() {
+        return Ok(Manifest::default());
+    }
+    serde_json::from_value(read_jsonc(&path)?)
+        .map_err(|e| format!("Invalid template {}: {}", path.display(), e))
+}
+
+fn user_template(dir: &Path) -> Option<ProjectTemplate> {
+    let id = dir.file_name()?.to_string_lossy().to_string();
+    let manifest = read_manifest(dir).ok()?;
+    let mut variables: Vec<TemplateVariable> = manifest
+        .variables
+        .into_iter()
+        .filter(|(name, _)| !BUILTIN_VARIABLES.contains(&name.as_str()))
+        .map(|(name, default)| TemplateVariable {
+            name,
+            default: Some(default),
+        })
+        .collect();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(ProjectTemplate {
+        name: manifest.name.unwrap_or_else(|| id.clone()),
+        description: manifest.description,
+        id,
+        source: ProjectTemplateSource::User,
+        variables,
+    })
+}
+
+fn user_templates(app: &AppHandle) -> Vec<(PathBuf, ProjectTemplate)> {
+    let Ok(entries) = user_templates_dir(app).and_then(|dir| {
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))
+    }) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<(PathBuf, ProjectTemplate)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|dir| Some((dir.clone(), user_template(&dir)?)))
+        .collect();
+    templates.sort_by(|a, b| a.1.id.cmp(&b.1.id));
+    templates
+}
+
+/// The built-in templates (Cargo binary and library, Vite + React, Python
+/// package) and then the user's, from directories under
+/// `project_templates` in the app config directory. A user template with
+/// a built-in's id replaces it.
+#[tauri::command]
+pub async fn list_project_templates(app: AppHandle) -> Result<Vec<ProjectTemplate>, String> {
+    let user = user_templates(&app);
+    let mut templates: Vec<ProjectTemplate> = builtin_templates()
+        .iter()
+        .filter(|builtin| !user.iter().any(|(_, template)| template.id == builtin.id))
+        .map(|builtin| ProjectTemplate {
+            id: builtin.id.clone(),
+            source: ProjectTemplateSource::Builtin,
+            name: builtin.name.clone(),
+            description: Some(builtin.description.clone()),
+            variables: Vec::new(),
+        })
+        .collect();
+    templates.extend(user.into_iter().map(|(_, template)| template));
+    Ok(templates)
+}
+
+/// Relative path of a template file after substitution, refusing any that
+/// would land outside the project.
+fn output_path(target: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative);
+    let escapes = relative
+        .components()
+        .any(|component| !matches!(component, std::path::Component::Normal(_)));
+    if relative.as_os_str().is_empty() || escapes {
+        return Err(format!("Invalid template path: {}", relative.display()));
+    }
+    Ok(target.join(relative))
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the template's files under `target` and returns their relative
+/// paths. Text files and file names have their variables filled in;
+/// anything that is not UTF-8 is copied as is.
+fn generate(
+    template: &str,
+    user_dir: Option<&Path>,
+    target: &Path,
+    values: &HashMap<String, String>,
+) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    match user_dir {
+        Some(dir) => {
+            let mut sources = Vec::new();
+            walk_files_with(
+                dir,
+                |path, is_dir| {
+                    path.file_name().is_some_and(|name| name == ".git")
+                        || (!is_dir && path == dir.join(MANIFEST))
+                },
+                |path| {
+                    sources.push(path.to_path_buf());
+                    true
+                },
+            );
+            for source in sources {
+                let relative = source
+                    .strip_prefix(dir)
+                    .map_err(|e| e.to_string())?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let relative = substitute(&relative, values);
+                let bytes = fs::read(&source)
+                    .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+                let content = match String::from_utf8(bytes) {
+                    Ok(text) => substitute(&text, values).into_bytes(),
+                    Err(e) => e.into_bytes(),
+                };
+                write_file(&output_path(target, &relative)?, &content)?;
+                files.push(relative);
+            }
+        }
+        None => {
+            let builtin = builtin_templates()
+                .iter()
+                .find(|builtin| builtin.id == template)
+                .ok_or_else(|| format!("No such project template: {}", template))?;
+            for file in &builtin.files {
+                let relative = substitute(&file.path, values);
+                let mut content = substitute(&file.content.join("\n"), values);
+                content.push('\n');
+                write_file(&output_path(target, &relative)?, content.as_bytes())?;
+                files.push(relative);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Creates the project `name` in the directory `dest` from `template`: a
+/// built-in id or the name of a user template directory, which wins.
+/// `{{var}}` in file contents and names is replaced with `options.vars`,
+/// the template's defaults and the built-in `name`, `package_name`
+/// (kebab-case), `module_name` (snake_case), `author` (from git) and
+/// `year`. `dest` must be accessible and the project directory must not
+/// exist yet or be empty; a failed project leaves nothing behind. The
+/// repository is initialised and the project opened unless `options` say
+/// not to.
+#[tauri::command]
+pub async fn create_project(
+    template: String,
+    name: String,
+    dest: String,
+    options: CreateProjectOptions,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<CreatedProject, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(format!("Invalid project name: {}", name));
+    }
+    let dest = workspace::authorize(&windows, &window, &dest)?;
+    let target = dest.join(&name);
+    let created = !target.exists();
+    let empty = fs::read_dir(&target)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false);
+    if !created && !empty {
+        return Err(format!(
+            "Destination already exists and is not empty: {}",
+            target.display()
+        ));
+    }
+
+    let user_dir = user_templates(&app)
+        .into_iter()
+        .find(|(_, user)| user.id == template)
+        .map(|(dir, _)| dir);
+    let mut values = builtin_values(&name, &dest);
+    if let Some(dir) = &user_dir {
+        values.extend(read_manifest(dir)?.variables);
+    }
+    values.extend(options.vars.into_iter().filter(|(key, _)| key != "name"));
+
+    let result = fs::create_dir_all(&target)
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))
+        .and_then(|_| generate(&template, user_dir.as_deref(), &target, &values));
+    let files = match result {
+        Ok(files) => files,
+        Err(e) => {
+            if created {
+                let _ = fs::remove_dir_all(&target);
+            } else if let Ok(entries) = fs::read_dir(&target) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let _ = if path.is_dir() {
+                        fs::remove_dir_all(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    };
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    // A project inside an existing repository is left to that one.
+    let inside_repo = git::run(&target, &["rev-parse", "--show-toplevel"]).is_ok();
+    let git_initialized = options.git_init && !inside_repo && git::run(&target, &["init"]).is_ok();
+    if options.open {
+        workspace::open_workspace(&window, &windows, &target)?;
+    }
+    Ok(CreatedProject {
+        path: target.to_string_lossy().to_string(),
+        files,
+        git_initialized,
+    })
+}
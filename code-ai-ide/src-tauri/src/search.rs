@@ -0,0 +1,458 @@
+use globset::GlobSet;
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tauri::{State, Window};
+
+use crate::documents::{Position, Range};
+use crate::encoding::{self, DecodedText};
+use crate::exclude::{self, ExclusionMatcher};
+use crate::metadata::LARGE_FILE_THRESHOLD;
+use crate::text_health::is_probably_binary;
+use crate::walk::walk_files_with;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Lines longer than this (minified bundles, data files) are cut down to a
+/// window around the match in previews.
+const MAX_PREVIEW_CHARS: usize = 250;
+const PREVIEW_CONTEXT_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    /// Treat the query as a regular expression instead of literal text.
+    pub is_regex: bool,
+    pub whole_word: bool,
+    /// Let matches span lines; `^`/`$` still match at line boundaries and
+    /// `.` also matches newlines.
+    pub multiline: bool,
+    /// Folders or files to search; defaults to every workspace root.
+    pub paths: Vec<String>,
+    /// Globs a file must match to be searched (any of them); empty means
+    /// every file. Same convention as the exclusion settings: patterns
+    /// without a `/` match names, others the workspace-relative path.
+    pub include: Vec<String>,
+    /// Globs for files and folders to leave out, on top of the exclusions.
+    pub exclude: Vec<String>,
+    /// Also search what `.gitignore` and the exclusion settings hide.
+    pub include_ignored: bool,
+    /// Also search dotfiles and files in dot-directories.
+    pub include_hidden: bool,
+    /// Files larger than this many bytes are skipped.
+    pub max_file_size: u64,
+    /// The search stops once this many matches were found.
+    pub max_results: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            case_sensitive: false,
+            is_regex: false,
+            whole_word: false,
+            multiline: false,
+            paths: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            include_ignored: false,
+            include_hidden: false,
+            max_file_size: LARGE_FILE_THRESHOLD,
+            max_results: 20_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureSpan {
+    pub name: Option<String>,
+    pub range: Range,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub range: Range,
+    /// Capture groups 1..n for regex queries, `None` where a group did not
+    /// take part in the match. Empty for literal queries.
+    pub captures: Vec<Option<CaptureSpan>>,
+    /// The line containing the start of the match, possibly shortened.
+    pub preview: String,
+    /// UTF-16 column in the line where `preview` begins; non-zero only when
+    /// a long line was shortened.
+    pub preview_offset: u32,
+}
+
+/// Emitted as `search-results`, once per file with matches.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatches {
+    pub search_id: String,
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Emitted as `search-complete` when the search ends for any reason.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchComplete {
+    pub search_id: String,
+    pub files_searched: usize,
+    pub match_count: usize,
+    pub limit_hit: bool,
+    pub cancelled: bool,
+}
+
+/// A compiled query. Outside multiline mode the pattern runs over each line
+/// on its own, so a match can never cross a line break.
+pub struct SearchPattern {
+    regex: Regex,
+    multiline: bool,
+    is_regex: bool,
+}
+
+impl SearchPattern {
+    pub fn new(query: &str, options: &SearchOptions) -> Result<SearchPattern, String> {
+        if query.is_empty() {
+            return Err("Search query is empty".to_string());
+        }
+        let mut pattern = if options.is_regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(options.multiline)
+            .dot_matches_new_line(options.multiline)
+            .build()
+            .map_err(|e| format!("Invalid search query: {}", e))?;
+        Ok(SearchPattern {
+            regex,
+            multiline: options.multiline,
+            is_regex: options.is_regex,
+        })
+    }
+
+    /// The text a match is replaced with: `$1`/`${name}` references are
+    /// expanded for regex queries, literal queries use `replacement` as is.
+    pub fn replacement(&self, caps: &Captures, replacement: &str) -> String {
+        if !self.is_regex {
+            return replacement.to_string();
+        }
+        let mut expanded = String::new();
+        caps.expand(replacement, &mut expanded);
+        expanded
+    }
+
+    /// Calls `f` with each non-empty match and the byte offset in `content`
+    /// of the slice the captures refer to. `f` returns `false` to stop.
+    pub fn for_each_match(&self, content: &str, mut f: impl FnMut(usize, &Captures) -> bool) {
+        if self.multiline {
+            for caps in self.regex.captures_iter(content) {
+                if !caps[0].is_empty() && !f(0, &caps) {
+                    return;
+                }
+            }
+            return;
+        }
+
+        let mut start = 0;
+        for line in content.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            for caps in self.regex.captures_iter(text) {
+                if !caps[0].is_empty() && !f(start, &caps) {
+                    return;
+                }
+            }
+            start += line.len();
+        }
+    }
+}
+
+/// Reads a file for searching. Binary files and files above `max_size`
+/// yield `None`; binary is decided by content, not by extension.
+pub fn read_searchable(path: &Path, max_size: u64) -> Option<DecodedText> {
+    let size = fs::metadata(path).ok()?.len();
+    if size > max_size {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    let decoded = encoding::decode(&bytes);
+    // UTF-16 text is full of NUL bytes, so only sniff other encodings.
+    if !decoded.encoding.starts_with("UTF-16") && is_probably_binary(&bytes) {
+        return None;
+    }
+    Some(decoded)
+}
+
+/// Byte offsets where each line of `content` starts.
+pub fn line_starts(content: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+pub fn position_at(content: &str, starts: &[usize], offset: usize) -> Position {
+    let line = starts.partition_point(|&start| start <= offset) - 1;
+    Position {
+        line: line as u32,
+        character: content[starts[line]..offset].encode_utf16().count() as u32,
+    }
+}
+
+pub fn line_text<'a>(content: &'a str, starts: &[usize], line: usize) -> &'a str {
+    let end = starts.get(line + 1).copied().unwrap_or(content.len());
+    content[starts[line]..end].trim_end_matches(['\n', '\r'])
+}
+
+fn preview_for(line: &str, match_character: u32) -> (String, u32) {
+    if line.chars().count() <= MAX_PREVIEW_CHARS {
+        return (line.to_string(), 0);
+    }
+    // Work in UTF-16 units to stay consistent with the match columns.
+    let units: Vec<u16> = line.encode_utf16().collect();
+    let start = (match_character as usize).saturating_sub(PREVIEW_CONTEXT_CHARS);
+    let end = (start + MAX_PREVIEW_CHARS).min(units.len());
+    (String::from_utf16_lossy(&units[start..end]), start as u32)
+}
+
+pub fn find_matches(content: &str, pattern: &SearchPattern, limit: usize) -> Vec<SearchMatch> {
+    let starts = line_starts(content);
+    let range_of = |start: usize, end: usize| Range {
+        start: position_at(content, &starts, start),
+        end: position_at(content, &starts, end),
+    };
+
+    let mut matches = Vec::new();
+    pattern.for_each_match(content, |base, caps| {
+        let whole = caps.get(0).unwrap();
+        let range = range_of(base + whole.start(), base + whole.end());
+        let captures = caps
+            .iter()
+            .zip(pattern.regex.capture_names())
+            .skip(1)
+            .map(|(group, name)| {
+                group.map(|group| CaptureSpan {
+                    name: name.map(str::to_string),
+                    range: range_of(base + group.start(), base + group.end()),
+                    text: group.as_str().to_string(),
+                })
+            })
+            .collect();
+
+        let line = line_text(content, &starts, range.start.line as usize);
+        let (preview, preview_offset) = preview_for(line, range.start.character);
+        matches.push(SearchMatch {
+            range,
+            captures,
+            preview,
+            preview_offset,
+        });
+        matches.len() < limit
+    });
+    matches
+}
+
+struct SearchRoot {
+    path: PathBuf,
+    /// Workspace root containing `path`; globs are relative to it.
+    base: PathBuf,
+    matcher: Option<ExclusionMatcher>,
+}
+
+/// What a search covers: each folder or file to search together with the
+/// rules deciding which files below it take part.
+pub struct SearchTargets {
+    roots: Vec<SearchRoot>,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    include_hidden: bool,
+}
+
+impl SearchTargets {
+    pub fn resolve(
+        windows: &WindowRegistry,
+        window: &Window,
+        options: &SearchOptions,
+    ) -> Result<SearchTargets, String> {
+        let workspace_roots = windows.scope(window.label()).workspace.roots();
+        let paths = if options.paths.is_empty() {
+            workspace_roots.clone()
+        } else {
+            options
+                .paths
+                .iter()
+                .map(|path| workspace::authorize(windows, window, path))
+                .collect::<Result<_, _>>()?
+        };
+        let roots = paths
+            .into_iter()
+            .map(|path| {
+                let base = workspace_roots
+                    .iter()
+                    .filter(|root| path.starts_with(root))
+                    .max_by_key(|root| root.components().count())
+                    .cloned()
+                    .unwrap_or_else(|| path.clone());
+                let matcher = if options.include_ignored {
+                    None
+                } else {
+                    Some(exclude::matcher_for(windows, window, &path)?)
+                };
+                Ok(SearchRoot {
+                    path,
+                    base,
+                    matcher,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        let include = if options.include.is_empty() {
+            None
+        } else {
+            let include: Vec<String> = options
+                .include
+                .iter()
+                .map(|glob| glob.trim_end_matches('/').to_string())
+                .collect();
+            Some(exclude::build_globset(&include)?)
+        };
+        Ok(SearchTargets {
+            roots,
+            include,
+            exclude: exclude::build_globset(&options.exclude)?,
+            include_hidden: options.include_hidden,
+        })
+    }
+
+    fn skips(&self, root: &SearchRoot, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&root.base).unwrap_or(path);
+        if !self.include_hidden
+            && path.strip_prefix(&root.path).is_ok_and(|below| {
+                below
+                    .components()
+                    .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+            })
+        {
+            return true;
+        }
+        if exclude::globs_match(&self.exclude, relative) {
+            return true;
+        }
+        // Include globs select files, either directly or through a folder
+        // they are in; folders themselves are always entered.
+        if let (Some(include), false) = (&self.include, is_dir) {
+            let included = relative
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .any(|p| exclude::globs_match(include, p));
+            if !included {
+                return true;
+            }
+        }
+        root.matcher
+            .as_ref()
+            .is_some_and(|matcher| matcher.is_excluded(path, is_dir))
+    }
+
+    /// Visits every file in scope until `f` returns `false`.
+    pub fn for_each_file(&self, mut f: impl FnMut(&Path) -> bool) {
+        let mut running = true;
+        for root in &self.roots {
+            if !running {
+                return;
+            }
+            if root.path.is_file() {
+                // An explicitly chosen file is searched regardless of filters.
+                running = f(&root.path);
+            } else {
+                walk_files_with(
+                    &root.path,
+                    |path, is_dir| self.skips(root, path, is_dir),
+                    |path| {
+                        running = f(path);
+                        running
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Starts a search in the background and returns its id. Matches stream in
+/// as `search-results` events and the run ends with `search-complete`.
+#[tauri::command]
+pub async fn search_workspace(
+    query: String,
+    options: Option<SearchOptions>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let pattern = SearchPattern::new(&query, &options)?;
+    let targets = SearchTargets::resolve(&windows, &window, &options)?;
+
+    let scope = windows.scope(window.label());
+    let search_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = scope.searches.register(&search_id);
+
+    let id = search_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files_searched = 0;
+        let mut match_count = 0;
+
+        targets.for_each_file(|path| {
+            if cancelled.load(Ordering::SeqCst) || match_count >= options.max_results {
+                return false;
+            }
+            if let Some(decoded) = read_searchable(path, options.max_file_size) {
+                files_searched += 1;
+                let matches = find_matches(
+                    &decoded.content,
+                    &pattern,
+                    options.max_results - match_count,
+                );
+                if !matches.is_empty() {
+                    match_count += matches.len();
+                    let _ = window.emit(
+                        "search-results",
+                        FileMatches {
+                            search_id: id.clone(),
+                            path: path.to_string_lossy().to_string(),
+                            matches,
+                        },
+                    );
+                }
+            }
+            true
+        });
+
+        scope.searches.finish(&id);
+        let _ = window.emit(
+            "search-complete",
+            SearchComplete {
+                search_id: id.clone(),
+                files_searched,
+                match_count,
+                limit_hit: match_count >= options.max_results,
+                cancelled: cancelled.load(Ordering::SeqCst),
+            },
+        );
+    });
+
+    Ok(search_id)
+}
+
+#[tauri::command]
+pub async fn cancel_search(
+    search_id: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    Ok(windows.scope(window.label()).searches.cancel(&search_id))
+}
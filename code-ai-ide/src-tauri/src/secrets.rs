@@ -0,0 +1,107 @@
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::settings;
+
+/// Keychain service the entries are stored under; the account is the
+/// provider.
+const SERVICE: &str = "code-ai-ide";
+
+/// Providers that may hold a secret, so the webview cannot use the
+/// keychain as general storage.
+const PROVIDERS: &[&str] = &["openai", "anthropic", "openai_compatible"];
+
+/// Settings that held keys in plain text before they moved to the
+/// keychain, with the provider each belongs to.
+const PLAINTEXT_SETTINGS: &[(&str, &str)] = &[
+    ("ai.openai.apiKey", "openai"),
+    ("ai.anthropic.apiKey", "anthropic"),
+    ("ai.compatible.apiKey", "openai_compatible"),
+];
+
+fn entry(provider: &str) -> Result<keyring::Entry, String> {
+    if !PROVIDERS.contains(&provider) {
+        return Err(format!("Unknown provider: {}", provider));
+    }
+    keyring::Entry::new(SERVICE, provider).map_err(|e| format!("Failed to open keychain: {}", e))
+}
+
+/// The provider's secret, or `None` if none is stored.
+pub fn get(provider: &str) -> Result<Option<String>, String> {
+    match entry(provider)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read from keychain: {}", e)),
+    }
+}
+
+fn set(provider: &str, secret: &str) -> Result<(), String> {
+    entry(provider)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to write to keychain: {}", e))
+}
+
+fn delete(provider: &str) -> Result<bool, String> {
+    match entry(provider)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to delete from keychain: {}", e)),
+    }
+}
+
+/// Moves keys left in the user settings file into the keychain. A key is
+/// only removed from the file once the keychain has it, and an existing
+/// keychain entry wins over the file. Returns how many were moved.
+pub fn migrate_plaintext(app: &AppHandle) -> Result<usize, String> {
+    let mut moved = 0;
+    for (key, provider) in PLAINTEXT_SETTINGS {
+        let taken = settings::take_user_setting(app, key, |value| {
+            match value.as_str().map(str::trim).filter(|s| !s.is_empty()) {
+                Some(secret) if get(provider)?.is_none() => set(provider, secret),
+                _ => Ok(()),
+            }
+        })?;
+        if taken
+            .as_ref()
+            .and_then(Value::as_str)
+            .is_some_and(|secret| !secret.trim().is_empty())
+        {
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Stores the API key for `provider` in the OS keychain.
+#[tauri::command]
+pub async fn set_secret(provider: String, secret: String) -> Result<(), String> {
+    let secret = secret.trim().to_string();
+    if secret.is_empty() {
+        return Err("Secret is empty".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || set(&provider, &secret))
+        .await
+        .map_err(|e| format!("Failed to store secret: {}", e))?
+}
+
+/// `get` for async callers. The keychain can block, e.g. on an unlock
+/// prompt, so it is read off the async runtime.
+pub async fn load(provider: &str) -> Result<Option<String>, String> {
+    let provider = provider.to_string();
+    tauri::async_runtime::spawn_blocking(move || get(&provider))
+        .await
+        .map_err(|e| format!("Failed to read secret: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_secret(provider: String) -> Result<Option<String>, String> {
+    load(&provider).await
+}
+
+/// Returns whether there was a secret to delete.
+#[tauri::command]
+pub async fn delete_secret(provider: String) -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(move || delete(&provider))
+        .await
+        .map_err(|e| format!("Failed to delete secret: {}", e))?
+}
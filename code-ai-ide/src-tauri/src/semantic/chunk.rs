@@ -0,0 +1,72 @@
+/// Lines per chunk before looking for a better place to cut.
+const CHUNK_LINES: usize = 40;
+/// Lines repeated at the start of the next chunk, so code that straddles a
+/// cut is still found whole in one of them.
+const OVERLAP_LINES: usize = 8;
+/// Hard limit per chunk; keeps minified files within embedding limits.
+const MAX_CHUNK_CHARS: usize = 2400;
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// Zero-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Splits a file into overlapping line windows. A window ends early at a
+/// blank line in its second half, which tends to fall between functions.
+pub fn chunk_text(text: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let limit = (start + CHUNK_LINES).min(lines.len());
+        let mut end = limit;
+        if limit < lines.len() {
+            if let Some(blank) = (start + CHUNK_LINES / 2..limit)
+                .rev()
+                .find(|&i| lines[i].trim().is_empty())
+            {
+                end = blank + 1;
+            }
+        }
+
+        let mut chars = 0;
+        let mut cut = start;
+        while cut < end {
+            chars += lines[cut].len() + 1;
+            if chars > MAX_CHUNK_CHARS && cut > start {
+                break;
+            }
+            cut += 1;
+        }
+        let end = cut;
+
+        let mut body = lines[start..end].join("\n");
+        if !body.trim().is_empty() {
+            if body.len() > MAX_CHUNK_CHARS {
+                // A single very long line.
+                let mut boundary = MAX_CHUNK_CHARS;
+                while !body.is_char_boundary(boundary) {
+                    boundary -= 1;
+                }
+                body.truncate(boundary);
+            }
+            chunks.push(Chunk {
+                start_line: start,
+                end_line: end - 1,
+                text: body,
+            });
+        }
+        if end >= lines.len() {
+            break;
+        }
+        start = if end - start > OVERLAP_LINES * 2 {
+            end - OVERLAP_LINES
+        } else {
+            end
+        };
+    }
+    chunks
+}
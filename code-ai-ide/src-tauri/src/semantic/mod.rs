@@ -0,0 +1,369 @@
+pub mod chunk;
+pub mod store;
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, State, Window};
+use tokio::sync::mpsc;
+
+use self::chunk::{chunk_text, Chunk};
+use self::store::VectorStore;
+use crate::ai::embeddings::{self, EmbeddingProvider};
+use crate::app_data;
+use crate::exclude::ExclusionMatcher;
+use crate::walk::walk_files;
+use crate::window_state::{WindowRegistry, WindowState};
+
+const STORE_DIR: &str = "semantic";
+/// Larger files are mostly generated or data, not code worth retrieving.
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const EMBED_BATCH: usize = 32;
+/// Watcher changes are collected this long before re-indexing, so a save
+/// that touches a file several times embeds it once.
+const REINDEX_DELAY: Duration = Duration::from_secs(2);
+const DEFAULT_RESULTS: usize = 10;
+const MAX_RESULTS: usize = 100;
+/// Emit progress every this many files during a full pass.
+const PROGRESS_EVERY: usize = 20;
+
+/// Emitted as `semantic-index-status` as indexing progresses and when it
+/// finishes.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SemanticIndexStatus {
+    pub root: Option<String>,
+    pub model: Option<String>,
+    pub indexing: bool,
+    /// Files looked at so far in the current pass, and how many it covers.
+    pub processed_files: usize,
+    pub total_files: usize,
+    pub chunk_count: usize,
+    /// The last failure, e.g. the embedding server being unreachable.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticHit {
+    pub path: String,
+    pub relative_path: String,
+    /// Zero-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Cosine similarity; higher is closer.
+    pub score: f32,
+    pub text: String,
+}
+
+struct IndexHandle {
+    root: PathBuf,
+    provider: Box<dyn EmbeddingProvider>,
+    store: Mutex<VectorStore>,
+    matcher: ExclusionMatcher,
+    status: Mutex<SemanticIndexStatus>,
+    cancelled: AtomicBool,
+}
+
+/// Embedding index of a window's active workspace. Built in the
+/// background, persisted per root in the app data directory so a reopened
+/// workspace only embeds what changed, and kept current from the watcher.
+#[derive(Default)]
+pub struct SemanticIndex {
+    current: Mutex<Option<Arc<IndexHandle>>>,
+    subscription: Mutex<Option<u64>>,
+}
+
+impl SemanticIndex {
+    fn handle(&self) -> Result<Arc<IndexHandle>, String> {
+        self.current
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| "The semantic index has not been started".to_string())
+    }
+
+    pub fn status(&self) -> SemanticIndexStatus {
+        match self.current.lock().unwrap().as_ref() {
+            Some(handle) => handle.status.lock().unwrap().clone(),
+            None => SemanticIndexStatus::default(),
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(handle) = self.current.lock().unwrap().take() {
+            handle.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl IndexHandle {
+    /// Also excluded when a directory above it is, which the matcher alone
+    /// does not check for a single path.
+    fn is_excluded(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|dir| dir.starts_with(&self.root) && *dir != self.root)
+            .enumerate()
+            .any(|(i, dir)| self.matcher.is_excluded(dir, i > 0))
+    }
+
+    fn set_status(&self, window: &Window, update: impl FnOnce(&mut SemanticIndexStatus)) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            update(&mut status);
+            status.chunk_count = self.store.lock().unwrap().chunk_count();
+            status.clone()
+        };
+        let _ = window.emit("semantic-index-status", status);
+    }
+
+    /// Brings what is stored for one file in line with the disk.
+    async fn update_file(&self, path: &Path) -> Result<(), String> {
+        let readable = path.is_file()
+            && !self.is_excluded(path)
+            && fs::metadata(path).is_ok_and(|meta| meta.len() <= MAX_FILE_BYTES);
+        let text = readable
+            .then(|| fs::read(path).ok())
+            .flatten()
+            .filter(|bytes| !bytes.contains(&0))
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        let Some(text) = text else {
+            return self.store.lock().unwrap().remove_under(path);
+        };
+
+        let hash = blake3::hash(text.as_bytes()).to_hex().to_string();
+        if self.store.lock().unwrap().file_hash(path)?.as_deref() == Some(hash.as_str()) {
+            return Ok(());
+        }
+        let chunks = chunk_text(&text);
+        let relative = path
+            .strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut embedded: Vec<(Chunk, Vec<f32>)> = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH) {
+            // The path tells the model what the snippet is part of.
+            let inputs: Vec<String> = batch
+                .iter()
+                .map(|chunk| format!("{}\n{}", relative, chunk.text))
+                .collect();
+            let vectors = self.provider.embed(&inputs).await?;
+            embedded.extend(batch.iter().cloned().zip(vectors));
+        }
+        self.store
+            .lock()
+            .unwrap()
+            .replace_file(path, &hash, embedded)
+    }
+
+    /// Updates `path`, or every file below it if it is a directory.
+    async fn update_path(&self, path: &Path) -> Result<(), String> {
+        if !path.is_dir() {
+            return self.update_file(path).await;
+        }
+        let mut files = Vec::new();
+        walk_files(path, Some(&self.matcher), |file| {
+            files.push(file.to_path_buf());
+            true
+        });
+        for file in files {
+            self.update_file(&file).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-embeds changed files and drops deleted ones. Stops at the first
+    /// embedding failure, keeping what was done; the next pass resumes.
+    async fn full_pass(&self, window: &Window) -> Result<(), String> {
+        let mut files = Vec::new();
+        walk_files(&self.root, Some(&self.matcher), |file| {
+            files.push(file.to_path_buf());
+            true
+        });
+        let present: HashSet<&PathBuf> = files.iter().collect();
+        let stale: Vec<PathBuf> = self
+            .store
+            .lock()
+            .unwrap()
+            .files()?
+            .into_iter()
+            .filter(|file| !present.contains(file))
+            .collect();
+        for file in stale {
+            self.store.lock().unwrap().remove_under(&file)?;
+        }
+
+        self.set_status(window, |status| {
+            status.indexing = true;
+            status.processed_files = 0;
+            status.total_files = files.len();
+            status.error = None;
+        });
+        for (i, file) in files.iter().enumerate() {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            self.update_file(file).await?;
+            if (i + 1) % PROGRESS_EVERY == 0 {
+                self.set_status(window, |status| status.processed_files = i + 1);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Starts (or restarts) indexing the window's active workspace. Progress
+/// arrives as `semantic-index-status` events; afterwards the index follows
+/// file changes until the workspace or window closes.
+#[tauri::command]
+pub async fn start_semantic_index(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<SemanticIndexStatus, String> {
+    let scope = windows.scope(window.label());
+    let root = scope
+        .workspace
+        .active()
+        .ok_or_else(|| "No workspace is open".to_string())?;
+    let provider = embeddings::provider(&app).await?;
+    let model = provider.id();
+    let store_path = app_data::app_data_path(
+        &app,
+        &format!(
+            "{}/{}.sqlite",
+            STORE_DIR,
+            blake3::hash(root.to_string_lossy().as_bytes()).to_hex()
+        ),
+    )?;
+    let store = {
+        let model = model.clone();
+        tauri::async_runtime::spawn_blocking(move || VectorStore::open(&store_path, &model))
+            .await
+            .map_err(|e| format!("Failed to open the semantic index: {}", e))??
+    };
+    let matcher = ExclusionMatcher::new(&root, &scope.exclusions.globs())?;
+    let handle = Arc::new(IndexHandle {
+        provider,
+        matcher,
+        status: Mutex::new(SemanticIndexStatus {
+            root: Some(root.to_string_lossy().to_string()),
+            model: Some(model),
+            chunk_count: store.chunk_count(),
+            ..Default::default()
+        }),
+        store: Mutex::new(store),
+        root,
+        cancelled: AtomicBool::new(false),
+    });
+    let status = handle.status.lock().unwrap().clone();
+
+    scope.semantic_index.stop();
+    *scope.semantic_index.current.lock().unwrap() = Some(handle.clone());
+    let (sender, mut changes) = mpsc::unbounded_channel::<PathBuf>();
+    let subscription = scope.watcher.subscribe(Box::new(move |change| {
+        for path in &change.paths {
+            let _ = sender.send(path.clone());
+        }
+    }));
+    if let Some(previous) = scope
+        .semantic_index
+        .subscription
+        .lock()
+        .unwrap()
+        .replace(subscription)
+    {
+        scope.watcher.unsubscribe(previous);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let result = handle.full_pass(&window).await;
+        handle.set_status(&window, |status| {
+            status.indexing = false;
+            status.processed_files = status.total_files;
+            status.error = result.err();
+        });
+
+        // Ends when the subscription is dropped with the watcher.
+        while let Some(first) = changes.recv().await {
+            tokio::time::sleep(REINDEX_DELAY).await;
+            let mut paths = HashSet::from([first]);
+            while let Ok(path) = changes.try_recv() {
+                paths.insert(path);
+            }
+            if handle.cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            let mut error = None;
+            for path in paths
+                .into_iter()
+                .filter(|path| path.starts_with(&handle.root))
+            {
+                if let Err(e) = handle.update_path(&path).await {
+                    error = Some(e);
+                }
+            }
+            handle.set_status(&window, |status| status.error = error);
+        }
+    });
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn get_semantic_index_status(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<SemanticIndexStatus, String> {
+    Ok(windows.scope(window.label()).semantic_index.status())
+}
+
+/// The `k` workspace chunks closest in meaning to `query`. Shared by the
+/// `semantic_search` command and chat context retrieval.
+pub async fn search(
+    scope: &WindowState,
+    query: &str,
+    k: usize,
+) -> Result<Vec<SemanticHit>, String> {
+    let handle = scope.semantic_index.handle()?;
+    let vector = handle
+        .provider
+        .embed(&[query.to_string()])
+        .await?
+        .pop()
+        .ok_or_else(|| "No embedding returned for the query".to_string())?;
+    let found = handle
+        .store
+        .lock()
+        .unwrap()
+        .search(vector, k.clamp(1, MAX_RESULTS))?;
+    Ok(found
+        .into_iter()
+        .map(|(score, chunk)| SemanticHit {
+            path: chunk.path.to_string_lossy().to_string(),
+            relative_path: chunk
+                .path
+                .strip_prefix(&handle.root)
+                .unwrap_or(&chunk.path)
+                .to_string_lossy()
+                .replace('\\', "/"),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            score,
+            text: chunk.text,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn semantic_search(
+    query: String,
+    k: Option<usize>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<SemanticHit>, String> {
+    let scope = windows.scope(window.label());
+    search(&scope, &query, k.unwrap_or(DEFAULT_RESULTS)).await
+}
@@ -0,0 +1,240 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+use super::chunk::Chunk;
+
+/// Chunk vectors of one workspace in SQLite, with every vector also held in
+/// memory for search. Queries compare against all of them; at the size of
+/// a workspace that is a few milliseconds and needs no approximate index.
+pub struct VectorStore {
+    conn: Connection,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    id: i64,
+    path: PathBuf,
+    /// Unit length, so a dot product is the cosine similarity.
+    vector: Vec<f32>,
+}
+
+pub struct StoredChunk {
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Failed to access the semantic index: {}", e)
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in &mut vector {
+            *x /= norm;
+        }
+    }
+    vector
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+impl VectorStore {
+    /// Opens or creates the store. One built with another embedding model
+    /// is emptied, since its vectors cannot be compared with new ones.
+    pub fn open(path: &Path, model: &str) -> Result<VectorStore, String> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS files (path TEXT PRIMARY KEY, hash TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS chunks (
+                 id INTEGER PRIMARY KEY,
+                 path TEXT NOT NULL,
+                 start_line INTEGER NOT NULL,
+                 end_line INTEGER NOT NULL,
+                 text TEXT NOT NULL,
+                 vector BLOB NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS chunks_path ON chunks (path);",
+        )
+        .map_err(db_error)?;
+
+        let stored: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'model'", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(db_error)?;
+        if stored.as_deref() != Some(model) {
+            conn.execute_batch("DELETE FROM chunks; DELETE FROM files;")
+                .map_err(db_error)?;
+            conn.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('model', ?1)",
+                params![model],
+            )
+            .map_err(db_error)?;
+        }
+
+        let entries = {
+            let mut statement = conn
+                .prepare("SELECT id, path, vector FROM chunks")
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok(Entry {
+                        id: row.get(0)?,
+                        path: PathBuf::from(row.get::<_, String>(1)?),
+                        vector: decode(&row.get::<_, Vec<u8>>(2)?),
+                    })
+                })
+                .map_err(db_error)?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(db_error)?
+        };
+        Ok(VectorStore { conn, entries })
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn file_hash(&self, path: &Path) -> Result<Option<String>, String> {
+        self.conn
+            .query_row(
+                "SELECT hash FROM files WHERE path = ?1",
+                params![path.to_string_lossy()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    pub fn files(&self) -> Result<Vec<PathBuf>, String> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path FROM files")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(db_error)?;
+        rows.map(|row| row.map(PathBuf::from))
+            .collect::<Result<_, _>>()
+            .map_err(db_error)
+    }
+
+    /// Replaces everything stored for `path` with `chunks`.
+    pub fn replace_file(
+        &mut self,
+        path: &Path,
+        hash: &str,
+        chunks: Vec<(Chunk, Vec<f32>)>,
+    ) -> Result<(), String> {
+        let key = path.to_string_lossy().to_string();
+        let tx = self.conn.transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM chunks WHERE path = ?1", params![key])
+            .map_err(db_error)?;
+        let mut added = Vec::with_capacity(chunks.len());
+        for (chunk, vector) in chunks {
+            let vector = normalize(vector);
+            tx.execute(
+                "INSERT INTO chunks (path, start_line, end_line, text, vector)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    key,
+                    chunk.start_line as i64,
+                    chunk.end_line as i64,
+                    chunk.text,
+                    encode(&vector)
+                ],
+            )
+            .map_err(db_error)?;
+            added.push(Entry {
+                id: tx.last_insert_rowid(),
+                path: path.to_path_buf(),
+                vector,
+            });
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO files (path, hash) VALUES (?1, ?2)",
+            params![key, hash],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.extend(added);
+        Ok(())
+    }
+
+    /// Drops `path` and, if it was a directory, everything below it.
+    pub fn remove_under(&mut self, path: &Path) -> Result<(), String> {
+        let removed: Vec<PathBuf> = self
+            .files()?
+            .into_iter()
+            .filter(|file| file.starts_with(path))
+            .collect();
+        if removed.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.transaction().map_err(db_error)?;
+        for file in &removed {
+            let key = file.to_string_lossy();
+            tx.execute("DELETE FROM chunks WHERE path = ?1", params![key])
+                .map_err(db_error)?;
+            tx.execute("DELETE FROM files WHERE path = ?1", params![key])
+                .map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)?;
+        self.entries.retain(|entry| !entry.path.starts_with(path));
+        Ok(())
+    }
+
+    /// The `k` chunks most similar to `query`, best first, with their
+    /// cosine similarity.
+    pub fn search(&self, query: Vec<f32>, k: usize) -> Result<Vec<(f32, StoredChunk)>, String> {
+        let query = normalize(query);
+        let mut scored: Vec<(f32, i64)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.vector.len() == query.len())
+            .map(|entry| {
+                let score: f32 = entry.vector.iter().zip(&query).map(|(a, b)| a * b).sum();
+                (score, entry.id)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(k);
+
+        let mut statement = self
+            .conn
+            .prepare("SELECT path, start_line, end_line, text FROM chunks WHERE id = ?1")
+            .map_err(db_error)?;
+        scored
+            .into_iter()
+            .map(|(score, id)| {
+                statement
+                    .query_row(params![id], |row| {
+                        Ok(StoredChunk {
+                            path: PathBuf::from(row.get::<_, String>(0)?),
+                            start_line: row.get::<_, i64>(1)? as usize,
+                            end_line: row.get::<_, i64>(2)? as usize,
+                            text: row.get(3)?,
+                        })
+                    })
+                    .map(|chunk| (score, chunk))
+                    .map_err(db_error)
+            })
+            .collect()
+    }
+}
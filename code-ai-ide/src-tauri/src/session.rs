@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::git;
+use crate::metadata::to_millis;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const SESSIONS_DIR: &str = "workspace_sessions";
+
+/// An editor tab. Positions are zero-based, as the editor reports them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenFile {
+    pub path: String,
+    pub line: u32,
+    pub column: u32,
+    /// First visible line.
+    #[serde(default)]
+    pub scroll_line: u32,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Editor group the tab is in, for split layouts.
+    #[serde(default)]
+    pub group: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalSession {
+    pub cwd: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub shell: Option<String>,
+}
+
+/// Where the user left a workspace. Only the frontend knows the editor
+/// and panel state, so it sends it; the branch is read here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSession {
+    #[serde(default)]
+    pub open_files: Vec<OpenFile>,
+    #[serde(default)]
+    pub active_file: Option<String>,
+    /// Sizes and visibility of the panels, stored as the frontend gives it.
+    #[serde(default)]
+    pub layout: Value,
+    #[serde(default)]
+    pub terminals: Vec<TerminalSession>,
+    /// Filled in on save.
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredSession {
+    #[serde(flatten)]
+    pub session: WorkspaceSession,
+    /// Open files that no longer exist; they are left out of `open_files`.
+    pub missing_files: Vec<String>,
+    /// The branch checked out now. It is never switched back on restore;
+    /// when it differs from `branch`, the frontend can offer to.
+    pub current_branch: Option<String>,
+}
+
+/// One file per workspace, named by a hash of its root.
+fn session_path(app: &AppHandle, workspace: &Path) -> Result<PathBuf, String> {
+    let hash = blake3::hash(workspace.to_string_lossy().as_bytes());
+    let name = format!("{}/{}.json", SESSIONS_DIR, &hash.to_hex()[..32]);
+    app_data::app_data_path(app, &name)
+}
+
+/// The short name of the checked-out branch; `None` outside a repository
+/// or on a detached HEAD.
+fn current_branch(workspace: &Path) -> Option<String> {
+    let branch = git::run(workspace, &["symbolic-ref", "--quiet", "--short", "HEAD"]).ok()?;
+    Some(branch.trim_end().to_string()).filter(|branch| !branch.is_empty())
+}
+
+/// Saves `session` for the window's active workspace, replacing what was
+/// saved before. Called by the frontend when the window closes or the
+/// workspace changes.
+#[tauri::command]
+pub async fn save_session(
+    session: WorkspaceSession,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let workspace = windows
+        .scope(window.label())
+        .workspace
+        .active()
+        .ok_or_else(|| "No workspace is open".to_string())?;
+    let path = session_path(&app, &workspace)?;
+    let session = WorkspaceSession {
+        branch: current_branch(&workspace),
+        saved_at: to_millis(SystemTime::now()).unwrap_or(0),
+        ..session
+    };
+    app_data::save_json(&path, &session)
+}
+
+/// The session last saved for `workspace`, one of the window's roots
+/// (default: the active one), `None` if there is none. Files that were
+/// deleted since are reported in `missing_files` and terminals whose
+/// directory is gone start in the workspace root.
+#[tauri::command]
+pub async fn restore_session(
+    workspace: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<RestoredSession>, String> {
+    let scope = windows.scope(window.label());
+    let workspace = match workspace {
+        Some(workspace) => {
+            let path = workspace::authorize(&windows, &window, &workspace)?;
+            if !scope.workspace.roots().contains(&path) {
+                return Err(format!("{} is not a workspace root", path.display()));
+            }
+            path
+        }
+        None => scope
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string())?,
+    };
+    let path = session_path(&app, &workspace)?;
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let mut session: WorkspaceSession = serde_json::from_str(&text)
+        .map_err(|e| format!("Failed to read session for {}: {}", workspace.display(), e))?;
+
+    let (open_files, missing): (Vec<OpenFile>, Vec<OpenFile>) = session
+        .open_files
+        .into_iter()
+        .partition(|file| Path::new(&file.path).is_file());
+    session.open_files = open_files;
+    let missing_files: Vec<String> = missing.into_iter().map(|file| file.path).collect();
+    if session
+        .active_file
+        .as_ref()
+        .is_some_and(|active| missing_files.contains(active))
+    {
+        session.active_file = session.open_files.first().map(|file| file.path.clone());
+    }
+    for terminal in &mut session.terminals {
+        if !Path::new(&terminal.cwd).is_dir() {
+            terminal.cwd = workspace.to_string_lossy().to_string();
+        }
+    }
+    Ok(Some(RestoredSession {
+        session,
+        missing_files,
+        current_branch: current_branch(&workspace),
+    }))
+}
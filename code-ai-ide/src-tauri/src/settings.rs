@@ -0,0 +1,600 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::window_state::WindowRegistry;
+
+const USER_SETTINGS_FILE: &str = "settings.json";
+/// Relative to the workspace root.
+const WORKSPACE_SETTINGS_FILE: &str = ".codeai/settings.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsTarget {
+    User,
+    Workspace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingType {
+    Boolean,
+    Integer { min: i64, max: i64 },
+    String,
+    Enum { values: &'static [&'static str] },
+    StringArray,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingSchema {
+    pub key: &'static str,
+    #[serde(flatten)]
+    pub kind: SettingType,
+    pub default: Value,
+    pub description: &'static str,
+}
+
+/// Emitted to every window as `settings-changed`; windows re-read what they
+/// need, since the effective value depends on each window's workspace.
+#[derive(Debug, Clone, Serialize)]
+struct SettingsChanged {
+    key: String,
+    target: SettingsTarget,
+    /// The workspace root whose settings changed, for workspace targets.
+    workspace: Option<String>,
+}
+
+fn schema() -> &'static [SettingSchema] {
+    static SCHEMA: OnceLock<Vec<SettingSchema>> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let setting = |key, kind, default, description| SettingSchema {
+            key,
+            kind,
+            default,
+            description,
+        };
+        vec![
+            setting(
+                "editor.tabSize",
+                SettingType::Integer { min: 1, max: 16 },
+                json!(4),
+                "Columns per indentation level.",
+            ),
+            setting(
+                "editor.insertSpaces",
+                SettingType::Boolean,
+                json!(true),
+                "Indent with spaces instead of tabs.",
+            ),
+            setting(
+                "editor.fontSize",
+                SettingType::Integer { min: 6, max: 72 },
+                json!(14),
+                "Editor font size in pixels.",
+            ),
+            setting(
+                "editor.fontFamily",
+                SettingType::String,
+                json!(""),
+                "Editor font; empty uses the theme's monospace font.",
+            ),
+            setting(
+                "editor.wordWrap",
+                SettingType::Enum {
+                    values: &["off", "on", "bounded"],
+                },
+                json!("off"),
+                "How long lines wrap.",
+            ),
+            setting(
+                "editor.formatOnSave",
+                SettingType::Boolean,
+                json!(false),
+                "Run the language's formatter when saving.",
+            ),
+            setting(
+                "files.autoSave",
+                SettingType::Enum {
+                    values: &["off", "afterDelay", "onFocusChange"],
+                },
+                json!("off"),
+                "When dirty buffers are saved without asking.",
+            ),
+            setting(
+                "files.autoSaveDelay",
+                SettingType::Integer {
+                    min: 100,
+                    max: 60_000,
+                },
+                json!(1000),
+                "Idle milliseconds before an auto-save.",
+            ),
+            setting(
+                "files.encoding",
+                SettingType::String,
+                json!("utf-8"),
+                "Encoding for new files.",
+            ),
+            setting(
+                "files.eol",
+                SettingType::Enum {
+                    values: &["auto", "lf", "crlf"],
+                },
+                json!("auto"),
+                "Line ending for new files.",
+            ),
+            setting(
+                "files.exclude",
+                SettingType::StringArray,
+                json!([]),
+                "Extra globs hidden from the explorer and search.",
+            ),
+            setting(
+                "workbench.colorTheme",
+                SettingType::String,
+                json!("dark"),
+                "Colour theme id.",
+            ),
+            setting(
+                "terminal.shell",
+                SettingType::String,
+                json!(""),
+                "Shell for new terminals; empty uses the login shell.",
+            ),
+            setting(
+                "ai.provider",
+                SettingType::Enum {
+                    values: &[
+                        "openai",
+                        "anthropic",
+                        "openai_compatible",
+                        "ollama",
+                        "llama_cpp",
+                    ],
+                },
+                json!("openai"),
+                "Which API chat requests go to.",
+            ),
+            setting(
+                "ai.model",
+                SettingType::String,
+                json!(""),
+                "Model id used when a request names none.",
+            ),
+            setting(
+                "ai.baseUrl",
+                SettingType::String,
+                json!(""),
+                "Endpoint of the OpenAI-compatible server, e.g. http://localhost:8000/v1.",
+            ),
+            setting(
+                "ai.fallback.provider",
+                SettingType::Enum {
+                    values: &[
+                        "none",
+                        "openai",
+                        "anthropic",
+                        "openai_compatible",
+                        "ollama",
+                        "llama_cpp",
+                    ],
+                },
+                json!("none"),
+                "Where chat requests go when ai.provider is rate limited, overloaded or unreachable.",
+            ),
+            setting(
+                "ai.fallback.model",
+                SettingType::String,
+                json!(""),
+                "Model id used with ai.fallback.provider.",
+            ),
+            setting(
+                "ai.retry.maxRetries",
+                SettingType::Integer { min: 0, max: 10 },
+                json!(3),
+                "How many times a rate-limited or failed AI request is retried.",
+            ),
+            setting(
+                "ai.retry.initialDelayMs",
+                SettingType::Integer {
+                    min: 100,
+                    max: 30_000,
+                },
+                json!(1000),
+                "Wait before the first retry; it doubles for each one after.",
+            ),
+            setting(
+                "ai.maxConcurrentRequests",
+                SettingType::Integer { min: 1, max: 64 },
+                json!(4),
+                "Chat requests in flight to each AI provider at once; more wait their turn.",
+            ),
+            setting(
+                "ai.auditLog.enabled",
+                SettingType::Boolean,
+                json!(false),
+                "Log provider, model, tokens, duration and files of every AI request, never its text, for usage and cost tracking.",
+            ),
+            setting(
+                "ai.auditLog.retentionDays",
+                SettingType::Integer { min: 0, max: 3650 },
+                json!(90),
+                "Days AI request log entries are kept; 0 keeps them forever.",
+            ),
+            setting(
+                "ai.embeddings.provider",
+                SettingType::Enum {
+                    values: &["openai", "openai_compatible", "ollama", "llama_cpp"],
+                },
+                json!("ollama"),
+                "Which API computes embeddings for semantic search.",
+            ),
+            setting(
+                "ai.embeddings.model",
+                SettingType::String,
+                json!(""),
+                "Embedding model; empty uses the provider's usual one.",
+            ),
+            setting(
+                "ai.ollama.url",
+                SettingType::String,
+                json!("http://localhost:11434"),
+                "Address of the local Ollama server.",
+            ),
+            setting(
+                "ai.llamaCpp.url",
+                SettingType::String,
+                json!("http://localhost:8080"),
+                "Address of the local llama.cpp server.",
+            ),
+            setting(
+                "ai.inlineCompletion.enabled",
+                SettingType::Boolean,
+                json!(true),
+                "Suggest completions inline while typing.",
+            ),
+            setting(
+                "ai.inlineCompletion.disabledLanguages",
+                SettingType::StringArray,
+                json!([]),
+                "Language ids without inline completions.",
+            ),
+            setting(
+                "ai.inlineCompletion.model",
+                SettingType::String,
+                json!(""),
+                "Fill-in-the-middle model for inline completions; empty uses ai.model.",
+            ),
+            setting(
+                "ai.inlineCompletion.debounceMs",
+                SettingType::Integer { min: 0, max: 2000 },
+                json!(150),
+                "How long typing must pause before a completion is requested.",
+            ),
+            setting(
+                "ai.budget.requestTokens",
+                SettingType::Integer {
+                    min: 0,
+                    max: 10_000_000,
+                },
+                json!(0),
+                "Most input tokens one chat request may send; 0 uses the model's context window.",
+            ),
+            setting(
+                "ai.budget.sessionTokens",
+                SettingType::Integer {
+                    min: 0,
+                    max: 1_000_000_000,
+                },
+                json!(0),
+                "Most tokens a chat session may use in total; 0 for no limit.",
+            ),
+        ]
+    })
+}
+
+fn schema_for(key: &str) -> Result<&'static SettingSchema, String> {
+    schema()
+        .iter()
+        .find(|setting| setting.key == key)
+        .ok_or_else(|| format!("Unknown setting: {}", key))
+}
+
+fn validate(setting: &SettingSchema, value: &Value) -> Result<(), String> {
+    let valid = match &setting.kind {
+        SettingType::Boolean => value.is_boolean(),
+        SettingType::Integer { min, max } => {
+            value.as_i64().is_some_and(|n| (*min..=*max).contains(&n))
+        }
+        SettingType::String => value.is_string(),
+        SettingType::Enum { values } => value.as_str().is_some_and(|v| values.contains(&v)),
+        SettingType::StringArray => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(Value::is_string)),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid value for {}: expected {}",
+            setting.key,
+            match &setting.kind {
+                SettingType::Boolean => "true or false".to_string(),
+                SettingType::Integer { min, max } => format!("an integer from {} to {}", min, max),
+                SettingType::String => "a string".to_string(),
+                SettingType::Enum { values } => format!("one of {}", values.join(", ")),
+                SettingType::StringArray => "a list of strings".to_string(),
+            }
+        ))
+    }
+}
+
+/// User-level settings, cached after the first read.
+#[derive(Default)]
+pub struct SettingsStore {
+    user: Mutex<Option<(PathBuf, Map<String, Value>)>>,
+}
+
+impl SettingsStore {
+    fn with_user<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut Map<String, Value>) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.user.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_config_path(app, USER_SETTINGS_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+}
+
+/// The user-level value of `key`, or its default, ignoring the workspace.
+/// For settings a workspace must not override, like where requests that
+/// carry API keys are sent.
+pub fn user_setting(app: &AppHandle, key: &str) -> Result<Value, String> {
+    let setting = schema_for(key)?;
+    app.state::<SettingsStore>().with_user(app, |user| {
+        let value = user
+            .get(key)
+            .filter(|value| validate(setting, value).is_ok())
+            .cloned()
+            .unwrap_or_else(|| setting.default.clone());
+        (value, false)
+    })
+}
+
+/// The effective value of `key` for the workspace of `window`.
+pub fn window_setting(app: &AppHandle, window: &Window, key: &str) -> Result<Value, String> {
+    schema_for(key)?;
+    let root = active_root(window, &app.state::<WindowRegistry>());
+    let mut settings = effective(app, &app.state::<SettingsStore>(), root.as_deref())?;
+    Ok(settings.remove(key).unwrap_or(Value::Null))
+}
+
+/// Removes `key` from the user settings file once `keep` has stored its
+/// value elsewhere; the file is left alone if `keep` fails. For settings
+/// that moved out of the file. Returns the removed value.
+pub fn take_user_setting(
+    app: &AppHandle,
+    key: &str,
+    keep: impl FnOnce(&Value) -> Result<(), String>,
+) -> Result<Option<Value>, String> {
+    app.state::<SettingsStore>().with_user(app, |user| {
+        let Some(value) = user.get(key) else {
+            return (Ok(None), false);
+        };
+        match keep(value) {
+            Ok(()) => (Ok(user.remove(key)), true),
+            Err(e) => (Err(e), false),
+        }
+    })?
+}
+
+fn read_workspace(root: &Path) -> Result<Map<String, Value>, String> {
+    match fs::read_to_string(root.join(WORKSPACE_SETTINGS_FILE)) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid {}: {}", WORKSPACE_SETTINGS_FILE, e)),
+        Err(_) => Ok(Map::new()),
+    }
+}
+
+fn write_workspace(root: &Path, settings: &Map<String, Value>) -> Result<(), String> {
+    let path = root.join(WORKSPACE_SETTINGS_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    app_data::save_json(&path, settings)
+}
+
+/// Checks `value` against the schema of `key`, as `set_setting` does.
+pub(crate) fn check_value(key: &str, value: &Value) -> Result<(), String> {
+    validate(schema_for(key)?, value)
+}
+
+/// What the workspace settings file of `root` sets.
+pub(crate) fn workspace_settings(root: &Path) -> Result<Map<String, Value>, String> {
+    read_workspace(root)
+}
+
+/// Adds `values` to the workspace settings of `root`, keeping any value the
+/// file already has. Returns the keys added.
+pub(crate) fn add_workspace_settings(
+    app: &AppHandle,
+    root: &Path,
+    values: Map<String, Value>,
+) -> Result<Vec<String>, String> {
+    let mut settings = read_workspace(root)?;
+    let mut added = Vec::new();
+    for (key, value) in values {
+        if settings.contains_key(&key) {
+            continue;
+        }
+        check_value(&key, &value)?;
+        settings.insert(key.clone(), value);
+        added.push(key);
+    }
+    if added.is_empty() {
+        return Ok(added);
+    }
+    write_workspace(root, &settings)?;
+    for key in &added {
+        let _ = app.emit_all(
+            "settings-changed",
+            SettingsChanged {
+                key: key.clone(),
+                target: SettingsTarget::Workspace,
+                workspace: Some(root.to_string_lossy().to_string()),
+            },
+        );
+    }
+    Ok(added)
+}
+
+fn layer(
+    target: SettingsTarget,
+    app: &AppHandle,
+    store: &SettingsStore,
+    root: Option<&Path>,
+) -> Result<Map<String, Value>, String> {
+    match (target, root) {
+        (SettingsTarget::User, _) => store.with_user(app, |user| (user.clone(), false)),
+        (SettingsTarget::Workspace, Some(root)) => read_workspace(root),
+        (SettingsTarget::Workspace, None) => Ok(Map::new()),
+    }
+}
+
+/// Defaults, overridden by user settings, overridden by the workspace's.
+/// Values that fail validation are skipped, so a hand-edited file with a
+/// typo falls back instead of breaking the editor.
+fn effective(
+    app: &AppHandle,
+    store: &SettingsStore,
+    root: Option<&Path>,
+) -> Result<Map<String, Value>, String> {
+    let user = layer(SettingsTarget::User, app, store, root)?;
+    let workspace = layer(SettingsTarget::Workspace, app, store, root)?;
+    Ok(schema()
+        .iter()
+        .map(|setting| {
+            let value = [&workspace, &user]
+                .into_iter()
+                .filter_map(|layer| layer.get(setting.key))
+                .find(|value| validate(setting, value).is_ok())
+                .cloned()
+                .unwrap_or_else(|| setting.default.clone());
+            (setting.key.to_string(), value)
+        })
+        .collect())
+}
+
+fn active_root(window: &Window, windows: &WindowRegistry) -> Option<PathBuf> {
+    windows.scope(window.label()).workspace.active()
+}
+
+#[tauri::command]
+pub async fn get_settings_schema() -> Result<Vec<SettingSchema>, String> {
+    Ok(schema().to_vec())
+}
+
+/// Every setting with its effective value for this window.
+#[tauri::command]
+pub async fn get_settings(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    store: State<'_, SettingsStore>,
+) -> Result<Map<String, Value>, String> {
+    effective(&app, &store, active_root(&window, &windows).as_deref())
+}
+
+/// The effective value of `key`, or with `target` just what that layer
+/// sets (`null` when it sets nothing).
+#[tauri::command]
+pub async fn get_setting(
+    key: String,
+    target: Option<SettingsTarget>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    store: State<'_, SettingsStore>,
+) -> Result<Value, String> {
+    schema_for(&key)?;
+    let root = active_root(&window, &windows);
+    let mut settings = match target {
+        Some(target) => layer(target, &app, &store, root.as_deref())?,
+        None => effective(&app, &store, root.as_deref())?,
+    };
+    Ok(settings.remove(&key).unwrap_or(Value::Null))
+}
+
+/// Sets `key` in the user or workspace layer; `null` removes it there.
+#[tauri::command]
+pub async fn set_setting(
+    key: String,
+    value: Value,
+    target: SettingsTarget,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    store: State<'_, SettingsStore>,
+) -> Result<(), String> {
+    let setting = schema_for(&key)?;
+    if !value.is_null() {
+        validate(setting, &value)?;
+    }
+    let update = |settings: &mut Map<String, Value>| {
+        let previous = if value.is_null() {
+            settings.remove(&key)
+        } else {
+            settings.insert(key.clone(), value.clone())
+        };
+        previous.as_ref() != Some(&value) && !(previous.is_none() && value.is_null())
+    };
+
+    let root = active_root(&window, &windows);
+    let changed = match target {
+        SettingsTarget::User => store.with_user(&app, |user| {
+            let changed = update(user);
+            (changed, changed)
+        })?,
+        SettingsTarget::Workspace => {
+            let root = root
+                .as_deref()
+                .ok_or_else(|| "No workspace is open".to_string())?;
+            let mut settings = read_workspace(root)?;
+            let changed = update(&mut settings);
+            if changed {
+                write_workspace(root, &settings)?;
+            }
+            changed
+        }
+    };
+
+    if changed {
+        let _ = app.emit_all(
+            "settings-changed",
+            SettingsChanged {
+                key,
+                target,
+                workspace: match target {
+                    SettingsTarget::User => None,
+                    SettingsTarget::Workspace => root.map(|r| r.to_string_lossy().to_string()),
+                },
+            },
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State, Window};
+
+use crate::app_data;
+use crate::jsonc::read_jsonc;
+use crate::window_state::WindowRegistry;
+
+/// In the app config directory.
+const USER_SNIPPETS_DIR: &str = "snippets";
+/// Relative to the workspace root.
+const WORKSPACE_SNIPPETS_DIR: &str = ".codeai/snippets";
+/// Snippets for every language, or for those in their `scope`.
+const GLOBAL_EXTENSION: &str = "code-snippets";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetSource {
+    User,
+    Workspace,
+}
+
+/// A snippet as written in VS Code snippet files. `prefix` and `body` may
+/// be a string or a list; body lines are joined with newlines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetDefinition {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub prefix: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub body: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Comma-separated language ids, for global snippet files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TabStop {
+    pub index: u32,
+    pub placeholder: Option<String>,
+    pub choices: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    pub name: String,
+    pub prefix: Vec<String>,
+    pub body: String,
+    pub description: Option<String>,
+    pub source: SnippetSource,
+    pub file: String,
+    /// In visiting order, `$0` last.
+    pub tab_stops: Vec<TabStop>,
+    /// Variables such as `TM_FILENAME` the body refers to.
+    pub variables: Vec<String>,
+}
+
+pub(crate) fn one_or_many<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+/// Index of the `}` closing the `${` at `start`, allowing nesting.
+fn closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Tab stops and variables of a snippet body in TextMate syntax: `$1`,
+/// `${1:default}`, `${1|a,b|}`, `$NAME` and `${NAME:default}`.
+fn analyze(body: &str) -> (Vec<TabStop>, Vec<String>) {
+    let chars: Vec<char> = body.chars().collect();
+    let mut stops: Vec<TabStop> = Vec::new();
+    let mut variables: Vec<String> = Vec::new();
+    let mut scan = vec![(0, chars.len())];
+    while let Some((mut i, end)) = scan.pop() {
+        while i < end {
+            if chars[i] == '\\' {
+                i += 2;
+                continue;
+            }
+            if chars[i] != '$' || i + 1 >= end {
+                i += 1;
+                continue;
+            }
+            let braced = chars[i + 1] == '{';
+            let name_start = if braced { i + 2 } else { i + 1 };
+            let mut name_end = name_start;
+            while name_end < end
+                && (chars[name_end].is_ascii_alphanumeric() || chars[name_end] == '_')
+            {
+                name_end += 1;
+            }
+            let name: String = chars[name_start..name_end].iter().collect();
+            let close = if braced {
+                closing_brace(&chars, i + 1).filter(|&c| c < end)
+            } else {
+                None
+            };
+            if name.is_empty() || (braced && close.is_none()) {
+                i += 1;
+                continue;
+            }
+            // Text between the name and the closing brace.
+            let (inner_start, next) = match close {
+                Some(close) => (name_end, close + 1),
+                None => (name_end, name_end),
+            };
+            let inner: String = match close {
+                Some(close) => chars[inner_start..close].iter().collect(),
+                None => String::new(),
+            };
+
+            if let Ok(index) = name.parse::<u32>() {
+                let (placeholder, choices) = if let Some(default) = inner.strip_prefix(':') {
+                    // Placeholders may nest further tab stops.
+                    scan.push((inner_start + 1, next - 1));
+                    (Some(default.to_string()), Vec::new())
+                } else if let Some(list) = inner.strip_prefix('|').and_then(|l| l.strip_suffix('|'))
+                {
+                    (
+                        None,
+                        list.split(',').map(|c| c.replace("\\,", ",")).collect(),
+                    )
+                } else {
+                    (None, Vec::new())
+                };
+                match stops.iter_mut().find(|stop| stop.index == index) {
+                    Some(stop) => {
+                        if stop.placeholder.is_none() {
+                            stop.placeholder = placeholder;
+                        }
+                        if stop.choices.is_empty() {
+                            stop.choices = choices;
+                        }
+                    }
+                    None => stops.push(TabStop {
+                        index,
+                        placeholder,
+                        choices,
+                    }),
+                }
+            } else if name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+            {
+                if !variables.contains(&name) {
+                    variables.push(name);
+                }
+                if inner.starts_with(':') {
+                    scan.push((inner_start + 1, next - 1));
+                }
+            }
+            i = next.max(i + 1);
+        }
+    }
+    // $0 is the final cursor position, after every other stop.
+    stops.sort_by_key(|stop| {
+        if stop.index == 0 {
+            u32::MAX
+        } else {
+            stop.index
+        }
+    });
+    (stops, variables)
+}
+
+fn applies_to(file: &Path, definition: &SnippetDefinition, language: &str) -> bool {
+    let global = file.extension().is_some_and(|ext| ext == GLOBAL_EXTENSION);
+    if !global {
+        return file.file_stem().is_some_and(|stem| stem == language);
+    }
+    match &definition.scope {
+        Some(scope) => scope.split(',').any(|id| id.trim() == language),
+        None => true,
+    }
+}
+
+fn read_file(path: &Path) -> Result<Map<String, Value>, String> {
+    match read_jsonc(path)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(format!("{} is not a JSON object", path.display())),
+    }
+}
+
+fn snippets_in(dir: &Path, source: SnippetSource, language: &str) -> Vec<Snippet> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    files.sort();
+
+    let mut snippets = Vec::new();
+    for file in files {
+        // Unreadable files are skipped so one typo does not hide the rest.
+        let Ok(entries) = read_file(&file) else {
+            continue;
+        };
+        for (name, value) in entries {
+            let Ok(definition) = serde_json::from_value::<SnippetDefinition>(value) else {
+                continue;
+            };
+            if !applies_to(&file, &definition, language) {
+                continue;
+            }
+            let body = definition.body.join("\n");
+            let (tab_stops, variables) = analyze(&body);
+            snippets.push(Snippet {
+                name,
+                prefix: definition.prefix,
+                body,
+                description: definition.description,
+                source,
+                file: file.to_string_lossy().to_string(),
+                tab_stops,
+                variables,
+            });
+        }
+    }
+    snippets
+}
+
+fn snippets_dir(
+    source: SnippetSource,
+    app: &AppHandle,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<PathBuf, String> {
+    match source {
+        SnippetSource::User => app_data::app_config_path(app, USER_SNIPPETS_DIR),
+        SnippetSource::Workspace => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .map(|root| root.join(WORKSPACE_SNIPPETS_DIR))
+            .ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+/// `<language>.json`, or the global file when no language is given.
+fn snippet_file(dir: &Path, language: Option<&str>) -> Result<PathBuf, String> {
+    let name = match language {
+        Some(language) if !language.is_empty() && !language.contains(['/', '\\', '.']) => {
+            format!("{}.json", language)
+        }
+        Some(language) => return Err(format!("Invalid language id: {}", language)),
+        None => format!("global.{}", GLOBAL_EXTENSION),
+    };
+    Ok(dir.join(name))
+}
+
+/// Every snippet for `language`, user ones first and then the workspace's,
+/// which win when the completion provider finds the same prefix in both.
+#[tauri::command]
+pub async fn list_snippets(
+    language: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<Snippet>, String> {
+    let mut snippets = Vec::new();
+    for source in [SnippetSource::User, SnippetSource::Workspace] {
+        if let Ok(dir) = snippets_dir(source, &app, &window, &windows) {
+            snippets.extend(snippets_in(&dir, source, &language));
+        }
+    }
+    Ok(snippets)
+}
+
+/// Creates or replaces the snippet `name` in the user's or workspace's
+/// file for `language` (the global file when omitted). The file is
+/// rewritten as plain JSON, so comments in it are lost.
+#[tauri::command]
+pub async fn save_snippet(
+    source: SnippetSource,
+    language: Option<String>,
+    name: String,
+    snippet: SnippetDefinition,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Snippet name must not be empty".to_string());
+    }
+    if snippet.prefix.is_empty() {
+        return Err("Snippet needs at least one prefix".to_string());
+    }
+    let dir = snippets_dir(source, &app, &window, &windows)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = snippet_file(&dir, language.as_deref())?;
+    let mut entries = if path.exists() {
+        read_file(&path)?
+    } else {
+        Map::new()
+    };
+    let value = serde_json::to_value(&snippet).map_err(|e| e.to_string())?;
+    entries.insert(name, value);
+    app_data::save_json(&path, &entries)
+}
+
+#[tauri::command]
+pub async fn delete_snippet(
+    source: SnippetSource,
+    language: Option<String>,
+    name: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let dir = snippets_dir(source, &app, &window, &windows)?;
+    let path = snippet_file(&dir, language.as_deref())?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    let mut entries = read_file(&path)?;
+    if entries.remove(&name).is_none() {
+        return Ok(false);
+    }
+    app_data::save_json(&path, &entries)?;
+    Ok(true)
+}
@@ -0,0 +1,507 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{State, Window};
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Tree};
+
+use crate::documents::{document_key, Position, Range};
+use crate::encoding;
+use crate::language;
+use crate::metadata::LARGE_FILE_THRESHOLD;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Module,
+    Class,
+    Object,
+    Method,
+    Property,
+    Field,
+    Enum,
+    EnumMember,
+    Interface,
+    Struct,
+    Function,
+    Variable,
+    Constant,
+    TypeAlias,
+    Macro,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole declaration, including its body.
+    pub range: Range,
+    /// Just the name, for reveal and highlight.
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FoldingKind {
+    Comment,
+    Imports,
+}
+
+/// Lines `start_line + 1..=end_line` are hidden when folded.
+#[derive(Debug, Clone, Serialize)]
+pub struct FoldingRange {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub kind: Option<FoldingKind>,
+}
+
+struct CachedTree {
+    version: u64,
+    language: String,
+    content: String,
+    tree: Tree,
+}
+
+/// Syntax trees of open documents, kept so an edit only re-parses what it
+/// touched.
+#[derive(Default)]
+pub struct SyntaxTrees {
+    trees: Mutex<HashMap<String, CachedTree>>,
+}
+
+impl SyntaxTrees {
+    pub fn forget(&self, path: &str) {
+        self.trees.lock().unwrap().remove(path);
+    }
+
+    pub fn clear(&self) {
+        self.trees.lock().unwrap().clear();
+    }
+
+    /// Parses the open document at `key`, starting from the previous tree
+    /// when there is one for the same language.
+    fn parse_document(
+        &self,
+        key: &str,
+        version: u64,
+        language: &str,
+        content: &str,
+        grammar: &Language,
+    ) -> Result<Tree, String> {
+        let mut trees = self.trees.lock().unwrap();
+        if let Some(cached) = trees.get_mut(key).filter(|c| c.language == language) {
+            if cached.version == version {
+                return Ok(cached.tree.clone());
+            }
+            cached.tree.edit(&input_edit(&cached.content, content));
+            let tree = parse(grammar, content, Some(&cached.tree))?;
+            cached.version = version;
+            cached.content = content.to_string();
+            cached.tree = tree.clone();
+            return Ok(tree);
+        }
+        let tree = parse(grammar, content, None)?;
+        trees.insert(
+            key.to_string(),
+            CachedTree {
+                version,
+                language: language.to_string(),
+                content: content.to_string(),
+                tree: tree.clone(),
+            },
+        );
+        Ok(tree)
+    }
+}
+
+/// The bundled grammar for a language id. TSX is told apart by extension,
+/// since `.tsx` files report plain `typescript`.
+pub(crate) fn grammar(language: &str, path: Option<&Path>) -> Option<Language> {
+    let tsx = path
+        .and_then(|p| p.extension())
+        .is_some_and(|ext| ext == "tsx");
+    Some(match language {
+        "rust" => tree_sitter_rust::language(),
+        "javascript" | "javascriptreact" => tree_sitter_javascript::language(),
+        "typescript" if tsx => tree_sitter_typescript::language_tsx(),
+        "typescript" => tree_sitter_typescript::language_typescript(),
+        "typescriptreact" => tree_sitter_typescript::language_tsx(),
+        "python" => tree_sitter_python::language(),
+        "go" => tree_sitter_go::language(),
+        "json" => tree_sitter_json::language(),
+        _ => return None,
+    })
+}
+
+pub(crate) fn parse(
+    grammar: &Language,
+    content: &str,
+    old_tree: Option<&Tree>,
+) -> Result<Tree, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(grammar)
+        .map_err(|e| format!("Failed to load grammar: {}", e))?;
+    parser
+        .parse(content, old_tree)
+        .ok_or_else(|| "Parsing was cancelled".to_string())
+}
+
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    let row = before.matches('\n').count();
+    let column = byte - before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Point { row, column }
+}
+
+/// Describes the change from `old` to `new` as one replaced span, found by
+/// trimming the common prefix and suffix.
+fn input_edit(old: &str, new: &str) -> InputEdit {
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    let old_end = old.len() - suffix;
+    let new_end = new.len() - suffix;
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old, prefix),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    }
+}
+
+struct Source<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Source<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Source { text, line_starts }
+    }
+
+    /// Tree-sitter counts columns in bytes; the editor in UTF-16 units.
+    fn position(&self, point: Point) -> Position {
+        let start = self
+            .line_starts
+            .get(point.row)
+            .copied()
+            .unwrap_or(self.text.len());
+        let line = self.text.get(start..start + point.column).unwrap_or("");
+        Position {
+            line: point.row as u32,
+            character: line.encode_utf16().count() as u32,
+        }
+    }
+
+    fn range(&self, node: Node) -> Range {
+        Range {
+            start: self.position(node.start_position()),
+            end: self.position(node.end_position()),
+        }
+    }
+
+    fn text(&self, node: Node) -> &'a str {
+        &self.text[node.byte_range()]
+    }
+}
+
+fn symbol_kind(language: &str, node: Node) -> Option<SymbolKind> {
+    let kind = match (language, node.kind()) {
+        ("rust", "function_item" | "function_signature_item") => SymbolKind::Function,
+        ("rust", "struct_item" | "union_item") => SymbolKind::Struct,
+        ("rust", "enum_item") => SymbolKind::Enum,
+        ("rust", "enum_variant") => SymbolKind::EnumMember,
+        ("rust", "trait_item") => SymbolKind::Interface,
+        ("rust", "impl_item") => SymbolKind::Object,
+        ("rust", "mod_item") => SymbolKind::Module,
+        ("rust", "const_item" | "static_item") => SymbolKind::Constant,
+        ("rust", "type_item") => SymbolKind::TypeAlias,
+        ("rust", "macro_definition") => SymbolKind::Macro,
+        ("rust", "field_declaration") => SymbolKind::Field,
+        ("python", "function_definition") => SymbolKind::Function,
+        ("python", "class_definition") => SymbolKind::Class,
+        ("go", "function_declaration") => SymbolKind::Function,
+        ("go", "method_declaration") => SymbolKind::Method,
+        ("go", "type_spec") => match node.child_by_field_name("type").map(|t| t.kind()) {
+            Some("struct_type") => SymbolKind::Struct,
+            Some("interface_type") => SymbolKind::Interface,
+            _ => SymbolKind::TypeAlias,
+        },
+        ("go", "const_spec") => SymbolKind::Constant,
+        ("json", "pair") => SymbolKind::Property,
+        (_, "function_declaration" | "generator_function_declaration") => SymbolKind::Function,
+        (_, "class_declaration" | "abstract_class_declaration") => SymbolKind::Class,
+        (_, "method_definition" | "method_signature") => SymbolKind::Method,
+        (_, "public_field_definition" | "field_definition") => SymbolKind::Property,
+        (_, "interface_declaration") => SymbolKind::Interface,
+        (_, "enum_declaration") => SymbolKind::Enum,
+        (_, "type_alias_declaration") => SymbolKind::TypeAlias,
+        (_, "internal_module" | "module") => SymbolKind::Module,
+        (_, "variable_declarator") => {
+            // Functions and classes bound to names count anywhere; other
+            // variables only at the top level, or the outline drowns.
+            match node.child_by_field_name("value").map(|v| v.kind()) {
+                Some("arrow_function" | "function_expression" | "function") => SymbolKind::Function,
+                Some("class") => SymbolKind::Class,
+                _ if is_top_level(node) => SymbolKind::Variable,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Whether a declarator sits in a declaration directly in the program,
+/// possibly behind `export`.
+fn is_top_level(node: Node) -> bool {
+    let mut parent = node.parent().and_then(|declaration| declaration.parent());
+    if parent.is_some_and(|p| p.kind() == "export_statement") {
+        parent = parent.and_then(|p| p.parent());
+    }
+    parent.is_some_and(|p| p.kind() == "program")
+}
+
+fn symbol_name<'a>(node: Node<'a>, source: &Source) -> Option<(String, Node<'a>)> {
+    if node.kind() == "impl_item" {
+        let target = node.child_by_field_name("type")?;
+        let name = match node.child_by_field_name("trait") {
+            Some(trait_) => format!("impl {} for {}", source.text(trait_), source.text(target)),
+            None => format!("impl {}", source.text(target)),
+        };
+        return Some((name, target));
+    }
+    let name_node = node
+        .child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("key"))?;
+    let name = source.text(name_node).trim_matches('"').to_string();
+    Some((name, name_node))
+}
+
+fn collect_symbols(
+    node: Node,
+    language: &str,
+    source: &Source,
+    parent: Option<SymbolKind>,
+    out: &mut Vec<DocumentSymbol>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let symbol =
+            symbol_kind(language, child).and_then(|kind| Some((kind, symbol_name(child, source)?)));
+        match symbol {
+            Some((mut kind, (name, name_node))) => {
+                if kind == SymbolKind::Function
+                    && matches!(
+                        parent,
+                        Some(SymbolKind::Class | SymbolKind::Object | SymbolKind::Interface)
+                    )
+                {
+                    kind = SymbolKind::Method;
+                }
+                let mut children = Vec::new();
+                collect_symbols(child, language, source, Some(kind), &mut children);
+                out.push(DocumentSymbol {
+                    name,
+                    kind,
+                    range: source.range(child),
+                    selection_range: source.range(name_node),
+                    children,
+                });
+            }
+            None => collect_symbols(child, language, source, parent, out),
+        }
+    }
+}
+
+fn is_import(kind: &str) -> bool {
+    matches!(
+        kind,
+        "use_declaration"
+            | "extern_crate_declaration"
+            | "import_statement"
+            | "import_from_statement"
+            | "import_declaration"
+    )
+}
+
+fn collect_folds(node: Node, out: &mut Vec<FoldingRange>) {
+    let start = node.start_position().row;
+    let mut end = node.end_position().row;
+    // Keep a closing bracket on its own line visible.
+    if let Some(last) = node.child(node.child_count().saturating_sub(1)) {
+        if matches!(last.kind(), "}" | "]" | ")") && last.start_position().row > start {
+            end = last.start_position().row - 1;
+        }
+    }
+    if end > start && node.parent().is_some() {
+        let kind = node
+            .kind()
+            .contains("comment")
+            .then_some(FoldingKind::Comment);
+        out.push(FoldingRange {
+            start_line: start as u32,
+            end_line: end as u32,
+            kind,
+        });
+    }
+
+    // Runs of imports fold as one region.
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    let mut i = 0;
+    while i < children.len() {
+        if is_import(children[i].kind()) {
+            let first = i;
+            while i + 1 < children.len() && is_import(children[i + 1].kind()) {
+                i += 1;
+            }
+            let (start, end) = (
+                children[first].start_position().row,
+                children[i].end_position().row,
+            );
+            if i > first && end > start {
+                out.push(FoldingRange {
+                    start_line: start as u32,
+                    end_line: end as u32,
+                    kind: Some(FoldingKind::Imports),
+                });
+            }
+        }
+        collect_folds(children[i], out);
+        i += 1;
+    }
+}
+
+/// Nested nodes often start on the same line (`fn f() {` and its block);
+/// only the widest fold per start line is kept.
+fn dedupe_folds(mut folds: Vec<FoldingRange>) -> Vec<FoldingRange> {
+    folds.sort_by(|a, b| {
+        a.start_line
+            .cmp(&b.start_line)
+            .then(b.end_line.cmp(&a.end_line))
+    });
+    folds.dedup_by_key(|fold| fold.start_line);
+    folds
+}
+
+/// Source text and its tree, from the text given, the open document, or
+/// the file on disk, in that order.
+fn parse_source(
+    path: Option<String>,
+    text: Option<String>,
+    language: Option<String>,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<(String, String, Tree), String> {
+    let path = match path {
+        Some(path) => Some(workspace::authorize(windows, window, &path)?),
+        None => None,
+    };
+    let scope = windows.scope(window.label());
+    let document = path
+        .as_ref()
+        .filter(|_| text.is_none())
+        .and_then(|path| scope.documents.get(&path.to_string_lossy()));
+
+    let content = match (text, &document, &path) {
+        (Some(text), _, _) => text,
+        (None, Some(document), _) => document.content.clone(),
+        (None, None, Some(path)) => {
+            let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            if metadata.len() > LARGE_FILE_THRESHOLD {
+                return Err("File is too large to parse".to_string());
+            }
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            encoding::decode(&bytes).content
+        }
+        (None, None, None) => return Err("Either a path or text is required".to_string()),
+    };
+    let language = match (language, &path) {
+        (Some(language), _) => language,
+        (None, Some(path)) => language::detect_language(path, &content),
+        (None, None) => return Err("A language is required for plain text".to_string()),
+    };
+    let grammar = grammar(&language, path.as_deref())
+        .ok_or_else(|| format!("No grammar for language: {}", language))?;
+
+    let tree = match (&document, &path) {
+        (Some(document), Some(path)) => scope.syntax_trees.parse_document(
+            &document_key(&path.to_string_lossy()),
+            document.version,
+            &language,
+            &content,
+            &grammar,
+        )?,
+        _ => parse(&grammar, &content, None)?,
+    };
+    Ok((content, language, tree))
+}
+
+/// Symbols of a file that is not open, parsed without caching the tree.
+/// `None` when there is no grammar for its language.
+pub fn outline(path: &Path, content: &str) -> Option<Vec<DocumentSymbol>> {
+    let language = language::detect_language(path, content);
+    let tree = parse(&grammar(&language, Some(path))?, content, None).ok()?;
+    let source = Source::new(content);
+    let mut symbols = Vec::new();
+    collect_symbols(tree.root_node(), &language, &source, None, &mut symbols);
+    Some(symbols)
+}
+
+/// Symbol tree of a file or of `text`. An open document is parsed from its
+/// buffer, reusing the previous tree for the unchanged parts.
+#[tauri::command]
+pub async fn get_document_outline(
+    path: Option<String>,
+    text: Option<String>,
+    language: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<DocumentSymbol>, String> {
+    let (content, language, tree) = parse_source(path, text, language, &window, &windows)?;
+    let source = Source::new(&content);
+    let mut symbols = Vec::new();
+    collect_symbols(tree.root_node(), &language, &source, None, &mut symbols);
+    Ok(symbols)
+}
+
+#[tauri::command]
+pub async fn get_folding_ranges(
+    path: Option<String>,
+    text: Option<String>,
+    language: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<FoldingRange>, String> {
+    let (_, _, tree) = parse_source(path, text, language, &window, &windows)?;
+    let mut folds = Vec::new();
+    collect_folds(tree.root_node(), &mut folds);
+    Ok(dedupe_folds(folds))
+}
@@ -0,0 +1,182 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tauri::{State, Window};
+
+use crate::trust;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Extensions the OS runs rather than opens in a viewer when asked to
+/// open them: Windows executables, installers, shortcuts and scripts,
+/// macOS apps and `.command` files, Linux launchers.
+const RUNNABLE_EXTENSIONS: &[&str] = &[
+    "app",
+    "appimage",
+    "application",
+    "bat",
+    "cmd",
+    "com",
+    "command",
+    "cpl",
+    "desktop",
+    "exe",
+    "hta",
+    "jar",
+    "js",
+    "jse",
+    "lnk",
+    "msc",
+    "msi",
+    "msp",
+    "pif",
+    "ps1",
+    "reg",
+    "scr",
+    "tool",
+    "url",
+    "vbe",
+    "vbs",
+    "workflow",
+    "ws",
+    "wsf",
+    "wsh",
+];
+
+/// Starts a helper program without waiting for it; file managers and
+/// default apps keep running long after the command returns.
+fn spawn_detached(program: &str, args: &[&str]) -> Result<(), String> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to launch {}: {}", program, e))
+}
+
+#[cfg(target_os = "macos")]
+fn reveal(path: &Path) -> Result<(), String> {
+    spawn_detached("open", &["-R", &path.to_string_lossy()])
+}
+
+#[cfg(target_os = "windows")]
+fn reveal(path: &Path) -> Result<(), String> {
+    // Explorer wants the flag and path as a single argument.
+    spawn_detached("explorer", &[&format!("/select,{}", path.display())])
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal(path: &Path) -> Result<(), String> {
+    // The FileManager1 D-Bus interface selects the item in Nautilus, Dolphin,
+    // Nemo and friends; without it, open the containing folder instead.
+    let uri = format!("array:string:{}", file_uri(path));
+    let selected = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+            &uri,
+            "string:",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if selected {
+        return Ok(());
+    }
+
+    let folder = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    spawn_detached("xdg-open", &[&folder.to_string_lossy()])
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn open_default(path: &Path) -> Result<(), String> {
+    let path = path.to_string_lossy();
+    if cfg!(target_os = "macos") {
+        spawn_detached("open", &[&path])
+    } else if cfg!(target_os = "windows") {
+        spawn_detached("explorer", &[&path])
+    } else {
+        spawn_detached("xdg-open", &[&path])
+    }
+}
+
+/// Shows the path selected in Explorer/Finder/the desktop file manager.
+#[tauri::command]
+pub async fn reveal_in_file_manager(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    if !path.exists() {
+        return Err(format!("Path does not exist: {}", path.display()));
+    }
+    reveal(&path)
+}
+
+/// Whether opening `path` with the system would run it.
+fn is_runnable(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if extension.is_some_and(|extension| RUNNABLE_EXTENSIONS.contains(&extension.as_str())) {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = path.metadata() {
+            return metadata.is_file() && metadata.permissions().mode() & 0o111 != 0;
+        }
+    }
+    false
+}
+
+/// Opens the path with the application the OS associates with it. Files
+/// that would run rather than open, such as `.exe` or `.app`, are treated
+/// as commands: the workspace must be trusted and the execution policy
+/// must allow them.
+#[tauri::command]
+pub async fn open_with_system(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    let path = workspace::authorize(&windows, &window, &path)?;
+    if !path.exists() {
+        return Err(format!("Path does not exist: {}", path.display()));
+    }
+    if is_runnable(&path) {
+        trust::check_execution(
+            &window,
+            &windows,
+            &path.to_string_lossy(),
+            false,
+            path.parent(),
+        )?;
+    }
+    open_default(&path)
+}
@@ -0,0 +1,670 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tauri::{Manager, State, Window};
+
+use crate::app_data;
+use crate::diagnostics;
+use crate::problem_matcher::{Diagnostic, ProblemMatcher};
+use crate::process::{self, CommandExit, ExecOptions, OutputHook, ProcessHooks, ProcessKind};
+use crate::window_state::{WindowRegistry, WindowState};
+
+/// User-defined tasks, relative to the workspace root.
+const CUSTOM_TASKS_FILE: &str = ".codeai/tasks.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSource {
+    Npm,
+    Cargo,
+    Make,
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependsOrder {
+    /// Dependencies run one after another, in the order listed.
+    #[default]
+    Sequence,
+    Parallel,
+}
+
+impl DependsOrder {
+    fn is_sequence(&self) -> bool {
+        *self == DependsOrder::Sequence
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Task {
+    /// Stable across listings, e.g. `npm:build` or `cargo:run:server`.
+    pub id: String,
+    pub label: String,
+    pub source: TaskSource,
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    /// What the task runs, when that differs from the command line (the
+    /// body of an npm script).
+    pub detail: Option<String>,
+    pub env: HashMap<String, String>,
+    pub use_shell: bool,
+    /// Ids of tasks that must succeed first.
+    pub depends_on: Vec<String>,
+    pub depends_order: DependsOrder,
+    /// Env profile loaded from the workspace's `.env` files.
+    pub env_profile: Option<String>,
+}
+
+impl Task {
+    /// The process to start for this task, ready for `process::start`.
+    pub fn command_for(&self, window: &Window) -> Result<Command, String> {
+        let windows = window.state::<WindowRegistry>();
+        let options = ExecOptions {
+            env: self.env.clone(),
+            use_shell: self.use_shell,
+            stdin: None,
+            env_profile: self.env_profile.clone(),
+            workspace_secrets: true,
+        };
+        process::prepare(
+            &self.command,
+            &self.args,
+            Some(self.cwd.clone()),
+            &options,
+            window,
+            &windows,
+        )
+    }
+}
+
+/// An entry of the custom tasks file.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CustomTask {
+    pub label: String,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Relative to the workspace root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shell: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "DependsOrder::is_sequence")]
+    pub depends_order: DependsOrder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_profile: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomTasksFile {
+    #[serde(default)]
+    tasks: Vec<CustomTask>,
+}
+
+/// Emitted as `task-started` when a step of a run spawns its process; its
+/// output arrives as `command-output` under `process_id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStarted {
+    pub run_id: String,
+    pub task_id: String,
+    pub process_id: String,
+}
+
+/// Emitted as `task-diagnostics` when a step exits, with the problems found
+/// in its output; replaces whatever the same task reported before.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskDiagnostics {
+    pub run_id: String,
+    pub task_id: String,
+    pub cwd: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskFinished {
+    pub run_id: String,
+    pub task_id: String,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+}
+
+/// Emitted as `task-run-complete` once the requested task and all its
+/// dependencies have run, or the first of them failed.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRunComplete {
+    pub run_id: String,
+    pub task_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+fn task(
+    source: TaskSource,
+    id: String,
+    label: String,
+    command: &str,
+    args: &[&str],
+    cwd: &Path,
+) -> Task {
+    Task {
+        id,
+        label,
+        source,
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        cwd: cwd.to_string_lossy().to_string(),
+        detail: None,
+        env: HashMap::new(),
+        // npm and friends are `.cmd` shims on Windows, which only the shell
+        // can start.
+        use_shell: cfg!(windows) && source == TaskSource::Npm,
+        depends_on: Vec::new(),
+        depends_order: DependsOrder::Sequence,
+        env_profile: None,
+    }
+}
+
+pub(crate) fn package_manager(root: &Path) -> &'static str {
+    if root.join("pnpm-lock.yaml").is_file() {
+        "pnpm"
+    } else if root.join("yarn.lock").is_file() {
+        "yarn"
+    } else if root.join("bun.lockb").is_file() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+fn npm_tasks(root: &Path) -> Vec<Task> {
+    let text = match fs::read_to_string(root.join("package.json")) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+    let manifest: serde_json::Value = match serde_json::from_str(&text) {
+        Ok(manifest) => manifest,
+        Err(_) => return Vec::new(),
+    };
+    let manager = package_manager(root);
+    let scripts = match manifest.get("scripts").and_then(|s| s.as_object()) {
+        Some(scripts) => scripts,
+        None => return Vec::new(),
+    };
+    scripts
+        .iter()
+        .map(|(name, body)| {
+            let mut task = task(
+                TaskSource::Npm,
+                format!("npm:{}", name),
+                format!("{} run {}", manager, name),
+                manager,
+                &["run", name],
+                root,
+            );
+            task.detail = body.as_str().map(str::to_string);
+            task
+        })
+        .collect()
+}
+
+/// Names of `[[bin]]`/`[[example]]` entries plus the conventional files.
+fn cargo_targets(manifest: &toml::Value, root: &Path, kind: &str, dir: &str) -> Vec<String> {
+    let mut names: Vec<String> = manifest
+        .get(kind)
+        .and_then(|t| t.as_array())
+        .map(|targets| {
+            targets
+                .iter()
+                .filter_map(|t| t.get("name")?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Ok(entries) = fs::read_dir(root.join(dir)) {
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = if path.extension().is_some_and(|ext| ext == "rs") {
+                path.file_stem()
+            } else if path.join("main.rs").is_file() {
+                path.file_name()
+            } else {
+                None
+            };
+            if let Some(name) = name.map(|n| n.to_string_lossy().to_string()) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+fn cargo_tasks(root: &Path) -> Vec<Task> {
+    let manifest: toml::Value = match fs::read_to_string(root.join("Cargo.toml"))
+        .ok()
+        .and_then(|text| text.parse().ok())
+    {
+        Some(manifest) => manifest,
+        None => return Vec::new(),
+    };
+    let workspace = manifest.get("workspace").is_some();
+    let scope: &[&str] = if workspace { &["--workspace"] } else { &[] };
+
+    let mut tasks = Vec::new();
+    for action in ["build", "check", "test", "clippy"] {
+        let args: Vec<&str> = std::iter::once(action)
+            .chain(scope.iter().copied())
+            .collect();
+        tasks.push(task(
+            TaskSource::Cargo,
+            format!("cargo:{}", action),
+            format!("cargo {}", args.join(" ")),
+            "cargo",
+            &args,
+            root,
+        ));
+    }
+
+    let package = match manifest.get("package").and_then(|p| p.get("name")) {
+        Some(package) => package.as_str().unwrap_or_default().to_string(),
+        // A virtual workspace has nothing to run by itself.
+        None => return tasks,
+    };
+    let mut bins = cargo_targets(&manifest, root, "bin", "src/bin");
+    if root.join("src/main.rs").is_file() && !bins.contains(&package) {
+        bins.insert(0, package);
+    }
+    for bin in &bins {
+        let (id, args) = if bins.len() == 1 {
+            ("cargo:run".to_string(), vec!["run"])
+        } else {
+            (
+                format!("cargo:run:{}", bin),
+                vec!["run", "--bin", bin.as_str()],
+            )
+        };
+        tasks.push(task(
+            TaskSource::Cargo,
+            id,
+            format!("cargo {}", args.join(" ")),
+            "cargo",
+            &args,
+            root,
+        ));
+    }
+    for example in cargo_targets(&manifest, root, "example", "examples") {
+        let args = ["run", "--example", example.as_str()];
+        tasks.push(task(
+            TaskSource::Cargo,
+            format!("cargo:example:{}", example),
+            format!("cargo {}", args.join(" ")),
+            "cargo",
+            &args,
+            root,
+        ));
+    }
+    tasks
+}
+
+fn make_tasks(root: &Path) -> Vec<Task> {
+    let text = match ["GNUmakefile", "makefile", "Makefile"]
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok())
+    {
+        Some(text) => text,
+        None => return Vec::new(),
+    };
+    // `target: deps`, but not `VAR := value` or `VAR ::= value`.
+    let rule = Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_./-]*)\s*:([^=]|$)").expect("valid regex");
+    let mut seen = HashSet::new();
+    text.lines()
+        .filter_map(|line| rule.captures(line))
+        .map(|captures| captures[1].to_string())
+        .filter(|target| seen.insert(target.clone()))
+        .map(|target| {
+            task(
+                TaskSource::Make,
+                format!("make:{}", target),
+                format!("make {}", target),
+                "make",
+                &[target.as_str()],
+                root,
+            )
+        })
+        .collect()
+}
+
+fn custom_tasks(root: &Path) -> Result<Vec<Task>, String> {
+    let path = root.join(CUSTOM_TASKS_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let file: CustomTasksFile =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", CUSTOM_TASKS_FILE, e))?;
+    Ok(file
+        .tasks
+        .into_iter()
+        .map(|custom| {
+            let cwd = custom
+                .cwd
+                .map(|cwd| root.join(cwd))
+                .unwrap_or_else(|| root.to_path_buf());
+            Task {
+                id: format!("custom:{}", custom.label),
+                label: custom.label,
+                source: TaskSource::Custom,
+                command: custom.command,
+                args: custom.args,
+                cwd: cwd.to_string_lossy().to_string(),
+                detail: None,
+                env: custom.env,
+                use_shell: custom.shell,
+                depends_on: custom.depends_on,
+                depends_order: custom.depends_order,
+                env_profile: custom.env_profile,
+            }
+        })
+        .collect())
+}
+
+/// Appends `tasks` to the custom tasks file of `root`, leaving the tasks
+/// it has alone. Returns the labels added; a label already in the file is
+/// skipped.
+pub(crate) fn add_custom_tasks(root: &Path, tasks: Vec<CustomTask>) -> Result<Vec<String>, String> {
+    let path = root.join(CUSTOM_TASKS_FILE);
+    let mut file = match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid {}: {}", CUSTOM_TASKS_FILE, e))?,
+        Err(_) => serde_json::json!({ "tasks": [] }),
+    };
+    let Some(entries) = file
+        .as_object_mut()
+        .map(|file| file.entry("tasks").or_insert_with(|| serde_json::json!([])))
+        .and_then(|entries| entries.as_array_mut())
+    else {
+        return Err(format!(
+            "Invalid {}: expected a tasks list",
+            CUSTOM_TASKS_FILE
+        ));
+    };
+    let mut added = Vec::new();
+    for task in tasks {
+        let exists = entries
+            .iter()
+            .any(|entry| entry["label"].as_str() == Some(task.label.as_str()));
+        if exists {
+            continue;
+        }
+        added.push(task.label.clone());
+        entries.push(serde_json::to_value(task).map_err(|e| e.to_string())?);
+    }
+    if !added.is_empty() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        app_data::save_json(&path, &file)?;
+    }
+    Ok(added)
+}
+
+/// Every task detected in `root`: custom tasks first, then package
+/// scripts, Cargo and Make targets.
+pub fn detect_tasks(root: &Path) -> Result<Vec<Task>, String> {
+    let mut tasks = custom_tasks(root)?;
+    tasks.extend(npm_tasks(root));
+    tasks.extend(cargo_tasks(root));
+    tasks.extend(make_tasks(root));
+    Ok(tasks)
+}
+
+/// Orders a task and its dependencies into stages: tasks within a stage
+/// run in parallel, stages one after another. Each task appears once.
+fn plan(
+    id: &str,
+    tasks: &HashMap<String, Task>,
+    visiting: &mut Vec<String>,
+) -> Result<Vec<Vec<String>>, String> {
+    let task = tasks
+        .get(id)
+        .ok_or_else(|| format!("Unknown task: {}", id))?;
+    if visiting.iter().any(|v| v == id) {
+        return Err(format!(
+            "Task dependencies form a cycle: {} -> {}",
+            visiting.join(" -> "),
+            id
+        ));
+    }
+    visiting.push(id.to_string());
+
+    let mut stages: Vec<Vec<String>> = Vec::new();
+    for dependency in &task.depends_on {
+        let sub = plan(dependency, tasks, visiting)?;
+        match task.depends_order {
+            DependsOrder::Sequence => stages.extend(sub),
+            DependsOrder::Parallel => {
+                for (i, stage) in sub.into_iter().enumerate() {
+                    match stages.get_mut(i) {
+                        Some(existing) => existing.extend(stage),
+                        None => stages.push(stage),
+                    }
+                }
+            }
+        }
+    }
+    stages.push(vec![id.to_string()]);
+    visiting.pop();
+
+    let mut seen = HashSet::new();
+    Ok(stages
+        .into_iter()
+        .map(|stage| {
+            stage
+                .into_iter()
+                .filter(|id| seen.insert(id.clone()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|stage| !stage.is_empty())
+        .collect())
+}
+
+struct TaskRun {
+    window: Window,
+    scope: Arc<WindowState>,
+    run_id: String,
+    task_id: String,
+    tasks: HashMap<String, Task>,
+    stages: Vec<Vec<String>>,
+}
+
+impl TaskRun {
+    fn complete(&self, success: bool, error: Option<String>) {
+        let _ = self.window.emit(
+            "task-run-complete",
+            TaskRunComplete {
+                run_id: self.run_id.clone(),
+                task_id: self.task_id.clone(),
+                success,
+                error,
+            },
+        );
+    }
+
+    fn spawn(
+        self: &Arc<Self>,
+        task: &Task,
+        on_done: impl FnOnce(bool) + Send + 'static,
+    ) -> Result<(), String> {
+        let cmd = task.command_for(&self.window)?;
+
+        let matcher = Arc::new(Mutex::new(ProblemMatcher::new(Path::new(&task.cwd))));
+        let feed = matcher.clone();
+        let on_output: OutputHook = Arc::new(Mutex::new(move |stream, chunk: &str| {
+            feed.lock().unwrap().feed(stream, chunk)
+        }));
+
+        let run = self.clone();
+        let (task_id, cwd) = (task.id.clone(), task.cwd.clone());
+        let on_exit = move |exit: &CommandExit| {
+            let found = matcher.lock().unwrap().finish();
+            let changed = run
+                .scope
+                .diagnostics
+                .set_all(&format!("task:{}", task_id), found.clone());
+            diagnostics::notify(&run.window, changed);
+            let _ = run.window.emit(
+                "task-diagnostics",
+                TaskDiagnostics {
+                    run_id: run.run_id.clone(),
+                    task_id: task_id.clone(),
+                    cwd,
+                    diagnostics: found,
+                },
+            );
+
+            let success = exit.exit_code == Some(0);
+            let _ = run.window.emit(
+                "task-finished",
+                TaskFinished {
+                    run_id: run.run_id.clone(),
+                    task_id,
+                    exit_code: exit.exit_code,
+                    success,
+                },
+            );
+            on_done(success);
+        };
+        let process_id = process::start(
+            self.window.clone(),
+            self.scope.clone(),
+            cmd,
+            ProcessKind::Task,
+            None,
+            None,
+            ProcessHooks {
+                on_output: Some(on_output),
+                on_exit: Some(Box::new(on_exit)),
+            },
+        )?;
+        let _ = self.window.emit(
+            "task-started",
+            TaskStarted {
+                run_id: self.run_id.clone(),
+                task_id: task.id.clone(),
+                process_id,
+            },
+        );
+        Ok(())
+    }
+
+    /// Starts every task of `stage` and moves on once all of them exit;
+    /// the run stops at the first stage with a failure.
+    fn run_stage(self: Arc<Self>, stage: usize) {
+        let ids = match self.stages.get(stage) {
+            Some(ids) => ids.clone(),
+            None => return self.complete(true, None),
+        };
+        let remaining = Arc::new(Mutex::new((ids.len(), true)));
+        for id in &ids {
+            let run = self.clone();
+            let remaining = remaining.clone();
+            let on_done = move |success: bool| {
+                let mut state = remaining.lock().unwrap();
+                state.0 -= 1;
+                state.1 &= success;
+                if state.0 > 0 {
+                    return;
+                }
+                if state.1 {
+                    run.run_stage(stage + 1);
+                } else {
+                    run.complete(false, None);
+                }
+            };
+            if let Err(error) = self.spawn(&self.tasks[id], on_done) {
+                // Tasks of this stage already started still report their
+                // exit; the run is over either way.
+                remaining.lock().unwrap().0 = usize::MAX;
+                return self.complete(false, Some(error));
+            }
+        }
+    }
+}
+
+/// The workspace root tasks are detected in: `root` when given, otherwise
+/// the active one.
+pub fn task_root(
+    windows: &WindowRegistry,
+    window: &Window,
+    root: Option<String>,
+) -> Result<PathBuf, String> {
+    match root {
+        Some(root) => crate::workspace::authorize(windows, window, &root),
+        None => {
+            let scope = windows.scope(window.label());
+            scope
+                .workspace
+                .active()
+                .or_else(|| scope.workspace.roots().into_iter().next())
+                .ok_or_else(|| "No workspace is open".to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn list_tasks(
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<Task>, String> {
+    let root = task_root(&windows, &window, root)?;
+    detect_tasks(&root)
+}
+
+/// Runs a task after its dependencies and returns a run id right away.
+/// Progress arrives as `task-started`/`task-finished` per step, the steps'
+/// output as `command-output`, and the end as `task-run-complete`.
+/// `env_profile` overrides the profile of every task in the run.
+#[tauri::command]
+pub async fn run_task(
+    id: String,
+    root: Option<String>,
+    env_profile: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<String, String> {
+    let root = task_root(&windows, &window, root)?;
+    let tasks: HashMap<String, Task> = detect_tasks(&root)?
+        .into_iter()
+        .map(|mut task| {
+            if env_profile.is_some() {
+                task.env_profile = env_profile.clone();
+            }
+            (task.id.clone(), task)
+        })
+        .collect();
+    let stages = plan(&id, &tasks, &mut Vec::new())?;
+
+    let run = Arc::new(TaskRun {
+        scope: windows.scope(window.label()),
+        window,
+        run_id: uuid::Uuid::new_v4().to_string(),
+        task_id: id,
+        tasks,
+        stages,
+    });
+    let run_id = run.run_id.clone();
+    run.run_stage(0);
+    Ok(run_id)
+}
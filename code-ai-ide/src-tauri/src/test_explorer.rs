@@ -0,0 +1,1307 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{State, Window};
+use tree_sitter::Node;
+
+use crate::exclude;
+use crate::language;
+use crate::metadata::LARGE_FILE_THRESHOLD;
+use crate::process::{
+    self, CommandExit, ExecOptions, OutputHook, OutputStream, ProcessHooks, ProcessKind,
+};
+use crate::project::{self, ProjectKind};
+use crate::syntax;
+use crate::tasks;
+use crate::walk::walk_files;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TestFramework {
+    Cargo,
+    Jest,
+    Vitest,
+    Pytest,
+}
+
+impl TestFramework {
+    /// What joins the parts of its test ids.
+    fn separator(self) -> &'static str {
+        match self {
+            TestFramework::Cargo | TestFramework::Pytest => "::",
+            TestFramework::Jest | TestFramework::Vitest => " > ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestItemKind {
+    File,
+    /// A Rust module, a pytest class or a `describe` block.
+    Suite,
+    Test,
+}
+
+/// A test, or a file or suite grouping tests.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestItem {
+    /// What results are reported under: the full test name for cargo
+    /// (`parser::tests::parses`), the node id for pytest
+    /// (`tests/test_app.py::TestApp::test_home`), and the file and titles
+    /// joined with ` > ` for jest and vitest. Files are their path relative
+    /// to the workspace root.
+    pub id: String,
+    pub label: String,
+    pub kind: TestItemKind,
+    pub framework: TestFramework,
+    pub path: String,
+    /// Zero-based; `None` for files.
+    pub line: Option<u32>,
+    pub children: Vec<TestItem>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    /// Includes errors outside the assertions, such as in a fixture.
+    Failed,
+    /// Ignored, skipped, todo, or an expected failure.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResult {
+    /// The id of the test's `TestItem`; files for file-level progress.
+    pub id: String,
+    pub outcome: TestOutcome,
+    pub duration_ms: Option<u64>,
+    /// Why it failed or was skipped, with what it printed when the runner
+    /// reports that.
+    pub message: Option<String>,
+}
+
+/// A node of the result tree: a test, or a group of them with the outcome
+/// of its worst test and their total duration.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResultNode {
+    pub id: String,
+    pub label: String,
+    pub outcome: TestOutcome,
+    pub duration_ms: Option<u64>,
+    pub message: Option<String>,
+    pub children: Vec<TestResultNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestRun {
+    pub run_id: String,
+    /// For `kill_process`, to stop the run.
+    pub process_id: String,
+}
+
+/// Emitted as `test-progress` each time the runner reports a test, or for
+/// jest and vitest a whole file, as done.
+#[derive(Debug, Clone, Serialize)]
+struct TestProgress {
+    run_id: String,
+    #[serde(flatten)]
+    result: TestResult,
+}
+
+/// Emitted as `test-run-finished` with everything the run reported.
+#[derive(Debug, Clone, Serialize)]
+struct TestRunFinished {
+    run_id: String,
+    framework: TestFramework,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    exit_code: Option<i32>,
+    cancelled: bool,
+    /// Set when the run failed without reporting any test, such as on a
+    /// compile error; the output has the details.
+    error: Option<String>,
+    results: Vec<TestResult>,
+    tree: Vec<TestResultNode>,
+}
+
+/// `path` relative to `root` with `/` separators, as test ids use it.
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The nearest directory above `path`, up to `root`, holding `manifest`.
+fn nearest(root: &Path, path: &Path, manifest: &str) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root))
+        .find(|dir| dir.join(manifest).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The test framework of a JavaScript package, from its dependencies;
+/// vitest wins when both are there.
+fn node_framework(package: &Path) -> Option<TestFramework> {
+    let text = fs::read_to_string(package.join("package.json")).ok()?;
+    let manifest: Value = serde_json::from_str(&text).ok()?;
+    let has_dependency = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| manifest[section].get(name).is_some())
+    };
+    if has_dependency("vitest") {
+        Some(TestFramework::Vitest)
+    } else if has_dependency("jest") || manifest.get("jest").is_some() {
+        Some(TestFramework::Jest)
+    } else {
+        None
+    }
+}
+
+/// Which framework runs the tests in `path`, if it holds any by the
+/// frameworks' naming conventions. `packages` caches `node_framework`.
+fn file_framework(
+    root: &Path,
+    path: &Path,
+    packages: &mut HashMap<PathBuf, Option<TestFramework>>,
+) -> Option<TestFramework> {
+    let name = path.file_name()?.to_str()?;
+    match path.extension()?.to_str()? {
+        "rs" => nearest(root, path, "Cargo.toml").map(|_| TestFramework::Cargo),
+        "py" => (name.starts_with("test_") || name.ends_with("_test.py"))
+            .then_some(TestFramework::Pytest),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => {
+            let is_test = name.contains(".test.")
+                || name.contains(".spec.")
+                || path
+                    .components()
+                    .any(|component| component.as_os_str() == "__tests__");
+            if !is_test {
+                return None;
+            }
+            let package = nearest(root, path, "package.json")?;
+            *packages
+                .entry(package.clone())
+                .or_insert_with(|| node_framework(&package))
+        }
+        _ => None,
+    }
+}
+
+/// The cargo target a Rust file belongs to, as `cargo test` arguments,
+/// and the module path of the file inside it. `None` for examples, benches
+/// and build scripts.
+fn rust_target(crate_dir: &Path, path: &Path) -> Option<(Vec<String>, Vec<String>)> {
+    let owned: Vec<String> = path
+        .strip_prefix(crate_dir)
+        .ok()?
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let parts: Vec<&str> = owned.iter().map(String::as_str).collect();
+    let strings = |parts: &[&str]| -> Vec<String> {
+        parts
+            .iter()
+            .filter(|part| **part != "mod")
+            .map(|part| part.to_string())
+            .collect()
+    };
+    let (target, module): (Vec<&str>, &[&str]) = match parts.as_slice() {
+        ["src", "lib"] => (vec!["--lib"], &[]),
+        ["src", "main"] => (vec!["--bins"], &[]),
+        ["src", "bin", name] | ["src", "bin", name, "main"] => (vec!["--bin", *name], &[]),
+        ["src", "bin", name, module @ ..] => (vec!["--bin", *name], module),
+        ["src", module @ ..] if crate_dir.join("src/lib.rs").is_file() => (vec!["--lib"], module),
+        ["src", module @ ..] => (vec!["--bins"], module),
+        ["tests", name] | ["tests", name, "main"] => (vec!["--test", *name], &[]),
+        ["tests", name, module @ ..]
+            if crate_dir.join("tests").join(name).join("main.rs").is_file() =>
+        {
+            (vec!["--test", *name], module)
+        }
+        _ => return None,
+    };
+    Some((strings(&target), strings(module)))
+}
+
+/// The file being scanned for tests.
+struct SourceFile<'a> {
+    framework: TestFramework,
+    path: &'a str,
+    text: &'a str,
+}
+
+impl<'a> SourceFile<'a> {
+    fn text(&self, node: Node) -> &'a str {
+        &self.text[node.byte_range()]
+    }
+
+    fn item(
+        &self,
+        id: String,
+        label: &str,
+        kind: TestItemKind,
+        node: Node,
+        children: Vec<TestItem>,
+    ) -> TestItem {
+        TestItem {
+            id,
+            label: label.to_string(),
+            kind,
+            framework: self.framework,
+            path: self.path.to_string(),
+            line: Some(node.start_position().row as u32),
+            children,
+        }
+    }
+}
+
+/// `#[test]`, `#[tokio::test]`, `#[tokio::test(flavor = ...)]` and the
+/// like.
+fn test_attribute() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^#\[\s*(?:\w+::)*test\s*[\](]").expect("valid regex"))
+}
+
+/// Functions marked as tests among the items of `node`, in the inline
+/// modules they are in.
+fn rust_tests(node: Node, file: &SourceFile, module: &mut Vec<String>, out: &mut Vec<TestItem>) {
+    let mut is_test = false;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "attribute_item" => {
+                is_test |= test_attribute().is_match(file.text(child));
+                continue;
+            }
+            "line_comment" | "block_comment" => continue,
+            "function_item" if is_test => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    let name = file.text(name);
+                    let id = module
+                        .iter()
+                        .map(String::as_str)
+                        .chain([name])
+                        .collect::<Vec<_>>()
+                        .join("::");
+                    out.push(file.item(id, name, TestItemKind::Test, child, Vec::new()));
+                }
+            }
+            "mod_item" => {
+                let name = child.child_by_field_name("name");
+                if let (Some(name), Some(body)) = (name, child.child_by_field_name("body")) {
+                    let name = file.text(name);
+                    module.push(name.to_string());
+                    let mut children = Vec::new();
+                    rust_tests(body, file, module, &mut children);
+                    let id = module.join("::");
+                    module.pop();
+                    if !children.is_empty() {
+                        out.push(file.item(id, name, TestItemKind::Suite, child, children));
+                    }
+                }
+            }
+            _ => {}
+        }
+        is_test = false;
+    }
+}
+
+/// `test*` functions and `Test*` classes with `test*` methods, as pytest
+/// collects them.
+fn python_tests(node: Node, file: &SourceFile, parents: &mut Vec<String>, out: &mut Vec<TestItem>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let definition = match child.kind() {
+            "decorated_definition" => child.child_by_field_name("definition"),
+            _ => Some(child),
+        };
+        let Some(definition) = definition else {
+            continue;
+        };
+        let Some(name) = definition.child_by_field_name("name").map(|n| file.text(n)) else {
+            continue;
+        };
+        parents.push(name.to_string());
+        let id = parents.join("::");
+        match definition.kind() {
+            "function_definition" if name.starts_with("test") => {
+                out.push(file.item(id, name, TestItemKind::Test, child, Vec::new()));
+            }
+            "class_definition" if name.starts_with("Test") => {
+                let mut children = Vec::new();
+                if let Some(body) = definition.child_by_field_name("body") {
+                    python_tests(body, file, parents, &mut children);
+                }
+                if !children.is_empty() {
+                    out.push(file.item(id, name, TestItemKind::Suite, child, children));
+                }
+            }
+            _ => {}
+        }
+        parents.pop();
+    }
+}
+
+/// The name a test call is made through: `it` for `it(...)`,
+/// `it.only(...)` and `it.each(table)(...)`.
+fn callee_name<'a>(node: Node, file: &SourceFile<'a>) -> Option<&'a str> {
+    match node.kind() {
+        "identifier" => Some(file.text(node)),
+        "member_expression" => callee_name(node.child_by_field_name("object")?, file),
+        "call_expression" => callee_name(node.child_by_field_name("function")?, file),
+        _ => None,
+    }
+}
+
+/// A test's title, when it is a literal.
+fn title(node: Node, file: &SourceFile) -> Option<String> {
+    let text = file.text(node);
+    match node.kind() {
+        "string" | "template_string" if text.len() >= 2 => {
+            Some(text[1..text.len() - 1].to_string())
+        }
+        _ => None,
+    }
+}
+
+/// `describe`, `it` and `test` calls anywhere under `node`, nested as
+/// they are. `titles` starts with the file's id.
+fn js_tests(node: Node, file: &SourceFile, titles: &mut Vec<String>, out: &mut Vec<TestItem>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind() == "call_expression" {
+            let kind = child
+                .child_by_field_name("function")
+                .and_then(|function| callee_name(function, file))
+                .and_then(|name| match name {
+                    "describe" | "suite" | "context" => Some(TestItemKind::Suite),
+                    "it" | "test" => Some(TestItemKind::Test),
+                    _ => None,
+                });
+            let arguments = child.child_by_field_name("arguments");
+            let name = arguments
+                .and_then(|arguments| arguments.named_child(0))
+                .and_then(|first| title(first, file));
+            if let (Some(kind), Some(arguments), Some(name)) = (kind, arguments, name) {
+                titles.push(name.clone());
+                let id = titles.join(" > ");
+                let mut children = Vec::new();
+                if kind == TestItemKind::Suite {
+                    js_tests(arguments, file, titles, &mut children);
+                }
+                titles.pop();
+                if kind == TestItemKind::Test || !children.is_empty() {
+                    out.push(file.item(id, &name, kind, child, children));
+                }
+                continue;
+            }
+        }
+        js_tests(child, file, titles, out);
+    }
+}
+
+/// The tests in one file, under an item for the file; `None` when it has
+/// none or cannot be parsed.
+fn discover_file(root: &Path, path: &Path, framework: TestFramework) -> Option<TestItem> {
+    let text = fs::read_to_string(path).ok()?;
+    if framework == TestFramework::Cargo && !text.contains("test") {
+        return None;
+    }
+    let language = language::language_for_path(path);
+    let tree = syntax::parse(&syntax::grammar(&language, Some(path))?, &text, None).ok()?;
+    let path_text = path.to_string_lossy();
+    let file = SourceFile {
+        framework,
+        path: &path_text,
+        text: &text,
+    };
+    let id = relative(root, path);
+    let mut children = Vec::new();
+    match framework {
+        TestFramework::Cargo => {
+            let crate_dir = nearest(root, path, "Cargo.toml")?;
+            let (_, mut module) = rust_target(&crate_dir, path)?;
+            rust_tests(tree.root_node(), &file, &mut module, &mut children);
+        }
+        TestFramework::Pytest => {
+            python_tests(
+                tree.root_node(),
+                &file,
+                &mut vec![id.clone()],
+                &mut children,
+            );
+        }
+        TestFramework::Jest | TestFramework::Vitest => {
+            js_tests(
+                tree.root_node(),
+                &file,
+                &mut vec![id.clone()],
+                &mut children,
+            );
+        }
+    }
+    if children.is_empty() {
+        return None;
+    }
+    Some(TestItem {
+        label: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| id.clone()),
+        id,
+        kind: TestItemKind::File,
+        framework,
+        path: path_text.to_string(),
+        line: None,
+        children,
+    })
+}
+
+fn find_item<'a>(items: &'a [TestItem], id: &str) -> Option<&'a TestItem> {
+    items.iter().find_map(|item| {
+        if item.id == id {
+            Some(item)
+        } else {
+            find_item(&item.children, id)
+        }
+    })
+}
+
+/// How to start a test run.
+struct Invocation {
+    command: String,
+    args: Vec<String>,
+    cwd: PathBuf,
+    /// Where the runner writes its report: JSON for jest, JUnit XML for
+    /// vitest and pytest.
+    report: Option<PathBuf>,
+}
+
+/// How the package manager of `dir` runs a locally installed binary.
+fn node_runner(dir: &Path, binary: &str) -> (String, Vec<String>) {
+    let (command, args): (&str, &[&str]) = match tasks::package_manager(dir) {
+        "pnpm" => ("pnpm", &["exec"]),
+        "yarn" => ("yarn", &[]),
+        "bun" => ("bunx", &[]),
+        _ => ("npx", &["--no"]),
+    };
+    let args = args.iter().copied().chain([binary]).map(str::to_string);
+    (command.to_string(), args.collect())
+}
+
+/// pytest in the project's environment: through uv, poetry or pdm when
+/// the project uses one, else the Python on PATH.
+fn pytest_runner(root: &Path) -> (String, Vec<String>) {
+    let manager = project::detect_projects(root)
+        .into_iter()
+        .find(|project| project.kind == ProjectKind::Python)
+        .map(|project| project.package_manager);
+    match manager.as_deref() {
+        Some(manager @ ("uv" | "poetry" | "pdm")) => {
+            (manager.to_string(), vec!["run".into(), "pytest".into()])
+        }
+        _ => {
+            let python = if cfg!(windows) { "python" } else { "python3" };
+            (python.to_string(), vec!["-m".into(), "pytest".into()])
+        }
+    }
+}
+
+/// jest's and vitest's `-t` pattern for a test or suite, matched against
+/// its titles joined with spaces.
+fn name_pattern(item: &TestItem) -> String {
+    let titles: Vec<&str> = item.id.split(" > ").skip(1).collect();
+    let end = match item.kind {
+        TestItemKind::Test => "$",
+        _ => " ",
+    };
+    format!("^{}{}", regex::escape(&titles.join(" ")), end)
+}
+
+fn invocation(
+    root: &Path,
+    framework: TestFramework,
+    path: Option<&Path>,
+    item: Option<&TestItem>,
+) -> Result<Invocation, String> {
+    let report = |extension: &str| {
+        std::env::temp_dir().join(format!(
+            "codeai-tests-{}.{}",
+            uuid::Uuid::new_v4(),
+            extension
+        ))
+    };
+    let path_arg = |path: &Path| path.to_string_lossy().to_string();
+    Ok(match framework {
+        TestFramework::Cargo => {
+            let cwd = path
+                .and_then(|path| nearest(root, path, "Cargo.toml"))
+                .unwrap_or_else(|| root.to_path_buf());
+            let mut args = vec!["test".to_string(), "--no-fail-fast".to_string()];
+            let mut filter = Vec::new();
+            if let Some(path) = path {
+                let (target, module) = rust_target(&cwd, path)
+                    .ok_or_else(|| format!("Cargo runs no tests from {}", path.display()))?;
+                args.extend(target);
+                match item {
+                    Some(item) if item.kind == TestItemKind::Test => {
+                        filter = vec![item.id.clone(), "--exact".to_string()];
+                    }
+                    Some(item) if item.kind == TestItemKind::Suite => {
+                        filter = vec![format!("{}::", item.id)];
+                    }
+                    _ if !module.is_empty() => filter = vec![format!("{}::", module.join("::"))],
+                    _ => {}
+                }
+            }
+            args.push("--".to_string());
+            args.extend(filter);
+            Invocation {
+                command: "cargo".to_string(),
+                args,
+                cwd,
+                report: None,
+            }
+        }
+        TestFramework::Jest | TestFramework::Vitest => {
+            let cwd = path
+                .and_then(|path| nearest(root, path, "package.json"))
+                .unwrap_or_else(|| root.to_path_buf());
+            let report = if framework == TestFramework::Jest {
+                report("json")
+            } else {
+                report("xml")
+            };
+            let (command, mut args) = if framework == TestFramework::Jest {
+                let (command, mut args) = node_runner(&cwd, "jest");
+                args.extend([
+                    "--json".to_string(),
+                    format!("--outputFile={}", report.display()),
+                ]);
+                if let Some(path) = path {
+                    args.extend(["--runTestsByPath".to_string(), path_arg(path)]);
+                }
+                (command, args)
+            } else {
+                let (command, mut args) = node_runner(&cwd, "vitest");
+                args.extend([
+                    "run".to_string(),
+                    "--reporter=default".to_string(),
+                    "--reporter=junit".to_string(),
+                    format!("--outputFile.junit={}", report.display()),
+                ]);
+                if let Some(path) = path {
+                    args.push(path_arg(path));
+                }
+                (command, args)
+            };
+            if let Some(item) = item.filter(|item| item.kind != TestItemKind::File) {
+                args.extend(["-t".to_string(), name_pattern(item)]);
+            }
+            Invocation {
+                command,
+                args,
+                cwd,
+                report: Some(report),
+            }
+        }
+        TestFramework::Pytest => {
+            let report = report("xml");
+            let (command, mut args) = pytest_runner(root);
+            args.extend([
+                "-v".to_string(),
+                "--color=no".to_string(),
+                format!("--junitxml={}", report.display()),
+            ]);
+            match (item, path) {
+                (Some(item), _) => args.push(item.id.clone()),
+                (None, Some(path)) => args.push(relative(root, path)),
+                (None, None) => {}
+            }
+            Invocation {
+                command,
+                args,
+                cwd: root.to_path_buf(),
+                report: Some(report),
+            }
+        }
+    })
+}
+
+/// A `<testcase>` of a JUnit XML report.
+struct JunitCase {
+    classname: String,
+    name: String,
+    time: Option<f64>,
+    outcome: TestOutcome,
+    message: Option<String>,
+}
+
+fn attributes(element: &BytesStart) -> HashMap<String, String> {
+    element
+        .attributes()
+        .flatten()
+        .filter_map(|attribute| {
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+            Some((key, attribute.unescape_value().ok()?.to_string()))
+        })
+        .collect()
+}
+
+/// The test cases of a JUnit XML report, as pytest and vitest write them.
+/// A `<failure>` or `<error>` fails the case and `<skipped>` skips it;
+/// their `message` and text make up the message.
+fn parse_junit(xml: &str) -> Result<Vec<JunitCase>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut cases = Vec::new();
+    let mut current: Option<JunitCase> = None;
+    // Inside a <failure>, <error> or <skipped> with text.
+    let mut in_detail = false;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid JUnit report: {}", e))?;
+        let detail = match &event {
+            Event::Text(text) => text.unescape().ok().map(|text| text.to_string()),
+            Event::CData(data) => Some(String::from_utf8_lossy(data).to_string()),
+            _ => None,
+        };
+        if let (Some(detail), true, Some(case)) = (detail, in_detail, current.as_mut()) {
+            case.message = Some(match case.message.take() {
+                Some(message) => format!("{}\n{}", message, detail),
+                None => detail,
+            });
+            continue;
+        }
+        let (element, empty) = match event {
+            Event::Eof => break,
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(element) => {
+                match element.name().as_ref() {
+                    b"testcase" => cases.extend(current.take()),
+                    b"failure" | b"error" | b"skipped" => in_detail = false,
+                    _ => {}
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let attributes = attributes(&element);
+        match element.name().as_ref() {
+            b"testcase" => {
+                let attribute = |name: &str| attributes.get(name).cloned().unwrap_or_default();
+                let case = JunitCase {
+                    classname: attribute("classname"),
+                    name: attribute("name"),
+                    time: attribute("time").parse().ok(),
+                    outcome: TestOutcome::Passed,
+                    message: None,
+                };
+                if empty {
+                    cases.push(case);
+                } else {
+                    current = Some(case);
+                }
+            }
+            name @ (b"failure" | b"error" | b"skipped") => {
+                if let Some(case) = current.as_mut() {
+                    case.outcome = if name == b"skipped" {
+                        TestOutcome::Skipped
+                    } else {
+                        TestOutcome::Failed
+                    };
+                    case.message = attributes.get("message").cloned();
+                    in_detail = !empty;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(cases)
+}
+
+struct Patterns {
+    ansi: Regex,
+    cargo_test: Regex,
+    cargo_output: Regex,
+    pytest: Regex,
+    jest_file: Regex,
+    vitest: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let re = |pattern: &str| Regex::new(pattern).expect("valid regex");
+        Patterns {
+            ansi: re(r"\x1b\[[0-9;]*[A-Za-z]"),
+            // test parser::tests::parses ... ok
+            cargo_test: re(r"^test (.+?)(?: - should panic)? \.\.\. (ok|FAILED|ignored)"),
+            // ---- parser::tests::parses stdout ----
+            cargo_output: re(r"^---- (.+?) stdout ----$"),
+            // tests/test_app.py::test_home PASSED   [ 50%]
+            pytest: re(r"^(\S.*?::.+?) (PASSED|FAILED|SKIPPED|ERROR|XFAIL|XPASS)\b"),
+            // PASS src/app.test.ts
+            jest_file: re(r"^\s*(PASS|FAIL)\s+(\S+)"),
+            // ✓ src/app.test.ts (3 tests) 12ms, or × src/app.test.ts > app > renders
+            vitest: re(
+                r"^\s*([✓✔×✗❯↓])\s+(\S+\.[cm]?[jt]sx?(?: > .+?)?)(?:\s+\(\d+ tests?[^)]*\))?(?:\s+\d+(?:\.\d+)?m?s)?\s*$",
+            ),
+        }
+    })
+}
+
+/// Turns a run's output into results as it streams in, one line at a time,
+/// then adds what the runner's report says.
+struct OutputParser {
+    framework: TestFramework,
+    /// What test ids are relative to.
+    root: PathBuf,
+    /// Where the runner runs; its output is relative to this.
+    cwd: PathBuf,
+    partial: [String; 2],
+    /// The cargo test whose captured output is being read, and the lines
+    /// read so far.
+    capture: Option<(String, Vec<String>)>,
+    results: Vec<TestResult>,
+}
+
+impl OutputParser {
+    fn new(framework: TestFramework, root: &Path, cwd: &Path) -> OutputParser {
+        OutputParser {
+            framework,
+            root: root.to_path_buf(),
+            cwd: cwd.to_path_buf(),
+            partial: [String::new(), String::new()],
+            capture: None,
+            results: Vec::new(),
+        }
+    }
+
+    /// Returns what the chunk reported as done, for progress events.
+    fn feed(&mut self, stream: OutputStream, chunk: &str) -> Vec<TestResult> {
+        let slot = match stream {
+            OutputStream::Stdout => 0,
+            OutputStream::Stderr => 1,
+        };
+        self.partial[slot].push_str(chunk);
+        let mut done = Vec::new();
+        while let Some(end) = self.partial[slot].find('\n') {
+            let line: String = self.partial[slot].drain(..=end).collect();
+            done.extend(self.line(stream, &line));
+        }
+        done
+    }
+
+    /// Flushes unterminated lines.
+    fn finish(&mut self) -> Vec<TestResult> {
+        let mut done = Vec::new();
+        for (slot, stream) in [OutputStream::Stdout, OutputStream::Stderr]
+            .into_iter()
+            .enumerate()
+        {
+            let line = std::mem::take(&mut self.partial[slot]);
+            if !line.is_empty() {
+                done.extend(self.line(stream, &line));
+            }
+        }
+        self.end_capture();
+        done
+    }
+
+    /// Adds a test's result, or updates what is known of it.
+    fn record(&mut self, result: TestResult) {
+        match self.results.iter_mut().find(|known| known.id == result.id) {
+            Some(known) => {
+                known.outcome = result.outcome;
+                known.duration_ms = result.duration_ms.or(known.duration_ms);
+                known.message = result.message.or(known.message.take());
+            }
+            None => self.results.push(result),
+        }
+    }
+
+    fn end_capture(&mut self) {
+        if let Some((id, lines)) = self.capture.take() {
+            let output = lines.join("\n").trim().to_string();
+            if let Some(result) = self.results.iter_mut().find(|result| result.id == id) {
+                if !output.is_empty() {
+                    result.message = Some(output);
+                }
+            }
+        }
+    }
+
+    /// A file id from a path the runner printed.
+    fn file_id(&self, path: &str) -> String {
+        relative(&self.root, &self.cwd.join(path))
+    }
+
+    fn line(&mut self, stream: OutputStream, raw: &str) -> Option<TestResult> {
+        let p = patterns();
+        let line = p.ansi.replace_all(raw.trim_end_matches(['\r', '\n']), "");
+        let result = |id: String, outcome| TestResult {
+            id,
+            outcome,
+            duration_ms: None,
+            message: None,
+        };
+        match (self.framework, stream) {
+            (TestFramework::Cargo, OutputStream::Stdout) => {
+                if line.starts_with('{') {
+                    return self.cargo_json(&line);
+                }
+                if let Some(captures) = p.cargo_output.captures(&line) {
+                    self.end_capture();
+                    self.capture = Some((captures[1].to_string(), Vec::new()));
+                    return None;
+                }
+                if line == "failures:" || line.starts_with("test result:") {
+                    self.end_capture();
+                    return None;
+                }
+                if let Some(captures) = p.cargo_test.captures(&line) {
+                    let outcome = match &captures[2] {
+                        "ok" => TestOutcome::Passed,
+                        "FAILED" => TestOutcome::Failed,
+                        _ => TestOutcome::Skipped,
+                    };
+                    let done = result(captures[1].to_string(), outcome);
+                    self.record(done.clone());
+                    return Some(done);
+                }
+                if let Some((_, lines)) = self.capture.as_mut() {
+                    lines.push(line.to_string());
+                }
+                None
+            }
+            (TestFramework::Pytest, OutputStream::Stdout) => {
+                let captures = p.pytest.captures(&line)?;
+                let outcome = match &captures[2] {
+                    "PASSED" | "XPASS" => TestOutcome::Passed,
+                    "SKIPPED" | "XFAIL" => TestOutcome::Skipped,
+                    _ => TestOutcome::Failed,
+                };
+                let done = result(captures[1].to_string(), outcome);
+                self.record(done.clone());
+                Some(done)
+            }
+            (TestFramework::Jest, OutputStream::Stderr) => {
+                let captures = p.jest_file.captures(&line)?;
+                let outcome = match &captures[1] {
+                    "PASS" => TestOutcome::Passed,
+                    _ => TestOutcome::Failed,
+                };
+                // Files only show progress; the report has the tests.
+                Some(result(self.file_id(&captures[2]), outcome))
+            }
+            (TestFramework::Vitest, OutputStream::Stdout) => {
+                let captures = p.vitest.captures(&line)?;
+                let outcome = match &captures[1] {
+                    "✓" | "✔" => TestOutcome::Passed,
+                    "↓" => TestOutcome::Skipped,
+                    _ => TestOutcome::Failed,
+                };
+                let (file, titles) = match captures[2].split_once(" > ") {
+                    Some((file, titles)) => (file, Some(titles)),
+                    None => (&captures[2], None),
+                };
+                let id = match titles {
+                    Some(titles) => format!("{} > {}", self.file_id(file), titles),
+                    None => self.file_id(file),
+                };
+                let done = result(id, outcome);
+                if titles.is_some() {
+                    self.record(done.clone());
+                }
+                Some(done)
+            }
+            _ => None,
+        }
+    }
+
+    /// libtest's JSON format, for toolchains that have it enabled.
+    fn cargo_json(&mut self, line: &str) -> Option<TestResult> {
+        let event: Value = serde_json::from_str(line).ok()?;
+        if event["type"] != "test" {
+            return None;
+        }
+        let outcome = match event["event"].as_str()? {
+            "ok" => TestOutcome::Passed,
+            "failed" | "timeout" => TestOutcome::Failed,
+            "ignored" => TestOutcome::Skipped,
+            _ => return None,
+        };
+        let done = TestResult {
+            id: event["name"].as_str()?.to_string(),
+            outcome,
+            duration_ms: event["exec_time"]
+                .as_f64()
+                .map(|secs| (secs * 1000.0) as u64),
+            message: event["stdout"]
+                .as_str()
+                .or(event["message"].as_str())
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty()),
+        };
+        self.record(done.clone());
+        Some(done)
+    }
+
+    /// pytest's JUnit report names tests by dotted class name; the node id
+    /// has the file instead, found by trying the longest prefix that is one.
+    fn pytest_id(&self, classname: &str, name: &str) -> String {
+        let parts: Vec<&str> = classname.split('.').collect();
+        for split in (1..=parts.len()).rev() {
+            let file = format!("{}.py", parts[..split].join("/"));
+            if self.cwd.join(&file).is_file() {
+                return std::iter::once(file.as_str())
+                    .chain(parts[split..].iter().copied())
+                    .chain([name])
+                    .collect::<Vec<_>>()
+                    .join("::");
+            }
+        }
+        format!("{}::{}", classname, name)
+    }
+
+    /// Adds what the runner's report says; it has the failure messages
+    /// and durations the streamed output lacks.
+    fn read_report(&mut self, report: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(report)
+            .map_err(|e| format!("Failed to read {}: {}", report.display(), e))?;
+        if self.framework == TestFramework::Jest {
+            let report: Value =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid jest report: {}", e))?;
+            for file in report["testResults"].as_array().into_iter().flatten() {
+                let file_id = self.file_id(file["name"].as_str().unwrap_or_default());
+                for test in file["assertionResults"].as_array().into_iter().flatten() {
+                    let titles = test["ancestorTitles"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .chain([&test["title"]])
+                        .filter_map(Value::as_str);
+                    let id = std::iter::once(file_id.as_str())
+                        .chain(titles)
+                        .collect::<Vec<_>>()
+                        .join(" > ");
+                    let outcome = match test["status"].as_str() {
+                        Some("passed") => TestOutcome::Passed,
+                        Some("failed") => TestOutcome::Failed,
+                        _ => TestOutcome::Skipped,
+                    };
+                    let messages: Vec<&str> = test["failureMessages"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .collect();
+                    self.record(TestResult {
+                        id,
+                        outcome,
+                        duration_ms: test["duration"].as_u64(),
+                        message: (!messages.is_empty()).then(|| messages.join("\n")),
+                    });
+                }
+            }
+            return Ok(());
+        }
+        for case in parse_junit(&text)? {
+            let id = match self.framework {
+                TestFramework::Pytest => self.pytest_id(&case.classname, &case.name),
+                // vitest: the file relative to where it ran, and the titles.
+                _ => format!("{} > {}", self.file_id(&case.classname), case.name),
+            };
+            self.record(TestResult {
+                id,
+                outcome: case.outcome,
+                duration_ms: case.time.map(|secs| (secs * 1000.0) as u64),
+                message: case.message,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Gives a group the outcome of its worst test and their total duration.
+fn summarize(node: &mut TestResultNode) {
+    if node.children.is_empty() {
+        return;
+    }
+    node.children.iter_mut().for_each(summarize);
+    let has = |outcome| node.children.iter().any(|child| child.outcome == outcome);
+    node.outcome = if has(TestOutcome::Failed) {
+        TestOutcome::Failed
+    } else if has(TestOutcome::Passed) {
+        TestOutcome::Passed
+    } else {
+        TestOutcome::Skipped
+    };
+    node.duration_ms = node
+        .children
+        .iter()
+        .filter_map(|child| child.duration_ms)
+        .reduce(|a, b| a + b);
+}
+
+/// The results as a tree, split on the framework's id separator: modules
+/// for cargo, files and classes for pytest, files and `describe` blocks
+/// for jest and vitest.
+fn result_tree(framework: TestFramework, results: &[TestResult]) -> Vec<TestResultNode> {
+    let separator = framework.separator();
+    let mut roots: Vec<TestResultNode> = Vec::new();
+    for result in results {
+        let parts: Vec<&str> = result.id.split(separator).collect();
+        let mut level = &mut roots;
+        for depth in 0..parts.len() {
+            let id = parts[..=depth].join(separator);
+            let index = match level.iter().position(|node| node.id == id) {
+                Some(index) => index,
+                None => {
+                    level.push(TestResultNode {
+                        id,
+                        label: parts[depth].to_string(),
+                        outcome: result.outcome,
+                        duration_ms: None,
+                        message: None,
+                        children: Vec::new(),
+                    });
+                    level.len() - 1
+                }
+            };
+            let node = &mut level[index];
+            if depth + 1 == parts.len() {
+                node.outcome = result.outcome;
+                node.duration_ms = result.duration_ms;
+                node.message = result.message.clone();
+            }
+            level = &mut node.children;
+        }
+    }
+    roots.iter_mut().for_each(summarize);
+    roots
+}
+
+/// The frameworks `root` uses, from its manifests.
+fn frameworks(root: &Path) -> Vec<TestFramework> {
+    let mut found = Vec::new();
+    for project in project::detect_projects(root) {
+        let framework = match project.kind {
+            ProjectKind::Cargo => Some(TestFramework::Cargo),
+            ProjectKind::Python => Some(TestFramework::Pytest),
+            ProjectKind::Node => node_framework(root),
+            ProjectKind::Go => None,
+        };
+        found.extend(framework);
+    }
+    found
+}
+
+fn test_root(
+    root: Option<String>,
+    window: &Window,
+    windows: &WindowRegistry,
+) -> Result<PathBuf, String> {
+    match root {
+        Some(root) => workspace::authorize(windows, window, &root),
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+/// The tests of `root` (default: the active workspace), or of the file
+/// `path` alone, found by parsing the sources rather than running
+/// anything: `#[test]` functions for cargo, `test_*` functions and
+/// `Test*` classes in `test_*.py` files for pytest, and `describe`/`it`/
+/// `test` calls in `*.test.*`, `*.spec.*` and `__tests__` files for jest
+/// and vitest, whichever the nearest `package.json` depends on. One item
+/// per file, holding its suites and tests.
+#[tauri::command]
+pub async fn discover_tests(
+    root: Option<String>,
+    path: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<TestItem>, String> {
+    let root = test_root(root, &window, &windows)?;
+    let path = path
+        .map(|path| workspace::authorize(&windows, &window, &path))
+        .transpose()?;
+    let matcher = exclude::matcher_for(&windows, &window, &root)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut packages = HashMap::new();
+        if let Some(path) = path {
+            return Ok(file_framework(&root, &path, &mut packages)
+                .and_then(|framework| discover_file(&root, &path, framework))
+                .into_iter()
+                .collect());
+        }
+        let mut items = Vec::new();
+        walk_files(&root, Some(&matcher), |path| {
+            let small = fs::metadata(path).is_ok_and(|m| m.len() <= LARGE_FILE_THRESHOLD);
+            if small {
+                if let Some(framework) = file_framework(&root, path, &mut packages) {
+                    items.extend(discover_file(&root, path, framework));
+                }
+            }
+            true
+        });
+        Ok(items)
+    })
+    .await
+    .map_err(|e| format!("Failed to discover tests: {}", e))?
+}
+
+/// Runs the tests of `framework` in `root` (default: the active
+/// workspace): all of them, the file `path`, or the test or suite `id`
+/// within it. Returns right away; each test reported as done arrives as
+/// `test-progress`, the output as `command-output`, and the results, also
+/// as a pass/fail tree, as `test-run-finished`. Cargo's output is parsed
+/// as it streams in; jest's JSON report and the JUnit XML reports of
+/// vitest and pytest add failure messages and durations at the end.
+#[tauri::command]
+pub async fn run_tests(
+    framework: TestFramework,
+    path: Option<String>,
+    id: Option<String>,
+    root: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<TestRun, String> {
+    let root = test_root(root, &window, &windows)?;
+    if !frameworks(&root).contains(&framework) && path.is_none() {
+        return Err(format!(
+            "{} does not use {:?} tests",
+            root.display(),
+            framework
+        ));
+    }
+    let path = path
+        .map(|path| workspace::authorize(&windows, &window, &path))
+        .transpose()?;
+    let item = match (&id, &path) {
+        (Some(id), Some(path)) => {
+            let file = discover_file(&root, path, framework)
+                .ok_or_else(|| format!("No tests found in {}", path.display()))?;
+            Some(
+                find_item(std::slice::from_ref(&file), id)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown test: {}", id))?,
+            )
+        }
+        (Some(_), None) => return Err("A test id needs the file it is in".to_string()),
+        _ => None,
+    };
+    let invocation = invocation(&root, framework, path.as_deref(), item.as_ref())?;
+
+    let options = ExecOptions {
+        env: [
+            ("NO_COLOR", "1"),
+            ("FORCE_COLOR", "0"),
+            ("CARGO_TERM_COLOR", "never"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect(),
+        // npx and friends are batch files on Windows.
+        use_shell: cfg!(windows)
+            && matches!(framework, TestFramework::Jest | TestFramework::Vitest),
+        workspace_secrets: true,
+        ..ExecOptions::default()
+    };
+    let cmd = process::prepare(
+        &invocation.command,
+        &invocation.args,
+        Some(invocation.cwd.to_string_lossy().to_string()),
+        &options,
+        &window,
+        &windows,
+    )?;
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let parser = Arc::new(Mutex::new(OutputParser::new(
+        framework,
+        &root,
+        &invocation.cwd,
+    )));
+    let on_output: OutputHook = {
+        let (window, parser, run_id) = (window.clone(), parser.clone(), run_id.clone());
+        Arc::new(Mutex::new(move |stream, chunk: &str| {
+            for result in parser.lock().unwrap().feed(stream, chunk) {
+                let run_id = run_id.clone();
+                let _ = window.emit("test-progress", TestProgress { run_id, result });
+            }
+        }))
+    };
+    let on_exit = {
+        let (window, run_id, report) = (window.clone(), run_id.clone(), invocation.report);
+        move |exit: &CommandExit| {
+            let mut parser = parser.lock().unwrap();
+            for result in parser.finish() {
+                let run_id = run_id.clone();
+                let _ = window.emit("test-progress", TestProgress { run_id, result });
+            }
+            if let Some(report) = report {
+                // Missing when the run failed before any test ran.
+                let _ = parser.read_report(&report);
+                let _ = fs::remove_file(&report);
+            }
+            let results = std::mem::take(&mut parser.results);
+            let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count();
+            let error = (results.is_empty() && exit.exit_code != Some(0) && !exit.cancelled)
+                .then(|| "The test run failed before reporting any test".to_string());
+            let _ = window.emit(
+                "test-run-finished",
+                TestRunFinished {
+                    run_id,
+                    framework,
+                    passed: count(TestOutcome::Passed),
+                    failed: count(TestOutcome::Failed),
+                    skipped: count(TestOutcome::Skipped),
+                    exit_code: exit.exit_code,
+                    cancelled: exit.cancelled,
+                    error,
+                    tree: result_tree(framework, &results),
+                    results,
+                },
+            );
+        }
+    };
+    let scope = windows.scope(window.label());
+    let process_id = process::start(
+        window,
+        scope,
+        cmd,
+        ProcessKind::Test,
+        None,
+        None,
+        ProcessHooks {
+            on_output: Some(on_output),
+            on_exit: Some(Box::new(on_exit)),
+        },
+    )?;
+    Ok(TestRun { run_id, process_id })
+}
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{State, Window};
+
+use crate::encoding;
+use crate::exclude;
+use crate::line_endings;
+use crate::metadata::LARGE_FILE_THRESHOLD;
+use crate::walk::walk_files;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// Bytes sniffed for a NUL to decide a file is binary and not text.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextIssue {
+    InvalidUtf8,
+    MixedLineEndings,
+    TrailingWhitespace,
+    MissingFinalNewline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileHealth {
+    pub path: String,
+    pub issues: Vec<TextIssue>,
+    /// Charset guessed for files that are not valid UTF-8.
+    pub detected_encoding: Option<String>,
+    pub lf_count: usize,
+    pub crlf_count: usize,
+    pub trailing_whitespace_lines: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TextHealthReport {
+    pub files_scanned: usize,
+    pub files: Vec<FileHealth>,
+}
+
+/// Which issues to fix in one file. Every issue in the report is fixable.
+#[derive(Debug, Deserialize)]
+pub struct TextHealthFix {
+    pub path: String,
+    pub issues: Vec<TextIssue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FixFailure {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextHealthFixResult {
+    pub fixed: Vec<String>,
+    pub failed: Vec<FixFailure>,
+}
+
+pub fn is_probably_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+fn check_file(path: &Path) -> Option<FileHealth> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.is_empty() || is_probably_binary(&bytes) {
+        return None;
+    }
+
+    let mut issues = Vec::new();
+    let mut detected_encoding = None;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => {
+            issues.push(TextIssue::InvalidUtf8);
+            let decoded = encoding::decode(&bytes);
+            detected_encoding = Some(decoded.encoding);
+            decoded.content
+        }
+    };
+
+    let (lf_count, crlf_count) = line_endings::count(&text);
+    if lf_count > 0 && crlf_count > 0 {
+        issues.push(TextIssue::MixedLineEndings);
+    }
+
+    let trailing_whitespace_lines = text
+        .lines()
+        .filter(|line| line.ends_with(' ') || line.ends_with('\t'))
+        .count();
+    if trailing_whitespace_lines > 0 {
+        issues.push(TextIssue::TrailingWhitespace);
+    }
+
+    if !text.ends_with('\n') {
+        issues.push(TextIssue::MissingFinalNewline);
+    }
+
+    if issues.is_empty() {
+        return None;
+    }
+    Some(FileHealth {
+        path: path.to_string_lossy().to_string(),
+        issues,
+        detected_encoding,
+        lf_count,
+        crlf_count,
+        trailing_whitespace_lines,
+    })
+}
+
+pub fn audit(root: &Path, matcher: &exclude::ExclusionMatcher) -> TextHealthReport {
+    let mut files_scanned = 0;
+    let mut files = Vec::new();
+    walk_files(root, Some(matcher), |path| {
+        let too_large = fs::metadata(path)
+            .map(|m| m.len() > LARGE_FILE_THRESHOLD)
+            .unwrap_or(true);
+        if !too_large {
+            files_scanned += 1;
+            files.extend(check_file(path));
+        }
+        true
+    });
+    TextHealthReport {
+        files_scanned,
+        files,
+    }
+}
+
+fn apply_fixes(path: &Path, issues: &[TextIssue]) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if is_probably_binary(&bytes) {
+        return Err("File looks binary; refusing to rewrite it".to_string());
+    }
+
+    let mut text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) if issues.contains(&TextIssue::InvalidUtf8) => encoding::decode(&bytes).content,
+        Err(_) => return Err("File is not valid UTF-8; include the invalid_utf8 fix".to_string()),
+    };
+
+    if issues.contains(&TextIssue::MixedLineEndings) {
+        // Normalize towards whichever style the file mostly uses.
+        let (lf, crlf) = line_endings::count(&text);
+        text = text.replace("\r\n", "\n");
+        if crlf > lf {
+            text = text.replace('\n', "\r\n");
+        }
+    }
+
+    if issues.contains(&TextIssue::TrailingWhitespace) {
+        let ends_with_newline = text.ends_with('\n');
+        let eol = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut trimmed = text
+            .lines()
+            .map(|line| line.trim_end_matches([' ', '\t']))
+            .collect::<Vec<_>>()
+            .join(eol);
+        if ends_with_newline {
+            trimmed.push_str(eol);
+        }
+        text = trimmed;
+    }
+
+    if issues.contains(&TextIssue::MissingFinalNewline) && !text.is_empty() && !text.ends_with('\n')
+    {
+        let eol = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        text.push_str(eol);
+    }
+
+    fs::write(path, text).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn audit_text_health(
+    workspace: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<TextHealthReport, String> {
+    let root = workspace::authorize(&windows, &window, &workspace)?;
+    let matcher = exclude::matcher_for(&windows, &window, &root)?;
+    tauri::async_runtime::spawn_blocking(move || audit(&root, &matcher))
+        .await
+        .map_err(|e| format!("Text health audit failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn apply_text_health_fixes(
+    selection: Vec<TextHealthFix>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<TextHealthFixResult, String> {
+    let mut result = TextHealthFixResult {
+        fixed: Vec::new(),
+        failed: Vec::new(),
+    };
+    for fix in selection {
+        let outcome = workspace::authorize(&windows, &window, &fix.path)
+            .and_then(|path| apply_fixes(&path, &fix.issues));
+        match outcome {
+            Ok(()) => result.fixed.push(fix.path),
+            Err(message) => result.failed.push(FixFailure {
+                path: fix.path,
+                message,
+            }),
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::app_data;
+use crate::jsonc::read_jsonc;
+
+/// In the app config directory. Holds loose `*.json` theme files and
+/// unpacked VS Code theme extensions (folders with a `package.json`).
+const THEMES_DIR: &str = "themes";
+/// `include` chains deeper than this are assumed to be cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeKind {
+    Color,
+    Icon,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeSummary {
+    pub id: String,
+    pub label: String,
+    pub kind: ThemeKind,
+    /// `dark`, `light`, `hc-dark` or `hc-light` for colour themes.
+    pub ui_theme: Option<String>,
+    pub path: String,
+    /// Problems found while loading; a theme with errors cannot be applied.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Theme {
+    #[serde(flatten)]
+    pub summary: ThemeSummary,
+    /// The theme with `include`s merged in and icon paths made absolute.
+    pub data: Value,
+}
+
+/// `contributes.themes` / `contributes.iconThemes` entries.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Contribution {
+    id: Option<String>,
+    label: Option<String>,
+    ui_theme: Option<String>,
+    path: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Contributes {
+    #[serde(default)]
+    themes: Vec<Contribution>,
+    #[serde(default)]
+    icon_themes: Vec<Contribution>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageJson {
+    name: String,
+    #[serde(default)]
+    contributes: Contributes,
+}
+
+fn is_color(value: &str) -> bool {
+    value.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Reads a colour theme, merging the theme it `include`s underneath it.
+fn load_color_theme(path: &Path, depth: usize) -> Result<Map<String, Value>, String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(format!(
+            "Theme includes nest too deeply at {}",
+            path.display()
+        ));
+    }
+    let Value::Object(mut theme) = read_jsonc(path)? else {
+        return Err(format!("{} is not a JSON object", path.display()));
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    // `tokenColors` may point at a separate file.
+    if let Some(Value::String(file)) = theme.get("tokenColors").cloned() {
+        let tokens = read_jsonc(&dir.join(file))?;
+        let tokens = tokens.get("tokenColors").cloned().unwrap_or(tokens);
+        theme.insert("tokenColors".to_string(), tokens);
+    }
+
+    let Some(Value::String(include)) = theme.remove("include") else {
+        return Ok(theme);
+    };
+    let mut base = load_color_theme(&dir.join(include), depth + 1)?;
+    for (key, value) in theme {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(base_colors)), Value::Object(colors)) => base_colors.extend(colors),
+            (Some(Value::Array(base_rules)), Value::Array(rules)) => base_rules.extend(rules),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+    Ok(base)
+}
+
+fn validate_color_theme(theme: &Map<String, Value>) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !theme.contains_key("colors") && !theme.contains_key("tokenColors") {
+        errors.push("Neither `colors` nor `tokenColors` is defined".to_string());
+    }
+    match theme.get("colors") {
+        Some(Value::Object(colors)) => {
+            for (key, value) in colors {
+                // `null` resets a colour to the default.
+                if !value.is_null() && !value.as_str().is_some_and(is_color) {
+                    errors.push(format!("colors.{} is not a colour: {}", key, value));
+                }
+            }
+        }
+        Some(_) => errors.push("`colors` must be an object".to_string()),
+        None => {}
+    }
+    match theme.get("tokenColors") {
+        Some(Value::Array(rules)) => {
+            for (i, rule) in rules.iter().enumerate() {
+                if !rule.get("settings").is_some_and(Value::is_object) {
+                    errors.push(format!("tokenColors[{}] has no `settings`", i));
+                }
+            }
+        }
+        Some(_) => errors.push("`tokenColors` must be a list".to_string()),
+        None => {}
+    }
+    errors
+}
+
+/// Reads an icon theme, making every `iconPath` and font `src` path
+/// absolute and reporting the ones that do not exist.
+fn load_icon_theme(path: &Path) -> Result<(Value, Vec<String>), String> {
+    let mut theme = read_jsonc(path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut errors = Vec::new();
+    let resolve = |value: &mut Value, errors: &mut Vec<String>| {
+        if let Some(relative) = value.as_str() {
+            let absolute = dir.join(relative);
+            if !absolute.exists() {
+                errors.push(format!("Missing icon file: {}", relative));
+            }
+            *value = Value::String(absolute.to_string_lossy().to_string());
+        }
+    };
+
+    match theme.get_mut("iconDefinitions") {
+        Some(Value::Object(definitions)) => {
+            for definition in definitions.values_mut() {
+                if let Some(icon_path) = definition.get_mut("iconPath") {
+                    resolve(icon_path, &mut errors);
+                }
+            }
+        }
+        _ => errors.push("`iconDefinitions` must be an object".to_string()),
+    }
+    if let Some(Value::Array(fonts)) = theme.get_mut("fonts") {
+        for font in fonts {
+            if let Some(Value::Array(sources)) = font.get_mut("src") {
+                for source in sources {
+                    if let Some(path) = source.get_mut("path") {
+                        resolve(path, &mut errors);
+                    }
+                }
+            }
+        }
+    }
+    Ok((theme, errors))
+}
+
+fn load(summary: &ThemeSummary) -> (Value, Vec<String>) {
+    let path = Path::new(&summary.path);
+    let loaded = match summary.kind {
+        ThemeKind::Color => load_color_theme(path, 0).map(|theme| {
+            let errors = validate_color_theme(&theme);
+            (Value::Object(theme), errors)
+        }),
+        ThemeKind::Icon => load_icon_theme(path),
+    };
+    loaded.unwrap_or_else(|e| (Value::Null, vec![e]))
+}
+
+fn ui_theme_of(theme: &Value) -> Option<String> {
+    match theme.get("type").and_then(Value::as_str)? {
+        "dark" => Some("dark"),
+        "light" => Some("light"),
+        "hc" | "hcDark" | "hc-black" => Some("hc-dark"),
+        "hcLight" | "hc-light" => Some("hc-light"),
+        _ => None,
+    }
+    .map(str::to_string)
+}
+
+fn from_package(dir: &Path) -> Vec<ThemeSummary> {
+    let Ok(package) = read_jsonc(&dir.join("package.json"))
+        .and_then(|v| serde_json::from_value::<PackageJson>(v).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let entries = package
+        .contributes
+        .themes
+        .into_iter()
+        .map(|c| (c, ThemeKind::Color))
+        .chain(
+            package
+                .contributes
+                .icon_themes
+                .into_iter()
+                .map(|c| (c, ThemeKind::Icon)),
+        );
+    entries
+        .map(|(contribution, kind)| {
+            let label = contribution
+                .label
+                .clone()
+                .or_else(|| contribution.id.clone())
+                .unwrap_or_else(|| contribution.path.clone());
+            let name = contribution.id.clone().unwrap_or_else(|| label.clone());
+            ThemeSummary {
+                id: format!("{}.{}", package.name, name),
+                label,
+                kind,
+                ui_theme: contribution.ui_theme.map(|ui| match ui.as_str() {
+                    "vs" => "light".to_string(),
+                    "vs-dark" => "dark".to_string(),
+                    "hc-black" => "hc-dark".to_string(),
+                    "hc-light" => "hc-light".to_string(),
+                    other => other.to_string(),
+                }),
+                path: dir.join(&contribution.path).to_string_lossy().to_string(),
+                errors: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn from_file(path: &Path) -> Option<ThemeSummary> {
+    let theme = read_jsonc(path).ok()?;
+    let kind = if theme.get("iconDefinitions").is_some() {
+        ThemeKind::Icon
+    } else {
+        ThemeKind::Color
+    };
+    let id = path.file_stem()?.to_string_lossy().to_string();
+    Some(ThemeSummary {
+        label: theme
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| id.clone()),
+        id,
+        kind,
+        ui_theme: ui_theme_of(&theme),
+        path: path.to_string_lossy().to_string(),
+        errors: Vec::new(),
+    })
+}
+
+fn themes_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app_data::app_config_path(app, THEMES_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn discover(app: &AppHandle) -> Result<Vec<ThemeSummary>, String> {
+    let dir = themes_dir(app)?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    let mut themes = Vec::new();
+    for path in entries {
+        if path.is_dir() {
+            themes.extend(from_package(&path));
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            themes.extend(from_file(&path));
+        }
+    }
+    Ok(themes)
+}
+
+/// The id of the installed colour theme labelled `label`, as VS Code
+/// settings name themes.
+pub(crate) fn color_theme_id(app: &AppHandle, label: &str) -> Option<String> {
+    discover(app)
+        .ok()?
+        .into_iter()
+        .find(|theme| theme.kind == ThemeKind::Color && theme.label == label)
+        .map(|theme| theme.id)
+}
+
+/// Every theme in the user themes directory, validated.
+#[tauri::command]
+pub async fn list_themes(app: AppHandle) -> Result<Vec<ThemeSummary>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut themes = discover(&app)?;
+        for theme in &mut themes {
+            let (data, errors) = load(theme);
+            if theme.ui_theme.is_none() && theme.kind == ThemeKind::Color {
+                theme.ui_theme = ui_theme_of(&data);
+            }
+            theme.errors = errors;
+        }
+        Ok(themes)
+    })
+    .await
+    .map_err(|e| format!("Failed to list themes: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_theme(id: String, app: AppHandle) -> Result<Theme, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut summary = discover(&app)?
+            .into_iter()
+            .find(|theme| theme.id == id)
+            .ok_or_else(|| format!("Unknown theme: {}", id))?;
+        let (data, errors) = load(&summary);
+        if summary.ui_theme.is_none() && summary.kind == ThemeKind::Color {
+            summary.ui_theme = ui_theme_of(&data);
+        }
+        summary.errors = errors;
+        Ok(Theme { summary, data })
+    })
+    .await
+    .map_err(|e| format!("Failed to load theme: {}", e))?
+}
@@ -0,0 +1,388 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::AppHandle;
+
+use crate::app_data;
+use crate::hashing::{self, HashAlgorithm};
+use crate::lsp::{self, ServerCommand};
+use crate::process;
+
+const INSTALL_DIR: &str = "language-servers";
+const USER_AGENT: &str = "code-ai-ide";
+
+/// Serializes installs, so two windows asking for the same server do not
+/// download it twice into the same directory.
+static INSTALLING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+enum Install {
+    /// A gzipped binary attached to a GitHub release, checked against the
+    /// SHA-256 digest GitHub records for the asset.
+    GithubRelease { repo: &'static str },
+    /// npm packages; npm checks each tarball against the registry's
+    /// integrity hash.
+    Npm { packages: &'static [&'static str] },
+    /// A Go module; `go install` checks it against the Go checksum database.
+    Go { module: &'static str },
+}
+
+/// A server the IDE can install itself, at a pinned version.
+struct Managed {
+    binary: &'static str,
+    version: &'static str,
+    install: Install,
+}
+
+const MANAGED: &[Managed] = &[
+    Managed {
+        binary: "rust-analyzer",
+        version: "2025-08-25",
+        install: Install::GithubRelease {
+            repo: "rust-lang/rust-analyzer",
+        },
+    },
+    Managed {
+        binary: "typescript-language-server",
+        version: "4.3.4",
+        install: Install::Npm {
+            packages: &["typescript-language-server@4.3.4", "typescript@5.6.3"],
+        },
+    },
+    Managed {
+        binary: "pyright-langserver",
+        version: "1.1.385",
+        install: Install::Npm {
+            packages: &["pyright@1.1.385"],
+        },
+    },
+    Managed {
+        binary: "gopls",
+        version: "v0.16.2",
+        install: Install::Go {
+            module: "golang.org/x/tools/gopls",
+        },
+    },
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerSource {
+    /// Found on the user's PATH.
+    Path,
+    /// Installed by the IDE into the app data directory.
+    Installed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageServerLocation {
+    pub language: String,
+    /// Ready to pass to `lsp_start`.
+    pub server: ServerCommand,
+    pub source: ServerSource,
+    /// The pinned version, for installed servers.
+    pub version: Option<String>,
+}
+
+/// Finds an executable on the login PATH, trying the usual Windows
+/// extensions.
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let path = process::login_path()
+        .map(OsString::from)
+        .or_else(|| std::env::var_os("PATH"))?;
+    let names: Vec<String> = if cfg!(windows) {
+        ["exe", "cmd", "bat"]
+            .iter()
+            .map(|ext| format!("{}.{}", name, ext))
+            .collect()
+    } else {
+        vec![name.to_string()]
+    };
+    std::env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+fn executable(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn install_dir(app: &AppHandle, managed: &Managed) -> Result<PathBuf, String> {
+    Ok(app_data::app_data_path(app, INSTALL_DIR)?
+        .join(managed.binary)
+        .join(managed.version))
+}
+
+/// Where the server's executable ends up inside its install directory.
+fn installed_binary(managed: &Managed, dir: &Path) -> PathBuf {
+    match managed.install {
+        Install::GithubRelease { .. } | Install::Go { .. } => dir.join(executable(managed.binary)),
+        Install::Npm { .. } => {
+            let bin = dir.join("node_modules").join(".bin");
+            if cfg!(windows) {
+                bin.join(format!("{}.cmd", managed.binary))
+            } else {
+                bin.join(managed.binary)
+            }
+        }
+    }
+}
+
+fn with_binary(server: &ServerCommand, binary: &Path) -> ServerCommand {
+    ServerCommand {
+        command: binary.to_string_lossy().to_string(),
+        args: server.args.clone(),
+    }
+}
+
+fn managed_for(binary: &str) -> Option<&'static Managed> {
+    MANAGED.iter().find(|managed| managed.binary == binary)
+}
+
+/// Whether `ensure_language_server` can install the server `binary`.
+pub fn can_install(binary: &str) -> bool {
+    managed_for(binary).is_some()
+}
+
+/// The server for `language` if it is available without installing
+/// anything: on PATH first, then a copy the IDE installed earlier.
+pub fn locate(app: &AppHandle, language: &str) -> Option<LanguageServerLocation> {
+    let server = lsp::default_server(language)?;
+    if let Some(binary) = find_on_path(&server.command) {
+        return Some(LanguageServerLocation {
+            language: language.to_string(),
+            server: with_binary(&server, &binary),
+            source: ServerSource::Path,
+            version: None,
+        });
+    }
+    let managed = managed_for(&server.command)?;
+    let binary = installed_binary(managed, &install_dir(app, managed).ok()?);
+    binary.is_file().then(|| LanguageServerLocation {
+        language: language.to_string(),
+        server: with_binary(&server, &binary),
+        source: ServerSource::Installed,
+        version: Some(managed.version.to_string()),
+    })
+}
+
+fn rust_target() -> Option<&'static str> {
+    Some(match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => "x86_64-unknown-linux-gnu",
+        ("aarch64", "linux") => "aarch64-unknown-linux-gnu",
+        ("x86_64", "macos") => "x86_64-apple-darwin",
+        ("aarch64", "macos") => "aarch64-apple-darwin",
+        ("x86_64", "windows") => "x86_64-pc-windows-msvc",
+        ("aarch64", "windows") => "aarch64-pc-windows-msvc",
+        _ => return None,
+    })
+}
+
+#[derive(Deserialize)]
+struct Release {
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    /// `sha256:<hex>`
+    digest: Option<String>,
+}
+
+async fn install_github_release(managed: &Managed, repo: &str, dir: &Path) -> Result<(), String> {
+    let target = rust_target().ok_or_else(|| {
+        format!(
+            "No {} download for this platform; install it manually",
+            managed.binary
+        )
+    })?;
+    let asset_name = format!("{}-{}.gz", managed.binary, target);
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let release: Release = client
+        .get(format!(
+            "https://api.github.com/repos/{}/releases/tags/{}",
+            repo, managed.version
+        ))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to look up release: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read release: {}", e))?;
+    let asset = release
+        .assets
+        .into_iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| format!("Release {} has no {}", managed.version, asset_name))?;
+    // Without a published digest there is nothing to verify against.
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .ok_or_else(|| format!("No checksum published for {}", asset_name))?
+        .to_ascii_lowercase();
+
+    let bytes = client
+        .get(&asset.browser_download_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", asset_name, e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", asset_name, e))?;
+    let (actual, _) = hashing::hash_reader(&bytes[..], HashAlgorithm::Sha256)
+        .map_err(|e| format!("Failed to hash download: {}", e))?;
+    if actual != expected {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            asset_name, expected, actual
+        ));
+    }
+
+    let mut binary = Vec::new();
+    flate2::read::GzDecoder::new(&bytes[..])
+        .read_to_end(&mut binary)
+        .map_err(|e| format!("Failed to unpack {}: {}", asset_name, e))?;
+    let path = dir.join(executable(managed.binary));
+    app_data::write_atomic(&path, &binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Runs an installer tool (npm, go) with the login PATH.
+fn run_installer(tool: &str, mut cmd: Command) -> Result<(), String> {
+    if let Some(path) = process::login_path() {
+        cmd.env("PATH", path);
+    }
+    let output = cmd
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed: {}",
+            tool,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn install_npm(managed: &Managed, packages: &[&str], dir: &Path) -> Result<(), String> {
+    let npm = find_on_path("npm")
+        .ok_or_else(|| format!("Node.js and npm are needed to install {}", managed.binary))?;
+    let mut cmd = Command::new(npm);
+    cmd.arg("install")
+        .arg("--prefix")
+        .arg(dir)
+        .args([
+            "--save-exact",
+            "--no-audit",
+            "--no-fund",
+            "--ignore-scripts",
+        ])
+        .args(packages);
+    run_installer("npm", cmd)
+}
+
+fn install_go(managed: &Managed, module: &str, dir: &Path) -> Result<(), String> {
+    let go =
+        find_on_path("go").ok_or_else(|| format!("Go is needed to install {}", managed.binary))?;
+    let mut cmd = Command::new(go);
+    cmd.args(["install", &format!("{}@{}", module, managed.version)])
+        .env("GOBIN", dir)
+        // Keep checksum verification on even if the environment turns it off.
+        .env("GOSUMDB", "sum.golang.org")
+        .env_remove("GONOSUMDB")
+        .env_remove("GONOSUMCHECK")
+        .env_remove("GOINSECURE")
+        .env_remove("GOFLAGS");
+    run_installer("go", cmd)
+}
+
+/// Installs into a scratch directory renamed into place at the end, so an
+/// interrupted install is never mistaken for a finished one.
+async fn install(managed: &'static Managed, dir: &Path) -> Result<(), String> {
+    let partial = dir.with_file_name(format!("{}.partial", managed.version));
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+
+    let result = match managed.install {
+        Install::GithubRelease { repo } => install_github_release(managed, repo, &partial).await,
+        Install::Npm { packages } => {
+            let target = partial.clone();
+            tauri::async_runtime::spawn_blocking(move || install_npm(managed, packages, &target))
+                .await
+                .map_err(|e| format!("Install failed: {}", e))?
+        }
+        Install::Go { module } => {
+            let target = partial.clone();
+            tauri::async_runtime::spawn_blocking(move || install_go(managed, module, &target))
+                .await
+                .map_err(|e| format!("Install failed: {}", e))?
+        }
+    };
+    let result = result.and_then(|_| {
+        if installed_binary(managed, &partial).is_file() {
+            let _ = fs::remove_dir_all(dir);
+            fs::rename(&partial, dir)
+                .map_err(|e| format!("Failed to move {} into place: {}", managed.binary, e))
+        } else {
+            Err(format!("Install finished without {}", managed.binary))
+        }
+    });
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&partial);
+    }
+    result
+}
+
+/// Finds the language server for `language`, installing the pinned version
+/// into the app data directory when it is not on PATH.
+#[tauri::command]
+pub async fn ensure_language_server(
+    language: String,
+    app: AppHandle,
+) -> Result<LanguageServerLocation, String> {
+    if let Some(location) = locate(&app, &language) {
+        return Ok(location);
+    }
+    let server = lsp::default_server(&language)
+        .ok_or_else(|| format!("No language server known for {}", language))?;
+    let managed = managed_for(&server.command).ok_or_else(|| {
+        format!(
+            "{} is not installed and cannot be installed automatically",
+            server.command
+        )
+    })?;
+
+    let _guard = INSTALLING.lock().await;
+    // Another window may have finished the install while we waited.
+    if let Some(location) = locate(&app, &language) {
+        return Ok(location);
+    }
+    let dir = install_dir(&app, managed)?;
+    install(managed, &dir).await?;
+    locate(&app, &language).ok_or_else(|| format!("Failed to install {}", managed.binary))
+}
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::dialog;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::window_state::WindowRegistry;
+
+const STORE_FILE: &str = "trust.json";
+
+/// Which executables commands and tasks may start. Names are matched
+/// without directory or `.exe`, case-insensitively. An empty allowlist
+/// allows everything not denied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionPolicy {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TrustData {
+    trusted_roots: Vec<String>,
+    policy: ExecutionPolicy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustStatus {
+    pub root: String,
+    pub trusted: bool,
+}
+
+/// Trusted workspaces and the execution policy, shared by every window and
+/// persisted in the app data directory. Only native dialogs change either,
+/// so the webview cannot grant itself the right to run programs.
+#[derive(Default)]
+pub struct TrustStore {
+    data: Mutex<Option<(PathBuf, TrustData)>>,
+}
+
+impl TrustStore {
+    fn with_data<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut TrustData) -> (R, bool),
+    ) -> Result<R, String> {
+        let mut guard = self.data.lock().unwrap();
+        if guard.is_none() {
+            let path = app_data::app_data_path(app, STORE_FILE)?;
+            let data = app_data::load_json(&path);
+            *guard = Some((path, data));
+        }
+        let (path, data) = guard.as_mut().unwrap();
+        let (result, changed) = f(data);
+        if changed {
+            app_data::save_json(path, data)?;
+        }
+        Ok(result)
+    }
+
+    pub fn is_trusted(&self, app: &AppHandle, root: &Path) -> Result<bool, String> {
+        self.with_data(app, |data| {
+            let trusted = data
+                .trusted_roots
+                .iter()
+                .any(|trusted| root.starts_with(trusted));
+            (trusted, false)
+        })
+    }
+
+    fn set_trusted(&self, app: &AppHandle, root: &Path, trusted: bool) -> Result<(), String> {
+        let root = root.to_string_lossy().to_string();
+        self.with_data(app, |data| {
+            data.trusted_roots.retain(|existing| *existing != root);
+            if trusted {
+                data.trusted_roots.push(root);
+            }
+            ((), true)
+        })
+    }
+
+    pub fn policy(&self, app: &AppHandle) -> Result<ExecutionPolicy, String> {
+        self.with_data(app, |data| (data.policy.clone(), false))
+    }
+}
+
+fn executable_name(program: &str) -> String {
+    let name = Path::new(program.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.strip_suffix(".exe")
+        .or_else(|| name.strip_suffix(".cmd"))
+        .or_else(|| name.strip_suffix(".bat"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// The programs a command would start. For a shell line, the first word of
+/// every pipeline segment; anything fancier (subshells, `$(...)`) is beyond
+/// a policy check and should be denied by leaving the shell off the
+/// allowlist.
+fn programs(command: &str, use_shell: bool) -> Vec<String> {
+    if !use_shell {
+        return vec![executable_name(command)];
+    }
+    // `2>&1` and `&>` are redirections, not command separators.
+    command
+        .replace(">&", ">")
+        .replace("&>", ">")
+        .split(['&', '|', ';', '\n'])
+        .filter_map(|segment| {
+            segment
+                .split_whitespace()
+                // Skip leading `VAR=value` assignments.
+                .find(|word| !word.contains('='))
+                .map(executable_name)
+        })
+        .collect()
+}
+
+/// The workspace root a command runs in: the root containing `cwd`, or the
+/// window's active root.
+fn root_for(windows: &WindowRegistry, window: &Window, cwd: Option<&Path>) -> Option<PathBuf> {
+    let scope = windows.scope(window.label());
+    let roots = scope.workspace.roots();
+    cwd.and_then(|cwd| roots.iter().find(|root| cwd.starts_with(root)).cloned())
+        .or_else(|| scope.workspace.active())
+        .or_else(|| roots.into_iter().next())
+}
+
+/// Refuses to start `command` unless the workspace it runs in is trusted
+/// and the execution policy allows every program involved.
+pub fn check_execution(
+    window: &Window,
+    windows: &WindowRegistry,
+    command: &str,
+    use_shell: bool,
+    cwd: Option<&Path>,
+) -> Result<(), String> {
+    let app = window.app_handle();
+    let store = window.state::<TrustStore>();
+    let root = root_for(windows, window, cwd).ok_or_else(|| "No workspace is open".to_string())?;
+    if !store.is_trusted(&app, &root)? {
+        return Err(format!(
+            "Workspace is not trusted: {}. Trust it to run commands and tasks.",
+            root.display()
+        ));
+    }
+
+    let policy = store.policy(&app)?;
+    let names = |list: &[String]| -> Vec<String> {
+        list.iter().map(|name| executable_name(name)).collect()
+    };
+    let (allow, deny) = (names(&policy.allow), names(&policy.deny));
+    let shell = if cfg!(windows) { "cmd" } else { "sh" };
+    let mut checked = programs(command, use_shell);
+    if use_shell {
+        checked.push(shell.to_string());
+    }
+    for program in checked {
+        if deny.contains(&program) || (!allow.is_empty() && !allow.contains(&program)) {
+            return Err(format!(
+                "Running {} is not allowed by the execution policy",
+                program
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn resolve_root(
+    windows: &WindowRegistry,
+    window: &Window,
+    root: Option<String>,
+) -> Result<PathBuf, String> {
+    match root {
+        Some(root) => crate::workspace::authorize(windows, window, &root),
+        None => root_for(windows, window, None).ok_or_else(|| "No workspace is open".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_workspace_trust(
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    trust: State<'_, TrustStore>,
+) -> Result<TrustStatus, String> {
+    let root = resolve_root(&windows, &window, root)?;
+    Ok(TrustStatus {
+        trusted: trust.is_trusted(&app, &root)?,
+        root: root.to_string_lossy().to_string(),
+    })
+}
+
+/// Asks the user, in a native dialog, whether to trust the workspace.
+#[tauri::command]
+pub async fn request_workspace_trust(
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    trust: State<'_, TrustStore>,
+) -> Result<TrustStatus, String> {
+    let root = resolve_root(&windows, &window, root)?;
+    let mut trusted = trust.is_trusted(&app, &root)?;
+    if !trusted {
+        trusted = dialog::blocking::ask(
+            Some(&window),
+            "Trust this workspace?",
+            format!(
+                "Trusting a workspace lets the editor run its build scripts, tasks and commands:\n\n{}\n\nOnly trust code you know.",
+                root.display()
+            ),
+        );
+        if trusted {
+            trust.set_trusted(&app, &root, true)?;
+        }
+    }
+    Ok(TrustStatus {
+        root: root.to_string_lossy().to_string(),
+        trusted,
+    })
+}
+
+/// Revoking needs no confirmation; it only takes permissions away.
+#[tauri::command]
+pub async fn revoke_workspace_trust(
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    trust: State<'_, TrustStore>,
+) -> Result<(), String> {
+    let root = resolve_root(&windows, &window, root)?;
+    trust.set_trusted(&app, &root, false)
+}
+
+#[tauri::command]
+pub async fn get_execution_policy(
+    app: AppHandle,
+    trust: State<'_, TrustStore>,
+) -> Result<ExecutionPolicy, String> {
+    trust.policy(&app)
+}
+
+/// Replaces the execution policy after the user confirms the new lists in
+/// a native dialog. Returns whether it was applied.
+#[tauri::command]
+pub async fn set_execution_policy(
+    policy: ExecutionPolicy,
+    app: AppHandle,
+    window: Window,
+    trust: State<'_, TrustStore>,
+) -> Result<bool, String> {
+    let list = |names: &[String]| {
+        if names.is_empty() {
+            "(none)".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+    let approved = dialog::blocking::ask(
+        Some(&window),
+        "Change execution policy?",
+        format!(
+            "Allowed programs: {}\nDenied programs: {}",
+            list(&policy.allow),
+            list(&policy.deny)
+        ),
+    );
+    if approved {
+        trust.with_data(&app, |data| {
+            data.policy = policy;
+            ((), true)
+        })?;
+    }
+    Ok(approved)
+}
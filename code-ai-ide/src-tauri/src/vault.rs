@@ -0,0 +1,259 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::window_state::WindowRegistry;
+
+/// Keychain entry holding the key every vault is sealed with.
+const KEYCHAIN_SERVICE: &str = "code-ai-ide";
+const KEYCHAIN_ACCOUNT: &str = "workspace-vault-key";
+
+/// Under app data, never in the workspace, so the secrets cannot be
+/// committed by accident.
+const VAULTS_DIR: &str = "vaults";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// A vault file. The secrets are a JSON object sealed with
+/// ChaCha20-Poly1305, bound to the workspace root as associated data so a
+/// vault copied to another workspace does not open.
+#[derive(Serialize, Deserialize)]
+struct SealedVault {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Serializes changes to vault files.
+#[derive(Default)]
+pub struct WorkspaceVaults {
+    lock: Mutex<()>,
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+        .map_err(|e| format!("Failed to open keychain: {}", e))
+}
+
+/// The vault key, created on first use when `create` is set.
+fn vault_key(create: bool) -> Result<Option<Key>, String> {
+    let entry = keychain_entry()?;
+    match entry.get_password() {
+        Ok(encoded) => {
+            let bytes = STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("Invalid vault key in keychain: {}", e))?;
+            if bytes.len() != 32 {
+                return Err("Invalid vault key in keychain".to_string());
+            }
+            Ok(Some(*Key::from_slice(&bytes)))
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            entry
+                .set_password(&STANDARD.encode(key))
+                .map_err(|e| format!("Failed to write to keychain: {}", e))?;
+            Ok(Some(key))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read from keychain: {}", e)),
+    }
+}
+
+fn vault_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let hash = blake3::hash(root.to_string_lossy().as_bytes());
+    app_data::app_data_path(
+        app,
+        &format!("{}/{}.vault", VAULTS_DIR, &hash.to_hex()[..32]),
+    )
+}
+
+fn read_vault(path: &Path, root: &Path, key: &Key) -> Result<BTreeMap<String, String>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let sealed: SealedVault = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid vault {}: {}", path.display(), e))?;
+    if sealed.version != FORMAT_VERSION {
+        return Err(format!("Unsupported vault version {}", sealed.version));
+    }
+    let decode = |text: &str| {
+        STANDARD
+            .decode(text)
+            .map_err(|e| format!("Invalid vault {}: {}", path.display(), e))
+    };
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(format!("Invalid vault {}", path.display()));
+    }
+    let plaintext = ChaCha20Poly1305::new(key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &decode(&sealed.ciphertext)?,
+                aad: root.to_string_lossy().as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            "Failed to unlock the workspace vault; it was sealed with another key".to_string()
+        })?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("Invalid vault {}: {}", path.display(), e))
+}
+
+fn write_vault(
+    path: &Path,
+    root: &Path,
+    key: &Key,
+    secrets: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: root.to_string_lossy().as_bytes(),
+            },
+        )
+        .map_err(|_| "Failed to seal the workspace vault".to_string())?;
+    app_data::save_json(
+        path,
+        &SealedVault {
+            version: FORMAT_VERSION,
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        },
+    )
+}
+
+/// The secrets stored for `root`; empty without touching the keychain
+/// when the workspace has no vault.
+pub fn secrets_for(app: &AppHandle, root: &Path) -> Result<BTreeMap<String, String>, String> {
+    let path = vault_path(app, root)?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    match vault_key(false)? {
+        Some(key) => read_vault(&path, root, &key),
+        None => Err("The workspace vault key is missing from the keychain".to_string()),
+    }
+}
+
+fn active_root(window: &Window, windows: &WindowRegistry) -> Result<PathBuf, String> {
+    windows
+        .scope(window.label())
+        .workspace
+        .active()
+        .ok_or_else(|| "No workspace is open".to_string())
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("Invalid variable name: {}", name));
+    }
+    Ok(())
+}
+
+/// Reads the vault of `root`, lets `change` edit it and seals it again
+/// when `change` says it changed anything.
+fn update<T>(
+    app: &AppHandle,
+    root: &Path,
+    change: impl FnOnce(&mut BTreeMap<String, String>) -> (T, bool),
+) -> Result<T, String> {
+    let vaults = app.state::<WorkspaceVaults>();
+    let _guard = vaults.lock.lock().unwrap();
+    let path = vault_path(app, root)?;
+    let key = vault_key(true)?.ok_or("Failed to create the vault key")?;
+    let mut secrets = read_vault(&path, root, &key)?;
+    let (result, changed) = change(&mut secrets);
+    if changed {
+        write_vault(&path, root, &key, &secrets)?;
+    }
+    Ok(result)
+}
+
+/// Stores `value` as the secret environment variable `name` of the active
+/// workspace. Secrets live in an encrypted file under app data whose key
+/// is in the OS keychain, never in the workspace, and are set for every
+/// task run there.
+#[tauri::command]
+pub async fn set_workspace_secret(
+    name: String,
+    value: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    check_name(&name)?;
+    let root = active_root(&window, &windows)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        update(&app, &root, |secrets| {
+            secrets.insert(name, value);
+            ((), true)
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to store secret: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_workspace_secret(
+    name: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    let root = active_root(&window, &windows)?;
+    tauri::async_runtime::spawn_blocking(move || Ok(secrets_for(&app, &root)?.remove(&name)))
+        .await
+        .map_err(|e| format!("Failed to read secret: {}", e))?
+}
+
+/// Names of the active workspace's secrets; values are never listed.
+#[tauri::command]
+pub async fn list_workspace_secrets(
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<String>, String> {
+    let root = active_root(&window, &windows)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(secrets_for(&app, &root)?.into_keys().collect())
+    })
+    .await
+    .map_err(|e| format!("Failed to read secrets: {}", e))?
+}
+
+/// Returns whether there was a secret to delete.
+#[tauri::command]
+pub async fn delete_workspace_secret(
+    name: String,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let root = active_root(&window, &windows)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        update(&app, &root, |secrets| {
+            let removed = secrets.remove(&name).is_some();
+            (removed, removed)
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to delete secret: {}", e))?
+}
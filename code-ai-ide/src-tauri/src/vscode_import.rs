@@ -0,0 +1,693 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, State, Window};
+
+use crate::debug::{self, LaunchConfig};
+use crate::jsonc::read_jsonc;
+use crate::settings;
+use crate::tasks::{self, CustomTask, DependsOrder};
+use crate::themes;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+const VSCODE_DIR: &str = ".vscode";
+
+/// A key of a `.vscode` file that was not imported.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedKey {
+    /// `settings.json`, `tasks.json` or `launch.json`.
+    pub file: String,
+    /// The setting, or a path such as `tasks[build].problemMatcher`.
+    pub key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VscodeImport {
+    pub root: String,
+    /// Settings added to the workspace settings.
+    pub settings: Vec<String>,
+    /// Labels of the tasks added to the custom tasks.
+    pub tasks: Vec<String>,
+    /// Names of the launch configurations added.
+    pub launch_configs: Vec<String>,
+    pub skipped: Vec<SkippedKey>,
+    /// Nothing was written; the lists say what would be.
+    pub dry_run: bool,
+}
+
+/// What an import found, before anything is written.
+#[derive(Default)]
+struct Found {
+    settings: Map<String, Value>,
+    tasks: Vec<CustomTask>,
+    launch_configs: Vec<LaunchConfig>,
+    skipped: Vec<SkippedKey>,
+}
+
+impl Found {
+    fn skip(&mut self, file: &str, key: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedKey {
+            file: file.to_string(),
+            key: key.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// VS Code's name for this platform in `.linux`-style keys and task
+/// overrides.
+fn platform() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "osx"
+    } else {
+        "linux"
+    }
+}
+
+fn is_other_platform(name: &str) -> bool {
+    ["windows", "osx", "linux"].contains(&name) && name != platform()
+}
+
+/// The default shell a terminal profile names, from
+/// `terminal.integrated.profiles.<platform>`.
+fn profile_shell(vscode: &Map<String, Value>, name: &str) -> Option<String> {
+    let profile = vscode
+        .get(&format!("terminal.integrated.profiles.{}", platform()))?
+        .get(name)?;
+    match &profile["path"] {
+        Value::String(path) => Some(path.clone()),
+        Value::Array(paths) => paths.first()?.as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+fn encoding_name(vscode: &str) -> Option<String> {
+    let label = match vscode {
+        "utf8" => "utf-8",
+        "utf16le" => "utf-16le",
+        "utf16be" => "utf-16be",
+        "windows1252" => "windows-1252",
+        "iso88591" => "iso-8859-1",
+        "shiftjis" => "shift_jis",
+        other => other,
+    };
+    encoding_rs::Encoding::for_label(label.as_bytes()).map(|e| e.name().to_ascii_lowercase())
+}
+
+/// The IDE setting a VS Code setting becomes, or why it has none.
+fn convert_setting(
+    app: &AppHandle,
+    vscode: &Map<String, Value>,
+    key: &str,
+    value: &Value,
+) -> Result<(String, Value), String> {
+    let same =
+        |value: &Value| -> Result<(String, Value), String> { Ok((key.to_string(), value.clone())) };
+    match key {
+        "editor.tabSize"
+        | "editor.insertSpaces"
+        | "editor.fontSize"
+        | "editor.fontFamily"
+        | "editor.formatOnSave"
+        | "files.autoSaveDelay" => same(value),
+        "editor.wordWrap" => match value.as_str() {
+            Some("wordWrapColumn") => same(&Value::from("bounded")),
+            _ => same(value),
+        },
+        "files.autoSave" => match value.as_str() {
+            Some("onWindowChange") => same(&Value::from("onFocusChange")),
+            _ => same(value),
+        },
+        "files.encoding" => match value.as_str() {
+            Some("utf8bom") => Err("new files are written without a byte order mark".to_string()),
+            Some(name) => encoding_name(name)
+                .map(|name| (key.to_string(), Value::from(name)))
+                .ok_or_else(|| format!("unknown encoding {}", name)),
+            None => same(value),
+        },
+        "files.eol" => match value.as_str() {
+            Some("\n") => same(&Value::from("lf")),
+            Some("\r\n") => same(&Value::from("crlf")),
+            _ => same(value),
+        },
+        // A map of globs to `true`, or to a `when` condition.
+        "files.exclude" => {
+            let globs = value
+                .as_object()
+                .ok_or("expected an object of globs")?
+                .iter()
+                .filter(|(_, enabled)| enabled.as_bool() == Some(true))
+                .map(|(glob, _)| Value::from(glob.clone()))
+                .collect();
+            Ok((key.to_string(), Value::Array(globs)))
+        }
+        // VS Code names themes by label.
+        "workbench.colorTheme" => {
+            let label = value.as_str().ok_or("expected a theme name")?;
+            themes::color_theme_id(app, label)
+                .map(|id| (key.to_string(), Value::from(id)))
+                .ok_or_else(|| format!("theme {} is not installed", label))
+        }
+        _ if key.starts_with('[') => {
+            Err("language-specific settings are not supported".to_string())
+        }
+        _ => {
+            let terminal = key
+                .strip_prefix("terminal.integrated.shell.")
+                .map(|os| (os, value.as_str().map(str::to_string)))
+                .or_else(|| {
+                    key.strip_prefix("terminal.integrated.defaultProfile.")
+                        .map(|os| {
+                            (
+                                os,
+                                value.as_str().and_then(|name| profile_shell(vscode, name)),
+                            )
+                        })
+                });
+            match terminal {
+                Some((os, _)) if is_other_platform(os) => Err("for another platform".to_string()),
+                Some((_, Some(shell))) => Ok(("terminal.shell".to_string(), Value::from(shell))),
+                Some((_, None)) => Err("the profile sets no shell path".to_string()),
+                None => Err("no equivalent setting".to_string()),
+            }
+        }
+    }
+}
+
+fn import_settings(app: &AppHandle, root: &Path, found: &mut Found) -> Result<(), String> {
+    const FILE: &str = "settings.json";
+    let path = root.join(VSCODE_DIR).join(FILE);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let Value::Object(vscode) = read_jsonc(&path)? else {
+        return Err(format!("Invalid {}: expected an object", path.display()));
+    };
+    let existing = settings::workspace_settings(root)?;
+    for (key, value) in &vscode {
+        // Read along with `defaultProfile`.
+        if key.starts_with("terminal.integrated.profiles.") {
+            continue;
+        }
+        let (ide_key, value) = match convert_setting(app, &vscode, key, value) {
+            Ok(converted) => converted,
+            Err(reason) => {
+                found.skip(FILE, key, reason);
+                continue;
+            }
+        };
+        if let Err(e) = settings::check_value(&ide_key, &value) {
+            found.skip(FILE, key, e);
+        } else if existing.contains_key(&ide_key) || found.settings.contains_key(&ide_key) {
+            found.skip(FILE, key, format!("{} is already set", ide_key));
+        } else {
+            found.settings.insert(ide_key, value);
+        }
+    }
+    Ok(())
+}
+
+/// `${name}` in task and launch properties.
+fn variable_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\$\{([^}]+)\}").expect("valid regex"))
+}
+
+/// Replaces the workspace variables in `text` with `to_root`, the path of
+/// the root from where the task runs. Other variables, such as `${file}`
+/// or `${input:name}`, depend on the moment the task runs and cannot be
+/// stored.
+fn substitute(text: &str, root: &Path, to_root: &str) -> Result<String, String> {
+    let mut unknown = None;
+    let result = variable_pattern().replace_all(text, |captures: &Captures| match &captures[1] {
+        "workspaceFolder" | "workspaceRoot" => to_root.to_string(),
+        "workspaceFolderBasename" => root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        "pathSeparator" | "/" => std::path::MAIN_SEPARATOR.to_string(),
+        other => {
+            unknown.get_or_insert_with(|| other.to_string());
+            String::new()
+        }
+    });
+    match unknown {
+        Some(name) => Err(format!("uses ${{{}}}", name)),
+        None => Ok(result.into_owned()),
+    }
+}
+
+/// A working directory relative to the root, `None` for the root itself.
+fn relative_cwd(cwd: &str, root: &Path) -> Result<Option<String>, String> {
+    let cwd = substitute(cwd, root, ".")?;
+    let mut parts = Vec::new();
+    for component in Path::new(&cwd).components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            _ => return Err("the working directory is outside the workspace".to_string()),
+        }
+    }
+    Ok((!parts.is_empty()).then(|| parts.join("/")))
+}
+
+/// The way back to the root from `cwd`.
+fn to_root(cwd: Option<&str>) -> String {
+    match cwd {
+        None => ".".to_string(),
+        Some(cwd) => vec![".."; cwd.split('/').count()].join("/"),
+    }
+}
+
+/// A command or argument: a string, or `{ "value": ... }` with quoting
+/// options.
+fn string_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Object(object) => string_value(object.get("value")?),
+        Value::Array(parts) => parts
+            .iter()
+            .map(string_value)
+            .collect::<Option<Vec<_>>>()
+            .map(|parts| parts.join(" ")),
+        _ => None,
+    }
+}
+
+fn string_list(value: Option<&Value>) -> Result<Vec<String>, String> {
+    match value {
+        None => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| string_value(item).ok_or_else(|| "expected strings".to_string()))
+            .collect(),
+        Some(_) => Err("expected a list".to_string()),
+    }
+}
+
+fn string_map(
+    value: Option<&Value>,
+    root: &Path,
+    to_root: &str,
+) -> Result<HashMap<String, String>, String> {
+    let Some(value) = value else {
+        return Ok(HashMap::new());
+    };
+    value
+        .as_object()
+        .ok_or("expected an object")?
+        .iter()
+        .map(|(key, value)| -> Result<(String, String), String> {
+            let value = value.as_str().ok_or("expected string values")?;
+            Ok((key.clone(), substitute(value, root, to_root)?))
+        })
+        .collect()
+}
+
+/// Merges the override for this platform (`windows`, `osx`, `linux`) into
+/// `object`, deeply for `options`.
+fn with_platform_override(object: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = object.clone();
+    let Some(Value::Object(overrides)) = object.get(platform()) else {
+        return merged;
+    };
+    for (key, value) in overrides {
+        match (merged.get_mut(key), value) {
+            (Some(Value::Object(base)), Value::Object(value)) if key == "options" => {
+                base.extend(value.clone());
+            }
+            _ => {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Properties of VS Code tasks with an equivalent, beyond those of
+/// `CustomTask`.
+const TASK_KEYS: &[&str] = &[
+    "label",
+    "type",
+    "command",
+    "args",
+    "options",
+    "dependsOn",
+    "dependsOrder",
+    "script",
+    "windows",
+    "osx",
+    "linux",
+];
+
+/// Turns a `tasks.json` task into a custom task. `ids` maps VS Code task
+/// labels to the ids of existing tasks, for `dependsOn`.
+fn convert_task(
+    task: &Map<String, Value>,
+    label: &str,
+    root: &Path,
+    ids: &HashMap<String, String>,
+    found: &mut Found,
+) -> Result<CustomTask, String> {
+    const FILE: &str = "tasks.json";
+    let task = with_platform_override(task);
+    let kind = task
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("process");
+    let options = task.get("options").and_then(Value::as_object);
+    let cwd = match options
+        .and_then(|options| options.get("cwd"))
+        .and_then(Value::as_str)
+    {
+        Some(cwd) => relative_cwd(cwd, root)?,
+        None => None,
+    };
+    let to_root = to_root(cwd.as_deref());
+    let command = task
+        .get("command")
+        .map(|command| string_value(command).ok_or("the command is not a string"))
+        .transpose()?;
+    let mut args = string_list(task.get("args"))?;
+    let (command, shell) = match (kind, command) {
+        ("shell", Some(command)) => (command, true),
+        ("process", Some(command)) => (command, false),
+        // rust-analyzer's tasks: `command` is the cargo subcommand.
+        ("cargo", Some(subcommand)) => {
+            args.insert(0, subcommand);
+            ("cargo".to_string(), false)
+        }
+        (_, None) => {
+            return Err(
+                "it has no command; tasks that only run others are not supported".to_string(),
+            )
+        }
+        (kind, Some(_)) => return Err(format!("{} tasks are not supported", kind)),
+    };
+    let command = substitute(&command, root, &to_root)?;
+    let args = args
+        .iter()
+        .map(|arg| substitute(arg, root, &to_root))
+        .collect::<Result<_, _>>()?;
+    let env = string_map(
+        options.and_then(|options| options.get("env")),
+        root,
+        &to_root,
+    )?;
+
+    let depends_on = match task.get("dependsOn") {
+        None => Vec::new(),
+        Some(Value::String(label)) => vec![label.clone()],
+        Some(labels) => string_list(Some(labels))?,
+    };
+    let depends_on = depends_on
+        .into_iter()
+        .filter_map(|dependency| match ids.get(&dependency) {
+            Some(id) => Some(id.clone()),
+            None => {
+                found.skip(
+                    FILE,
+                    format!("tasks[{}].dependsOn", label),
+                    format!("no task is labelled {}", dependency),
+                );
+                None
+            }
+        })
+        .collect();
+
+    for key in task.keys().filter(|key| !TASK_KEYS.contains(&key.as_str())) {
+        let reason = match key.as_str() {
+            "problemMatcher" => "problems in task output are matched automatically",
+            _ => "no equivalent task property",
+        };
+        found.skip(FILE, format!("tasks[{}].{}", label, key), reason);
+    }
+    if let Some(options) = options {
+        for key in options
+            .keys()
+            .filter(|key| !["cwd", "env"].contains(&key.as_str()))
+        {
+            found.skip(
+                FILE,
+                format!("tasks[{}].options.{}", label, key),
+                "no equivalent task option",
+            );
+        }
+    }
+
+    Ok(CustomTask {
+        label: label.to_string(),
+        command,
+        args,
+        cwd,
+        env,
+        shell,
+        depends_on,
+        depends_order: match task.get("dependsOrder").and_then(Value::as_str) {
+            Some("parallel") => DependsOrder::Parallel,
+            _ => DependsOrder::Sequence,
+        },
+        env_profile: None,
+    })
+}
+
+fn is_npm(task: &Map<String, Value>) -> bool {
+    task.get("type").and_then(Value::as_str) == Some("npm")
+}
+
+/// The label VS Code gives a task that sets none.
+fn task_label(task: &Map<String, Value>) -> Option<String> {
+    if let Some(label) = task.get("label").and_then(Value::as_str) {
+        return Some(label.to_string());
+    }
+    let kind = task.get("type")?.as_str()?;
+    match kind {
+        "npm" => Some(format!("npm: {}", task.get("script")?.as_str()?)),
+        "cargo" => Some(format!("rust: cargo {}", task.get("command")?.as_str()?)),
+        _ => None,
+    }
+}
+
+fn import_tasks(
+    root: &Path,
+    ids: &mut HashMap<String, String>,
+    found: &mut Found,
+) -> Result<(), String> {
+    const FILE: &str = "tasks.json";
+    let path = root.join(VSCODE_DIR).join(FILE);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let file = read_jsonc(&path)?;
+    let Some(vscode_tasks) = file.get("tasks").and_then(Value::as_array) else {
+        return Ok(());
+    };
+    for key in file
+        .as_object()
+        .into_iter()
+        .flat_map(|file| file.keys())
+        .filter(|key| !["version", "tasks"].contains(&key.as_str()))
+    {
+        found.skip(FILE, key, "no equivalent");
+    }
+
+    let tasks: Vec<(String, &Map<String, Value>)> = vscode_tasks
+        .iter()
+        .enumerate()
+        .filter_map(|(i, task)| {
+            let task = task.as_object()?;
+            match task_label(task) {
+                Some(label) => Some((label, task)),
+                None => {
+                    found.skip(FILE, format!("tasks[{}]", i), "it has no label");
+                    None
+                }
+            }
+        })
+        .collect();
+    // npm scripts are detected as they are; the rest become custom tasks.
+    for (label, task) in &tasks {
+        let id = match task.get("script").and_then(Value::as_str) {
+            Some(script) if is_npm(task) => format!("npm:{}", script),
+            _ => format!("custom:{}", label),
+        };
+        ids.entry(label.clone()).or_insert(id);
+    }
+    for (label, task) in tasks {
+        if is_npm(task) {
+            continue;
+        }
+        match convert_task(task, &label, root, ids, found) {
+            Ok(task) => found.tasks.push(task),
+            Err(reason) => found.skip(FILE, format!("tasks[{}]", label), reason),
+        }
+    }
+    Ok(())
+}
+
+/// Copies `launch.json` configurations into the workspace's, with this
+/// platform's overrides applied. The format is the same; only
+/// `preLaunchTask` labels are mapped to task ids.
+fn import_launch(
+    root: &Path,
+    ids: &HashMap<String, String>,
+    found: &mut Found,
+) -> Result<(), String> {
+    const FILE: &str = "launch.json";
+    let path = root.join(VSCODE_DIR).join(FILE);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let file = read_jsonc(&path)?;
+    if file.get("compounds").is_some() {
+        found.skip(FILE, "compounds", "no equivalent");
+    }
+    let configs = file
+        .get("configurations")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object);
+    for (i, config) in configs.enumerate() {
+        let mut config = with_platform_override(config);
+        for os in ["windows", "osx", "linux"] {
+            config.remove(os);
+        }
+        let mut config: LaunchConfig = match serde_json::from_value(Value::Object(config)) {
+            Ok(config) => config,
+            Err(e) => {
+                found.skip(FILE, format!("configurations[{}]", i), e.to_string());
+                continue;
+            }
+        };
+        let key = format!("configurations[{}]", config.name);
+        if !debug::has_adapter(&config.kind) && !config.properties.contains_key("debugServer") {
+            found.skip(FILE, key, format!("no debug adapter for {}", config.kind));
+            continue;
+        }
+        if let Some(Value::String(task)) = config.properties.get_mut("preLaunchTask") {
+            match ids.get(task.as_str()) {
+                Some(id) => *task = id.clone(),
+                None => found.skip(
+                    FILE,
+                    format!("{}.preLaunchTask", key),
+                    format!("no task is labelled {}", task),
+                ),
+            }
+        }
+        found.launch_configs.push(config);
+    }
+    Ok(())
+}
+
+fn import(app: &AppHandle, root: PathBuf, dry_run: bool) -> Result<VscodeImport, String> {
+    if !root.join(VSCODE_DIR).is_dir() {
+        return Err(format!("No {} folder in {}", VSCODE_DIR, root.display()));
+    }
+    let mut found = Found::default();
+    import_settings(app, &root, &mut found)?;
+
+    let existing = tasks::detect_tasks(&root)?;
+    let mut ids: HashMap<String, String> = existing
+        .iter()
+        .map(|task| (task.label.clone(), task.id.clone()))
+        .collect();
+    import_tasks(&root, &mut ids, &mut found)?;
+    import_launch(&root, &ids, &mut found)?;
+
+    // Custom tasks the workspace already has are kept.
+    let (taken, mut tasks): (Vec<CustomTask>, Vec<CustomTask>) =
+        found.tasks.into_iter().partition(|task| {
+            existing
+                .iter()
+                .any(|existing| existing.id == format!("custom:{}", task.label))
+        });
+    for task in taken {
+        found.skipped.push(SkippedKey {
+            file: VSCODE_DIR.to_string(),
+            key: task.label,
+            reason: "a custom task has this label already".to_string(),
+        });
+    }
+    // Dependencies on tasks that could not be imported would fail to run.
+    let available: Vec<String> = existing
+        .iter()
+        .map(|task| task.id.clone())
+        .chain(tasks.iter().map(|task| format!("custom:{}", task.label)))
+        .collect();
+    for task in &mut tasks {
+        let label = &task.label;
+        let skipped = &mut found.skipped;
+        task.depends_on.retain(|id| {
+            let known = available.contains(id);
+            if !known {
+                skipped.push(SkippedKey {
+                    file: VSCODE_DIR.to_string(),
+                    key: format!("{}: dependency {}", label, id),
+                    reason: "the task it depends on was not imported".to_string(),
+                });
+            }
+            known
+        });
+    }
+    let (settings, tasks, launch_configs) = if dry_run {
+        (
+            found.settings.keys().cloned().collect(),
+            tasks.iter().map(|task| task.label.clone()).collect(),
+            found
+                .launch_configs
+                .iter()
+                .map(|config| config.name.clone())
+                .collect(),
+        )
+    } else {
+        (
+            settings::add_workspace_settings(app, &root, found.settings)?,
+            tasks::add_custom_tasks(&root, tasks)?,
+            debug::add_launch_configs(&root, found.launch_configs)?,
+        )
+    };
+    Ok(VscodeImport {
+        root: root.to_string_lossy().to_string(),
+        settings,
+        tasks,
+        launch_configs,
+        skipped: found.skipped,
+        dry_run,
+    })
+}
+
+/// Imports a project's `.vscode` configuration: `settings.json` into the
+/// workspace settings, `tasks.json` into the custom tasks and
+/// `launch.json` into the launch configurations. Whatever the workspace
+/// already sets is kept. Everything that could not be carried over is listed with
+/// the reason. `dry_run` reports without writing. Defaults to the active
+/// workspace.
+#[tauri::command]
+pub async fn import_vscode_config(
+    root: Option<String>,
+    dry_run: Option<bool>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<VscodeImport, String> {
+    let root = match root {
+        Some(root) => workspace::authorize(&windows, &window, &root)?,
+        None => windows
+            .scope(window.label())
+            .workspace
+            .active()
+            .ok_or_else(|| "No workspace is open".to_string())?,
+    };
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || import(&app, root, dry_run))
+        .await
+        .map_err(|e| format!("Failed to import VS Code settings: {}", e))?
+}
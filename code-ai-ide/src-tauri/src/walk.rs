@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exclude::ExclusionMatcher;
+
+/// Depth-first walk over the files under `root`, skipping anything the
+/// matcher excludes. `visit` returns `false` to stop the walk early.
+/// Unreadable directories are skipped rather than aborting the walk.
+///
+/// Symlinks are followed only when their target stays inside `root`, and a
+/// directory reached twice (a link back to an ancestor, or two links to the
+/// same place) is walked once, so link cycles cannot hang the walk.
+pub fn walk_files(
+    root: &Path,
+    matcher: Option<&ExclusionMatcher>,
+    visit: impl FnMut(&Path) -> bool,
+) {
+    walk_files_with(
+        root,
+        |path, is_dir| matcher.is_some_and(|matcher| matcher.is_excluded(path, is_dir)),
+        visit,
+    )
+}
+
+/// Like `walk_files`, with a custom `skip(path, is_dir)` in place of the
+/// exclusion matcher. Skipped directories are not descended into.
+pub fn walk_files_with(
+    root: &Path,
+    mut skip: impl FnMut(&Path, bool) -> bool,
+    mut visit: impl FnMut(&Path) -> bool,
+) {
+    let root_canonical = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    visited.insert(root_canonical.clone());
+
+    // (path as reached, canonical location on disk)
+    let mut pending: Vec<(PathBuf, PathBuf)> = vec![(root.to_path_buf(), root_canonical.clone())];
+
+    while let Some((dir, dir_canonical)) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+
+            let path = entry.path();
+            let (is_dir, canonical) = if file_type.is_symlink() {
+                // Dangling links and links leaving the root are skipped.
+                let target = match fs::canonicalize(&path) {
+                    Ok(target) if target.starts_with(&root_canonical) => target,
+                    _ => continue,
+                };
+                (target.is_dir(), target)
+            } else {
+                (file_type.is_dir(), dir_canonical.join(entry.file_name()))
+            };
+
+            if skip(&path, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                // Real directories are always walked (ancestors are visited
+                // first, so they are already recorded); links only when
+                // their target has not been seen yet.
+                if !visited.insert(canonical.clone()) && file_type.is_symlink() {
+                    continue;
+                }
+                subdirs.push((path, canonical));
+            } else if !visit(&path) {
+                return;
+            }
+        }
+
+        // Push in reverse so directories are visited in sorted order.
+        pending.extend(subdirs.into_iter().rev());
+    }
+}
@@ -0,0 +1,98 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Window;
+
+use crate::exclude::ExclusionMatcher;
+
+pub type WatchCallback = Box<dyn Fn(&FsChange) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsChange {
+    pub kind: FsChangeKind,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Recursive watcher over a window's active workspace. Changes are emitted
+/// to the window as `fs-changed` and fanned out to backend subscribers
+/// (index, preview server, ...).
+#[derive(Default)]
+pub struct FsWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    subscribers: Arc<Mutex<HashMap<u64, WatchCallback>>>,
+    next_id: AtomicU64,
+}
+
+impl FsWatcher {
+    /// Starts watching `root`, replacing any previous watch.
+    pub fn watch(
+        &self,
+        window: Window,
+        root: &Path,
+        matcher: ExclusionMatcher,
+    ) -> Result<(), String> {
+        let subscribers = self.subscribers.clone();
+        let handler = move |result: notify::Result<notify::Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            let kind = match event.kind {
+                EventKind::Create(_) => FsChangeKind::Created,
+                EventKind::Remove(_) => FsChangeKind::Removed,
+                EventKind::Modify(notify::event::ModifyKind::Name(_)) => FsChangeKind::Renamed,
+                EventKind::Modify(_) => FsChangeKind::Modified,
+                _ => return,
+            };
+            let paths: Vec<PathBuf> = event
+                .paths
+                .into_iter()
+                .filter(|path| !matcher.is_excluded(path, path.is_dir()))
+                .collect();
+            if paths.is_empty() {
+                return;
+            }
+
+            let change = FsChange { kind, paths };
+            let _ = window.emit("fs-changed", &change);
+            for callback in subscribers.lock().unwrap().values() {
+                callback(&change);
+            }
+        };
+
+        let mut watcher = notify::recommended_watcher(handler)
+            .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+        *self.watcher.lock().unwrap() = Some(watcher);
+        Ok(())
+    }
+
+    pub fn subscribe(&self, callback: WatchCallback) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.subscribers.lock().unwrap().insert(id, callback);
+        id
+    }
+
+    pub fn unsubscribe(&self, id: u64) {
+        self.subscribers.lock().unwrap().remove(&id);
+    }
+
+    pub fn stop(&self) {
+        self.watcher.lock().unwrap().take();
+        self.subscribers.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,388 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::app_data;
+use crate::debug;
+use crate::window_state::{WindowRegistry, WindowState};
+
+const WATCHES_DIR: &str = "watches";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchExpression {
+    pub id: String,
+    pub expression: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WatchesFile {
+    #[serde(default)]
+    watches: Vec<WatchExpression>,
+}
+
+/// What an adapter made of an expression.
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub value: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Non-zero when the value has children, fetched with a `variables`
+    /// request.
+    pub variables_reference: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchResult {
+    pub id: String,
+    pub expression: String,
+    /// `None` when the expression could not be evaluated.
+    #[serde(flatten)]
+    pub evaluation: Option<Evaluation>,
+    /// Why not: a syntax error, an unknown name, and so on.
+    pub error: Option<String>,
+}
+
+/// Emitted as `watch-results` each time a session stops, with the watch
+/// expressions evaluated in its top stack frame.
+#[derive(Debug, Clone, Serialize)]
+struct WatchResults {
+    session_id: String,
+    frame_id: i64,
+    results: Vec<WatchResult>,
+}
+
+/// Where a window's debug sessions are stopped. The watch expressions
+/// themselves are stored per workspace under app data.
+#[derive(Default)]
+pub struct WatchState {
+    /// Serializes changes to the watch files.
+    lock: Mutex<()>,
+    /// The top stack frame of each stopped session, keyed by session id.
+    frames: Mutex<HashMap<String, i64>>,
+}
+
+impl WatchState {
+    /// Forgets where a session stopped once the client resumes it;
+    /// adapters only send `continued` when something else did.
+    pub fn observe_request(&self, session_id: &str, message: &Value) {
+        let resumes = matches!(
+            message["command"].as_str(),
+            Some("continue" | "next" | "stepIn" | "stepOut" | "stepBack" | "reverseContinue")
+        );
+        if resumes {
+            self.end_session(session_id);
+        }
+    }
+
+    /// Forgets where a session stopped, once it runs again or has ended.
+    pub fn end_session(&self, session_id: &str) {
+        self.frames.lock().unwrap().remove(session_id);
+    }
+
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+}
+
+/// One file per workspace, named by a hash of its root.
+fn watches_path(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let hash = blake3::hash(root.to_string_lossy().as_bytes());
+    let name = format!("{}/{}.json", WATCHES_DIR, &hash.to_hex()[..32]);
+    app_data::app_data_path(app, &name)
+}
+
+fn load(app: &AppHandle, root: &Path) -> Result<Vec<WatchExpression>, String> {
+    let file: WatchesFile = app_data::load_json(&watches_path(app, root)?);
+    Ok(file.watches)
+}
+
+/// Loads the watch expressions of `root`, lets `change` edit them and
+/// saves them when `change` says it changed anything.
+fn update<T>(
+    app: &AppHandle,
+    scope: &WindowState,
+    root: &Path,
+    change: impl FnOnce(&mut Vec<WatchExpression>) -> (T, bool),
+) -> Result<T, String> {
+    let _guard = scope.watches.lock.lock().unwrap();
+    let mut watches = load(app, root)?;
+    let (result, changed) = change(&mut watches);
+    if changed {
+        app_data::save_json(&watches_path(app, root)?, &WatchesFile { watches })?;
+    }
+    Ok(result)
+}
+
+fn check_expression(expression: &str) -> Result<String, String> {
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return Err("The watch expression is empty".to_string());
+    }
+    Ok(expression.to_string())
+}
+
+/// Evaluates `expression` in `frame_id` of the session. Blocking.
+fn evaluate(
+    scope: &WindowState,
+    session_id: &str,
+    expression: &str,
+    frame_id: Option<i64>,
+    context: &str,
+) -> Result<Evaluation, String> {
+    let mut arguments = json!({ "expression": expression, "context": context });
+    if let Some(frame_id) = frame_id {
+        arguments["frameId"] = frame_id.into();
+    }
+    let body = debug::request(scope, session_id, "evaluate", arguments)?;
+    Ok(Evaluation {
+        value: body["result"].as_str().unwrap_or_default().to_string(),
+        kind: body["type"].as_str().map(str::to_string),
+        variables_reference: body["variablesReference"].as_i64().unwrap_or(0),
+    })
+}
+
+/// Evaluates every watch expression of `root`; each failure is reported
+/// in its own result. Blocking.
+fn evaluate_all(
+    app: &AppHandle,
+    scope: &WindowState,
+    session_id: &str,
+    root: &Path,
+    frame_id: i64,
+) -> Result<Vec<WatchResult>, String> {
+    Ok(load(app, root)?
+        .into_iter()
+        .map(|watch| {
+            let evaluation = evaluate(
+                scope,
+                session_id,
+                &watch.expression,
+                Some(frame_id),
+                "watch",
+            );
+            WatchResult {
+                id: watch.id,
+                expression: watch.expression,
+                error: evaluation.as_ref().err().cloned(),
+                evaluation: evaluation.ok(),
+            }
+        })
+        .collect())
+}
+
+/// The id of the top stack frame of the thread a `stopped` event is about,
+/// or of the first thread when it names none. Blocking.
+fn top_frame(scope: &WindowState, session_id: &str, stopped: &Value) -> Result<i64, String> {
+    let thread_id = match stopped["threadId"].as_i64() {
+        Some(thread_id) => thread_id,
+        None => debug::request(scope, session_id, "threads", json!({}))?["threads"][0]["id"]
+            .as_i64()
+            .ok_or("The debuggee has no threads")?,
+    };
+    debug::request(
+        scope,
+        session_id,
+        "stackTrace",
+        json!({ "threadId": thread_id, "startFrame": 0, "levels": 1 }),
+    )?["stackFrames"][0]["id"]
+        .as_i64()
+        .ok_or_else(|| "The stopped thread has no stack frames".to_string())
+}
+
+/// Follows a session's `stopped` and `continued` events: on each stop the
+/// watch expressions are evaluated in the top stack frame and sent as a
+/// `watch-results` event. The requests go out from another thread, as
+/// their responses arrive on the one calling this.
+pub fn observe_message(
+    window: &Window,
+    scope: &Arc<WindowState>,
+    session_id: &str,
+    message: &Value,
+) {
+    if message["type"] != "event" {
+        return;
+    }
+    match message["event"].as_str() {
+        Some("continued") => scope.watches.end_session(session_id),
+        Some("stopped") => {
+            let (window, scope) = (window.clone(), scope.clone());
+            let session_id = session_id.to_string();
+            let stopped = message["body"].clone();
+            thread::spawn(move || {
+                let Some(root) = scope.debug_sessions.root(&session_id) else {
+                    return;
+                };
+                let Ok(frame_id) = top_frame(&scope, &session_id, &stopped) else {
+                    return;
+                };
+                scope
+                    .watches
+                    .frames
+                    .lock()
+                    .unwrap()
+                    .insert(session_id.clone(), frame_id);
+                let app = window.app_handle();
+                if let Ok(results) = evaluate_all(&app, &scope, &session_id, &root, frame_id) {
+                    if !results.is_empty() {
+                        let _ = window.emit(
+                            "watch-results",
+                            WatchResults {
+                                session_id,
+                                frame_id,
+                                results,
+                            },
+                        );
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+}
+
+/// The watch expressions of `root` (default: the active workspace).
+#[tauri::command]
+pub async fn list_watches(
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<WatchExpression>, String> {
+    let root = debug::launch_root(root, &window, &windows)?;
+    load(&app, &root)
+}
+
+/// Adds a watch expression, evaluated whenever a debug session stops.
+#[tauri::command]
+pub async fn add_watch(
+    expression: String,
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<WatchExpression, String> {
+    let expression = check_expression(&expression)?;
+    let root = debug::launch_root(root, &window, &windows)?;
+    let scope = windows.scope(window.label());
+    update(&app, &scope, &root, |watches| {
+        let watch = WatchExpression {
+            id: uuid::Uuid::new_v4().to_string(),
+            expression,
+        };
+        watches.push(watch.clone());
+        (watch, true)
+    })
+}
+
+/// Changes the expression of the watch `id`. Returns whether there was
+/// one.
+#[tauri::command]
+pub async fn update_watch(
+    id: String,
+    expression: String,
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let expression = check_expression(&expression)?;
+    let root = debug::launch_root(root, &window, &windows)?;
+    let scope = windows.scope(window.label());
+    update(&app, &scope, &root, |watches| {
+        match watches.iter_mut().find(|watch| watch.id == id) {
+            Some(watch) => {
+                watch.expression = expression;
+                (true, true)
+            }
+            None => (false, false),
+        }
+    })
+}
+
+/// Returns whether there was a watch to remove.
+#[tauri::command]
+pub async fn remove_watch(
+    id: String,
+    root: Option<String>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let root = debug::launch_root(root, &window, &windows)?;
+    let scope = windows.scope(window.label());
+    update(&app, &scope, &root, |watches| {
+        let before = watches.len();
+        watches.retain(|watch| watch.id != id);
+        let removed = watches.len() != before;
+        (removed, removed)
+    })
+}
+
+/// Evaluates the watch expressions of the session's workspace in
+/// `frame_id`, by default the top frame of where the session last
+/// stopped; for when the user picks another frame or edits the list.
+#[tauri::command]
+pub async fn evaluate_watches(
+    session_id: String,
+    frame_id: Option<i64>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<WatchResult>, String> {
+    let scope = windows.scope(window.label());
+    let root = scope
+        .debug_sessions
+        .root(&session_id)
+        .ok_or_else(|| format!("Debug session is not running: {}", session_id))?;
+    let frame_id = frame_id
+        .or_else(|| {
+            scope
+                .watches
+                .frames
+                .lock()
+                .unwrap()
+                .get(&session_id)
+                .copied()
+        })
+        .ok_or("The debug session is not stopped")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        evaluate_all(&app, &scope, &session_id, &root, frame_id)
+    })
+    .await
+    .map_err(|e| format!("Failed to evaluate watches: {}", e))?
+}
+
+/// Evaluates one expression in the session, as the debug console or a
+/// hover does. `context` is the adapter's: `repl` (the default), `hover`
+/// or `watch`. Errors from the adapter, such as an invalid expression,
+/// are returned as the command's error.
+#[tauri::command]
+pub async fn debug_evaluate(
+    session_id: String,
+    expression: String,
+    frame_id: Option<i64>,
+    context: Option<String>,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Evaluation, String> {
+    let scope = windows.scope(window.label());
+    let frame_id = frame_id.or_else(|| {
+        scope
+            .watches
+            .frames
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .copied()
+    });
+    let context = context.unwrap_or_else(|| "repl".to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        evaluate(&scope, &session_id, &expression, frame_id, &context)
+    })
+    .await
+    .map_err(|e| format!("Failed to evaluate expression: {}", e))?
+}
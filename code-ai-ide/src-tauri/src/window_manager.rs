@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::dialog;
+use tauri::{AppHandle, Manager, State, Window, WindowBuilder, WindowUrl};
+
+use crate::deep_link;
+use crate::window_state::WindowRegistry;
+use crate::workspace::{self, OpenedPath};
+
+const TITLE: &str = "Code AI IDE";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    pub label: String,
+    pub title: String,
+    /// The active workspace root; `None` for an empty window.
+    pub workspace: Option<String>,
+    pub focused: bool,
+}
+
+/// What each new window was opened for, until its frontend has loaded and
+/// asks. Events emitted before then would be lost.
+#[derive(Default)]
+pub struct PendingOpens {
+    opens: Mutex<HashMap<String, OpenedPath>>,
+}
+
+impl PendingOpens {
+    pub fn insert(&self, label: &str, opened: OpenedPath) {
+        self.opens.lock().unwrap().insert(label.to_string(), opened);
+    }
+
+    pub fn take(&self, label: &str) -> Option<OpenedPath> {
+        self.opens.lock().unwrap().remove(label)
+    }
+}
+
+fn create_window(app: &AppHandle) -> Result<Window, String> {
+    let label = format!("window-{}", uuid::Uuid::new_v4().simple());
+    WindowBuilder::new(app, label, WindowUrl::App("index.html".into()))
+        .title(TITLE)
+        .inner_size(1200.0, 800.0)
+        .min_inner_size(800.0, 600.0)
+        .build()
+        .map_err(|e| format!("Failed to open window: {}", e))
+}
+
+pub(crate) fn focus(window: &Window) -> Result<(), String> {
+    let _ = window.unminimize();
+    window
+        .show()
+        .and_then(|_| window.set_focus())
+        .map_err(|e| format!("Failed to focus window: {}", e))
+}
+
+/// The window with `path` inside one of its workspace roots, preferring
+/// one where it is inside the active root. Empty windows never match.
+fn window_with(app: &AppHandle, windows: &WindowRegistry, path: &Path) -> Option<Window> {
+    let mut candidates: Vec<(bool, Window)> = app
+        .windows()
+        .into_values()
+        .filter_map(|window| {
+            let scope = windows.scope(window.label());
+            let active = scope.workspace.active()?;
+            let contains = scope
+                .workspace
+                .roots()
+                .iter()
+                .any(|root| path.starts_with(root));
+            contains.then(|| (path.starts_with(&active), window))
+        })
+        .collect();
+    candidates.sort_by_key(|(in_active, _)| !*in_active);
+    candidates.into_iter().next().map(|(_, window)| window)
+}
+
+/// The focused window, or else the first one, to open loose files in.
+pub(crate) fn current_window(app: &AppHandle) -> Option<Window> {
+    let windows = app.windows();
+    windows
+        .values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| windows.get("main"))
+        .or_else(|| windows.values().next())
+        .cloned()
+}
+
+/// Opens `path` where it belongs: a window whose workspace already holds
+/// it, or for a file outside every workspace the current window, is
+/// focused and gets a `path-opened` event; otherwise a new window opens
+/// it. `new_window` always opens a new one. Without `path`, opens an
+/// empty window. Returns the label of the window used; a new window
+/// reads what it was opened for with `take_pending_open`.
+pub fn open(
+    app: &AppHandle,
+    windows: &WindowRegistry,
+    path: Option<&Path>,
+    line: Option<u32>,
+    column: Option<u32>,
+    new_window: bool,
+) -> Result<String, String> {
+    let Some(path) = path else {
+        return Ok(create_window(app)?.label().to_string());
+    };
+    let target: PathBuf =
+        std::fs::canonicalize(path).map_err(|e| format!("Failed to open path: {}", e))?;
+
+    if !new_window {
+        let window = window_with(app, windows, &target)
+            .or_else(|| target.is_file().then(|| current_window(app)).flatten());
+        if let Some(window) = window {
+            let opened = workspace::open_in(&window, windows, &target, line, column)?;
+            let _ = window.emit("path-opened", &opened);
+            focus(&window)?;
+            return Ok(window.label().to_string());
+        }
+    }
+
+    let window = create_window(app)?;
+    let opened = match workspace::open_in(&window, windows, &target, line, column) {
+        Ok(opened) => opened,
+        Err(e) => {
+            let _ = window.close();
+            return Err(e);
+        }
+    };
+    app.state::<PendingOpens>().insert(window.label(), opened);
+    Ok(window.label().to_string())
+}
+
+/// Opens a window for the frontend. With `pick_folder`, the user picks
+/// the folder in a native dialog and it opens as `open` would, in a new
+/// window when `new_window`; `None` when the dialog is cancelled. Without
+/// it, opens an empty window. The webview never names the path, so it
+/// cannot make an arbitrary directory a workspace root.
+#[tauri::command]
+pub async fn open_window(
+    pick_folder: Option<bool>,
+    new_window: Option<bool>,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    if !pick_folder.unwrap_or(false) {
+        return open(&app, &windows, None, None, None, true).map(Some);
+    }
+    let folder = dialog::blocking::FileDialogBuilder::new()
+        .set_parent(&window)
+        .pick_folder();
+    folder
+        .map(|folder| {
+            open(
+                &app,
+                &windows,
+                Some(&folder),
+                None,
+                None,
+                new_window.unwrap_or(false),
+            )
+        })
+        .transpose()
+}
+
+/// What the calling window was opened for, once; `None` for empty
+/// windows. Call once the window listens for events: `codeai:` links the
+/// app was started with are handled then.
+#[tauri::command]
+pub async fn take_pending_open(
+    app: AppHandle,
+    window: Window,
+    pending: State<'_, PendingOpens>,
+) -> Result<Option<OpenedPath>, String> {
+    let opened = pending.take(window.label());
+    deep_link::frontend_ready(&app);
+    Ok(opened)
+}
+
+#[tauri::command]
+pub async fn list_windows(
+    app: AppHandle,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Vec<WindowInfo>, String> {
+    let mut list: Vec<WindowInfo> = app
+        .windows()
+        .into_values()
+        .map(|window| WindowInfo {
+            title: window.title().unwrap_or_default(),
+            workspace: windows
+                .scope(window.label())
+                .workspace
+                .active()
+                .map(|root| root.to_string_lossy().to_string()),
+            focused: window.is_focused().unwrap_or(false),
+            label: window.label().to_string(),
+        })
+        .collect();
+    list.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok(list)
+}
+
+#[tauri::command]
+pub async fn focus_window(label: String, app: AppHandle) -> Result<(), String> {
+    let window = app
+        .get_window(&label)
+        .ok_or_else(|| format!("No such window: {}", label))?;
+    focus(&window)
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::ai::inline::InlineCompletions;
+use crate::ai::AiRequests;
+use crate::autosave::AutoSave;
+use crate::breakpoints::BreakpointBindings;
+use crate::debug::DebugSessions;
+use crate::dev_server::DevServers;
+use crate::diagnostics::DiagnosticStore;
+use crate::documents::DocumentStore;
+use crate::exclude::ExclusionSettings;
+use crate::extensions::ExtensionHosts;
+use crate::file_index::FileIndex;
+use crate::fs_ops::BatchOperations;
+use crate::git::remote::GitOperations;
+use crate::highlight::HighlightStates;
+use crate::large_file::LargeFileIndexes;
+use crate::lsp::LanguageServers;
+use crate::preview::PreviewServers;
+use crate::process::ProcessRegistry;
+use crate::semantic::SemanticIndex;
+use crate::syntax::SyntaxTrees;
+use crate::watcher::FsWatcher;
+use crate::watches::WatchState;
+use crate::workspace::WorkspaceRoots;
+
+/// Everything the backend holds on behalf of one window. Commands look this
+/// up by window label instead of sharing global state, and the whole
+/// container is torn down when the window is destroyed. Settings, secrets,
+/// recent projects, trust, chat sessions and the AI audit log are managed
+/// app-wide instead and shared by every window.
+#[derive(Default)]
+pub struct WindowState {
+    pub documents: DocumentStore,
+    pub syntax_trees: SyntaxTrees,
+    pub autosave: AutoSave,
+    pub large_files: LargeFileIndexes,
+    pub highlight_states: HighlightStates,
+    pub workspace: WorkspaceRoots,
+    pub exclusions: ExclusionSettings,
+    pub batch_operations: BatchOperations,
+    pub searches: BatchOperations,
+    pub git_operations: GitOperations,
+    pub processes: ProcessRegistry,
+    pub dev_servers: DevServers,
+    pub language_servers: LanguageServers,
+    pub debug_sessions: DebugSessions,
+    pub breakpoints: BreakpointBindings,
+    pub watches: WatchState,
+    pub diagnostics: DiagnosticStore,
+    pub file_index: FileIndex,
+    pub watcher: FsWatcher,
+    pub preview_servers: PreviewServers,
+    pub extensions: ExtensionHosts,
+    pub ai_requests: AiRequests,
+    pub inline_completions: InlineCompletions,
+    pub semantic_index: SemanticIndex,
+}
+
+impl WindowState {
+    /// Releases every resource owned by the window. Called once, after the
+    /// window has been removed from the registry.
+    fn shutdown(&self) {
+        self.batch_operations.cancel_all();
+        self.searches.cancel_all();
+        self.git_operations.cancel_all();
+        self.processes.cancel_all();
+        self.extensions.clear();
+        self.ai_requests.cancel_all();
+        self.inline_completions.cancel();
+        self.language_servers.clear();
+        self.debug_sessions.clear();
+        self.breakpoints.clear();
+        self.watches.clear();
+        self.preview_servers.stop_all();
+        self.semantic_index.stop();
+        self.watcher.stop();
+        self.autosave.clear();
+        self.documents.clear();
+        self.syntax_trees.clear();
+        self.large_files.clear();
+        self.highlight_states.clear();
+        self.file_index.clear();
+        self.diagnostics.clear();
+        self.workspace.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct WindowRegistry {
+    windows: Mutex<HashMap<String, Arc<WindowState>>>,
+}
+
+impl WindowRegistry {
+    /// Returns the state for `label`, creating it on first use.
+    pub fn scope(&self, label: &str) -> Arc<WindowState> {
+        self.windows
+            .lock()
+            .unwrap()
+            .entry(label.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Every open window's state, e.g. to stop something app-wide.
+    pub fn scopes(&self) -> Vec<Arc<WindowState>> {
+        self.windows.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn teardown(&self, label: &str) {
+        let removed = self.windows.lock().unwrap().remove(label);
+        if let Some(state) = removed {
+            state.shutdown();
+        }
+    }
+}
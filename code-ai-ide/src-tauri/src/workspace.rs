@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::api::dialog;
+use tauri::{Manager, State, Window};
+
+use crate::exclude::ExclusionMatcher;
+use crate::file_index;
+use crate::git;
+use crate::recent::{RecentKind, RecentStore};
+use crate::window_state::WindowRegistry;
+
+/// Project roots opened in a window plus any individual paths the user has
+/// explicitly approved. Every fs command validates its path against this so
+/// the webview cannot reach arbitrary locations like `~/.ssh`.
+///
+/// Processes are outside this sandbox: only their working directory is
+/// checked, and a program run from the workspace can still read or delete
+/// anything the user can. What may run at all is up to workspace trust and
+/// the execution policy (`trust::check_execution`).
+#[derive(Default)]
+pub struct WorkspaceRoots {
+    roots: Mutex<Vec<PathBuf>>,
+    granted: Mutex<Vec<PathBuf>>,
+    active: Mutex<Option<PathBuf>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceRootsInfo {
+    pub roots: Vec<String>,
+    pub granted: Vec<String>,
+    pub active: Option<String>,
+    /// Open roots that are worktrees of one repository, grouped so the UI
+    /// can tell them apart by branch. Only groups of two or more.
+    pub worktrees: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenedKind {
+    Workspace,
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedPath {
+    pub kind: OpenedKind,
+    pub path: String,
+    /// The active workspace root after opening.
+    pub root: Option<String>,
+    /// Where to put the cursor in a file, one-based as written on a command
+    /// line (`file:line:column`).
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl WorkspaceRoots {
+    pub fn add_root(&self, path: &Path) -> Result<PathBuf, String> {
+        let root =
+            fs::canonicalize(path).map_err(|e| format!("Failed to open workspace root: {}", e))?;
+        if !root.is_dir() {
+            return Err(format!(
+                "Workspace root is not a directory: {}",
+                root.display()
+            ));
+        }
+
+        let mut roots = self.roots.lock().unwrap();
+        if !roots.contains(&root) {
+            roots.push(root.clone());
+        }
+        Ok(root)
+    }
+
+    pub fn remove_root(&self, path: &Path) {
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.roots.lock().unwrap().retain(|root| *root != target);
+        let mut active = self.active.lock().unwrap();
+        if active.as_ref() == Some(&target) {
+            *active = None;
+        }
+    }
+
+    /// Registers `path` as a root and makes it the window's active workspace.
+    pub fn open(&self, path: &Path) -> Result<PathBuf, String> {
+        let root = self.add_root(path)?;
+        *self.active.lock().unwrap() = Some(root.clone());
+        Ok(root)
+    }
+
+    pub fn active(&self) -> Option<PathBuf> {
+        self.active.lock().unwrap().clone()
+    }
+
+    /// The innermost open root holding `path`, else the active root.
+    pub fn root_for(&self, path: Option<&Path>) -> Option<PathBuf> {
+        let roots = self.roots.lock().unwrap().clone();
+        path.and_then(|path| {
+            roots
+                .into_iter()
+                .filter(|root| path.starts_with(root))
+                .max_by_key(|root| root.as_os_str().len())
+        })
+        .or_else(|| self.active())
+    }
+
+    /// Empty until the user opens a folder.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.lock().unwrap().clone()
+    }
+
+    pub fn grant(&self, path: &Path) -> Result<PathBuf, String> {
+        let resolved = resolve(path)?;
+        let mut granted = self.granted.lock().unwrap();
+        if !granted.contains(&resolved) {
+            granted.push(resolved.clone());
+        }
+        Ok(resolved)
+    }
+
+    pub fn clear(&self) {
+        self.roots.lock().unwrap().clear();
+        self.granted.lock().unwrap().clear();
+        *self.active.lock().unwrap() = None;
+    }
+
+    /// Resolves `path` and returns it if it lies inside an open root or a
+    /// granted path; otherwise returns an access-denied error.
+    pub fn check(&self, path: &Path) -> Result<PathBuf, String> {
+        let resolved = resolve(path)?;
+        let roots = self.roots();
+        let allowed = roots.iter().any(|root| resolved.starts_with(root))
+            || self
+                .granted
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|granted| resolved.starts_with(granted));
+
+        if allowed {
+            Ok(resolved)
+        } else if roots.is_empty() {
+            Err("No workspace is open".to_string())
+        } else {
+            Err(format!(
+                "Access denied: {} is outside the open workspace",
+                resolved.display()
+            ))
+        }
+    }
+
+    /// Like `check`, but resolves only the parent and keeps the last
+    /// component as given, so deleting or moving a symlink acts on the
+    /// link rather than on what it points to.
+    pub fn check_entry(&self, path: &Path) -> Result<PathBuf, String> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("Failed to resolve path: {}", path.display()))?;
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        Ok(self.check(parent)?.join(name))
+    }
+
+    fn info(&self) -> WorkspaceRootsInfo {
+        let mut repositories: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for root in self.roots() {
+            if let Some(common_dir) = git::common_dir(&root) {
+                repositories
+                    .entry(common_dir)
+                    .or_default()
+                    .push(root.to_string_lossy().to_string());
+            }
+        }
+
+        WorkspaceRootsInfo {
+            roots: self
+                .roots()
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            granted: self
+                .granted
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            active: self.active().map(|p| p.to_string_lossy().to_string()),
+            worktrees: repositories
+                .into_values()
+                .filter(|roots| roots.len() > 1)
+                .collect(),
+        }
+    }
+}
+
+/// Canonicalizes `path`, even when it does not exist yet, by canonicalizing
+/// its deepest existing ancestor and re-attaching the remaining components.
+/// `..` is rejected in the non-existent tail since it cannot be resolved
+/// through symlinks safely.
+pub fn resolve(path: &Path) -> Result<PathBuf, String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map_err(|e| format!("Failed to resolve path: {}", e))?
+            .join(path)
+    };
+
+    let mut existing = absolute.as_path();
+    let mut tail = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(canonical) => {
+                let mut resolved = canonical;
+                for component in tail.iter().rev() {
+                    resolved.push(component);
+                }
+                return Ok(resolved);
+            }
+            Err(_) => {
+                match existing.components().next_back() {
+                    Some(Component::Normal(name)) => tail.push(name.to_os_string()),
+                    Some(Component::CurDir) => {}
+                    _ => return Err(format!("Failed to resolve path: {}", path.display())),
+                }
+                existing = existing
+                    .parent()
+                    .ok_or_else(|| format!("Failed to resolve path: {}", path.display()))?;
+            }
+        }
+    }
+}
+
+/// Validates `path` against the calling window's workspace, returning the
+/// resolved path commands should operate on.
+pub fn authorize(windows: &WindowRegistry, window: &Window, path: &str) -> Result<PathBuf, String> {
+    windows
+        .scope(window.label())
+        .workspace
+        .check(Path::new(path))
+}
+
+/// `authorize` for commands that act on a directory entry itself, such as
+/// deletes and moves; see `WorkspaceRoots::check_entry`.
+pub fn authorize_entry(
+    windows: &WindowRegistry,
+    window: &Window,
+    path: &str,
+) -> Result<PathBuf, String> {
+    windows
+        .scope(window.label())
+        .workspace
+        .check_entry(Path::new(path))
+}
+
+/// Adds a folder the user picks in a native dialog as another root. The
+/// webview cannot name the folder itself, so it cannot widen its own
+/// sandbox. `None` when the dialog is cancelled.
+#[tauri::command]
+pub async fn add_workspace_root(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    let folder = dialog::blocking::FileDialogBuilder::new()
+        .set_parent(&window)
+        .pick_folder();
+
+    match folder {
+        Some(folder) => {
+            let root = windows.scope(window.label()).workspace.add_root(&folder)?;
+            Ok(Some(root.to_string_lossy().to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub async fn remove_workspace_root(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<(), String> {
+    windows
+        .scope(window.label())
+        .workspace
+        .remove_root(Path::new(&path));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_workspace_roots(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<WorkspaceRootsInfo, String> {
+    Ok(windows.scope(window.label()).workspace.info())
+}
+
+/// Asks the user, through a native dialog the webview cannot answer on its
+/// own, whether a path outside the workspace may be accessed.
+#[tauri::command]
+pub async fn request_path_access(
+    path: String,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<bool, String> {
+    let scope = windows.scope(window.label());
+    if scope.workspace.check(Path::new(&path)).is_ok() {
+        return Ok(true);
+    }
+
+    let resolved = resolve(Path::new(&path))?;
+    let approved = dialog::blocking::ask(
+        Some(&window),
+        "Allow file access?",
+        format!(
+            "The editor is requesting access to a location outside the open workspace:\n\n{}",
+            resolved.display()
+        ),
+    );
+    if approved {
+        scope.workspace.grant(&resolved)?;
+    }
+    Ok(approved)
+}
+
+/// Opens `root` as the window's active workspace and starts indexing it.
+pub fn open_workspace(
+    window: &Window,
+    windows: &WindowRegistry,
+    root: &Path,
+) -> Result<PathBuf, String> {
+    let scope = windows.scope(window.label());
+    let root = scope.workspace.open(root)?;
+    let matcher = ExclusionMatcher::new(&root, &scope.exclusions.globs())?;
+    scope.watcher.watch(window.clone(), &root, matcher)?;
+    file_index::start_indexing(window.clone(), scope, root.clone())?;
+    window
+        .state::<RecentStore>()
+        .record(&window.app_handle(), &root, RecentKind::Project)?;
+    Ok(root)
+}
+
+#[tauri::command]
+pub async fn open_folder_dialog(
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+) -> Result<Option<String>, String> {
+    let folder = dialog::blocking::FileDialogBuilder::new()
+        .set_parent(&window)
+        .pick_folder();
+
+    match folder {
+        Some(folder) => {
+            let root = open_workspace(&window, &windows, &folder)?;
+            Ok(Some(root.to_string_lossy().to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Opens a path handed over from outside the UI (command line, deep link,
+/// another launch of the app). Directories become the active workspace;
+/// files are granted and their parent directory is opened when no
+/// workspace is active yet. `line` and `column` are passed back for the
+/// editor to jump to.
+///
+/// Not a command: the paths come from the OS, never from the webview,
+/// which only opens folders the user picks in a native dialog.
+pub fn open_in(
+    window: &Window,
+    windows: &WindowRegistry,
+    path: &Path,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> Result<OpenedPath, String> {
+    let target = fs::canonicalize(path).map_err(|e| format!("Failed to open path: {}", e))?;
+    let scope = windows.scope(window.label());
+
+    if target.is_dir() {
+        let root = open_workspace(window, windows, &target)?;
+        return Ok(OpenedPath {
+            kind: OpenedKind::Workspace,
+            path: root.to_string_lossy().to_string(),
+            root: Some(root.to_string_lossy().to_string()),
+            line: None,
+            column: None,
+        });
+    }
+
+    scope.workspace.grant(&target)?;
+    if scope.workspace.active().is_none() {
+        if let Some(parent) = target.parent() {
+            open_workspace(window, windows, parent)?;
+        }
+    }
+    Ok(OpenedPath {
+        kind: OpenedKind::File,
+        path: target.to_string_lossy().to_string(),
+        root: scope
+            .workspace
+            .active()
+            .map(|root| root.to_string_lossy().to_string()),
+        line,
+        column,
+    })
+}
@@ -0,0 +1,660 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State, Window};
+
+use crate::documents::{self, Document, DocumentStore, DocumentSummary, TextEdit};
+use crate::encoding;
+use crate::fs_ops;
+use crate::local_history::{LocalHistory, RevisionSource};
+use crate::lsp;
+use crate::replace;
+use crate::window_state::WindowRegistry;
+use crate::workspace;
+
+/// An LSP `WorkspaceEdit`, as language servers send it for renames and code
+/// actions.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEdit {
+    #[serde(default)]
+    pub changes: BTreeMap<String, Vec<TextEdit>>,
+    /// Takes precedence over `changes` when present, as in the spec.
+    #[serde(default)]
+    pub document_changes: Option<Vec<DocumentChange>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DocumentChange {
+    Resource(ResourceOperation),
+    Edit(TextDocumentEdit),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ResourceOperation {
+    Create {
+        uri: String,
+        #[serde(default)]
+        options: FileOptions,
+    },
+    #[serde(rename_all = "camelCase")]
+    Rename {
+        old_uri: String,
+        new_uri: String,
+        #[serde(default)]
+        options: FileOptions,
+    },
+    Delete {
+        uri: String,
+        #[serde(default)]
+        options: FileOptions,
+    },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOptions {
+    #[serde(default)]
+    pub overwrite: bool,
+    #[serde(default)]
+    pub ignore_if_exists: bool,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub ignore_if_not_exists: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentEdit {
+    pub text_document: VersionedDocument,
+    pub edits: Vec<TextEdit>,
+}
+
+/// `version` is the document version the edits were computed against;
+/// `None` means any.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionedDocument {
+    pub uri: String,
+    pub version: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EditFailure {
+    /// Position of the change in `documentChanges` (or in `changes`, in
+    /// URI order).
+    pub index: usize,
+    pub uri: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceEditResult {
+    /// All or nothing: when false, nothing was changed.
+    pub applied: bool,
+    pub failures: Vec<EditFailure>,
+    /// Files written, created, renamed or deleted on disk.
+    pub changed_files: Vec<String>,
+    /// Open documents whose buffers were edited, with their new versions.
+    pub documents: Vec<DocumentSummary>,
+}
+
+/// One validated change, in the order the edit lists them.
+enum Step {
+    /// Edits to an open document land in its buffer, unsaved, as if typed.
+    EditDocument {
+        path: PathBuf,
+        content: String,
+    },
+    WriteFile {
+        path: PathBuf,
+        file: StagedFile,
+    },
+    Create {
+        path: PathBuf,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Delete {
+        path: PathBuf,
+    },
+}
+
+#[derive(Clone)]
+struct StagedFile {
+    content: String,
+    encoding: String,
+    has_bom: bool,
+}
+
+impl StagedFile {
+    fn empty() -> Self {
+        StagedFile {
+            content: String::new(),
+            encoding: "utf-8".to_string(),
+            has_bom: false,
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let decoded = encoding::decode(&bytes);
+        Ok(StagedFile {
+            content: decoded.content,
+            encoding: decoded.encoding,
+            has_bom: decoded.has_bom,
+        })
+    }
+}
+
+enum Entry {
+    File(StagedFile),
+    Dir,
+    Gone,
+}
+
+/// What the disk and the open documents will look like after the changes
+/// validated so far, so later changes are checked against earlier ones:
+/// editing a file created above, renaming one edited above.
+#[derive(Default)]
+struct Simulation {
+    entries: HashMap<PathBuf, Entry>,
+    /// Buffers of open documents edited so far.
+    buffers: HashMap<PathBuf, String>,
+    /// New path to old path, for open documents renamed so far.
+    renamed: HashMap<PathBuf, PathBuf>,
+}
+
+impl Simulation {
+    fn exists(&self, path: &Path) -> bool {
+        let gone_above = path
+            .ancestors()
+            .skip(1)
+            .any(|dir| matches!(self.entries.get(dir), Some(Entry::Gone)));
+        match self.entries.get(path) {
+            Some(Entry::Gone) => false,
+            Some(_) => true,
+            None => !gone_above && path.exists(),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.entries.get(path) {
+            Some(entry) => matches!(entry, Entry::Dir),
+            None => path.is_dir(),
+        }
+    }
+
+    fn open_document(&self, store: &DocumentStore, path: &Path) -> Option<Document> {
+        if matches!(self.entries.get(path), Some(Entry::Gone)) {
+            return None;
+        }
+        let original = self.renamed.get(path).map(PathBuf::as_path).unwrap_or(path);
+        store.get(&original.to_string_lossy())
+    }
+
+    fn edit(
+        &mut self,
+        store: &DocumentStore,
+        path: &Path,
+        change: &TextDocumentEdit,
+    ) -> Result<Step, String> {
+        if let Some(doc) = self.open_document(store, path) {
+            // Versions refer to the document before this workspace edit.
+            let base = match self.buffers.get(path) {
+                Some(buffer) => buffer.clone(),
+                None => {
+                    if let Some(version) = change.text_document.version {
+                        if version != doc.version {
+                            return Err(format!(
+                                "Document changed: edits are for version {} but the editor is at {}",
+                                version, doc.version
+                            ));
+                        }
+                    }
+                    doc.content
+                }
+            };
+            let content = documents::edit_text(&base, &change.edits)?;
+            self.buffers.insert(path.to_path_buf(), content.clone());
+            return Ok(Step::EditDocument {
+                path: path.to_path_buf(),
+                content,
+            });
+        }
+
+        if change.text_document.version.is_some() {
+            return Err("Document is not open in the editor".to_string());
+        }
+        let mut file = match self.entries.get(path) {
+            Some(Entry::File(file)) => file.clone(),
+            Some(Entry::Dir) => return Err("Is a folder".to_string()),
+            _ if !self.exists(path) => return Err("File does not exist".to_string()),
+            _ => StagedFile::read(path)?,
+        };
+        file.content = documents::edit_text(&file.content, &change.edits)?;
+        self.entries
+            .insert(path.to_path_buf(), Entry::File(file.clone()));
+        Ok(Step::WriteFile {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Refuses to replace or remove a document with unsaved changes.
+    fn check_not_dirty(&self, store: &DocumentStore, path: &Path) -> Result<(), String> {
+        let dirty = self.open_document(store, path).is_some_and(|doc| doc.dirty)
+            || self.buffers.contains_key(path);
+        if dirty {
+            Err("File has unsaved changes in the editor".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn create(
+        &mut self,
+        store: &DocumentStore,
+        path: &Path,
+        options: &FileOptions,
+    ) -> Result<Option<Step>, String> {
+        if self.exists(path) {
+            if options.ignore_if_exists && !options.overwrite {
+                return Ok(None);
+            }
+            if !options.overwrite || self.is_dir(path) {
+                return Err("File already exists".to_string());
+            }
+            self.check_not_dirty(store, path)?;
+        }
+        self.entries
+            .insert(path.to_path_buf(), Entry::File(StagedFile::empty()));
+        Ok(Some(Step::Create {
+            path: path.to_path_buf(),
+        }))
+    }
+
+    fn rename(
+        &mut self,
+        store: &DocumentStore,
+        from: &Path,
+        to: &Path,
+        options: &FileOptions,
+    ) -> Result<Option<Step>, String> {
+        if !self.exists(from) {
+            return Err("File does not exist".to_string());
+        }
+        if self.exists(to) {
+            if options.ignore_if_exists && !options.overwrite {
+                return Ok(None);
+            }
+            if !options.overwrite {
+                return Err(format!("{} already exists", to.display()));
+            }
+            self.check_not_dirty(store, to)?;
+        }
+
+        let open = self.open_document(store, from).is_some();
+        let entry = match self.entries.remove(from) {
+            Some(entry) => entry,
+            None if from.is_dir() => Entry::Dir,
+            None => Entry::File(StagedFile::read(from)?),
+        };
+        self.entries.insert(from.to_path_buf(), Entry::Gone);
+        self.entries.insert(to.to_path_buf(), entry);
+        if let Some(buffer) = self.buffers.remove(from) {
+            self.buffers.insert(to.to_path_buf(), buffer);
+        }
+        if open {
+            let original = self
+                .renamed
+                .remove(from)
+                .unwrap_or_else(|| from.to_path_buf());
+            self.renamed.insert(to.to_path_buf(), original);
+        }
+        Ok(Some(Step::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        }))
+    }
+
+    fn delete(
+        &mut self,
+        store: &DocumentStore,
+        path: &Path,
+        options: &FileOptions,
+    ) -> Result<Option<Step>, String> {
+        if !self.exists(path) {
+            return if options.ignore_if_not_exists {
+                Ok(None)
+            } else {
+                Err("File does not exist".to_string())
+            };
+        }
+        if self.is_dir(path) {
+            let empty = fs::read_dir(path)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(true);
+            if !options.recursive && !empty {
+                return Err("Folder is not empty".to_string());
+            }
+            let dirty_inside = store
+                .dirty_snapshots()
+                .iter()
+                .any(|doc| Path::new(&doc.path).starts_with(path));
+            if dirty_inside {
+                return Err("Folder contains files with unsaved changes".to_string());
+            }
+        } else {
+            self.check_not_dirty(store, path)?;
+        }
+        self.entries.insert(path.to_path_buf(), Entry::Gone);
+        Ok(Some(Step::Delete {
+            path: path.to_path_buf(),
+        }))
+    }
+}
+
+/// How to take a step back when a later one fails.
+enum Undo {
+    Document(Document),
+    /// Restore the bytes, or remove the file if it did not exist.
+    File {
+        path: PathBuf,
+        previous: Option<Vec<u8>>,
+    },
+    RenameBack {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Move a deleted or overwritten path back from the trash.
+    Untrash {
+        trash: PathBuf,
+        path: PathBuf,
+    },
+}
+
+/// Runs validated steps, journaling each so a failure can put everything
+/// back. Deleted and overwritten paths are moved aside rather than removed
+/// until every step has succeeded.
+#[derive(Default)]
+struct Execution {
+    journal: Vec<Undo>,
+    trash: Vec<PathBuf>,
+    /// Previous contents of files written or deleted, for local history.
+    previous: Vec<(PathBuf, Vec<u8>)>,
+    changed_files: Vec<PathBuf>,
+    edited_documents: Vec<PathBuf>,
+}
+
+impl Execution {
+    /// Moves `path` to a hidden sibling so it can be restored.
+    fn move_aside(&mut self, path: &Path) -> Result<(), String> {
+        if path.is_file() {
+            if let Ok(bytes) = fs::read(path) {
+                self.previous.push((path.to_path_buf(), bytes));
+            }
+        }
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+        let trash = path.with_file_name(format!(
+            ".{}.codeai-trash-{}",
+            name.to_string_lossy(),
+            uuid::Uuid::new_v4()
+        ));
+        fs::rename(path, &trash)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        self.journal.push(Undo::Untrash {
+            trash: trash.clone(),
+            path: path.to_path_buf(),
+        });
+        self.trash.push(trash);
+        Ok(())
+    }
+
+    fn create_parent(path: &Path) -> Result<(), String> {
+        match path.parent() {
+            Some(parent) => fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e)),
+            None => Ok(()),
+        }
+    }
+
+    fn run(&mut self, store: &DocumentStore, step: Step) -> Result<(), String> {
+        match step {
+            Step::EditDocument { path, content } => {
+                let key = path.to_string_lossy();
+                let doc = store
+                    .get(&key)
+                    .ok_or_else(|| format!("Document is not open: {}", key))?;
+                store.replace_content(&key, content)?;
+                self.journal.push(Undo::Document(doc));
+                self.edited_documents.push(path);
+            }
+            Step::WriteFile { path, file } => {
+                let previous = fs::read(&path).ok();
+                let bytes = encoding::encode(&file.content, &file.encoding, file.has_bom)?;
+                Self::create_parent(&path)?;
+                replace::write_replacing(&path, &bytes)?;
+                if let Some(previous) = &previous {
+                    self.previous.push((path.clone(), previous.clone()));
+                }
+                self.journal.push(Undo::File {
+                    path: path.clone(),
+                    previous,
+                });
+                self.changed_files.push(path);
+            }
+            Step::Create { path } => {
+                if path.exists() {
+                    self.move_aside(&path)?;
+                }
+                Self::create_parent(&path)?;
+                fs::write(&path, "").map_err(|e| format!("Failed to create file: {}", e))?;
+                self.journal.push(Undo::File {
+                    path: path.clone(),
+                    previous: None,
+                });
+                self.changed_files.push(path);
+            }
+            Step::Rename { from, to } => {
+                if to.exists() {
+                    self.move_aside(&to)?;
+                }
+                Self::create_parent(&to)?;
+                fs_ops::move_path(&from, &to)
+                    .map_err(|e| format!("Failed to rename {}: {}", from.display(), e))?;
+                store.rename(&from, &to);
+                self.journal.push(Undo::RenameBack {
+                    from: to.clone(),
+                    to: from.clone(),
+                });
+                self.changed_files.extend([from, to]);
+            }
+            Step::Delete { path } => {
+                if let Some(doc) = store.close(&path.to_string_lossy()) {
+                    self.journal.push(Undo::Document(doc));
+                }
+                self.move_aside(&path)?;
+                self.changed_files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Best effort: undoes the journal newest first.
+    fn rollback(self, store: &DocumentStore) {
+        for undo in self.journal.into_iter().rev() {
+            match undo {
+                Undo::Document(doc) => store.restore(doc),
+                Undo::File {
+                    path,
+                    previous: Some(bytes),
+                } => {
+                    let _ = replace::write_replacing(&path, &bytes);
+                }
+                Undo::File {
+                    path,
+                    previous: None,
+                } => {
+                    let _ = fs::remove_file(&path);
+                }
+                Undo::RenameBack { from, to } => {
+                    if fs_ops::move_path(&from, &to).is_ok() {
+                        store.rename(&from, &to);
+                    }
+                }
+                Undo::Untrash { trash, path } => {
+                    let _ = fs::rename(&trash, &path);
+                }
+            }
+        }
+    }
+
+    fn finish(
+        self,
+        app: &AppHandle,
+        history: &LocalHistory,
+        store: &DocumentStore,
+    ) -> WorkspaceEditResult {
+        for trash in &self.trash {
+            let _ = fs_ops::remove_path(trash);
+        }
+        // Like a workspace replace, every overwritten file can be restored
+        // from local history.
+        for (path, bytes) in &self.previous {
+            let _ = history.record(app, path, bytes, RevisionSource::Replace);
+        }
+        let mut changed_files: Vec<String> = self
+            .changed_files
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        changed_files.sort();
+        changed_files.dedup();
+        let documents = store
+            .summaries()
+            .into_iter()
+            .filter(|doc| {
+                self.edited_documents
+                    .iter()
+                    .any(|path| documents::document_key(&path.to_string_lossy()) == doc.path)
+            })
+            .collect();
+        WorkspaceEditResult {
+            applied: true,
+            failures: Vec::new(),
+            changed_files,
+            documents,
+        }
+    }
+}
+
+fn changes_in_order(edit: WorkspaceEdit) -> Vec<DocumentChange> {
+    match edit.document_changes {
+        Some(changes) => changes,
+        None => edit
+            .changes
+            .into_iter()
+            .map(|(uri, edits)| {
+                DocumentChange::Edit(TextDocumentEdit {
+                    text_document: VersionedDocument { uri, version: None },
+                    edits,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn not_applied(failures: Vec<EditFailure>) -> WorkspaceEditResult {
+    WorkspaceEditResult {
+        applied: false,
+        failures,
+        changed_files: Vec::new(),
+        documents: Vec::new(),
+    }
+}
+
+/// Applies a `WorkspaceEdit` all or nothing. Every change is validated
+/// first (paths, document versions, ranges, existing files) and all
+/// failures are reported; if any change fails nothing is touched, and a
+/// failure while writing rolls back the changes already made. Edits to
+/// open documents go to their buffers; other files are written on disk
+/// with their previous content kept in local history.
+#[tauri::command]
+pub async fn apply_workspace_edit(
+    edit: WorkspaceEdit,
+    app: AppHandle,
+    window: Window,
+    windows: State<'_, WindowRegistry>,
+    history: State<'_, LocalHistory>,
+) -> Result<WorkspaceEditResult, String> {
+    let scope = windows.scope(window.label());
+    let store = &scope.documents;
+    let resolve = |uri: &str| {
+        let path = lsp::uri_to_path(uri).ok_or_else(|| format!("Not a file URI: {}", uri))?;
+        workspace::authorize(&windows, &window, &path)
+    };
+
+    let mut simulation = Simulation::default();
+    let mut steps = Vec::new();
+    let mut failures = Vec::new();
+    for (index, change) in changes_in_order(edit).into_iter().enumerate() {
+        let (uri, outcome) = match &change {
+            DocumentChange::Edit(edit) => (
+                edit.text_document.uri.clone(),
+                resolve(&edit.text_document.uri)
+                    .and_then(|path| simulation.edit(store, &path, edit).map(Some)),
+            ),
+            DocumentChange::Resource(ResourceOperation::Create { uri, options }) => (
+                uri.clone(),
+                resolve(uri).and_then(|path| simulation.create(store, &path, options)),
+            ),
+            DocumentChange::Resource(ResourceOperation::Rename {
+                old_uri,
+                new_uri,
+                options,
+            }) => (
+                old_uri.clone(),
+                resolve(old_uri).and_then(|from| {
+                    let to = resolve(new_uri)?;
+                    simulation.rename(store, &from, &to, options)
+                }),
+            ),
+            DocumentChange::Resource(ResourceOperation::Delete { uri, options }) => (
+                uri.clone(),
+                resolve(uri).and_then(|path| simulation.delete(store, &path, options)),
+            ),
+        };
+        match outcome {
+            Ok(step) => steps.extend(step.map(|step| (index, uri, step))),
+            Err(message) => failures.push(EditFailure {
+                index,
+                uri,
+                message,
+            }),
+        }
+    }
+    if !failures.is_empty() {
+        return Ok(not_applied(failures));
+    }
+
+    let mut execution = Execution::default();
+    for (index, uri, step) in steps {
+        if let Err(message) = execution.run(store, step) {
+            execution.rollback(store);
+            return Ok(not_applied(vec![EditFailure {
+                index,
+                uri,
+                message,
+            }]));
+        }
+    }
+    Ok(execution.finish(&app, &history, store))
+}
@@ -45,9 +45,10 @@ export function Sidebar() {
       const files = await listDirectory(path)
       const tree: FileTreeNode[] = []
 
-      for (const fileName of files) {
+      for (const entry of files) {
+        const fileName = entry.name
         const fullPath = path === '.' ? fileName : `${path}/${fileName}`
-        const isDirectory = !fileName.includes('.')
+        const isDirectory = entry.is_dir
 
         tree.push({
           id: fullPath,
@@ -78,9 +79,10 @@ export function Sidebar() {
         const files = await listDirectory(node.path)
         const children: FileTreeNode[] = []
 
-        for (const fileName of files) {
+        for (const entry of files) {
+          const fileName = entry.name
           const fullPath = `${node.path}/${fileName}`
-          const isDirectory = !fileName.includes('.')
+          const isDirectory = entry.is_dir
 
           children.push({
             id: fullPath,
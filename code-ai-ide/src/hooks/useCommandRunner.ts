@@ -1,14 +1,54 @@
-import { useState, useCallback } from 'react'
+import { useState, useCallback, useRef } from 'react'
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
 
-interface CommandResult {
+export interface CommandResult {
   success: boolean
+  /** stdout and stderr interleaved in arrival order. */
   output: string
+  stdout: string
+  stderr: string
+  exitCode: number | null
+  durationMs: number
   error?: string
 }
 
+const failure = (error: string): CommandResult => ({
+  success: false,
+  output: '',
+  stdout: '',
+  stderr: '',
+  exitCode: null,
+  durationMs: 0,
+  error
+})
+
+export interface CommandOutputChunk {
+  id: string
+  stream: 'stdout' | 'stderr'
+  data: string
+}
+
+interface CommandExit {
+  id: string
+  exit_code: number | null
+  cancelled: boolean
+  timed_out: boolean
+  duration_ms: number
+}
+
+export interface RunCommandOptions {
+  onOutput?: (chunk: CommandOutputChunk) => void
+  timeoutMs?: number
+  env?: Record<string, string>
+  /** Run through the shell so `&&`, pipes and globs work. */
+  useShell?: boolean
+  stdin?: string
+}
+
 interface CommandRunnerHook {
-  runCommand: (command: string, args: string[], cwd?: string) => Promise<CommandResult>
+  runCommand: (command: string, args: string[], cwd?: string, options?: RunCommandOptions) => Promise<CommandResult>
+  cancelCommand: () => Promise<void>
   buildProject: (language: string, cwd?: string) => Promise<CommandResult>
   runProject: (language: string, cwd?: string) => Promise<CommandResult>
   testProject: (language: string, cwd?: string) => Promise<CommandResult>
@@ -21,30 +61,110 @@ interface CommandRunnerHook {
 export function useCommandRunner(): CommandRunnerHook {
   const [isLoading, setIsLoading] = useState(false)
   const [error, setError] = useState<string | null>(null)
+  const runningIds = useRef(new Set<string>())
 
-  const runCommand = useCallback(async (command: string, args: string[], cwd?: string): Promise<CommandResult> => {
+  const runCommand = useCallback(async (
+    command: string,
+    args: string[],
+    cwd?: string,
+    options: RunCommandOptions = {}
+  ): Promise<CommandResult> => {
     setIsLoading(true)
     setError(null)
-    
+
+    // Events may arrive before spawn_command returns the id, so collect
+    // everything until it is known.
+    let id: string | null = null
+    const early: CommandOutputChunk[] = []
+    let stdout = ''
+    let stderr = ''
+    let combined = ''
+    const handleChunk = (chunk: CommandOutputChunk) => {
+      combined += chunk.data
+      if (chunk.stream === 'stdout') {
+        stdout += chunk.data
+      } else {
+        stderr += chunk.data
+      }
+      options.onOutput?.(chunk)
+    }
+
+    let finish: (exit: CommandExit) => void = () => {}
+    let earlyExit: CommandExit | null = null
+    const exited = new Promise<CommandExit>(resolve => {
+      finish = resolve
+    })
+
+    const unlistenOutput = await listen<CommandOutputChunk>('command-output', event => {
+      if (id === null) {
+        early.push(event.payload)
+      } else if (event.payload.id === id) {
+        handleChunk(event.payload)
+      }
+    })
+    const unlistenExit = await listen<CommandExit>('command-exit', event => {
+      if (id === null) {
+        earlyExit = event.payload
+      } else if (event.payload.id === id) {
+        finish(event.payload)
+      }
+    })
+
     try {
-      const output = await invoke('run_command', { command, args, cwd })
-      return {
-        success: true,
-        output: output as string
+      id = await invoke<string>('spawn_command', {
+        command,
+        args,
+        cwd,
+        timeoutMs: options.timeoutMs,
+        options: {
+          env: options.env ?? {},
+          use_shell: options.useShell ?? false,
+          stdin: options.stdin ?? null
+        }
+      })
+      runningIds.current.add(id)
+      early.filter(chunk => chunk.id === id).forEach(handleChunk)
+      const pendingExit = earlyExit as CommandExit | null
+      if (pendingExit && pendingExit.id === id) {
+        finish(pendingExit)
+      }
+
+      const exit = await exited
+      runningIds.current.delete(id)
+      const result = {
+        output: combined,
+        stdout,
+        stderr,
+        exitCode: exit.exit_code,
+        durationMs: exit.duration_ms
       }
+      if (exit.exit_code === 0) {
+        return { ...result, success: true }
+      }
+      const reason = exit.timed_out
+        ? `Timed out after ${exit.duration_ms} ms`
+        : exit.cancelled
+          ? 'Cancelled'
+          : stderr || `Exited with code ${exit.exit_code ?? 'unknown'}`
+      setError(reason)
+      return { ...result, success: false, error: reason }
     } catch (err) {
       const errorMsg = err as string
       setError(errorMsg)
-      return {
-        success: false,
-        output: '',
-        error: errorMsg
-      }
+      return failure(errorMsg)
     } finally {
+      unlistenOutput()
+      unlistenExit()
       setIsLoading(false)
     }
   }, [])
 
+  const cancelCommand = useCallback(async () => {
+    await Promise.all(
+      Array.from(runningIds.current).map(id => invoke('cancel_command', { id }))
+    )
+  }, [])
+
   const buildProject = useCallback(async (language: string, cwd?: string): Promise<CommandResult> => {
     const commands: { [key: string]: { cmd: string; args: string[] } } = {
       typescript: { cmd: 'npm', args: ['run', 'build'] },
@@ -59,11 +179,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
     const command = commands[language]
     if (!command) {
-      return {
-        success: false,
-        output: '',
-        error: `Unsupported language: ${language}`
-      }
+      return failure(`Unsupported language: ${language}`)
     }
 
     return runCommand(command.cmd, command.args, cwd)
@@ -83,11 +199,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
     const command = commands[language]
     if (!command) {
-      return {
-        success: false,
-        output: '',
-        error: `Unsupported language: ${language}`
-      }
+      return failure(`Unsupported language: ${language}`)
     }
 
     return runCommand(command.cmd, command.args, cwd)
@@ -107,11 +219,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
     const command = commands[language]
     if (!command) {
-      return {
-        success: false,
-        output: '',
-        error: `Unsupported language: ${language}`
-      }
+      return failure(`Unsupported language: ${language}`)
     }
 
     return runCommand(command.cmd, command.args, cwd)
@@ -131,11 +239,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
     const command = commands[language]
     if (!command) {
-      return {
-        success: false,
-        output: '',
-        error: `Unsupported language: ${language}`
-      }
+      return failure(`Unsupported language: ${language}`)
     }
 
     return runCommand(command.cmd, command.args, cwd)
@@ -155,11 +259,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
     const command = commands[language]
     if (!command) {
-      return {
-        success: false,
-        output: '',
-        error: `Unsupported language: ${language}`
-      }
+      return failure(`Unsupported language: ${language}`)
     }
 
     return runCommand(command.cmd, command.args, cwd)
@@ -167,6 +267,7 @@ export function useCommandRunner(): CommandRunnerHook {
 
   return {
     runCommand,
+    cancelCommand,
     buildProject,
     runProject,
     testProject,
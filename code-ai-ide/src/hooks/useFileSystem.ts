@@ -2,11 +2,19 @@ import { useState, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/tauri'
 import { File } from '../contexts/IDEContext'
 
+export interface DirectoryEntry {
+  name: string
+  path: string
+  is_dir: boolean
+  is_symlink: boolean
+  symlink_target: string | null
+}
+
 interface FileSystemHook {
   openFile: () => Promise<File | null>
   saveFile: (path: string, content: string) => Promise<boolean>
   readFile: (path: string) => Promise<string | null>
-  listDirectory: (path: string) => Promise<string[]>
+  listDirectory: (path: string) => Promise<DirectoryEntry[]>
   createFile: (path: string, name: string) => Promise<boolean>
   createDirectory: (path: string, name: string) => Promise<boolean>
   deleteFile: (path: string) => Promise<boolean>
@@ -66,8 +74,8 @@ export function useFileSystem(): FileSystemHook {
     setError(null)
     
     try {
-      const content = await invoke('read_file', { path })
-      return content as string
+      const result = await invoke('read_file', { path }) as { content: string }
+      return result.content
     } catch (err) {
       setError(err as string)
       return null
@@ -76,13 +84,13 @@ export function useFileSystem(): FileSystemHook {
     }
   }, [])
 
-  const listDirectory = useCallback(async (path: string): Promise<string[]> => {
+  const listDirectory = useCallback(async (path: string): Promise<DirectoryEntry[]> => {
     setIsLoading(true)
     setError(null)
     
     try {
-      const files = await invoke('list_directory', { path })
-      return files as string[]
+      const entries = await invoke('list_directory', { path })
+      return entries as DirectoryEntry[]
     } catch (err) {
       setError(err as string)
       return []